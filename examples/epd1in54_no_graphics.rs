@@ -1,7 +1,7 @@
 #![deny(warnings)]
 
 use embedded_hal::delay::DelayNs;
-use epd_waveshare::{epd1in54::Epd1in54, prelude::*};
+use epd_waveshare::{epd1in54::Epd1in54, error::Error, prelude::*};
 use linux_embedded_hal::{
     spidev::{self, SpidevOptions},
     sysfs_gpio::Direction,
@@ -12,7 +12,7 @@ use linux_embedded_hal::{
 // needs to be run with sudo because of some sysfs_gpio permission problems and follow-up timing problems
 // see https://github.com/rust-embedded/rust-sysfs-gpio/issues/5 and follow-up issues
 
-fn main() -> Result<(), SPIError> {
+fn main() -> Result<(), Error<SPIError>> {
     // Configure SPI
     // SPI settings are from eink-waveshare-rs documenation
     let mut spi = SpidevDevice::open("/dev/spidev0.0")?;
@@ -58,7 +58,15 @@ fn main() -> Result<(), SPIError> {
 
     // Setup of the needed pins is finished here
     // Now the "real" usage of the eink-waveshare-rs crate begins
-    let mut epd = Epd1in54::new(&mut spi, busy, dc, rst, &mut delay, Some(5))?;
+    let mut epd = Epd1in54::new(
+        &mut spi,
+        busy,
+        dc,
+        Some(rst),
+        None::<NoPin>,
+        &mut delay,
+        Some(5),
+    )?;
 
     // Clear the full screen
     epd.clear_frame(&mut spi, &mut delay)?;
@@ -10,6 +10,7 @@ use embedded_hal::delay::DelayNs;
 use epd_waveshare::{
     color::*,
     epd2in13bc::{Display2in13bc, Epd2in13bc},
+    error::Error,
     graphics::DisplayRotation,
     prelude::*,
 };
@@ -34,7 +35,7 @@ use linux_embedded_hal::{
 //
 // after finishing, put the display to sleep
 
-fn main() -> Result<(), SPIError> {
+fn main() -> Result<(), Error<SPIError>> {
     let busy = SysfsPin::new(24); // GPIO 24, board J-18
     busy.export().expect("busy export");
     while !busy.is_exported() {}
@@ -71,8 +72,16 @@ fn main() -> Result<(), SPIError> {
 
     let mut delay = Delay {};
 
-    let mut epd2in13 =
-        Epd2in13bc::new(&mut spi, busy, dc, rst, &mut delay, None).expect("eink initalize error");
+    let mut epd2in13 = Epd2in13bc::new(
+        &mut spi,
+        busy,
+        dc,
+        Some(rst),
+        None::<NoPin>,
+        &mut delay,
+        None,
+    )
+    .expect("eink initalize error");
 
     println!("Test all the rotations");
     let mut display = Display2in13bc::default();
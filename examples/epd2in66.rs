@@ -0,0 +1,126 @@
+use std::error::Error;
+
+// This example tests rotations, draws an analog clock and some text and clears the display again.
+use embedded_graphics::{
+    mono_font::MonoTextStyleBuilder,
+    prelude::*,
+    primitives::{Circle, Line, PrimitiveStyle},
+    text::{Baseline, Text, TextStyleBuilder},
+};
+use embedded_hal::delay::DelayNs;
+use epd_waveshare::{color::Color, epd2in66::*, graphics::DisplayRotation, prelude::*};
+use linux_embedded_hal::{
+    gpio_cdev::{Chip, LineRequestFlags},
+    spidev::{SpiModeFlags, SpidevOptions},
+    CdevPin, Delay, SpidevDevice,
+};
+
+// GPIO pin definitions (BCM numbering - no offset needed for cdev)
+const EPD_RST_PIN: u32 = 17;
+const EPD_DC_PIN: u32 = 25;
+const EPD_BUSY_PIN: u32 = 24;
+const EPD_PWR_PIN: u32 = 18;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    // Set up the device
+    // Open the GPIO chip (usually gpiochip0 on Raspberry Pi)
+    let mut chip = Chip::new("/dev/gpiochip0")?;
+
+    // Get GPIO lines and configure them
+    let rst_line = chip.get_line(EPD_RST_PIN)?;
+    let rst_handle = rst_line.request(LineRequestFlags::OUTPUT, 0, "epd-rst")?;
+    let rst_pin = CdevPin::new(rst_handle)?;
+
+    let dc_line = chip.get_line(EPD_DC_PIN)?;
+    let dc_handle = dc_line.request(LineRequestFlags::OUTPUT, 0, "epd-dc")?;
+    let dc_pin = CdevPin::new(dc_handle)?;
+
+    let busy_line = chip.get_line(EPD_BUSY_PIN)?;
+    let busy_handle = busy_line.request(LineRequestFlags::INPUT, 0, "epd-busy")?;
+    let busy_pin = CdevPin::new(busy_handle)?;
+
+    // the driver switches the power on in `new` and off in `sleep`
+    let pwr_line = chip.get_line(EPD_PWR_PIN)?;
+    let pwr_handle = pwr_line.request(LineRequestFlags::OUTPUT, 0, "epd-pwr")?;
+    let pwr_pin = CdevPin::new(pwr_handle)?;
+
+    // Initialize SPI
+    let mut spi = SpidevDevice::open("/dev/spidev0.0")?;
+    let options = SpidevOptions::new()
+        .bits_per_word(8)
+        .max_speed_hz(10_000_000)
+        .mode(SpiModeFlags::SPI_MODE_0)
+        .build();
+    spi.configure(&options)?;
+
+    let mut delay = Delay {};
+
+    let mut epd2in66 = Epd2in66::new(
+        &mut spi,
+        busy_pin,
+        dc_pin,
+        Some(rst_pin),
+        Some(pwr_pin),
+        &mut delay,
+        None,
+    )
+    .expect("epd new");
+    let mut display = Display2in66::default();
+    display.clear(Color::White).ok();
+    println!("Device successfully initialized!");
+
+    println!("Test all the rotations");
+
+    display.set_rotation(DisplayRotation::Rotate0);
+    draw_text(&mut display, "Rotate 0!", 5, 50);
+
+    display.set_rotation(DisplayRotation::Rotate90);
+    draw_text(&mut display, "Rotate 90!", 5, 50);
+
+    display.set_rotation(DisplayRotation::Rotate180);
+    draw_text(&mut display, "Rotate 180!", 5, 50);
+
+    display.set_rotation(DisplayRotation::Rotate270);
+    draw_text(&mut display, "Rotate 270!", 5, 50);
+
+    epd2in66.update_and_display_frame(&mut spi, display.buffer(), &mut delay)?;
+    delay.delay_ms(5000);
+
+    // Draw an analog clock
+    println!("Draw a clock");
+    display.set_rotation(DisplayRotation::Rotate0);
+    display.clear(Color::White).ok();
+    let _ = Circle::with_center(Point::new(76, 76), 80)
+        .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
+        .draw(&mut display);
+    let _ = Line::new(Point::new(76, 76), Point::new(40, 50))
+        .into_styled(PrimitiveStyle::with_stroke(Color::Black, 4))
+        .draw(&mut display);
+    let _ = Line::new(Point::new(76, 76), Point::new(90, 40))
+        .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
+        .draw(&mut display);
+    draw_text(&mut display, "It's working!", 20, 200);
+
+    epd2in66.update_and_display_frame(&mut spi, display.buffer(), &mut delay)?;
+    delay.delay_ms(5000);
+
+    // Clear and sleep
+    println!("Clear the display");
+    epd2in66.clear_frame(&mut spi, &mut delay)?;
+    epd2in66.display_frame(&mut spi, &mut delay)?;
+    println!("Finished tests - going to sleep");
+    epd2in66.sleep(&mut spi, &mut delay)?;
+    Ok(())
+}
+
+fn draw_text(display: &mut Display2in66, text: &str, x: i32, y: i32) {
+    let style = MonoTextStyleBuilder::new()
+        .font(&embedded_graphics::mono_font::ascii::FONT_6X10)
+        .text_color(Color::Black)
+        .background_color(Color::White)
+        .build();
+
+    let text_style = TextStyleBuilder::new().baseline(Baseline::Top).build();
+
+    let _ = Text::with_text_style(text, Point::new(x, y), style, text_style).draw(display);
+}
@@ -10,6 +10,7 @@ use embedded_hal::delay::DelayNs;
 use epd_waveshare::{
     color::*,
     epd4in2::{Display4in2, Epd4in2},
+    error::Error,
     graphics::DisplayRotation,
     prelude::*,
 };
@@ -23,7 +24,7 @@ use linux_embedded_hal::{
 // needs to be run with sudo because of some sysfs_gpio permission problems and follow-up timing problems
 // see https://github.com/rust-embedded/rust-sysfs-gpio/issues/5 and follow-up issues
 
-fn main() -> Result<(), SPIError> {
+fn main() -> Result<(), Error<SPIError>> {
     // Configure SPI
     // Settings are taken from
     let mut spi = SpidevDevice::open("/dev/spidev0.0").expect("spidev directory");
@@ -61,8 +62,16 @@ fn main() -> Result<(), SPIError> {
 
     let mut delay = Delay {};
 
-    let mut epd4in2 =
-        Epd4in2::new(&mut spi, busy, dc, rst, &mut delay, None).expect("eink initalize error");
+    let mut epd4in2 = Epd4in2::new(
+        &mut spi,
+        busy,
+        dc,
+        Some(rst),
+        None::<NoPin>,
+        &mut delay,
+        None,
+    )
+    .expect("eink initalize error");
 
     println!("Test all the rotations");
     let mut display = Display4in2::default();
@@ -41,8 +41,10 @@ fn main() -> Result<(), Box<dyn Error>> {
     let busy_handle = busy_line.request(LineRequestFlags::INPUT, 0, "epd-busy")?;
     let busy_pin = CdevPin::new(busy_handle)?;
 
+    // the driver switches the power on in `new` and off in `sleep`
     let pwr_line = chip.get_line(EPD_PWR_PIN)?;
-    let _ = pwr_line.request(LineRequestFlags::OUTPUT, 1, "epd-pwr")?;
+    let pwr_handle = pwr_line.request(LineRequestFlags::OUTPUT, 0, "epd-pwr")?;
+    let pwr_pin = CdevPin::new(pwr_handle)?;
 
     // Initialize SPI
     let mut spi = SpidevDevice::open("/dev/spidev0.0")?;
@@ -55,8 +57,16 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let mut delay = Delay {};
 
-    let mut epd7in5 =
-        Epd7in5::new(&mut spi, busy_pin, dc_pin, rst_pin, &mut delay, None).expect("epd new");
+    let mut epd7in5 = Epd7in5::new(
+        &mut spi,
+        busy_pin,
+        dc_pin,
+        Some(rst_pin),
+        Some(pwr_pin),
+        &mut delay,
+        None,
+    )
+    .expect("epd new");
     epd7in5.set_lut(&mut spi, &mut delay, Some(RefreshLut::Quick))?;
     let mut display = Display7in5::default();
     display.clear(Color::White);
@@ -103,7 +113,8 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // Draw some text
     println!("Print text in all sizes");
-    // Color is inverted - black means white, white means black; the output will be black text on white background
+    // The panel shows a set bit as black, so the colors are swapped unless the `color_polarity`
+    // feature is enabled
     display.clear(Color::White).ok();
     let fonts = [
         &FONT_4X6,
@@ -0,0 +1,153 @@
+use std::error::Error;
+
+// This example shows a digital clock with the quick refresh: only the pixels which changed since the
+// last second are driven, a full refresh every minute removes the ghosting.
+use embedded_graphics::{
+    mono_font::MonoTextStyleBuilder,
+    prelude::*,
+    text::{Baseline, Text, TextStyleBuilder},
+};
+use embedded_hal::delay::DelayNs;
+#[cfg(feature = "graphics")]
+use epd_waveshare::{color::Color, epd7in5_v2::*, graphics::VarDisplay, prelude::*};
+use linux_embedded_hal::{
+    gpio_cdev::{Chip, LineRequestFlags},
+    spidev::{SpiModeFlags, SpidevOptions},
+    CdevPin, Delay, SpidevDevice,
+};
+
+// GPIO pin definitions (BCM numbering - no offset needed for cdev)
+const EPD_RST_PIN: u32 = 17;
+const EPD_DC_PIN: u32 = 25;
+const EPD_BUSY_PIN: u32 = 24;
+const EPD_PWR_PIN: u32 = 18;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    // Set up the device
+    // Open the GPIO chip (usually gpiochip0 on Raspberry Pi)
+    let mut chip = Chip::new("/dev/gpiochip0")?;
+
+    // Get GPIO lines and configure them
+    let rst_line = chip.get_line(EPD_RST_PIN)?;
+    let rst_handle = rst_line.request(LineRequestFlags::OUTPUT, 0, "epd-rst")?;
+    let rst_pin = CdevPin::new(rst_handle)?;
+
+    let dc_line = chip.get_line(EPD_DC_PIN)?;
+    let dc_handle = dc_line.request(LineRequestFlags::OUTPUT, 0, "epd-dc")?;
+    let dc_pin = CdevPin::new(dc_handle)?;
+
+    let busy_line = chip.get_line(EPD_BUSY_PIN)?;
+    let busy_handle = busy_line.request(LineRequestFlags::INPUT, 0, "epd-busy")?;
+    let busy_pin = CdevPin::new(busy_handle)?;
+
+    // the driver switches the power on in `new` and off in `sleep`
+    let pwr_line = chip.get_line(EPD_PWR_PIN)?;
+    let pwr_handle = pwr_line.request(LineRequestFlags::OUTPUT, 0, "epd-pwr")?;
+    let pwr_pin = CdevPin::new(pwr_handle)?;
+
+    // Initialize SPI
+    let mut spi = SpidevDevice::open("/dev/spidev0.0")?;
+    let options = SpidevOptions::new()
+        .bits_per_word(8)
+        .max_speed_hz(10_000_000)
+        .mode(SpiModeFlags::SPI_MODE_0)
+        .build();
+    spi.configure(&options)?;
+
+    let mut delay = Delay {};
+
+    let mut epd7in5 = Epd7in5::new(
+        &mut spi,
+        busy_pin,
+        dc_pin,
+        Some(rst_pin),
+        Some(pwr_pin),
+        &mut delay,
+        None,
+    )
+    .expect("epd new");
+    let mut display = Display7in5::default();
+    display.clear(Color::White).ok();
+    epd7in5.update_and_display_frame(&mut spi, display.buffer(), &mut delay)?;
+    println!("Device successfully initialized!");
+
+    // "HH:MM:SS" in FONT_10X20, x and width are multiples of 8
+    let (x, y, width, height) = (320, 230, 80, 20);
+    let mut old_buffer = [0u8; 80 / 8 * 20];
+    let mut new_buffer = [0u8; 80 / 8 * 20];
+    let mut old_clock = VarDisplay::new(width, height, &mut old_buffer, false).unwrap();
+    let mut new_clock = VarDisplay::new(width, height, &mut new_buffer, false).unwrap();
+    old_clock.clear(Color::White).ok();
+
+    let (mut hours, mut minutes, mut seconds) = (12u32, 34u32, 50u32);
+    for _ in 0..120 {
+        if seconds == 0 {
+            // remove the ghosting once per minute
+            epd7in5.set_lut(&mut spi, &mut delay, Some(RefreshLut::Full))?;
+            display.clear(Color::White).ok();
+            draw_time(&mut display, hours, minutes, seconds, x as i32, y as i32);
+            epd7in5.update_and_display_frame(&mut spi, display.buffer(), &mut delay)?;
+            epd7in5.set_lut(&mut spi, &mut delay, Some(RefreshLut::PartialRefresh))?;
+        } else {
+            new_clock.clear(Color::White).ok();
+            draw_time(&mut new_clock, hours, minutes, seconds, 0, 0);
+
+            epd7in5.update_partial_old_frame(
+                &mut spi,
+                &mut delay,
+                old_clock.buffer(),
+                x,
+                y,
+                width,
+                height,
+            )?;
+            epd7in5.update_partial_new_frame(
+                &mut spi,
+                &mut delay,
+                new_clock.buffer(),
+                x,
+                y,
+                width,
+                height,
+            )?;
+            epd7in5.display_new_frame(&mut spi, &mut delay)?;
+        }
+        old_clock.clear(Color::White).ok();
+        draw_time(&mut old_clock, hours, minutes, seconds, 0, 0);
+
+        seconds = (seconds + 1) % 60;
+        if seconds == 0 {
+            minutes = (minutes + 1) % 60;
+            if minutes == 0 {
+                hours = (hours + 1) % 24;
+            }
+        }
+        delay.delay_ms(1000);
+    }
+
+    println!("Finished - going to sleep");
+    epd7in5.set_lut(&mut spi, &mut delay, Some(RefreshLut::Full))?;
+    epd7in5.clear_frame(&mut spi, &mut delay)?;
+    epd7in5.sleep(&mut spi, &mut delay)?;
+    Ok(())
+}
+
+fn draw_time<D: DrawTarget<Color = Color>>(
+    display: &mut D,
+    hours: u32,
+    minutes: u32,
+    seconds: u32,
+    x: i32,
+    y: i32,
+) {
+    let style = MonoTextStyleBuilder::new()
+        .font(&embedded_graphics::mono_font::ascii::FONT_10X20)
+        .text_color(Color::Black)
+        .background_color(Color::White)
+        .build();
+
+    let text_style = TextStyleBuilder::new().baseline(Baseline::Top).build();
+
+    let time = format!("{hours:02}:{minutes:02}:{seconds:02}");
+    let _ = Text::with_text_style(&time, Point::new(x, y), style, text_style).draw(display);
+}
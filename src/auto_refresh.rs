@@ -0,0 +1,701 @@
+//! Automatic full refreshes for [`QuickRefresh`] displays
+//!
+//! Every quick refresh leaves some ghosting behind. [`AutoFullRefresh`] counts the quick
+//! refreshes of a display and replaces one of them with a full refresh once a threshold is
+//! reached.
+//!
+//! For the full refresh the wrapper needs the complete frame, it keeps a copy of every new frame
+//! (and of every partial new frame) in a buffer provided by you. The buffer takes as many bytes
+//! as a full frame of the display, e.g. 15 000 bytes for the 4.2" display, on top of your own
+//! frame buffer.
+//!
+//! # Example
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), epd_waveshare::error::Error<embedded_hal::spi::ErrorKind>> {
+//!use core::num::NonZeroU32;
+//!use epd_waveshare::{auto_refresh::AutoFullRefresh, epd4in2::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = digital::Mock::new(&expectations);
+//!# let dc = digital::Mock::new(&expectations);
+//!# let rst = digital::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
+//!
+//!let mut epd = Epd4in2::new(&mut spi, busy_in, dc, Some(rst), None::<NoPin>, &mut delay, None)?;
+//!epd.set_lut(&mut spi, &mut delay, Some(RefreshLut::Quick))?;
+//!
+//!let mut frame = [0xFF; 400 / 8 * 300];
+//!let mut epd = AutoFullRefresh::new(epd, &mut frame, RefreshLut::Quick);
+//!epd.set_auto_full_refresh(NonZeroU32::new(5));
+//!
+//!let old = [0xFF; 400 / 8 * 300];
+//!let new = [0x00; 400 / 8 * 300];
+//!epd.update_old_frame(&mut spi, &old, &mut delay)?;
+//!epd.update_new_frame(&mut spi, &new, &mut delay)?;
+//!// every fifth call is a full refresh of `new`
+//!epd.display_new_frame(&mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+use crate::error::Error;
+use core::num::NonZeroU32;
+
+use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
+
+use crate::traits::{QuickRefresh, RefreshLut, WaveshareDisplay};
+use crate::{buffer_len, check_buffer_len, check_partial_window};
+
+/// Wrapper around a [`QuickRefresh`] display which does a full refresh after a number of quick
+/// refreshes
+///
+/// See the [module documentation](self) for the memory it needs.
+pub struct AutoFullRefresh<'a, EPD> {
+    epd: EPD,
+    frame: &'a mut [u8],
+    quick_lut: RefreshLut,
+    threshold: Option<NonZeroU32>,
+    partial_refreshes: u32,
+}
+
+impl<'a, EPD> AutoFullRefresh<'a, EPD> {
+    /// Wraps `epd`, the automatic full refresh is disabled until
+    /// [`set_auto_full_refresh`](Self::set_auto_full_refresh) is called
+    ///
+    /// `frame` must have the size of a full frame and hold the current content of the display.
+    /// `quick_lut` is restored with [`WaveshareDisplay::set_lut`] after each full refresh.
+    pub fn new(epd: EPD, frame: &'a mut [u8], quick_lut: RefreshLut) -> Self {
+        AutoFullRefresh {
+            epd,
+            frame,
+            quick_lut,
+            threshold: None,
+            partial_refreshes: 0,
+        }
+    }
+
+    /// Sets after how many quick refreshes a full refresh is done, `None` disables it
+    pub fn set_auto_full_refresh(&mut self, threshold: Option<NonZeroU32>) {
+        self.threshold = threshold;
+    }
+
+    /// Number of quick refreshes since the last full refresh
+    ///
+    /// Useful to schedule a [`full_refresh`](Self::full_refresh) at a convenient moment.
+    pub fn partial_refreshes_since_full(&self) -> u32 {
+        self.partial_refreshes
+    }
+
+    /// The last complete frame the wrapper has seen
+    pub fn frame(&self) -> &[u8] {
+        self.frame
+    }
+
+    /// Returns the wrapped display
+    pub fn epd(&mut self) -> &mut EPD {
+        &mut self.epd
+    }
+
+    /// Returns the wrapped display and the frame buffer
+    pub fn release(self) -> (EPD, &'a mut [u8]) {
+        (self.epd, self.frame)
+    }
+}
+
+impl<EPD> AutoFullRefresh<'_, EPD> {
+    /// Refreshes the last complete frame with the full LUT and resets the counter
+    pub fn full_refresh<SPI, BUSY, DC, RST, DELAY, PWR>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>
+    where
+        SPI: SpiDevice,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        PWR: OutputPin,
+        DELAY: DelayNs,
+        EPD: WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>,
+    {
+        self.epd.set_lut(spi, delay, Some(RefreshLut::Full))?;
+        self.epd.update_and_display_frame(spi, self.frame, delay)?;
+        self.epd.set_lut(spi, delay, Some(self.quick_lut))?;
+        self.partial_refreshes = 0;
+        Ok(())
+    }
+
+    /// See [`QuickRefresh::update_old_frame`]
+    pub fn update_old_frame<SPI, BUSY, DC, RST, DELAY, PWR>(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>
+    where
+        SPI: SpiDevice,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        PWR: OutputPin,
+        DELAY: DelayNs,
+        EPD: QuickRefresh<SPI, BUSY, DC, RST, DELAY, PWR>,
+    {
+        self.epd.update_old_frame(spi, buffer, delay)
+    }
+
+    /// See [`QuickRefresh::update_new_frame`], the frame is copied for the next full refresh
+    ///
+    /// Fails with [`Error::InvalidBufferLength`] if `buffer` doesn't have the size of the frame,
+    /// the copy is only updated once the display took the frame.
+    pub fn update_new_frame<SPI, BUSY, DC, RST, DELAY, PWR>(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>
+    where
+        SPI: SpiDevice,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        PWR: OutputPin,
+        DELAY: DelayNs,
+        EPD: QuickRefresh<SPI, BUSY, DC, RST, DELAY, PWR>,
+    {
+        check_buffer_len(buffer.len(), self.frame.len())?;
+        self.epd.update_new_frame(spi, buffer, delay)?;
+        self.frame.copy_from_slice(buffer);
+        Ok(())
+    }
+
+    /// See [`QuickRefresh::update_partial_old_frame`]
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_partial_old_frame<SPI, BUSY, DC, RST, DELAY, PWR>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>>
+    where
+        SPI: SpiDevice,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        PWR: OutputPin,
+        DELAY: DelayNs,
+        EPD: QuickRefresh<SPI, BUSY, DC, RST, DELAY, PWR>,
+    {
+        self.epd
+            .update_partial_old_frame(spi, delay, buffer, x, y, width, height)
+    }
+
+    /// See [`QuickRefresh::update_partial_new_frame`], the window is copied into the frame for
+    /// the next full refresh
+    ///
+    /// The frame is expected to be monochrome with one bit per pixel. Fails with
+    /// [`Error::OutOfBounds`] if the window isn't byte aligned or doesn't fit on the display, and
+    /// with [`Error::InvalidBufferLength`] if `buffer` doesn't cover it or the frame doesn't
+    /// have the size of the display. The copy is only updated once the display took the window.
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_partial_new_frame<SPI, BUSY, DC, RST, DELAY, PWR>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>>
+    where
+        SPI: SpiDevice,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        PWR: OutputPin,
+        DELAY: DelayNs,
+        EPD: QuickRefresh<SPI, BUSY, DC, RST, DELAY, PWR>
+            + WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>,
+    {
+        let (frame_width, frame_height) = (self.epd.width(), self.epd.height());
+        check_buffer_len(
+            self.frame.len(),
+            buffer_len(frame_width as usize, frame_height as usize),
+        )?;
+        check_partial_window(buffer.len(), x, y, width, height, frame_width, frame_height)?;
+        self.epd
+            .update_partial_new_frame(spi, delay, buffer, x, y, width, height)?;
+        copy_window(self.frame, frame_width, buffer, x, y, width);
+        Ok(())
+    }
+
+    /// See [`QuickRefresh::display_new_frame`], does a full refresh instead when the threshold
+    /// is reached
+    pub fn display_new_frame<SPI, BUSY, DC, RST, DELAY, PWR>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>
+    where
+        SPI: SpiDevice,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        PWR: OutputPin,
+        DELAY: DelayNs,
+        EPD: QuickRefresh<SPI, BUSY, DC, RST, DELAY, PWR>
+            + WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>,
+    {
+        match self.threshold {
+            Some(threshold) if self.partial_refreshes + 1 >= threshold.get() => {
+                self.full_refresh::<SPI, BUSY, DC, RST, DELAY, PWR>(spi, delay)
+            }
+            _ => {
+                self.epd.display_new_frame(spi, delay)?;
+                self.partial_refreshes = self.partial_refreshes.saturating_add(1);
+                Ok(())
+            }
+        }
+    }
+
+    /// [`update_new_frame`](Self::update_new_frame) followed by
+    /// [`display_new_frame`](Self::display_new_frame)
+    pub fn update_and_display_new_frame<SPI, BUSY, DC, RST, DELAY, PWR>(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>
+    where
+        SPI: SpiDevice,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        PWR: OutputPin,
+        DELAY: DelayNs,
+        EPD: QuickRefresh<SPI, BUSY, DC, RST, DELAY, PWR>
+            + WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>,
+    {
+        self.update_new_frame::<SPI, BUSY, DC, RST, DELAY, PWR>(spi, buffer, delay)?;
+        self.display_new_frame::<SPI, BUSY, DC, RST, DELAY, PWR>(spi, delay)
+    }
+}
+
+/// Copies a window of `width` pixels into a frame of `frame_width` pixels
+///
+/// The window must have been checked with [`check_partial_window`] against the frame.
+fn copy_window(frame: &mut [u8], frame_width: u32, window: &[u8], x: u32, y: u32, width: u32) {
+    let stride = buffer_len(frame_width as usize, 1);
+    let row_len = buffer_len(width as usize, 1);
+    for (row, line) in window.chunks_exact(row_len).enumerate() {
+        let start = (y as usize + row) * stride + x as usize / 8;
+        frame[start..start + row_len].copy_from_slice(line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+    use crate::color::Color;
+    use crate::NoPin;
+    use embedded_hal_mock::eh1::{
+        delay::NoopDelay,
+        digital::Mock as PinMock,
+        spi::{Mock as SpiMock, Transaction as SpiTransaction},
+    };
+    use std::{vec, vec::Vec};
+
+    /// Records the calls instead of talking to a display
+    #[derive(Default)]
+    struct FakeEpd {
+        calls: Vec<&'static str>,
+        frames: Vec<Vec<u8>>,
+        background: Color,
+        /// The new frames time out
+        stuck: bool,
+    }
+
+    impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR> for FakeEpd
+    where
+        SPI: SpiDevice,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        PWR: OutputPin,
+        DELAY: DelayNs,
+    {
+        type DisplayColor = Color;
+
+        fn new(
+            _spi: &mut SPI,
+            _busy: BUSY,
+            _dc: DC,
+            _rst: Option<RST>,
+            _pwr: Option<PWR>,
+            _delay: &mut DELAY,
+            _delay_us: Option<u32>,
+        ) -> Result<Self, Error<SPI::Error>> {
+            Ok(FakeEpd::default())
+        }
+
+        fn sleep(&mut self, _spi: &mut SPI, _delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+            Ok(())
+        }
+
+        fn wake_up(&mut self, _spi: &mut SPI, _delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+            Ok(())
+        }
+
+        fn set_background_color(&mut self, color: Color) {
+            self.background = color;
+        }
+
+        fn set_busy_timeout(&mut self, _timeout_us: Option<u32>) {}
+
+        fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+            Ok(())
+        }
+
+        fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+            Ok(false)
+        }
+
+        fn background_color(&self) -> &Color {
+            &self.background
+        }
+
+        fn width(&self) -> u32 {
+            16
+        }
+
+        fn height(&self) -> u32 {
+            2
+        }
+
+        fn update_frame(
+            &mut self,
+            _spi: &mut SPI,
+            buffer: &[u8],
+            _delay: &mut DELAY,
+        ) -> Result<(), Error<SPI::Error>> {
+            self.calls.push("update_frame");
+            self.frames.push(buffer.to_vec());
+            Ok(())
+        }
+
+        fn update_partial_frame(
+            &mut self,
+            _spi: &mut SPI,
+            _delay: &mut DELAY,
+            _buffer: &[u8],
+            _x: u32,
+            _y: u32,
+            _width: u32,
+            _height: u32,
+        ) -> Result<(), Error<SPI::Error>> {
+            unimplemented!()
+        }
+
+        fn display_frame(
+            &mut self,
+            _spi: &mut SPI,
+            _delay: &mut DELAY,
+        ) -> Result<(), Error<SPI::Error>> {
+            self.calls.push("display_frame");
+            Ok(())
+        }
+
+        fn update_and_display_frame(
+            &mut self,
+            spi: &mut SPI,
+            buffer: &[u8],
+            delay: &mut DELAY,
+        ) -> Result<(), Error<SPI::Error>> {
+            WaveshareDisplay::<SPI, BUSY, DC, RST, DELAY, PWR>::update_frame(
+                self, spi, buffer, delay,
+            )?;
+            WaveshareDisplay::<SPI, BUSY, DC, RST, DELAY, PWR>::display_frame(self, spi, delay)
+        }
+
+        fn clear_frame(
+            &mut self,
+            _spi: &mut SPI,
+            _delay: &mut DELAY,
+        ) -> Result<(), Error<SPI::Error>> {
+            unimplemented!()
+        }
+
+        fn set_lut(
+            &mut self,
+            _spi: &mut SPI,
+            _delay: &mut DELAY,
+            refresh_rate: Option<RefreshLut>,
+        ) -> Result<(), Error<SPI::Error>> {
+            self.calls.push(match refresh_rate {
+                Some(RefreshLut::Full) => "set_lut(Full)",
+                _ => "set_lut(Quick)",
+            });
+            Ok(())
+        }
+
+        fn wait_until_idle(
+            &mut self,
+            _spi: &mut SPI,
+            _delay: &mut DELAY,
+        ) -> Result<(), Error<SPI::Error>> {
+            Ok(())
+        }
+    }
+
+    impl<SPI, BUSY, DC, RST, DELAY, PWR> QuickRefresh<SPI, BUSY, DC, RST, DELAY, PWR> for FakeEpd
+    where
+        SPI: SpiDevice,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        PWR: OutputPin,
+        DELAY: DelayNs,
+    {
+        fn update_old_frame(
+            &mut self,
+            _spi: &mut SPI,
+            _buffer: &[u8],
+            _delay: &mut DELAY,
+        ) -> Result<(), Error<SPI::Error>> {
+            self.calls.push("update_old_frame");
+            Ok(())
+        }
+
+        fn update_new_frame(
+            &mut self,
+            _spi: &mut SPI,
+            _buffer: &[u8],
+            _delay: &mut DELAY,
+        ) -> Result<(), Error<SPI::Error>> {
+            self.calls.push("update_new_frame");
+            if self.stuck {
+                return Err(Error::BusyTimeout);
+            }
+            Ok(())
+        }
+
+        fn display_new_frame(
+            &mut self,
+            _spi: &mut SPI,
+            _delay: &mut DELAY,
+        ) -> Result<(), Error<SPI::Error>> {
+            self.calls.push("display_new_frame");
+            Ok(())
+        }
+
+        fn update_and_display_new_frame(
+            &mut self,
+            spi: &mut SPI,
+            buffer: &[u8],
+            delay: &mut DELAY,
+        ) -> Result<(), Error<SPI::Error>> {
+            QuickRefresh::<SPI, BUSY, DC, RST, DELAY, PWR>::update_new_frame(
+                self, spi, buffer, delay,
+            )?;
+            QuickRefresh::<SPI, BUSY, DC, RST, DELAY, PWR>::display_new_frame(self, spi, delay)
+        }
+
+        fn update_partial_old_frame(
+            &mut self,
+            _spi: &mut SPI,
+            _delay: &mut DELAY,
+            _buffer: &[u8],
+            _x: u32,
+            _y: u32,
+            _width: u32,
+            _height: u32,
+        ) -> Result<(), Error<SPI::Error>> {
+            self.calls.push("update_partial_old_frame");
+            Ok(())
+        }
+
+        fn update_partial_new_frame(
+            &mut self,
+            _spi: &mut SPI,
+            _delay: &mut DELAY,
+            _buffer: &[u8],
+            _x: u32,
+            _y: u32,
+            _width: u32,
+            _height: u32,
+        ) -> Result<(), Error<SPI::Error>> {
+            self.calls.push("update_partial_new_frame");
+            if self.stuck {
+                return Err(Error::BusyTimeout);
+            }
+            Ok(())
+        }
+
+        fn clear_partial_frame(
+            &mut self,
+            _spi: &mut SPI,
+            _delay: &mut DELAY,
+            _x: u32,
+            _y: u32,
+            _width: u32,
+            _height: u32,
+        ) -> Result<(), Error<SPI::Error>> {
+            unimplemented!()
+        }
+    }
+
+    type Wrapper<'a> = AutoFullRefresh<'a, FakeEpd>;
+
+    /// Runs `f` with a spi mock which must not be used
+    fn with_spi(f: impl FnOnce(&mut SpiMock<u8>, &mut NoopDelay)) {
+        let mut spi = SpiMock::new(&[] as &[SpiTransaction<u8>]);
+        f(&mut spi, &mut NoopDelay::new());
+        spi.done();
+    }
+
+    fn display(epd: &mut Wrapper, spi: &mut SpiMock<u8>, delay: &mut NoopDelay) {
+        epd.display_new_frame::<_, PinMock, PinMock, PinMock, _, NoPin>(spi, delay)
+            .unwrap();
+    }
+
+    #[test]
+    fn counts_without_threshold() {
+        with_spi(|spi, delay| {
+            let mut frame = [0xFF; 4];
+            let mut epd = AutoFullRefresh::new(FakeEpd::default(), &mut frame, RefreshLut::Quick);
+            for _ in 0..10 {
+                display(&mut epd, spi, delay);
+            }
+            assert_eq!(epd.partial_refreshes_since_full(), 10);
+            assert_eq!(epd.epd().calls, vec!["display_new_frame"; 10]);
+        });
+    }
+
+    #[test]
+    fn full_refresh_at_threshold() {
+        with_spi(|spi, delay| {
+            let mut frame = [0xFF; 4];
+            let mut epd = AutoFullRefresh::new(FakeEpd::default(), &mut frame, RefreshLut::Quick);
+            epd.set_auto_full_refresh(NonZeroU32::new(3));
+
+            epd.update_new_frame::<_, PinMock, PinMock, PinMock, _, NoPin>(
+                spi,
+                &[1, 2, 3, 4],
+                delay,
+            )
+            .unwrap();
+            display(&mut epd, spi, delay);
+            display(&mut epd, spi, delay);
+            assert_eq!(epd.partial_refreshes_since_full(), 2);
+            display(&mut epd, spi, delay);
+            assert_eq!(epd.partial_refreshes_since_full(), 0);
+
+            assert_eq!(
+                epd.epd().calls,
+                vec![
+                    "update_new_frame",
+                    "display_new_frame",
+                    "display_new_frame",
+                    "set_lut(Full)",
+                    "update_frame",
+                    "display_frame",
+                    "set_lut(Quick)",
+                ]
+            );
+            assert_eq!(epd.epd().frames, vec![vec![1, 2, 3, 4]]);
+        });
+    }
+
+    #[test]
+    fn partial_windows_are_kept() {
+        with_spi(|spi, delay| {
+            let mut frame = [0xFF; 4];
+            let mut epd = AutoFullRefresh::new(FakeEpd::default(), &mut frame, RefreshLut::Quick);
+
+            // second byte of both rows of the 16x2 frame
+            epd.update_partial_new_frame::<_, PinMock, PinMock, PinMock, _, NoPin>(
+                spi,
+                delay,
+                &[0x00, 0x0F],
+                8,
+                0,
+                8,
+                2,
+            )
+            .unwrap();
+            assert_eq!(epd.frame(), &[0xFF, 0x00, 0xFF, 0x0F]);
+        });
+    }
+
+    #[test]
+    fn bad_buffers_are_rejected() {
+        with_spi(|spi, delay| {
+            let mut frame = [0xFF; 4];
+            let mut epd = AutoFullRefresh::new(FakeEpd::default(), &mut frame, RefreshLut::Quick);
+
+            let result =
+                epd.update_new_frame::<_, PinMock, PinMock, PinMock, _, NoPin>(spi, &[0; 3], delay);
+            assert_eq!(
+                result,
+                Err(Error::InvalidBufferLength {
+                    expected: 4,
+                    got: 3
+                })
+            );
+            for (x, width) in [(4, 8), (8, 16), (0xFFFF_FFF8, 8)] {
+                let result = epd
+                    .update_partial_new_frame::<_, PinMock, PinMock, PinMock, _, NoPin>(
+                        spi,
+                        delay,
+                        &[0x00, 0x0F],
+                        x,
+                        0,
+                        width,
+                        2,
+                    );
+                assert!(matches!(result, Err(Error::OutOfBounds { .. })));
+            }
+            assert!(epd.epd().calls.is_empty());
+            assert_eq!(epd.frame(), &[0xFF; 4]);
+        });
+    }
+
+    #[test]
+    fn failed_updates_keep_the_frame() {
+        with_spi(|spi, delay| {
+            let mut frame = [0xFF; 4];
+            let fake = FakeEpd {
+                stuck: true,
+                ..FakeEpd::default()
+            };
+            let mut epd = AutoFullRefresh::new(fake, &mut frame, RefreshLut::Quick);
+
+            let result = epd.update_new_frame::<_, PinMock, PinMock, PinMock, _, NoPin>(
+                spi,
+                &[1, 2, 3, 4],
+                delay,
+            );
+            assert_eq!(result, Err(Error::BusyTimeout));
+            let result = epd.update_partial_new_frame::<_, PinMock, PinMock, PinMock, _, NoPin>(
+                spi,
+                delay,
+                &[0x00, 0x0F],
+                8,
+                0,
+                8,
+                2,
+            );
+            assert_eq!(result, Err(Error::BusyTimeout));
+            assert_eq!(epd.frame(), &[0xFF; 4]);
+        });
+    }
+}
@@ -7,6 +7,8 @@
 use embedded_graphics_core::pixelcolor::BinaryColor;
 #[cfg(feature = "graphics")]
 use embedded_graphics_core::pixelcolor::PixelColor;
+#[cfg(feature = "graphics")]
+use embedded_graphics_core::pixelcolor::{Gray2, GrayColor};
 
 /// When trying to parse u8 to one of the color types
 #[derive(Debug, PartialEq, Eq)]
@@ -46,6 +48,16 @@ pub enum TriColor {
     Chromatic,
 }
 
+/// How the chromatic buffer handed to a tri-color display encodes the chromatic pixels
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum DisplayColorRendering {
+    /// A set bit is chromatic, as drawn by a `TriColor` display buffer
+    #[default]
+    Positive,
+    /// A cleared bit is chromatic, e.g. for inverted artwork
+    Negative,
+}
+
 /// For the 7 Color Displays
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
 pub enum OctColor {
@@ -68,6 +80,47 @@ pub enum OctColor {
     HiZ = 0x07,
 }
 
+/// For the 4 Color Displays (Waveshare "G" series)
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum QuadColor {
+    /// Black Color
+    Black = 0b00,
+    /// White Color
+    #[default]
+    White = 0b01,
+    /// Yellow Color
+    Yellow = 0b10,
+    /// Red Color
+    Red = 0b11,
+}
+
+/// Colors which [`deghost`](crate::traits::WaveshareDisplay::deghost) drives every pixel through
+pub trait DeghostColors: Copy + 'static {
+    /// One cycle, the panel is cleared to each color in turn
+    const CYCLE: &'static [Self];
+}
+
+impl DeghostColors for Color {
+    const CYCLE: &'static [Self] = &[Color::Black, Color::White];
+}
+
+impl DeghostColors for TriColor {
+    const CYCLE: &'static [Self] = &[TriColor::Black, TriColor::Chromatic, TriColor::White];
+}
+
+impl DeghostColors for OctColor {
+    const CYCLE: &'static [Self] = &[OctColor::Black, OctColor::White];
+}
+
+impl DeghostColors for QuadColor {
+    const CYCLE: &'static [Self] = &[
+        QuadColor::Black,
+        QuadColor::Red,
+        QuadColor::Yellow,
+        QuadColor::White,
+    ];
+}
+
 /// Color trait for use in `Display`s
 pub trait ColorType {
     /// Number of bit used to represent this color type in a single buffer.
@@ -87,7 +140,7 @@ pub trait ColorType {
     /// Return values are :
     /// * .0 is the mask used to exclude this pixel from the byte (eg: 0x7F in BiColor)
     /// * .1 are the bits used to set the color in the byte (eg: 0x80 in BiColor)
-    ///      this is u16 because we set 2 bytes in case of split buffer
+    ///   this is u16 because we set 2 bytes in case of split buffer
     fn bitmask(&self, bwrbit: bool, pos: u32) -> (u8, u16);
 }
 
@@ -133,6 +186,28 @@ impl ColorType for OctColor {
     }
 }
 
+impl ColorType for QuadColor {
+    const BITS_PER_PIXEL_PER_BUFFER: usize = 2;
+    const BUFFER_COUNT: usize = 1;
+    fn bitmask(&self, _bwrbit: bool, pos: u32) -> (u8, u16) {
+        let shift = 6 - (pos % 4) * 2;
+        let mask = !(0b11 << shift);
+        (mask, (self.get_bits() as u16) << shift)
+    }
+}
+
+/// Four gray levels at 2 bits per pixel, `0b00` is black and `0b11` white
+#[cfg(feature = "graphics")]
+impl ColorType for Gray2 {
+    const BITS_PER_PIXEL_PER_BUFFER: usize = 2;
+    const BUFFER_COUNT: usize = 1;
+    fn bitmask(&self, _bwrbit: bool, pos: u32) -> (u8, u16) {
+        let shift = 6 - (pos % 4) * 2;
+        let mask = !(0b11 << shift);
+        (mask, (self.luma() as u16) << shift)
+    }
+}
+
 #[cfg(feature = "graphics")]
 impl From<BinaryColor> for OctColor {
     fn from(b: BinaryColor) -> OctColor {
@@ -243,6 +318,97 @@ impl OctColor {
         }
     }
 }
+
+#[cfg(feature = "graphics")]
+impl From<BinaryColor> for QuadColor {
+    fn from(b: BinaryColor) -> QuadColor {
+        match b {
+            BinaryColor::On => QuadColor::Black,
+            BinaryColor::Off => QuadColor::White,
+        }
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl From<QuadColor> for embedded_graphics_core::pixelcolor::Rgb888 {
+    fn from(b: QuadColor) -> Self {
+        let (r, g, b) = b.rgb();
+        Self::new(r, g, b)
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl From<embedded_graphics_core::pixelcolor::Rgb888> for QuadColor {
+    fn from(p: embedded_graphics_core::pixelcolor::Rgb888) -> QuadColor {
+        use embedded_graphics_core::prelude::RgbColor;
+        let colors = [
+            QuadColor::Black,
+            QuadColor::White,
+            QuadColor::Yellow,
+            QuadColor::Red,
+        ];
+
+        // pick the nearest color
+        *colors
+            .iter()
+            .map(|c| (c, c.rgb()))
+            .map(|(c, (r, g, b))| {
+                let dist = (i32::from(r) - i32::from(p.r())).pow(2)
+                    + (i32::from(g) - i32::from(p.g())).pow(2)
+                    + (i32::from(b) - i32::from(p.b())).pow(2);
+                (c, dist)
+            })
+            .min_by_key(|(_c, dist)| *dist)
+            .map(|(c, _)| c)
+            .unwrap_or(&QuadColor::White)
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl From<embedded_graphics_core::pixelcolor::raw::RawU2> for QuadColor {
+    fn from(b: embedded_graphics_core::pixelcolor::raw::RawU2) -> Self {
+        use embedded_graphics_core::prelude::RawData;
+        QuadColor::from_bits(b.into_inner())
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl PixelColor for QuadColor {
+    type Raw = embedded_graphics_core::pixelcolor::raw::RawU2;
+}
+
+impl QuadColor {
+    /// Gets the 2 bit representation of the Color as needed by the display
+    pub fn get_bits(self) -> u8 {
+        self as u8
+    }
+
+    /// Gets a full byte of 4 pixels of this color
+    pub fn get_byte_value(self) -> u8 {
+        self.get_bits() * 0b0101_0101
+    }
+
+    /// Converts the lower 2 bits to a QuadColor
+    pub fn from_bits(bits: u8) -> QuadColor {
+        match bits & 0b11 {
+            0b00 => QuadColor::Black,
+            0b01 => QuadColor::White,
+            0b10 => QuadColor::Yellow,
+            _ => QuadColor::Red,
+        }
+    }
+
+    /// Converts to limited range of RGB values.
+    pub fn rgb(self) -> (u8, u8, u8) {
+        match self {
+            QuadColor::Black => (0x00, 0x00, 0x00),
+            QuadColor::White => (0xff, 0xff, 0xff),
+            QuadColor::Yellow => (0xff, 0xff, 0x00),
+            QuadColor::Red => (0xff, 0x00, 0x00),
+        }
+    }
+}
+
 //TODO: Rename get_bit_value to bit() and get_byte_value to byte() ?
 
 impl Color {
@@ -530,6 +696,56 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_quad() {
+        for bits in 0..4 {
+            assert_eq!(QuadColor::from_bits(bits).get_bits(), bits);
+        }
+        assert_eq!(QuadColor::White.get_byte_value(), 0b01010101);
+        assert_eq!(QuadColor::Red.get_byte_value(), 0xFF);
+    }
+
+    #[test]
+    fn test_quadcolor_bitmask() {
+        assert_eq!(QuadColor::Red.bitmask(false, 0), (0b00111111, 0b11000000));
+        assert_eq!(
+            QuadColor::Yellow.bitmask(false, 1),
+            (0b11001111, 0b00100000)
+        );
+        assert_eq!(QuadColor::White.bitmask(false, 2), (0b11110011, 0b00000100));
+        assert_eq!(QuadColor::Black.bitmask(false, 7), (0b11111100, 0b00000000));
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn test_quadcolor_rgb888() {
+        use embedded_graphics_core::pixelcolor::Rgb888;
+        for color in [
+            QuadColor::Black,
+            QuadColor::White,
+            QuadColor::Yellow,
+            QuadColor::Red,
+        ] {
+            assert_eq!(QuadColor::from(Rgb888::from(color)), color);
+        }
+        assert_eq!(QuadColor::from(Rgb888::new(255, 140, 0)), QuadColor::Yellow);
+        assert_eq!(QuadColor::from(Rgb888::new(200, 30, 20)), QuadColor::Red);
+        assert_eq!(QuadColor::from(Rgb888::new(40, 40, 60)), QuadColor::Black);
+        assert_eq!(
+            QuadColor::from(Rgb888::new(210, 220, 230)),
+            QuadColor::White
+        );
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn test_gray2_bitmask() {
+        assert_eq!(Gray2::BLACK.bitmask(false, 0), (0b00111111, 0b00000000));
+        assert_eq!(Gray2::new(1).bitmask(false, 1), (0b11001111, 0b00010000));
+        assert_eq!(Gray2::new(2).bitmask(false, 2), (0b11110011, 0b00001000));
+        assert_eq!(Gray2::WHITE.bitmask(false, 7), (0b11111100, 0b00000011));
+    }
+
     #[test]
     fn test_tricolor_bitmask() {
         assert_eq!(
@@ -0,0 +1,6 @@
+//! Controller code shared by the drivers of panels with the same controller
+//!
+//! A driver only keeps its resolution, init quirks and waveforms, while the controller module
+//! holds the command set, the RAM addressing and the update sequencing.
+
+pub(crate) mod ssd1680;
@@ -0,0 +1,94 @@
+//! SPI Commands of the SSD1680
+
+use crate::traits;
+
+/// SSD1680 commands
+///
+/// Should rarely (never?) be needed directly.
+///
+/// For more infos about the addresses and what they are doing look into the SSD1680 datasheet
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub(crate) enum Command {
+    /// Driver Output control
+    ///     3 Databytes:
+    ///     A[7:0]: lines - 1
+    ///     0.. A[8]
+    ///     0.. B[2:0]: gate scanning order
+    DriverOutputControl = 0x01,
+    /// Gate driving voltage, 1 Databyte
+    GateDrivingVoltage = 0x03,
+    /// Source driving voltage, 3 Databytes
+    SourceDrivingVoltage = 0x04,
+    /// Booster soft start, 4 Databytes
+    BoosterSoftStartControl = 0x0C,
+    /// Deep Sleep Mode Control
+    ///     1 Databyte:
+    ///     0x00: Normal Mode (POR)
+    ///     0x01: Deep Sleep Mode 1, the RAM is kept
+    ///     0x03: Deep Sleep Mode 2, the RAM is lost
+    DeepSleepMode = 0x10,
+    /// Data Entry mode setting
+    ///     1 Databyte: A[1:0] x/y increment, A[2] direction of the address counter
+    DataEntryModeSetting = 0x11,
+    /// Resets the commands and parameters to their defaults, the RAM is kept
+    SwReset = 0x12,
+    /// Temperature sensor selection, 0x80 for the internal sensor
+    TemperatureSensorSelection = 0x18,
+    /// Writes the temperature register, which selects the waveform of the OTP
+    TemperatureSensorControl = 0x1A,
+    /// Reads the temperature register, 2 Databytes with the integer °C in the first
+    TemperatureSensorRead = 0x1B,
+    /// Starts the sequence selected with `DisplayUpdateControl2`, BUSY is high until it's done
+    MasterActivation = 0x20,
+    /// RAM content options, `[0x00, 0x80]` shows the black and white RAM only
+    DisplayUpdateControl1 = 0x21,
+    /// Selects the sequence of `MasterActivation`
+    DisplayUpdateControl2 = 0x22,
+    /// Writes the black and white RAM, the new image
+    WriteRam = 0x24,
+    /// Writes the red RAM, the old image of a quick refresh on monochrome panels
+    WriteRam2 = 0x26,
+    /// VCOM register, 1 Databyte
+    WriteVcomRegister = 0x2C,
+    /// Waveform of the LUT register, 153 Databytes
+    WriteLutRegister = 0x32,
+    /// OTP selection options, 10 Databytes
+    WriteOtpSelection = 0x37,
+    /// Border waveform
+    BorderWaveformControl = 0x3C,
+    /// End option of the LUT, 1 Databyte
+    WriteLutRegisterEnd = 0x3F,
+    /// Start and end of the RAM window in x, in bytes
+    SetRamXAddressStartEndPosition = 0x44,
+    /// Start and end of the RAM window in y, 2 Databytes each
+    SetRamYAddressStartEndPosition = 0x45,
+    /// Address counter in x, in bytes
+    SetRamXAddressCounter = 0x4E,
+    /// Address counter in y, 2 Databytes
+    SetRamYAddressCounter = 0x4F,
+    /// No operation, also terminates a frame memory write
+    Nop = 0x7F,
+}
+
+impl traits::Command for Command {
+    /// Returns the address of the command
+    fn address(self) -> u8 {
+        self as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Command;
+    use crate::traits::Command as CommandTrait;
+
+    #[test]
+    fn command_addr() {
+        assert_eq!(Command::DriverOutputControl.address(), 0x01);
+
+        assert_eq!(Command::SetRamXAddressCounter.address(), 0x4E);
+
+        assert_eq!(Command::Nop.address(), 0x7F);
+    }
+}
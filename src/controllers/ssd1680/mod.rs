@@ -0,0 +1,561 @@
+//! Controller code shared by the panels with a Solomon Systech SSD1680
+//!
+//! The SSD1680 keeps two frames: the black and white RAM (`WriteRam`) and the red RAM
+//! (`WriteRam2`), which the monochrome panels use as the old image of a quick refresh. The panels
+//! only differ in their resolution, where they start in the RAM and the waveforms they load, which
+//! stays in the drivers.
+//!
+//! The RAM is addressed in bytes of 8 horizontal pixels, every window is set with an inclusive
+//! end and with the address counters at its start. `x` and `width` of a window must be multiples
+//! of 8.
+//!
+//! Ported so far: [`epd2in13_v4`](crate::epd2in13_v4), [`epd2in9_v2`](crate::epd2in9_v2)
+
+pub(crate) mod command;
+
+use crate::error::Error;
+use embedded_hal::{
+    delay::DelayNs,
+    digital::{InputPin, OutputPin},
+    spi::SpiDevice,
+};
+
+use crate::interface::DisplayInterface;
+use crate::{buffer_len, check_partial_window};
+
+use self::command::Command;
+
+/// BUSY is high active
+pub(crate) const IS_BUSY_LOW: bool = false;
+
+/// Resolution of a panel and its position in the RAM of the controller
+#[derive(Clone, Copy)]
+pub(crate) struct Panel {
+    /// Pixels of a line
+    pub(crate) width: u32,
+    /// Number of lines
+    pub(crate) height: u32,
+    /// First RAM column of the panel, in bytes
+    pub(crate) x_offset: u32,
+}
+
+impl Panel {
+    /// Bytes of a line, including the padding of a width that isn't a multiple of 8
+    pub(crate) const fn line_bytes(&self) -> u32 {
+        (self.width + 7) / 8
+    }
+
+    /// Width of the RAM used by the panel, the padding pixels can be addressed
+    pub(crate) const fn ram_width(&self) -> u32 {
+        self.line_bytes() * 8
+    }
+
+    /// Length of a full frame
+    pub(crate) const fn buffer_len(&self) -> usize {
+        buffer_len(self.width as usize, self.height as usize)
+    }
+}
+
+/// Waits until BUSY is low
+pub(crate) fn wait_until_idle<SPI, BUSY, DC, RST, DELAY, PWR, const SINGLE_BYTE_WRITE: bool>(
+    interface: &mut DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    delay: &mut DELAY,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    interface.wait_until_idle(delay, IS_BUSY_LOW)
+}
+
+/// Resets the commands and registers, the RAM is kept
+pub(crate) fn software_reset<SPI, BUSY, DC, RST, DELAY, PWR, const SINGLE_BYTE_WRITE: bool>(
+    interface: &mut DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    wait_until_idle(interface, delay)?;
+    interface.cmd(spi, Command::SwReset)?;
+    wait_until_idle(interface, delay)?;
+    Ok(())
+}
+
+/// Sets the number of lines and an address counter which moves in x first
+pub(crate) fn set_driver_output<SPI, BUSY, DC, RST, DELAY, PWR, const SINGLE_BYTE_WRITE: bool>(
+    interface: &mut DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    spi: &mut SPI,
+    panel: Panel,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    let lines = panel.height - 1;
+    interface.cmd_with_data(
+        spi,
+        Command::DriverOutputControl,
+        &[lines as u8, (lines >> 8) as u8, 0x00],
+    )?;
+    // x increment, y increment, address counter is updated in x direction
+    interface.cmd_with_data(spi, Command::DataEntryModeSetting, &[0x03])
+}
+
+/// Selects the RAM window and moves the address counters to its start
+pub(crate) fn set_window<SPI, BUSY, DC, RST, DELAY, PWR, const SINGLE_BYTE_WRITE: bool>(
+    interface: &mut DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    spi: &mut SPI,
+    panel: Panel,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    let start_x = panel.x_offset + x / 8;
+    let end_x = panel.x_offset + (x + width - 1) / 8;
+    let end_y = y + height - 1;
+    interface.cmd_with_data(
+        spi,
+        Command::SetRamXAddressStartEndPosition,
+        &[start_x as u8, end_x as u8],
+    )?;
+    interface.cmd_with_data(
+        spi,
+        Command::SetRamYAddressStartEndPosition,
+        &[y as u8, (y >> 8) as u8, end_y as u8, (end_y >> 8) as u8],
+    )?;
+    interface.cmd_with_data(spi, Command::SetRamXAddressCounter, &[start_x as u8])?;
+    interface.cmd_with_data(
+        spi,
+        Command::SetRamYAddressCounter,
+        &[y as u8, (y >> 8) as u8],
+    )
+}
+
+/// Writes a window of `ram`, which is either `WriteRam` or `WriteRam2`
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn write_window<SPI, BUSY, DC, RST, DELAY, PWR, const SINGLE_BYTE_WRITE: bool>(
+    interface: &mut DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+    panel: Panel,
+    ram: Command,
+    buffer: &[u8],
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    check_partial_window(
+        buffer.len(),
+        x,
+        y,
+        width,
+        height,
+        panel.ram_width(),
+        panel.height,
+    )?;
+    wait_until_idle(interface, delay)?;
+    set_window(interface, spi, panel, x, y, width, height)?;
+    interface.cmd_with_data(spi, ram, buffer)
+}
+
+/// Writes a window of `ram` with the data taken from an iterator, see
+/// [`DisplayInterface::data_iter`]
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn write_window_iter<SPI, BUSY, DC, RST, DELAY, PWR, const SINGLE_BYTE_WRITE: bool>(
+    interface: &mut DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+    panel: Panel,
+    ram: Command,
+    data: impl IntoIterator<Item = u8>,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    let len = buffer_len(width as usize, height as usize);
+    check_partial_window(len, x, y, width, height, panel.ram_width(), panel.height)?;
+    wait_until_idle(interface, delay)?;
+    set_window(interface, spi, panel, x, y, width, height)?;
+    interface.cmd(spi, ram)?;
+    interface.data_iter(spi, data, len)
+}
+
+/// Fills a window of `ram` with `color`
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn fill_window<SPI, BUSY, DC, RST, DELAY, PWR, const SINGLE_BYTE_WRITE: bool>(
+    interface: &mut DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+    panel: Panel,
+    ram: Command,
+    color: u8,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    let len = buffer_len(width as usize, height as usize);
+    check_partial_window(len, x, y, width, height, panel.ram_width(), panel.height)?;
+    wait_until_idle(interface, delay)?;
+    set_window(interface, spi, panel, x, y, width, height)?;
+    interface.cmd(spi, ram)?;
+    interface.data_x_times(spi, color, len as u32)
+}
+
+/// Writes a full frame to `ram`
+pub(crate) fn write_frame<SPI, BUSY, DC, RST, DELAY, PWR, const SINGLE_BYTE_WRITE: bool>(
+    interface: &mut DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+    panel: Panel,
+    ram: Command,
+    buffer: &[u8],
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    let (width, height) = (panel.ram_width(), panel.height);
+    write_window(
+        interface, spi, delay, panel, ram, buffer, 0, 0, width, height,
+    )
+}
+
+/// Fills the full frame of `ram` with `color`
+pub(crate) fn fill_frame<SPI, BUSY, DC, RST, DELAY, PWR, const SINGLE_BYTE_WRITE: bool>(
+    interface: &mut DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+    panel: Panel,
+    ram: Command,
+    color: u8,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    let (width, height) = (panel.ram_width(), panel.height);
+    fill_window(
+        interface, spi, delay, panel, ram, color, 0, 0, width, height,
+    )
+}
+
+/// Runs the display update `sequence` (`DisplayUpdateControl2`) and waits until it is done
+pub(crate) fn activate<SPI, BUSY, DC, RST, DELAY, PWR, const SINGLE_BYTE_WRITE: bool>(
+    interface: &mut DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+    sequence: u8,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    wait_until_idle(interface, delay)?;
+    interface.cmd_with_data(spi, Command::DisplayUpdateControl2, &[sequence])?;
+    interface.cmd(spi, Command::MasterActivation)?;
+    wait_until_idle(interface, delay)?;
+    Ok(())
+}
+
+/// Loads the temperature of the internal sensor with its waveform and reads it back, in °C
+pub(crate) fn read_temperature<SPI, BUSY, DC, RST, DELAY, PWR, const SINGLE_BYTE_WRITE: bool>(
+    interface: &mut DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+) -> Result<i8, Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    interface.cmd_with_data(spi, Command::TemperatureSensorSelection, &[0x80])?;
+    activate(interface, spi, delay, 0xB1)?;
+    let mut temperature = [0; 2];
+    interface.cmd_and_read(spi, Command::TemperatureSensorRead, &mut temperature)?;
+    Ok(temperature[0] as i8)
+}
+
+/// Overwrites the temperature with `celsius` and loads its waveform from the OTP, a refresh
+/// with `0xC7` keeps it
+pub(crate) fn write_temperature<SPI, BUSY, DC, RST, DELAY, PWR, const SINGLE_BYTE_WRITE: bool>(
+    interface: &mut DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+    celsius: i8,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    interface.cmd_with_data(
+        spi,
+        Command::TemperatureSensorControl,
+        &[celsius as u8, 0x00],
+    )?;
+    activate(interface, spi, delay, 0x91)
+}
+
+/// Writes the VCOM register, see [`ssd_vcom`](crate::traits::ssd_vcom) for the values
+pub(crate) fn write_vcom<SPI, BUSY, DC, RST, DELAY, PWR, const SINGLE_BYTE_WRITE: bool>(
+    interface: &mut DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    spi: &mut SPI,
+    vcom: u8,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    interface.cmd_with_data(spi, Command::WriteVcomRegister, &[vcom])
+}
+
+/// Enters the deep sleep mode 1, the RAM is kept but a reset is needed to wake up
+pub(crate) fn deep_sleep<SPI, BUSY, DC, RST, DELAY, PWR, const SINGLE_BYTE_WRITE: bool>(
+    interface: &mut DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    wait_until_idle(interface, delay)?;
+    interface.cmd_with_data(spi, Command::DeepSleepMode, &[0x01])
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+    use crate::interface::mock::expect_commands;
+    use crate::NoPin;
+    use embedded_hal_mock::eh1::{
+        delay::NoopDelay,
+        digital::{Mock as PinMock, State, Transaction as PinTransaction},
+        spi::{Mock as SpiMock, Transaction as SpiTransaction},
+    };
+    use std::vec;
+
+    const PANEL: Panel = Panel {
+        width: 122,
+        height: 250,
+        x_offset: 0,
+    };
+
+    /// Writes an 8 x 2 window at (x, 300) of a panel starting at `x_offset` and checks the commands
+    fn check_window(x_offset: u32, x: u32, expected_x: u8) {
+        let panel = Panel {
+            height: 400,
+            x_offset,
+            ..PANEL
+        };
+        let buffer = [0xAA; 2];
+        let (spi_expectations, dc_expectations) = expect_commands(&[
+            (0x44, &[expected_x, expected_x]),
+            (0x45, &[0x2C, 0x01, 0x2D, 0x01]),
+            (0x4E, &[expected_x]),
+            (0x4F, &[0x2C, 0x01]),
+            (0x24, &buffer),
+        ]);
+        let mut spi = SpiMock::new(&spi_expectations);
+        let mut busy = PinMock::new(&[PinTransaction::get(State::Low)]);
+        let mut dc = PinMock::new(&dc_expectations);
+        let mut rst = PinMock::new(&[]);
+
+        let mut interface = DisplayInterface::<_, _, _, _, NoopDelay, NoPin, true>::new(
+            busy.clone(),
+            dc.clone(),
+            Some(rst.clone()),
+            None,
+            None,
+        );
+        write_window(
+            &mut interface,
+            &mut spi,
+            &mut NoopDelay::new(),
+            panel,
+            Command::WriteRam,
+            &buffer,
+            x,
+            300,
+            8,
+            2,
+        )
+        .unwrap();
+        drop(interface);
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
+
+    #[test]
+    fn panel_geometry() {
+        assert_eq!(PANEL.line_bytes(), 16);
+        assert_eq!(PANEL.ram_width(), 128);
+        assert_eq!(PANEL.buffer_len(), 4000);
+    }
+
+    #[test]
+    fn window_in_the_padding_byte() {
+        check_window(0, 120, 0x0F);
+    }
+
+    #[test]
+    fn window_with_an_x_offset() {
+        check_window(2, 8, 0x03);
+    }
+
+    #[test]
+    fn window_at_x_3() {
+        let mut spi = SpiMock::new(&[]);
+        let mut busy = PinMock::new(&[]);
+        let mut dc = PinMock::new(&[]);
+        let mut rst = PinMock::new(&[]);
+
+        let mut interface = DisplayInterface::<_, _, _, _, NoopDelay, NoPin, true>::new(
+            busy.clone(),
+            dc.clone(),
+            Some(rst.clone()),
+            None,
+            None,
+        );
+        let result = write_window(
+            &mut interface,
+            &mut spi,
+            &mut NoopDelay::new(),
+            PANEL,
+            Command::WriteRam,
+            &[0xAA; 2],
+            3,
+            0,
+            8,
+            2,
+        );
+        assert_eq!(
+            result,
+            Err(Error::OutOfBounds {
+                x: 3,
+                y: 0,
+                width: 8,
+                height: 2
+            })
+        );
+        drop(interface);
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
+
+    #[test]
+    fn read_a_temperature_below_zero() {
+        let (mut spi_expectations, mut dc_expectations) =
+            expect_commands(&[(0x18, &[0x80]), (0x22, &[0xB1]), (0x20, &[]), (0x1B, &[])]);
+        dc_expectations.push(PinTransaction::set(State::High));
+        spi_expectations.push(SpiTransaction::transaction_start());
+        spi_expectations.push(SpiTransaction::read_vec(vec![0xE7, 0x80]));
+        spi_expectations.push(SpiTransaction::transaction_end());
+        let mut spi = SpiMock::new(&spi_expectations);
+        let mut busy = PinMock::new(&vec![PinTransaction::get(State::Low); 2]);
+        let mut dc = PinMock::new(&dc_expectations);
+        let mut rst = PinMock::new(&[]);
+
+        let mut interface = DisplayInterface::<_, _, _, _, NoopDelay, NoPin, true>::new(
+            busy.clone(),
+            dc.clone(),
+            Some(rst.clone()),
+            None,
+            None,
+        );
+        assert_eq!(
+            read_temperature(&mut interface, &mut spi, &mut NoopDelay::new()),
+            Ok(-25)
+        );
+        drop(interface);
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
+}
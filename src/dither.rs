@@ -0,0 +1,633 @@
+//! Dithering of `Rgb888` images to the colors of the panels
+//!
+//! # 7 colors
+//!
+//! Mapping each pixel to its nearest color, as `OctColor::from` does, turns photos into flat
+//! patches. [`OctDither`] spreads the error of each pixel over its neighbours instead
+//! (Floyd–Steinberg), one row at a time, so it only keeps the errors of two rows and the image can
+//! be streamed from a file or a decoder.
+//!
+//! [`dither_into`] fills a caller slice in the layout of the `OctColor` displays, e.g. a strip for
+//! `Epd5in65f::update_band`, and [`dither_display`] draws to a `DrawTarget` like `Display5in65f`.
+//! Both allocate the errors of the rows. Without the `alloc` feature, [`OctDither::with_errors`]
+//! keeps them in a caller slice of [`errors_len`] entries instead.
+//!
+//!```rust
+//!use embedded_graphics::pixelcolor::Rgb888;
+//!use epd_waveshare::dither::dither_into;
+//!
+//!// a 4x2 image, two pixels per byte
+//!let pixels = (0..8).map(|i| Rgb888::new(i * 32, 0x80, 0xff - i * 32));
+//!let mut buffer = [0u8; 4];
+//!dither_into(4, 2, pixels, &mut buffer).unwrap();
+//!```
+//!
+//! # Black, white and red
+//!
+//! [`TriColorConversion`] maps reddish pixels to `Chromatic` and the others to black or white by
+//! their luma, optionally with a Bayer matrix so gray areas become patterns. It keeps no state
+//! and doesn't allocate, the pixels can be converted one by one as they arrive.
+//!
+//!```rust
+//!use embedded_graphics::pixelcolor::Rgb888;
+//!use epd_waveshare::dither::TriColorConversion;
+//!
+//!let conversion = TriColorConversion {
+//!    dither: true,
+//!    ..Default::default()
+//!};
+//!let row = (0..8).map(|i| Rgb888::new(0xff, i * 32, i * 32));
+//!let (mut bw, mut chromatic) = ([0u8; 1], [0u8; 1]);
+//!conversion.convert_row(0, row, &mut bw, &mut chromatic, true);
+//!```
+
+use crate::color::{ColorType, OctColor, TriColor};
+use embedded_graphics_core::pixelcolor::Rgb888;
+use embedded_graphics_core::prelude::*;
+#[cfg(feature = "alloc")]
+use embedded_graphics_core::primitives::Rectangle;
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
+/// The colors the panels can show, `HiZ` isn't a color
+const PALETTE: [OctColor; 7] = [
+    OctColor::Black,
+    OctColor::White,
+    OctColor::Green,
+    OctColor::Blue,
+    OctColor::Red,
+    OctColor::Yellow,
+    OctColor::Orange,
+];
+
+/// Error of the dithering, the image doesn't match its buffers
+///
+/// `E` is the error of the `DrawTarget` of [`dither_display`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DitherError<E = core::convert::Infallible> {
+    /// A row of the image didn't have the width of the image
+    RowLength {
+        /// The width of the image
+        expected: u32,
+        /// The number of pixels of the row
+        actual: u32,
+    },
+    /// A buffer doesn't have the length the image needs
+    BufferLength {
+        /// The length the image needs, the least one for the errors of [`OctDither`]
+        expected: usize,
+        /// The length of the buffer
+        actual: usize,
+    },
+    /// Drawing failed
+    Draw(E),
+}
+
+#[cfg(feature = "alloc")]
+impl DitherError {
+    /// The same error with the error `E` of a `DrawTarget`
+    fn with_draw_error<E>(self) -> DitherError<E> {
+        match self {
+            DitherError::RowLength { expected, actual } => {
+                DitherError::RowLength { expected, actual }
+            }
+            DitherError::BufferLength { expected, actual } => {
+                DitherError::BufferLength { expected, actual }
+            }
+            DitherError::Draw(never) => match never {},
+        }
+    }
+}
+
+/// Number of entries of the errors of [`OctDither`] for images of `width` pixels
+///
+/// The errors of two rows are kept, with a pixel of margin at both ends.
+pub const fn errors_len(width: u32) -> usize {
+    2 * (width as usize + 2)
+}
+
+/// Floyd–Steinberg error diffusion, one row of `width` pixels at a time
+///
+/// The rows have to be passed from the top to the bottom of the image. `ERRORS` holds the errors
+/// of the red, green and blue channels carried into the current and the next row, a `Vec` for
+/// [`new`](OctDither::new) or a caller slice for [`with_errors`](OctDither::with_errors).
+pub struct OctDither<ERRORS> {
+    width: u32,
+    errors: ERRORS,
+    /// The errors of the current row are in the second half of `errors`
+    flipped: bool,
+}
+
+#[cfg(feature = "alloc")]
+impl OctDither<Vec<[i16; 3]>> {
+    /// Creates the error state for images of `width` pixels
+    pub fn new(width: u32) -> Self {
+        OctDither {
+            width,
+            errors: vec![[0; 3]; errors_len(width)],
+            flipped: false,
+        }
+    }
+}
+
+impl<ERRORS: AsMut<[[i16; 3]]>> OctDither<ERRORS> {
+    /// Creates the error state for images of `width` pixels in `errors`, e.g. a
+    /// `[[0; 3]; errors_len(WIDTH)]`
+    ///
+    /// Fails if `errors` has less than [`errors_len`] entries, further ones aren't used.
+    pub fn with_errors(width: u32, mut errors: ERRORS) -> Result<Self, DitherError> {
+        let len = errors.as_mut().len();
+        if len < errors_len(width) {
+            return Err(DitherError::BufferLength {
+                expected: errors_len(width),
+                actual: len,
+            });
+        }
+        errors.as_mut()[..errors_len(width)].fill([0; 3]);
+        Ok(OctDither {
+            width,
+            errors,
+            flipped: false,
+        })
+    }
+
+    /// Dithers the next row of the image and passes each of its colors to `put`, with its `x`
+    ///
+    /// Fails if `row` doesn't yield `width` pixels, the errors carried into the next row are
+    /// dropped then.
+    pub fn dither_row(
+        &mut self,
+        row: impl IntoIterator<Item = Rgb888>,
+        mut put: impl FnMut(u32, OctColor),
+    ) -> Result<(), DitherError> {
+        let width = self.width;
+        let errors = &mut self.errors.as_mut()[..errors_len(width)];
+        let (first, second) = errors.split_at_mut(errors_len(width) / 2);
+        let (current, next) = if self.flipped {
+            (second, first)
+        } else {
+            (first, second)
+        };
+        let mut x = 0;
+        for pixel in row {
+            if x < width {
+                let i = x as usize + 1;
+                let wanted = [pixel.r(), pixel.g(), pixel.b()];
+                let mut value = [0; 3];
+                for c in 0..3 {
+                    value[c] = (i16::from(wanted[c]) + current[i][c]).clamp(0, 255);
+                }
+                let color = nearest(value);
+                let (r, g, b) = color.rgb();
+                let shown = [r, g, b];
+                for c in 0..3 {
+                    let error = value[c] - i16::from(shown[c]);
+                    current[i + 1][c] += error * 7 / 16;
+                    next[i - 1][c] += error * 3 / 16;
+                    next[i][c] += error * 5 / 16;
+                    next[i + 1][c] += error / 16;
+                }
+                put(x, color);
+            }
+            x = x.saturating_add(1);
+        }
+
+        current.fill([0; 3]);
+        if x != width {
+            next.fill([0; 3]);
+            return Err(DitherError::RowLength {
+                expected: width,
+                actual: x,
+            });
+        }
+        self.flipped = !self.flipped;
+        Ok(())
+    }
+
+    /// Dithers the next row of the image into `row`, two pixels per byte
+    ///
+    /// Fails if `row` doesn't hold `width` pixels, or like [`dither_row`](Self::dither_row).
+    pub fn dither_row_into(
+        &mut self,
+        pixels: impl IntoIterator<Item = Rgb888>,
+        row: &mut [u8],
+    ) -> Result<(), DitherError> {
+        let row_bytes = (self.width as usize + 1) / 2;
+        if row.len() != row_bytes {
+            return Err(DitherError::BufferLength {
+                expected: row_bytes,
+                actual: row.len(),
+            });
+        }
+        self.dither_row(pixels, |x, color| {
+            let byte = &mut row[x as usize / 2];
+            if x % 2 == 0 {
+                *byte = color.get_nibble() << 4 | *byte & 0x0F;
+            } else {
+                *byte = *byte & 0xF0 | color.get_nibble();
+            }
+        })
+    }
+}
+
+/// Returns the color of the palette closest to `value`, the first one wins a tie
+fn nearest(value: [i16; 3]) -> OctColor {
+    *PALETTE
+        .iter()
+        .min_by_key(|color| {
+            let (r, g, b) = color.rgb();
+            [r, g, b]
+                .iter()
+                .zip(value)
+                .map(|(&shown, wanted)| (i32::from(shown) - i32::from(wanted)).pow(2))
+                .sum::<i32>()
+        })
+        .expect("the palette isn't empty")
+}
+
+/// Dithers a `width` x `height` image, given row by row, into `buffer`
+///
+/// The buffer has the layout of the `OctColor` displays, each row starting at a new byte.
+/// Fails if `buffer` doesn't hold the image or `pixels` runs out early.
+#[cfg(feature = "alloc")]
+pub fn dither_into(
+    width: u32,
+    height: u32,
+    pixels: impl IntoIterator<Item = Rgb888>,
+    buffer: &mut [u8],
+) -> Result<(), DitherError> {
+    let row_bytes = (width as usize + 1) / 2;
+    if buffer.len() != row_bytes * height as usize {
+        return Err(DitherError::BufferLength {
+            expected: row_bytes * height as usize,
+            actual: buffer.len(),
+        });
+    }
+    let mut dither = OctDither::new(width);
+    let mut pixels = pixels.into_iter();
+    for row in buffer.chunks_exact_mut(row_bytes) {
+        dither.dither_row_into(pixels.by_ref().take(width as usize), row)?;
+    }
+    Ok(())
+}
+
+/// Dithers an image of the size of `display`, given row by row, and draws it
+///
+/// The image is drawn in the coordinates of `display`, so it follows its rotation and mirroring.
+/// Fails if `pixels` runs out early, the rows before are drawn.
+#[cfg(feature = "alloc")]
+pub fn dither_display<D: DrawTarget<Color = OctColor>>(
+    display: &mut D,
+    pixels: impl IntoIterator<Item = Rgb888>,
+) -> Result<(), DitherError<D::Error>> {
+    let Rectangle { top_left, size } = display.bounding_box();
+    let mut dither = OctDither::new(size.width);
+    let mut pixels = pixels.into_iter();
+    let mut row = Vec::with_capacity(size.width as usize);
+    for y in 0..size.height as i32 {
+        let dithered = dither.dither_row(pixels.by_ref().take(size.width as usize), |x, color| {
+            row.push(Pixel(top_left + Point::new(x as i32, y), color));
+        });
+        dithered.map_err(DitherError::with_draw_error)?;
+        display
+            .draw_iter(row.drain(..))
+            .map_err(DitherError::Draw)?;
+    }
+    Ok(())
+}
+
+/// Thresholds of the luma with ordered dithering, a 4x4 Bayer matrix scaled to `0..=255`
+pub(crate) const BAYER: [[u8; 4]; 4] = [
+    [8, 136, 40, 168],
+    [200, 72, 232, 104],
+    [56, 184, 24, 152],
+    [248, 120, 216, 88],
+];
+
+/// Maps `Rgb888` pixels to `TriColor`
+///
+/// A pixel is `Chromatic` if its red exceeds its green and its blue by `red_threshold`. Otherwise
+/// it's `Black` if its luma is below the middle gray, or below the threshold of the Bayer matrix
+/// at its position when `dither` is set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TriColorConversion {
+    /// How much redder than green and blue a pixel has to be to be `Chromatic`, 0 makes every
+    /// pixel with the most red chromatic and 255 only pure red
+    pub red_threshold: u8,
+    /// Dither the luma with a 4x4 Bayer matrix
+    pub dither: bool,
+}
+
+impl Default for TriColorConversion {
+    fn default() -> Self {
+        TriColorConversion {
+            red_threshold: 96,
+            dither: false,
+        }
+    }
+}
+
+impl TriColorConversion {
+    /// Returns the color of `pixel` at `x`, `y`, the position only matters with `dither`
+    pub fn color(&self, x: u32, y: u32, pixel: Rgb888) -> TriColor {
+        let (r, g, b) = (pixel.r(), pixel.g(), pixel.b());
+        if r > g.max(b) && r - g.max(b) >= self.red_threshold.max(1) {
+            return TriColor::Chromatic;
+        }
+        // ITU-R BT.601, the weights add up to 256
+        let luma = (77 * u16::from(r) + 150 * u16::from(g) + 29 * u16::from(b)) >> 8;
+        let threshold = if self.dither {
+            BAYER[y as usize % 4][x as usize % 4]
+        } else {
+            128
+        };
+        if luma < u16::from(threshold) {
+            TriColor::Black
+        } else {
+            TriColor::White
+        }
+    }
+
+    /// Converts the line `y` of an image into the lines of both planes, in the layout of the
+    /// `TriColor` displays with `bwrbit`
+    ///
+    /// The line is as wide as the pixels yielded, `bw` and `chromatic` must hold them. Their bits
+    /// past the last pixel are kept.
+    pub fn convert_row(
+        &self,
+        y: u32,
+        pixels: impl IntoIterator<Item = Rgb888>,
+        bw: &mut [u8],
+        chromatic: &mut [u8],
+        bwrbit: bool,
+    ) {
+        for (x, pixel) in (0..).zip(pixels) {
+            let (mask, bits) = self.color(x, y, pixel).bitmask(bwrbit, x);
+            let i = x as usize / 8;
+            bw[i] = bw[i] & mask | (bits & 0xFF) as u8;
+            chromatic[i] = chromatic[i] & mask | (bits >> 8) as u8;
+        }
+    }
+
+    /// Converts a `width` x `height` image, given row by row, into both planes
+    ///
+    /// Fails if the planes don't hold the image, a line takes `(width + 7) / 8` bytes.
+    pub fn convert_into(
+        &self,
+        width: u32,
+        height: u32,
+        pixels: impl IntoIterator<Item = Rgb888>,
+        bw: &mut [u8],
+        chromatic: &mut [u8],
+        bwrbit: bool,
+    ) -> Result<(), DitherError> {
+        let len = crate::buffer_len(width as usize, height as usize);
+        if let Some(plane) = [&*bw, &*chromatic].iter().find(|plane| plane.len() != len) {
+            return Err(DitherError::BufferLength {
+                expected: len,
+                actual: plane.len(),
+            });
+        }
+        let line = (width as usize + 7) / 8;
+        let mut pixels = pixels.into_iter();
+        let rows = bw
+            .chunks_exact_mut(line)
+            .zip(chromatic.chunks_exact_mut(line));
+        for (y, (bw, chromatic)) in (0..).zip(rows) {
+            let row = pixels.by_ref().take(width as usize);
+            self.convert_row(y, row, bw, chromatic, bwrbit);
+        }
+        Ok(())
+    }
+
+    /// Converts an image of the size of `display`, given row by row, and draws it
+    ///
+    /// The image is drawn in the coordinates of `display`, so it follows its rotation and
+    /// mirroring. Drawing stops when `pixels` runs out.
+    pub fn draw<D: DrawTarget<Color = TriColor>>(
+        &self,
+        display: &mut D,
+        pixels: impl IntoIterator<Item = Rgb888>,
+    ) -> Result<(), D::Error> {
+        let area = display.bounding_box();
+        let origin = area.top_left;
+        let converted = area.points().zip(pixels).map(|(point, pixel)| {
+            let at = point - origin;
+            Pixel(point, self.color(at.x as u32, at.y as u32, pixel))
+        });
+        display.draw_iter(converted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::Display;
+
+    /// A 6x4 gray ramp from black to white, with blue fading in towards the bottom
+    fn gradient() -> impl Iterator<Item = Rgb888> {
+        (0..4u8).flat_map(|y| (0..6u8).map(move |x| Rgb888::new(x * 51, x * 51, y * 85)))
+    }
+
+    /// The gradient dithered by the reference implementation
+    const REFERENCE: [u8; 12] = [
+        0x00, 0x62, 0x65, 0x00, 0x36, 0x51, 0x33, 0x53, 0x15, 0x33, 0x11, 0x11,
+    ];
+
+    #[test]
+    fn gradient_matches_the_reference() {
+        let mut buffer = [0u8; 12];
+        dither_into(6, 4, gradient(), &mut buffer).unwrap();
+        assert_eq!(buffer, REFERENCE);
+    }
+
+    #[test]
+    fn palette_colors_are_kept() {
+        for color in PALETTE {
+            let mut buffer = [0u8; 6];
+            dither_into(3, 3, core::iter::repeat(color.into()), &mut buffer).unwrap();
+            let byte = OctColor::colors_byte(color, color);
+            let last = OctColor::colors_byte(color, OctColor::Black);
+            assert_eq!(buffer, [byte, last, byte, last, byte, last]);
+        }
+    }
+
+    #[test]
+    fn display_matches_the_buffer() {
+        let mut display = Display::<6, 4, false, 12, OctColor>::default();
+        dither_display(&mut display, gradient()).unwrap();
+        assert_eq!(display.buffer(), REFERENCE);
+    }
+
+    #[test]
+    fn tricolor_thresholds() {
+        let conversion = TriColorConversion::default();
+        let cases = [
+            (Rgb888::new(0xff, 0x00, 0x00), TriColor::Chromatic),
+            (Rgb888::new(0x80, 0x10, 0x20), TriColor::Chromatic),
+            (Rgb888::new(0xff, 0xa0, 0x90), TriColor::White),
+            (Rgb888::new(0x50, 0x20, 0x20), TriColor::Black),
+            (Rgb888::new(0x7f, 0x7f, 0x7f), TriColor::Black),
+            (Rgb888::new(0x80, 0x80, 0x80), TriColor::White),
+            (Rgb888::new(0x00, 0x00, 0xff), TriColor::Black),
+            (Rgb888::new(0xff, 0xff, 0x00), TriColor::White),
+        ];
+        for (pixel, color) in cases {
+            assert_eq!(conversion.color(3, 5, pixel), color, "{pixel:?}");
+        }
+
+        let strict = TriColorConversion {
+            red_threshold: 0xff,
+            ..conversion
+        };
+        assert_eq!(
+            strict.color(0, 0, Rgb888::new(0xff, 0x01, 0)),
+            TriColor::Black
+        );
+        assert_eq!(
+            strict.color(0, 0, Rgb888::new(0xff, 0, 0)),
+            TriColor::Chromatic
+        );
+    }
+
+    #[test]
+    fn tricolor_dithers_gray() {
+        let conversion = TriColorConversion {
+            dither: true,
+            ..Default::default()
+        };
+        // the middle gray is black at half of the matrix, in a checkerboard
+        let gray = core::iter::repeat(Rgb888::new(0x80, 0x80, 0x80));
+        let mut bw = [0u8; 4];
+        let mut chromatic = [0xFFu8; 4];
+        conversion
+            .convert_into(8, 4, gray, &mut bw, &mut chromatic, false)
+            .unwrap();
+        assert_eq!(bw, [0b1010_1010, 0b0101_0101, 0b1010_1010, 0b0101_0101]);
+        assert_eq!(chromatic, [0; 4]);
+
+        // a dark gray gets a few white pixels
+        let dark = core::iter::repeat(Rgb888::new(0x40, 0x40, 0x40));
+        let (mut bw, mut chromatic) = ([0u8; 4], [0u8; 4]);
+        conversion
+            .convert_into(4, 4, dark, &mut bw, &mut chromatic, false)
+            .unwrap();
+        let white: u32 = bw.iter().map(|b| b.count_ones()).sum();
+        assert_eq!(white, 4);
+    }
+
+    #[test]
+    fn tricolor_display_matches_the_planes() {
+        let conversion = TriColorConversion {
+            dither: true,
+            ..Default::default()
+        };
+        let image = || {
+            (0..6u8).flat_map(|y| (0..10u8).map(move |x| Rgb888::new(x * 25 + y * 5, x * 8, 60)))
+        };
+        let mut display = Display::<10, 6, true, { 2 * 2 * 6 }, TriColor>::default();
+        conversion.draw(&mut display, image()).unwrap();
+        let (mut bw, mut chromatic) = ([0u8; 12], [0u8; 12]);
+        conversion
+            .convert_into(10, 6, image(), &mut bw, &mut chromatic, true)
+            .unwrap();
+        assert_eq!(display.bw_buffer(), bw);
+        assert_eq!(display.chromatic_buffer(), chromatic);
+        assert!(chromatic.iter().any(|&b| b != 0));
+    }
+
+    #[test]
+    fn caller_errors_match_the_reference() {
+        let mut errors = [[0; 3]; errors_len(6)];
+        let mut dither = OctDither::with_errors(6, &mut errors[..]).unwrap();
+        let mut buffer = [0u8; 12];
+        let mut pixels = gradient();
+        for row in buffer.chunks_exact_mut(3) {
+            dither
+                .dither_row_into(pixels.by_ref().take(6), row)
+                .unwrap();
+        }
+        assert_eq!(buffer, REFERENCE);
+
+        assert_eq!(
+            OctDither::with_errors(6, [[0; 3]; 15]).err(),
+            Some(DitherError::BufferLength {
+                expected: 16,
+                actual: 15
+            })
+        );
+    }
+
+    #[test]
+    fn image_ends_early() {
+        let mut buffer = [0u8; 12];
+        assert_eq!(
+            dither_into(6, 4, gradient().take(20), &mut buffer),
+            Err(DitherError::RowLength {
+                expected: 6,
+                actual: 2
+            })
+        );
+
+        let mut display = Display::<6, 4, false, 12, OctColor>::default();
+        assert_eq!(
+            dither_display(&mut display, gradient().take(20)),
+            Err(DitherError::RowLength {
+                expected: 6,
+                actual: 2
+            })
+        );
+    }
+
+    #[test]
+    fn rows_of_the_wrong_length_are_rejected() {
+        let mut dither = OctDither::new(6);
+        let mut put = |_, _| {};
+        assert_eq!(
+            dither.dither_row(gradient().take(7), &mut put),
+            Err(DitherError::RowLength {
+                expected: 6,
+                actual: 7
+            })
+        );
+        assert_eq!(
+            dither.dither_row_into(gradient().take(6), &mut [0u8; 4]),
+            Err(DitherError::BufferLength {
+                expected: 3,
+                actual: 4
+            })
+        );
+        // the next image starts over
+        let mut buffer = [0u8; 12];
+        let mut pixels = gradient();
+        for row in buffer.chunks_exact_mut(3) {
+            dither
+                .dither_row_into(pixels.by_ref().take(6), row)
+                .unwrap();
+        }
+        assert_eq!(buffer, REFERENCE);
+    }
+
+    #[test]
+    fn buffers_of_the_wrong_length_are_rejected() {
+        assert_eq!(
+            dither_into(6, 4, gradient(), &mut [0u8; 11]),
+            Err(DitherError::BufferLength {
+                expected: 12,
+                actual: 11
+            })
+        );
+        let conversion = TriColorConversion::default();
+        let pixels = core::iter::repeat(Rgb888::new(0, 0, 0));
+        assert_eq!(
+            conversion.convert_into(8, 4, pixels, &mut [0u8; 4], &mut [0u8; 5], false),
+            Err(DitherError::BufferLength {
+                expected: 4,
+                actual: 5
+            })
+        );
+    }
+}
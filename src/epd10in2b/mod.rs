@@ -0,0 +1,393 @@
+//! A simple Driver for the 10.2" (B) Black/White/Red E-Ink Display via SPI
+//!
+//! The controller uses a black/white RAM bank (0x24) and a red RAM bank (0x26) with 16 bit
+//! x and y address counters, the x address is given in pixels.
+//!
+//! One plane takes [`PLANE_LEN`] (76 800) bytes, both planes together 153 600 bytes. Instead of
+//! holding both in RAM each plane can be sent on its own with
+//! [`update_achromatic_frame`](WaveshareThreeColorDisplay::update_achromatic_frame) and
+//! [`update_chromatic_frame`](WaveshareThreeColorDisplay::update_chromatic_frame), or streamed
+//! in chunks of full lines with [`Epd10in2b::update_achromatic_lines`] and
+//! [`Epd10in2b::update_chromatic_lines`], e.g. straight from an external flash.
+
+use crate::error::Error;
+use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
+
+use crate::color::TriColor;
+use crate::interface::DisplayInterface;
+use crate::traits::{InternalWiAdditions, WaveshareDisplay, WaveshareThreeColorDisplay};
+use crate::type_a::command::Command;
+use crate::{buffer_len, check_buffer_len, check_partial_window};
+
+/// Width of the display
+pub const WIDTH: u32 = 960;
+/// Height of the display
+pub const HEIGHT: u32 = 640;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: TriColor = TriColor::White;
+/// Number of bytes of one color plane (black/white or red)
+pub const PLANE_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize);
+const LINE_LEN: usize = buffer_len(WIDTH as usize, 1);
+const IS_BUSY_LOW: bool = false;
+const SINGLE_BYTE_WRITE: bool = false;
+
+/// Full size buffer for use with the 10in2b EPD
+///
+/// It takes 2 * [`PLANE_LEN`] bytes of RAM.
+#[cfg(feature = "graphics")]
+pub type Display10in2b = crate::graphics::Display<WIDTH, HEIGHT, true, { PLANE_LEN * 2 }, TriColor>;
+
+/// Epd10in2b driver
+pub struct Epd10in2b<SPI, BUSY, DC, RST, DELAY, PWR> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    background_color: TriColor,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd10in2b<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, 10_000, 2_000)?;
+
+        self.wait_until_idle(spi, delay)?;
+        self.interface.cmd(spi, Command::SwReset)?;
+        self.wait_until_idle(spi, delay)?;
+
+        // 640 gate lines, default scan order
+        self.interface.cmd_with_data(
+            spi,
+            Command::DriverOutputControl,
+            &[(HEIGHT - 1) as u8, ((HEIGHT - 1) >> 8) as u8, 0x00],
+        )?;
+
+        self.interface
+            .cmd_with_data(spi, Command::BorderWaveformControl, &[0x01])?;
+
+        // x increment, y increment, address counter is updated in x direction
+        self.interface
+            .cmd_with_data(spi, Command::DataEntryModeSetting, &[0x03])?;
+
+        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
+
+        // internal temperature sensor
+        self.interface
+            .cmd_with_data(spi, Command::TemperatureSensorSelection, &[0x80])?;
+
+        self.set_ram_counter(spi, 0, 0)?;
+        self.wait_until_idle(spi, delay)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd10in2b<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    fn update_color_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+        chromatic: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_achromatic_frame(spi, delay, black)?;
+        self.update_chromatic_frame(spi, delay, chromatic)
+    }
+
+    fn update_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(black.len(), PLANE_LEN)?;
+        self.update_achromatic_lines(spi, delay, 0, black)
+    }
+
+    /// A set bit in `chromatic` is a red pixel
+    fn update_chromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        chromatic: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(chromatic.len(), PLANE_LEN)?;
+        self.update_chromatic_lines(spi, delay, 0, chromatic)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd10in2b<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    type DisplayColor = TriColor;
+
+    fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new_color(busy, dc, rst, pwr, delay_us);
+
+        let mut epd = Epd10in2b {
+            interface,
+            background_color: DEFAULT_BACKGROUND_COLOR,
+        };
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        // 0x00 for Normal mode (Power on Reset), 0x01 for Deep Sleep Mode
+        self.interface
+            .cmd_with_data(spi, Command::DeepSleepMode, &[0x01])?;
+        self.interface.power_off()
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.init(spi, delay)
+    }
+
+    fn set_background_color(&mut self, background_color: TriColor) {
+        self.background_color = background_color;
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
+    fn background_color(&self) -> &TriColor {
+        &self.background_color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    /// Updates the black/white plane and clears the red one
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_achromatic_frame(spi, delay, buffer)?;
+        self.fill_ram(spi, Command::WriteRam2, 0x00)
+    }
+
+    fn update_partial_frame(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+        _buffer: &[u8],
+        _x: u32,
+        _y: u32,
+        _width: u32,
+        _height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::Unsupported)
+    }
+
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xF7])?;
+        self.interface.cmd(spi, Command::MasterActivation)?;
+        self.wait_until_idle(spi, delay)
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+
+        let (black, chromatic) = match self.background_color {
+            TriColor::White => (0xFF, 0x00),
+            TriColor::Black => (0x00, 0x00),
+            TriColor::Chromatic => (0x00, 0xFF),
+        };
+        self.fill_ram(spi, Command::WriteRam, black)?;
+        self.fill_ram(spi, Command::WriteRam2, chromatic)
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd10in2b<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Writes full lines of the black/white plane, starting at line `y`
+    ///
+    /// `lines` must contain a multiple of `WIDTH / 8` bytes, so a plane can be sent in chunks.
+    pub fn update_achromatic_lines(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        y: u32,
+        lines: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.write_lines(spi, delay, Command::WriteRam, y, lines)
+    }
+
+    /// Writes full lines of the red plane, starting at line `y`
+    ///
+    /// `lines` must contain a multiple of `WIDTH / 8` bytes, so a plane can be sent in chunks.
+    pub fn update_chromatic_lines(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        y: u32,
+        lines: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.write_lines(spi, delay, Command::WriteRam2, y, lines)
+    }
+
+    fn write_lines(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        ram: Command,
+        y: u32,
+        lines: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        // only full lines of WIDTH / 8 bytes can be written
+        let height = (lines.len() / LINE_LEN) as u32;
+        check_buffer_len(lines.len(), height as usize * LINE_LEN)?;
+        if height == 0 {
+            return Ok(());
+        }
+        check_partial_window(lines.len(), 0, y, WIDTH, height, WIDTH, HEIGHT)?;
+
+        self.wait_until_idle(spi, delay)?;
+        self.set_ram_area(spi, 0, y, WIDTH - 1, y + height - 1)?;
+        self.set_ram_counter(spi, 0, y)?;
+        self.interface.cmd_with_data(spi, ram, lines)
+    }
+
+    fn fill_ram(
+        &mut self,
+        spi: &mut SPI,
+        ram: Command,
+        value: u8,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
+        self.set_ram_counter(spi, 0, 0)?;
+        self.interface.cmd(spi, ram)?;
+        self.interface.data_x_times(spi, value, PLANE_LEN as u32)
+    }
+
+    fn set_ram_area(
+        &mut self,
+        spi: &mut SPI,
+        start_x: u32,
+        start_y: u32,
+        end_x: u32,
+        end_y: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamXAddressStartEndPosition,
+            &[
+                start_x as u8,
+                (start_x >> 8) as u8,
+                end_x as u8,
+                (end_x >> 8) as u8,
+            ],
+        )?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamYAddressStartEndPosition,
+            &[
+                start_y as u8,
+                (start_y >> 8) as u8,
+                end_y as u8,
+                (end_y >> 8) as u8,
+            ],
+        )
+    }
+
+    fn set_ram_counter(&mut self, spi: &mut SPI, x: u32, y: u32) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamXAddressCounter,
+            &[x as u8, (x >> 8) as u8],
+        )?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamYAddressCounter,
+            &[y as u8, (y >> 8) as u8],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 960);
+        assert_eq!(HEIGHT, 640);
+        assert_eq!(PLANE_LEN, 76_800);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, TriColor::White);
+    }
+}
@@ -7,16 +7,28 @@
 //! - [Wiki](https://www.waveshare.com/wiki/12.48inch_e-Paper_Module_(B))
 //! - [Waveshare C drivers](https://github.com/waveshareteam/12.48inch-e-paper/)
 //!
+//! The panel is driven by four controllers with their own chip select, see [`Peripherals`]. The
+//! frame (~320 kB for both colors) doesn't need to be kept in RAM: the `write_data*` methods
+//! split each row over the controllers and repeat a buffer that holds fewer rows than the window,
+//! so it can be sent in bands with [`write_data1_partial`](EpdDriver::write_data1_partial).
+//!
+//! Waiting for the controllers fails with [`Error::BusyTimeout`] after 60s, see
+//! [`set_busy_timeout`](EpdDriver::set_busy_timeout).
+//!
 
 mod command;
 mod config;
 
+use core::ops::Range;
+
 use embedded_hal::{
     delay::DelayNs,
     digital::{InputPin, OutputPin, PinState},
     spi::SpiBus,
 };
 
+use crate::error::Error;
+use crate::interface::COLOR_BUSY_TIMEOUT_US;
 pub use crate::rect::Rect;
 use command::Command;
 pub use config::*;
@@ -131,6 +143,7 @@ where
     peris: Peripherals<INPUT, OUTPUT, SPI>,
     delay: DELAY,
     control_state: CS,
+    busy_timeout_us: Option<u32>,
 }
 
 impl<INPUT, OUTPUT, SPI, DELAY> EpdDriver<INPUT, OUTPUT, SPI, DELAY>
@@ -151,9 +164,16 @@ where
             peris,
             delay,
             control_state: 0,
+            busy_timeout_us: Some(COLOR_BUSY_TIMEOUT_US),
         }
     }
 
+    /// Sets the number of us the BUSY lines may stay busy before waiting for them fails with
+    /// [`Error::BusyTimeout`], `None` waits forever
+    pub fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.busy_timeout_us = timeout_us;
+    }
+
     /// Consumes EpdDriver, releasing peripherals to the caller.
     pub fn into_peripherals(self) -> Peripherals<INPUT, OUTPUT, SPI> {
         self.peris
@@ -161,7 +181,7 @@ where
 
     /// Reset the display, potentially waking it up from deep sleep.
     /// Normally should be followed by a call to [`init()`](EpdDriver::init).
-    pub fn reset(&mut self) -> Result<(), OUTPUT::Error> {
+    pub fn reset(&mut self) -> Result<(), Error<SPI::Error>> {
         drop(self.peris.m1_cs.set_high());
         drop(self.peris.s1_cs.set_high());
         drop(self.peris.m2_cs.set_high());
@@ -170,25 +190,25 @@ where
         drop(self.peris.m2s2_dc.set_low());
         self.control_state = 0;
 
-        self.peris.m1s1_rst.set_high()?;
-        self.peris.m2s2_rst.set_high()?;
+        self.peris.m1s1_rst.set_high().map_err(Error::pin)?;
+        self.peris.m2s2_rst.set_high().map_err(Error::pin)?;
         self.delay.delay_ms(1);
 
-        self.peris.m1s1_rst.set_low()?;
+        self.peris.m1s1_rst.set_low().map_err(Error::pin)?;
         self.delay.delay_us(100); // min RST low = 50us
-        self.peris.m1s1_rst.set_high()?;
+        self.peris.m1s1_rst.set_high().map_err(Error::pin)?;
         self.delay.delay_ms(100); // min wait after RST = 10ms
 
-        self.peris.m2s2_rst.set_low()?;
+        self.peris.m2s2_rst.set_low().map_err(Error::pin)?;
         self.delay.delay_us(100);
-        self.peris.m2s2_rst.set_high()?;
+        self.peris.m2s2_rst.set_high().map_err(Error::pin)?;
         self.delay.delay_ms(100);
 
         Ok(())
     }
 
     /// Initialize display registers.
-    pub fn init(&mut self, config: &Config) -> Result<(), SPI::Error> {
+    pub fn init(&mut self, config: &Config) -> Result<(), Error<SPI::Error>> {
         // booster soft start
         self.cmd_with_data(CS_ALL, Command::BoosterSoftStart, &[0x17, 0x17, 0x39, 0x17])?;
 
@@ -218,7 +238,7 @@ where
     }
 
     /// Set data "polarity", waveform lookup table mode, etc, without re-initializing anything else.
-    pub fn set_mode(&mut self, config: &Config) -> Result<(), SPI::Error> {
+    pub fn set_mode(&mut self, config: &Config) -> Result<(), Error<SPI::Error>> {
         let ddx = match (config.inverted_r, config.inverted_kw) {
             (false, true) => 0b00,
             (false, false) => 0b01,
@@ -259,28 +279,36 @@ where
     ///
     /// `pixels` may contain a lesser number of rows than the window being written,
     /// in which case it will be treated as circular.
-    pub fn write_data1(&mut self, pixels: &[u8]) -> Result<(), SPI::Error> {
+    pub fn write_data1(&mut self, pixels: &[u8]) -> Result<(), Error<SPI::Error>> {
         self.write_window_data(Command::DataStartTransmission1, FULL_RECT, pixels)?;
         self.flush()
     }
 
     /// Fill data2 buffer with pixels.
     /// See also [`write_data1`](EpdDriver::write_data1).
-    pub fn write_data2(&mut self, pixels: &[u8]) -> Result<(), SPI::Error> {
+    pub fn write_data2(&mut self, pixels: &[u8]) -> Result<(), Error<SPI::Error>> {
         self.write_window_data(Command::DataStartTransmission2, FULL_RECT, pixels)?;
         self.flush()
     }
 
     /// Fill a window in the data1 buffer with pixels.
     /// See also [`write_data1`](EpdDriver::write_data1).
-    pub fn write_data1_partial(&mut self, window: Rect, pixels: &[u8]) -> Result<(), SPI::Error> {
+    pub fn write_data1_partial(
+        &mut self,
+        window: Rect,
+        pixels: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
         self.write_partial(Command::DataStartTransmission1, window, pixels)?;
         self.flush()
     }
 
     /// Fill a window in the data2 buffer with pixels.
     /// See also [`write_data1`](EpdDriver::write_data1).
-    pub fn write_data2_partial(&mut self, window: Rect, pixels: &[u8]) -> Result<(), SPI::Error> {
+    pub fn write_data2_partial(
+        &mut self,
+        window: Rect,
+        pixels: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
         self.write_partial(Command::DataStartTransmission2, window, pixels)?;
         self.flush()
     }
@@ -290,41 +318,46 @@ where
     /// If LUT data is shorter than expected, the rest is filled with zeroes.<br>
     /// Note that stored lookup tables need to be activated by setting
     /// [`Config::external_lut`](config::Config::external_lut)`=true`.
-    pub fn set_lutc(&mut self, data: &[u8]) -> Result<(), SPI::Error> {
+    pub fn set_lutc(&mut self, data: &[u8]) -> Result<(), Error<SPI::Error>> {
         self.set_lut(Command::LutC, data, 60)
     }
 
     /// Store White-to-White Look-Up Table.
     /// See also [`write_data1`](EpdDriver::set_lutc).
-    pub fn set_lutww(&mut self, data: &[u8]) -> Result<(), SPI::Error> {
+    pub fn set_lutww(&mut self, data: &[u8]) -> Result<(), Error<SPI::Error>> {
         self.set_lut(Command::LutWW, data, 42)
     }
 
     /// Store Black-to-White (KW mode) / Red (KWR mode) Look-Up Table.
     /// See also [`write_data1`](EpdDriver::set_lutc).
-    pub fn set_lutkw_lutr(&mut self, data: &[u8]) -> Result<(), SPI::Error> {
+    pub fn set_lutkw_lutr(&mut self, data: &[u8]) -> Result<(), Error<SPI::Error>> {
         self.set_lut(Command::LutKW_LutR, data, 60)
     }
 
     /// Store White-to-Black (KW mode) / White (KWR mode) Look-Up Table.
     /// See also [`write_data1`](EpdDriver::set_lutc).
-    pub fn set_lutwk_lutw(&mut self, data: &[u8]) -> Result<(), SPI::Error> {
+    pub fn set_lutwk_lutw(&mut self, data: &[u8]) -> Result<(), Error<SPI::Error>> {
         self.set_lut(Command::LutWK_LutW, data, 60)
     }
 
     /// Store Black-to-Black (KW mode) / Black (KWR mode) Look-Up Table.
     /// See also [`write_data1`](EpdDriver::set_lutc).
-    pub fn set_lutkk_lutk(&mut self, data: &[u8]) -> Result<(), SPI::Error> {
+    pub fn set_lutkk_lutk(&mut self, data: &[u8]) -> Result<(), Error<SPI::Error>> {
         self.set_lut(Command::LutKK_LutK, data, 60)
     }
 
     /// Store Border Look-Up Table.
     /// See also [`write_data1`](EpdDriver::set_lutc).
-    pub fn set_lutbd(&mut self, data: &[u8]) -> Result<(), SPI::Error> {
+    pub fn set_lutbd(&mut self, data: &[u8]) -> Result<(), Error<SPI::Error>> {
         self.set_lut(Command::LutBD, data, 42)
     }
 
-    fn set_lut(&mut self, cmd: Command, data: &[u8], reqd_len: usize) -> Result<(), SPI::Error> {
+    fn set_lut(
+        &mut self,
+        cmd: Command,
+        data: &[u8],
+        reqd_len: usize,
+    ) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(CS_ALL, cmd, data)?;
         if data.len() < reqd_len {
             let zeroes = [0; 60];
@@ -334,17 +367,17 @@ where
     }
 
     /// Refresh the entire display.
-    pub fn refresh_display(&mut self) -> Result<(), SPI::Error> {
+    pub fn refresh_display(&mut self) -> Result<(), Error<SPI::Error>> {
         self.begin_refresh_display()?;
-        drop(self.wait_ready(CS_ALL));
+        self.wait_ready(CS_ALL)?;
         Ok(())
     }
 
     /// Asynchronous version of [`refresh_display`](EpdDriver::refresh_display).
     /// Use [`is_busy`](EpdDriver::is_busy) to poll for completion.
-    pub fn begin_refresh_display(&mut self) -> Result<(), SPI::Error> {
+    pub fn begin_refresh_display(&mut self) -> Result<(), Error<SPI::Error>> {
         self.cmd(CS_ALL, Command::PowerOn)?;
-        drop(self.wait_ready(CS_ALL));
+        self.wait_ready(CS_ALL)?;
         // Appears to be required to reliably trigger display refresh after a power-on.
         self.delay.delay_ms(100);
 
@@ -356,20 +389,20 @@ where
     /// Refresh the specified sub-window of the display.  
     ///
     /// Technically, this works, however, after 2+ partial updates, the rest of the displayed image becomes visibly degraded.
-    pub fn refresh_display_partial(&mut self, window: Rect) -> Result<(), SPI::Error> {
+    pub fn refresh_display_partial(&mut self, window: Rect) -> Result<(), Error<SPI::Error>> {
         self.begin_refresh_display_partial(window)?;
 
-        drop(self.wait_ready(CS_ALL));
+        self.wait_ready(CS_ALL)?;
         Ok(())
     }
 
     /// Asynchronous version of [`refresh_display_partial`](EpdDriver::refresh_display_partial).
     /// Use [`is_busy`](EpdDriver::is_busy) to poll for completion.
-    pub fn begin_refresh_display_partial(&mut self, window: Rect) -> Result<(), SPI::Error> {
+    pub fn begin_refresh_display_partial(&mut self, window: Rect) -> Result<(), Error<SPI::Error>> {
         self.setup_partial_windows(window)?;
 
         self.cmd(CS_ALL, Command::PowerOn)?;
-        drop(self.wait_ready(CS_ALL));
+        self.wait_ready(CS_ALL)?;
         self.delay.delay_ms(100);
 
         self.cmd(CS_ALL, Command::PartialIn)?;
@@ -381,55 +414,30 @@ where
 
     /// Turn off booster, controller, source driver, gate driver, VCOM, and temperature sensor.
     /// However, the contents of the data memory buffers will be retained.
-    pub fn power_off(&mut self) -> Result<(), SPI::Error> {
+    pub fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
         self.cmd(CS_ALL, Command::PowerOff)?;
-        drop(self.wait_ready(CS_ALL));
+        self.wait_ready(CS_ALL)?;
 
         self.flush()
     }
 
     /// Put display into deep sleep.  Only [`reset()`](EpdDriver::reset) can bring it out of this state.
     /// The contents of the data memory buffers will be lost.
-    pub fn hibernate(&mut self) -> Result<(), SPI::Error> {
+    pub fn hibernate(&mut self) -> Result<(), Error<SPI::Error>> {
         self.cmd(CS_ALL, Command::PowerOff)?;
-        drop(self.wait_ready(CS_ALL));
+        self.wait_ready(CS_ALL)?;
 
         self.cmd_with_data(CS_ALL, Command::DeepSleep, &[0xA5])?;
 
         self.flush()
     }
 
-    fn setup_partial_windows(&mut self, window: Rect) -> Result<(), SPI::Error> {
+    fn setup_partial_windows(&mut self, window: Rect) -> Result<(), Error<SPI::Error>> {
         let s2_part = window.intersect(S2_RECT).sub_offset(S2_RECT.x, S2_RECT.y);
         let m2_part = window.intersect(M2_RECT).sub_offset(M2_RECT.x, M2_RECT.y);
         let m1_part = window.intersect(M1_RECT).sub_offset(M1_RECT.x, M1_RECT.y);
         let s1_part = window.intersect(S1_RECT).sub_offset(S1_RECT.x, S1_RECT.y);
 
-        fn partial_window_data(window: Rect, reverse_scan: Option<u32>) -> [u8; 9] {
-            if window.is_empty() {
-                [0x00, 0x00, 0xFF, 0xFF, 0x00, 0x00, 0xFF, 0xFF, 0x01]
-            } else {
-                let start_x = match reverse_scan {
-                    Some(width) => width - window.x - window.w,
-                    None => window.x,
-                };
-                let end_x = start_x + window.w - 1;
-                let start_y = window.y;
-                let end_y = start_y + window.h - 1;
-                [
-                    (start_x / 256) as u8,
-                    (start_x % 256) as u8,
-                    (end_x / 256) as u8,
-                    (end_x % 256) as u8,
-                    (start_y / 256) as u8,
-                    (start_y % 256) as u8,
-                    (end_y / 256) as u8,
-                    (end_y % 256) as u8,
-                    0x01,
-                ]
-            }
-        }
-
         self.cmd_with_data(
             CS_S2,
             Command::PartialWindow,
@@ -459,9 +467,20 @@ where
         transmission_cmd: Command,
         window: Rect,
         pixels: &[u8],
-    ) -> Result<(), SPI::Error> {
-        if window.x % 8 != 0 || window.w % 8 != 0 {
-            panic!("Window is not 8-aligned horizontally");
+    ) -> Result<(), Error<SPI::Error>> {
+        let fits =
+            |start: u32, len: u32, max: u32| start.checked_add(len).map_or(false, |end| end <= max);
+        if window.x % 8 != 0
+            || window.w % 8 != 0
+            || !fits(window.x, window.w, WIDTH)
+            || !fits(window.y, window.h, HEIGHT)
+        {
+            return Err(Error::OutOfBounds {
+                x: window.x,
+                y: window.y,
+                width: window.w,
+                height: window.h,
+            });
         }
 
         self.cmd(CS_ALL, Command::PartialIn)?;
@@ -478,43 +497,34 @@ where
         transmission_cmd: Command,
         window: Rect,
         pixels: &[u8],
-    ) -> Result<(), SPI::Error> {
-        assert!(!pixels.is_empty());
-
-        let s2_part = window.intersect(S2_RECT);
-        let s1_part = window.intersect(S1_RECT);
-
-        let top_rows = s2_part.h as usize;
-        let bottom_rows = s1_part.h as usize;
-        let left_bytes = (s2_part.w / 8) as usize;
-        let right_bytes = (s1_part.w / 8) as usize;
-
-        let row_offset = |row| {
-            let offset = row * (left_bytes + right_bytes);
-            if offset < pixels.len() {
-                offset
-            } else {
-                // Wrap around
-                offset % pixels.len()
-            }
-        };
+    ) -> Result<(), Error<SPI::Error>> {
+        let split = WindowSplit::new(window);
+        // whole rows, repeated if there are fewer than in the window
+        let row_bytes = split.left_bytes + split.right_bytes;
+        if row_bytes > 0 && (pixels.is_empty() || pixels.len() % row_bytes != 0) {
+            return Err(Error::InvalidBufferLength {
+                expected: row_bytes * window.h as usize,
+                got: pixels.len(),
+            });
+        }
+        let top_rows = split.top_rows;
+        let bottom_rows = split.bottom_rows;
+        let left_bytes = split.left_bytes;
+        let right_bytes = split.right_bytes;
+        let row_range = |row, right| split.row_range(pixels.len(), row, right);
 
         if top_rows > 0 {
             if left_bytes > 0 {
                 self.cmd(CS_S2, transmission_cmd)?;
                 for y in 0..top_rows {
-                    let begin = row_offset(y);
-                    let end = begin + left_bytes;
-                    self.spi_write(CS_S2 | CS_DATA, &pixels[begin..end])?;
+                    self.spi_write(CS_S2 | CS_DATA, &pixels[row_range(y, false)])?;
                 }
             }
 
             if right_bytes > 0 {
                 self.cmd(CS_M2, transmission_cmd)?;
                 for y in 0..top_rows {
-                    let begin = row_offset(y) + left_bytes;
-                    let end = begin + right_bytes;
-                    self.spi_write(CS_M2 | CS_DATA, &pixels[begin..end])?;
+                    self.spi_write(CS_M2 | CS_DATA, &pixels[row_range(y, true)])?;
                 }
             }
         }
@@ -523,18 +533,16 @@ where
             if left_bytes > 0 {
                 self.cmd(CS_M1, transmission_cmd)?;
                 for y in 0..bottom_rows {
-                    let begin = row_offset(top_rows + y);
-                    let end = begin + left_bytes;
-                    self.spi_write(CS_M1 | CS_DATA, &pixels[begin..end])?;
+                    let range = row_range(top_rows + y, false);
+                    self.spi_write(CS_M1 | CS_DATA, &pixels[range])?;
                 }
             }
 
             if right_bytes > 0 {
                 self.cmd(CS_S1, transmission_cmd)?;
                 for y in 0..bottom_rows {
-                    let begin = row_offset(top_rows + y) + left_bytes;
-                    let end = begin + right_bytes;
-                    self.spi_write(CS_S1 | CS_DATA, &pixels[begin..end])?;
+                    let range = row_range(top_rows + y, true);
+                    self.spi_write(CS_S1 | CS_DATA, &pixels[range])?;
                 }
             }
         }
@@ -542,7 +550,7 @@ where
         Ok(())
     }
 
-    fn cmd(&mut self, chips: CS, command: Command) -> Result<(), SPI::Error> {
+    fn cmd(&mut self, chips: CS, command: Command) -> Result<(), Error<SPI::Error>> {
         self.spi_write(chips, &[command as u8])
     }
 
@@ -551,13 +559,13 @@ where
         chips: CS,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.spi_write(chips, &[command as u8])?;
         self.spi_write(chips | CS_DATA, data)
     }
 
     // Set control pins to the specified state, then send data via SPI.
-    fn spi_write(&mut self, control: CS, data: &[u8]) -> Result<(), SPI::Error> {
+    fn spi_write(&mut self, control: CS, data: &[u8]) -> Result<(), Error<SPI::Error>> {
         if self.control_state != control {
             fn pin_state(high: bool) -> PinState {
                 if high {
@@ -585,11 +593,12 @@ where
             self.control_state = control;
         }
 
-        self.peris.spi.write(data)
+        self.peris.spi.write(data)?;
+        Ok(())
     }
 
     // Flush SPI, reset control pins to the default state.
-    fn flush(&mut self) -> Result<(), SPI::Error> {
+    fn flush(&mut self) -> Result<(), Error<SPI::Error>> {
         self.peris.spi.flush()?;
         drop(self.peris.m1_cs.set_high());
         drop(self.peris.s1_cs.set_high());
@@ -601,43 +610,51 @@ where
         Ok(())
     }
 
-    fn wait_ready(&mut self, chips: CS) -> Result<(), INPUT::Error> {
+    fn wait_ready(&mut self, chips: CS) -> Result<(), Error<SPI::Error>> {
+        let mut waited = 0u32;
         while self.busy_chips(chips)? != 0 {
+            if self
+                .busy_timeout_us
+                .map_or(false, |timeout| waited >= timeout)
+            {
+                return Err(Error::BusyTimeout);
+            }
             self.delay.delay_ms(200);
+            waited = waited.saturating_add(200_000);
         }
         Ok(())
     }
 
-    fn busy_chips(&mut self, chips: CS) -> Result<CS, INPUT::Error> {
+    fn busy_chips(&mut self, chips: CS) -> Result<CS, Error<SPI::Error>> {
         let mut busy = 0;
-        if chips & CS_M1 != 0 && self.peris.m1_busy.is_low()? {
+        if chips & CS_M1 != 0 && self.peris.m1_busy.is_low().map_err(Error::pin)? {
             busy |= CS_M1;
         }
-        if chips & CS_S1 != 0 && self.peris.s1_busy.is_low()? {
+        if chips & CS_S1 != 0 && self.peris.s1_busy.is_low().map_err(Error::pin)? {
             busy |= CS_S1;
         }
-        if chips & CS_M2 != 0 && self.peris.m2_busy.is_low()? {
+        if chips & CS_M2 != 0 && self.peris.m2_busy.is_low().map_err(Error::pin)? {
             busy |= CS_M2;
         }
-        if chips & CS_S2 != 0 && self.peris.s2_busy.is_low()? {
+        if chips & CS_S2 != 0 && self.peris.s2_busy.is_low().map_err(Error::pin)? {
             busy |= CS_S2;
         }
         Ok(busy)
     }
 
     /// Poll readiness status of all sub-displays and return a bit mask of the busy ones.
-    pub fn get_busy(&mut self) -> u8 {
-        self.busy_chips(CS_ALL).unwrap()
+    pub fn get_busy(&mut self) -> Result<u8, Error<SPI::Error>> {
+        self.busy_chips(CS_ALL)
     }
 
     /// Check if any of the sub-displays is busy.
-    pub fn is_busy(&mut self) -> bool {
-        self.busy_chips(CS_ALL).unwrap() != 0
+    pub fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        Ok(self.busy_chips(CS_ALL)? != 0)
     }
 
     /// Query and return the status byte of each sub-display.
     /// Order: \[M1, S1, M2, S2\].
-    pub fn get_status(&mut self) -> Result<[u8; 4], SPI::Error> {
+    pub fn get_status(&mut self) -> Result<[u8; 4], Error<SPI::Error>> {
         self.control_state = 0xFF;
         let mut status = [0u8; 4];
         for i in 0..4 {
@@ -669,3 +686,254 @@ where
         Ok(status)
     }
 }
+
+/// Partial window register data of a sub-display, `reverse_scan` is the width of a sub-display
+/// whose sources are scanned from right to left
+fn partial_window_data(window: Rect, reverse_scan: Option<u32>) -> [u8; 9] {
+    if window.is_empty() {
+        [0x00, 0x00, 0xFF, 0xFF, 0x00, 0x00, 0xFF, 0xFF, 0x01]
+    } else {
+        let start_x = match reverse_scan {
+            Some(width) => width - window.x - window.w,
+            None => window.x,
+        };
+        let end_x = start_x + window.w - 1;
+        let start_y = window.y;
+        let end_y = start_y + window.h - 1;
+        [
+            (start_x / 256) as u8,
+            (start_x % 256) as u8,
+            (end_x / 256) as u8,
+            (end_x % 256) as u8,
+            (start_y / 256) as u8,
+            (start_y % 256) as u8,
+            (end_y / 256) as u8,
+            (end_y % 256) as u8,
+            0x01,
+        ]
+    }
+}
+
+/// How the bytes of a window are split over the four sub-displays
+///
+/// Each row of the window starts with the `left_bytes` of `S2` (top) or `M1` (bottom), followed
+/// by the `right_bytes` of `M2` (top) or `S1` (bottom).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct WindowSplit {
+    top_rows: usize,
+    bottom_rows: usize,
+    left_bytes: usize,
+    right_bytes: usize,
+}
+
+impl WindowSplit {
+    fn new(window: Rect) -> Self {
+        let s2_part = window.intersect(S2_RECT);
+        let s1_part = window.intersect(S1_RECT);
+        WindowSplit {
+            top_rows: s2_part.h as usize,
+            bottom_rows: s1_part.h as usize,
+            left_bytes: (s2_part.w / 8) as usize,
+            right_bytes: (s1_part.w / 8) as usize,
+        }
+    }
+
+    /// Bytes of `row` (counted from the top of the window) for the left or right sub-display
+    ///
+    /// A buffer of `len` bytes with less rows than the window is repeated.
+    fn row_range(&self, len: usize, row: usize, right: bool) -> Range<usize> {
+        let mut offset = row * (self.left_bytes + self.right_bytes);
+        if offset >= len {
+            // Wrap around
+            offset %= len;
+        }
+        if right {
+            offset + self.left_bytes..offset + self.left_bytes + self.right_bytes
+        } else {
+            offset..offset + self.left_bytes
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+    use embedded_hal::digital::ErrorKind;
+    use embedded_hal_mock::eh1::{
+        delay::NoopDelay,
+        digital::{Mock as PinMock, State, Transaction as PinTransaction},
+        spi::Mock as SpiMock,
+        MockError,
+    };
+    use std::vec::Vec;
+
+    /// Runs `f` on a driver whose M1 BUSY line gives `m1_busy`, the other lines stay idle
+    fn with_busy_lines(
+        m1_busy: &[PinTransaction],
+        rounds: usize,
+        f: impl FnOnce(&mut EpdDriver<PinMock, PinMock, SpiMock<u8>, NoopDelay>),
+    ) {
+        let idle = std::vec![PinTransaction::get(State::High); rounds];
+        let busy_pins = [
+            PinMock::new(m1_busy),
+            PinMock::new(&idle),
+            PinMock::new(&idle),
+            PinMock::new(&idle),
+        ];
+        let output = PinMock::new(&[]);
+        let spi = SpiMock::new(&[]);
+        let mut epd = EpdDriver::new(
+            Peripherals {
+                spi: spi.clone(),
+                m1_cs: output.clone(),
+                s1_cs: output.clone(),
+                m2_cs: output.clone(),
+                s2_cs: output.clone(),
+                m1s1_dc: output.clone(),
+                m2s2_dc: output.clone(),
+                m1s1_rst: output.clone(),
+                m2s2_rst: output.clone(),
+                m1_busy: busy_pins[0].clone(),
+                s1_busy: busy_pins[1].clone(),
+                m2_busy: busy_pins[2].clone(),
+                s2_busy: busy_pins[3].clone(),
+            },
+            NoopDelay::new(),
+        );
+        f(&mut epd);
+        drop(epd);
+
+        spi.clone().done();
+        output.clone().done();
+        for mut pin in busy_pins {
+            pin.done();
+        }
+    }
+
+    #[test]
+    fn busy_line_errors_are_returned() {
+        let m1_busy = [
+            PinTransaction::get(State::Low),
+            PinTransaction::get(State::High).with_error(MockError::Io(std::io::ErrorKind::Other)),
+        ];
+        with_busy_lines(&m1_busy, 1, |epd| {
+            assert_eq!(epd.is_busy(), Ok(true));
+            assert_eq!(epd.get_busy(), Err(Error::Pin(ErrorKind::Other)));
+        });
+    }
+
+    #[test]
+    fn waiting_times_out() {
+        let m1_busy = std::vec![PinTransaction::get(State::Low); 3];
+        with_busy_lines(&m1_busy, 3, |epd| {
+            // two sleeps of 200ms
+            epd.set_busy_timeout(Some(400_000));
+            assert_eq!(epd.wait_ready(CS_ALL), Err(Error::BusyTimeout));
+        });
+    }
+
+    #[test]
+    fn bad_windows_are_rejected() {
+        with_busy_lines(&[], 0, |epd| {
+            assert_eq!(
+                epd.write_data1_partial(Rect::new(4, 0, 8, 1), &[0]),
+                Err(Error::OutOfBounds {
+                    x: 4,
+                    y: 0,
+                    width: 8,
+                    height: 1
+                })
+            );
+            assert!(matches!(
+                epd.write_data1_partial(Rect::new(0, HEIGHT - 1, 8, 2), &[0]),
+                Err(Error::OutOfBounds { .. })
+            ));
+            assert_eq!(
+                epd.write_data1(&[0; 100]),
+                Err(Error::InvalidBufferLength {
+                    expected: (WIDTH / 8 * HEIGHT) as usize,
+                    got: 100
+                })
+            );
+        });
+    }
+
+    #[test]
+    fn split_full_frame() {
+        let split = WindowSplit::new(FULL_RECT);
+        assert_eq!(
+            split,
+            WindowSplit {
+                top_rows: 492,
+                bottom_rows: 492,
+                left_bytes: 81,
+                right_bytes: 82,
+            }
+        );
+        let len = (WIDTH / 8 * HEIGHT) as usize;
+        assert_eq!(split.row_range(len, 0, false), 0..81);
+        assert_eq!(split.row_range(len, 0, true), 81..163);
+        assert_eq!(split.row_range(len, 492, false), 492 * 163..492 * 163 + 81);
+    }
+
+    #[test]
+    fn split_window_over_all_quadrants() {
+        // 2 bytes wide and 4 rows high, centered on the corner of all four sub-displays
+        let split = WindowSplit::new(Rect::new(S2_WIDTH - 8, S2_HEIGHT - 2, 16, 4));
+        assert_eq!(
+            split,
+            WindowSplit {
+                top_rows: 2,
+                bottom_rows: 2,
+                left_bytes: 1,
+                right_bytes: 1,
+            }
+        );
+        let pixels = [0, 1, 2, 3, 4, 5, 6, 7];
+        let s2: Vec<u8> = (0..2)
+            .flat_map(|row| pixels[split.row_range(8, row, false)].to_vec())
+            .collect();
+        let m2: Vec<u8> = (0..2)
+            .flat_map(|row| pixels[split.row_range(8, row, true)].to_vec())
+            .collect();
+        let m1: Vec<u8> = (2..4)
+            .flat_map(|row| pixels[split.row_range(8, row, false)].to_vec())
+            .collect();
+        let s1: Vec<u8> = (2..4)
+            .flat_map(|row| pixels[split.row_range(8, row, true)].to_vec())
+            .collect();
+        assert_eq!(s2, [0, 2]);
+        assert_eq!(m2, [1, 3]);
+        assert_eq!(m1, [4, 6]);
+        assert_eq!(s1, [5, 7]);
+    }
+
+    #[test]
+    fn split_repeats_short_buffer() {
+        let split = WindowSplit::new(Rect::new(0, 0, 16, 4));
+        assert_eq!(split.right_bytes, 0);
+        // a single row of 2 bytes is sent for every row of the window
+        for row in 0..4 {
+            assert_eq!(split.row_range(2, row, false), 0..2);
+        }
+    }
+
+    #[test]
+    fn mirrored_partial_window() {
+        // S2 and M2 are scanned from right to left
+        let window = Rect::new(8, 2, 16, 3);
+        assert_eq!(
+            partial_window_data(window, Some(S2_WIDTH)),
+            [0x02, 0x70, 0x02, 0x7F, 0x00, 0x02, 0x00, 0x04, 0x01]
+        );
+        assert_eq!(
+            partial_window_data(window, None),
+            [0x00, 0x08, 0x00, 0x17, 0x00, 0x02, 0x00, 0x04, 0x01]
+        );
+        assert_eq!(
+            partial_window_data(Rect::default(), None),
+            [0x00, 0x00, 0xFF, 0xFF, 0x00, 0x00, 0xFF, 0xFF, 0x01]
+        );
+    }
+}
@@ -0,0 +1,401 @@
+//! A simple Driver for the Waveshare 13.3" (K) E-Ink Display via SPI
+//!
+//! The display controller IC is the SSD1677. Unlike the smaller SSD16xx controllers its RAM
+//! x and y address counters are 16 bit wide and the x address is given in pixels.
+//!
+//! The full frame buffer takes [`BUFFER_LEN`] (81 600) bytes, which is too much for many
+//! microcontrollers. [`update_partial_frame`](WaveshareDisplay::update_partial_frame) can be used
+//! to send the frame in bands, e.g. drawn into a small [`VarDisplay`](crate::graphics::VarDisplay),
+//! before calling [`display_frame`](WaveshareDisplay::display_frame) once.
+//!
+//! # References
+//!
+//! - [Waveshare Wiki](https://www.waveshare.com/wiki/13.3inch_e-Paper_HAT_(K))
+//! - [Waveshare C driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_13in3k.c)
+
+use crate::error::Error;
+use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
+
+use crate::color::Color;
+use crate::interface::DisplayInterface;
+use crate::traits::{InternalWiAdditions, WaveshareDisplay};
+use crate::type_a::command::Command;
+use crate::{buffer_len, check_buffer_len, check_partial_window};
+
+/// Width of the display
+pub const WIDTH: u32 = 960;
+/// Height of the display
+pub const HEIGHT: u32 = 680;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+/// Number of bytes of a full frame, to allocate your own buffer
+pub const BUFFER_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize);
+const IS_BUSY_LOW: bool = false;
+const SINGLE_BYTE_WRITE: bool = false;
+
+/// Full size buffer for use with the 13in3k EPD
+///
+/// It takes [`BUFFER_LEN`] bytes of RAM.
+#[cfg(feature = "graphics")]
+pub type Display13in3k = crate::graphics::Display<WIDTH, HEIGHT, false, BUFFER_LEN, Color>;
+
+/// Epd13in3k driver
+pub struct Epd13in3k<SPI, BUSY, DC, RST, DELAY, PWR> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    background_color: Color,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd13in3k<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, 10_000, 2_000)?;
+
+        self.wait_until_idle(spi, delay)?;
+        self.interface.cmd(spi, Command::SwReset)?;
+        self.wait_until_idle(spi, delay)?;
+
+        self.interface.cmd_with_data(
+            spi,
+            Command::BoosterSoftStartControl,
+            &[0xAE, 0xC7, 0xC3, 0xC0, 0x80],
+        )?;
+
+        // 680 gate lines, interlaced scan order
+        self.interface.cmd_with_data(
+            spi,
+            Command::DriverOutputControl,
+            &[(HEIGHT - 1) as u8, ((HEIGHT - 1) >> 8) as u8, 0x02],
+        )?;
+
+        self.interface
+            .cmd_with_data(spi, Command::BorderWaveformControl, &[0x01])?;
+
+        // x increment, y increment, address counter is updated in x direction
+        self.interface
+            .cmd_with_data(spi, Command::DataEntryModeSetting, &[0x03])?;
+
+        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
+
+        // internal temperature sensor
+        self.interface
+            .cmd_with_data(spi, Command::TemperatureSensorSelection, &[0x80])?;
+
+        // load the temperature and the waveform
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xB1])?;
+        self.interface.cmd(spi, Command::MasterActivation)?;
+        self.wait_until_idle(spi, delay)?;
+
+        self.set_ram_counter(spi, 0, 0)?;
+        self.wait_until_idle(spi, delay)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd13in3k<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    type DisplayColor = Color;
+
+    fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(busy, dc, rst, pwr, delay_us);
+
+        let mut epd = Epd13in3k {
+            interface,
+            background_color: DEFAULT_BACKGROUND_COLOR,
+        };
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        // deep sleep mode 2, the RAM content is not retained
+        self.interface
+            .cmd_with_data(spi, Command::DeepSleepMode, &[0x03])?;
+        self.interface.power_off()
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.init(spi, delay)
+    }
+
+    fn set_background_color(&mut self, background_color: Color) {
+        self.background_color = background_color;
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.background_color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer.len(), BUFFER_LEN)?;
+        self.wait_until_idle(spi, delay)?;
+        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
+        self.set_ram_counter(spi, 0, 0)?;
+        self.interface.cmd_with_data(spi, Command::WriteRam, buffer)
+    }
+
+    /// Writes a window of the frame to the RAM, without refreshing the display
+    ///
+    /// `x` and `width` must be multiples of 8.
+    fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_partial_window(buffer.len(), x, y, width, height, WIDTH, HEIGHT)?;
+
+        self.wait_until_idle(spi, delay)?;
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)?;
+        self.set_ram_counter(spi, x, y)?;
+        self.interface.cmd_with_data(spi, Command::WriteRam, buffer)
+    }
+
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xF7])?;
+        self.interface.cmd(spi, Command::MasterActivation)?;
+        self.wait_until_idle(spi, delay)
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
+        self.set_ram_counter(spi, 0, 0)?;
+
+        let color = self.background_color.get_byte_value();
+        self.interface.cmd(spi, Command::WriteRam)?;
+        self.interface.data_x_times(spi, color, BUFFER_LEN as u32)
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd13in3k<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Writes a window of the frame to the RAM like
+    /// [`update_partial_frame`](WaveshareDisplay::update_partial_frame), with the data taken
+    /// from an iterator
+    ///
+    /// Exactly `width / 8 * height` bytes are taken, it fails with
+    /// `Error::InvalidBufferLength` if `data` ends early and leaves any further items unconsumed.
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_partial_frame_iter<I: IntoIterator<Item = u8>>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        data: I,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        let len = buffer_len(width as usize, height as usize);
+        check_partial_window(len, x, y, width, height, WIDTH, HEIGHT)?;
+
+        self.wait_until_idle(spi, delay)?;
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)?;
+        self.set_ram_counter(spi, x, y)?;
+
+        self.interface.cmd(spi, Command::WriteRam)?;
+        self.interface.data_iter(spi, data, len)
+    }
+
+    fn set_ram_area(
+        &mut self,
+        spi: &mut SPI,
+        start_x: u32,
+        start_y: u32,
+        end_x: u32,
+        end_y: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamXAddressStartEndPosition,
+            &address_range(start_x, end_x),
+        )?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamYAddressStartEndPosition,
+            &address_range(start_y, end_y),
+        )
+    }
+
+    fn set_ram_counter(&mut self, spi: &mut SPI, x: u32, y: u32) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .cmd_with_data(spi, Command::SetRamXAddressCounter, &address(x))?;
+        self.interface
+            .cmd_with_data(spi, Command::SetRamYAddressCounter, &address(y))
+    }
+}
+
+/// 16 bit address, low byte first
+fn address(value: u32) -> [u8; 2] {
+    [value as u8, (value >> 8) as u8]
+}
+
+fn address_range(start: u32, end: u32) -> [u8; 4] {
+    let [start_low, start_high] = address(start);
+    let [end_low, end_high] = address(end);
+    [start_low, start_high, end_low, end_high]
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+    use crate::NoPin;
+    use embedded_hal_mock::eh1::{
+        delay::NoopDelay,
+        digital::{Mock as PinMock, State, Transaction as PinTransaction},
+        spi::{Mock as SpiMock, Transaction as SpiTransaction},
+    };
+    use std::{vec, vec::Vec};
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 960);
+        assert_eq!(HEIGHT, 680);
+        assert_eq!(BUFFER_LEN, 81_600);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+
+    #[test]
+    fn ram_addresses() {
+        assert_eq!(address(0), [0x00, 0x00]);
+        assert_eq!(address(WIDTH - 1), [0xBF, 0x03]);
+        assert_eq!(address_range(0, HEIGHT - 1), [0x00, 0x00, 0xA7, 0x02]);
+    }
+
+    #[test]
+    fn window_from_an_iterator_is_sent_in_chunks() {
+        let mut spi_expectations = Vec::new();
+        let mut dc_expectations = Vec::new();
+        let mut write = |data: Vec<u8>, state| {
+            dc_expectations.push(PinTransaction::set(state));
+            spi_expectations.push(SpiTransaction::transaction_start());
+            spi_expectations.push(SpiTransaction::write_vec(data));
+            spi_expectations.push(SpiTransaction::transaction_end());
+        };
+        // a row of 320 pixels, 40 bytes
+        for (command, data) in [
+            (0x44, vec![0x00, 0x00, 0x3F, 0x01]),
+            (0x45, vec![0x02, 0x00, 0x02, 0x00]),
+            (0x4E, vec![0x00, 0x00]),
+            (0x4F, vec![0x02, 0x00]),
+        ] {
+            write(vec![command], State::Low);
+            write(data, State::High);
+        }
+        write(vec![0x24], State::Low);
+        write((0..32).collect(), State::High);
+        spi_expectations.push(SpiTransaction::transaction_start());
+        spi_expectations.push(SpiTransaction::write_vec((32..40).collect()));
+        spi_expectations.push(SpiTransaction::transaction_end());
+
+        let mut spi = SpiMock::new(&spi_expectations);
+        let mut busy = PinMock::new(&[PinTransaction::get(State::Low)]);
+        let mut dc = PinMock::new(&dc_expectations);
+        let mut rst = PinMock::new(&[]);
+
+        let mut epd = Epd13in3k {
+            interface: DisplayInterface::new(
+                busy.clone(),
+                dc.clone(),
+                Some(rst.clone()),
+                None::<NoPin>,
+                None,
+            ),
+            background_color: DEFAULT_BACKGROUND_COLOR,
+        };
+        epd.update_partial_frame_iter(&mut spi, &mut NoopDelay::new(), 0.., 0, 2, 320, 1)
+            .unwrap();
+        drop(epd);
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
+}
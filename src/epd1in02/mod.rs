@@ -4,6 +4,7 @@
 //!
 //! The display controller IC is UC8175
 
+use crate::error::Error;
 use embedded_hal::{
     delay::DelayNs,
     digital::{InputPin, OutputPin},
@@ -13,11 +14,14 @@ use embedded_hal::{
 use crate::color::Color;
 use crate::interface::DisplayInterface;
 use crate::prelude::WaveshareDisplay;
-use crate::traits::{InternalWiAdditions, QuickRefresh, RefreshLut};
+use crate::traits::{
+    check_refresh_lut, uc_border, uc_vcm_dc, BorderColor, InternalWiAdditions, QuickRefresh,
+    RefreshLut,
+};
 
 pub(crate) mod command;
 use self::command::Command;
-use crate::buffer_len;
+use crate::{buffer_len, check_buffer_len, check_partial_window};
 
 pub(crate) mod constants;
 use self::constants::{
@@ -43,41 +47,57 @@ pub const HEIGHT: u32 = 128;
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 /// BUSY is low active
 const IS_BUSY_LOW: bool = true;
+/// -1.0 V
+const DEFAULT_VCOM: u8 = 0x12;
 /// Number of bytes to contain values of all display pixels
 const NUMBER_OF_BYTES: u32 = WIDTH * HEIGHT / 8;
 const SINGLE_BYTE_WRITE: bool = true;
 
 /// Epd1in02 driver
 ///
-pub struct Epd1in02<SPI, BUSY, DC, RST, DELAY> {
+pub struct Epd1in02<SPI, BUSY, DC, RST, DELAY, PWR> {
     /// Connection Interface
-    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
     /// Background Color
     color: Color,
     is_turned_on: bool,
     refresh_mode: RefreshLut,
+    /// LUT used outside of the partial mode, chosen with `set_lut`
+    refresh: RefreshLut,
+    /// VCOM register value, see [`set_vcom`](Self::set_vcom)
+    vcom: u8,
+    /// VCOM and data interval setting of [`set_border`](WaveshareDisplay::set_border), follows
+    /// the background color if `None`
+    border: Option<u8>,
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
-    for Epd1in02<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd1in02<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     type DisplayColor = Color;
+    const REFRESH_LUTS: &'static [RefreshLut] = &[
+        RefreshLut::Full,
+        RefreshLut::Quick,
+        RefreshLut::PartialRefresh,
+    ];
 
     fn new(
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(busy, dc, rst, pwr, delay_us);
         let color = DEFAULT_BACKGROUND_COLOR;
 
         let mut epd = Epd1in02 {
@@ -85,6 +105,9 @@ where
             color,
             is_turned_on: false,
             refresh_mode: RefreshLut::Full,
+            refresh: RefreshLut::Full,
+            vcom: DEFAULT_VCOM,
+            border: None,
         };
 
         epd.init(spi, delay)?;
@@ -92,7 +115,7 @@ where
         Ok(epd)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.turn_off(spi, delay)?;
         self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
@@ -100,10 +123,10 @@ where
         // display registers are set to default value
         self.refresh_mode = RefreshLut::Full;
 
-        Ok(())
+        self.interface.power_off()
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.init(spi, delay)
     }
 
@@ -111,6 +134,29 @@ where
         self.color = color;
     }
 
+    fn set_vcom(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        millivolts: i16,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.vcom = uc_vcm_dc(millivolts)?;
+        self.wait_until_idle(spi, delay)?;
+        self.cmd_with_data(spi, Command::VcomDcSetting, &[self.vcom])
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
     fn background_color(&self) -> &Color {
         &self.color
     }
@@ -128,7 +174,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
 
         self.set_full_mode(spi, delay)?;
@@ -143,28 +189,44 @@ where
         Ok(())
     }
 
-    // Implemented as quick partial update
-    // as it requires old frame update
+    /// Updates only the given window with the full LUT, so no old frame is needed
+    ///
+    /// `x` and `width` must be multiples of 8
     fn update_partial_frame(
         &mut self,
-        _spi: &mut SPI,
-        _delay: &mut DELAY,
-        _buffer: &[u8],
-        _x: u32,
-        _y: u32,
-        _width: u32,
-        _height: u32,
-    ) -> Result<(), SPI::Error> {
-        unimplemented!()
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_partial_window(buffer.len(), x, y, width, height, WIDTH, HEIGHT)?;
+
+        self.wait_until_idle(spi, delay)?;
+        self.set_full_mode(spi, delay)?;
+        self.command(spi, Command::PartialIn)?;
+        self.set_partial_window(spi, delay, x, y, width, height)?;
+
+        let color_value = self.background_color().get_byte_value();
+
+        self.command(spi, Command::DataStartTransmission1)?;
+        self.interface
+            .data_x_times(spi, color_value, buffer.len() as u32)?;
+
+        self.cmd_with_data(spi, Command::DataStartTransmission2, buffer)?;
+
+        self.command(spi, Command::PartialOut)?;
+        Ok(())
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.turn_on_if_turned_off(spi, delay)?;
 
         self.command(spi, Command::DisplayRefresh)?;
-        self.wait_until_idle(spi, delay)?;
-        Ok(())
+        self.wait_until_idle(spi, delay)
     }
 
     fn update_and_display_frame(
@@ -172,13 +234,13 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.set_full_mode(spi, delay)?;
 
@@ -200,33 +262,37 @@ where
         spi: &mut SPI,
         _delay: &mut DELAY,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
-        let (white_lut, black_lut) = match refresh_rate {
-            Some(RefreshLut::Full) => (&LUT_FULL_UPDATE_WHITE, &LUT_FULL_UPDATE_BLACK),
-            Some(RefreshLut::Quick | RefreshLut::PartialRefresh) => {
-                (&LUT_PARTIAL_UPDATE_WHITE, &LUT_PARTIAL_UPDATE_BLACK)
-            }
-            None => return Ok(()),
-        };
+    ) -> Result<(), Error<SPI::Error>> {
+        check_refresh_lut(refresh_rate, Self::REFRESH_LUTS)?;
+        if let Some(refresh) = refresh_rate {
+            self.refresh = refresh;
+        }
+        self.load_lut(spi)
+    }
 
-        self.cmd_with_data(spi, Command::SetWhiteLut, white_lut)?;
-        self.cmd_with_data(spi, Command::SetBlackLut, black_lut)?;
-        Ok(())
+    fn set_border(&mut self, spi: &mut SPI, border: BorderColor) -> Result<(), Error<SPI::Error>> {
+        let value = uc_border(0x97, border)?;
+        self.border = Some(value);
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[value])
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
-        Ok(())
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> QuickRefresh<SPI, BUSY, DC, RST, DELAY>
-    for Epd1in02<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> QuickRefresh<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd1in02<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     /// To be followed immediately by update_new_frame
@@ -235,7 +301,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.set_partial_mode(spi, delay)?;
         self.set_partial_window(spi, delay, 0, 0, WIDTH, HEIGHT)?;
 
@@ -249,13 +315,17 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(spi, Command::DataStartTransmission2, buffer)?;
         Ok(())
     }
 
-    fn display_new_frame(&mut self, _spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        unimplemented!()
+    fn display_new_frame(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::Unsupported)
     }
 
     fn update_and_display_new_frame(
@@ -263,8 +333,8 @@ where
         _spi: &mut SPI,
         _buffer: &[u8],
         _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
-        unimplemented!()
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::Unsupported)
     }
 
     /// To be followed immediately by update_partial_new_frame
@@ -278,10 +348,8 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
-        if !is_buffer_size_ok(buffer, width, height) {
-            panic!("Image buffer size is not correct")
-        }
+    ) -> Result<(), Error<SPI::Error>> {
+        check_partial_window(buffer.len(), x, y, width, height, WIDTH, HEIGHT)?;
 
         self.set_partial_mode(spi, delay)?;
         self.set_partial_window(spi, delay, x, y, width, height)?;
@@ -300,10 +368,8 @@ where
         _y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
-        if !is_buffer_size_ok(buffer, width, height) {
-            panic!("Image buffer size is not correct")
-        }
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer.len(), buffer_len(width as usize, height as usize))?;
 
         self.cmd_with_data(spi, Command::DataStartTransmission2, buffer)?;
         Ok(())
@@ -318,7 +384,7 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         // set full LUT as quick LUT requires old image
         self.set_full_mode(spi, delay)?;
@@ -342,18 +408,21 @@ where
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
-    for Epd1in02<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd1in02<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         // Reset the device
-        self.interface.reset(delay, 20_000, 2000);
+        self.interface.reset(delay, 20_000, 2000)?;
+        self.interface
+            .uc_software_reset(spi, delay, Command::PanelSetting, 0x6F, IS_BUSY_LOW)?;
 
         // Set the panel settings: LUT from register
         self.cmd_with_data(spi, Command::PanelSetting, &[0x6F])?;
@@ -371,11 +440,11 @@ where
         self.cmd_with_data(spi, Command::PllControl, &[0x17])?;
 
         // Set Vcom and data interval: default
-        // set the border color the same as background color
-        let value = match self.background_color() {
+        // set the border color the same as background color, unless chosen with set_border
+        let value = self.border.unwrap_or(match self.background_color() {
             Color::Black => 0x57,
             Color::White => 0x97,
-        };
+        });
         self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[value])?;
 
         // Set the non-overlapping period of Gate and Source: 24us
@@ -385,31 +454,31 @@ where
         self.send_resolution(spi)?;
 
         // Set Vcom DC value: -1 V
-        self.cmd_with_data(spi, Command::VcomDcSetting, &[0x12])?;
+        self.cmd_with_data(spi, Command::VcomDcSetting, &[self.vcom])?;
 
         // Set pover saving settings
         self.cmd_with_data(spi, Command::PowerSaving, &[0x33])?;
 
-        self.set_lut(spi, delay, Some(self.refresh_mode))?;
+        self.load_lut(spi)?;
 
-        self.wait_until_idle(spi, delay)?;
-        Ok(())
+        self.wait_until_idle(spi, delay)
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> Epd1in02<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd1in02<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
         self.interface.data(spi, data)
     }
 
@@ -418,11 +487,11 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
-    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
         let w = self.width();
         let h = self.height();
 
@@ -436,7 +505,7 @@ where
         &mut self,
         spi: &mut SPI,
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         if !self.is_turned_on {
             self.command(spi, Command::PowerOn)?;
             self.wait_until_idle(spi, delay)?;
@@ -445,27 +514,52 @@ where
         Ok(())
     }
 
-    fn turn_off(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn turn_off(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.command(spi, Command::PowerOff)?;
         self.wait_until_idle(spi, delay)?;
         self.is_turned_on = false;
         Ok(())
     }
 
-    fn set_full_mode(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    /// Writes the LUT of the partial mode, or the one chosen with `set_lut` outside of it
+    fn load_lut(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        let lut = match self.refresh_mode {
+            RefreshLut::Full => self.refresh,
+            RefreshLut::Quick | RefreshLut::PartialRefresh => RefreshLut::Quick,
+        };
+        let (white_lut, black_lut) = match lut {
+            RefreshLut::Full => (&LUT_FULL_UPDATE_WHITE, &LUT_FULL_UPDATE_BLACK),
+            RefreshLut::Quick | RefreshLut::PartialRefresh => {
+                (&LUT_PARTIAL_UPDATE_WHITE, &LUT_PARTIAL_UPDATE_BLACK)
+            }
+        };
+
+        self.cmd_with_data(spi, Command::SetWhiteLut, white_lut)?;
+        self.cmd_with_data(spi, Command::SetBlackLut, black_lut)
+    }
+
+    fn set_full_mode(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
         if self.refresh_mode != RefreshLut::Full {
             self.command(spi, Command::PartialOut)?;
-            self.set_lut(spi, delay, Some(RefreshLut::Full))?;
             self.refresh_mode = RefreshLut::Full;
+            self.load_lut(spi)?;
         }
         Ok(())
     }
 
-    fn set_partial_mode(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn set_partial_mode(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
         if self.refresh_mode != RefreshLut::Quick {
             self.command(spi, Command::PartialIn)?;
-            self.set_lut(spi, delay, Some(RefreshLut::Quick))?;
             self.refresh_mode = RefreshLut::Quick;
+            self.load_lut(spi)?;
         }
         Ok(())
     }
@@ -478,10 +572,10 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
-        if !is_window_size_ok(x, y, width, height) {
-            panic!("Partial update window size is not correct")
-        }
+    ) -> Result<(), Error<SPI::Error>> {
+        // 3 less significant bits are ignored
+        let len = buffer_len(width as usize, height as usize);
+        check_partial_window(len, x, y, width, height, WIDTH, HEIGHT)?;
 
         self.cmd_with_data(
             spi,
@@ -499,20 +593,13 @@ where
     }
 }
 
-fn is_window_size_ok(x: u32, y: u32, width: u32, height: u32) -> bool {
-    // partial update window is inside the screen
-    x + width <= WIDTH && y + height <= HEIGHT
-    // 3 less significant bits are ignored
-    && x % 8 == 0 && width % 8 == 0
-}
-
-fn is_buffer_size_ok(buffer: &[u8], width: u32, height: u32) -> bool {
-    buffer_len(width as usize, height as usize) == buffer.len()
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::NoPin;
+    use embedded_hal_mock::eh1::{
+        delay::NoopDelay, digital::Mock as PinMock, spi::Mock as SpiMock,
+    };
 
     #[test]
     fn epd_size() {
@@ -521,6 +608,15 @@ mod tests {
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
 
+    fn is_window_size_ok(x: u32, y: u32, width: u32, height: u32) -> bool {
+        let len = buffer_len(width as usize, height as usize);
+        check_partial_window::<()>(len, x, y, width, height, WIDTH, HEIGHT).is_ok()
+    }
+
+    fn is_buffer_size_ok(buffer: &[u8], width: u32, height: u32) -> bool {
+        check_partial_window::<()>(buffer.len(), 0, 0, width, height, WIDTH, HEIGHT).is_ok()
+    }
+
     #[test]
     fn inside_of_screen() {
         assert!(is_window_size_ok(0, 0, 80, 128));
@@ -557,4 +653,68 @@ mod tests {
         let buf = [0u8; 10];
         assert!(is_buffer_size_ok(&buf, 8, 10));
     }
+
+    #[test]
+    fn partial_frame_rejects_an_unaligned_window() {
+        let mut spi = SpiMock::new(&[]);
+        let mut busy = PinMock::new(&[]);
+        let mut dc = PinMock::new(&[]);
+        let mut rst = PinMock::new(&[]);
+        let mut epd = Epd1in02 {
+            interface: DisplayInterface::new(
+                busy.clone(),
+                dc.clone(),
+                Some(rst.clone()),
+                None::<NoPin>,
+                None,
+            ),
+            color: DEFAULT_BACKGROUND_COLOR,
+            is_turned_on: true,
+            refresh_mode: RefreshLut::Full,
+            refresh: RefreshLut::Full,
+            vcom: DEFAULT_VCOM,
+            border: None,
+        };
+
+        let buffer = [0u8; 10];
+        let result =
+            epd.update_partial_frame(&mut spi, &mut NoopDelay::new(), &buffer, 4, 0, 8, 10);
+        assert!(matches!(result, Err(Error::OutOfBounds { x: 4, .. })));
+        let result =
+            epd.update_partial_frame(&mut spi, &mut NoopDelay::new(), &buffer, 0, 0, 8, 11);
+        assert!(matches!(
+            result,
+            Err(Error::InvalidBufferLength {
+                expected: 11,
+                got: 10
+            })
+        ));
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn buffer_line_length() {
+        use embedded_graphics::{prelude::*, primitives::Line, primitives::PrimitiveStyle};
+
+        // the default buffer is all black, draw white pixels at the end of
+        // the first and the start of the second line
+        let mut display = Display1in02::default();
+        let _ = Line::new(Point::new(79, 0), Point::new(79, 0))
+            .into_styled(PrimitiveStyle::with_stroke(Color::White, 1))
+            .draw(&mut display);
+        let _ = Line::new(Point::new(0, 1), Point::new(0, 1))
+            .into_styled(PrimitiveStyle::with_stroke(Color::White, 1))
+            .draw(&mut display);
+
+        let buffer = display.buffer();
+        assert_eq!(buffer.len(), NUMBER_OF_BYTES as usize);
+        // a line of 80 pixels takes exactly 10 bytes
+        assert_eq!(buffer[9], 0x01);
+        assert_eq!(buffer[10], 0x80);
+    }
 }
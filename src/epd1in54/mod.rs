@@ -4,7 +4,7 @@
 //!
 //!```rust, no_run
 //!# use embedded_hal_mock::eh1::*;
-//!# fn main() -> Result<(), embedded_hal::spi::ErrorKind> {
+//!# fn main() -> Result<(), epd_waveshare::error::Error<embedded_hal::spi::ErrorKind>> {
 //!use embedded_graphics::{
 //!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyleBuilder},
 //!};
@@ -20,7 +20,7 @@
 //!# let mut delay = delay::NoopDelay::new();
 //!
 //!// Setup EPD
-//!let mut epd = Epd1in54::new(&mut spi, busy_in, dc, rst, &mut delay, None)?;
+//!let mut epd = Epd1in54::new(&mut spi, busy_in, dc, Some(rst), None::<NoPin>, &mut delay, None)?;
 //!
 //!// Use display graphics from embedded-graphics
 //!let mut display = Display1in54::default();
@@ -43,6 +43,23 @@
 //!# Ok(())
 //!# }
 //!```
+//!
+//! # Partial refresh
+//!
+//! [`set_lut`](WaveshareDisplay::set_lut) with [`RefreshLut::Quick`] loads the partial LUT, which
+//! refreshes without flashing. [`update_partial_frame`](WaveshareDisplay::update_partial_frame)
+//! writes a window of the frame, `x` and `width` must be multiples of 8.
+//!
+//! The controller has two frame memories and toggles between them with every refresh. Write the
+//! same content again after a partial refresh, otherwise the next partial refresh starts from the
+//! content before the last one. Switch back with `set_lut(.., Some(RefreshLut::Full))`, which
+//! loads the full LUT again, and do a full refresh from time to time to clear the ghosting.
+//!
+//! # Custom LUT
+//!
+//! A tuned waveform can be loaded with [`Epd1in54::set_custom_lut`]. It replaces the built-in
+//! [`LUT_FULL_UPDATE`] and [`LUT_PARTIAL_UPDATE`] of every refresh mode, is loaded again on
+//! [`wake_up`](WaveshareDisplay::wake_up) and stays until [`Epd1in54::clear_custom_lut`].
 
 /// Width of the display
 pub const WIDTH: u32 = 200;
@@ -54,19 +71,18 @@ pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 const IS_BUSY_LOW: bool = false;
 const SINGLE_BYTE_WRITE: bool = true;
 
+use crate::error::Error;
 use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
 
-use crate::type_a::{
-    command::Command,
-    constants::{LUT_FULL_UPDATE, LUT_PARTIAL_UPDATE},
-};
+use crate::type_a::command::Command;
+pub use crate::type_a::constants::{CUSTOM_LUT_LEN, LUT_FULL_UPDATE, LUT_PARTIAL_UPDATE};
 
 use crate::color::Color;
 
-use crate::traits::{RefreshLut, WaveshareDisplay};
+use crate::traits::{check_refresh_lut, copy_lut, ssd_vcom, RefreshLut, WaveshareDisplay};
 
-use crate::buffer_len;
 use crate::interface::DisplayInterface;
+use crate::{buffer_len, check_partial_window};
 
 /// Full size buffer for use with the 1in54b EPD
 #[cfg(feature = "graphics")]
@@ -79,25 +95,32 @@ pub type Display1in54 = crate::graphics::Display<
 >;
 
 /// Epd1in54 driver
-pub struct Epd1in54<SPI, BUSY, DC, RST, DELAY> {
+pub struct Epd1in54<SPI, BUSY, DC, RST, DELAY, PWR> {
     /// SPI
-    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
     /// Color
     background_color: Color,
     /// Refresh LUT
     refresh: RefreshLut,
+    /// Replaces the built-in LUTs if set
+    custom_lut: Option<[u8; CUSTOM_LUT_LEN]>,
+    /// VCOM register value of [`set_vcom`](WaveshareDisplay::set_vcom), replaces 0xA8 if set
+    vcom: Option<u8>,
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> Epd1in54<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd1in54<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.reset(delay, 10_000, 10_000);
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, 10_000, 10_000)?;
+        self.interface
+            .ssd_software_reset(spi, delay, Command::SwReset, IS_BUSY_LOW)?;
 
         // 3 Databytes:
         // A[7:0]
@@ -119,8 +142,11 @@ where
             .cmd_with_data(spi, Command::BoosterSoftStartControl, &[0xD7, 0xD6, 0x9D])?;
 
         // One Databyte with value 0xA8 for 7V VCOM
-        self.interface
-            .cmd_with_data(spi, Command::WriteVcomRegister, &[0xA8])?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::WriteVcomRegister,
+            &[self.vcom.unwrap_or(0xA8)],
+        )?;
 
         // One Databyte with default value 0x1A for 4 dummy lines per gate
         self.interface
@@ -137,21 +163,26 @@ where
 
         self.set_lut(spi, delay, None)?;
 
-        self.wait_until_idle(spi, delay)?;
-        Ok(())
+        self.wait_until_idle(spi, delay)
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
-    for Epd1in54<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd1in54<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     type DisplayColor = Color;
+    const REFRESH_LUTS: &'static [RefreshLut] = &[
+        RefreshLut::Full,
+        RefreshLut::Quick,
+        RefreshLut::PartialRefresh,
+    ];
     fn width(&self) -> u32 {
         WIDTH
     }
@@ -164,16 +195,19 @@ where
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(busy, dc, rst, pwr, delay_us);
 
         let mut epd = Epd1in54 {
             interface,
             background_color: DEFAULT_BACKGROUND_COLOR,
             refresh: RefreshLut::Full,
+            custom_lut: None,
+            vcom: None,
         };
 
         epd.init(spi, delay)?;
@@ -181,17 +215,17 @@ where
         Ok(epd)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         // 0x00 for Normal mode (Power on Reset), 0x01 for Deep Sleep Mode
         //TODO: is 0x00 needed here or would 0x01 be even more efficient?
         self.interface
             .cmd_with_data(spi, Command::DeepSleepMode, &[0x00])?;
-        Ok(())
+        self.interface.power_off()
     }
 
     fn update_frame(
@@ -199,7 +233,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.use_full_frame(spi, delay)?;
         self.interface
@@ -207,7 +241,9 @@ where
         Ok(())
     }
 
-    //TODO: update description: last 3 bits will be ignored for width and x_pos
+    /// Writes a window of the frame to the RAM, without refreshing the display
+    ///
+    /// `x` and `width` must be multiples of 8, the RAM is addressed in bytes.
     fn update_partial_frame(
         &mut self,
         spi: &mut SPI,
@@ -217,9 +253,11 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        check_partial_window(buffer.len(), x, y, width, height, WIDTH, HEIGHT)?;
+
         self.wait_until_idle(spi, delay)?;
-        self.set_ram_area(spi, delay, x, y, x + width, y + height)?;
+        self.set_ram_area(spi, delay, x, y, x + width - 1, y + height - 1)?;
         self.set_ram_counter(spi, delay, x, y)?;
 
         self.interface
@@ -227,7 +265,7 @@ where
         Ok(())
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         // enable clock signal, enable cp, display pattern -> 0xC4 (tested with the arduino version)
         //TODO: test control_1 or control_2 with default value 0xFF (from the datasheet)
@@ -246,13 +284,13 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.use_full_frame(spi, delay)?;
 
@@ -269,6 +307,31 @@ where
         self.background_color = background_color;
     }
 
+    fn set_vcom(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        millivolts: i16,
+    ) -> Result<(), Error<SPI::Error>> {
+        let vcom = ssd_vcom(millivolts)?;
+        self.vcom = Some(vcom);
+        self.wait_until_idle(spi, delay)?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteVcomRegister, &[vcom])
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
     fn background_color(&self) -> &Color {
         &self.background_color
     }
@@ -278,37 +341,70 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        check_refresh_lut(refresh_rate, Self::REFRESH_LUTS)?;
         if let Some(refresh_lut) = refresh_rate {
             self.refresh = refresh_lut;
         }
-        match self.refresh {
-            RefreshLut::Full => self.set_lut_helper(spi, delay, &LUT_FULL_UPDATE),
-            RefreshLut::Quick | RefreshLut::PartialRefresh => {
-                self.set_lut_helper(spi, delay, &LUT_PARTIAL_UPDATE)
-            }
-        }
+        let lut = match (self.custom_lut, self.refresh) {
+            (Some(lut), _) => lut,
+            (None, RefreshLut::Full) => LUT_FULL_UPDATE,
+            (None, RefreshLut::Quick | RefreshLut::PartialRefresh) => LUT_PARTIAL_UPDATE,
+        };
+        self.set_lut_helper(spi, delay, &lut)
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
-        Ok(())
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> Epd1in54<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd1in54<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
+    /// Writes a window of the frame to the RAM like
+    /// [`update_partial_frame`](WaveshareDisplay::update_partial_frame), with the data taken
+    /// from an iterator
+    ///
+    /// Exactly `width / 8 * height` bytes are taken, it fails with
+    /// `Error::InvalidBufferLength` if `data` ends early and leaves any further items unconsumed.
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_partial_frame_iter<I: IntoIterator<Item = u8>>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        data: I,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        let len = buffer_len(width as usize, height as usize);
+        check_partial_window(len, x, y, width, height, WIDTH, HEIGHT)?;
+
+        self.wait_until_idle(spi, delay)?;
+        self.set_ram_area(spi, delay, x, y, x + width - 1, y + height - 1)?;
+        self.set_ram_counter(spi, delay, x, y)?;
+
+        self.interface.cmd(spi, Command::WriteRam)?;
+        self.interface.data_iter(spi, data, len)
+    }
+
     pub(crate) fn use_full_frame(
         &mut self,
         spi: &mut SPI,
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         // choose full frame/ram
         self.set_ram_area(spi, delay, 0, 0, WIDTH - 1, HEIGHT - 1)?;
 
@@ -324,10 +420,10 @@ where
         start_y: u32,
         end_x: u32,
         end_y: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
-        assert!(start_x < end_x);
-        assert!(start_y < end_y);
+        assert!(start_x <= end_x);
+        assert!(start_y <= end_y);
 
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
@@ -357,7 +453,7 @@ where
         delay: &mut DELAY,
         x: u32,
         y: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
@@ -373,12 +469,37 @@ where
         Ok(())
     }
 
+    /// Loads `lut` instead of the built-in waveforms, see [`CUSTOM_LUT_LEN`] for its layout
+    ///
+    /// The refresh mode selected with [`set_lut`](WaveshareDisplay::set_lut) still decides how
+    /// the frame is displayed. Fails with `Error::InvalidBufferLength` if `lut` doesn't have
+    /// `CUSTOM_LUT_LEN` bytes.
+    pub fn set_custom_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        lut: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.custom_lut = Some(copy_lut(lut)?);
+        self.set_lut(spi, delay, None)
+    }
+
+    /// Drops the LUT of `set_custom_lut` and loads the built-in one of the refresh mode again
+    pub fn clear_custom_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.custom_lut = None;
+        self.set_lut(spi, delay, None)
+    }
+
     fn set_lut_helper(
         &mut self,
         spi: &mut SPI,
         delay: &mut DELAY,
         buffer: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         assert!(buffer.len() == 30);
 
@@ -390,7 +511,55 @@ where
 
 #[cfg(test)]
 mod tests {
+    extern crate std;
     use super::*;
+    use crate::interface::mock::expect_commands;
+    use crate::NoPin;
+    use embedded_hal_mock::eh1::{
+        delay::NoopDelay,
+        digital::{Mock as PinMock, State, Transaction as PinTransaction},
+        spi::Mock as SpiMock,
+        MockError,
+    };
+    use std::io::ErrorKind;
+    use std::{vec, vec::Vec};
+
+    /// Runs `f` on a driver which expects `commands` and `busy_reads` idle busy reads
+    fn with_epd(
+        commands: &[(u8, &[u8])],
+        busy_reads: usize,
+        f: impl FnOnce(
+            &mut Epd1in54<SpiMock<u8>, PinMock, PinMock, PinMock, NoopDelay, NoPin>,
+            &mut SpiMock<u8>,
+        ),
+    ) {
+        let (spi_expectations, dc_expectations) = expect_commands(commands);
+        let mut spi = SpiMock::new(&spi_expectations);
+        let mut busy = PinMock::new(&vec![PinTransaction::get(State::Low); busy_reads]);
+        let mut dc = PinMock::new(&dc_expectations);
+        let mut rst = PinMock::new(&[]);
+
+        let mut epd = Epd1in54 {
+            interface: DisplayInterface::new(
+                busy.clone(),
+                dc.clone(),
+                Some(rst.clone()),
+                None::<NoPin>,
+                None,
+            ),
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+            custom_lut: None,
+            vcom: None,
+        };
+        f(&mut epd, &mut spi);
+        drop(epd);
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
 
     #[test]
     fn epd_size() {
@@ -398,4 +567,136 @@ mod tests {
         assert_eq!(HEIGHT, 200);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn vcom_in_millivolts() {
+        with_epd(&[(0x2C, &[0x3C])], 1, |epd, spi| {
+            let mut delay = NoopDelay::new();
+            epd.set_vcom(spi, &mut delay, -1500).unwrap();
+            assert_eq!(
+                epd.set_vcom(spi, &mut delay, -3100),
+                Err(Error::Unsupported)
+            );
+            assert_eq!(epd.vcom, Some(0x3C));
+        });
+    }
+
+    #[test]
+    fn switching_back_restores_the_full_lut() {
+        with_epd(
+            &[(0x32, &LUT_PARTIAL_UPDATE), (0x32, &LUT_FULL_UPDATE)],
+            2,
+            |epd, spi| {
+                let mut delay = NoopDelay::new();
+                epd.set_lut(spi, &mut delay, Some(RefreshLut::Quick))
+                    .unwrap();
+                epd.set_lut(spi, &mut delay, Some(RefreshLut::Full))
+                    .unwrap();
+            },
+        );
+    }
+
+    #[test]
+    fn window_at_the_corner() {
+        let buffer = [0x00; 10];
+        with_epd(
+            &[
+                (0x44, &[0x18, 0x18]),
+                (0x45, &[0xBE, 0x00, 0xC7, 0x00]),
+                (0x4E, &[0x18]),
+                (0x4F, &[0xBE, 0x00]),
+                (0x24, &buffer),
+            ],
+            3,
+            |epd, spi| {
+                epd.update_partial_frame(spi, &mut NoopDelay::new(), &buffer, 192, 190, 8, 10)
+                    .unwrap();
+            },
+        );
+    }
+
+    #[test]
+    fn deghost_cycles_and_restores_the_background() {
+        let black = vec![0x00; (WIDTH / 8 * HEIGHT) as usize];
+        let white = vec![0xFF; (WIDTH / 8 * HEIGHT) as usize];
+        let mut commands: Vec<(u8, &[u8])> = vec![(0x32, &LUT_FULL_UPDATE)];
+        for frame in [&black, &white, &black] {
+            commands.extend_from_slice(&[
+                (0x44, &[0x00, 0x18]),
+                (0x45, &[0x00, 0x00, 0xC7, 0x00]),
+                (0x4E, &[0x00]),
+                (0x4F, &[0x00, 0x00]),
+                (0x24, frame),
+                (0x22, &[0xC4]),
+                (0x20, &[]),
+                (0xFF, &[]),
+            ]);
+        }
+        with_epd(&commands, 1 + 3 * 4, |epd, spi| {
+            epd.refresh = RefreshLut::Quick;
+            epd.set_background_color(Color::Black);
+            epd.deghost(spi, &mut NoopDelay::new(), 1).unwrap();
+            assert_eq!(epd.background_color(), &Color::Black);
+            assert_eq!(epd.refresh, RefreshLut::Full);
+        });
+    }
+
+    #[test]
+    fn deghost_restores_the_background_on_errors() {
+        let (spi_expectations, dc_expectations) = expect_commands(&[(0x32, &LUT_FULL_UPDATE)]);
+        let mut spi = SpiMock::new(&spi_expectations);
+        let mut busy = PinMock::new(&[
+            PinTransaction::get(State::Low),
+            PinTransaction::get(State::Low).with_error(MockError::Io(ErrorKind::Other)),
+        ]);
+        let mut dc = PinMock::new(&dc_expectations);
+        let mut rst = PinMock::new(&[]);
+
+        let mut epd = Epd1in54 {
+            interface: DisplayInterface::new(
+                busy.clone(),
+                dc.clone(),
+                Some(rst.clone()),
+                None::<NoPin>,
+                None,
+            ),
+            background_color: Color::Black,
+            refresh: RefreshLut::Full,
+            custom_lut: None,
+            vcom: None,
+        };
+        let result = epd.deghost(&mut spi, &mut NoopDelay::new(), 1);
+        assert_eq!(
+            result,
+            Err(Error::Pin(embedded_hal::digital::ErrorKind::Other))
+        );
+        assert_eq!(epd.background_color(), &Color::Black);
+        drop(epd);
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
+
+    #[test]
+    fn window_from_an_iterator() {
+        let buffer = [0x00, 0x01, 0x02, 0x03];
+        with_epd(
+            &[
+                (0x44, &[0x01, 0x02]),
+                (0x45, &[0x08, 0x00, 0x09, 0x00]),
+                (0x4E, &[0x01]),
+                (0x4F, &[0x08, 0x00]),
+                (0x24, &buffer),
+            ],
+            3,
+            |epd, spi| {
+                let mut data = 0..;
+                epd.update_partial_frame_iter(spi, &mut NoopDelay::new(), &mut data, 8, 8, 16, 2)
+                    .unwrap();
+                assert_eq!(data.next(), Some(4));
+            },
+        );
+    }
 }
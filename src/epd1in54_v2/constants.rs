@@ -1,6 +1,7 @@
-#[rustfmt::skip]
 // Original Waveforms from Waveshare for 1in54_V2
-pub(crate) const LUT_FULL_UPDATE: [u8; 159] =[
+/// Built-in LUT of the full refresh, e.g. to pass to [`Epd1in54::set_custom_lut`](super::Epd1in54::set_custom_lut)
+#[rustfmt::skip]
+pub const LUT_FULL_UPDATE: [u8; 159] =[
     0x80, 0x48, 0x40, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 
     0x40, 0x48, 0x80, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 
     0x80, 0x48, 0x40, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 
@@ -22,8 +23,9 @@ pub(crate) const LUT_FULL_UPDATE: [u8; 159] =[
     0x22, 0x17, 0x41, 0x0, 0x32, 0x20
 ];
 
+/// Built-in LUT of the quick refresh
 #[rustfmt::skip]
-pub(crate) const LUT_PARTIAL_UPDATE: [u8; 159] =[
+pub const LUT_PARTIAL_UPDATE: [u8; 159] =[
     0x0, 0x40, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 
     0x80, 0x80, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 
     0x40, 0x40, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 
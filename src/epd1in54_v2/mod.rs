@@ -1,6 +1,16 @@
 //! A simple Driver for the Waveshare 1.54" E-Ink Display via SPI
 //!
 //! GDEH0154D67
+//!
+//! # Custom LUT
+//!
+//! The built-in waveforms flash the panel on every refresh. A tuned waveform, e.g. one of the
+//! no-flash partial LUTs of GxEPD2, can be loaded with [`Epd1in54::set_custom_lut`]. It replaces
+//! the built-in waveform of every refresh mode, is loaded again on
+//! [`wake_up`](WaveshareDisplay::wake_up) and stays until [`Epd1in54::clear_custom_lut`]. The
+//! built-in waveforms are [`LUT_FULL_UPDATE`] and [`LUT_PARTIAL_UPDATE`].
+//!
+//! The last byte of every LUT is the VCOM, [`Epd1in54::set_vcom`] overrides it for all of them.
 
 /// Width of the display
 pub const WIDTH: u32 = 200;
@@ -10,44 +20,61 @@ pub const HEIGHT: u32 = 200;
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 const IS_BUSY_LOW: bool = false;
 const SINGLE_BYTE_WRITE: bool = true;
+/// Length of a LUT for [`Epd1in54::set_custom_lut`]
+///
+/// 153 bytes of waveform (0x32), followed by the end option (0x3F), the gate voltage (0x03), 3
+/// bytes of source voltages (0x04) and the VCOM (0x2C).
+pub const CUSTOM_LUT_LEN: usize = 159;
 
+use crate::error::Error;
 use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
 
 use crate::type_a::command::Command;
 
 mod constants;
-use crate::epd1in54_v2::constants::{LUT_FULL_UPDATE, LUT_PARTIAL_UPDATE};
+pub use crate::epd1in54_v2::constants::{LUT_FULL_UPDATE, LUT_PARTIAL_UPDATE};
 
 use crate::color::Color;
 
-use crate::traits::{RefreshLut, WaveshareDisplay};
+use crate::traits::{
+    check_refresh_lut, ssd_border, ssd_vcom, BorderColor, RefreshLut, WaveshareDisplay,
+};
 
 use crate::interface::DisplayInterface;
 
+use crate::{buffer_len, check_partial_window};
+
 #[cfg(feature = "graphics")]
 pub use crate::epd1in54::Display1in54;
 
 /// Epd1in54 driver
-pub struct Epd1in54<SPI, BUSY, DC, RST, DELAY> {
+pub struct Epd1in54<SPI, BUSY, DC, RST, DELAY, PWR> {
     /// SPI
-    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
     /// Color
     background_color: Color,
 
     /// Refresh LUT
     refresh: RefreshLut,
+    /// LUT loaded instead of the built-in ones
+    custom_lut: Option<[u8; CUSTOM_LUT_LEN]>,
+    /// VCOM register value of [`set_vcom`](Self::set_vcom), replaces the one of the LUTs if set
+    vcom: Option<u8>,
+    /// Border waveform of `set_border`, replaces the default of each mode if set
+    border: Option<u8>,
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> Epd1in54<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd1in54<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.reset(delay, 10_000, 10_000);
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, 10_000, 10_000)?;
         self.wait_until_idle(spi, delay)?;
         self.interface.cmd(spi, Command::SwReset)?;
         self.wait_until_idle(spi, delay)?;
@@ -79,24 +106,34 @@ where
 
         self.set_ram_counter(spi, delay, 0, 0)?;
 
+        if let Some(border) = self.border {
+            self.interface
+                .cmd_with_data(spi, Command::BorderWaveformControl, &[border])?;
+        }
+
         //Initialize the lookup table with a refresh waveform
         self.set_lut(spi, delay, None)?;
 
-        self.wait_until_idle(spi, delay)?;
-        Ok(())
+        self.wait_until_idle(spi, delay)
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
-    for Epd1in54<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd1in54<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     type DisplayColor = Color;
+    const REFRESH_LUTS: &'static [RefreshLut] = &[
+        RefreshLut::Full,
+        RefreshLut::Quick,
+        RefreshLut::PartialRefresh,
+    ];
     fn width(&self) -> u32 {
         WIDTH
     }
@@ -109,16 +146,20 @@ where
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(busy, dc, rst, pwr, delay_us);
 
         let mut epd = Epd1in54 {
             interface,
             background_color: DEFAULT_BACKGROUND_COLOR,
             refresh: RefreshLut::Full,
+            custom_lut: None,
+            vcom: None,
+            border: None,
         };
 
         epd.init(spi, delay)?;
@@ -126,15 +167,15 @@ where
         Ok(epd)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.interface
             .cmd_with_data(spi, Command::DeepSleepMode, &[0x01])?;
-        Ok(())
+        self.interface.power_off()
     }
 
     fn update_frame(
@@ -142,7 +183,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.use_full_frame(spi, delay)?;
         self.interface
@@ -150,7 +191,9 @@ where
         Ok(())
     }
 
-    //TODO: update description: last 3 bits will be ignored for width and x_pos
+    /// Writes a window of the frame to the RAM, without refreshing the display
+    ///
+    /// `x` and `width` must be multiples of 8, the RAM is addressed in bytes.
     fn update_partial_frame(
         &mut self,
         spi: &mut SPI,
@@ -160,9 +203,11 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        check_partial_window(buffer.len(), x, y, width, height, WIDTH, HEIGHT)?;
+
         self.wait_until_idle(spi, delay)?;
-        self.set_ram_area(spi, delay, x, y, x + width, y + height)?;
+        self.set_ram_area(spi, delay, x, y, x + width - 1, y + height - 1)?;
         self.set_ram_counter(spi, delay, x, y)?;
 
         self.interface
@@ -170,7 +215,7 @@ where
         Ok(())
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         if self.refresh == RefreshLut::Full {
             self.interface
@@ -192,13 +237,13 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.use_full_frame(spi, delay)?;
 
@@ -218,6 +263,33 @@ where
         self.background_color = background_color;
     }
 
+    /// Replaces the VCOM byte of the built-in LUTs and of one given to
+    /// [`set_custom_lut`](Epd1in54::set_custom_lut)
+    fn set_vcom(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        millivolts: i16,
+    ) -> Result<(), Error<SPI::Error>> {
+        let vcom = ssd_vcom(millivolts)?;
+        self.vcom = Some(vcom);
+        self.wait_until_idle(spi, delay)?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteVcomRegister, &[vcom])
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
     fn background_color(&self) -> &Color {
         &self.background_color
     }
@@ -227,16 +299,17 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        check_refresh_lut(refresh_rate, Self::REFRESH_LUTS)?;
         if let Some(refresh_lut) = refresh_rate {
             self.refresh = refresh_lut;
         }
-        match self.refresh {
-            RefreshLut::Full => self.set_lut_helper(spi, delay, &LUT_FULL_UPDATE),
-            RefreshLut::Quick | RefreshLut::PartialRefresh => {
-                self.set_lut_helper(spi, delay, &LUT_PARTIAL_UPDATE)
-            }
-        }?;
+        let lut = match (self.custom_lut, self.refresh) {
+            (Some(lut), _) => lut,
+            (None, RefreshLut::Full) => LUT_FULL_UPDATE,
+            (None, RefreshLut::Quick | RefreshLut::PartialRefresh) => LUT_PARTIAL_UPDATE,
+        };
+        self.set_lut_helper(spi, delay, &lut)?;
 
         // Additional configuration required only for partial updates
         if self.refresh == RefreshLut::Quick {
@@ -245,8 +318,11 @@ where
                 Command::WriteOtpSelection,
                 &[0x0, 0x0, 0x0, 0x0, 0x0, 0x40, 0x0, 0x0, 0x0, 0x0],
             )?;
-            self.interface
-                .cmd_with_data(spi, Command::BorderWaveformControl, &[0x80])?;
+            self.interface.cmd_with_data(
+                spi,
+                Command::BorderWaveformControl,
+                &[self.border.unwrap_or(0x80)],
+            )?;
             self.interface
                 .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xc0])?;
             self.interface.cmd(spi, Command::MasterActivation)?;
@@ -257,25 +333,93 @@ where
         Ok(())
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
-        Ok(())
+    fn set_border(&mut self, spi: &mut SPI, border: BorderColor) -> Result<(), Error<SPI::Error>> {
+        let border = ssd_border(border, false)?;
+        self.border = Some(border);
+        self.interface
+            .cmd_with_data(spi, Command::BorderWaveformControl, &[border])
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> Epd1in54<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd1in54<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
+    /// Writes a window of the frame to the RAM like
+    /// [`update_partial_frame`](WaveshareDisplay::update_partial_frame), with the data taken
+    /// from an iterator
+    ///
+    /// Exactly `width / 8 * height` bytes are taken, it fails with
+    /// `Error::InvalidBufferLength` if `data` ends early and leaves any further items unconsumed.
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_partial_frame_iter<I: IntoIterator<Item = u8>>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        data: I,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        let len = buffer_len(width as usize, height as usize);
+        check_partial_window(len, x, y, width, height, WIDTH, HEIGHT)?;
+
+        self.wait_until_idle(spi, delay)?;
+        self.set_ram_area(spi, delay, x, y, x + width - 1, y + height - 1)?;
+        self.set_ram_counter(spi, delay, x, y)?;
+
+        self.interface.cmd(spi, Command::WriteRam)?;
+        self.interface.data_iter(spi, data, len)
+    }
+
+    /// Loads `lut` instead of the built-in waveforms, see [`CUSTOM_LUT_LEN`] for its layout
+    ///
+    /// The refresh mode selected with [`set_lut`](WaveshareDisplay::set_lut) still decides how
+    /// the frame is displayed, e.g. choose `RefreshLut::Quick` for a partial LUT. Fails with
+    /// `Error::InvalidBufferLength` if `lut` doesn't have `CUSTOM_LUT_LEN` bytes.
+    pub fn set_custom_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        lut: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        let lut = lut.try_into().map_err(|_| Error::InvalidBufferLength {
+            expected: CUSTOM_LUT_LEN,
+            got: lut.len(),
+        })?;
+        self.custom_lut = Some(lut);
+        self.set_lut(spi, delay, None)
+    }
+
+    /// Drops the LUT of `set_custom_lut` and loads the built-in one of the refresh mode again
+    pub fn clear_custom_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.custom_lut = None;
+        self.set_lut(spi, delay, None)
+    }
+
     pub(crate) fn use_full_frame(
         &mut self,
         spi: &mut SPI,
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         // choose full frame/ram
         self.set_ram_area(spi, delay, 0, 0, WIDTH - 1, HEIGHT - 1)?;
 
@@ -291,10 +435,10 @@ where
         start_y: u32,
         end_x: u32,
         end_y: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
-        assert!(start_x < end_x);
-        assert!(start_y < end_y);
+        assert!(start_x <= end_x);
+        assert!(start_y <= end_y);
 
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
@@ -324,7 +468,7 @@ where
         delay: &mut DELAY,
         x: u32,
         y: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
@@ -345,7 +489,7 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         buffer: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         assert!(buffer.len() == 159);
 
@@ -365,8 +509,11 @@ where
             Command::SourceDrivingVoltage,
             &[buffer[155], buffer[156], buffer[157]],
         )?;
-        self.interface
-            .cmd_with_data(spi, Command::WriteVcomRegister, &[buffer[158]])?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::WriteVcomRegister,
+            &[self.vcom.unwrap_or(buffer[158])],
+        )?;
 
         Ok(())
     }
@@ -374,7 +521,65 @@ where
 
 #[cfg(test)]
 mod tests {
+    extern crate std;
     use super::*;
+    use crate::interface::mock::expect_commands;
+    use crate::NoPin;
+    use embedded_hal_mock::eh1::{
+        delay::NoopDelay,
+        digital::{Mock as PinMock, State, Transaction as PinTransaction},
+        spi::Mock as SpiMock,
+    };
+    use std::vec;
+
+    /// Runs `f` on a driver which expects `commands` and `busy_reads` idle busy reads
+    fn with_epd(
+        commands: &[(u8, &[u8])],
+        busy_reads: usize,
+        f: impl FnOnce(
+            &mut Epd1in54<SpiMock<u8>, PinMock, PinMock, PinMock, NoopDelay, NoPin>,
+            &mut SpiMock<u8>,
+        ),
+    ) {
+        let (spi_expectations, dc_expectations) = expect_commands(commands);
+        let mut spi = SpiMock::new(&spi_expectations);
+        let mut busy = PinMock::new(&vec![PinTransaction::get(State::Low); busy_reads]);
+        let mut dc = PinMock::new(&dc_expectations);
+        let mut rst = PinMock::new(&[]);
+
+        let mut epd = Epd1in54 {
+            interface: DisplayInterface::new(
+                busy.clone(),
+                dc.clone(),
+                Some(rst.clone()),
+                None::<NoPin>,
+                None,
+            ),
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+            custom_lut: None,
+            vcom: None,
+            border: None,
+        };
+        f(&mut epd, &mut spi);
+        drop(epd);
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
+
+    /// Commands of `set_lut_helper` for `lut`
+    fn lut_commands(lut: &[u8]) -> [(u8, &[u8]); 5] {
+        [
+            (0x32, &lut[..153]),
+            (0x3F, &lut[153..154]),
+            (0x03, &lut[154..155]),
+            (0x04, &lut[155..158]),
+            (0x2C, &lut[158..]),
+        ]
+    }
 
     #[test]
     fn epd_size() {
@@ -382,4 +587,35 @@ mod tests {
         assert_eq!(HEIGHT, 200);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn custom_lut_is_kept_until_cleared() {
+        let custom = [0x42; CUSTOM_LUT_LEN];
+        let commands = [
+            &lut_commands(&custom)[..],
+            // `init` reloads the LUT the same way
+            &lut_commands(&custom)[..],
+            &lut_commands(&LUT_FULL_UPDATE)[..],
+        ]
+        .concat();
+        with_epd(&commands, 6, |epd, spi| {
+            let delay = &mut NoopDelay::new();
+            epd.set_custom_lut(spi, delay, &custom).unwrap();
+            epd.set_lut(spi, delay, None).unwrap();
+            epd.clear_custom_lut(spi, delay).unwrap();
+        });
+    }
+
+    #[test]
+    fn custom_lut_of_the_wrong_length() {
+        with_epd(&[], 0, |epd, spi| {
+            assert_eq!(
+                epd.set_custom_lut(spi, &mut NoopDelay::new(), &[0; 153]),
+                Err(Error::InvalidBufferLength {
+                    expected: CUSTOM_LUT_LEN,
+                    got: 153
+                })
+            );
+        });
+    }
 }
@@ -1,10 +1,12 @@
 //! A simple Driver for the Waveshare 1.54" (B) E-Ink Display via SPI
 
+use crate::error::Error;
 use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
 
 use crate::interface::DisplayInterface;
 use crate::traits::{
-    InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+    check_refresh_lut, uc_border, uc_vcm_dc, BorderColor, InternalWiAdditions, RefreshLut,
+    WaveshareDisplay, WaveshareThreeColorDisplay,
 };
 
 //The Lookup Tables for the Display
@@ -18,6 +20,10 @@ pub const HEIGHT: u32 = 200;
 /// Default Background Color (white)
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 const IS_BUSY_LOW: bool = true;
+/// VCOM and data interval setting until `set_border` is called
+const DEFAULT_VCOM_DATA_INTERVAL: u8 = 0x37;
+/// -0.8 V
+const DEFAULT_VCOM: u8 = 0x0E;
 const SINGLE_BYTE_WRITE: bool = true;
 
 use crate::color::Color;
@@ -38,22 +44,30 @@ pub type Display1in54b = crate::graphics::Display<
 >;
 
 /// Epd1in54b driver
-pub struct Epd1in54b<SPI, BUSY, DC, RST, DELAY> {
-    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+pub struct Epd1in54b<SPI, BUSY, DC, RST, DELAY, PWR> {
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
     color: Color,
+    /// VCOM register value, see [`set_vcom`](Self::set_vcom)
+    vcom: u8,
+    /// VCOM and data interval setting, holds the border of
+    /// [`set_border`](WaveshareDisplay::set_border)
+    vcom_data_interval: u8,
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
-    for Epd1in54b<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd1in54b<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.reset(delay, 10_000, 10_000);
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, 10_000, 10_000)?;
+        self.interface
+            .uc_software_reset(spi, delay, Command::PanelSetting, 0xCF, IS_BUSY_LOW)?;
 
         // set the power settings
         self.interface
@@ -71,7 +85,11 @@ where
         // set the panel settings
         self.cmd_with_data(spi, Command::PanelSetting, &[0xCF])?;
 
-        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x37])?;
+        self.cmd_with_data(
+            spi,
+            Command::VcomAndDataIntervalSetting,
+            &[self.vcom_data_interval],
+        )?;
 
         // PLL
         self.cmd_with_data(spi, Command::PllControl, &[0x39])?;
@@ -79,23 +97,22 @@ where
         // set resolution
         self.send_resolution(spi)?;
 
-        self.cmd_with_data(spi, Command::VcmDcSetting, &[0x0E])?;
+        self.cmd_with_data(spi, Command::VcmDcSetting, &[self.vcom])?;
 
         self.set_lut(spi, delay, None)?;
 
-        self.wait_until_idle(spi, delay)?;
-
-        Ok(())
+        self.wait_until_idle(spi, delay)
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>
-    for Epd1in54b<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd1in54b<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     fn update_color_frame(
@@ -104,7 +121,7 @@ where
         delay: &mut DELAY,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_achromatic_frame(spi, delay, black)?;
         self.update_chromatic_frame(spi, delay, chromatic)
     }
@@ -114,7 +131,7 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         black: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.send_resolution(spi)?;
 
@@ -132,20 +149,21 @@ where
         spi: &mut SPI,
         _delay: &mut DELAY,
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
         self.interface.data(spi, chromatic)?;
         Ok(())
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
-    for Epd1in54b<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd1in54b<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     type DisplayColor = Color;
@@ -153,21 +171,27 @@ where
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new_color(busy, dc, rst, pwr, delay_us);
         let color = DEFAULT_BACKGROUND_COLOR;
 
-        let mut epd = Epd1in54b { interface, color };
+        let mut epd = Epd1in54b {
+            interface,
+            color,
+            vcom: DEFAULT_VCOM,
+            vcom_data_interval: DEFAULT_VCOM_DATA_INTERVAL,
+        };
 
         epd.init(spi, delay)?;
 
         Ok(epd)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.interface
             .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x17])?; //border floating
@@ -184,10 +208,10 @@ where
 
         self.command(spi, Command::PowerOff)?;
 
-        Ok(())
+        self.interface.power_off()
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.init(spi, delay)
     }
 
@@ -195,6 +219,29 @@ where
         self.color = color;
     }
 
+    fn set_vcom(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        millivolts: i16,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.vcom = uc_vcm_dc(millivolts)?;
+        self.wait_until_idle(spi, delay)?;
+        self.cmd_with_data(spi, Command::VcmDcSetting, &[self.vcom])
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
     fn background_color(&self) -> &Color {
         &self.color
     }
@@ -212,7 +259,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.send_resolution(spi)?;
 
@@ -247,11 +294,11 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
-        unimplemented!()
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::Unsupported)
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.command(spi, Command::DisplayRefresh)?;
         Ok(())
@@ -262,13 +309,13 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.send_resolution(spi)?;
 
@@ -292,8 +339,9 @@ where
         &mut self,
         spi: &mut SPI,
         _delay: &mut DELAY,
-        _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_refresh_lut(refresh_rate, Self::REFRESH_LUTS)?;
         self.interface
             .cmd_with_data(spi, Command::LutForVcom, LUT_VCOM0)?;
         self.interface
@@ -312,25 +360,38 @@ where
         Ok(())
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
-        Ok(())
+    fn set_border(&mut self, spi: &mut SPI, border: BorderColor) -> Result<(), Error<SPI::Error>> {
+        self.vcom_data_interval = uc_border(self.vcom_data_interval, border)?;
+        self.cmd_with_data(
+            spi,
+            Command::VcomAndDataIntervalSetting,
+            &[self.vcom_data_interval],
+        )
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> Epd1in54b<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd1in54b<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
         self.interface.data(spi, data)
     }
 
@@ -339,11 +400,11 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
-    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
         let w = self.width();
         let h = self.height();
 
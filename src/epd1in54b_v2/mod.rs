@@ -0,0 +1,484 @@
+//! A simple Driver for the Waveshare 1.54" B V2 Tri-Color E-Ink Display via SPI
+//!
+//! The V2 uses a SSD1681 controller with a black and a red RAM bank and is not compatible
+//! with the [`epd1in54b`](crate::epd1in54b) driver.
+//!
+//! The 200 pixels of a line fill the 25 bytes of the RAM x range `0x00..=0x18`, the first
+//! column is at x address 0. The y address counts down from the last line.
+//!
+//! The black RAM of the controller is inverted (a set bit is white) while the red RAM is not
+//! (a set bit is red). This is exactly how the [`Display1in54b`] buffers store the pixels, so
+//! both planes are sent unchanged.
+//!
+//! # References
+//!
+//! - [Waveshare Wiki](https://www.waveshare.com/wiki/1.54inch_e-Paper_Module_(B))
+//! - [Waveshare C driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_1in54b_V2.c)
+//!
+//! # Example for the 1.54" B V2 E-Ink Display
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), epd_waveshare::error::Error<embedded_hal::spi::ErrorKind>> {
+//!use embedded_graphics::{prelude::*, primitives::{Line, PrimitiveStyle}};
+//!use epd_waveshare::{epd1in54b_v2::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = digital::Mock::new(&expectations);
+//!# let dc = digital::Mock::new(&expectations);
+//!# let rst = digital::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
+//!
+//!// Setup EPD
+//!let mut epd = Epd1in54b::new(&mut spi, busy_in, dc, Some(rst), None::<NoPin>, &mut delay, None)?;
+//!
+//!// Use display graphics from embedded-graphics
+//!let mut display = Display1in54b::default();
+//!display.clear(TriColor::White);
+//!
+//!// A black and a red line
+//!let _ = Line::new(Point::new(0, 20), Point::new(0, 100))
+//!    .into_styled(PrimitiveStyle::with_stroke(TriColor::Black, 1))
+//!    .draw(&mut display);
+//!let _ = Line::new(Point::new(15, 20), Point::new(15, 100))
+//!    .into_styled(PrimitiveStyle::with_stroke(TriColor::Chromatic, 1))
+//!    .draw(&mut display);
+//!
+//!// Display updated frame
+//!epd.update_color_frame(
+//!    &mut spi,
+//!    &mut delay,
+//!    display.bw_buffer(),
+//!    display.chromatic_buffer(),
+//!)?;
+//!epd.display_frame(&mut spi, &mut delay)?;
+//!
+//!// Set the EPD to sleep
+//!epd.sleep(&mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+
+use crate::error::Error;
+use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
+
+use crate::color::TriColor;
+use crate::interface::DisplayInterface;
+use crate::traits::{
+    ssd_border, BorderColor, InternalWiAdditions, WaveshareDisplay, WaveshareThreeColorDisplay,
+};
+use crate::type_a::command::Command;
+use crate::{buffer_len, check_buffer_len};
+
+/// Width of the display
+pub const WIDTH: u32 = 200;
+/// Height of the display
+pub const HEIGHT: u32 = 200;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: TriColor = TriColor::White;
+
+const NUM_DISPLAY_BITS: u32 = WIDTH / 8 * HEIGHT;
+
+const IS_BUSY_LOW: bool = false;
+const SINGLE_BYTE_WRITE: bool = true;
+
+/// Full size buffer for use with the 1in54b V2 EPD
+#[cfg(feature = "graphics")]
+pub type Display1in54b = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    true,
+    { buffer_len(WIDTH as usize, HEIGHT as usize * 2) },
+    TriColor,
+>;
+
+/// Epd1in54b V2 driver
+pub struct Epd1in54b<SPI, BUSY, DC, RST, DELAY, PWR> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    color: TriColor,
+    /// Border waveform of `set_border`, replaces the default if set
+    border: Option<u8>,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd1in54b<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, 200_000, 2_000)?;
+
+        self.wait_until_idle(spi, delay)?;
+        self.interface.cmd(spi, Command::SwReset)?;
+        self.wait_until_idle(spi, delay)?;
+
+        self.interface.cmd_with_data(
+            spi,
+            Command::DriverOutputControl,
+            &[(HEIGHT - 1) as u8, ((HEIGHT - 1) >> 8) as u8, 0x01],
+        )?;
+
+        // x increment, y decrement, address counter is updated in x direction
+        self.interface
+            .cmd_with_data(spi, Command::DataEntryModeSetting, &[0x01])?;
+
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamXAddressStartEndPosition,
+            &[0x00, (WIDTH / 8 - 1) as u8],
+        )?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamYAddressStartEndPosition,
+            &[(HEIGHT - 1) as u8, ((HEIGHT - 1) >> 8) as u8, 0x00, 0x00],
+        )?;
+
+        self.interface.cmd_with_data(
+            spi,
+            Command::BorderWaveformControl,
+            &[self.border.unwrap_or(0x05)],
+        )?;
+
+        // internal temperature sensor
+        self.interface
+            .cmd_with_data(spi, Command::TemperatureSensorSelection, &[0x80])?;
+
+        self.set_ram_counter(spi)?;
+        self.wait_until_idle(spi, delay)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd1in54b<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    fn update_color_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+        chromatic: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_achromatic_frame(spi, delay, black)?;
+        self.update_chromatic_frame(spi, delay, chromatic)
+    }
+
+    /// Update only the black/white data of the display, a set bit is white
+    fn update_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(black.len(), NUM_DISPLAY_BITS as usize)?;
+        self.wait_until_idle(spi, delay)?;
+        self.set_ram_counter(spi)?;
+        self.interface.cmd_with_data(spi, Command::WriteRam, black)
+    }
+
+    /// Update only the chromatic data of the display, a set bit is red
+    fn update_chromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        chromatic: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(chromatic.len(), NUM_DISPLAY_BITS as usize)?;
+        self.wait_until_idle(spi, delay)?;
+        self.set_ram_counter(spi)?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam2, chromatic)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd1in54b<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    type DisplayColor = TriColor;
+
+    fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new_color(busy, dc, rst, pwr, delay_us);
+        let color = DEFAULT_BACKGROUND_COLOR;
+
+        let mut epd = Epd1in54b {
+            interface,
+            color,
+            border: None,
+        };
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.interface
+            .cmd_with_data(spi, Command::DeepSleepMode, &[0x01])?;
+        self.interface.power_off()
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.init(spi, delay)
+    }
+
+    fn set_background_color(&mut self, color: TriColor) {
+        self.color = color;
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
+    fn background_color(&self) -> &TriColor {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_achromatic_frame(spi, delay, buffer)?;
+
+        // Clear the chromatic layer
+        self.interface.cmd(spi, Command::WriteRam2)?;
+        self.interface.data_x_times(spi, 0x00, NUM_DISPLAY_BITS)
+    }
+
+    fn update_partial_frame(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+        _buffer: &[u8],
+        _x: u32,
+        _y: u32,
+        _width: u32,
+        _height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::Unsupported)
+    }
+
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xF7])?;
+        self.interface.cmd(spi, Command::MasterActivation)?;
+        self.wait_until_idle(spi, delay)
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.set_ram_counter(spi)?;
+
+        let (black, chromatic) = match self.color {
+            TriColor::White => (0xFF, 0x00),
+            TriColor::Black => (0x00, 0x00),
+            TriColor::Chromatic => (0xFF, 0xFF),
+        };
+
+        self.interface.cmd(spi, Command::WriteRam)?;
+        self.interface.data_x_times(spi, black, NUM_DISPLAY_BITS)?;
+
+        self.interface.cmd(spi, Command::WriteRam2)?;
+        self.interface
+            .data_x_times(spi, chromatic, NUM_DISPLAY_BITS)
+    }
+
+    fn set_border(&mut self, spi: &mut SPI, border: BorderColor) -> Result<(), Error<SPI::Error>> {
+        let border = ssd_border(border, true)?;
+        self.border = Some(border);
+        self.interface
+            .cmd_with_data(spi, Command::BorderWaveformControl, &[border])
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd1in54b<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Moves the counters to the first byte, the y address counts down from the last line
+    fn set_ram_counter(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .cmd_with_data(spi, Command::SetRamXAddressCounter, &[0x00])?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamYAddressCounter,
+            &[(HEIGHT - 1) as u8, ((HEIGHT - 1) >> 8) as u8],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interface::mock::expect_commands;
+    use crate::NoPin;
+    use embedded_hal_mock::eh1::{
+        delay::NoopDelay,
+        digital::{Mock as PinMock, State, Transaction as PinTransaction},
+        spi::Mock as SpiMock,
+    };
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 200);
+        assert_eq!(HEIGHT, 200);
+        assert_eq!(NUM_DISPLAY_BITS, 5000);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, TriColor::White);
+    }
+
+    #[test]
+    fn init_sequence() {
+        let (spi_expectations, dc_expectations) = expect_commands(&[
+            (0x12, &[]),
+            (0x01, &[0xC7, 0x00, 0x01]),
+            (0x11, &[0x01]),
+            // the first column is at x address 0
+            (0x44, &[0x00, 0x18]),
+            (0x45, &[0xC7, 0x00, 0x00, 0x00]),
+            (0x3C, &[0x05]),
+            (0x18, &[0x80]),
+            (0x4E, &[0x00]),
+            (0x4F, &[0xC7, 0x00]),
+        ]);
+        let mut spi = SpiMock::new(&spi_expectations);
+        // not busy before and after the software reset and at the end of the init
+        let mut busy = PinMock::new(&[
+            PinTransaction::get(State::Low),
+            PinTransaction::get(State::Low),
+            PinTransaction::get(State::Low),
+        ]);
+        let mut dc = PinMock::new(&dc_expectations);
+        let mut rst = PinMock::new(&[
+            PinTransaction::set(State::High),
+            PinTransaction::set(State::Low),
+            PinTransaction::set(State::High),
+        ]);
+
+        let epd = Epd1in54b::new(
+            &mut spi,
+            busy.clone(),
+            dc.clone(),
+            Some(rst.clone()),
+            None::<NoPin>,
+            &mut NoopDelay::new(),
+            None,
+        )
+        .unwrap();
+        drop(epd);
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
+
+    #[test]
+    fn init_without_reset_pin() {
+        // the software reset of the init takes over the reset
+        let (spi_expectations, dc_expectations) = expect_commands(&[
+            (0x12, &[]),
+            (0x01, &[0xC7, 0x00, 0x01]),
+            (0x11, &[0x01]),
+            (0x44, &[0x00, 0x18]),
+            (0x45, &[0xC7, 0x00, 0x00, 0x00]),
+            (0x3C, &[0x05]),
+            (0x18, &[0x80]),
+            (0x4E, &[0x00]),
+            (0x4F, &[0xC7, 0x00]),
+        ]);
+        let mut spi = SpiMock::new(&spi_expectations);
+        let mut busy = PinMock::new(&[
+            PinTransaction::get(State::Low),
+            PinTransaction::get(State::Low),
+            PinTransaction::get(State::Low),
+        ]);
+        let mut dc = PinMock::new(&dc_expectations);
+
+        let epd = Epd1in54b::new(
+            &mut spi,
+            busy.clone(),
+            dc.clone(),
+            None::<NoPin>,
+            None::<NoPin>,
+            &mut NoopDelay::new(),
+            None,
+        )
+        .unwrap();
+        drop(epd);
+
+        spi.done();
+        busy.done();
+        dc.done();
+    }
+}
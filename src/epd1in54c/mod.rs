@@ -1,60 +1,69 @@
 //! A simple Driver for the Waveshare 1.54" (C) E-Ink Display via SPI
-
+//!
+//! The panel shows black, white and yellow, [`TriColor::Chromatic`] is yellow.
+//!
+//! The panel itself shows yellow for a cleared bit of the chromatic plane. By default the
+//! chromatic buffer is expected the way a [`Display1in54c`] draws it, a set bit is yellow.
+//! For inverted artwork use [`Epd1in54c::set_color_rendering`] with
+//! [`DisplayColorRendering::Negative`].
+
+use crate::error::Error;
 use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
 
 use crate::interface::DisplayInterface;
-use crate::traits::{
-    InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
-};
+use crate::traits::{InternalWiAdditions, WaveshareDisplay, WaveshareThreeColorDisplay};
 
 /// Width of epd1in54 in pixels
 pub const WIDTH: u32 = 152;
 /// Height of epd1in54 in pixels
 pub const HEIGHT: u32 = 152;
 /// Default Background Color (white)
-pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+pub const DEFAULT_BACKGROUND_COLOR: TriColor = TriColor::White;
 const IS_BUSY_LOW: bool = true;
 const NUM_DISPLAY_BITS: u32 = WIDTH / 8 * HEIGHT;
 const SINGLE_BYTE_WRITE: bool = true;
 
-use crate::color::Color;
+use crate::color::{DisplayColorRendering, TriColor};
 
 pub(crate) mod command;
 use self::command::Command;
 use crate::buffer_len;
 
 /// Full size buffer for use with the 1in54c EPD
-/// TODO this should be a TriColor, but let's keep it as is at first
 #[cfg(feature = "graphics")]
 pub type Display1in54c = crate::graphics::Display<
     WIDTH,
     HEIGHT,
     false,
-    { buffer_len(WIDTH as usize, HEIGHT as usize) },
-    Color,
+    { buffer_len(WIDTH as usize, HEIGHT as usize * 2) },
+    TriColor,
 >;
 
 /// Epd1in54c driver
-pub struct Epd1in54c<SPI, BUSY, DC, RST, DELAY> {
-    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
-    color: Color,
+pub struct Epd1in54c<SPI, BUSY, DC, RST, DELAY, PWR> {
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    color: TriColor,
+    rendering: DisplayColorRendering,
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
-    for Epd1in54c<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd1in54c<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         // Based on Reference Program Code from:
         // https://www.waveshare.com/w/upload/a/ac/1.54inch_e-Paper_Module_C_Specification.pdf
         // and:
         // https://github.com/waveshare/e-Paper/blob/master/STM32/STM32-F103ZET6/User/e-Paper/EPD_1in54c.c
-        self.interface.reset(delay, 10_000, 2_000);
+        self.interface.reset(delay, 10_000, 2_000)?;
+        self.interface
+            .uc_software_reset(spi, delay, Command::PanelSetting, 0x0F, IS_BUSY_LOW)?;
 
         // start the booster
         self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x17])?;
@@ -76,13 +85,14 @@ where
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>
-    for Epd1in54c<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd1in54c<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     fn update_color_frame(
@@ -91,7 +101,7 @@ where
         delay: &mut DELAY,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_achromatic_frame(spi, delay, black)?;
         self.update_chromatic_frame(spi, delay, chromatic)
     }
@@ -101,73 +111,105 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         black: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.cmd_with_data(spi, Command::DataStartTransmission1, black)?;
 
         Ok(())
     }
 
+    /// Update only the chromatic data of the display.
+    ///
+    /// The buffer is interpreted according to the [`DisplayColorRendering`] set with
+    /// [`Epd1in54c::set_color_rendering`].
     fn update_chromatic_frame(
         &mut self,
         spi: &mut SPI,
         delay: &mut DELAY,
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
-        self.cmd_with_data(spi, Command::DataStartTransmission2, chromatic)?;
+        match self.rendering {
+            DisplayColorRendering::Positive => {
+                self.command(spi, Command::DataStartTransmission2)?;
+                for byte in chromatic {
+                    self.send_data(spi, &[!byte])?;
+                }
+            }
+            DisplayColorRendering::Negative => {
+                self.cmd_with_data(spi, Command::DataStartTransmission2, chromatic)?;
+            }
+        }
 
         Ok(())
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
-    for Epd1in54c<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd1in54c<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    type DisplayColor = Color;
+    type DisplayColor = TriColor;
     fn new(
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new_color(busy, dc, rst, pwr, delay_us);
         let color = DEFAULT_BACKGROUND_COLOR;
 
-        let mut epd = Epd1in54c { interface, color };
+        let mut epd = Epd1in54c {
+            interface,
+            color,
+            rendering: DisplayColorRendering::default(),
+        };
 
         epd.init(spi, delay)?;
 
         Ok(epd)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
 
         self.command(spi, Command::PowerOff)?;
         self.wait_until_idle(spi, delay)?;
         self.cmd_with_data(spi, Command::DeepSleep, &[0xa5])?;
 
-        Ok(())
+        self.interface.power_off()
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.init(spi, delay)
     }
 
-    fn set_background_color(&mut self, color: Color) {
+    fn set_background_color(&mut self, color: TriColor) {
         self.color = color;
     }
 
-    fn background_color(&self) -> &Color {
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
+    fn background_color(&self) -> &TriColor {
         &self.color
     }
 
@@ -184,14 +226,12 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_achromatic_frame(spi, delay, buffer)?;
 
         // Clear the chromatic layer
-        let color = self.color.get_byte_value();
-
         self.command(spi, Command::DataStartTransmission2)?;
-        self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)?;
+        self.interface.data_x_times(spi, 0xFF, NUM_DISPLAY_BITS)?;
 
         Ok(())
     }
@@ -206,15 +246,13 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
-        unimplemented!()
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::Unsupported)
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.command(spi, Command::DisplayRefresh)?;
-        self.wait_until_idle(spi, delay)?;
-
-        Ok(())
+        self.wait_until_idle(spi, delay)
     }
 
     fn update_and_display_frame(
@@ -222,56 +260,68 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
 
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
-        let color = DEFAULT_BACKGROUND_COLOR.get_byte_value();
+        let (black, chromatic) = match self.color {
+            TriColor::White => (0xFF, 0xFF),
+            TriColor::Black => (0x00, 0xFF),
+            TriColor::Chromatic => (0xFF, 0x00),
+        };
 
         // Clear the black
         self.command(spi, Command::DataStartTransmission1)?;
-        self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)?;
+        self.interface.data_x_times(spi, black, NUM_DISPLAY_BITS)?;
 
         // Clear the chromatic
         self.command(spi, Command::DataStartTransmission2)?;
-        self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)?;
+        self.interface
+            .data_x_times(spi, chromatic, NUM_DISPLAY_BITS)?;
 
         Ok(())
     }
 
-    fn set_lut(
+    fn wait_until_idle(
         &mut self,
         _spi: &mut SPI,
-        _delay: &mut DELAY,
-        _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
-        Ok(())
-    }
-
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
-        Ok(())
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> Epd1in54c<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd1in54c<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    /// Sets how the buffers passed to
+    /// [`update_chromatic_frame`](WaveshareThreeColorDisplay::update_chromatic_frame) and
+    /// [`update_color_frame`](WaveshareThreeColorDisplay::update_color_frame) encode yellow
+    pub fn set_color_rendering(&mut self, rendering: DisplayColorRendering) {
+        self.rendering = rendering;
+    }
+
+    /// Returns the current [`DisplayColorRendering`]
+    pub fn color_rendering(&self) -> DisplayColorRendering {
+        self.rendering
+    }
+
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
         self.interface.data(spi, data)
     }
 
@@ -280,11 +330,11 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
-    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
         let w = self.width();
         let h = self.height();
 
@@ -304,3 +354,15 @@ where
         self.send_data(spi, &[h as u8])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 152);
+        assert_eq!(HEIGHT, 152);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, TriColor::White);
+    }
+}
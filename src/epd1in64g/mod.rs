@@ -0,0 +1,348 @@
+//! A simple Driver for the Waveshare 1.64" (G) square 4-Color E-Ink Display via SPI
+//!
+//! The panel shows black, white, yellow and red. Each pixel takes 2 bits of the buffer
+//! (see [`QuadColor`]), the whole frame is written at once into a single RAM.
+//!
+//! It uses the same controller as the [`epd2in36g`](crate::epd2in36g). The 168 pixel wide rows
+//! take 42 bytes each.
+//!
+//! # References
+//!
+//! - [Waveshare Wiki](https://www.waveshare.com/wiki/1.64inch_e-Paper_Module_(G))
+//! - [Waveshare C driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_1in64g.c)
+//!
+//! # Example for the 1.64" G E-Ink Display
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), epd_waveshare::error::Error<embedded_hal::spi::ErrorKind>> {
+//!use embedded_graphics::{prelude::*, primitives::{Line, PrimitiveStyle}};
+//!use epd_waveshare::{epd1in64g::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = digital::Mock::new(&expectations);
+//!# let dc = digital::Mock::new(&expectations);
+//!# let rst = digital::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
+//!
+//!// Setup EPD
+//!let mut epd = Epd1in64g::new(&mut spi, busy_in, dc, Some(rst), None::<NoPin>, &mut delay, None)?;
+//!
+//!// Use display graphics from embedded-graphics
+//!let mut display = Display1in64g::default();
+//!display.clear(QuadColor::White);
+//!
+//!// A red and a yellow line in the same frame
+//!let _ = Line::new(Point::new(0, 20), Point::new(0, 100))
+//!    .into_styled(PrimitiveStyle::with_stroke(QuadColor::Red, 1))
+//!    .draw(&mut display);
+//!let _ = Line::new(Point::new(15, 20), Point::new(15, 100))
+//!    .into_styled(PrimitiveStyle::with_stroke(QuadColor::Yellow, 1))
+//!    .draw(&mut display);
+//!
+//!// Display updated frame
+//!epd.update_and_display_frame(&mut spi, display.buffer(), &mut delay)?;
+//!
+//!// Set the EPD to sleep
+//!epd.sleep(&mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+
+use crate::error::Error;
+use embedded_hal::{
+    delay::DelayNs,
+    digital::{InputPin, OutputPin},
+    spi::SpiDevice,
+};
+
+use crate::color::QuadColor;
+use crate::interface::DisplayInterface;
+use crate::traits::{InternalWiAdditions, WaveshareDisplay};
+
+use crate::type_g::command::Command;
+use crate::{buffer_len, check_buffer_len};
+
+/// Width of the display
+pub const WIDTH: u32 = 168;
+/// Height of the display
+pub const HEIGHT: u32 = 168;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: QuadColor = QuadColor::White;
+/// Number of bytes of a full frame at 2 bits per pixel
+const NUM_DISPLAY_BYTES: u32 = WIDTH / 4 * HEIGHT;
+const IS_BUSY_LOW: bool = true;
+const SINGLE_BYTE_WRITE: bool = true;
+
+/// Full size buffer for use with the 1in64g EPD
+#[cfg(feature = "graphics")]
+pub type Display1in64g = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize * 2, HEIGHT as usize) },
+    QuadColor,
+>;
+
+/// Epd1in64g driver
+pub struct Epd1in64g<SPI, BUSY, DC, RST, DELAY, PWR> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    color: QuadColor,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd1in64g<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, 20_000, 2_000)?;
+        self.interface
+            .uc_software_reset(spi, delay, Command::PanelSetting, 0x4F, IS_BUSY_LOW)?;
+        self.wait_until_idle(spi, delay)?;
+
+        self.cmd_with_data(
+            spi,
+            Command::Undocumented66,
+            &[0x49, 0x55, 0x13, 0x5D, 0x05, 0x10],
+        )?;
+        self.cmd_with_data(spi, Command::UndocumentedB0, &[0x00])?;
+        self.cmd_with_data(spi, Command::PowerSetting, &[0x0F, 0x00])?;
+        self.cmd_with_data(spi, Command::PanelSetting, &[0x4F, 0x6B])?;
+        self.cmd_with_data(spi, Command::BoosterSoftStart, &[0xD7, 0xDE, 0x12])?;
+        self.send_resolution(spi)?;
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x37])?;
+        self.cmd_with_data(spi, Command::TconSetting, &[0x0C, 0x05])?;
+        self.cmd_with_data(spi, Command::PowerSaving, &[0xFF])?;
+        self.cmd_with_data(spi, Command::Undocumented84, &[0x00])?;
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd1in64g<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    type DisplayColor = QuadColor;
+
+    fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new_color(busy, dc, rst, pwr, delay_us);
+        let color = DEFAULT_BACKGROUND_COLOR;
+
+        let mut epd = Epd1in64g { interface, color };
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.cmd_with_data(spi, Command::PowerOff, &[0x00])?;
+        self.wait_until_idle(spi, delay)?;
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
+        self.interface.power_off()
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.init(spi, delay)
+    }
+
+    fn set_background_color(&mut self, color: QuadColor) {
+        self.color = color;
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
+    fn background_color(&self) -> &QuadColor {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer.len(), NUM_DISPLAY_BYTES as usize)?;
+        self.start_frame(spi, delay)?;
+        self.cmd_with_data(spi, Command::DataStartTransmission, buffer)?;
+        self.end_frame(spi)
+    }
+
+    fn update_partial_frame(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+        _buffer: &[u8],
+        _x: u32,
+        _y: u32,
+        _width: u32,
+        _height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::Unsupported)
+    }
+
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.cmd_with_data(spi, Command::DisplayRefresh, &[0x00])?;
+        self.wait_until_idle(spi, delay)
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.start_frame(spi, delay)?;
+        self.command(spi, Command::DataStartTransmission)?;
+        self.interface
+            .data_x_times(spi, self.color.get_byte_value(), NUM_DISPLAY_BYTES)?;
+        self.end_frame(spi)
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd1in64g<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    /// Enables the RAM write and powers on the panel
+    fn start_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.cmd_with_data(spi, Command::Undocumented68, &[0x01])?;
+        self.command(spi, Command::PowerOn)?;
+        self.wait_until_idle(spi, delay)
+    }
+
+    fn end_frame(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        self.cmd_with_data(spi, Command::Undocumented68, &[0x00])
+    }
+
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        self.cmd_with_data(
+            spi,
+            Command::TconResolution,
+            &[
+                (WIDTH >> 8) as u8,
+                WIDTH as u8,
+                (HEIGHT >> 8) as u8,
+                HEIGHT as u8,
+            ],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 168);
+        assert_eq!(HEIGHT, 168);
+        assert_eq!(NUM_DISPLAY_BYTES, 7_056);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, QuadColor::White);
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn four_colors_in_one_frame() {
+        use embedded_graphics::{pixelcolor::raw::RawU2, prelude::*};
+
+        let mut display = Display1in64g::default();
+        assert_eq!(display.buffer().len(), NUM_DISPLAY_BYTES as usize);
+        display.clear(QuadColor::White).unwrap();
+        assert_eq!(display.buffer()[0], 0b01010101);
+
+        Pixel(Point::new(0, 0), QuadColor::Black)
+            .draw(&mut display)
+            .unwrap();
+        Pixel(Point::new(1, 0), QuadColor::Yellow)
+            .draw(&mut display)
+            .unwrap();
+        Pixel(Point::new(2, 0), QuadColor::Red)
+            .draw(&mut display)
+            .unwrap();
+        assert_eq!(display.buffer()[0], 0b00101101);
+        assert_eq!(display.buffer()[1], 0b01010101);
+
+        // the last pixel of the first line
+        Pixel(Point::new(WIDTH as i32 - 1, 0), QuadColor::Red)
+            .draw(&mut display)
+            .unwrap();
+        assert_eq!(display.buffer()[41], 0b01010111);
+        assert_eq!(display.buffer()[42], 0b01010101);
+
+        assert_eq!(QuadColor::from(RawU2::new(0b10)), QuadColor::Yellow);
+    }
+}
@@ -0,0 +1,77 @@
+use core::marker::PhantomData;
+use embedded_hal::{delay::*, digital::*, i2c::I2c};
+
+/// I2C address of the command register
+const ADDRESS_COMMAND: u8 = 0x3C;
+/// I2C address of the data register
+const ADDRESS_DATA: u8 = 0x3D;
+
+/// The connection interface of the 1.9" segment display
+///
+/// Unlike the SPI [`DisplayInterface`](crate::interface::DisplayInterface) the commands and the
+/// data are written to two I2C addresses.
+pub(crate) struct I2cInterface<I2C, BUSY, RST, DELAY> {
+    /// I2C
+    _i2c: PhantomData<I2C>,
+    /// DELAY
+    _delay: PhantomData<DELAY>,
+    /// Low for busy, Wait until display is ready!
+    busy: BUSY,
+    /// Pin for Resetting
+    rst: RST,
+    /// number of us the idle loop should sleep on
+    delay_us: u32,
+}
+
+impl<I2C, BUSY, RST, DELAY> I2cInterface<I2C, BUSY, RST, DELAY>
+where
+    I2C: I2c,
+    BUSY: InputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Creates a new `I2cInterface` struct
+    ///
+    /// If no delay is given, a default delay of 1ms is used.
+    pub fn new(busy: BUSY, rst: RST, delay_us: Option<u32>) -> Self {
+        I2cInterface {
+            _i2c: PhantomData,
+            _delay: PhantomData,
+            busy,
+            rst,
+            delay_us: delay_us.unwrap_or(1_000),
+        }
+    }
+
+    /// Sends a command, the parameters of this controller are commands as well
+    pub(crate) fn cmd(&mut self, i2c: &mut I2C, command: u8) -> Result<(), I2C::Error> {
+        i2c.write(ADDRESS_COMMAND, &[command])
+    }
+
+    /// Sends data bytes, one write per byte
+    pub(crate) fn data(&mut self, i2c: &mut I2C, data: &[u8]) -> Result<(), I2C::Error> {
+        for byte in data {
+            i2c.write(ADDRESS_DATA, &[*byte])?;
+        }
+        Ok(())
+    }
+
+    /// Waits until device isn't busy anymore (busy == LOW)
+    pub(crate) fn wait_until_idle(&mut self, delay: &mut DELAY) {
+        while self.busy.is_low().unwrap_or(false) {
+            if self.delay_us > 0 {
+                delay.delay_us(self.delay_us);
+            }
+        }
+    }
+
+    /// Resets the device
+    pub(crate) fn reset(&mut self, delay: &mut DELAY) {
+        let _ = self.rst.set_high();
+        delay.delay_ms(200);
+        let _ = self.rst.set_low();
+        delay.delay_ms(20);
+        let _ = self.rst.set_high();
+        delay.delay_ms(200);
+    }
+}
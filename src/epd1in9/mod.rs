@@ -0,0 +1,390 @@
+//! A driver for the Waveshare 1.9" segment E-Ink Display via I2C
+//!
+//! This panel has no pixels: it shows a temperature (up to 199.9 with °C or °F) and a humidity
+//! (up to 99.9 %) with seven segment digits. It is connected via I2C instead of SPI and doesn't
+//! implement [`WaveshareDisplay`](crate::traits::WaveshareDisplay).
+//!
+//! The waveform depends on the ambient temperature, set it with
+//! [`set_ambient_temperature`](Epd1in9::set_ambient_temperature) when it changes by a few degrees.
+//!
+//! # References
+//!
+//! - [Waveshare Wiki](https://www.waveshare.com/wiki/1.9inch_Segment_e-Paper_Module)
+//! - [Waveshare C driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_1in9.c)
+//!
+//! # Example for the 1.9" segment E-Ink Display
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), embedded_hal::i2c::ErrorKind> {
+//!use epd_waveshare::epd1in9::*;
+//!#
+//!# let expectations = [];
+//!# let mut i2c = i2c::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = digital::Mock::new(&expectations);
+//!# let rst = digital::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
+//!
+//!// Setup EPD
+//!let mut epd = Epd1in9::new(&mut i2c, busy_in, rst, &mut delay, None)?;
+//!
+//!let mut segments = Segments::default();
+//!segments.set_temperature(23.4);
+//!segments.set_humidity(56.7);
+//!epd.display(&mut i2c, &mut delay, &segments)?;
+//!
+//!// Set the EPD to sleep
+//!epd.sleep(&mut i2c, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+
+use embedded_hal::{delay::*, digital::*, i2c::I2c};
+
+mod interface;
+use self::interface::I2cInterface;
+
+/// Number of bytes of the segment RAM
+pub const NUM_SEGMENT_BYTES: usize = 15;
+/// Number of seven segment digits, see [`set_digit`](Segments::set_digit)
+pub const NUM_DIGITS: usize = 6;
+
+/// Segment bytes of the digits 0 to 9
+const DIGITS: [[u8; 2]; 10] = [
+    [0xBF, 0x1F],
+    [0x00, 0x1F],
+    [0xFD, 0x17],
+    [0xF5, 0x1F],
+    [0x47, 0x1F],
+    [0xF7, 0x1D],
+    [0xFF, 0x1D],
+    [0x21, 0x1F],
+    [0xFF, 0x1F],
+    [0xF7, 0x1F],
+];
+
+/// Byte of the leading "1" of a temperature of 100 or more
+const TEMPERATURE_HUNDREDS: usize = 0;
+/// Digits of the temperature: tens, ones and tenths
+const TEMPERATURE_DIGITS: [usize; 3] = [0, 1, 5];
+/// Digits of the humidity: tens, ones and tenths
+const HUMIDITY_DIGITS: [usize; 3] = [2, 3, 4];
+/// Byte and bit of the temperature's decimal point
+const TEMPERATURE_POINT: (usize, u8) = (4, 0x20);
+/// Byte and bit of the humidity's decimal point
+const HUMIDITY_POINT: (usize, u8) = (8, 0x20);
+/// Byte and bit of the % sign
+const PERCENT: (usize, u8) = (10, 0x20);
+/// Byte of the temperature unit
+const UNIT: usize = 13;
+
+/// Unit shown next to the temperature
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TemperatureUnit {
+    /// °C
+    #[default]
+    Celsius,
+    /// °F
+    Fahrenheit,
+}
+
+impl TemperatureUnit {
+    fn segments(self) -> u8 {
+        match self {
+            TemperatureUnit::Celsius => 0x05,
+            TemperatureUnit::Fahrenheit => 0x06,
+        }
+    }
+}
+
+/// Content of the segment RAM, a set bit is black
+///
+/// It takes the role of the frame buffer of the pixel displays and is sent with
+/// [`display`](Epd1in9::display).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segments {
+    segments: [u8; NUM_SEGMENT_BYTES],
+    unit: TemperatureUnit,
+}
+
+impl Default for Segments {
+    fn default() -> Self {
+        Segments {
+            segments: [0; NUM_SEGMENT_BYTES],
+            unit: TemperatureUnit::default(),
+        }
+    }
+}
+
+impl Segments {
+    /// The segment RAM as sent by [`display`](Epd1in9::display)
+    pub fn as_bytes(&self) -> &[u8; NUM_SEGMENT_BYTES] {
+        &self.segments
+    }
+
+    /// Sets the raw segment RAM, a set bit is black
+    pub fn set_bytes(&mut self, segments: [u8; NUM_SEGMENT_BYTES]) {
+        self.segments = segments;
+    }
+
+    /// Clears all segments to white
+    pub fn clear(&mut self) {
+        self.segments = [0; NUM_SEGMENT_BYTES];
+    }
+
+    /// Shows a digit (0-9) or nothing (`None`) at `position`
+    ///
+    /// The positions are the temperature tens and ones (0, 1), the humidity tens, ones and
+    /// tenths (2, 3, 4) and the temperature tenths (5).
+    pub fn set_digit(&mut self, position: usize, value: Option<u8>) {
+        assert!(position < NUM_DIGITS);
+        let [high, low] = match value {
+            Some(value) => {
+                assert!(value < 10);
+                DIGITS[value as usize]
+            }
+            None => [0, 0],
+        };
+        let byte = 1 + position * 2;
+        self.segments[byte] = high;
+        // keep the decimal points and the % sign
+        self.segments[byte + 1] = (self.segments[byte + 1] & 0x20) | low;
+    }
+
+    /// Sets the unit shown by [`set_temperature`](Segments::set_temperature)
+    pub fn set_temperature_unit(&mut self, unit: TemperatureUnit) {
+        self.unit = unit;
+    }
+
+    /// Shows a temperature from 0.0 to 199.9 with one decimal
+    pub fn set_temperature(&mut self, value: f32) {
+        let tenths = to_tenths(value, 1999);
+        self.segments[TEMPERATURE_HUNDREDS] = if tenths >= 1000 { 0xFF } else { 0x00 };
+        self.set_number(TEMPERATURE_DIGITS, tenths % 1000, tenths >= 1000);
+        self.segments[TEMPERATURE_POINT.0] |= TEMPERATURE_POINT.1;
+        self.segments[UNIT] = self.unit.segments();
+    }
+
+    /// Shows a humidity from 0.0 to 99.9 % with one decimal
+    pub fn set_humidity(&mut self, value: f32) {
+        let tenths = to_tenths(value, 999);
+        self.set_number(HUMIDITY_DIGITS, tenths, false);
+        self.segments[HUMIDITY_POINT.0] |= HUMIDITY_POINT.1;
+        self.segments[PERCENT.0] |= PERCENT.1;
+    }
+
+    /// Shows `tenths` (below 1000) on the tens, ones and tenths digits
+    ///
+    /// A leading zero is only shown after the hundreds.
+    fn set_number(&mut self, digits: [usize; 3], tenths: u32, hundreds: bool) {
+        let tens = tenths / 100;
+        let leading = if tens == 0 && !hundreds {
+            None
+        } else {
+            Some(tens as u8)
+        };
+        self.set_digit(digits[0], leading);
+        self.set_digit(digits[1], Some((tenths / 10 % 10) as u8));
+        self.set_digit(digits[2], Some((tenths % 10) as u8));
+    }
+}
+
+/// Epd1in9 driver
+pub struct Epd1in9<I2C, BUSY, RST, DELAY> {
+    /// Connection Interface
+    interface: I2cInterface<I2C, BUSY, RST, DELAY>,
+    /// Ambient temperature in °C, selects the waveform
+    ambient_temperature: f32,
+}
+
+impl<I2C, BUSY, RST, DELAY> Epd1in9<I2C, BUSY, RST, DELAY>
+where
+    I2C: I2c,
+    BUSY: InputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Creates the driver and initialises the display with the waveform for 20 °C
+    pub fn new(
+        i2c: &mut I2C,
+        busy: BUSY,
+        rst: RST,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, I2C::Error> {
+        let interface = I2cInterface::new(busy, rst, delay_us);
+
+        let mut epd = Epd1in9 {
+            interface,
+            ambient_temperature: 20.0,
+        };
+
+        epd.init(i2c, delay)?;
+
+        Ok(epd)
+    }
+
+    fn init(&mut self, i2c: &mut I2C, delay: &mut DELAY) -> Result<(), I2C::Error> {
+        self.interface.reset(delay);
+        delay.delay_ms(100);
+
+        // power on
+        self.interface.cmd(i2c, 0x2B)?;
+        delay.delay_ms(10);
+        // booster on, temperature sensor on
+        self.interface.cmd(i2c, 0xA7)?;
+        self.interface.cmd(i2c, 0xE0)?;
+        delay.delay_ms(10);
+
+        self.send_waveform(i2c, delay)
+    }
+
+    /// Wakes the display up from `sleep`
+    pub fn wake_up(&mut self, i2c: &mut I2C, delay: &mut DELAY) -> Result<(), I2C::Error> {
+        self.init(i2c, delay)
+    }
+
+    /// Powers the display off and lets it enter the deep sleep
+    pub fn sleep(&mut self, i2c: &mut I2C, delay: &mut DELAY) -> Result<(), I2C::Error> {
+        self.interface.cmd(i2c, 0x28)?;
+        self.interface.wait_until_idle(delay);
+        self.interface.cmd(i2c, 0xAD)
+    }
+
+    /// Selects the waveform for the ambient temperature in °C
+    pub fn set_ambient_temperature(
+        &mut self,
+        i2c: &mut I2C,
+        delay: &mut DELAY,
+        celsius: f32,
+    ) -> Result<(), I2C::Error> {
+        self.ambient_temperature = celsius;
+        self.send_waveform(i2c, delay)
+    }
+
+    fn send_waveform(&mut self, i2c: &mut I2C, delay: &mut DELAY) -> Result<(), I2C::Error> {
+        let (waveform, frame_time) = waveform(self.ambient_temperature);
+        for command in [waveform, 0x81, 0xB4] {
+            self.interface.cmd(i2c, command)?;
+        }
+        self.interface.wait_until_idle(delay);
+        self.interface.cmd(i2c, 0xE7)?;
+        self.interface.cmd(i2c, frame_time)
+    }
+
+    /// Writes the segments to the display and refreshes it
+    pub fn display(
+        &mut self,
+        i2c: &mut I2C,
+        delay: &mut DELAY,
+        segments: &Segments,
+    ) -> Result<(), I2C::Error> {
+        // reconnect the temperature sensor, write from RAM address 0 to the first RAM
+        for command in [0xAC, 0x2B, 0x40, 0xA9, 0xA8] {
+            self.interface.cmd(i2c, command)?;
+        }
+        self.interface.data(i2c, segments.as_bytes())?;
+        self.interface.data(i2c, &[0x00])?;
+        // second RAM, display on
+        for command in [0xAB, 0xAA, 0xAF] {
+            self.interface.cmd(i2c, command)?;
+        }
+        self.interface.wait_until_idle(delay);
+        // display off, high voltage off, temperature sensor on
+        for command in [0xAE, 0x28, 0xAD] {
+            self.interface.cmd(i2c, command)?;
+        }
+        Ok(())
+    }
+}
+
+/// Rounds to tenths, clamped to `0..=max`
+fn to_tenths(value: f32, max: u32) -> u32 {
+    let tenths = value * 10.0 + 0.5;
+    if tenths <= 0.0 {
+        0
+    } else if tenths >= max as f32 {
+        max
+    } else {
+        tenths as u32
+    }
+}
+
+/// Waveform command and frame time (in 20 ms steps) for an ambient temperature in °C
+fn waveform(celsius: f32) -> (u8, u8) {
+    let waveform = if celsius < 10.0 { 0x7E } else { 0x7B };
+    let frame_time = if celsius < 5.0 {
+        0x31
+    } else if celsius < 10.0 {
+        0x22
+    } else if celsius < 15.0 {
+        0x18
+    } else if celsius < 20.0 {
+        0x13
+    } else {
+        0x0E
+    };
+    (waveform, frame_time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waveform_for_temperature() {
+        assert_eq!(waveform(-3.0), (0x7E, 0x31));
+        assert_eq!(waveform(7.5), (0x7E, 0x22));
+        assert_eq!(waveform(12.0), (0x7B, 0x18));
+        assert_eq!(waveform(19.9), (0x7B, 0x13));
+        assert_eq!(waveform(25.0), (0x7B, 0x0E));
+    }
+
+    #[test]
+    fn rounding() {
+        assert_eq!(to_tenths(23.44, 1999), 234);
+        assert_eq!(to_tenths(23.46, 1999), 235);
+        assert_eq!(to_tenths(-5.0, 1999), 0);
+        assert_eq!(to_tenths(250.0, 1999), 1999);
+    }
+
+    #[test]
+    fn temperature_segments() {
+        let mut segments = Segments::default();
+        segments.set_temperature(23.4);
+        let s = segments.as_bytes();
+        assert_eq!(s[0], 0x00);
+        assert_eq!(s[1..3], DIGITS[2]);
+        assert_eq!(s[3], DIGITS[3][0]);
+        assert_eq!(s[4], DIGITS[3][1] | 0x20);
+        assert_eq!(s[11..13], DIGITS[4]);
+        assert_eq!(s[13], 0x05);
+
+        segments.set_temperature_unit(TemperatureUnit::Fahrenheit);
+        segments.set_temperature(104.0);
+        let s = segments.as_bytes();
+        assert_eq!(s[0], 0xFF);
+        assert_eq!(s[1..3], DIGITS[0]);
+        assert_eq!(s[13], 0x06);
+    }
+
+    #[test]
+    fn humidity_segments() {
+        let mut segments = Segments::default();
+        segments.set_humidity(5.6);
+        let s = segments.as_bytes();
+        // no leading zero
+        assert_eq!(s[5..7], [0x00, 0x00]);
+        assert_eq!(s[7], DIGITS[5][0]);
+        assert_eq!(s[8], DIGITS[5][1] | 0x20);
+        assert_eq!(s[9], DIGITS[6][0]);
+        assert_eq!(s[10], DIGITS[6][1] | 0x20);
+    }
+
+    #[test]
+    #[should_panic]
+    fn digit_out_of_range() {
+        Segments::default().set_digit(0, Some(10));
+    }
+}
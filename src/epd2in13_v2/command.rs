@@ -201,54 +201,13 @@ pub enum DeepSleepMode {
 
 pub(crate) struct GateDrivingVoltage(pub u8);
 pub(crate) struct SourceDrivingVoltage(pub u8);
-pub(crate) struct Vcom(pub u8);
 
 pub(crate) trait I32Ext {
-    fn vcom(self) -> Vcom;
     fn gate_driving_decivolt(self) -> GateDrivingVoltage;
     fn source_driving_decivolt(self) -> SourceDrivingVoltage;
 }
 
 impl I32Ext for i32 {
-    // This is really not very nice. Until I find something better, this will be
-    // a placeholder.
-    fn vcom(self) -> Vcom {
-        assert!((-30..=-2).contains(&self));
-        let u = match -self {
-            2 => 0x08,
-            3 => 0x0B,
-            4 => 0x10,
-            5 => 0x14,
-            6 => 0x17,
-            7 => 0x1B,
-            8 => 0x20,
-            9 => 0x24,
-            10 => 0x28,
-            11 => 0x2C,
-            12 => 0x2F,
-            13 => 0x34,
-            14 => 0x37,
-            15 => 0x3C,
-            16 => 0x40,
-            17 => 0x44,
-            18 => 0x48,
-            19 => 0x4B,
-            20 => 0x50,
-            21 => 0x54,
-            22 => 0x58,
-            23 => 0x5B,
-            24 => 0x5F,
-            25 => 0x64,
-            26 => 0x68,
-            27 => 0x6C,
-            28 => 0x6F,
-            29 => 0x73,
-            30 => 0x78,
-            _ => 0,
-        };
-        Vcom(u)
-    }
-
     fn gate_driving_decivolt(self) -> GateDrivingVoltage {
         assert!((100..=210).contains(&self) && self % 5 == 0);
         GateDrivingVoltage(((self - 100) / 5 + 0x03) as u8)
@@ -14,23 +14,50 @@
 //! - [Waveshare Python driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd2in9b_V3.py)
 //! - [Controller Datasheet SS1780](http://www.e-paper-display.com/download_detail/downloadsId=682.html)
 //!
+//! # Differential updates
+//!
+//! A quick refresh only drives the pixels which differ between the red RAM, the old frame, and the
+//! black and white RAM, the new frame. [`Epd2in13::display_partial_diff`] writes both and refreshes
+//! in the right order, [`DiffDisplay2in13`] additionally remembers the shown frame, so that only
+//! the new one has to be drawn.
+//!
+//! # Quick LUT
+//!
+//! Some batches of the panel keep faint ghosts with the built-in quick waveform. A longer one can
+//! be supplied with [`Epd2in13::set_quick_lut`], it's used by every following quick refresh and
+//! kept over [`wake_up`](WaveshareDisplay::wake_up).
+//!
+//! # VCOM
+//!
+//! The driver uses -2.1 V for full and -0.9 V for quick refreshes. The optimal VCOM of a panel is
+//! printed on its flex cable and can be set for both with [`Epd2in13::set_vcom`].
+//!
+//! # Border
+//!
+//! By default the border follows the waveform of the refresh mode, so the quick refreshes
+//! change its shade. [`set_border`](WaveshareDisplay::set_border) drives it to a fixed color in
+//! both modes.
 
+use crate::error::Error;
 use embedded_hal::{
     delay::DelayNs,
     digital::{InputPin, OutputPin},
     spi::SpiDevice,
 };
 
-use crate::buffer_len;
 use crate::color::Color;
 use crate::interface::DisplayInterface;
-use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::traits::{
+    check_refresh_lut, ssd_border, ssd_vcom, BorderColor, InternalWiAdditions, RefreshLut,
+    WaveshareDisplay,
+};
+use crate::{buffer_len, check_buffer_len, check_partial_window};
 
 pub(crate) mod command;
 use self::command::{
     BorderWaveForm, BorderWaveFormFixLevel, BorderWaveFormGs, BorderWaveFormVbd, Command,
     DataEntryModeDir, DataEntryModeIncr, DeepSleepMode, DisplayUpdateControl2, DriverOutput,
-    GateDrivingVoltage, I32Ext, SourceDrivingVoltage, Vcom,
+    GateDrivingVoltage, I32Ext, SourceDrivingVoltage,
 };
 
 pub(crate) mod constants;
@@ -55,49 +82,132 @@ pub type Display2in13 = crate::graphics::Display<
     Color,
 >;
 
+/// Full size buffer which remembers the frame shown on the 2in13 v2 and v3 EPD
+///
+/// Draw into [`frame_mut`](Self::frame_mut) and show the changes with
+/// [`display_diff`](Self::display_diff), which hands the shown and the new
+/// frame to [`Epd2in13::display_partial_diff`].
+#[cfg(feature = "graphics")]
+pub struct DiffDisplay2in13 {
+    frame: Display2in13,
+    shown: [u8; buffer_len(WIDTH as usize, HEIGHT as usize)],
+}
+
+#[cfg(feature = "graphics")]
+impl DiffDisplay2in13 {
+    /// Wraps `frame`, which has to be what the panel currently shows, e.g. the
+    /// frame of the last full refresh
+    pub fn new(frame: Display2in13) -> Self {
+        let mut shown = [0; buffer_len(WIDTH as usize, HEIGHT as usize)];
+        shown.copy_from_slice(frame.buffer());
+        DiffDisplay2in13 { frame, shown }
+    }
+
+    /// The frame to draw into
+    pub fn frame_mut(&mut self) -> &mut Display2in13 {
+        &mut self.frame
+    }
+
+    /// The frame to draw into
+    pub fn frame(&self) -> &Display2in13 {
+        &self.frame
+    }
+
+    /// The frame the panel shows
+    pub fn shown(&self) -> &[u8] {
+        &self.shown
+    }
+
+    /// Shows the changes since the last call with a quick refresh of the whole
+    /// frame
+    pub fn display_diff<SPI, BUSY, DC, RST, DELAY, PWR>(
+        &mut self,
+        epd: &mut Epd2in13<SPI, BUSY, DC, RST, DELAY, PWR>,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>
+    where
+        SPI: SpiDevice,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        PWR: OutputPin,
+        DELAY: DelayNs,
+    {
+        epd.display_partial_diff(
+            spi,
+            delay,
+            &self.shown,
+            self.frame.buffer(),
+            0,
+            0,
+            RAM_WIDTH,
+            HEIGHT,
+        )?;
+        self.shown.copy_from_slice(self.frame.buffer());
+        Ok(())
+    }
+}
+
 /// Width of the display.
 pub const WIDTH: u32 = 122;
 
 /// Height of the display
 pub const HEIGHT: u32 = 250;
 
+/// Width of the RAM, the last byte of each line is only partly visible
+const RAM_WIDTH: u32 = (WIDTH + 7) / 8 * 8;
+
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 const IS_BUSY_LOW: bool = false;
 const SINGLE_BYTE_WRITE: bool = true;
+/// Length of a LUT for [`Epd2in13::set_quick_lut`], 70 bytes for the V2 and 159 for the V3
+pub const QUICK_LUT_LEN: usize = LUT_PARTIAL_UPDATE.len();
 
 /// Epd2in13 (V2 & V3) driver
 ///
 /// To use this driver for V2 of the display, feature \"epd2in13_v3\" needs to be disabled and feature \"epd2in13_v2\" enabled.
-pub struct Epd2in13<SPI, BUSY, DC, RST, DELAY> {
+pub struct Epd2in13<SPI, BUSY, DC, RST, DELAY, PWR> {
     /// Connection Interface
-    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
 
     sleep_mode: DeepSleepMode,
 
     /// Background Color
     background_color: Color,
     refresh: RefreshLut,
+    /// Replaces `LUT_PARTIAL_UPDATE` if set
+    quick_lut: Option<[u8; QUICK_LUT_LEN]>,
+    /// VCOM register value of [`set_vcom`](Self::set_vcom), replaces the default of each mode
+    vcom: Option<u8>,
+    /// Border waveform of `set_border`, replaces the default of each mode
+    border: Option<u8>,
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
-    for Epd2in13<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in13<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         // HW reset
-        self.interface.reset(delay, 10_000, 10_000);
+        self.interface.reset(delay, 10_000, 10_000)?;
 
         if self.refresh == RefreshLut::Quick {
-            self.set_vcom_register(spi, (-9).vcom())?;
+            self.interface
+                .ssd_software_reset(spi, delay, Command::SwReset, IS_BUSY_LOW)?;
+
+            // -0.9 V
+            self.set_vcom_register(spi, self.vcom.unwrap_or(0x24))?;
             self.wait_until_idle(spi, delay)?;
 
-            self.set_lut(spi, delay, Some(self.refresh))?;
+            self.load_lut(spi)?;
 
             // Python code does this, not sure why
             // self.cmd_with_data(spi, Command::WriteOtpSelection, &[0, 0, 0, 0, 0x40, 0, 0])?;
@@ -153,7 +263,8 @@ where
                 },
             )?;
 
-            self.set_vcom_register(spi, (-21).vcom())?;
+            // -2.1 V
+            self.set_vcom_register(spi, self.vcom.unwrap_or(0x54))?;
 
             self.set_gate_driving_voltage(spi, 190.gate_driving_decivolt())?;
             self.set_source_driving_voltage(
@@ -165,48 +276,57 @@ where
 
             self.set_gate_line_width(spi, 10)?;
 
-            self.set_lut(spi, delay, Some(self.refresh))?;
+            self.load_lut(spi)?;
         }
 
-        self.wait_until_idle(spi, delay)?;
-        Ok(())
+        self.wait_until_idle(spi, delay)
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
-    for Epd2in13<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in13<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     type DisplayColor = Color;
+    const REFRESH_LUTS: &'static [RefreshLut] = &[
+        RefreshLut::Full,
+        RefreshLut::Quick,
+        RefreshLut::PartialRefresh,
+    ];
     fn new(
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
+    ) -> Result<Self, Error<SPI::Error>> {
         let mut epd = Epd2in13 {
-            interface: DisplayInterface::new(busy, dc, rst, delay_us),
+            interface: DisplayInterface::new(busy, dc, rst, pwr, delay_us),
             sleep_mode: DeepSleepMode::Mode1,
             background_color: DEFAULT_BACKGROUND_COLOR,
             refresh: RefreshLut::Full,
+            quick_lut: None,
+            vcom: None,
+            border: None,
         };
 
         epd.init(spi, delay)?;
         Ok(epd)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
 
         // All sample code enables and disables analog/clocks...
@@ -221,7 +341,7 @@ where
         self.command(spi, Command::MasterActivation)?;
 
         self.set_sleep_mode(spi, self.sleep_mode)?;
-        Ok(())
+        self.interface.power_off()
     }
 
     fn update_frame(
@@ -229,8 +349,8 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
-        assert!(buffer.len() == buffer_len(WIDTH as usize, HEIGHT as usize));
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer.len(), buffer_len(WIDTH as usize, HEIGHT as usize))?;
         self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
         self.set_ram_address_counters(spi, delay, 0, 0)?;
 
@@ -247,8 +367,10 @@ where
     }
 
     /// Updating only a part of the frame is not supported when using the
-    /// partial refresh feature. The function will panic if called when set to
+    /// partial refresh feature. The function fails with `Error::Unsupported` if called when set to
     /// use partial refresh.
+    ///
+    /// `x` and `width` must be multiples of 8, the RAM is addressed in bytes.
     fn update_partial_frame(
         &mut self,
         spi: &mut SPI,
@@ -258,8 +380,8 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
-        assert!((width * height / 8) as usize == buffer.len());
+    ) -> Result<(), Error<SPI::Error>> {
+        check_partial_window(buffer.len(), x, y, width, height, RAM_WIDTH, HEIGHT)?;
 
         // This should not be used when doing partial refresh. The RAM_RED must
         // be updated with the last buffer having been displayed. Doing partial
@@ -267,16 +389,18 @@ where
         // RAM content). Using this function will most probably make the actual
         // display incorrect as the controler will compare with something
         // incorrect.
-        assert!(self.refresh == RefreshLut::Full);
+        if self.refresh != RefreshLut::Full {
+            return Err(Error::Unsupported);
+        }
 
-        self.set_ram_area(spi, x, y, x + width, y + height)?;
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)?;
         self.set_ram_address_counters(spi, delay, x, y)?;
 
         self.cmd_with_data(spi, Command::WriteRam, buffer)?;
 
         if self.refresh == RefreshLut::Full {
             // Always keep the base buffer equals to current if not doing partial refresh.
-            self.set_ram_area(spi, x, y, x + width, y + height)?;
+            self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)?;
             self.set_ram_address_counters(spi, delay, x, y)?;
 
             self.cmd_with_data(spi, Command::WriteRamRed, buffer)?;
@@ -287,7 +411,7 @@ where
 
     /// Never use directly this function when using partial refresh, or also
     /// keep the base buffer in syncd using `set_partial_base_buffer` function.
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         if self.refresh == RefreshLut::Full {
             self.set_display_update_control_2(
                 spi,
@@ -302,9 +426,7 @@ where
             self.set_display_update_control_2(spi, DisplayUpdateControl2::new().display())?;
         }
         self.command(spi, Command::MasterActivation)?;
-        self.wait_until_idle(spi, delay)?;
-
-        Ok(())
+        self.wait_until_idle(spi, delay)
     }
 
     fn update_and_display_frame(
@@ -312,7 +434,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
 
@@ -322,7 +444,7 @@ where
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         let color = self.background_color.get_byte_value();
 
         self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
@@ -354,6 +476,32 @@ where
         self.background_color = background_color;
     }
 
+    /// Replaces the default of both the full and the quick mode, it is written again whenever the
+    /// mode changes
+    fn set_vcom(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        millivolts: i16,
+    ) -> Result<(), Error<SPI::Error>> {
+        let vcom = ssd_vcom(millivolts)?;
+        self.vcom = Some(vcom);
+        self.wait_until_idle(spi, delay)?;
+        self.set_vcom_register(spi, vcom)
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
     fn background_color(&self) -> &Color {
         &self.background_color
     }
@@ -369,29 +517,38 @@ where
     fn set_lut(
         &mut self,
         spi: &mut SPI,
-        _delay: &mut DELAY,
+        delay: &mut DELAY,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
-        let buffer = match refresh_rate {
-            Some(RefreshLut::Full) | None => &LUT_FULL_UPDATE,
-            Some(RefreshLut::Quick | RefreshLut::PartialRefresh) => &LUT_PARTIAL_UPDATE,
-        };
+    ) -> Result<(), Error<SPI::Error>> {
+        check_refresh_lut(refresh_rate, Self::REFRESH_LUTS)?;
+        match refresh_rate {
+            Some(refresh) if refresh != self.refresh => self.set_refresh(spi, delay, refresh),
+            _ => self.load_lut(spi),
+        }
+    }
 
-        self.cmd_with_data(spi, Command::WriteLutRegister, buffer)
+    fn set_border(&mut self, spi: &mut SPI, border: BorderColor) -> Result<(), Error<SPI::Error>> {
+        let border = ssd_border(border, false)?;
+        self.border = Some(border);
+        self.cmd_with_data(spi, Command::BorderWaveformControl, &[border])
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
-        Ok(())
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> Epd2in13<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd2in13<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     /// When using partial refresh, the controller uses the provided buffer for
@@ -401,8 +558,8 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         buffer: &[u8],
-    ) -> Result<(), SPI::Error> {
-        assert!(buffer_len(WIDTH as usize, HEIGHT as usize) == buffer.len());
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer.len(), buffer_len(WIDTH as usize, HEIGHT as usize))?;
         self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
         self.set_ram_address_counters(spi, delay, 0, 0)?;
 
@@ -410,6 +567,58 @@ where
         Ok(())
     }
 
+    /// Shows a window with a quick refresh which only drives the pixels that
+    /// changed from `old` to `new`
+    ///
+    /// Writes `old` into the red RAM, which the controller compares with,
+    /// `new` into the black and white RAM, refreshes and finally writes `new`
+    /// into the red RAM as the base of the next diff. `old` has to be what the
+    /// panel currently shows in this window. Switches to `RefreshLut::Quick`
+    /// with [`set_refresh`](Self::set_refresh) first if needed.
+    ///
+    /// The slices must have the same length, which must match the window.
+    /// `x` and `width` must be multiples of 8, other values fail with `Error::OutOfBounds`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn display_partial_diff(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        old: &[u8],
+        new: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(old.len(), new.len())?;
+        check_partial_window(new.len(), x, y, width, height, RAM_WIDTH, HEIGHT)?;
+        self.set_refresh(spi, delay, RefreshLut::Quick)?;
+
+        self.write_window(spi, delay, Command::WriteRamRed, old, x, y, width, height)?;
+        self.write_window(spi, delay, Command::WriteRam, new, x, y, width, height)?;
+        self.display_frame(spi, delay)?;
+        self.write_window(spi, delay, Command::WriteRamRed, new, x, y, width, height)
+    }
+
+    /// Replaces the built-in quick LUT with `lut` of [`QUICK_LUT_LEN`] bytes
+    ///
+    /// It's loaded in quick mode by the next [`set_lut`](WaveshareDisplay::set_lut),
+    /// [`set_refresh`](Self::set_refresh) or [`wake_up`](WaveshareDisplay::wake_up). Fails with
+    /// `Error::InvalidBufferLength` if the length doesn't match.
+    pub fn set_quick_lut(&mut self, lut: &[u8]) -> Result<(), Error<SPI::Error>> {
+        let lut = lut.try_into().map_err(|_| Error::InvalidBufferLength {
+            expected: QUICK_LUT_LEN,
+            got: lut.len(),
+        })?;
+        self.quick_lut = Some(lut);
+        Ok(())
+    }
+
+    /// Goes back to the built-in quick LUT, from the next `set_lut` on
+    pub fn reset_quick_lut(&mut self) {
+        self.quick_lut = None;
+    }
+
     /// Selects which sleep mode will be used when triggering the deep sleep.
     pub fn set_deep_sleep_mode(&mut self, mode: DeepSleepMode) {
         self.sleep_mode = mode;
@@ -417,12 +626,14 @@ where
 
     /// Sets the refresh mode. When changing mode, the screen will be
     /// re-initialized accordingly.
+    ///
+    /// Same as [`set_lut`](WaveshareDisplay::set_lut) with `Some(refresh)`.
     pub fn set_refresh(
         &mut self,
         spi: &mut SPI,
         delay: &mut DELAY,
         refresh: RefreshLut,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         if self.refresh != refresh {
             self.refresh = refresh;
             self.init(spi, delay)?;
@@ -430,11 +641,40 @@ where
         Ok(())
     }
 
+    /// Writes the LUT of the current refresh mode
+    fn load_lut(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        let buffer = match self.refresh {
+            RefreshLut::Full => LUT_FULL_UPDATE,
+            RefreshLut::Quick | RefreshLut::PartialRefresh => {
+                self.quick_lut.unwrap_or(LUT_PARTIAL_UPDATE)
+            }
+        };
+
+        self.cmd_with_data(spi, Command::WriteLutRegister, &buffer)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn write_window(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        command: Command,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)?;
+        self.set_ram_address_counters(spi, delay, x, y)?;
+        self.cmd_with_data(spi, command, buffer)
+    }
+
     fn set_gate_scan_start_position(
         &mut self,
         spi: &mut SPI,
         start: u16,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         assert!(start <= 295);
         self.cmd_with_data(
             spi,
@@ -443,27 +683,26 @@ where
         )
     }
 
+    /// Writes the border of [`set_border`](WaveshareDisplay::set_border), `default` if it
+    /// wasn't called
     fn set_border_waveform(
         &mut self,
         spi: &mut SPI,
-        borderwaveform: BorderWaveForm,
-    ) -> Result<(), SPI::Error> {
-        self.cmd_with_data(
-            spi,
-            Command::BorderWaveformControl,
-            &[borderwaveform.to_u8()],
-        )
+        default: BorderWaveForm,
+    ) -> Result<(), Error<SPI::Error>> {
+        let border = self.border.unwrap_or(default.to_u8());
+        self.cmd_with_data(spi, Command::BorderWaveformControl, &[border])
     }
 
-    fn set_vcom_register(&mut self, spi: &mut SPI, vcom: Vcom) -> Result<(), SPI::Error> {
-        self.cmd_with_data(spi, Command::WriteVcomRegister, &[vcom.0])
+    fn set_vcom_register(&mut self, spi: &mut SPI, vcom: u8) -> Result<(), Error<SPI::Error>> {
+        self.cmd_with_data(spi, Command::WriteVcomRegister, &[vcom])
     }
 
     fn set_gate_driving_voltage(
         &mut self,
         spi: &mut SPI,
         voltage: GateDrivingVoltage,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(spi, Command::GateDrivingVoltageCtrl, &[voltage.0])
     }
 
@@ -471,12 +710,12 @@ where
         &mut self,
         spi: &mut SPI,
         number_of_lines: u8,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         assert!(number_of_lines <= 127);
         self.cmd_with_data(spi, Command::SetDummyLinePeriod, &[number_of_lines])
     }
 
-    fn set_gate_line_width(&mut self, spi: &mut SPI, width: u8) -> Result<(), SPI::Error> {
+    fn set_gate_line_width(&mut self, spi: &mut SPI, width: u8) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(spi, Command::SetGateLineWidth, &[width & 0x0F])
     }
 
@@ -487,7 +726,7 @@ where
         vsh1: SourceDrivingVoltage,
         vsh2: SourceDrivingVoltage,
         vsl: SourceDrivingVoltage,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(
             spi,
             Command::SourceDrivingVoltageCtrl,
@@ -501,16 +740,24 @@ where
         &mut self,
         spi: &mut SPI,
         value: DisplayUpdateControl2,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[value.0])
     }
 
     /// Triggers the deep sleep mode
-    fn set_sleep_mode(&mut self, spi: &mut SPI, mode: DeepSleepMode) -> Result<(), SPI::Error> {
+    fn set_sleep_mode(
+        &mut self,
+        spi: &mut SPI,
+        mode: DeepSleepMode,
+    ) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(spi, Command::DeepSleepMode, &[mode as u8])
     }
 
-    fn set_driver_output(&mut self, spi: &mut SPI, output: DriverOutput) -> Result<(), SPI::Error> {
+    fn set_driver_output(
+        &mut self,
+        spi: &mut SPI,
+        output: DriverOutput,
+    ) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(spi, Command::DriverOutputControl, &output.to_bytes())
     }
 
@@ -521,7 +768,7 @@ where
         spi: &mut SPI,
         counter_incr_mode: DataEntryModeIncr,
         counter_direction: DataEntryModeDir,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         let mode = counter_incr_mode as u8 | counter_direction as u8;
         self.cmd_with_data(spi, Command::DataEntryModeSetting, &[mode])
     }
@@ -534,7 +781,7 @@ where
         start_y: u32,
         end_x: u32,
         end_y: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(
             spi,
             Command::SetRamXAddressStartEndPosition,
@@ -560,7 +807,7 @@ where
         delay: &mut DELAY,
         x: u32,
         y: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.cmd_with_data(spi, Command::SetRamXAddressCounter, &[(x >> 3) as u8])?;
 
@@ -572,7 +819,7 @@ where
         Ok(())
     }
 
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
@@ -581,14 +828,63 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    extern crate std;
     use super::*;
+    use crate::interface::mock::expect_commands;
+    use crate::NoPin;
+    use embedded_hal_mock::eh1::{
+        delay::NoopDelay,
+        digital::{Mock as PinMock, State, Transaction as PinTransaction},
+        spi::Mock as SpiMock,
+    };
+    use std::{vec, vec::Vec};
+
+    /// Runs `f` on a driver in quick refresh mode which expects `commands`
+    /// and `busy_reads` idle reads of BUSY
+    fn with_quick_epd(
+        commands: &[(u8, &[u8])],
+        busy_reads: usize,
+        f: impl FnOnce(
+            &mut Epd2in13<SpiMock<u8>, PinMock, PinMock, PinMock, NoopDelay, NoPin>,
+            &mut SpiMock<u8>,
+        ),
+    ) {
+        let (spi_expectations, dc_expectations) = expect_commands(commands);
+        let mut spi = SpiMock::new(&spi_expectations);
+        let mut busy = PinMock::new(&vec![PinTransaction::get(State::Low); busy_reads]);
+        let mut dc = PinMock::new(&dc_expectations);
+        let mut rst = PinMock::new(&[]);
+
+        let mut epd = Epd2in13 {
+            interface: DisplayInterface::new(
+                busy.clone(),
+                dc.clone(),
+                Some(rst.clone()),
+                None::<NoPin>,
+                None,
+            ),
+            sleep_mode: DeepSleepMode::Mode1,
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Quick,
+            quick_lut: None,
+            vcom: None,
+            border: None,
+        };
+        f(&mut epd, &mut spi);
+        drop(epd);
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
 
     #[test]
     fn epd_size() {
@@ -596,4 +892,194 @@ mod tests {
         assert_eq!(HEIGHT, 250);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn quick_lut_override() {
+        let lut = [0x11; QUICK_LUT_LEN];
+        let commands: [(u8, &[u8]); 2] = [(0x32, &lut), (0x32, &LUT_PARTIAL_UPDATE)];
+        with_quick_epd(&commands, 0, |epd, spi| {
+            let delay = &mut NoopDelay::new();
+            epd.set_quick_lut(&lut).unwrap();
+            epd.set_lut(spi, delay, Some(RefreshLut::Quick)).unwrap();
+            epd.reset_quick_lut();
+            epd.set_lut(spi, delay, None).unwrap();
+        });
+    }
+
+    #[test]
+    fn quick_lut_of_the_wrong_length() {
+        with_quick_epd(&[], 0, |epd, _spi| {
+            assert_eq!(
+                epd.set_quick_lut(&[0; QUICK_LUT_LEN - 1]),
+                Err(Error::InvalidBufferLength {
+                    expected: QUICK_LUT_LEN,
+                    got: QUICK_LUT_LEN - 1
+                })
+            );
+        });
+    }
+
+    #[test]
+    fn vcom_in_millivolts() {
+        // -1.48 V is rounded to the -1.4 V of the table
+        with_quick_epd(&[(0x2C, &[0x37])], 1, |epd, spi| {
+            let delay = &mut NoopDelay::new();
+            epd.set_vcom(spi, delay, -1480).unwrap();
+            assert_eq!(epd.set_vcom(spi, delay, -100), Err(Error::Unsupported));
+            assert_eq!(epd.vcom, Some(0x37));
+        });
+    }
+
+    #[test]
+    fn border_replaces_the_default_waveform() {
+        with_quick_epd(&[(0x3C, &[0x60]), (0x3C, &[0x60])], 0, |epd, spi| {
+            epd.set_border(spi, BorderColor::White).unwrap();
+            assert_eq!(
+                epd.set_border(spi, BorderColor::Chromatic),
+                Err(Error::Unsupported)
+            );
+            epd.set_border_waveform(
+                spi,
+                BorderWaveForm {
+                    vbd: BorderWaveFormVbd::Gs,
+                    fix_level: BorderWaveFormFixLevel::Vss,
+                    gs_trans: BorderWaveFormGs::Lut1,
+                },
+            )
+            .unwrap();
+        });
+    }
+
+    #[test]
+    fn partial_diff_writes_old_then_new() {
+        let old = [0xFF; 2];
+        let new = [0x0F; 2];
+        let window: [(u8, &[u8]); 3] = [
+            (0x44, &[0x0F, 0x0F]),
+            (0x45, &[0x0A, 0x00, 0x0B, 0x00]),
+            (0x4E, &[0x0F]),
+        ];
+        let mut commands = Vec::new();
+        for (ram, buffer) in [(0x26, &old), (0x24, &new)] {
+            commands.extend_from_slice(&window);
+            commands.push((0x4F, &[0x0A, 0x00][..]));
+            commands.push((ram, &buffer[..]));
+        }
+        commands.push((0x22, &[0x04]));
+        commands.push((0x20, &[]));
+        commands.extend_from_slice(&window);
+        commands.push((0x4F, &[0x0A, 0x00]));
+        commands.push((0x26, &new));
+
+        with_quick_epd(&commands, 4, |epd, spi| {
+            epd.display_partial_diff(spi, &mut NoopDelay::new(), &old, &new, 120, 10, 8, 2)
+                .unwrap();
+        });
+    }
+
+    #[test]
+    fn partial_diff_with_different_lengths() {
+        with_quick_epd(&[], 0, |epd, spi| {
+            let result = epd.display_partial_diff(
+                spi,
+                &mut NoopDelay::new(),
+                &[0xFF; 2],
+                &[0xFF; 4],
+                0,
+                0,
+                8,
+                2,
+            );
+            assert_eq!(
+                result,
+                Err(Error::InvalidBufferLength {
+                    expected: 4,
+                    got: 2
+                })
+            );
+        });
+    }
+
+    #[test]
+    fn partial_diff_with_a_wrong_window() {
+        with_quick_epd(&[], 0, |epd, spi| {
+            let result = epd.display_partial_diff(
+                spi,
+                &mut NoopDelay::new(),
+                &[0xFF; 2],
+                &[0xFF; 2],
+                0,
+                0,
+                16,
+                2,
+            );
+            assert_eq!(
+                result,
+                Err(Error::InvalidBufferLength {
+                    expected: 4,
+                    got: 2
+                })
+            );
+        });
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn diff_display_remembers_the_shown_frame() {
+        use embedded_graphics::{
+            prelude::*,
+            primitives::{Line, PrimitiveStyle},
+        };
+
+        let mut frame = Display2in13::default();
+        frame.clear(Color::White).unwrap();
+        let mut display = DiffDisplay2in13::new(frame);
+        let white = display.shown().to_vec();
+        let _ = Line::new(Point::new(0, 0), Point::new(0, 249))
+            .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
+            .draw(display.frame_mut());
+        let drawn = display.frame().buffer().to_vec();
+        assert_ne!(white, drawn);
+
+        let full_window: [(u8, &[u8]); 4] = [
+            (0x44, &[0x00, 0x0F]),
+            (0x45, &[0x00, 0x00, 0xF9, 0x00]),
+            (0x4E, &[0x00]),
+            (0x4F, &[0x00, 0x00]),
+        ];
+        let mut commands = Vec::new();
+        commands.extend_from_slice(&full_window);
+        commands.push((0x26, &white[..]));
+        commands.extend_from_slice(&full_window);
+        commands.push((0x24, &drawn[..]));
+        commands.push((0x22, &[0x04]));
+        commands.push((0x20, &[]));
+        commands.extend_from_slice(&full_window);
+        commands.push((0x26, &drawn[..]));
+
+        with_quick_epd(&commands, 4, |epd, spi| {
+            display
+                .display_diff(epd, spi, &mut NoopDelay::new())
+                .unwrap();
+        });
+        assert_eq!(display.shown(), &drawn[..]);
+    }
+
+    #[test]
+    fn window_in_the_partly_visible_last_byte() {
+        check_partial_window::<()>(buffer_len(8, 10), 120, 0, 8, 10, RAM_WIDTH, HEIGHT).unwrap();
+    }
+
+    #[test]
+    fn window_at_x_121() {
+        assert_eq!(
+            check_partial_window::<()>(buffer_len(1, 10), 121, 0, 1, 10, RAM_WIDTH, HEIGHT),
+            Err(Error::OutOfBounds {
+                x: 121,
+                y: 0,
+                width: 1,
+                height: 10
+            })
+        );
+    }
 }
@@ -0,0 +1,727 @@
+//! A simple Driver for the Waveshare 2.13" V4 E-Ink Display via SPI
+//!
+//! The V4 uses a SSD1680 class controller with the waveforms in its OTP, so neither the full nor
+//! the quick refresh has to upload a LUT. The quick refresh of [`QuickRefresh`] uses the
+//! controller's built-in partial waveform (display update sequence `0xFF`).
+//!
+//! # References
+//!
+//! - [Waveshare Wiki](https://www.waveshare.com/wiki/2.13inch_e-Paper_HAT_Manual)
+//! - [Waveshare C driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_2in13_V4.c)
+//!
+//! # Example for the 2.13" V4 E-Ink Display
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), epd_waveshare::error::Error<embedded_hal::spi::ErrorKind>> {
+//!use embedded_graphics::{prelude::*, primitives::{Line, PrimitiveStyle}};
+//!use epd_waveshare::{epd2in13_v4::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = digital::Mock::new(&expectations);
+//!# let dc = digital::Mock::new(&expectations);
+//!# let rst = digital::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
+//!
+//!// Setup EPD
+//!let mut epd = Epd2in13::new(&mut spi, busy_in, dc, Some(rst), None::<NoPin>, &mut delay, None)?;
+//!
+//!// Use display graphics from embedded-graphics
+//!let mut display = Display2in13::default();
+//!display.clear(Color::White);
+//!
+//!// Show the base image with a full refresh
+//!epd.update_old_frame(&mut spi, display.buffer(), &mut delay)?;
+//!epd.display_frame(&mut spi, &mut delay)?;
+//!
+//!// Draw a line and show it with a quick refresh
+//!let _ = Line::new(Point::new(0, 120), Point::new(0, 200))
+//!    .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
+//!    .draw(&mut display);
+//!epd.update_and_display_new_frame(&mut spi, display.buffer(), &mut delay)?;
+//!
+//!// Set the EPD to sleep
+//!epd.sleep(&mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+
+use crate::error::Error;
+use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
+
+use crate::color::Color;
+use crate::controllers::ssd1680::{self, command::Command, Panel};
+use crate::interface::DisplayInterface;
+use crate::traits::{
+    check_refresh_lut, ssd_vcom, InternalWiAdditions, QuickRefresh, RefreshLut, WaveshareDisplay,
+};
+use crate::{buffer_len, check_buffer_len};
+
+/// Width of the display
+pub const WIDTH: u32 = 122;
+/// Height of the display
+pub const HEIGHT: u32 = 250;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+const SINGLE_BYTE_WRITE: bool = true;
+/// Temperature which selects the fast waveform of the OTP
+const FAST_TEMPERATURE: i8 = 100;
+
+/// The 122 pixels of a line are padded to 16 bytes
+const PANEL: Panel = Panel {
+    width: WIDTH,
+    height: HEIGHT,
+    x_offset: 0,
+};
+
+/// Full size buffer for use with the 2in13 V4 EPD
+#[cfg(feature = "graphics")]
+pub type Display2in13 = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize, HEIGHT as usize) },
+    Color,
+>;
+
+/// Epd2in13 V4 driver
+pub struct Epd2in13<SPI, BUSY, DC, RST, DELAY, PWR> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    background_color: Color,
+    /// Refresh LUT of `display_frame`
+    refresh: RefreshLut,
+    /// Temperature of [`set_temperature`](WaveshareDisplay::set_temperature), `None` for the
+    /// internal sensor
+    temperature: Option<i8>,
+    /// VCOM register value of [`set_vcom`](WaveshareDisplay::set_vcom), replaces the one of the
+    /// waveforms if set
+    vcom: Option<u8>,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in13<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, 20_000, 2_000)?;
+        ssd1680::software_reset(&mut self.interface, spi, delay)?;
+
+        ssd1680::set_driver_output(&mut self.interface, spi, PANEL)?;
+        self.interface
+            .cmd_with_data(spi, Command::BorderWaveformControl, &[0x05])?;
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl1, &[0x00, 0x80])?;
+
+        // internal temperature sensor
+        self.interface
+            .cmd_with_data(spi, Command::TemperatureSensorSelection, &[0x80])?;
+        self.wait_until_idle(spi, delay)?;
+
+        self.restore_waveform(spi, delay)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in13<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    type DisplayColor = Color;
+    const REFRESH_LUTS: &'static [RefreshLut] = &[
+        RefreshLut::Full,
+        RefreshLut::Quick,
+        RefreshLut::PartialRefresh,
+    ];
+
+    fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(busy, dc, rst, pwr, delay_us);
+
+        let mut epd = Epd2in13 {
+            interface,
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+            temperature: None,
+            vcom: None,
+        };
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        ssd1680::deep_sleep(&mut self.interface, spi, delay)?;
+        self.interface.power_off()
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.init(spi, delay)
+    }
+
+    fn set_background_color(&mut self, background_color: Color) {
+        self.background_color = background_color;
+    }
+
+    /// Replaces the VCOM of the waveforms in the OTP, except for the quick refresh of
+    /// [`QuickRefresh`], which loads its waveform with its VCOM
+    fn set_vcom(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        millivolts: i16,
+    ) -> Result<(), Error<SPI::Error>> {
+        let vcom = ssd_vcom(millivolts)?;
+        self.vcom = Some(vcom);
+        self.wait_until_idle(spi, delay)?;
+        ssd1680::write_vcom(&mut self.interface, spi, vcom)
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(ssd1680::IS_BUSY_LOW)
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.background_color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer.len(), PANEL.buffer_len())?;
+        self.wait_until_idle(spi, delay)?;
+        // a previous quick refresh changed the border waveform
+        self.interface
+            .cmd_with_data(spi, Command::BorderWaveformControl, &[0x05])?;
+        ssd1680::write_frame(
+            &mut self.interface,
+            spi,
+            delay,
+            PANEL,
+            Command::WriteRam,
+            buffer,
+        )
+    }
+
+    /// Writes a window of the frame, `x` and `width` must be multiples of 8
+    fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        ssd1680::write_window(
+            &mut self.interface,
+            spi,
+            delay,
+            PANEL,
+            Command::WriteRam,
+            buffer,
+            x,
+            y,
+            width,
+            height,
+        )
+    }
+
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let sequence = match self.refresh {
+            RefreshLut::Full if self.temperature.is_none() && self.vcom.is_none() => 0xF7,
+            // the quick refresh of `QuickRefresh` loads the sensor and the VCOM of the OTP again
+            RefreshLut::Full => {
+                self.load_waveform(spi, delay)?;
+                0xC7
+            }
+            RefreshLut::Quick | RefreshLut::PartialRefresh => 0xC7,
+        };
+        ssd1680::activate(&mut self.interface, spi, delay, sequence)
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let color = self.background_color.get_byte_value();
+        for ram in [Command::WriteRam, Command::WriteRam2] {
+            ssd1680::fill_frame(&mut self.interface, spi, delay, PANEL, ram, color)?;
+        }
+        Ok(())
+    }
+
+    /// Selects the full (`RefreshLut::Full`) or the vendor's fast full refresh
+    /// (`RefreshLut::Quick`) for `display_frame`
+    fn set_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_refresh_lut(refresh_rate, Self::REFRESH_LUTS)?;
+        if let Some(refresh_lut) = refresh_rate {
+            self.refresh = refresh_lut;
+            self.load_waveform(spi, delay)?;
+        }
+        Ok(())
+    }
+
+    fn read_temperature(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<i8, Error<SPI::Error>> {
+        let celsius = ssd1680::read_temperature(&mut self.interface, spi, delay)?;
+        self.restore_waveform(spi, delay)?;
+        Ok(celsius)
+    }
+
+    fn set_temperature(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        celsius: Option<i8>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.temperature = celsius;
+        self.load_waveform(spi, delay)
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        ssd1680::wait_until_idle(&mut self.interface, delay)?;
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> QuickRefresh<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in13<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Writes the base image into both RAMs, show it with a full `display_frame`.
+    fn update_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        ssd1680::write_frame(
+            &mut self.interface,
+            spi,
+            delay,
+            PANEL,
+            Command::WriteRam2,
+            buffer,
+        )
+    }
+
+    /// Writes the new image, the controller compares it to the old one on refresh.
+    fn update_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer.len(), PANEL.buffer_len())?;
+        self.wait_until_idle(spi, delay)?;
+        self.set_quick_mode(spi)?;
+        ssd1680::write_frame(
+            &mut self.interface,
+            spi,
+            delay,
+            PANEL,
+            Command::WriteRam,
+            buffer,
+        )
+    }
+
+    /// Quick refresh with the built-in partial waveform
+    fn display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        ssd1680::activate(&mut self.interface, spi, delay, 0xFF)
+    }
+
+    fn update_and_display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_new_frame(spi, buffer, delay)?;
+        self.display_new_frame(spi, delay)
+    }
+
+    /// `x` and `width` must be multiples of 8
+    fn update_partial_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        ssd1680::write_window(
+            &mut self.interface,
+            spi,
+            delay,
+            PANEL,
+            Command::WriteRam2,
+            buffer,
+            x,
+            y,
+            width,
+            height,
+        )
+    }
+
+    /// `x` and `width` must be multiples of 8
+    fn update_partial_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.set_quick_mode(spi)?;
+        ssd1680::write_window(
+            &mut self.interface,
+            spi,
+            delay,
+            PANEL,
+            Command::WriteRam,
+            buffer,
+            x,
+            y,
+            width,
+            height,
+        )
+    }
+
+    /// `x` and `width` must be multiples of 8
+    fn clear_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        let color = self.background_color.get_byte_value();
+        for ram in [Command::WriteRam, Command::WriteRam2] {
+            ssd1680::fill_window(
+                &mut self.interface,
+                spi,
+                delay,
+                PANEL,
+                ram,
+                color,
+                x,
+                y,
+                width,
+                height,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd2in13<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Writes a window of the frame to the RAM like
+    /// [`update_partial_frame`](WaveshareDisplay::update_partial_frame), with the data taken
+    /// from an iterator
+    ///
+    /// Exactly `width / 8 * height` bytes are taken, it fails with
+    /// `Error::InvalidBufferLength` if `data` ends early and leaves any further items unconsumed.
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_partial_frame_iter<I: IntoIterator<Item = u8>>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        data: I,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        ssd1680::write_window_iter(
+            &mut self.interface,
+            spi,
+            delay,
+            PANEL,
+            Command::WriteRam,
+            data,
+            x,
+            y,
+            width,
+            height,
+        )
+    }
+
+    /// Registers of the quick refresh, as set by the vendor before each partial update
+    fn set_quick_mode(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .cmd_with_data(spi, Command::BorderWaveformControl, &[0x80])?;
+        ssd1680::set_driver_output(&mut self.interface, spi, PANEL)
+    }
+
+    /// Temperature written instead of the one of the internal sensor, the fast refresh forces
+    /// its own
+    fn waveform_temperature(&self) -> Option<i8> {
+        match self.refresh {
+            RefreshLut::Quick => Some(FAST_TEMPERATURE),
+            _ => self.temperature,
+        }
+    }
+
+    /// Loads the waveform of the refresh mode, from the internal sensor or a forced temperature
+    fn load_waveform(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        if self.waveform_temperature().is_none() {
+            self.wait_until_idle(spi, delay)?;
+            self.interface
+                .cmd_with_data(spi, Command::TemperatureSensorSelection, &[0x80])?;
+            ssd1680::activate(&mut self.interface, spi, delay, 0xB1)?;
+        }
+        self.restore_waveform(spi, delay)
+    }
+
+    /// Loads the waveform of a forced temperature again and writes the VCOM of `set_vcom` over the
+    /// one of the waveform
+    fn restore_waveform(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        if let Some(celsius) = self.waveform_temperature() {
+            ssd1680::write_temperature(&mut self.interface, spi, delay, celsius)?;
+        }
+        if let Some(vcom) = self.vcom {
+            ssd1680::write_vcom(&mut self.interface, spi, vcom)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+    use crate::interface::mock::expect_commands;
+    use crate::NoPin;
+    use embedded_hal_mock::eh1::{
+        delay::NoopDelay,
+        digital::{Mock as PinMock, State, Transaction as PinTransaction},
+        spi::Mock as SpiMock,
+    };
+    use std::{vec, vec::Vec};
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 122);
+        assert_eq!(HEIGHT, 250);
+        assert_eq!(PANEL.line_bytes(), 16);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+
+    #[test]
+    fn misaligned_window() {
+        let mut spi = SpiMock::new(&[]);
+        let mut busy = PinMock::new(&[]);
+        let mut dc = PinMock::new(&[]);
+        let mut rst = PinMock::new(&[]);
+        let mut epd = Epd2in13 {
+            interface: DisplayInterface::new(
+                busy.clone(),
+                dc.clone(),
+                Some(rst.clone()),
+                None::<NoPin>,
+                None,
+            ),
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+            temperature: None,
+            vcom: None,
+        };
+        assert_eq!(
+            epd.clear_partial_frame(&mut spi, &mut NoopDelay::new(), 4, 0, 8, 8),
+            Err(Error::OutOfBounds {
+                x: 4,
+                y: 0,
+                width: 8,
+                height: 8
+            })
+        );
+        drop(epd);
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
+
+    #[test]
+    fn forced_temperature_until_cleared() {
+        let forced: &[(u8, &[u8])] = &[(0x1A, &[0x28, 0x00]), (0x22, &[0x91]), (0x20, &[])];
+        let mut commands = Vec::new();
+        // set_temperature and the full refresh both load the waveform of 40 °C
+        commands.extend_from_slice(forced);
+        commands.extend_from_slice(forced);
+        commands.extend_from_slice(&[(0x22, &[0xC7][..]), (0x20, &[])]);
+        // back to the sensor
+        commands.extend_from_slice(&[
+            (0x18, &[0x80][..]),
+            (0x22, &[0xB1]),
+            (0x20, &[]),
+            (0x22, &[0xF7]),
+            (0x20, &[]),
+        ]);
+        let (spi_expectations, dc_expectations) = expect_commands(&commands);
+        let mut spi = SpiMock::new(&spi_expectations);
+        let mut busy = PinMock::new(&vec![PinTransaction::get(State::Low); 11]);
+        let mut dc = PinMock::new(&dc_expectations);
+        let mut rst = PinMock::new(&[]);
+        let mut delay = NoopDelay::new();
+        let mut epd = Epd2in13 {
+            interface: DisplayInterface::new(
+                busy.clone(),
+                dc.clone(),
+                Some(rst.clone()),
+                None::<NoPin>,
+                None,
+            ),
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+            temperature: None,
+            vcom: None,
+        };
+        epd.set_temperature(&mut spi, &mut delay, Some(40)).unwrap();
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        epd.set_temperature(&mut spi, &mut delay, None).unwrap();
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        drop(epd);
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
+
+    #[test]
+    fn vcom_replaces_the_one_of_the_waveform() {
+        let (spi_expectations, dc_expectations) = expect_commands(&[
+            (0x2C, &[0x3C]),
+            // the full refresh loads the waveform first
+            (0x18, &[0x80]),
+            (0x22, &[0xB1]),
+            (0x20, &[]),
+            (0x2C, &[0x3C]),
+            (0x22, &[0xC7]),
+            (0x20, &[]),
+        ]);
+        let mut spi = SpiMock::new(&spi_expectations);
+        let mut busy = PinMock::new(&vec![PinTransaction::get(State::Low); 6]);
+        let mut dc = PinMock::new(&dc_expectations);
+        let mut rst = PinMock::new(&[]);
+        let mut delay = NoopDelay::new();
+        let mut epd = Epd2in13 {
+            interface: DisplayInterface::new(
+                busy.clone(),
+                dc.clone(),
+                Some(rst.clone()),
+                None::<NoPin>,
+                None,
+            ),
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+            temperature: None,
+            vcom: None,
+        };
+        assert_eq!(
+            epd.set_vcom(&mut spi, &mut delay, -100),
+            Err(Error::Unsupported)
+        );
+        epd.set_vcom(&mut spi, &mut delay, -1500).unwrap();
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        drop(epd);
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
+}
@@ -20,7 +20,7 @@
 //!# let mut delay = delay::NoopDelay::new();
 //!
 //!// Setup EPD
-//!let mut epd = Epd2in13b::new(&mut spi, busy_in, dc, rst, &mut delay, None).unwrap();
+//!let mut epd = Epd2in13b::new(&mut spi, busy_in, dc, Some(rst), None::<NoPin>, &mut delay, None).unwrap();
 //!
 //!// Use display graphics from embedded-graphics
 //!// This display is for the black/white/chromatic pixels
@@ -51,18 +51,20 @@
 //!# }
 //!```
 // Original Waveforms from Waveshare
+use crate::error::Error;
 use embedded_hal::{
     delay::DelayNs,
     digital::{InputPin, OutputPin},
     spi::SpiDevice,
 };
 
-use crate::buffer_len;
 use crate::color::TriColor;
 use crate::interface::DisplayInterface;
 use crate::traits::{
-    InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+    ssd_border, ssd_vcom, BorderColor, InternalWiAdditions, WaveshareDisplay,
+    WaveshareThreeColorDisplay,
 };
+use crate::{buffer_len, check_buffer_len};
 
 pub(crate) mod command;
 use self::command::{
@@ -92,28 +94,35 @@ pub const HEIGHT: u32 = 250;
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: TriColor = TriColor::White;
 const IS_BUSY_LOW: bool = false;
+/// -1.35 V
+const DEFAULT_VCOM: u8 = 0x36;
 
 /// Epd2in13b (V4) driver
-pub struct Epd2in13b<SPI, BUSY, DC, RST, DELAY> {
+pub struct Epd2in13b<SPI, BUSY, DC, RST, DELAY, PWR> {
     /// Connection Interface
-    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
 
     /// Background Color
     background_color: TriColor,
+    /// VCOM register value, see [`set_vcom`](Self::set_vcom)
+    vcom: u8,
+    /// Border waveform of `set_border`, replaces the default if set
+    border: Option<u8>,
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
-    for Epd2in13b<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in13b<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         // HW reset
-        self.interface.reset(delay, 10_000, 10_000);
+        self.interface.reset(delay, 10_000, 10_000)?;
 
         self.wait_until_idle(spi, delay)?;
         self.interface.cmd(spi, Command::SwReset)?;
@@ -132,6 +141,8 @@ where
         self.set_data_entry_mode(spi, DataEntryModeIncr::XIncrYIncr, DataEntryModeDir::XDir)?;
 
         // Use simple X/Y auto increase
+        // The 122 pixels of a line are padded to 16 bytes, the same way the graphics buffer
+        // pads them, so the RAM X range has to cover those full 16 bytes
         self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
         self.set_ram_address_counters(spi, delay, 0, 0)?;
 
@@ -144,7 +155,7 @@ where
             },
         )?;
 
-        self.cmd_with_data(spi, Command::WriteVcomRegister, &[0x36])?;
+        self.cmd_with_data(spi, Command::WriteVcomRegister, &[self.vcom])?;
         self.cmd_with_data(spi, Command::GateDrivingVoltageCtrl, &[0x17])?;
         self.cmd_with_data(spi, Command::SourceDrivingVoltageCtrl, &[0x41, 0x00, 0x32])?;
 
@@ -157,19 +168,18 @@ where
             },
         )?;
 
-        self.wait_until_idle(spi, delay)?;
-
-        Ok(())
+        self.wait_until_idle(spi, delay)
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>
-    for Epd2in13b<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in13b<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     fn update_color_frame(
@@ -178,7 +188,7 @@ where
         delay: &mut DELAY,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_achromatic_frame(spi, delay, black)?;
         self.update_chromatic_frame(spi, delay, chromatic)
     }
@@ -188,7 +198,7 @@ where
         spi: &mut SPI,
         _delay: &mut DELAY,
         black: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, Command::WriteRam)?;
         self.interface.data(spi, black)?;
         Ok(())
@@ -199,20 +209,21 @@ where
         spi: &mut SPI,
         _delay: &mut DELAY,
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, Command::WriteRamRed)?;
         self.interface.data(spi, chromatic)?;
         Ok(())
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
-    for Epd2in13b<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in13b<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     type DisplayColor = TriColor;
@@ -220,26 +231,29 @@ where
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
+    ) -> Result<Self, Error<SPI::Error>> {
         let mut epd = Epd2in13b {
-            interface: DisplayInterface::new(busy, dc, rst, delay_us),
+            interface: DisplayInterface::new_color(busy, dc, rst, pwr, delay_us),
             background_color: DEFAULT_BACKGROUND_COLOR,
+            vcom: DEFAULT_VCOM,
+            border: None,
         };
 
         epd.init(spi, delay)?;
         Ok(epd)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.set_sleep_mode(spi, DeepSleepMode::Normal)?;
-        Ok(())
+    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.set_sleep_mode(spi, DeepSleepMode::Mode1)?;
+        self.interface.power_off()
     }
 
     fn update_frame(
@@ -247,8 +261,8 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
-        assert!(buffer.len() == buffer_len(WIDTH as usize, HEIGHT as usize));
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer.len(), buffer_len(WIDTH as usize, HEIGHT as usize))?;
         self.cmd_with_data(spi, Command::WriteRam, buffer)?;
 
         self.command(spi, Command::WriteRamRed)?;
@@ -269,15 +283,13 @@ where
         _y: u32,
         _width: u32,
         _height: u32,
-    ) -> Result<(), SPI::Error> {
-        unimplemented!();
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::Unsupported)
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.command(spi, Command::MasterActivation)?;
-        self.wait_until_idle(spi, delay)?;
-
-        Ok(())
+        self.wait_until_idle(spi, delay)
     }
 
     fn update_and_display_frame(
@@ -285,13 +297,13 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.clear_achromatic_frame(spi)?;
         self.clear_chromatic_frame(spi)
     }
@@ -300,6 +312,29 @@ where
         self.background_color = background_color;
     }
 
+    fn set_vcom(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        millivolts: i16,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.vcom = ssd_vcom(millivolts)?;
+        self.wait_until_idle(spi, delay)?;
+        self.cmd_with_data(spi, Command::WriteVcomRegister, &[self.vcom])
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
     fn background_color(&self) -> &TriColor {
         &self.background_color
     }
@@ -312,34 +347,35 @@ where
         HEIGHT
     }
 
-    fn set_lut(
-        &mut self,
-        _spi: &mut SPI,
-        _delay: &mut DELAY,
-        _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
-        unimplemented!()
+    fn set_border(&mut self, spi: &mut SPI, border: BorderColor) -> Result<(), Error<SPI::Error>> {
+        let border = ssd_border(border, true)?;
+        self.border = Some(border);
+        self.cmd_with_data(spi, Command::BorderWaveformControl, &[border])
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
-        Ok(())
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> Epd2in13b<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd2in13b<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     fn set_display_update_control(
         &mut self,
         spi: &mut SPI,
         display_update_control: DisplayUpdateControl,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(
             spi,
             Command::DisplayUpdateControl1,
@@ -347,24 +383,31 @@ where
         )
     }
 
+    /// Writes the border of [`set_border`](WaveshareDisplay::set_border), `default` if it
+    /// wasn't called
     fn set_border_waveform(
         &mut self,
         spi: &mut SPI,
-        borderwaveform: BorderWaveForm,
-    ) -> Result<(), SPI::Error> {
-        self.cmd_with_data(
-            spi,
-            Command::BorderWaveformControl,
-            &[borderwaveform.to_u8()],
-        )
+        default: BorderWaveForm,
+    ) -> Result<(), Error<SPI::Error>> {
+        let border = self.border.unwrap_or(default.to_u8());
+        self.cmd_with_data(spi, Command::BorderWaveformControl, &[border])
     }
 
     /// Triggers the deep sleep mode
-    fn set_sleep_mode(&mut self, spi: &mut SPI, mode: DeepSleepMode) -> Result<(), SPI::Error> {
+    fn set_sleep_mode(
+        &mut self,
+        spi: &mut SPI,
+        mode: DeepSleepMode,
+    ) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(spi, Command::DeepSleepMode, &[mode as u8])
     }
 
-    fn set_driver_output(&mut self, spi: &mut SPI, output: DriverOutput) -> Result<(), SPI::Error> {
+    fn set_driver_output(
+        &mut self,
+        spi: &mut SPI,
+        output: DriverOutput,
+    ) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(spi, Command::DriverOutputControl, &output.to_bytes())
     }
 
@@ -375,7 +418,7 @@ where
         spi: &mut SPI,
         counter_incr_mode: DataEntryModeIncr,
         counter_direction: DataEntryModeDir,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         let mode = counter_incr_mode as u8 | counter_direction as u8;
         self.cmd_with_data(spi, Command::DataEntryModeSetting, &[mode])
     }
@@ -388,7 +431,7 @@ where
         start_y: u32,
         end_x: u32,
         end_y: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(
             spi,
             Command::SetRamXAddressStartEndPosition,
@@ -414,7 +457,7 @@ where
         delay: &mut DELAY,
         x: u32,
         y: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.cmd_with_data(spi, Command::SetRamXAddressCounter, &[(x >> 3) as u8])?;
 
@@ -426,7 +469,7 @@ where
         Ok(())
     }
 
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
@@ -435,11 +478,11 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
-    fn clear_achromatic_frame(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn clear_achromatic_frame(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
         match self.background_color {
             TriColor::White => {
                 self.command(spi, Command::WriteRam)?;
@@ -470,10 +513,10 @@ where
         Ok(())
     }
 
-    fn clear_chromatic_frame(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn clear_chromatic_frame(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
         match self.background_color {
             TriColor::White => {
-                self.command(spi, Command::WriteRam)?;
+                self.command(spi, Command::WriteRamRed)?;
                 self.interface.data_x_times(
                     spi,
                     0x00,
@@ -481,7 +524,7 @@ where
                 )?;
             }
             TriColor::Chromatic => {
-                self.command(spi, Command::WriteRam)?;
+                self.command(spi, Command::WriteRamRed)?;
                 self.interface.data_x_times(
                     spi,
                     0xFF,
@@ -489,7 +532,7 @@ where
                 )?;
             }
             TriColor::Black => {
-                self.command(spi, Command::WriteRam)?;
+                self.command(spi, Command::WriteRamRed)?;
                 self.interface.data_x_times(
                     spi,
                     0x00,
@@ -512,4 +555,19 @@ mod tests {
         assert_eq!(HEIGHT, 250);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, TriColor::White);
     }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn buffer_line_padding() {
+        use embedded_graphics_core::prelude::*;
+
+        let mut display = Display2in13b::default();
+        display.set_pixel(Pixel(Point::new(121, 0), TriColor::Chromatic));
+        display.set_pixel(Pixel(Point::new(0, 1), TriColor::White));
+
+        // 16 bytes per line, the last 6 bits of each line are padding
+        assert_eq!(display.bw_buffer().len(), 16 * 250);
+        assert_eq!(display.chromatic_buffer()[15], 0b0100_0000);
+        assert_eq!(display.bw_buffer()[16], 0b1000_0000);
+    }
 }
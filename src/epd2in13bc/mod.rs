@@ -6,7 +6,7 @@
 //!
 //!```rust, no_run
 //!# use embedded_hal_mock::eh1::*;
-//!# fn main() -> Result<(), embedded_hal::spi::ErrorKind> {
+//!# fn main() -> Result<(), epd_waveshare::error::Error<embedded_hal::spi::ErrorKind>> {
 //!use embedded_graphics::{prelude::*, primitives::{Line, PrimitiveStyle, PrimitiveStyleBuilder}};
 //!use epd_waveshare::{epd2in13bc::*, prelude::*};
 //!#
@@ -20,7 +20,7 @@
 //!# let mut delay = delay::NoopDelay::new();
 //!
 //!// Setup EPD
-//!let mut epd = Epd2in13bc::new(&mut spi, busy_in, dc, rst, &mut delay, None)?;
+//!let mut epd = Epd2in13bc::new(&mut spi, busy_in, dc, Some(rst), None::<NoPin>, &mut delay, None)?;
 //!
 //!// Use display graphics from embedded-graphics
 //!// This display is for the black/white/chromatic pixels
@@ -50,11 +50,23 @@
 //!# Ok(())
 //!# }
 //!```
+//!
+//! # Updating only the black plane
+//!
+//! [`update_achromatic_frame_only`](Epd2in13bc::update_achromatic_frame_only) rewrites the black
+//! plane and keeps the chromatic plane in the controller RAM, e.g. for a fixed red header. It
+//! halves the SPI traffic and the chromatic buffer doesn't need to be kept in the MCU RAM. The
+//! panel still runs the full-length three color refresh cycle, that's a limit of the hardware.
+//!
+//! The controller RAM doesn't survive [`sleep`](WaveshareDisplay::sleep), send the chromatic
+//! plane again after waking the display up.
+use crate::error::Error;
 use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
 
 use crate::interface::DisplayInterface;
 use crate::traits::{
-    InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+    uc_border, uc_vcm_dc, BorderColor, InternalWiAdditions, WaveshareDisplay,
+    WaveshareThreeColorDisplay,
 };
 
 /// Width of epd2in13bc in pixels
@@ -68,18 +80,20 @@ pub const DEFAULT_BACKGROUND_COLOR: TriColor = TriColor::White;
 const NUM_DISPLAY_BITS: u32 = WIDTH / 8 * HEIGHT;
 
 const IS_BUSY_LOW: bool = true;
+/// -0.6 V
+const DEFAULT_VCOM: u8 = 0x0A;
 const VCOM_DATA_INTERVAL: u8 = 0x07;
 const WHITE_BORDER: u8 = 0x70;
-const BLACK_BORDER: u8 = 0x30;
-const CHROMATIC_BORDER: u8 = 0xb0;
 const FLOATING_BORDER: u8 = 0xF0;
+/// VCOM and data interval setting until `set_border` is called
+const DEFAULT_VCOM_DATA_INTERVAL: u8 = WHITE_BORDER | VCOM_DATA_INTERVAL;
 const SINGLE_BYTE_WRITE: bool = true;
 
 use crate::color::TriColor;
 
 pub(crate) mod command;
 use self::command::Command;
-use crate::buffer_len;
+use crate::{buffer_len, check_buffer_len};
 
 /// Full size buffer for use with the 2.13" b/c EPD
 #[cfg(feature = "graphics")]
@@ -92,24 +106,32 @@ pub type Display2in13bc = crate::graphics::Display<
 >;
 
 /// Epd2in13bc driver
-pub struct Epd2in13bc<SPI, BUSY, DC, RST, DELAY> {
-    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+pub struct Epd2in13bc<SPI, BUSY, DC, RST, DELAY, PWR> {
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
     color: TriColor,
+    /// VCOM register value, see [`set_vcom`](Self::set_vcom)
+    vcom: u8,
+    /// VCOM and data interval setting, holds the border of
+    /// [`set_border`](WaveshareDisplay::set_border)
+    vcom_data_interval: u8,
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
-    for Epd2in13bc<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in13bc<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         // Values taken from datasheet and sample code
 
-        self.interface.reset(delay, 10_000, 10_000);
+        self.interface.reset(delay, 10_000, 10_000)?;
+        self.interface
+            .uc_software_reset(spi, delay, Command::PanelSetting, 0x8F, IS_BUSY_LOW)?;
 
         // start the booster
         self.interface
@@ -126,27 +148,26 @@ where
         self.cmd_with_data(
             spi,
             Command::VcomAndDataIntervalSetting,
-            &[WHITE_BORDER | VCOM_DATA_INTERVAL],
+            &[self.vcom_data_interval],
         )?;
 
         // set resolution
         self.send_resolution(spi)?;
 
-        self.cmd_with_data(spi, Command::VcmDcSetting, &[0x0A])?;
+        self.cmd_with_data(spi, Command::VcmDcSetting, &[self.vcom])?;
 
-        self.wait_until_idle(spi, delay)?;
-
-        Ok(())
+        self.wait_until_idle(spi, delay)
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>
-    for Epd2in13bc<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in13bc<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     fn update_color_frame(
@@ -155,7 +176,7 @@ where
         delay: &mut DELAY,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_achromatic_frame(spi, delay, black)?;
         self.update_chromatic_frame(spi, delay, chromatic)
     }
@@ -168,7 +189,7 @@ where
         spi: &mut SPI,
         _delay: &mut DELAY,
         black: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
         self.interface.data(spi, black)?;
         Ok(())
@@ -182,22 +203,33 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
         self.interface.data(spi, chromatic)?;
 
-        self.wait_until_idle(spi, delay)?;
-        Ok(())
+        self.wait_until_idle(spi, delay)
+    }
+
+    /// Uses [`update_achromatic_frame_only`](Epd2in13bc::update_achromatic_frame_only)
+    fn update_achromatic_and_display(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_achromatic_frame_only(spi, delay, black)?;
+        self.display_frame(spi, delay)
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
-    for Epd2in13bc<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in13bc<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     type DisplayColor = TriColor;
@@ -205,21 +237,27 @@ where
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new_color(busy, dc, rst, pwr, delay_us);
         let color = DEFAULT_BACKGROUND_COLOR;
 
-        let mut epd = Epd2in13bc { interface, color };
+        let mut epd = Epd2in13bc {
+            interface,
+            color,
+            vcom: DEFAULT_VCOM,
+            vcom_data_interval: DEFAULT_VCOM_DATA_INTERVAL,
+        };
 
         epd.init(spi, delay)?;
 
         Ok(epd)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         // Section 8.2 from datasheet
         self.interface.cmd_with_data(
             spi,
@@ -233,10 +271,10 @@ where
 
         self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
 
-        Ok(())
+        self.interface.power_off()
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.init(spi, delay)
     }
 
@@ -244,6 +282,29 @@ where
         self.color = color;
     }
 
+    fn set_vcom(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        millivolts: i16,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.vcom = uc_vcm_dc(millivolts)?;
+        self.wait_until_idle(spi, delay)?;
+        self.cmd_with_data(spi, Command::VcmDcSetting, &[self.vcom])
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
     fn background_color(&self) -> &TriColor {
         &self.color
     }
@@ -261,7 +322,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
 
         self.interface.data(spi, buffer)?;
@@ -272,8 +333,7 @@ where
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
         self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)?;
 
-        self.wait_until_idle(spi, delay)?;
-        Ok(())
+        self.wait_until_idle(spi, delay)
     }
 
     #[allow(unused)]
@@ -286,15 +346,14 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         Ok(())
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.command(spi, Command::DisplayRefresh)?;
 
-        self.wait_until_idle(spi, delay)?;
-        Ok(())
+        self.wait_until_idle(spi, delay)
     }
 
     fn update_and_display_frame(
@@ -302,13 +361,13 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.send_resolution(spi)?;
 
         let color = DEFAULT_BACKGROUND_COLOR.get_byte_value();
@@ -322,38 +381,41 @@ where
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
         self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)?;
 
-        self.wait_until_idle(spi, delay)?;
-        Ok(())
+        self.wait_until_idle(spi, delay)
     }
 
-    fn set_lut(
-        &mut self,
-        _spi: &mut SPI,
-        _delay: &mut DELAY,
-        _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
-        Ok(())
+    fn set_border(&mut self, spi: &mut SPI, border: BorderColor) -> Result<(), Error<SPI::Error>> {
+        self.vcom_data_interval = uc_border(self.vcom_data_interval, border)?;
+        self.cmd_with_data(
+            spi,
+            Command::VcomAndDataIntervalSetting,
+            &[self.vcom_data_interval],
+        )
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
-        Ok(())
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> Epd2in13bc<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd2in13bc<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
         self.interface.data(spi, data)
     }
 
@@ -362,11 +424,11 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
-    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
         let w = self.width();
         let h = self.height();
 
@@ -377,17 +439,136 @@ where
         self.send_data(spi, &[h as u8])
     }
 
+    /// Writes only the black/white plane and keeps the chromatic plane in the controller RAM
+    ///
+    /// Show the frame with [`display_frame`](WaveshareDisplay::display_frame). See the
+    /// [module documentation](self) for when the chromatic plane needs to be sent again.
+    pub fn update_achromatic_frame_only(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(black.len(), NUM_DISPLAY_BITS as usize)?;
+        self.wait_until_idle(spi, delay)?;
+        self.interface
+            .cmd_with_data(spi, Command::DataStartTransmission1, black)
+    }
+
     /// Set the outer border of the display to the chosen color.
-    pub fn set_border_color(&mut self, spi: &mut SPI, color: TriColor) -> Result<(), SPI::Error> {
+    pub fn set_border_color(
+        &mut self,
+        spi: &mut SPI,
+        color: TriColor,
+    ) -> Result<(), Error<SPI::Error>> {
         let border = match color {
-            TriColor::Black => BLACK_BORDER,
-            TriColor::White => WHITE_BORDER,
-            TriColor::Chromatic => CHROMATIC_BORDER,
+            TriColor::Black => BorderColor::Black,
+            TriColor::White => BorderColor::White,
+            TriColor::Chromatic => BorderColor::Chromatic,
         };
-        self.cmd_with_data(
-            spi,
-            Command::VcomAndDataIntervalSetting,
-            &[border | VCOM_DATA_INTERVAL],
-        )
+        self.set_border(spi, border)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+    use crate::NoPin;
+    use embedded_hal_mock::eh1::{
+        delay::NoopDelay,
+        digital::{Mock as PinMock, State, Transaction as PinTransaction},
+        spi::{Mock as SpiMock, Transaction as SpiTransaction},
+    };
+    use std::{vec, vec::Vec};
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 104);
+        assert_eq!(HEIGHT, 212);
+        assert_eq!(NUM_DISPLAY_BITS, 2_756);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, TriColor::White);
+    }
+
+    #[test]
+    fn achromatic_and_display_refreshes_without_the_chromatic_plane() {
+        let black = [0xF0; NUM_DISPLAY_BITS as usize];
+
+        let mut spi_expectations = Vec::new();
+        for byte in [0x10].into_iter().chain(black).chain([0x12]) {
+            spi_expectations.push(SpiTransaction::transaction_start());
+            spi_expectations.push(SpiTransaction::write_vec(vec![byte]));
+            spi_expectations.push(SpiTransaction::transaction_end());
+        }
+        let mut spi = SpiMock::new(&spi_expectations);
+        let mut busy = PinMock::new(&vec![PinTransaction::get(State::High); 2]);
+        let mut dc = PinMock::new(&[
+            PinTransaction::set(State::Low),
+            PinTransaction::set(State::High),
+            PinTransaction::set(State::Low),
+        ]);
+        let mut rst = PinMock::new(&[]);
+
+        let mut epd = Epd2in13bc {
+            interface: DisplayInterface::new(
+                busy.clone(),
+                dc.clone(),
+                Some(rst.clone()),
+                None::<NoPin>,
+                None,
+            ),
+            color: DEFAULT_BACKGROUND_COLOR,
+            vcom: DEFAULT_VCOM,
+            vcom_data_interval: DEFAULT_VCOM_DATA_INTERVAL,
+        };
+        epd.update_achromatic_and_display(&mut spi, &black, &mut NoopDelay::new())
+            .unwrap();
+        drop(epd);
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
+
+    #[test]
+    fn achromatic_frame_only_keeps_the_chromatic_ram() {
+        let black = [0x0F; NUM_DISPLAY_BITS as usize];
+
+        // only the first data transmission, each byte in its own transaction
+        let mut spi_expectations = Vec::new();
+        for byte in core::iter::once(0x10).chain(black.iter().copied()) {
+            spi_expectations.push(SpiTransaction::transaction_start());
+            spi_expectations.push(SpiTransaction::write_vec(vec![byte]));
+            spi_expectations.push(SpiTransaction::transaction_end());
+        }
+        let mut spi = SpiMock::new(&spi_expectations);
+        let mut busy = PinMock::new(&[PinTransaction::get(State::High)]);
+        let mut dc = PinMock::new(&[
+            PinTransaction::set(State::Low),
+            PinTransaction::set(State::High),
+        ]);
+        let mut rst = PinMock::new(&[]);
+
+        let mut epd = Epd2in13bc {
+            interface: DisplayInterface::new(
+                busy.clone(),
+                dc.clone(),
+                Some(rst.clone()),
+                None::<NoPin>,
+                None,
+            ),
+            color: DEFAULT_BACKGROUND_COLOR,
+            vcom: DEFAULT_VCOM,
+            vcom_data_interval: DEFAULT_VCOM_DATA_INTERVAL,
+        };
+        epd.update_achromatic_frame_only(&mut spi, &mut NoopDelay::new(), &black)
+            .unwrap();
+        drop(epd);
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
     }
 }
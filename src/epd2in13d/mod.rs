@@ -0,0 +1,504 @@
+//! A simple Driver for the Waveshare 2.13" D flexible E-Ink Display via SPI
+//!
+//! The display controller IC is the UC8151D. The flexible panel has its own power settings and
+//! waveforms, don't drive it with one of the drivers of the rigid 2.13" displays.
+//!
+//! The panel is 104 pixels wide, a row takes [`LINE_BYTES`] (13) bytes and a full frame
+//! [`BUFFER_LEN`] bytes. Several vendor examples assume 16 bytes per row, buffers of those
+//! examples can't be used with this driver.
+//!
+//! # Partial refresh
+//!
+//! [`update_partial_frame`](WaveshareDisplay::update_partial_frame) loads the partial waveforms,
+//! sets a window with the partial window commands (0x90/0x91) and writes the window to the RAM.
+//! The following [`display_frame`](WaveshareDisplay::display_frame) only refreshes this window,
+//! so call it after each partial update. Calling
+//! [`update_frame`](WaveshareDisplay::update_frame) or
+//! [`clear_frame`](WaveshareDisplay::clear_frame) switches back to the full refresh.
+//!
+//! The driver doesn't keep a copy of the previous frame. The old data of the window is written as
+//! the inverse of the new data, which drives every pixel of the window to its new color.
+//!
+//! # Refresh constraints
+//!
+//! Like the other flexible panels, do a full refresh after a few partial refreshes and put the
+//! display to [`sleep`](WaveshareDisplay::sleep) after every refresh.
+//!
+//! # References
+//!
+//! - [Waveshare Wiki](https://www.waveshare.com/wiki/2.13inch_e-Paper_HAT_(D))
+//! - [Waveshare C driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_2in13d.c)
+//!
+//! # Examples
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), epd_waveshare::error::Error<embedded_hal::spi::ErrorKind>> {
+//!use embedded_graphics::{prelude::*, primitives::{Line, PrimitiveStyle}};
+//!use epd_waveshare::{epd2in13d::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let cs_pin = digital::Mock::new(&expectations);
+//!# let busy_in = digital::Mock::new(&expectations);
+//!# let dc = digital::Mock::new(&expectations);
+//!# let rst = digital::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
+//!
+//!// Setup EPD
+//!let mut epd = Epd2in13d::new(&mut spi, busy_in, dc, Some(rst), None::<NoPin>, &mut delay, None)?;
+//!
+//!// Use display graphics from embedded-graphics
+//!let mut display = Display2in13d::default();
+//!
+//!// Draw a line and show it with a full refresh
+//!let _ = Line::new(Point::new(0, 120), Point::new(0, 200))
+//!    .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
+//!    .draw(&mut display);
+//!epd.update_and_display_frame(&mut spi, display.buffer(), &mut delay)?;
+//!
+//!// Set the EPD to sleep
+//!epd.sleep(&mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+
+use crate::error::Error;
+use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
+
+use crate::color::Color;
+use crate::interface::DisplayInterface;
+use crate::traits::{InternalWiAdditions, WaveshareDisplay};
+use crate::{buffer_len, check_buffer_len, check_partial_window};
+
+//The Lookup Tables for the Display
+mod constants;
+use self::constants::*;
+
+pub(crate) mod command;
+use self::command::Command;
+
+/// Width of Epd2in13d in pixels
+pub const WIDTH: u32 = 104;
+/// Height of Epd2in13d in pixels
+pub const HEIGHT: u32 = 212;
+/// Number of bytes of a row
+pub const LINE_BYTES: usize = buffer_len(WIDTH as usize, 1);
+/// Number of bytes of a full frame
+pub const BUFFER_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize);
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+const IS_BUSY_LOW: bool = true;
+const SINGLE_BYTE_WRITE: bool = true;
+
+/// Display with Fullsize buffer for use with the 2in13 EPD D
+#[cfg(feature = "graphics")]
+pub type Display2in13d = crate::graphics::Display<WIDTH, HEIGHT, false, BUFFER_LEN, Color>;
+
+/// Epd2in13d driver
+pub struct Epd2in13d<SPI, BUSY, DC, RST, DELAY, PWR> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    background_color: Color,
+    /// Whether the partial waveforms are loaded and the partial window is active
+    is_partial_refresh: bool,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in13d<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, 10_000, 2_000)?;
+        self.interface
+            .uc_software_reset(spi, delay, Command::PanelSetting, 0xBF, IS_BUSY_LOW)?;
+
+        // VDS_EN, VDG_EN, VCOM_HV, VGHL_LV, VDH, VDL, VDHR
+        self.interface.cmd_with_data(
+            spi,
+            Command::PowerSetting,
+            &[0x03, 0x00, 0x2b, 0x2b, 0x03],
+        )?;
+        self.interface
+            .cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x17])?;
+
+        self.interface.cmd(spi, Command::PowerOn)?;
+        self.wait_until_idle(spi, delay)?;
+
+        // LUT from register, black and white mode
+        self.interface
+            .cmd_with_data(spi, Command::PanelSetting, &[0xbf, 0x0d])?;
+
+        // 100 Hz
+        self.interface
+            .cmd_with_data(spi, Command::PllControl, &[0x3a])?;
+
+        self.interface.cmd_with_data(
+            spi,
+            Command::ResolutionSetting,
+            &[WIDTH as u8, (HEIGHT >> 8) as u8, HEIGHT as u8],
+        )?;
+
+        self.interface
+            .cmd_with_data(spi, Command::VcmDcSetting, &[0x28])?;
+
+        self.interface
+            .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x97])?;
+
+        self.is_partial_refresh = false;
+        self.set_full_reg(spi)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in13d<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    type DisplayColor = Color;
+
+    fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(busy, dc, rst, pwr, delay_us);
+
+        let mut epd = Epd2in13d {
+            interface,
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            is_partial_refresh: false,
+        };
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0xf7])?;
+        self.interface.cmd(spi, Command::PowerOff)?;
+        self.wait_until_idle(spi, delay)?;
+        self.interface
+            .cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
+        self.interface.power_off()
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.init(spi, delay)
+    }
+
+    fn set_background_color(&mut self, background_color: Color) {
+        self.background_color = background_color;
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.background_color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer.len(), BUFFER_LEN)?;
+        self.wait_until_idle(spi, delay)?;
+        self.leave_partial_refresh(spi)?;
+
+        // the full waveforms drive every pixel, the old data only selects between them
+        self.interface.cmd(spi, Command::DataStartTransmission1)?;
+        self.interface.data_x_times(spi, 0x00, BUFFER_LEN as u32)?;
+
+        self.interface
+            .cmd_with_data(spi, Command::DataStartTransmission2, buffer)
+    }
+
+    /// Writes a window of the frame and switches to the partial refresh
+    ///
+    /// `x` and `width` must be multiples of 8, see the [module documentation](self) for how the
+    /// window is refreshed.
+    fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_partial_window(buffer.len(), x, y, width, height, WIDTH, HEIGHT)?;
+
+        self.wait_until_idle(spi, delay)?;
+        if !self.is_partial_refresh {
+            self.set_part_reg(spi)?;
+            self.is_partial_refresh = true;
+        }
+
+        self.interface.cmd(spi, Command::PartialIn)?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::PartialWindow,
+            &partial_window(x, y, width, height),
+        )?;
+
+        self.interface.cmd(spi, Command::DataStartTransmission1)?;
+        for byte in buffer {
+            self.interface.data(spi, &[!byte])?;
+        }
+
+        self.interface
+            .cmd_with_data(spi, Command::DataStartTransmission2, buffer)
+    }
+
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, Command::DisplayRefresh)?;
+        delay.delay_us(1_000);
+        self.wait_until_idle(spi, delay)
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.leave_partial_refresh(spi)?;
+
+        let color = self.background_color.get_byte_value();
+        self.interface.cmd(spi, Command::DataStartTransmission1)?;
+        self.interface.data_x_times(spi, 0x00, BUFFER_LEN as u32)?;
+
+        self.interface.cmd(spi, Command::DataStartTransmission2)?;
+        self.interface.data_x_times(spi, color, BUFFER_LEN as u32)
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd2in13d<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    fn leave_partial_refresh(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        if self.is_partial_refresh {
+            self.interface.cmd(spi, Command::PartialOut)?;
+            self.set_full_reg(spi)?;
+            self.is_partial_refresh = false;
+        }
+        Ok(())
+    }
+
+    fn set_full_reg(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .cmd_with_data(spi, Command::VcmDcSetting, &[0x00])?;
+        self.interface
+            .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0xb7])?;
+        self.set_lut_helper(spi, &LUT_VCOM_DC, &LUT_WW, &LUT_BW, &LUT_WB, &LUT_BB)
+    }
+
+    fn set_part_reg(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .cmd_with_data(spi, Command::VcmDcSetting, &[0x00])?;
+        self.interface
+            .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0xb7])?;
+        self.set_lut_helper(spi, &LUT_VCOM1, &LUT_WW1, &LUT_BW1, &LUT_WB1, &LUT_BB1)
+    }
+
+    fn set_lut_helper(
+        &mut self,
+        spi: &mut SPI,
+        lut_vcom: &[u8],
+        lut_ww: &[u8],
+        lut_bw: &[u8],
+        lut_wb: &[u8],
+        lut_bb: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .cmd_with_data(spi, Command::LutForVcom, lut_vcom)?;
+        self.interface
+            .cmd_with_data(spi, Command::LutWhiteToWhite, lut_ww)?;
+        self.interface
+            .cmd_with_data(spi, Command::LutBlackToWhite, lut_bw)?;
+        self.interface
+            .cmd_with_data(spi, Command::LutWhiteToBlack, lut_wb)?;
+        self.interface
+            .cmd_with_data(spi, Command::LutBlackToBlack, lut_bb)
+    }
+}
+
+/// Data of the partial window command
+///
+/// The horizontal start and end are given in pixels, the lowest three bits of the start are
+/// ignored by the controller. The last byte only scans the gates inside of the window.
+fn partial_window(x: u32, y: u32, width: u32, height: u32) -> [u8; 7] {
+    let x_end = x + width - 1;
+    let y_end = y + height - 1;
+    [
+        x as u8,
+        x_end as u8,
+        (y >> 8) as u8,
+        y as u8,
+        (y_end >> 8) as u8,
+        y_end as u8,
+        0x28,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+    use crate::interface::mock::expect_commands;
+    use crate::NoPin;
+    use embedded_hal_mock::eh1::{
+        delay::NoopDelay,
+        digital::{Mock as PinMock, State, Transaction as PinTransaction},
+        spi::{Mock as SpiMock, Transaction as SpiTransaction},
+    };
+    use std::vec;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 104);
+        assert_eq!(HEIGHT, 212);
+        assert_eq!(LINE_BYTES, 13);
+        assert_eq!(BUFFER_LEN, 2_756);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+
+    #[test]
+    fn partial_windows() {
+        assert_eq!(
+            partial_window(0, 0, WIDTH, HEIGHT),
+            [0x00, 0x67, 0x00, 0x00, 0x00, 0xD3, 0x28]
+        );
+        assert_eq!(
+            partial_window(96, 200, 8, 12),
+            [0x60, 0x67, 0x00, 0xC8, 0x00, 0xD3, 0x28]
+        );
+    }
+
+    #[test]
+    fn partial_update_inverts_old_data() {
+        let (mut spi_expectations, mut dc_expectations) = expect_commands(&[
+            (0x82, &[0x00]),
+            (0x50, &[0xb7]),
+            (0x20, &LUT_VCOM1),
+            (0x21, &LUT_WW1),
+            (0x22, &LUT_BW1),
+            (0x23, &LUT_WB1),
+            (0x24, &LUT_BB1),
+            (0x91, &[]),
+            (0x90, &[0x08, 0x0F, 0x00, 0x02, 0x00, 0x03, 0x28]),
+            (0x10, &[0xF0]),
+        ]);
+        // the inverted old data is sent byte by byte
+        dc_expectations.push(PinTransaction::set(State::High));
+        spi_expectations.push(SpiTransaction::transaction_start());
+        spi_expectations.push(SpiTransaction::write_vec(vec![0x00]));
+        spi_expectations.push(SpiTransaction::transaction_end());
+        let (spi_new_data, dc_new_data) = expect_commands(&[(0x13, &[0x0F, 0xFF])]);
+        spi_expectations.extend(spi_new_data);
+        dc_expectations.extend(dc_new_data);
+        let mut spi = SpiMock::new(&spi_expectations);
+        let mut busy = PinMock::new(&[PinTransaction::get(State::High)]);
+        let mut dc = PinMock::new(&dc_expectations);
+        let mut rst = PinMock::new(&[]);
+
+        let mut epd = Epd2in13d {
+            interface: DisplayInterface::new(
+                busy.clone(),
+                dc.clone(),
+                Some(rst.clone()),
+                None::<NoPin>,
+                None,
+            ),
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            is_partial_refresh: false,
+        };
+        epd.update_partial_frame(&mut spi, &mut NoopDelay::new(), &[0x0F, 0xFF], 8, 2, 8, 2)
+            .unwrap();
+        drop(epd);
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn row_stride() {
+        use embedded_graphics::prelude::*;
+
+        let mut display = Display2in13d::default();
+        assert_eq!(display.buffer().len(), BUFFER_LEN);
+        display.clear(Color::White).unwrap();
+        Pixel(Point::new(0, 1), Color::Black)
+            .draw(&mut display)
+            .unwrap();
+        Pixel(Point::new(103, 1), Color::Black)
+            .draw(&mut display)
+            .unwrap();
+        assert_eq!(display.buffer()[LINE_BYTES - 1], 0xFF);
+        assert_eq!(display.buffer()[LINE_BYTES], 0x7F);
+        assert_eq!(display.buffer()[2 * LINE_BYTES - 1], 0xFE);
+    }
+}
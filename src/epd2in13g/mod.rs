@@ -0,0 +1,264 @@
+//! A simple Driver for the Waveshare 2.13" (G) 4-Color E-Ink Display via SPI
+//!
+//! The panel shows black, white, yellow and red. Each pixel takes 2 bits of the buffer
+//! (see [`QuadColor`]). The 122 pixels of a line don't fill the last byte, its 2 padding pixels
+//! are always sent as white, otherwise the right edge shows colored garbage.
+//!
+//! # References
+//!
+//! - [Waveshare Wiki](https://www.waveshare.com/wiki/2.13inch_e-Paper_HAT_(G))
+//! - [Waveshare C driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_2in13g.c)
+//!
+//! # Example for the 2.13" G E-Ink Display
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), epd_waveshare::error::Error<embedded_hal::spi::ErrorKind>> {
+//!use embedded_graphics::{prelude::*, primitives::{Line, PrimitiveStyle}};
+//!use epd_waveshare::{epd2in13g::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = digital::Mock::new(&expectations);
+//!# let dc = digital::Mock::new(&expectations);
+//!# let rst = digital::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
+//!
+//!// Setup EPD
+//!let mut epd = Epd2in13g::new(&mut spi, busy_in, dc, Some(rst), None::<NoPin>, &mut delay, None)?;
+//!
+//!// Use display graphics from embedded-graphics
+//!let mut display = Display2in13g::default();
+//!display.clear(QuadColor::White);
+//!
+//!// A red and a yellow line in the same frame
+//!let _ = Line::new(Point::new(0, 120), Point::new(0, 200))
+//!    .into_styled(PrimitiveStyle::with_stroke(QuadColor::Red, 1))
+//!    .draw(&mut display);
+//!let _ = Line::new(Point::new(15, 120), Point::new(15, 200))
+//!    .into_styled(PrimitiveStyle::with_stroke(QuadColor::Yellow, 1))
+//!    .draw(&mut display);
+//!
+//!// Display updated frame
+//!epd.update_and_display_frame(&mut spi, display.buffer(), &mut delay)?;
+//!
+//!// Set the EPD to sleep
+//!epd.sleep(&mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+
+use crate::error::Error;
+use embedded_hal::{
+    delay::DelayNs,
+    digital::{InputPin, OutputPin},
+    spi::SpiDevice,
+};
+
+use crate::color::QuadColor;
+use crate::interface::DisplayInterface;
+use crate::traits::{InternalWiAdditions, WaveshareDisplay};
+
+use crate::buffer_len;
+use crate::type_g::controller;
+
+/// Width of the display
+pub const WIDTH: u32 = 122;
+/// Height of the display
+pub const HEIGHT: u32 = 250;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: QuadColor = QuadColor::White;
+const SINGLE_BYTE_WRITE: bool = true;
+
+/// Full size buffer for use with the 2in13g EPD
+#[cfg(feature = "graphics")]
+pub type Display2in13g = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize * 2, HEIGHT as usize) },
+    QuadColor,
+>;
+
+/// Epd2in13g driver
+pub struct Epd2in13g<SPI, BUSY, DC, RST, DELAY, PWR> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    color: QuadColor,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in13g<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        controller::init(&mut self.interface, spi, delay, WIDTH, HEIGHT)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in13g<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    type DisplayColor = QuadColor;
+
+    fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new_color(busy, dc, rst, pwr, delay_us);
+        let color = DEFAULT_BACKGROUND_COLOR;
+
+        let mut epd = Epd2in13g { interface, color };
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        controller::sleep(&mut self.interface, spi, delay)?;
+        self.interface.power_off()
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.init(spi, delay)
+    }
+
+    fn set_background_color(&mut self, color: QuadColor) {
+        self.color = color;
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(controller::IS_BUSY_LOW)
+    }
+
+    fn background_color(&self) -> &QuadColor {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        controller::update_frame(&mut self.interface, spi, delay, buffer, WIDTH, HEIGHT)
+    }
+
+    fn update_partial_frame(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+        _buffer: &[u8],
+        _x: u32,
+        _y: u32,
+        _width: u32,
+        _height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::Unsupported)
+    }
+
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        controller::display_frame(&mut self.interface, spi, delay)
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        controller::clear_frame(&mut self.interface, spi, delay, self.color, WIDTH, HEIGHT)
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .wait_until_idle(delay, controller::IS_BUSY_LOW)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Bytes of a line at 2 bits per pixel, including the padding
+    const LINE_BYTES: u32 = controller::line_bytes(WIDTH);
+    /// Number of bytes of a full frame at 2 bits per pixel
+    const NUM_DISPLAY_BYTES: u32 = LINE_BYTES * HEIGHT;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 122);
+        assert_eq!(HEIGHT, 250);
+        assert_eq!(LINE_BYTES, 31);
+        assert_eq!(NUM_DISPLAY_BYTES, 7_750);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, QuadColor::White);
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn last_byte_of_line() {
+        use embedded_graphics::prelude::*;
+
+        let mut display = Display2in13g::default();
+        assert_eq!(display.buffer().len(), NUM_DISPLAY_BYTES as usize);
+
+        // the last two pixels of the first line share the byte with the padding
+        Pixel(Point::new(WIDTH as i32 - 2, 0), QuadColor::Red)
+            .draw(&mut display)
+            .unwrap();
+        Pixel(Point::new(WIDTH as i32 - 1, 0), QuadColor::Yellow)
+            .draw(&mut display)
+            .unwrap();
+        let last = display.buffer()[LINE_BYTES as usize - 1];
+        assert_eq!(last, 0b11_10_00_00);
+        assert_eq!(controller::pad_line_end(WIDTH, last), 0b11_10_01_01);
+        // the next line starts in a new byte
+        assert_eq!(display.buffer()[LINE_BYTES as usize], 0x00);
+    }
+}
@@ -0,0 +1,304 @@
+//! A simple Driver for the Waveshare 2.15" (G) 4-Color E-Ink Display via SPI
+//!
+//! The panel shows black, white, yellow and red. Each pixel takes 2 bits of the buffer
+//! (see [`QuadColor`]). It has the same controller as the
+//! [`epd2in13g`](crate::epd2in13g) with a different resolution.
+//!
+//! # References
+//!
+//! - [Waveshare Wiki](https://www.waveshare.com/wiki/2.13inch_e-Paper_HAT_(G))
+//! - [Waveshare C driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_2in15g.c)
+//!
+//! # Example for the 2.15" G E-Ink Display
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), epd_waveshare::error::Error<embedded_hal::spi::ErrorKind>> {
+//!use embedded_graphics::{prelude::*, primitives::{Line, PrimitiveStyle}};
+//!use epd_waveshare::{epd2in15g::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = digital::Mock::new(&expectations);
+//!# let dc = digital::Mock::new(&expectations);
+//!# let rst = digital::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
+//!
+//!// Setup EPD
+//!let mut epd = Epd2in15g::new(&mut spi, busy_in, dc, Some(rst), None::<NoPin>, &mut delay, None)?;
+//!
+//!// Use display graphics from embedded-graphics
+//!let mut display = Display2in15g::default();
+//!display.clear(QuadColor::White);
+//!
+//!// A red and a yellow line in the same frame
+//!let _ = Line::new(Point::new(0, 120), Point::new(0, 200))
+//!    .into_styled(PrimitiveStyle::with_stroke(QuadColor::Red, 1))
+//!    .draw(&mut display);
+//!let _ = Line::new(Point::new(15, 120), Point::new(15, 200))
+//!    .into_styled(PrimitiveStyle::with_stroke(QuadColor::Yellow, 1))
+//!    .draw(&mut display);
+//!
+//!// Display updated frame
+//!epd.update_and_display_frame(&mut spi, display.buffer(), &mut delay)?;
+//!
+//!// Set the EPD to sleep
+//!epd.sleep(&mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+
+use crate::error::Error;
+use embedded_hal::{
+    delay::DelayNs,
+    digital::{InputPin, OutputPin},
+    spi::SpiDevice,
+};
+
+use crate::color::QuadColor;
+use crate::interface::DisplayInterface;
+use crate::traits::{InternalWiAdditions, WaveshareDisplay};
+
+use crate::buffer_len;
+use crate::type_g::controller;
+
+/// Width of the display
+pub const WIDTH: u32 = 160;
+/// Height of the display
+pub const HEIGHT: u32 = 296;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: QuadColor = QuadColor::White;
+const SINGLE_BYTE_WRITE: bool = true;
+
+/// Full size buffer for use with the 2in15g EPD
+#[cfg(feature = "graphics")]
+pub type Display2in15g = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize * 2, HEIGHT as usize) },
+    QuadColor,
+>;
+
+/// Epd2in15g driver
+pub struct Epd2in15g<SPI, BUSY, DC, RST, DELAY, PWR> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    color: QuadColor,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in15g<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        controller::init(&mut self.interface, spi, delay, WIDTH, HEIGHT)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in15g<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    type DisplayColor = QuadColor;
+
+    fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new_color(busy, dc, rst, pwr, delay_us);
+        let color = DEFAULT_BACKGROUND_COLOR;
+
+        let mut epd = Epd2in15g { interface, color };
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        controller::sleep(&mut self.interface, spi, delay)?;
+        self.interface.power_off()
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.init(spi, delay)
+    }
+
+    fn set_background_color(&mut self, color: QuadColor) {
+        self.color = color;
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(controller::IS_BUSY_LOW)
+    }
+
+    fn background_color(&self) -> &QuadColor {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        controller::update_frame(&mut self.interface, spi, delay, buffer, WIDTH, HEIGHT)
+    }
+
+    fn update_partial_frame(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+        _buffer: &[u8],
+        _x: u32,
+        _y: u32,
+        _width: u32,
+        _height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::Unsupported)
+    }
+
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        controller::display_frame(&mut self.interface, spi, delay)
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        controller::clear_frame(&mut self.interface, spi, delay, self.color, WIDTH, HEIGHT)
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .wait_until_idle(delay, controller::IS_BUSY_LOW)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interface::mock::expect_commands;
+    use crate::NoPin;
+    use embedded_hal_mock::eh1::{
+        delay::NoopDelay,
+        digital::{Mock as PinMock, State, Transaction as PinTransaction},
+        spi::Mock as SpiMock,
+    };
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 160);
+        assert_eq!(HEIGHT, 296);
+        assert_eq!(controller::line_bytes(WIDTH) * HEIGHT, 11_840);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, QuadColor::White);
+    }
+
+    #[test]
+    fn init_sequence() {
+        let (spi_expectations, dc_expectations) = expect_commands(&[
+            (0x4D, &[0x78]),
+            (0x00, &[0x0F, 0x29]),
+            (0x01, &[0x07, 0x00]),
+            (0x03, &[0x10, 0x54, 0x44]),
+            (0x06, &[0x05, 0x00, 0x3F, 0x0A, 0x25, 0x12, 0x1A]),
+            (0x50, &[0x37]),
+            (0x60, &[0x02, 0x02]),
+            (0x61, &[0x00, 0xA0, 0x01, 0x28]),
+            (0xE7, &[0x1C]),
+            (0xE3, &[0x22]),
+            (0xB4, &[0xD0]),
+            (0xB5, &[0x03]),
+            (0xE9, &[0x01]),
+            (0x30, &[0x08]),
+            (0x04, &[]),
+        ]);
+        let mut spi = SpiMock::new(&spi_expectations);
+        // not busy after the reset and after the power on
+        let mut busy = PinMock::new(&[
+            PinTransaction::get(State::High),
+            PinTransaction::get(State::High),
+        ]);
+        let mut dc = PinMock::new(&dc_expectations);
+        let mut rst = PinMock::new(&[
+            PinTransaction::set(State::High),
+            PinTransaction::set(State::Low),
+            PinTransaction::set(State::High),
+        ]);
+
+        let epd = Epd2in15g::new(
+            &mut spi,
+            busy.clone(),
+            dc.clone(),
+            Some(rst.clone()),
+            None::<NoPin>,
+            &mut NoopDelay::new(),
+            None,
+        )
+        .unwrap();
+        drop(epd);
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn white_background() {
+        use embedded_graphics::prelude::*;
+
+        let mut display = Display2in15g::default();
+        assert_eq!(display.buffer().len(), 11_840);
+        display.clear(DEFAULT_BACKGROUND_COLOR).unwrap();
+        assert!(display.buffer().iter().all(|&byte| byte == 0b01010101));
+    }
+}
@@ -11,6 +11,11 @@
 //! Information on this display/hat can be found at the [Waveshare Wiki](https://www.waveshare.com/wiki/Pico-ePaper-2.66-B).
 //! Do read this documentation, in particular to understand how often this display both should and should not be updated.
 //!
+//! A full refresh including the red plane takes around 15 seconds, [`display_frame`](WaveshareDisplay::display_frame)
+//! blocks for that long. It fails with `Error::BusyTimeout` once the busy wait exceeds the 60 seconds of the colored
+//! panels, [`set_busy_timeout`](WaveshareDisplay::set_busy_timeout) changes the limit. [`clear_frame`](WaveshareDisplay::clear_frame)
+//! overwrites both the black/white and the red RAM bank, otherwise red pixels of the previous image would ghost.
+//!
 //! # Example for the 'Pico-ePaper-2.66-B' B/W/R Pi Pico Hat E-Ink Display
 //! This example was created in an environment using the [Knurling](https://github.com/knurling-rs) ```flip-link```, ```defmt``` and ```probe-run``` tools - you will
 //! need to adjust for your preferred setup.
@@ -126,7 +131,6 @@
 //!    // Setup the EPD driver
 //!    let mut e_paper = Epd2in66b::new(
 //!        &mut spi,
-//!        chip_select_pin,
 //!        is_busy_pin,
 //!        data_or_command_pin,
 //!        reset_pin,
@@ -166,6 +170,7 @@
 //!}
 //!```
 
+use crate::error::Error;
 use embedded_hal::{
     delay::DelayNs,
     digital::{InputPin, OutputPin},
@@ -174,9 +179,7 @@ use embedded_hal::{
 
 use crate::color::TriColor;
 use crate::interface::DisplayInterface;
-use crate::traits::{
-    InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
-};
+use crate::traits::{InternalWiAdditions, WaveshareDisplay, WaveshareThreeColorDisplay};
 
 pub(crate) mod command;
 use self::command::*;
@@ -203,21 +206,22 @@ pub type Display2in66b = crate::graphics::Display<
 >;
 
 /// The EPD 2in66-B driver.
-pub struct Epd2in66b<SPI, BUSY, DC, RST, DELAY> {
-    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+pub struct Epd2in66b<SPI, BUSY, DC, RST, DELAY, PWR> {
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
     background: TriColor,
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
-    for Epd2in66b<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in66b<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         // We follow the sequence of the Pi-Pico hat example code.
         self.hw_reset(delay)?;
         self.sw_reset(spi, delay)?;
@@ -235,13 +239,14 @@ where
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>
-    for Epd2in66b<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in66b<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     fn update_color_frame(
@@ -250,7 +255,7 @@ where
         delay: &mut DELAY,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_achromatic_frame(spi, delay, black)?;
         self.update_chromatic_frame(spi, delay, chromatic)
     }
@@ -260,7 +265,7 @@ where
         spi: &mut SPI,
         _delay: &mut DELAY,
         black: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.set_cursor(spi, 0, 0)?;
         self.interface.cmd(spi, Command::WriteBlackWhiteRAM)?;
         self.interface.data(spi, black)
@@ -271,20 +276,21 @@ where
         spi: &mut SPI,
         _delay: &mut DELAY,
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.set_cursor(spi, 0, 0)?;
         self.interface.cmd(spi, Command::WriteRedRAM)?;
         self.interface.data(spi, chromatic)
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
-    for Epd2in66b<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in66b<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     type DisplayColor = TriColor;
@@ -293,30 +299,32 @@ where
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error>
+    ) -> Result<Self, Error<SPI::Error>>
     where
         Self: Sized,
     {
         let mut epd = Self {
-            interface: DisplayInterface::new(busy, dc, rst, delay_us),
+            interface: DisplayInterface::new_color(busy, dc, rst, pwr, delay_us),
             background: DEFAULT_BACKGROUND_COLOR,
         };
         epd.init(spi, delay)?;
         Ok(epd)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(
             spi,
             Command::DeepSleepMode,
             &[DeepSleep::SleepLosingRAM as u8],
-        )
+        )?;
+        self.interface.power_off()
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.init(spi, delay)
     }
 
@@ -324,6 +332,18 @@ where
         self.background = color;
     }
 
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(false)
+    }
+
     fn background_color(&self) -> &Self::DisplayColor {
         &self.background
     }
@@ -341,7 +361,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.set_cursor(spi, 0, 0)?;
         self.update_achromatic_frame(spi, delay, buffer)?;
         self.red_pattern(spi, delay, PatW::W160, PatH::H296, StartWith::Zero) // do NOT consider background here since red overrides other colors
@@ -356,14 +376,14 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.set_display_window(spi, x, y, x + width, y + height)?;
         self.set_cursor(spi, x, y)?;
         self.update_achromatic_frame(spi, delay, buffer)?;
         self.set_display_window(spi, 0, 0, WIDTH, HEIGHT)
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, Command::MasterActivation)?;
         self.wait_until_idle(delay)
     }
@@ -373,12 +393,12 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         let (white, red) = match self.background {
             TriColor::Black => (StartWith::Zero, StartWith::Zero),
             TriColor::White => (StartWith::One, StartWith::Zero),
@@ -388,39 +408,34 @@ where
         self.red_pattern(spi, delay, PatW::W160, PatH::H296, red)
     }
 
-    fn set_lut(
+    fn wait_until_idle(
         &mut self,
         _spi: &mut SPI,
-        _delay: &mut DELAY,
-        _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
-        Ok(())
-    }
-
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(delay)
     }
 }
 
 // Helper functions that enforce some type and value constraints. Meant to help with code readability. They caught some of my silly errors -> yay rust!.
-impl<SPI, BUSY, DC, RST, DELAY> Epd2in66b<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd2in66b<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn wait_until_idle(&mut self, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.wait_until_idle(delay, false);
-        Ok(())
+    fn wait_until_idle(&mut self, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, false)
     }
-    fn hw_reset(&mut self, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn hw_reset(&mut self, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         // The initial delay is taken from other code here, the 2 ms comes from the SSD1675B datasheet.
-        self.interface.reset(delay, 20_000, 2_000);
+        self.interface.reset(delay, 20_000, 2_000)?;
         self.wait_until_idle(delay)
     }
-    fn sw_reset(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sw_reset(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, Command::Reset)?;
         self.wait_until_idle(delay)
     }
@@ -429,7 +444,7 @@ where
         spi: &mut SPI,
         row: DataEntryRow,
         sign: DataEntrySign,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface
             .cmd_with_data(spi, Command::DataEntryMode, &[row as u8 | sign as u8])
     }
@@ -440,7 +455,7 @@ where
         ystart: u32,
         xend: u32,
         yend: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(
             spi,
             Command::SetXAddressRange,
@@ -463,7 +478,7 @@ where
         red_mode: WriteMode,
         bw_mode: WriteMode,
         source: OutputSource,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(
             spi,
             Command::DisplayUpdateControl1,
@@ -471,7 +486,7 @@ where
         )
     }
 
-    fn set_cursor(&mut self, spi: &mut SPI, x: u32, y: u32) -> Result<(), SPI::Error> {
+    fn set_cursor(&mut self, spi: &mut SPI, x: u32, y: u32) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(
             spi,
             Command::SetXAddressCounter,
@@ -491,7 +506,7 @@ where
         w: PatW,
         h: PatH,
         phase: StartWith,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(
             spi,
             Command::BlackWhiteRAMTestPattern,
@@ -506,7 +521,7 @@ where
         w: PatW,
         h: PatH,
         phase: StartWith,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(
             spi,
             Command::RedRAMTestPattern,
@@ -515,3 +530,15 @@ where
         self.wait_until_idle(delay)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 152);
+        assert_eq!(HEIGHT, 296);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, TriColor::White);
+    }
+}
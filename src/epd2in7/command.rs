@@ -67,6 +67,8 @@ pub(crate) enum Command {
     LutBlackToWhite = 0x22,
     LutWhiteToBlack = 0x23,
     LutBlackToBlack = 0x24,
+    /// This command builds the Look-up table for the border, only used by the 4-gray mode
+    LutForBorder = 0x25,
     /// The command controls the PLL clock frequency.
     PllControl = 0x30,
     /// This command reads the temperature sensed by the temperature sensor.
@@ -54,3 +54,60 @@ pub(crate) const LUT_WB: [u8; 42] =[
 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
 ];
+
+// LUTs of the 4-gray mode, from the vendor's `EPD_2IN7_4GrayDisplay`
+#[rustfmt::skip]
+pub(crate) const LUT_VCOM_GRAY: [u8; 44] = [
+0x00, 0x00,
+0x00, 0x0A, 0x00, 0x00, 0x00, 0x01,
+0x60, 0x14, 0x14, 0x00, 0x00, 0x01,
+0x00, 0x14, 0x00, 0x00, 0x00, 0x01,
+0x00, 0x13, 0x0A, 0x01, 0x00, 0x01,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00
+];
+
+#[rustfmt::skip]
+pub(crate) const LUT_WW_GRAY: [u8; 42] = [
+0x40, 0x0A, 0x00, 0x00, 0x00, 0x01,
+0x90, 0x14, 0x14, 0x00, 0x00, 0x01,
+0x10, 0x14, 0x0A, 0x00, 0x00, 0x01,
+0xA0, 0x13, 0x01, 0x00, 0x00, 0x01,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00
+];
+
+#[rustfmt::skip]
+pub(crate) const LUT_BW_GRAY: [u8; 42] = [
+0x40, 0x0A, 0x00, 0x00, 0x00, 0x01,
+0x90, 0x14, 0x14, 0x00, 0x00, 0x01,
+0x00, 0x14, 0x0A, 0x00, 0x00, 0x01,
+0x99, 0x0C, 0x01, 0x03, 0x04, 0x01,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00
+];
+
+#[rustfmt::skip]
+pub(crate) const LUT_WB_GRAY: [u8; 42] = [
+0x40, 0x0A, 0x00, 0x00, 0x00, 0x01,
+0x90, 0x14, 0x14, 0x00, 0x00, 0x01,
+0x00, 0x14, 0x0A, 0x00, 0x00, 0x01,
+0x99, 0x0B, 0x04, 0x04, 0x01, 0x01,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00
+];
+
+#[rustfmt::skip]
+pub(crate) const LUT_BB_GRAY: [u8; 42] = [
+0x80, 0x0A, 0x00, 0x00, 0x00, 0x01,
+0x90, 0x14, 0x14, 0x00, 0x00, 0x01,
+0x20, 0x14, 0x0A, 0x00, 0x00, 0x01,
+0x50, 0x13, 0x01, 0x00, 0x00, 0x01,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+0x00, 0x00, 0x00, 0x00, 0x00, 0x00
+];
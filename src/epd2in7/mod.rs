@@ -1,7 +1,20 @@
 //! A simple Driver for the Waveshare 2.7" E-Ink Display via SPI
 //!
 //! [Documentation](https://www.waveshare.com/wiki/2.7inch_e-Paper_HAT)
+//!
+//! # Grayscale
+//!
+//! Four gray levels can be shown with [`Epd2in7::update_and_display_frame_grayscale`], e.g. drawn
+//! on a [`Display2in7Gray`]. The next black/white update resets the display to leave the 4-gray
+//! mode again.
+//!
+//! # Custom LUT
+//!
+//! [`Epd2in7::set_custom_lut`] replaces the built-in [`LUT_FULL`] of the black/white refresh
+//! until [`Epd2in7::clear_custom_lut`], also after [`wake_up`](WaveshareDisplay::wake_up). The
+//! grayscale frames keep their LUTs.
 
+use crate::error::Error;
 use embedded_hal::{
     delay::DelayNs,
     digital::{InputPin, OutputPin},
@@ -9,7 +22,10 @@ use embedded_hal::{
 };
 
 use crate::interface::DisplayInterface;
-use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::traits::{
+    check_refresh_lut, uc_border, uc_vcm_dc, BorderColor, InternalWiAdditions, LutBanks,
+    LutBanksBuf, RefreshLut, WaveshareDisplay,
+};
 
 // The Lookup Tables for the Display
 mod constants;
@@ -21,14 +37,29 @@ pub const WIDTH: u32 = 176;
 pub const HEIGHT: u32 = 264;
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+/// Number of bytes of a full frame with 2 bits per pixel, as used by
+/// [`Epd2in7::update_and_display_frame_grayscale`]
+pub const GRAY_BUFFER_LEN: usize = buffer_len(WIDTH as usize * 2, HEIGHT as usize);
+/// Built-in LUTs of the full refresh, e.g. to pass to [`Epd2in7::set_custom_lut`]
+pub const LUT_FULL: LutBanks<'static> = LutBanks {
+    vcom: &LUT_VCOM_DC,
+    ww: &LUT_WW,
+    bw: &LUT_BW,
+    wb: &LUT_WB,
+    bb: &LUT_BB,
+};
 const IS_BUSY_LOW: bool = true;
+/// VCOM and data interval setting until `set_border` is called
+const DEFAULT_VCOM_DATA_INTERVAL: u8 = 0x57;
+/// -1.0 V
+const DEFAULT_VCOM: u8 = 0x12;
 const SINGLE_BYTE_WRITE: bool = true;
 
 use crate::color::Color;
 
 pub(crate) mod command;
 use self::command::Command;
-use crate::buffer_len;
+use crate::{buffer_len, check_buffer_len};
 
 /// Full size buffer for use with the 2in7 EPD
 #[cfg(feature = "graphics")]
@@ -40,66 +71,73 @@ pub type Display2in7 = crate::graphics::Display<
     Color,
 >;
 
+/// Full size buffer with 4 gray levels for use with
+/// [`Epd2in7::update_and_display_frame_grayscale`]
+#[cfg(feature = "graphics")]
+pub type Display2in7Gray = crate::graphics::DisplayGray2<WIDTH, HEIGHT, GRAY_BUFFER_LEN>;
+
 /// Epd2in7 driver
-pub struct Epd2in7<SPI, BUSY, DC, RST, DELAY> {
+pub struct Epd2in7<SPI, BUSY, DC, RST, DELAY, PWR> {
     /// Connection Interface
-    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
     /// Background Color
     color: Color,
+    /// Whether the registers are currently set up for the 4-gray mode
+    gray_mode: bool,
+    /// Replaces `LUT_FULL` if set
+    custom_lut: Option<LutBanksBuf<44, 42, 42>>,
+    /// VCOM register value, see [`set_vcom`](Self::set_vcom)
+    vcom: u8,
+    /// VCOM and data interval setting, holds the border of
+    /// [`set_border`](WaveshareDisplay::set_border)
+    vcom_data_interval: u8,
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
-    for Epd2in7<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in7<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         // reset the device
-        self.interface.reset(delay, 10_000, 2_000);
+        self.interface.reset(delay, 10_000, 2_000)?;
+        self.interface
+            .uc_software_reset(spi, delay, Command::PanelSetting, 0xAF, IS_BUSY_LOW)?;
+        self.gray_mode = false;
 
         // power setting
         self.cmd_with_data(spi, Command::PowerSetting, &[0x03, 0x00, 0x2b, 0x2b, 0x09])?;
-        // booster soft start
-        self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x07, 0x07, 0x17])?;
-        // power optimization
-        self.cmd_with_data(spi, Command::PowerOptimization, &[0x60, 0xa5])?;
-        self.cmd_with_data(spi, Command::PowerOptimization, &[0x89, 0xa5])?;
-        self.cmd_with_data(spi, Command::PowerOptimization, &[0x90, 0x00])?;
-        self.cmd_with_data(spi, Command::PowerOptimization, &[0x93, 0x2a])?;
-        self.cmd_with_data(spi, Command::PowerOptimization, &[0xa0, 0xa5])?;
-        self.cmd_with_data(spi, Command::PowerOptimization, &[0xa1, 0x00])?;
-        self.cmd_with_data(spi, Command::PowerOptimization, &[0x73, 0x41])?;
-        // partial display refresh
-        self.cmd_with_data(spi, Command::PartialDisplayRefresh, &[0x00])?;
-        // power on
-        self.command(spi, Command::PowerOn)?;
-        delay.delay_us(5000);
-        self.wait_until_idle(spi, delay)?;
+        self.power_on(spi, delay)?;
         // panel setting
         self.cmd_with_data(spi, Command::PanelSetting, &[0xaf])?;
         // pll control
         self.cmd_with_data(spi, Command::PllControl, &[0x3a])?;
         // vcom and data interval setting
-        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x57])?;
+        self.cmd_with_data(
+            spi,
+            Command::VcomAndDataIntervalSetting,
+            &[self.vcom_data_interval],
+        )?;
         // cvm dc setting register
-        self.cmd_with_data(spi, Command::VcmDcSetting, &[0x12])?;
+        self.cmd_with_data(spi, Command::VcmDcSetting, &[self.vcom])?;
         self.set_lut(spi, delay, None)?;
-        self.wait_until_idle(spi, delay)?;
-        Ok(())
+        self.wait_until_idle(spi, delay)
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
-    for Epd2in7<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in7<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     type DisplayColor = Color;
@@ -107,25 +145,33 @@ where
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(busy, dc, rst, pwr, delay_us);
         let color = DEFAULT_BACKGROUND_COLOR;
 
-        let mut epd = Epd2in7 { interface, color };
+        let mut epd = Epd2in7 {
+            interface,
+            color,
+            gray_mode: false,
+            custom_lut: None,
+            vcom: DEFAULT_VCOM,
+            vcom_data_interval: DEFAULT_VCOM_DATA_INTERVAL,
+        };
 
         epd.init(spi, delay)?;
 
         Ok(epd)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.interface
             .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0xf7])?;
@@ -134,15 +180,16 @@ where
         self.wait_until_idle(spi, delay)?;
         self.interface
             .cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
-        Ok(())
+        self.interface.power_off()
     }
 
     fn update_frame(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
-        _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.leave_gray_mode(spi, delay)?;
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
         self.interface
             .data_x_times(spi, self.color.get_byte_value(), WIDTH * HEIGHT / 8)?;
@@ -160,7 +207,8 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.leave_gray_mode(spi, delay)?;
         self.interface
             .cmd(spi, Command::PartialDataStartTransmission1)?;
 
@@ -176,10 +224,9 @@ where
         self.send_data(spi, buffer)
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.command(spi, Command::DisplayRefresh)?;
-        self.wait_until_idle(spi, delay)?;
-        Ok(())
+        self.wait_until_idle(spi, delay)
     }
 
     fn update_and_display_frame(
@@ -187,13 +234,14 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.command(spi, Command::DisplayRefresh)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.leave_gray_mode(spi, delay)?;
         self.wait_until_idle(spi, delay)?;
 
         let color_value = self.color.get_byte_value();
@@ -210,6 +258,29 @@ where
         self.color = color;
     }
 
+    fn set_vcom(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        millivolts: i16,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.vcom = uc_vcm_dc(millivolts)?;
+        self.wait_until_idle(spi, delay)?;
+        self.cmd_with_data(spi, Command::VcmDcSetting, &[self.vcom])
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
     fn background_color(&self) -> &Color {
         &self.color
     }
@@ -226,36 +297,52 @@ where
         &mut self,
         spi: &mut SPI,
         delay: &mut DELAY,
-        _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_refresh_lut(refresh_rate, Self::REFRESH_LUTS)?;
+        let custom_lut = self.custom_lut;
+        let luts = custom_lut.as_ref().map_or(LUT_FULL, LutBanksBuf::banks);
         self.wait_until_idle(spi, delay)?;
-        self.cmd_with_data(spi, Command::LutForVcom, &LUT_VCOM_DC)?;
-        self.cmd_with_data(spi, Command::LutWhiteToWhite, &LUT_WW)?;
-        self.cmd_with_data(spi, Command::LutBlackToWhite, &LUT_BW)?;
-        self.cmd_with_data(spi, Command::LutWhiteToBlack, &LUT_WB)?;
-        self.cmd_with_data(spi, Command::LutBlackToBlack, &LUT_BB)?;
+        self.cmd_with_data(spi, Command::LutForVcom, luts.vcom)?;
+        self.cmd_with_data(spi, Command::LutWhiteToWhite, luts.ww)?;
+        self.cmd_with_data(spi, Command::LutBlackToWhite, luts.bw)?;
+        self.cmd_with_data(spi, Command::LutWhiteToBlack, luts.wb)?;
+        self.cmd_with_data(spi, Command::LutBlackToBlack, luts.bb)?;
         Ok(())
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
-        Ok(())
+    fn set_border(&mut self, spi: &mut SPI, border: BorderColor) -> Result<(), Error<SPI::Error>> {
+        self.vcom_data_interval = uc_border(self.vcom_data_interval, border)?;
+        self.cmd_with_data(
+            spi,
+            Command::VcomAndDataIntervalSetting,
+            &[self.vcom_data_interval],
+        )
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> Epd2in7<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd2in7<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
         self.interface.data(spi, data)
     }
 
@@ -264,9 +351,164 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
+
+    /// Loads `luts` instead of [`LUT_FULL`]
+    ///
+    /// The VCOM LUT has 44 bytes, the others 42. Fails with `Error::InvalidBufferLength` for a
+    /// LUT of another length.
+    pub fn set_custom_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        luts: LutBanks,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.custom_lut = Some(LutBanksBuf::new(luts)?);
+        self.set_lut(spi, delay, None)
+    }
+
+    /// Drops the LUTs of `set_custom_lut` and loads `LUT_FULL` again
+    pub fn clear_custom_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.custom_lut = None;
+        self.set_lut(spi, delay, None)
+    }
+
+    /// Transmit a full frame with 4 gray levels and display it
+    ///
+    /// The buffer holds 2 bits per pixel (4 pixels per byte, first pixel in the most
+    /// significant bits) and must be of size [`GRAY_BUFFER_LEN`]:
+    ///
+    /// - `0b00`: black
+    /// - `0b01`: dark gray
+    /// - `0b10`: light gray
+    /// - `0b11`: white
+    ///
+    /// The next black/white update resets the display to leave the 4-gray mode again.
+    pub fn update_and_display_frame_grayscale(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer.len(), GRAY_BUFFER_LEN)?;
+        self.enter_gray_mode(spi, delay)?;
+
+        for (command, old) in [
+            (Command::DataStartTransmission1, true),
+            (Command::DataStartTransmission2, false),
+        ] {
+            self.command(spi, command)?;
+            for pair in buffer.chunks(2) {
+                let (old_byte, new_byte) = gray_planes(pair[0], pair[1]);
+                self.send_data(spi, &[if old { old_byte } else { new_byte }])?;
+            }
+        }
+
+        self.cmd_with_data(spi, Command::LutForVcom, &LUT_VCOM_GRAY)?;
+        self.cmd_with_data(spi, Command::LutWhiteToWhite, &LUT_WW_GRAY)?;
+        self.cmd_with_data(spi, Command::LutBlackToWhite, &LUT_BW_GRAY)?;
+        self.cmd_with_data(spi, Command::LutWhiteToBlack, &LUT_WB_GRAY)?;
+        self.cmd_with_data(spi, Command::LutBlackToBlack, &LUT_BB_GRAY)?;
+        self.cmd_with_data(spi, Command::LutForBorder, &LUT_WW_GRAY)?;
+
+        self.command(spi, Command::DisplayRefresh)?;
+        delay.delay_us(200_000);
+        self.wait_until_idle(spi, delay)
+    }
+
+    /// Resets the display into the registers of the vendor's 4-gray mode, if not done yet
+    fn enter_gray_mode(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        if self.gray_mode {
+            return Ok(());
+        }
+        self.interface.reset(delay, 10_000, 2_000)?;
+        self.interface
+            .uc_software_reset(spi, delay, Command::PanelSetting, 0xBF, IS_BUSY_LOW)?;
+        self.gray_mode = true;
+
+        self.cmd_with_data(spi, Command::PowerSetting, &[0x03, 0x00, 0x2b, 0x2b])?;
+        self.power_on(spi, delay)?;
+        // KW mode with the LUTs from the registers
+        self.cmd_with_data(spi, Command::PanelSetting, &[0xbf])?;
+        // 100 Hz
+        self.cmd_with_data(spi, Command::PllControl, &[0x90])?;
+        self.cmd_with_data(spi, Command::ResolutionSetting, &[0x00, 0xb0, 0x01, 0x08])?;
+        self.cmd_with_data(spi, Command::VcmDcSetting, &[self.vcom])?;
+        self.cmd_with_data(
+            spi,
+            Command::VcomAndDataIntervalSetting,
+            &[self.vcom_data_interval],
+        )
+    }
+
+    /// Resets the display to its black/white mode after a grayscale frame
+    fn leave_gray_mode(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        if self.gray_mode {
+            self.init(spi, delay)?;
+        }
+        Ok(())
+    }
+
+    /// Starts the booster and powers on, shared by the black/white and the 4-gray mode
+    fn power_on(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        // booster soft start
+        self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x07, 0x07, 0x17])?;
+        // power optimization
+        self.cmd_with_data(spi, Command::PowerOptimization, &[0x60, 0xa5])?;
+        self.cmd_with_data(spi, Command::PowerOptimization, &[0x89, 0xa5])?;
+        self.cmd_with_data(spi, Command::PowerOptimization, &[0x90, 0x00])?;
+        self.cmd_with_data(spi, Command::PowerOptimization, &[0x93, 0x2a])?;
+        self.cmd_with_data(spi, Command::PowerOptimization, &[0xa0, 0xa5])?;
+        self.cmd_with_data(spi, Command::PowerOptimization, &[0xa1, 0x00])?;
+        self.cmd_with_data(spi, Command::PowerOptimization, &[0x73, 0x41])?;
+        // partial display refresh
+        self.cmd_with_data(spi, Command::PartialDisplayRefresh, &[0x00])?;
+        // power on
+        self.command(spi, Command::PowerOn)?;
+        delay.delay_us(5000);
+        self.wait_until_idle(spi, delay)
+    }
+}
+
+/// Bits of each gray level in the old (`DataStartTransmission1`) and the new
+/// (`DataStartTransmission2`) data, indexed by the 2 bit value of the pixel
+///
+/// The pair of bits selects the LUT which drives the pixel from the old to the new state, so the
+/// gray LUTs stop each level at a different gray. The vendor code spells this out as a lookup
+/// of every level.
+const GRAY_LEVEL_BITS: [(u8, u8); 4] = [
+    // black: black to black
+    (0, 0),
+    // dark gray: black to white
+    (0, 1),
+    // light gray: white to black
+    (1, 0),
+    // white: white to white
+    (1, 1),
+];
+
+/// Converts two bytes of a 2 bits per pixel buffer into one byte of the old and one byte of the
+/// new data
+fn gray_planes(first: u8, second: u8) -> (u8, u8) {
+    let pixels = u16::from_be_bytes([first, second]);
+    (0..8).fold((0, 0), |(old, new), i| {
+        let (old_bit, new_bit) = GRAY_LEVEL_BITS[(pixels >> (14 - 2 * i)) as usize & 0b11];
+        (old | old_bit << (7 - i), new | new_bit << (7 - i))
+    })
 }
 
 #[cfg(test)]
@@ -277,6 +519,28 @@ mod tests {
     fn epd_size() {
         assert_eq!(WIDTH, 176);
         assert_eq!(HEIGHT, 264);
+        assert_eq!(GRAY_BUFFER_LEN, 11_616);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn gray_levels_of_a_test_pattern() {
+        // black, dark gray, light gray, white, white, light gray, dark gray, black
+        let (first, second) = (0b00_01_10_11, 0b11_10_01_00);
+        let (old, new) = gray_planes(first, second);
+        // light gray and white start from the white LUTs
+        assert_eq!(old, 0b0011_1100);
+        // dark gray and white end at white
+        assert_eq!(new, 0b0101_1010);
+
+        for (level, bits) in [
+            (0b00, (0x00, 0x00)),
+            (0b01, (0x00, 0xFF)),
+            (0b10, (0xFF, 0x00)),
+        ] {
+            let byte = level * 0b0101_0101;
+            assert_eq!(gray_planes(byte, byte), bits);
+        }
+        assert_eq!(gray_planes(0xFF, 0xFF), (0xFF, 0xFF));
+    }
 }
@@ -1,5 +1,12 @@
 //! A simple Driver for the Waveshare 2.7inch v2 e-Paper HAT Display via SPI
 //!
+//! The V2 uses a SSD1680 class controller and is not compatible with the original 2.7" panel,
+//! which is driven by [`epd2in7`](crate::epd2in7). The V2 boards have a "V2" marking on the
+//! sticker on the back of the module. With the wrong driver the display simply stays blank.
+//!
+//! Besides the full refresh the vendor's fast refresh is supported, select it with
+//! [`set_lut`](WaveshareDisplay::set_lut) and [`RefreshLut::Quick`].
+//!
 //! 4 Gray support and partial refresh is not fully implemented yet.
 //!
 //! # References
@@ -8,6 +15,7 @@
 //! - [Waveshare C driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_2in7_V2.c)
 //! - [Waveshare Python driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd2in7_V2.py)
 
+use crate::error::Error;
 use embedded_hal::{
     delay::DelayNs,
     digital::{InputPin, OutputPin},
@@ -15,10 +23,10 @@ use embedded_hal::{
 };
 
 use crate::{
-    buffer_len,
+    buffer_len, check_partial_window,
     color::Color,
     interface::DisplayInterface,
-    traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay},
+    traits::{check_refresh_lut, InternalWiAdditions, RefreshLut, WaveshareDisplay},
     type_a::command::Command,
 };
 
@@ -31,6 +39,8 @@ pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 
 const IS_BUSY_LOW: bool = false;
 const SINGLE_BYTE_WRITE: bool = true;
+/// Temperature which selects the fast waveform of the OTP
+const FAST_TEMPERATURE: i8 = 100;
 
 /// Full size buffer for use with the 2in7B EPD
 /// TODO this should be a TriColor, but let's keep it as is at first
@@ -44,26 +54,30 @@ pub type Display2in7 = crate::graphics::Display<
 >;
 
 /// Epd2in7b driver
-pub struct Epd2in7<SPI, BUSY, DC, RST, DELAY> {
+pub struct Epd2in7<SPI, BUSY, DC, RST, DELAY, PWR> {
     /// Connection Interface
-    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
     /// Background Color
     color: Color,
     refresh: RefreshLut,
+    /// Temperature of [`set_temperature`](WaveshareDisplay::set_temperature), `None` for the
+    /// internal sensor
+    temperature: Option<i8>,
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
-    for Epd2in7<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in7<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         // reset the device
-        self.interface.reset(delay, 200_000, 2_000);
+        self.interface.reset(delay, 200_000, 2_000)?;
 
         self.wait_until_idle(spi, delay)?;
         self.command(spi, Command::SwReset)?;
@@ -74,35 +88,44 @@ where
         self.interface
             .cmd_with_data(spi, Command::DataEntryModeSetting, &[0x03])?;
 
+        // keep the refresh mode and the temperature across a wake up
+        if self.waveform_temperature().is_some() {
+            self.load_temperature(spi, delay)?;
+        }
+
         Ok(())
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
-    for Epd2in7<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in7<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     type DisplayColor = Color;
+    const REFRESH_LUTS: &'static [RefreshLut] = &[RefreshLut::Full, RefreshLut::Quick];
     fn new(
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(busy, dc, rst, pwr, delay_us);
         let color = DEFAULT_BACKGROUND_COLOR;
 
         let mut epd = Epd2in7 {
             interface,
             color,
             refresh: RefreshLut::Full,
+            temperature: None,
         };
 
         epd.init(spi, delay)?;
@@ -110,15 +133,15 @@ where
         Ok(epd)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.interface
             .cmd_with_data(spi, Command::DeepSleepMode, &[0x01])?;
-        Ok(())
+        self.interface.power_off()
     }
 
     fn update_frame(
@@ -126,7 +149,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.use_full_frame(spi, delay)?;
         self.interface
@@ -134,6 +157,9 @@ where
         Ok(())
     }
 
+    /// Writes a window of the frame to the RAM, without refreshing the display
+    ///
+    /// `x` and `width` must be multiples of 8, the RAM is addressed in bytes.
     fn update_partial_frame(
         &mut self,
         spi: &mut SPI,
@@ -143,9 +169,11 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        check_partial_window(buffer.len(), x, y, width, height, WIDTH, HEIGHT)?;
+
         self.wait_until_idle(spi, delay)?;
-        self.set_ram_area(spi, x, y, x + width, y + height)?;
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)?;
         self.set_ram_counter(spi, delay, x, y)?;
 
         self.interface
@@ -154,19 +182,19 @@ where
         Ok(())
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
-        if self.refresh == RefreshLut::Full {
+        if self.refresh == RefreshLut::Full && self.temperature.is_none() {
             self.interface
                 .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xF7])?;
-        } else if self.refresh == RefreshLut::Quick {
+        } else {
+            // the waveform of the forced temperature is already loaded
             self.interface
                 .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xC7])?;
         }
 
         self.interface.cmd(spi, Command::MasterActivation)?;
-        self.wait_until_idle(spi, delay)?;
-        Ok(())
+        self.wait_until_idle(spi, delay)
     }
 
     fn update_and_display_frame(
@@ -174,13 +202,13 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.use_full_frame(spi, delay)?;
 
@@ -197,6 +225,18 @@ where
         self.color = color;
     }
 
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
     fn background_color(&self) -> &Color {
         &self.color
     }
@@ -209,33 +249,94 @@ where
         HEIGHT
     }
 
+    /// Switches between the full and the fast refresh
+    ///
+    /// The fast refresh loads its waveform by writing a fixed temperature to the controller,
+    /// the full refresh goes back to the internal temperature sensor.
     fn set_lut(
         &mut self,
-        _spi: &mut SPI,
-        _delay: &mut DELAY,
+        spi: &mut SPI,
+        delay: &mut DELAY,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        check_refresh_lut(refresh_rate, Self::REFRESH_LUTS)?;
         if let Some(refresh_lut) = refresh_rate {
             self.refresh = refresh_lut;
+            self.load_temperature(spi, delay)?;
         }
         Ok(())
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
-        Ok(())
+    fn read_temperature(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<i8, Error<SPI::Error>> {
+        self.load_sensor_temperature(spi, delay)?;
+        let mut celsius = [0; 2];
+        self.interface
+            .cmd_and_read(spi, Command::TemperatureSensorRead, &mut celsius)?;
+        self.force_temperature(spi, delay)?;
+        Ok(celsius[0] as i8)
+    }
+
+    fn set_temperature(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        celsius: Option<i8>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.temperature = celsius;
+        self.load_temperature(spi, delay)
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> Epd2in7<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd2in7<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    /// Writes a window of the frame to the RAM like
+    /// [`update_partial_frame`](WaveshareDisplay::update_partial_frame), with the data taken
+    /// from an iterator
+    ///
+    /// Exactly `width / 8 * height` bytes are taken, it fails with
+    /// `Error::InvalidBufferLength` if `data` ends early and leaves any further items unconsumed.
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_partial_frame_iter<I: IntoIterator<Item = u8>>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        data: I,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        let len = buffer_len(width as usize, height as usize);
+        check_partial_window(len, x, y, width, height, WIDTH, HEIGHT)?;
+
+        self.wait_until_idle(spi, delay)?;
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)?;
+        self.set_ram_counter(spi, delay, x, y)?;
+
+        self.interface.cmd(spi, Command::WriteRam)?;
+        self.interface.data_iter(spi, data, len)
+    }
+
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
@@ -246,9 +347,9 @@ where
         start_y: u32,
         end_x: u32,
         end_y: u32,
-    ) -> Result<(), SPI::Error> {
-        assert!(start_x < end_x);
-        assert!(start_y < end_y);
+    ) -> Result<(), Error<SPI::Error>> {
+        assert!(start_x <= end_x);
+        assert!(start_y <= end_y);
 
         self.interface.cmd_with_data(
             spi,
@@ -275,7 +376,7 @@ where
         delay: &mut DELAY,
         x: u32,
         y: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.interface
             .cmd_with_data(spi, Command::SetRamXAddressCounter, &[(x & 0xFF) as u8])?;
@@ -288,7 +389,63 @@ where
         Ok(())
     }
 
-    fn use_full_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    /// Loads the temperature from the internal sensor, and overwrites it for the fast refresh
+    /// or with the one of `set_temperature`
+    fn load_temperature(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.load_sensor_temperature(spi, delay)?;
+        self.force_temperature(spi, delay)
+    }
+
+    fn load_sensor_temperature(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.interface
+            .cmd_with_data(spi, Command::TemperatureSensorSelection, &[0x80])?;
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xB1])?;
+        self.interface.cmd(spi, Command::MasterActivation)?;
+        self.wait_until_idle(spi, delay)
+    }
+
+    fn force_temperature(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        if let Some(celsius) = self.waveform_temperature() {
+            self.interface.cmd_with_data(
+                spi,
+                Command::TemperatureSensorControl,
+                &[celsius as u8, 0x00],
+            )?;
+            self.interface
+                .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0x91])?;
+            self.interface.cmd(spi, Command::MasterActivation)?;
+            self.wait_until_idle(spi, delay)?;
+        }
+        Ok(())
+    }
+
+    /// Temperature written instead of the one of the internal sensor
+    fn waveform_temperature(&self) -> Option<i8> {
+        match self.refresh {
+            RefreshLut::Quick => Some(FAST_TEMPERATURE),
+            _ => self.temperature,
+        }
+    }
+
+    fn use_full_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
         // choose full frame/ram
         self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
 
@@ -299,7 +456,15 @@ where
 
 #[cfg(test)]
 mod tests {
+    extern crate std;
     use super::*;
+    use crate::NoPin;
+    use embedded_hal_mock::eh1::{
+        delay::NoopDelay,
+        digital::{Mock as PinMock, State, Transaction as PinTransaction},
+        spi::{Mock as SpiMock, Transaction as SpiTransaction},
+    };
+    use std::{vec, vec::Vec};
 
     #[test]
     fn epd_size() {
@@ -307,4 +472,63 @@ mod tests {
         assert_eq!(HEIGHT, 264);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn reading_the_temperature_keeps_the_fast_waveform() {
+        let mut spi_expectations = Vec::new();
+        let mut dc_expectations = Vec::new();
+        let commands: [(u8, &[u8]); 7] = [
+            (0x18, &[0x80]),
+            (0x22, &[0xB1]),
+            (0x20, &[]),
+            (0x1B, &[]),
+            (0x1A, &[0x64, 0x00]),
+            (0x22, &[0x91]),
+            (0x20, &[]),
+        ];
+        for (command, data) in commands {
+            dc_expectations.push(PinTransaction::set(State::Low));
+            if !data.is_empty() {
+                dc_expectations.push(PinTransaction::set(State::High));
+            }
+            for byte in core::iter::once(command).chain(data.iter().copied()) {
+                spi_expectations.push(SpiTransaction::transaction_start());
+                spi_expectations.push(SpiTransaction::write_vec(vec![byte]));
+                spi_expectations.push(SpiTransaction::transaction_end());
+            }
+            if command == 0x1B {
+                dc_expectations.push(PinTransaction::set(State::High));
+                spi_expectations.push(SpiTransaction::transaction_start());
+                spi_expectations.push(SpiTransaction::read_vec(vec![0x17, 0x00]));
+                spi_expectations.push(SpiTransaction::transaction_end());
+            }
+        }
+        let mut spi = SpiMock::new(&spi_expectations);
+        let mut busy = PinMock::new(&vec![PinTransaction::get(State::Low); 3]);
+        let mut dc = PinMock::new(&dc_expectations);
+        let mut rst = PinMock::new(&[]);
+
+        let mut epd = Epd2in7 {
+            interface: DisplayInterface::new(
+                busy.clone(),
+                dc.clone(),
+                Some(rst.clone()),
+                None::<NoPin>,
+                None,
+            ),
+            color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Quick,
+            temperature: None,
+        };
+        assert_eq!(
+            epd.read_temperature(&mut spi, &mut NoopDelay::new()),
+            Ok(23)
+        );
+        drop(epd);
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
 }
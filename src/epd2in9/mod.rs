@@ -5,7 +5,7 @@
 //!
 //!```rust, no_run
 //!# use embedded_hal_mock::eh1::*;
-//!# fn main() -> Result<(), embedded_hal::spi::ErrorKind> {
+//!# fn main() -> Result<(), epd_waveshare::error::Error<embedded_hal::spi::ErrorKind>> {
 //!use embedded_graphics::{
 //!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
 //!};
@@ -21,7 +21,7 @@
 //!# let mut delay = delay::NoopDelay::new();
 //!
 //!// Setup EPD
-//!let mut epd = Epd2in9::new(&mut spi, busy_in, dc, rst, &mut delay, None)?;
+//!let mut epd = Epd2in9::new(&mut spi, busy_in, dc, Some(rst), None::<NoPin>, &mut delay, None)?;
 //!
 //!// Use display graphics from embedded-graphics
 //!let mut display = Display2in9::default();
@@ -40,6 +40,20 @@
 //!# Ok(())
 //!# }
 //!```
+//!
+//! # Quick refresh
+//!
+//! The IL3820 doesn't have a RAM for the old frame, it keeps the image of the last refresh
+//! itself and the partial LUT only drives the pixels which changed since then. The
+//! [`QuickRefresh`] methods load that LUT when they are first used, switch back with
+//! `set_lut(.., Some(RefreshLut::Full))` for a full refresh, which should be done every few
+//! quick refreshes to remove the ghosting.
+//!
+//! # Custom LUT
+//!
+//! A tuned waveform can be loaded with [`Epd2in9::set_custom_lut`]. It replaces the built-in
+//! [`LUT_FULL_UPDATE`] and [`LUT_PARTIAL_UPDATE`] of every refresh mode, is loaded again on
+//! [`wake_up`](WaveshareDisplay::wake_up) and stays until [`Epd2in9::clear_custom_lut`].
 
 /// Width of epd2in9 in pixels
 pub const WIDTH: u32 = 128;
@@ -50,19 +64,18 @@ pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 const IS_BUSY_LOW: bool = false;
 const SINGLE_BYTE_WRITE: bool = true;
 
+use crate::error::Error;
 use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
 
-use crate::type_a::{
-    command::Command,
-    constants::{LUT_FULL_UPDATE, LUT_PARTIAL_UPDATE},
-};
+use crate::type_a::command::Command;
+pub use crate::type_a::constants::{CUSTOM_LUT_LEN, LUT_FULL_UPDATE, LUT_PARTIAL_UPDATE};
 
 use crate::color::Color;
 
 use crate::traits::*;
 
-use crate::buffer_len;
 use crate::interface::DisplayInterface;
+use crate::{buffer_len, check_partial_window};
 
 /// Display with Fullsize buffer for use with the 2in9 EPD
 #[cfg(feature = "graphics")]
@@ -76,25 +89,32 @@ pub type Display2in9 = crate::graphics::Display<
 
 /// Epd2in9 driver
 ///
-pub struct Epd2in9<SPI, BUSY, DC, RST, DELAY> {
+pub struct Epd2in9<SPI, BUSY, DC, RST, DELAY, PWR> {
     /// SPI
-    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
     /// Color
     background_color: Color,
     /// Refresh LUT
     refresh: RefreshLut,
+    /// Replaces the built-in LUTs if set
+    custom_lut: Option<[u8; CUSTOM_LUT_LEN]>,
+    /// VCOM register value of [`set_vcom`](WaveshareDisplay::set_vcom), replaces 0xA8 if set
+    vcom: Option<u8>,
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> Epd2in9<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd2in9<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.reset(delay, 10_000, 10_000);
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, 10_000, 10_000)?;
+        self.interface
+            .ssd_software_reset(spi, delay, Command::SwReset, IS_BUSY_LOW)?;
 
         self.wait_until_idle(spi, delay)?;
 
@@ -115,8 +135,11 @@ where
             .cmd_with_data(spi, Command::BoosterSoftStartControl, &[0xD7, 0xD6, 0x9D])?;
 
         // One Databyte with value 0xA8 for 7V VCOM
-        self.interface
-            .cmd_with_data(spi, Command::WriteVcomRegister, &[0xA8])?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::WriteVcomRegister,
+            &[self.vcom.unwrap_or(0xA8)],
+        )?;
 
         // One Databyte with default value 0x1A for 4 dummy lines per gate
         self.interface
@@ -135,16 +158,22 @@ where
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
-    for Epd2in9<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in9<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     type DisplayColor = Color;
+    const REFRESH_LUTS: &'static [RefreshLut] = &[
+        RefreshLut::Full,
+        RefreshLut::Quick,
+        RefreshLut::PartialRefresh,
+    ];
     fn width(&self) -> u32 {
         WIDTH
     }
@@ -157,16 +186,19 @@ where
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(busy, dc, rst, pwr, delay_us);
 
         let mut epd = Epd2in9 {
             interface,
             background_color: DEFAULT_BACKGROUND_COLOR,
             refresh: RefreshLut::Full,
+            custom_lut: None,
+            vcom: None,
         };
 
         epd.init(spi, delay)?;
@@ -174,16 +206,16 @@ where
         Ok(epd)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         // 0x00 for Normal mode (Power on Reset), 0x01 for Deep Sleep Mode
         //TODO: is 0x00 needed here? (see also epd1in54)
         self.interface
             .cmd_with_data(spi, Command::DeepSleepMode, &[0x00])?;
-        Ok(())
+        self.interface.power_off()
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.init(spi, delay)?;
         Ok(())
@@ -194,7 +226,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.use_full_frame(spi, delay)?;
 
@@ -203,7 +235,9 @@ where
         Ok(())
     }
 
-    //TODO: update description: last 3 bits will be ignored for width and x_pos
+    /// Writes a window of the frame to the RAM, without refreshing the display
+    ///
+    /// `x` and `width` must be multiples of 8, the RAM is addressed in bytes.
     fn update_partial_frame(
         &mut self,
         spi: &mut SPI,
@@ -213,9 +247,11 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        check_partial_window(buffer.len(), x, y, width, height, WIDTH, HEIGHT)?;
+
         self.wait_until_idle(spi, delay)?;
-        self.set_ram_area(spi, x, y, x + width, y + height)?;
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)?;
         self.set_ram_counter(spi, delay, x, y)?;
 
         self.interface
@@ -223,7 +259,7 @@ where
         Ok(())
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         // enable clock signal, enable cp, display pattern -> 0xC4 (tested with the arduino version)
         //TODO: test control_1 or control_2 with default value 0xFF (from the datasheet)
@@ -242,13 +278,13 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.use_full_frame(spi, delay)?;
 
@@ -265,6 +301,31 @@ where
         self.background_color = background_color;
     }
 
+    fn set_vcom(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        millivolts: i16,
+    ) -> Result<(), Error<SPI::Error>> {
+        let vcom = ssd_vcom(millivolts)?;
+        self.vcom = Some(vcom);
+        self.wait_until_idle(spi, delay)?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteVcomRegister, &[vcom])
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
     fn background_color(&self) -> &Color {
         &self.background_color
     }
@@ -274,33 +335,185 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        check_refresh_lut(refresh_rate, Self::REFRESH_LUTS)?;
         if let Some(refresh_lut) = refresh_rate {
             self.refresh = refresh_lut;
         }
-        match self.refresh {
-            RefreshLut::Full => self.set_lut_helper(spi, delay, &LUT_FULL_UPDATE),
-            RefreshLut::Quick | RefreshLut::PartialRefresh => {
-                self.set_lut_helper(spi, delay, &LUT_PARTIAL_UPDATE)
-            }
-        }
+        let lut = match (self.custom_lut, self.refresh) {
+            (Some(lut), _) => lut,
+            (None, RefreshLut::Full) => LUT_FULL_UPDATE,
+            (None, RefreshLut::Quick | RefreshLut::PartialRefresh) => LUT_PARTIAL_UPDATE,
+        };
+        self.set_lut_helper(spi, delay, &lut)
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
-        Ok(())
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> QuickRefresh<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in9<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Writes the frame which is on the display to the RAM
+    ///
+    /// The controller keeps the image of the last refresh itself, this only makes sure the RAM
+    /// matches it outside of the windows which are updated afterwards.
+    fn update_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)
+    }
+
+    /// Loads the partial LUT and writes the new frame, to be shown with `display_new_frame`
+    fn update_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.use_quick_lut(spi, delay)?;
+        self.update_frame(spi, buffer, delay)
+    }
+
+    /// Refreshes the display with the partial LUT
+    fn display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.display_frame(spi, delay)
+    }
+
+    /// Updates and displays the new frame.
+    fn update_and_display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_new_frame(spi, buffer, delay)?;
+        self.display_new_frame(spi, delay)
+    }
+
+    /// Writes the old content of a window to the RAM
+    ///
+    /// `x` and `width` must be multiples of 8.
+    fn update_partial_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_partial_frame(spi, delay, buffer, x, y, width, height)
+    }
+
+    /// Loads the partial LUT and writes the new content of a window, to be shown with
+    /// `display_new_frame`
+    ///
+    /// `x` and `width` must be multiples of 8.
+    fn update_partial_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        // don't load the LUT for a window which is rejected
+        check_partial_window(buffer.len(), x, y, width, height, WIDTH, HEIGHT)?;
+        self.use_quick_lut(spi, delay)?;
+        self.update_partial_frame(spi, delay, buffer, x, y, width, height)
+    }
+
+    /// Clears a window of the RAM with the background color
+    ///
+    /// `x` and `width` must be multiples of 8.
+    fn clear_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        let len = buffer_len(width as usize, height as usize);
+        check_partial_window(len, x, y, width, height, WIDTH, HEIGHT)?;
+
+        self.wait_until_idle(spi, delay)?;
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)?;
+        self.set_ram_counter(spi, delay, x, y)?;
+
+        let color = self.background_color.get_byte_value();
+        self.interface.cmd(spi, Command::WriteRam)?;
+        self.interface.data_x_times(spi, color, len as u32)
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> Epd2in9<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd2in9<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn use_full_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    /// Writes a window of the frame to the RAM like
+    /// [`update_partial_frame`](WaveshareDisplay::update_partial_frame), with the data taken
+    /// from an iterator
+    ///
+    /// Exactly `width / 8 * height` bytes are taken, it fails with
+    /// `Error::InvalidBufferLength` if `data` ends early and leaves any further items unconsumed.
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_partial_frame_iter<I: IntoIterator<Item = u8>>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        data: I,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        let len = buffer_len(width as usize, height as usize);
+        check_partial_window(len, x, y, width, height, WIDTH, HEIGHT)?;
+
+        self.wait_until_idle(spi, delay)?;
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)?;
+        self.set_ram_counter(spi, delay, x, y)?;
+
+        self.interface.cmd(spi, Command::WriteRam)?;
+        self.interface.data_iter(spi, data, len)
+    }
+
+    fn use_full_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
         // choose full frame/ram
         self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
 
@@ -315,9 +528,9 @@ where
         start_y: u32,
         end_x: u32,
         end_y: u32,
-    ) -> Result<(), SPI::Error> {
-        assert!(start_x < end_x);
-        assert!(start_y < end_y);
+    ) -> Result<(), Error<SPI::Error>> {
+        assert!(start_x <= end_x);
+        assert!(start_y <= end_y);
 
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
@@ -346,7 +559,7 @@ where
         delay: &mut DELAY,
         x: u32,
         y: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
@@ -362,13 +575,46 @@ where
         Ok(())
     }
 
+    /// Loads the partial LUT, unless it's already in use
+    fn use_quick_lut(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        if self.refresh == RefreshLut::Full {
+            self.set_lut(spi, delay, Some(RefreshLut::Quick))?;
+        }
+        Ok(())
+    }
+
+    /// Loads `lut` instead of the built-in waveforms, see [`CUSTOM_LUT_LEN`] for its layout
+    ///
+    /// The refresh mode selected with [`set_lut`](WaveshareDisplay::set_lut) still decides how
+    /// the frame is displayed. Fails with `Error::InvalidBufferLength` if `lut` doesn't have
+    /// `CUSTOM_LUT_LEN` bytes.
+    pub fn set_custom_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        lut: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.custom_lut = Some(copy_lut(lut)?);
+        self.set_lut(spi, delay, None)
+    }
+
+    /// Drops the LUT of `set_custom_lut` and loads the built-in one of the refresh mode again
+    pub fn clear_custom_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.custom_lut = None;
+        self.set_lut(spi, delay, None)
+    }
+
     /// Set your own LUT, this function is also used internally for set_lut
     fn set_lut_helper(
         &mut self,
         spi: &mut SPI,
         delay: &mut DELAY,
         buffer: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         assert!(buffer.len() == 30);
         self.interface
@@ -379,7 +625,53 @@ where
 
 #[cfg(test)]
 mod tests {
+    extern crate std;
     use super::*;
+    use crate::interface::mock::expect_commands;
+    use crate::NoPin;
+    use embedded_hal_mock::eh1::{
+        delay::NoopDelay,
+        digital::{Mock as PinMock, State, Transaction as PinTransaction},
+        spi::Mock as SpiMock,
+    };
+    use std::vec;
+
+    /// Runs `f` on a driver which expects `commands` and `busy_reads` idle busy reads
+    fn with_epd(
+        commands: &[(u8, &[u8])],
+        busy_reads: usize,
+        f: impl FnOnce(
+            &mut Epd2in9<SpiMock<u8>, PinMock, PinMock, PinMock, NoopDelay, NoPin>,
+            &mut SpiMock<u8>,
+        ),
+    ) {
+        let (spi_expectations, dc_expectations) = expect_commands(commands);
+        let mut spi = SpiMock::new(&spi_expectations);
+        let mut busy = PinMock::new(&vec![PinTransaction::get(State::Low); busy_reads]);
+        let mut dc = PinMock::new(&dc_expectations);
+        let mut rst = PinMock::new(&[]);
+
+        let mut epd = Epd2in9 {
+            interface: DisplayInterface::new(
+                busy.clone(),
+                dc.clone(),
+                Some(rst.clone()),
+                None::<NoPin>,
+                None,
+            ),
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+            custom_lut: None,
+            vcom: None,
+        };
+        f(&mut epd, &mut spi);
+        drop(epd);
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
 
     #[test]
     fn epd_size() {
@@ -387,4 +679,76 @@ mod tests {
         assert_eq!(HEIGHT, 296);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn is_busy_reads_busy_high() {
+        // BUSY is high while the controller is busy, the low line is idle
+        with_epd(&[], 1, |epd, _| assert_eq!(epd.is_busy(), Ok(false)));
+    }
+
+    #[test]
+    fn window_at_x_3() {
+        assert_eq!(
+            check_partial_window::<()>(buffer_len(16, 10), 3, 0, 16, 10, WIDTH, HEIGHT),
+            Err(Error::OutOfBounds {
+                x: 3,
+                y: 0,
+                width: 16,
+                height: 10
+            })
+        );
+    }
+
+    #[test]
+    fn window_with_odd_width() {
+        assert_eq!(
+            check_partial_window::<()>(buffer_len(3, 10), 8, 0, 3, 10, WIDTH, HEIGHT),
+            Err(Error::OutOfBounds {
+                x: 8,
+                y: 0,
+                width: 3,
+                height: 10
+            })
+        );
+    }
+
+    #[test]
+    fn quick_lut_is_loaded_once() {
+        let buffer = [0x0F; 2 * 4];
+        let window: [(u8, &[u8]); 5] = [
+            (0x44, &[0x01, 0x02]),
+            (0x45, &[0x0A, 0x00, 0x0D, 0x00]),
+            (0x4E, &[0x01]),
+            (0x4F, &[0x0A, 0x00]),
+            (0x24, &buffer),
+        ];
+        let mut commands = vec![(0x32, &LUT_PARTIAL_UPDATE[..])];
+        commands.extend_from_slice(&window);
+        commands.extend_from_slice(&window);
+        commands.push((0x32, &LUT_FULL_UPDATE));
+        with_epd(&commands, 6, |epd, spi| {
+            let mut delay = NoopDelay::new();
+            for _ in 0..2 {
+                epd.update_partial_new_frame(spi, &mut delay, &buffer, 8, 10, 16, 4)
+                    .unwrap();
+            }
+            epd.set_lut(spi, &mut delay, Some(RefreshLut::Full))
+                .unwrap();
+        });
+    }
+
+    #[test]
+    fn misaligned_window_keeps_the_lut() {
+        with_epd(&[], 0, |epd, spi| {
+            assert_eq!(
+                epd.update_partial_new_frame(spi, &mut NoopDelay::new(), &[0; 6], 4, 0, 16, 3),
+                Err(Error::OutOfBounds {
+                    x: 4,
+                    y: 0,
+                    width: 16,
+                    height: 3
+                })
+            );
+        });
+    }
 }
@@ -6,7 +6,7 @@
 //!
 //!```rust, no_run
 //!# use embedded_hal_mock::eh1::*;
-//!# fn main() -> Result<(), embedded_hal::spi::ErrorKind> {
+//!# fn main() -> Result<(), epd_waveshare::error::Error<embedded_hal::spi::ErrorKind>> {
 //!use embedded_graphics::{
 //!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
 //!};
@@ -22,7 +22,7 @@
 //!# let mut delay = delay::NoopDelay::new();
 //!
 //!// Setup EPD
-//!let mut epd = Epd2in9::new(&mut spi, busy_in, dc, rst, &mut delay, None)?;
+//!let mut epd = Epd2in9::new(&mut spi, busy_in, dc, Some(rst), None::<NoPin>, &mut delay, None)?;
 //!
 //!// Use display graphics from embedded-graphics
 //!let mut display = Display2in9::default();
@@ -53,6 +53,21 @@
 //!# Ok(())
 //!# }
 //!```
+//!
+//! # Partial windows
+//!
+//! [`update_partial_frame`](WaveshareDisplay::update_partial_frame) and the partial functions of
+//! [`QuickRefresh`] restrict the RAM to a window (0x44/0x45) and set the address counters
+//! (0x4E/0x4F) to its start. The RAM is addressed in bytes, so `x` and `width` must be multiples
+//! of 8, other values fail with `Error::OutOfBounds` instead of being rounded.
+//!
+//! # Custom LUT
+//!
+//! The full refresh runs with the waveform [`WS_20_30`] loaded by the host, which
+//! [`Epd2in9::set_custom_lut`] replaces until [`Epd2in9::clear_custom_lut`], also after
+//! [`wake_up`](WaveshareDisplay::wake_up). The quick refresh keeps [`LUT_PARTIAL_2IN9`].
+//!
+//! The last byte of both LUTs is the VCOM, [`Epd2in9::set_vcom`] overrides it for every refresh.
 
 /// Width of epd2in9 in pixels
 pub const WIDTH: u32 = 128;
@@ -60,10 +75,21 @@ pub const WIDTH: u32 = 128;
 pub const HEIGHT: u32 = 296;
 /// Default Background Color (white)
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
-const IS_BUSY_LOW: bool = false;
 const SINGLE_BYTE_WRITE: bool = true;
-
-const LUT_PARTIAL_2IN9: [u8; 159] = [
+/// Length of a LUT for [`Epd2in9::set_custom_lut`]
+///
+/// 153 bytes of waveform (0x32), followed by the end option (0x3F), the gate voltage (0x03), 3
+/// bytes of source voltages (0x04) and the VCOM (0x2C).
+pub const CUSTOM_LUT_LEN: usize = 159;
+
+const PANEL: Panel = Panel {
+    width: WIDTH,
+    height: HEIGHT,
+    x_offset: 0,
+};
+
+/// Built-in LUT of the quick refresh
+pub const LUT_PARTIAL_2IN9: [u8; CUSTOM_LUT_LEN] = [
     0x0, 0x40, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x80, 0x80, 0x0, 0x0, 0x0, 0x0,
     0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x40, 0x40, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
     0x0, 0x80, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
@@ -75,7 +101,8 @@ const LUT_PARTIAL_2IN9: [u8; 159] = [
     0x22, 0x0, 0x0, 0x0, 0x22, 0x17, 0x41, 0xB0, 0x32, 0x36,
 ];
 
-const WS_20_30: [u8; 159] = [
+/// Built-in LUT of the full refresh, e.g. to pass to [`Epd2in9::set_custom_lut`]
+pub const WS_20_30: [u8; CUSTOM_LUT_LEN] = [
     0x80, 0x66, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x40, 0x0, 0x0, 0x0, 0x10, 0x66, 0x0, 0x0, 0x0, 0x0,
     0x0, 0x0, 0x20, 0x0, 0x0, 0x0, 0x80, 0x66, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x40, 0x0, 0x0, 0x0,
     0x10, 0x66, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x20, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
@@ -87,17 +114,18 @@ const WS_20_30: [u8; 159] = [
     0x44, 0x44, 0x0, 0x0, 0x0, 0x22, 0x17, 0x41, 0x0, 0x32, 0x36,
 ];
 
+use crate::error::Error;
 use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
 
-use crate::type_a::command::Command;
+use crate::controllers::ssd1680::{self, command::Command, Panel};
 
 use crate::color::Color;
 
 use crate::traits::*;
 
-use crate::buffer_len;
 use crate::interface::DisplayInterface;
 use crate::traits::QuickRefresh;
+use crate::{buffer_len, check_partial_window};
 
 /// Display with Fullsize buffer for use with the 2in9 EPD V2
 #[cfg(feature = "graphics")]
@@ -111,74 +139,73 @@ pub type Display2in9 = crate::graphics::Display<
 
 /// Epd2in9 driver
 ///
-pub struct Epd2in9<SPI, BUSY, DC, RST, DELAY> {
+pub struct Epd2in9<SPI, BUSY, DC, RST, DELAY, PWR> {
     /// SPI
-    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
     /// Color
     background_color: Color,
-    /// Refresh LUT
-    refresh: RefreshLut,
+    /// Replaces `WS_20_30` if set
+    custom_lut: Option<[u8; CUSTOM_LUT_LEN]>,
+    /// VCOM register value of [`set_vcom`](Self::set_vcom), replaces the one of the LUTs if set
+    vcom: Option<u8>,
+    /// Border waveform of `set_border`, replaces the default of each mode if set
+    border: Option<u8>,
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> Epd2in9<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd2in9<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.reset(delay, 10_000, 2_000);
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, 10_000, 2_000)?;
+        ssd1680::software_reset(&mut self.interface, spi, delay)?;
 
-        self.wait_until_idle(spi, delay)?;
-        self.interface.cmd(spi, Command::SwReset)?;
-        self.wait_until_idle(spi, delay)?;
-
-        // 3 Databytes:
-        // A[7:0]
-        // 0.. A[8]
-        // 0.. B[2:0]
-        // Default Values: A = Height of Screen (0x127), B = 0x00 (GD, SM and TB=0?)
-        self.interface
-            .cmd_with_data(spi, Command::DriverOutputControl, &[0x27, 0x01, 0x00])?;
-
-        // One Databyte with default value 0x03
-        //  -> address: x increment, y increment, address counter is updated in x direction
-        self.interface
-            .cmd_with_data(spi, Command::DataEntryModeSetting, &[0x03])?;
-
-        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
+        // 296 lines, address counter is updated in x direction
+        ssd1680::set_driver_output(&mut self.interface, spi, PANEL)?;
 
         self.interface
             .cmd_with_data(spi, Command::DisplayUpdateControl1, &[0x00, 0x80])?;
 
-        self.set_ram_counter(spi, delay, 0, 0)?;
+        ssd1680::set_window(&mut self.interface, spi, PANEL, 0, 0, WIDTH, HEIGHT)?;
 
         self.wait_until_idle(spi, delay)?;
 
+        if let Some(border) = self.border {
+            self.interface
+                .cmd_with_data(spi, Command::BorderWaveformControl, &[border])?;
+        }
+
         // set LUT by host
-        self.set_lut_helper(spi, delay, &WS_20_30[0..153])?;
-        self.interface
-            .cmd_with_data(spi, Command::WriteLutRegisterEnd, &WS_20_30[153..154])?;
+        self.load_lut(spi, delay)
+    }
+
+    /// Loads the LUT of the full refresh with its voltages
+    fn load_lut(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let lut = self.custom_lut.unwrap_or(WS_20_30);
+        self.set_lut_helper(spi, delay, &lut[0..153])?;
         self.interface
-            .cmd_with_data(spi, Command::GateDrivingVoltage, &WS_20_30[154..155])?;
+            .cmd_with_data(spi, Command::WriteLutRegisterEnd, &lut[153..154])?;
         self.interface
-            .cmd_with_data(spi, Command::SourceDrivingVoltage, &WS_20_30[155..158])?;
+            .cmd_with_data(spi, Command::GateDrivingVoltage, &lut[154..155])?;
         self.interface
-            .cmd_with_data(spi, Command::WriteVcomRegister, &WS_20_30[158..159])?;
-
-        Ok(())
+            .cmd_with_data(spi, Command::SourceDrivingVoltage, &lut[155..158])?;
+        ssd1680::write_vcom(&mut self.interface, spi, self.vcom.unwrap_or(lut[158]))
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
-    for Epd2in9<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in9<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     type DisplayColor = Color;
@@ -194,16 +221,19 @@ where
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(busy, dc, rst, pwr, delay_us);
 
         let mut epd = Epd2in9 {
             interface,
             background_color: DEFAULT_BACKGROUND_COLOR,
-            refresh: RefreshLut::Full,
+            custom_lut: None,
+            vcom: None,
+            border: None,
         };
 
         epd.init(spi, delay)?;
@@ -211,15 +241,12 @@ where
         Ok(epd)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle(spi, delay)?;
-        // 0x00 for Normal mode (Power on Reset), 0x01 for Deep Sleep Mode
-        self.interface
-            .cmd_with_data(spi, Command::DeepSleepMode, &[0x01])?;
-        Ok(())
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        ssd1680::deep_sleep(&mut self.interface, spi, delay)?;
+        self.interface.power_off()
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.init(spi, delay)?;
         Ok(())
     }
@@ -229,11 +256,20 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle(spi, delay)?;
-        self.interface.cmd_with_data(spi, Command::WriteRam, buffer)
+    ) -> Result<(), Error<SPI::Error>> {
+        ssd1680::write_frame(
+            &mut self.interface,
+            spi,
+            delay,
+            PANEL,
+            Command::WriteRam,
+            buffer,
+        )
     }
 
+    /// Writes a window of the frame to the RAM, without refreshing the display
+    ///
+    /// `x` and `width` must be multiples of 8, the RAM is addressed in bytes.
     fn update_partial_frame(
         &mut self,
         spi: &mut SPI,
@@ -243,26 +279,25 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
-        //TODO This is copied from epd2in9 but it seems not working. Partial refresh supported by version 2?
-        self.wait_until_idle(spi, delay)?;
-        self.set_ram_area(spi, x, y, x + width, y + height)?;
-        self.set_ram_counter(spi, delay, x, y)?;
-
-        self.interface
-            .cmd_with_data(spi, Command::WriteRam, buffer)?;
-        Ok(())
+    ) -> Result<(), Error<SPI::Error>> {
+        ssd1680::write_window(
+            &mut self.interface,
+            spi,
+            delay,
+            PANEL,
+            Command::WriteRam,
+            buffer,
+            x,
+            y,
+            width,
+            height,
+        )
     }
 
     /// actually is the "Turn on Display" sequence
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle(spi, delay)?;
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         // Enable clock signal, Enable Analog, Load temperature value, DISPLAY with DISPLAY Mode 1, Disable Analog, Disable OSC
-        self.interface
-            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xC7])?;
-        self.interface.cmd(spi, Command::MasterActivation)?;
-        self.wait_until_idle(spi, delay)?;
-        Ok(())
+        ssd1680::activate(&mut self.interface, spi, delay, 0xC7)
     }
 
     fn update_and_display_frame(
@@ -270,119 +305,161 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle(spi, delay)?;
-
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         // clear the ram with the background color
         let color = self.background_color.get_byte_value();
-
-        self.interface.cmd(spi, Command::WriteRam)?;
-        self.interface
-            .data_x_times(spi, color, WIDTH / 8 * HEIGHT)?;
-        self.interface.cmd(spi, Command::WriteRam2)?;
-        self.interface.data_x_times(spi, color, WIDTH / 8 * HEIGHT)
+        for ram in [Command::WriteRam, Command::WriteRam2] {
+            ssd1680::fill_frame(&mut self.interface, spi, delay, PANEL, ram, color)?;
+        }
+        Ok(())
     }
 
     fn set_background_color(&mut self, background_color: Color) {
         self.background_color = background_color;
     }
 
+    /// Replaces the VCOM byte of the full and the quick LUT, also of one given to
+    /// [`set_custom_lut`](Epd2in9::set_custom_lut)
+    fn set_vcom(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        millivolts: i16,
+    ) -> Result<(), Error<SPI::Error>> {
+        let vcom = ssd_vcom(millivolts)?;
+        self.vcom = Some(vcom);
+        self.wait_until_idle(spi, delay)?;
+        ssd1680::write_vcom(&mut self.interface, spi, vcom)
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(ssd1680::IS_BUSY_LOW)
+    }
+
     fn background_color(&self) -> &Color {
         &self.background_color
     }
 
-    fn set_lut(
-        &mut self,
-        _spi: &mut SPI,
-        _delay: &mut DELAY,
-        refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
-        if let Some(refresh_lut) = refresh_rate {
-            self.refresh = refresh_lut;
-        }
-        Ok(())
+    fn set_border(&mut self, spi: &mut SPI, border: BorderColor) -> Result<(), Error<SPI::Error>> {
+        let border = ssd_border(border, false)?;
+        self.border = Some(border);
+        self.interface
+            .cmd_with_data(spi, Command::BorderWaveformControl, &[border])
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        ssd1680::wait_until_idle(&mut self.interface, delay)?;
         Ok(())
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> Epd2in9<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd2in9<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn use_full_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        // choose full frame/ram
-        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
-
-        // start from the beginning
-        self.set_ram_counter(spi, delay, 0, 0)
+    /// Writes a window of the frame to the RAM like
+    /// [`update_partial_frame`](WaveshareDisplay::update_partial_frame), with the data taken
+    /// from an iterator
+    ///
+    /// Exactly `width / 8 * height` bytes are taken, it fails with
+    /// `Error::InvalidBufferLength` if `data` ends early and leaves any further items unconsumed.
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_partial_frame_iter<I: IntoIterator<Item = u8>>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        data: I,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        ssd1680::write_window_iter(
+            &mut self.interface,
+            spi,
+            delay,
+            PANEL,
+            Command::WriteRam,
+            data,
+            x,
+            y,
+            width,
+            height,
+        )
     }
 
-    fn set_ram_area(
+    /// Loads `lut` instead of [`WS_20_30`] for the full refresh, see [`CUSTOM_LUT_LEN`] for its
+    /// layout
+    ///
+    /// Fails with `Error::InvalidBufferLength` if `lut` doesn't have `CUSTOM_LUT_LEN` bytes.
+    pub fn set_custom_lut(
         &mut self,
         spi: &mut SPI,
-        start_x: u32,
-        start_y: u32,
-        end_x: u32,
-        end_y: u32,
-    ) -> Result<(), SPI::Error> {
-        assert!(start_x < end_x);
-        assert!(start_y < end_y);
-
-        // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
-        // aren't relevant
-        self.interface.cmd_with_data(
-            spi,
-            Command::SetRamXAddressStartEndPosition,
-            &[(start_x >> 3) as u8, (end_x >> 3) as u8],
-        )?;
+        delay: &mut DELAY,
+        lut: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.custom_lut = Some(copy_lut(lut)?);
+        self.load_lut(spi, delay)
+    }
 
-        // 2 Databytes: A[7:0] & 0..A[8] for each - start and end
-        self.interface.cmd_with_data(
-            spi,
-            Command::SetRamYAddressStartEndPosition,
-            &[
-                start_y as u8,
-                (start_y >> 8) as u8,
-                end_y as u8,
-                (end_y >> 8) as u8,
-            ],
-        )
+    /// Drops the LUT of `set_custom_lut` and loads `WS_20_30` again
+    pub fn clear_custom_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.custom_lut = None;
+        self.load_lut(spi, delay)
     }
 
-    fn set_ram_counter(
+    /// Loads the partial LUT, the RAM content is kept
+    fn load_partial_lut(
         &mut self,
         spi: &mut SPI,
         delay: &mut DELAY,
-        x: u32,
-        y: u32,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle(spi, delay)?;
-        // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
-        // aren't relevant
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, 10_000, 2_000)?;
         self.interface
-            .cmd_with_data(spi, Command::SetRamXAddressCounter, &[x as u8])?;
+            .ssd_software_reset(spi, delay, Command::SwReset, ssd1680::IS_BUSY_LOW)?;
 
-        // 2 Databytes: A[7:0] & 0..A[8]
+        self.set_lut_helper(spi, delay, &LUT_PARTIAL_2IN9)?;
+        if let Some(vcom) = self.vcom {
+            ssd1680::write_vcom(&mut self.interface, spi, vcom)?;
+        }
         self.interface.cmd_with_data(
             spi,
-            Command::SetRamYAddressCounter,
-            &[y as u8, (y >> 8) as u8],
+            Command::WriteOtpSelection,
+            &[0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00],
         )?;
-        Ok(())
+        self.interface.cmd_with_data(
+            spi,
+            Command::BorderWaveformControl,
+            &[self.border.unwrap_or(0x80)],
+        )?;
+        ssd1680::activate(&mut self.interface, spi, delay, 0xC0)
     }
 
     /// Set your own LUT, this function is also used internally for set_lut
@@ -391,22 +468,22 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         buffer: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.interface
             .cmd_with_data(spi, Command::WriteLutRegister, buffer)?;
-        self.wait_until_idle(spi, delay)?;
-        Ok(())
+        self.wait_until_idle(spi, delay)
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> QuickRefresh<SPI, BUSY, DC, RST, DELAY>
-    for Epd2in9<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> QuickRefresh<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in9<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     /// To be followed immediately by `update_new_frame`.
@@ -415,12 +492,11 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle(spi, delay)?;
-        self.interface
-            .cmd_with_data(spi, Command::WriteRam, buffer)?;
-        self.interface
-            .cmd_with_data(spi, Command::WriteRam2, buffer)
+    ) -> Result<(), Error<SPI::Error>> {
+        for ram in [Command::WriteRam, Command::WriteRam2] {
+            ssd1680::write_frame(&mut self.interface, spi, delay, PANEL, ram, buffer)?;
+        }
+        Ok(())
     }
 
     /// To be used immediately after `update_old_frame`.
@@ -429,39 +505,27 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
-        self.interface.reset(delay, 10_000, 2_000);
+        self.load_partial_lut(spi, delay)?;
 
-        self.set_lut_helper(spi, delay, &LUT_PARTIAL_2IN9)?;
-        self.interface.cmd_with_data(
+        ssd1680::write_frame(
+            &mut self.interface,
             spi,
-            Command::WriteOtpSelection,
-            &[0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00],
-        )?;
-        self.interface
-            .cmd_with_data(spi, Command::BorderWaveformControl, &[0x80])?;
-        self.interface
-            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xC0])?;
-        self.interface.cmd(spi, Command::MasterActivation)?;
-
-        self.wait_until_idle(spi, delay)?;
-
-        self.use_full_frame(spi, delay)?;
-
-        self.interface
-            .cmd_with_data(spi, Command::WriteRam, buffer)?;
-        Ok(())
+            delay,
+            PANEL,
+            Command::WriteRam,
+            buffer,
+        )
     }
 
     /// For a quick refresh of the new updated frame. To be used immediately after `update_new_frame`
-    fn display_new_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle(spi, delay)?;
-        self.interface
-            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0x0F])?;
-        self.interface.cmd(spi, Command::MasterActivation)?;
-        self.wait_until_idle(spi, delay)?;
-        Ok(())
+    fn display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        ssd1680::activate(&mut self.interface, spi, delay, 0x0F)
     }
 
     /// Updates and displays the new frame.
@@ -470,14 +534,16 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_new_frame(spi, buffer, delay)?;
         self.display_new_frame(spi, delay)?;
         Ok(())
     }
 
-    /// Partial quick refresh not supported yet
-    #[allow(unused)]
+    /// Writes the old content of a window to both RAMs, to be followed by
+    /// `update_partial_new_frame` with the same window
+    ///
+    /// `x` and `width` must be multiples of 8.
     fn update_partial_old_frame(
         &mut self,
         spi: &mut SPI,
@@ -487,13 +553,28 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
-        //TODO supported by display?
-        unimplemented!()
+    ) -> Result<(), Error<SPI::Error>> {
+        for ram in [Command::WriteRam, Command::WriteRam2] {
+            ssd1680::write_window(
+                &mut self.interface,
+                spi,
+                delay,
+                PANEL,
+                ram,
+                buffer,
+                x,
+                y,
+                width,
+                height,
+            )?;
+        }
+        Ok(())
     }
 
-    /// Partial quick refresh not supported yet
-    #[allow(unused)]
+    /// Loads the partial LUT and writes the new content of a window, to be shown with
+    /// `display_new_frame`
+    ///
+    /// `x` and `width` must be multiples of 8.
     fn update_partial_new_frame(
         &mut self,
         spi: &mut SPI,
@@ -503,13 +584,29 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
-        //TODO supported by display?
-        unimplemented!()
+    ) -> Result<(), Error<SPI::Error>> {
+        // don't load the LUT for a window which is rejected
+        check_partial_window(buffer.len(), x, y, width, height, WIDTH, HEIGHT)?;
+        self.wait_until_idle(spi, delay)?;
+        self.load_partial_lut(spi, delay)?;
+
+        ssd1680::write_window(
+            &mut self.interface,
+            spi,
+            delay,
+            PANEL,
+            Command::WriteRam,
+            buffer,
+            x,
+            y,
+            width,
+            height,
+        )
     }
 
-    /// Partial quick refresh not supported yet
-    #[allow(unused)]
+    /// Clears a window of both RAMs with the background color
+    ///
+    /// `x` and `width` must be multiples of 8.
     fn clear_partial_frame(
         &mut self,
         spi: &mut SPI,
@@ -518,15 +615,38 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
-        //TODO supported by display?
-        unimplemented!()
+    ) -> Result<(), Error<SPI::Error>> {
+        let color = self.background_color.get_byte_value();
+        for ram in [Command::WriteRam, Command::WriteRam2] {
+            ssd1680::fill_window(
+                &mut self.interface,
+                spi,
+                delay,
+                PANEL,
+                ram,
+                color,
+                x,
+                y,
+                width,
+                height,
+            )?;
+        }
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
+    extern crate std;
     use super::*;
+    use crate::interface::mock::expect_commands;
+    use crate::NoPin;
+    use embedded_hal_mock::eh1::{
+        delay::NoopDelay,
+        digital::{Mock as PinMock, State, Transaction as PinTransaction},
+        spi::Mock as SpiMock,
+    };
+    use std::vec;
 
     #[test]
     fn epd_size() {
@@ -534,4 +654,168 @@ mod tests {
         assert_eq!(HEIGHT, 296);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn window_at_the_last_column() {
+        let buffer = [0xAA; 4 * 2];
+        let (spi_expectations, dc_expectations) = expect_commands(&[
+            (0x44, &[0x0C, 0x0F]),
+            (0x45, &[0x0A, 0x00, 0x0B, 0x00]),
+            (0x4E, &[0x0C]),
+            (0x4F, &[0x0A, 0x00]),
+            (0x24, &buffer),
+        ]);
+        let mut spi = SpiMock::new(&spi_expectations);
+        let mut busy = PinMock::new(&[PinTransaction::get(State::Low)]);
+        let mut dc = PinMock::new(&dc_expectations);
+        let mut rst = PinMock::new(&[]);
+
+        let mut epd = Epd2in9 {
+            interface: DisplayInterface::new(
+                busy.clone(),
+                dc.clone(),
+                Some(rst.clone()),
+                None::<NoPin>,
+                None,
+            ),
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            custom_lut: None,
+            vcom: None,
+            border: None,
+        };
+        epd.update_partial_frame(&mut spi, &mut NoopDelay::new(), &buffer, 96, 10, 32, 2)
+            .unwrap();
+        drop(epd);
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
+
+    /// Commands loading a LUT of the full refresh
+    fn lut_commands(lut: &[u8]) -> [(u8, &[u8]); 5] {
+        [
+            (0x32, &lut[0..153]),
+            (0x3F, &lut[153..154]),
+            (0x03, &lut[154..155]),
+            (0x04, &lut[155..158]),
+            (0x2C, &lut[158..159]),
+        ]
+    }
+
+    #[test]
+    fn custom_lut_until_cleared() {
+        let custom = [0x42; CUSTOM_LUT_LEN];
+        let commands = [lut_commands(&custom), lut_commands(&WS_20_30)].concat();
+        let (spi_expectations, dc_expectations) = expect_commands(&commands);
+        let mut spi = SpiMock::new(&spi_expectations);
+        let mut busy = PinMock::new(&vec![PinTransaction::get(State::Low); 4]);
+        let mut dc = PinMock::new(&dc_expectations);
+        let mut rst = PinMock::new(&[]);
+
+        let mut epd = Epd2in9 {
+            interface: DisplayInterface::new(
+                busy.clone(),
+                dc.clone(),
+                Some(rst.clone()),
+                None::<NoPin>,
+                None,
+            ),
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            custom_lut: None,
+            vcom: None,
+            border: None,
+        };
+        let delay = &mut NoopDelay::new();
+        assert_eq!(
+            epd.set_custom_lut(&mut spi, delay, &custom[..153]),
+            Err(Error::InvalidBufferLength {
+                expected: CUSTOM_LUT_LEN,
+                got: 153
+            })
+        );
+        epd.set_custom_lut(&mut spi, delay, &custom).unwrap();
+        epd.clear_custom_lut(&mut spi, delay).unwrap();
+        drop(epd);
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
+
+    #[test]
+    fn wake_up_restores_the_settings() {
+        let custom = [0x42; CUSTOM_LUT_LEN];
+        let vcom = [ssd_vcom::<()>(-1500).unwrap()];
+        let mut woken = custom;
+        woken[158] = vcom[0];
+        let commands = [
+            &lut_commands(&custom)[..],
+            &[(0x2C, &vcom[..]), (0x3C, &[0x60]), (0x10, &[0x01])],
+            // reset and init
+            &[
+                (0x12, &[]),
+                (0x01, &[0x27, 0x01, 0x00]),
+                (0x11, &[0x03]),
+                (0x21, &[0x00, 0x80]),
+                (0x44, &[0x00, 0x0F]),
+                (0x45, &[0x00, 0x00, 0x27, 0x01]),
+                (0x4E, &[0x00]),
+                (0x4F, &[0x00, 0x00]),
+                (0x3C, &[0x60]),
+            ],
+            &lut_commands(&woken),
+        ]
+        .concat();
+        let (spi_expectations, dc_expectations) = expect_commands(&commands);
+        let mut spi = SpiMock::new(&spi_expectations);
+        let mut busy = PinMock::new(&vec![PinTransaction::get(State::Low); 9]);
+        let mut dc = PinMock::new(&dc_expectations);
+        let mut rst = PinMock::new(&[
+            PinTransaction::set(State::High),
+            PinTransaction::set(State::Low),
+            PinTransaction::set(State::High),
+        ]);
+
+        let mut epd = Epd2in9 {
+            interface: DisplayInterface::new(
+                busy.clone(),
+                dc.clone(),
+                Some(rst.clone()),
+                None::<NoPin>,
+                None,
+            ),
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            custom_lut: None,
+            vcom: None,
+            border: None,
+        };
+        let delay = &mut NoopDelay::new();
+        epd.set_custom_lut(&mut spi, delay, &custom).unwrap();
+        epd.set_vcom(&mut spi, delay, -1500).unwrap();
+        epd.set_border(&mut spi, BorderColor::White).unwrap();
+        epd.sleep(&mut spi, delay).unwrap();
+        epd.wake_up(&mut spi, delay).unwrap();
+        drop(epd);
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
+
+    #[test]
+    fn unaligned_window() {
+        assert_eq!(
+            check_partial_window::<()>(4, 4, 0, 32, 1, WIDTH, HEIGHT),
+            Err(Error::OutOfBounds {
+                x: 4,
+                y: 0,
+                width: 32,
+                height: 1
+            })
+        );
+    }
 }
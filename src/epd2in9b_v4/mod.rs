@@ -1,14 +1,24 @@
 //! A simple Driver for the Waveshare 2.9" B (v4) Tri-Color E-Ink Display via SPI
 //!
+//! Besides the full refresh of about 15 s, the vendor's fast refresh of about 7 s is selected
+//! with [`RefreshLut::Quick`] via [`set_lut`](WaveshareDisplay::set_lut).
+//!
+//! Unlike on the [V3](crate::epd2in9b_v3), both planes of a [`Display2in9b`] are sent as they
+//! are: a set bit is white in the black/white plane and red in the chromatic plane.
+//!
 //! [Documentation](https://www.waveshare.com/wiki/2.9inch_e-Paper_Module_(B)_Manual)
 //!
 //! [Reference code](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_2in9b_V4.c)
 
+use crate::error::Error;
 use crate::{
     buffer_len,
-    color::TriColor,
+    color::{DeghostColors, TriColor},
     interface::DisplayInterface,
-    traits::{InternalWiAdditions, WaveshareDisplay, WaveshareThreeColorDisplay},
+    traits::{
+        check_refresh_lut, InternalWiAdditions, RefreshLut, WaveshareDisplay,
+        WaveshareThreeColorDisplay,
+    },
 };
 use embedded_hal::{
     delay::DelayNs,
@@ -41,27 +51,29 @@ pub type Display2in9b = crate::graphics::Display<
 >;
 
 /// Epd2in9b (v4) driver
-pub struct Epd2in9b<SPI, BUSY, DC, RST, DELAY> {
+pub struct Epd2in9b<SPI, BUSY, DC, RST, DELAY, PWR> {
     /// SPI
-    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
     /// Color
     background_color: TriColor,
+    /// Refresh LUT
+    refresh: RefreshLut,
 }
 
-#[allow(dead_code)]
 enum DisplayMode {
     Default,
     Partial,
-    Fast, // TODO: Add support in future
+    Fast,
     Base,
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> Epd2in9b<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd2in9b<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     /// set the base image before partially update
@@ -73,7 +85,7 @@ where
         black: &[u8],
         chromatic: Option<&[u8]>,
         delay: &mut DELAY,
-    ) -> Result<(), <SPI>::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, black, delay)?;
         if let Some(chromatic) = chromatic {
             self.update_chromatic_frame(spi, delay, chromatic)?;
@@ -95,25 +107,44 @@ where
         &mut self,
         spi: &mut SPI,
         delay: &mut DELAY,
-    ) -> Result<(), <SPI>::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.turn_on_display(spi, delay, DisplayMode::Partial)?;
         Ok(())
     }
 
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
         self.interface.data(spi, data)
     }
 
+    /// Loads the built-in temperature, then overwrites it to select the fast waveform of the OTP
+    fn load_fast_temperature(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.command(spi, Command::TurnOnDisplay)?;
+        self.send_data(spi, &[0xb1])?;
+        self.command(spi, Command::ActivateDisplayUpdateSequence)?;
+        self.wait_until_idle(spi, delay)?;
+
+        self.command(spi, Command::WriteTemperatureRegister)?;
+        self.send_data(spi, &[0x5a, 0x00])?;
+        self.command(spi, Command::TurnOnDisplay)?;
+        self.send_data(spi, &[0x91])?;
+        self.command(spi, Command::ActivateDisplayUpdateSequence)?;
+        self.wait_until_idle(spi, delay)
+    }
+
     fn turn_on_display(
         &mut self,
         spi: &mut SPI,
         delay: &mut DELAY,
         mode: DisplayMode,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.command(spi, Command::TurnOnDisplay)?;
 
         let data = match mode {
@@ -125,25 +156,25 @@ where
 
         self.send_data(spi, &[data])?;
         self.command(spi, Command::ActivateDisplayUpdateSequence)?;
-        self.wait_until_idle(spi, delay)?;
-        Ok(())
+        self.wait_until_idle(spi, delay)
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
-    for Epd2in9b<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in9b<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         let w = self.width();
         let h = self.height();
 
-        self.interface.reset(delay, 200_000, 2_000);
+        self.interface.reset(delay, 200_000, 2_000)?;
 
         self.wait_until_idle(spi, delay)?;
         self.command(spi, Command::SwReset)?;
@@ -177,25 +208,28 @@ where
         self.command(spi, Command::ReadBuiltInTemperatureSensor)?;
         self.send_data(spi, &[0x80])?;
 
+        if self.refresh == RefreshLut::Quick {
+            self.load_fast_temperature(spi, delay)?;
+        }
+
         self.command(spi, Command::RamXAddressCount)?;
         self.send_data(spi, &[0x00])?; // set RAM x address count to 0
         self.command(spi, Command::RamYAddressCount)?; // set RAM y address count to 0X199
         self.send_data(spi, &[0x00])?;
         self.send_data(spi, &[0x00])?;
 
-        self.wait_until_idle(spi, delay)?;
-
-        Ok(())
+        self.wait_until_idle(spi, delay)
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>
-    for Epd2in9b<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in9b<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     fn update_color_frame(
@@ -204,7 +238,7 @@ where
         delay: &mut DELAY,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), <SPI>::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_achromatic_frame(spi, delay, black)?;
         self.update_chromatic_frame(spi, delay, chromatic)?;
         Ok(())
@@ -215,7 +249,7 @@ where
         spi: &mut SPI,
         _delay: &mut DELAY,
         black: &[u8],
-    ) -> Result<(), <SPI>::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.command(spi, Command::WriteBlackData)?;
         self.send_data(spi, black)?;
         Ok(())
@@ -226,41 +260,45 @@ where
         spi: &mut SPI,
         _delay: &mut DELAY,
         chromatic: &[u8],
-    ) -> Result<(), <SPI>::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.command(spi, Command::WriteRedData)?;
         self.send_data(spi, chromatic)?;
         Ok(())
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
-    for Epd2in9b<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in9b<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     type DisplayColor = TriColor;
+    const REFRESH_LUTS: &'static [RefreshLut] = &[RefreshLut::Full, RefreshLut::Quick];
 
     fn new(
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, <SPI>::Error>
+    ) -> Result<Self, Error<SPI::Error>>
     where
         Self: Sized,
     {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        let interface = DisplayInterface::new_color(busy, dc, rst, pwr, delay_us);
         let background_color = DEFAULT_BACKGROUND_COLOR;
 
         let mut epd = Epd2in9b {
             interface,
             background_color,
+            refresh: RefreshLut::Full,
         };
 
         epd.init(spi, delay)?;
@@ -268,15 +306,15 @@ where
         Ok(epd)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), <SPI>::Error> {
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.command(spi, Command::DeepSleep)?;
         self.send_data(spi, &[1])?;
         delay.delay_ms(100);
 
-        Ok(())
+        self.interface.power_off()
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), <SPI>::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.init(spi, delay)?;
         Ok(())
     }
@@ -285,6 +323,18 @@ where
         self.background_color = color
     }
 
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
     fn background_color(&self) -> &Self::DisplayColor {
         &self.background_color
     }
@@ -302,7 +352,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         _delay: &mut DELAY,
-    ) -> Result<(), <SPI>::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.command(spi, Command::WriteBlackData)?;
         self.send_data(spi, buffer)?;
 
@@ -320,8 +370,15 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), <SPI>::Error> {
-        assert!(width % 8 == 0, "width must multiple of 8");
+    ) -> Result<(), Error<SPI::Error>> {
+        if width % 8 != 0 {
+            return Err(Error::OutOfBounds {
+                x,
+                y,
+                width,
+                height,
+            });
+        }
         let mut x_start = x;
         let mut x_end = x + width;
 
@@ -372,8 +429,12 @@ where
         Ok(())
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), <SPI>::Error> {
-        self.turn_on_display(spi, delay, DisplayMode::Default)?;
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let mode = match self.refresh {
+            RefreshLut::Quick => DisplayMode::Fast,
+            _ => DisplayMode::Default,
+        };
+        self.turn_on_display(spi, delay, mode)?;
 
         Ok(())
     }
@@ -383,14 +444,14 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), <SPI>::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
 
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), <SPI>::Error> {
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         const SIZE: u32 = WIDTH / 8 * HEIGHT;
 
         self.command(spi, Command::WriteBlackData)?;
@@ -403,17 +464,145 @@ where
         Ok(())
     }
 
-    fn set_lut(
+    /// `clear_frame` always clears to white, so the RAM is filled directly
+    fn deghost(
         &mut self,
-        _spi: &mut SPI,
-        _delay: &mut DELAY,
-        _refresh_rate: Option<crate::traits::RefreshLut>,
-    ) -> Result<(), <SPI>::Error> {
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        cycles: u8,
+    ) -> Result<(), Error<SPI::Error>>
+    where
+        Self::DisplayColor: DeghostColors,
+    {
+        const SIZE: u32 = WIDTH / 8 * HEIGHT;
+
+        self.set_lut(spi, delay, Some(RefreshLut::Full))?;
+        for _ in 0..cycles {
+            // black, chromatic and white
+            for (black, chromatic) in [(0x00, 0x00), (0xFF, 0xFF), (0xFF, 0x00)] {
+                self.command(spi, Command::WriteBlackData)?;
+                self.interface.data_x_times(spi, black, SIZE)?;
+
+                self.command(spi, Command::WriteRedData)?;
+                self.interface.data_x_times(spi, chromatic, SIZE)?;
+
+                self.display_frame(spi, delay)?;
+            }
+        }
         Ok(())
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), <SPI>::Error> {
-        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
+    /// [`RefreshLut::Full`] and the fast [`RefreshLut::Quick`] are supported
+    fn set_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_refresh_lut(refresh_rate, Self::REFRESH_LUTS)?;
+        if let Some(refresh_lut) = refresh_rate {
+            if refresh_lut != self.refresh {
+                self.refresh = refresh_lut;
+                // the temperature of the fast refresh is only reset by a hardware reset
+                self.init(spi, delay)?;
+            }
+        }
         Ok(())
     }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+    use crate::NoPin;
+    use embedded_hal_mock::eh1::{
+        delay::NoopDelay,
+        digital::{Mock as PinMock, State, Transaction as PinTransaction},
+        spi::{Mock as SpiMock, Transaction as SpiTransaction},
+    };
+    use std::vec;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 128);
+        assert_eq!(HEIGHT, 296);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, TriColor::White);
+    }
+
+    #[test]
+    fn planes_are_sent_unchanged() {
+        let black = [0b1010_0000, 0xFF];
+        let chromatic = [0b0000_0101, 0x00];
+        let mut spi = SpiMock::new(&[
+            SpiTransaction::transaction_start(),
+            SpiTransaction::write_vec(vec![0x24]),
+            SpiTransaction::transaction_end(),
+            SpiTransaction::transaction_start(),
+            SpiTransaction::write_vec(black.to_vec()),
+            SpiTransaction::transaction_end(),
+            SpiTransaction::transaction_start(),
+            SpiTransaction::write_vec(vec![0x26]),
+            SpiTransaction::transaction_end(),
+            SpiTransaction::transaction_start(),
+            SpiTransaction::write_vec(chromatic.to_vec()),
+            SpiTransaction::transaction_end(),
+        ]);
+        let mut busy = PinMock::new(&[]);
+        let mut dc = PinMock::new(&[
+            PinTransaction::set(State::Low),
+            PinTransaction::set(State::High),
+            PinTransaction::set(State::Low),
+            PinTransaction::set(State::High),
+        ]);
+        let mut rst = PinMock::new(&[]);
+
+        let mut epd = Epd2in9b {
+            interface: DisplayInterface::new(
+                busy.clone(),
+                dc.clone(),
+                Some(rst.clone()),
+                None::<NoPin>,
+                None,
+            ),
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+        };
+        epd.update_color_frame(&mut spi, &mut NoopDelay::new(), &black, &chromatic)
+            .unwrap();
+        drop(epd);
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn graphics_planes() {
+        use embedded_graphics::prelude::*;
+
+        let mut display = Display2in9b::default();
+        display.clear(TriColor::White).unwrap();
+        Pixel(Point::new(0, 0), TriColor::Black)
+            .draw(&mut display)
+            .unwrap();
+        Pixel(Point::new(1, 0), TriColor::Chromatic)
+            .draw(&mut display)
+            .unwrap();
+
+        // the panel shows white for a set B/W bit and red for a set chromatic bit, which wins
+        // over the cleared B/W bit of the red pixel
+        assert_eq!(display.bw_buffer()[0], 0b0011_1111);
+        assert_eq!(display.chromatic_buffer()[0], 0b0100_0000);
+    }
 }
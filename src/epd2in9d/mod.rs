@@ -4,7 +4,27 @@
 //! 参考[Waveshare](https://www.waveshare.net/wiki/2.9inch_e-Paper_HAT_%28D%29)的文档/例程进行构建
 //!
 //! Specification: <https://www.waveshare.net/w/upload/b/b5/2.9inch_e-Paper_%28D%29_Specification.pdf>
+//!
+//! The flexible panel has its own power settings and waveforms, don't drive it with one of the
+//! drivers of the rigid 2.9" displays.
+//!
+//! # Refresh constraints
+//!
+//! The flexible film degrades when it is refreshed too often or kept under voltage, the
+//! specification asks for:
+//!
+//! - at least 180 s between two refreshes
+//! - a full refresh after at most 5 partial refreshes
+//! - at least one refresh every 24 h, even if the content does not change
+//! - [`sleep`](WaveshareDisplay::sleep) after every refresh, the panel must not stay powered on
+//!
+//! # Custom LUT
+//!
+//! The full refresh runs with the waveform of the OTP, the partial refresh with [`LUT_PARTIAL`]
+//! loaded by the host. [`Epd2in9d::set_custom_lut`] replaces the latter until
+//! [`Epd2in9d::clear_custom_lut`], also after [`wake_up`](WaveshareDisplay::wake_up).
 
+use crate::error::Error;
 use core::slice::from_raw_parts;
 
 use embedded_hal::{
@@ -14,7 +34,10 @@ use embedded_hal::{
 };
 
 use crate::interface::DisplayInterface;
-use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::traits::{
+    check_refresh_lut, uc_border, uc_vcm_dc, BorderColor, InternalWiAdditions, LutBanks,
+    LutBanksBuf, RefreshLut, WaveshareDisplay,
+};
 
 //The Lookup Tables for the Display
 mod constants;
@@ -29,10 +52,22 @@ pub const HEIGHT: u32 = 296;
 pub const EPD_ARRAY: u32 = 4736;
 /// Default Background Color (white)
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::Black;
+/// Built-in LUTs of the partial refresh, e.g. to pass to [`Epd2in9d::set_custom_lut`]
+pub const LUT_PARTIAL: LutBanks<'static> = LutBanks {
+    vcom: &LUT_VCOM1,
+    ww: &LUT_WW1,
+    bw: &LUT_BW1,
+    wb: &LUT_WB1,
+    bb: &LUT_BB1,
+};
 const IS_BUSY_LOW: bool = false;
+/// VCOM and data interval setting until `set_border` is called
+const DEFAULT_VCOM_DATA_INTERVAL: u8 = 0x97;
+/// -1.0 V
+const DEFAULT_VCOM: u8 = 0x12;
 const SINGLE_BYTE_WRITE: bool = true;
 
-use crate::color::Color;
+use crate::color::{Color, DeghostColors};
 
 pub(crate) mod command;
 use self::command::Command;
@@ -50,31 +85,39 @@ pub type Display2in9d = crate::graphics::Display<
 
 /// Epd2in9d driver
 ///
-pub struct Epd2in9d<'a, SPI, BUSY, DC, RST, DELAY> {
+pub struct Epd2in9d<'a, SPI, BUSY, DC, RST, DELAY, PWR> {
     /// SPI
-    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
     /// Color
     // background_color: Color,
     color: Color,
-    /// Refresh LUT
-    refresh: RefreshLut,
     // Storing old data for partial refreshes
     old_data: &'a [u8],
     // 标记是否局刷的状态
     is_partial_refresh: bool,
+    /// Replaces `LUT_PARTIAL` if set
+    custom_lut: Option<LutBanksBuf<44, 42, 42>>,
+    /// VCOM register value, see [`set_vcom`](Self::set_vcom)
+    vcom: u8,
+    /// VCOM and data interval setting, holds the border of
+    /// [`set_border`](WaveshareDisplay::set_border)
+    vcom_data_interval: u8,
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
-    for Epd2in9d<'_, SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in9d<'_, SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.reset(delay, 10_000, 2_000);
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, 10_000, 2_000)?;
+        self.interface
+            .uc_software_reset(spi, delay, Command::PanelSetting, 0x1F, IS_BUSY_LOW)?;
 
         //panel setting
         //LUT from OTP，KW-BF   KWR-AF	BWROTP 0f	BWOTP 1f
@@ -89,20 +132,24 @@ where
         self.wait_until_idle(spi, delay)?;
 
         //VCOM AND DATA INTERVAL SETTING
-        self.interface
-            .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x97])?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::VcomAndDataIntervalSetting,
+            &[self.vcom_data_interval],
+        )?;
 
         Ok(())
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
-    for Epd2in9d<'_, SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd2in9d<'_, SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     type DisplayColor = Color;
@@ -110,11 +157,12 @@ where
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(busy, dc, rst, pwr, delay_us);
         let color = DEFAULT_BACKGROUND_COLOR;
         let old_data: &[u8] = &[];
         let is_partial_refresh = false;
@@ -122,9 +170,11 @@ where
         let mut epd = Epd2in9d {
             interface,
             color,
-            refresh: RefreshLut::Full,
             old_data,
             is_partial_refresh,
+            custom_lut: None,
+            vcom: DEFAULT_VCOM,
+            vcom_data_interval: DEFAULT_VCOM_DATA_INTERVAL,
         };
 
         epd.init(spi, delay)?;
@@ -132,7 +182,7 @@ where
         Ok(epd)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.is_partial_refresh = false;
         self.interface
             .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0xf7])?;
@@ -142,10 +192,10 @@ where
         self.interface
             .cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
 
-        Ok(())
+        self.interface.power_off()
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.init(spi, delay)?;
         Ok(())
     }
@@ -154,6 +204,30 @@ where
         self.color = background_color;
     }
 
+    fn set_vcom(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        millivolts: i16,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.vcom = uc_vcm_dc(millivolts)?;
+        self.wait_until_idle(spi, delay)?;
+        self.interface
+            .cmd_with_data(spi, Command::VcmDcSetting, &[self.vcom])
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
     fn background_color(&self) -> &Color {
         &self.color
     }
@@ -173,7 +247,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         if self.is_partial_refresh {
             // Modify local refresh status if full refresh is performed.
             self.is_partial_refresh = false;
@@ -200,7 +274,7 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         if !self.is_partial_refresh {
             // Initialize only on first call
             self.set_part_reg(spi, delay)?;
@@ -231,11 +305,10 @@ where
     }
 
     /// actually is the "Turn on Display" sequence
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, Command::DisplayRefresh)?;
         delay.delay_us(1_000);
-        self.wait_until_idle(spi, delay)?;
-        Ok(())
+        self.wait_until_idle(spi, delay)
     }
 
     fn update_and_display_frame(
@@ -243,13 +316,13 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
         self.interface.data_x_times(spi, 0x00, EPD_ARRAY)?;
 
@@ -261,43 +334,77 @@ where
         Ok(())
     }
 
+    /// `clear_frame` always clears to white, so the RAM is filled directly
+    fn deghost(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        cycles: u8,
+    ) -> Result<(), Error<SPI::Error>>
+    where
+        Self::DisplayColor: DeghostColors,
+    {
+        for _ in 0..cycles {
+            for byte in [0x00, 0xFF] {
+                self.interface.cmd(spi, Command::DataStartTransmission1)?;
+                self.interface.data_x_times(spi, 0x00, EPD_ARRAY)?;
+
+                self.interface.cmd(spi, Command::DataStartTransmission2)?;
+                self.interface.data_x_times(spi, byte, EPD_ARRAY)?;
+
+                self.display_frame(spi, delay)?;
+            }
+        }
+        Ok(())
+    }
+
     fn set_lut(
         &mut self,
         spi: &mut SPI,
         delay: &mut DELAY,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
-        if let Some(refresh_lut) = refresh_rate {
-            self.refresh = refresh_lut;
+    ) -> Result<(), Error<SPI::Error>> {
+        check_refresh_lut(refresh_rate, Self::REFRESH_LUTS)?;
+        match self.custom_lut {
+            Some(lut) => self.set_lut_helper(spi, delay, lut.banks()),
+            None => self.set_lut_helper(spi, delay, LUT_PARTIAL),
         }
-        self.set_lut_helper(
-            spi, delay, &LUT_VCOM1, &LUT_WW1, &LUT_BW1, &LUT_WB1, &LUT_BB1,
-        )?;
+    }
 
-        Ok(())
+    fn set_border(&mut self, spi: &mut SPI, border: BorderColor) -> Result<(), Error<SPI::Error>> {
+        self.vcom_data_interval = uc_border(self.vcom_data_interval, border)?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::VcomAndDataIntervalSetting,
+            &[self.vcom_data_interval],
+        )
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
-        Ok(())
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> Epd2in9d<'_, SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd2in9d<'_, SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     // /// Wake Up Screen
     // ///
     // /// After the screen sleeps, it enters deep sleep mode. If you need to refresh the screen while in deep sleep mode, you must first execute awaken().
     // /// Wake the screen.
-    // fn awaken(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    // fn awaken(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
     //     // reset the device
-    //     self.interface.reset(delay, 20_000, 2_000);
+    //     self.interface.reset(delay, 20_000, 2_000)?;
     //     self.wait_until_idle(spi, delay)?;
 
     //     // panel setting
@@ -315,10 +422,12 @@ where
     //     Ok(())
     // }
 
-    fn set_part_reg(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn set_part_reg(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         // Reset the EPD driver circuit
         //TODO: 这里在微雪的例程中反复刷新了3次，后面有显示问题再进行修改
-        self.interface.reset(delay, 10_000, 2_000);
+        self.interface.reset(delay, 10_000, 2_000)?;
+        self.interface
+            .uc_software_reset(spi, delay, Command::PanelSetting, 0xBF, IS_BUSY_LOW)?;
 
         // Power settings
         //TODO: The data in the document is [0x03,0x00,0x2b,0x2b,0x09].
@@ -348,7 +457,7 @@ where
 
         // vcom_DC settings
         self.interface
-            .cmd_with_data(spi, Command::VcmDcSetting, &[0x12])?;
+            .cmd_with_data(spi, Command::VcmDcSetting, &[self.vcom])?;
 
         self.set_lut(spi, delay, None)?;
 
@@ -371,37 +480,63 @@ where
         Ok(())
     }
 
-    #[allow(clippy::too_many_arguments)]
+    /// Loads `luts` instead of [`LUT_PARTIAL`] for the partial refresh
+    ///
+    /// The VCOM LUT has 44 bytes, the others 42. Outside of the partial refresh they're loaded
+    /// by the next [`update_partial_frame`](WaveshareDisplay::update_partial_frame). Fails with
+    /// `Error::InvalidBufferLength` for a LUT of another length.
+    pub fn set_custom_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        luts: LutBanks,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.custom_lut = Some(LutBanksBuf::new(luts)?);
+        if self.is_partial_refresh {
+            self.set_lut(spi, delay, None)?;
+        }
+        Ok(())
+    }
+
+    /// Drops the LUTs of `set_custom_lut` and goes back to `LUT_PARTIAL`
+    pub fn clear_custom_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.custom_lut = None;
+        if self.is_partial_refresh {
+            self.set_lut(spi, delay, None)?;
+        }
+        Ok(())
+    }
+
     fn set_lut_helper(
         &mut self,
         spi: &mut SPI,
         delay: &mut DELAY,
-        lut_vcom: &[u8],
-        lut_ww: &[u8],
-        lut_bw: &[u8],
-        lut_wb: &[u8],
-        lut_bb: &[u8],
-    ) -> Result<(), SPI::Error> {
+        luts: LutBanks,
+    ) -> Result<(), Error<SPI::Error>> {
         let _ = delay;
         // LUT VCOM
         self.interface
-            .cmd_with_data(spi, Command::LutForVcom, lut_vcom)?;
+            .cmd_with_data(spi, Command::LutForVcom, luts.vcom)?;
 
         // LUT WHITE to WHITE
         self.interface
-            .cmd_with_data(spi, Command::LutWhiteToWhite, lut_ww)?;
+            .cmd_with_data(spi, Command::LutWhiteToWhite, luts.ww)?;
 
         // LUT BLACK to WHITE
         self.interface
-            .cmd_with_data(spi, Command::LutBlackToWhite, lut_bw)?;
+            .cmd_with_data(spi, Command::LutBlackToWhite, luts.bw)?;
 
         // LUT WHITE to BLACK
         self.interface
-            .cmd_with_data(spi, Command::LutWhiteToBlack, lut_wb)?;
+            .cmd_with_data(spi, Command::LutWhiteToBlack, luts.wb)?;
 
         // LUT BLACK to BLACK
         self.interface
-            .cmd_with_data(spi, Command::LutBlackToBlack, lut_bb)?;
+            .cmd_with_data(spi, Command::LutBlackToBlack, luts.bb)?;
         Ok(())
     }
 }
@@ -0,0 +1,34 @@
+//! SPI Commands for the Waveshare 3.52" E-Ink Display
+use crate::traits;
+
+#[derive(Copy, Clone)]
+pub(crate) enum Command {
+    PanelSetting = 0x00,
+    PowerSetting = 0x01,
+    PowerOff = 0x02,
+    BoosterSoftStart = 0x06,
+    DeepSleep = 0x07,
+    DataStartTransmission2 = 0x13,
+    /// Power on, refresh and power off in one command
+    AutoSequence = 0x17,
+
+    LutForVcom = 0x20,
+    LutWhiteToWhite = 0x21,
+    LutBlackToWhite = 0x22,
+    LutWhiteToBlack = 0x23,
+    LutBlackToBlack = 0x24,
+
+    PllControl = 0x30,
+    VcomAndDataIntervalSetting = 0x50,
+    TconSetting = 0x60,
+    ResolutionSetting = 0x61,
+    VcmDcSetting = 0x82,
+    PowerSaving = 0xE3,
+}
+
+impl traits::Command for Command {
+    /// Returns the address of the command
+    fn address(self) -> u8 {
+        self as u8
+    }
+}
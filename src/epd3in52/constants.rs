@@ -0,0 +1,126 @@
+//! This file contains look-up-tables used to set voltages used during
+//! various categories of pixel refreshes.
+//!
+//! The values are the `GC` (full) and `DU` (quick) tables of the Waveshare C driver.
+
+/**
+ * full screen update LUT
+**/
+#[rustfmt::skip]
+pub(crate) const LUT_VCOM_GC: [u8; 56] = [
+    0x01, 0x0F, 0x0F, 0x0F, 0x01, 0x01, 0x01,
+    0x01, 0x0F, 0x0F, 0x0F, 0x01, 0x01, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const LUT_WW_GC: [u8; 42] = [
+    0x01, 0x4F, 0x8F, 0x0F, 0x01, 0x01, 0x01,
+    0x01, 0x4F, 0x8F, 0x0F, 0x01, 0x01, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const LUT_KW_GC: [u8; 56] = [
+    0x01, 0x0F, 0x8F, 0x0F, 0x01, 0x01, 0x01,
+    0x01, 0x0F, 0x8F, 0x0F, 0x01, 0x01, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const LUT_WK_GC: [u8; 56] = [
+    0x01, 0x8F, 0x4F, 0x0F, 0x01, 0x01, 0x01,
+    0x01, 0x8F, 0x4F, 0x0F, 0x01, 0x01, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const LUT_KK_GC: [u8; 56] = [
+    0x01, 0x8F, 0x0F, 0x0F, 0x01, 0x01, 0x01,
+    0x01, 0x8F, 0x0F, 0x0F, 0x01, 0x01, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+/**
+ * quick screen update LUT
+**/
+#[rustfmt::skip]
+pub(crate) const LUT_VCOM_DU: [u8; 56] = [
+    0x01, 0x0F, 0x01, 0x00, 0x00, 0x01, 0x01,
+    0x01, 0x0F, 0x01, 0x00, 0x00, 0x01, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const LUT_WW_DU: [u8; 42] = [
+    0x01, 0x4F, 0x01, 0x00, 0x00, 0x01, 0x01,
+    0x01, 0x4F, 0x01, 0x00, 0x00, 0x01, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const LUT_KW_DU: [u8; 56] = [
+    0x01, 0x8F, 0x01, 0x00, 0x00, 0x01, 0x01,
+    0x01, 0x8F, 0x01, 0x00, 0x00, 0x01, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const LUT_WK_DU: [u8; 56] = [
+    0x01, 0x4F, 0x01, 0x00, 0x00, 0x01, 0x01,
+    0x01, 0x4F, 0x01, 0x00, 0x00, 0x01, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const LUT_KK_DU: [u8; 56] = [
+    0x01, 0x0F, 0x01, 0x00, 0x00, 0x01, 0x01,
+    0x01, 0x0F, 0x01, 0x00, 0x00, 0x01, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
@@ -0,0 +1,375 @@
+//! A simple Driver for the Waveshare 3.52" E-Ink Display via SPI
+//!
+//! The UC8253 controller runs with the LUTs of the host, the full (`GC`) refresh [`LUT_FULL`] is
+//! used by default and a quick (`DU`) one [`LUT_QUICK`] can be selected with
+//! `set_lut(.., Some(RefreshLut::Quick))`. [`Epd3in52::set_custom_lut`] replaces both until
+//! [`Epd3in52::clear_custom_lut`].
+//!
+//! # References
+//!
+//! - [Waveshare Wiki](https://www.waveshare.com/wiki/3.52inch_e-Paper_HAT)
+//! - [Waveshare C driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_3in52.c)
+
+use crate::error::Error;
+use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
+
+use crate::color::Color;
+use crate::interface::DisplayInterface;
+use crate::traits::{
+    check_refresh_lut, InternalWiAdditions, LutBanks, LutBanksBuf, RefreshLut, WaveshareDisplay,
+};
+use crate::{buffer_len, check_buffer_len};
+
+pub(crate) mod command;
+use self::command::Command;
+
+mod constants;
+use self::constants::*;
+
+/// Width of the display
+pub const WIDTH: u32 = 240;
+/// Height of the display
+pub const HEIGHT: u32 = 360;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+/// Built-in LUTs of the full (`GC`) refresh, e.g. to pass to [`Epd3in52::set_custom_lut`]
+pub const LUT_FULL: LutBanks<'static> = LutBanks {
+    vcom: &LUT_VCOM_GC,
+    ww: &LUT_WW_GC,
+    bw: &LUT_KW_GC,
+    wb: &LUT_WK_GC,
+    bb: &LUT_KK_GC,
+};
+/// Built-in LUTs of the quick (`DU`) refresh
+pub const LUT_QUICK: LutBanks<'static> = LutBanks {
+    vcom: &LUT_VCOM_DU,
+    ww: &LUT_WW_DU,
+    bw: &LUT_KW_DU,
+    wb: &LUT_WK_DU,
+    bb: &LUT_KK_DU,
+};
+const IS_BUSY_LOW: bool = true;
+const SINGLE_BYTE_WRITE: bool = true;
+
+const NUM_DISPLAY_BYTES: u32 = WIDTH / 8 * HEIGHT;
+
+/// Full size buffer for use with the 3in52 EPD
+#[cfg(feature = "graphics")]
+pub type Display3in52 = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize, HEIGHT as usize) },
+    Color,
+>;
+
+/// Epd3in52 driver
+pub struct Epd3in52<SPI, BUSY, DC, RST, DELAY, PWR> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    color: Color,
+    /// Refresh LUT
+    refresh: RefreshLut,
+    /// The black to white and white to black LUTs are swapped on every refresh
+    swap_luts: bool,
+    /// Replaces the built-in LUTs if set
+    custom_lut: Option<LutBanksBuf<56, 42, 56>>,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd3in52<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, 200_000, 2_000)?;
+        self.interface
+            .uc_software_reset(spi, delay, Command::PanelSetting, 0xFF, IS_BUSY_LOW)?;
+
+        // 240x360, B/W mode, LUT from register
+        self.cmd_with_data(spi, Command::PanelSetting, &[0xFF, 0x01])?;
+
+        // VGH 20 V, VGL -20 V, VSH 15 V, VSL -15 V, VDHR 6.4 V
+        self.cmd_with_data(spi, Command::PowerSetting, &[0x03, 0x10, 0x3F, 0x3F, 0x03])?;
+
+        self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x37, 0x3D, 0x3D])?;
+
+        self.cmd_with_data(spi, Command::TconSetting, &[0x22])?;
+
+        self.cmd_with_data(spi, Command::VcmDcSetting, &[0x07])?;
+
+        self.cmd_with_data(spi, Command::PllControl, &[0x09])?;
+
+        self.cmd_with_data(spi, Command::PowerSaving, &[0x88])?;
+
+        self.cmd_with_data(
+            spi,
+            Command::ResolutionSetting,
+            &[WIDTH as u8, (HEIGHT >> 8) as u8, HEIGHT as u8],
+        )?;
+
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0xB7])
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd3in52<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    type DisplayColor = Color;
+    const REFRESH_LUTS: &'static [RefreshLut] = &[RefreshLut::Full, RefreshLut::Quick];
+
+    fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(busy, dc, rst, pwr, delay_us);
+
+        let mut epd = Epd3in52 {
+            interface,
+            color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+            swap_luts: false,
+            custom_lut: None,
+        };
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        // floating border
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0xF7])?;
+        self.command(spi, Command::PowerOff)?;
+        self.wait_until_idle(spi, delay)?;
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
+        self.interface.power_off()
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.init(spi, delay)
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer.len(), NUM_DISPLAY_BYTES as usize)?;
+        self.wait_until_idle(spi, delay)?;
+        self.cmd_with_data(spi, Command::DataStartTransmission2, buffer)
+    }
+
+    #[allow(unused)]
+    fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::Unsupported)
+    }
+
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.set_lut_helper(spi)?;
+        self.cmd_with_data(spi, Command::AutoSequence, &[0xA5])?;
+        self.wait_until_idle(spi, delay)
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        let color = self.color.get_byte_value();
+        self.command(spi, Command::DataStartTransmission2)?;
+        self.interface.data_x_times(spi, color, NUM_DISPLAY_BYTES)
+    }
+
+    /// `Full` selects the `GC` and `Quick` the `DU` LUTs, they are sent with every `display_frame`
+    fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_refresh_lut(refresh_rate, Self::REFRESH_LUTS)?;
+        if let Some(refresh_lut) = refresh_rate {
+            self.refresh = refresh_lut;
+        }
+        Ok(())
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd3in52<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    /// Sends the LUTs of the current refresh mode, like the vendor the black to white and the
+    /// white to black LUTs are swapped on every call
+    fn set_lut_helper(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        let custom_lut = self.custom_lut;
+        let luts = match custom_lut.as_ref() {
+            Some(custom_lut) => custom_lut.banks(),
+            None => luts(self.refresh)?,
+        };
+        let (kw, wk) = if self.swap_luts {
+            (luts.wb, luts.bw)
+        } else {
+            (luts.bw, luts.wb)
+        };
+        self.swap_luts = !self.swap_luts;
+
+        self.cmd_with_data(spi, Command::LutForVcom, luts.vcom)?;
+        self.cmd_with_data(spi, Command::LutWhiteToWhite, luts.ww)?;
+        self.cmd_with_data(spi, Command::LutBlackToBlack, luts.bb)?;
+        self.cmd_with_data(spi, Command::LutBlackToWhite, kw)?;
+        self.cmd_with_data(spi, Command::LutWhiteToBlack, wk)
+    }
+
+    /// Sends `luts` instead of the built-in LUTs with every `display_frame`
+    ///
+    /// The white to white LUT has 42 bytes, the others 56. The refresh mode selected with
+    /// [`set_lut`](WaveshareDisplay::set_lut) no longer matters. Fails with
+    /// `Error::InvalidBufferLength` for a LUT of another length.
+    pub fn set_custom_lut(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+        luts: LutBanks,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.custom_lut = Some(LutBanksBuf::new(luts)?);
+        Ok(())
+    }
+
+    /// Drops the LUTs of `set_custom_lut`, the next `display_frame` sends the built-in ones of
+    /// the refresh mode again
+    pub fn clear_custom_lut(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.custom_lut = None;
+        Ok(())
+    }
+}
+
+/// The built-in LUTs of a refresh mode, fails with `Error::Unsupported` for the partial refresh
+fn luts<SpiE>(refresh: RefreshLut) -> Result<LutBanks<'static>, Error<SpiE>> {
+    match refresh {
+        RefreshLut::Full => Ok(LUT_FULL),
+        RefreshLut::Quick => Ok(LUT_QUICK),
+        RefreshLut::PartialRefresh => Err(Error::Unsupported),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 240);
+        assert_eq!(HEIGHT, 360);
+        assert_eq!(NUM_DISPLAY_BYTES, 10_800);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+
+    #[test]
+    fn lut_selection() {
+        let full = luts::<()>(RefreshLut::Full).unwrap();
+        let quick = luts::<()>(RefreshLut::Quick).unwrap();
+        assert_eq!(full.vcom, &LUT_VCOM_GC[..]);
+        assert_eq!(quick.vcom, &LUT_VCOM_DU[..]);
+        assert_eq!(quick.ww.len(), 42);
+        assert!(matches!(
+            luts::<()>(RefreshLut::PartialRefresh),
+            Err(Error::Unsupported)
+        ));
+    }
+}
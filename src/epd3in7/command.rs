@@ -46,6 +46,10 @@ pub(crate) enum Command {
     DisplayUpdateSequenceSetting = 0x22,
     /// This command will transfer its data to B/W RAM, until another command is written
     WriteRam = 0x24,
+    /// This command will transfer its data to RED RAM, until another command is written
+    ///
+    /// In 4-gray mode it holds the high bit of every pixel
+    WriteRam2 = 0x26,
     /// This command writes VCOM register from MCU interface
     WriteVcomRegister = 0x2C,
     /// This command writes LUT register from MCU interface (105 bytes),
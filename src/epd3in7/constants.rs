@@ -1,5 +1,5 @@
-// This LUT clears the whole display during updates.
-pub(crate) const LUT_1GRAY_GC: [u8; 105] = [
+/// Built-in LUT of the full refresh, which clears the whole display during updates
+pub const LUT_1GRAY_GC: [u8; super::CUSTOM_LUT_LEN] = [
     0x2A, 0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //1
     0x05, 0x2A, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //2
     0x2A, 0x15, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //3
@@ -13,8 +13,8 @@ pub(crate) const LUT_1GRAY_GC: [u8; 105] = [
     0x22, 0x22, 0x22, 0x22, 0x22,
 ];
 
-// This LUT updates only the pixels that have changed.
-pub(crate) const LUT_1GRAY_DU: [u8; 105] = [
+/// Built-in LUT of the quick refresh, which updates only the pixels that have changed
+pub const LUT_1GRAY_DU: [u8; super::CUSTOM_LUT_LEN] = [
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //1
     0x01, 0x2A, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //2
     0x0A, 0x55, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //3
@@ -27,3 +27,18 @@ pub(crate) const LUT_1GRAY_DU: [u8; 105] = [
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //10
     0x22, 0x22, 0x22, 0x22, 0x22,
 ];
+
+// This LUT drives the four gray levels, it is used together with both RAM planes.
+pub(crate) const LUT_4GRAY_GC: [u8; super::CUSTOM_LUT_LEN] = [
+    0x2A, 0x06, 0x15, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //1
+    0x28, 0x06, 0x14, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //2
+    0x20, 0x06, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //3
+    0x14, 0x06, 0x28, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //4
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //5
+    0x00, 0x02, 0x02, 0x0A, 0x00, 0x00, 0x00, 0x08, 0x08, 0x02, //6
+    0x00, 0x02, 0x02, 0x0A, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //7
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //8
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //9
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //10
+    0x22, 0x22, 0x22, 0x22, 0x22,
+];
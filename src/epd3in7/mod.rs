@@ -2,6 +2,23 @@
 //!
 //!
 //! Build with the help of documentation/code from [Waveshare](https://www.waveshare.com/wiki/3.7inch_e-Paper_HAT),
+//!
+//! Besides the normal black/white mode of [`WaveshareDisplay`] the panel supports four
+//! gray levels, see [`EPD3in7::update_and_display_frame_grayscale`].
+//!
+//! # Quick refresh
+//!
+//! The [`QuickRefresh`] methods load the direct update LUT, which only drives the pixels
+//! towards the new frame and takes a fraction of the time of a full refresh. It stays loaded
+//! until the next grayscale frame or `set_lut`, do a full refresh now and
+//! then to remove the ghosting.
+//!
+//! # Custom LUT
+//!
+//! [`EPD3in7::set_custom_lut`] replaces the built-in [`LUT_1GRAY_GC`] and [`LUT_1GRAY_DU`] of
+//! the black/white refreshes until [`EPD3in7::clear_custom_lut`], also after
+//! [`wake_up`](WaveshareDisplay::wake_up). The grayscale frames keep their LUT.
+use crate::error::Error;
 use embedded_hal::{
     delay::DelayNs,
     digital::{InputPin, OutputPin},
@@ -13,11 +30,15 @@ mod constants;
 
 use self::command::Command;
 use self::constants::*;
+pub use self::constants::{LUT_1GRAY_DU, LUT_1GRAY_GC};
 
-use crate::buffer_len;
 use crate::color::Color;
 use crate::interface::DisplayInterface;
-use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::traits::{
+    check_refresh_lut, copy_lut, ssd_vcom, InternalWiAdditions, QuickRefresh, RefreshLut,
+    WaveshareDisplay,
+};
+use crate::{buffer_len, check_buffer_len, check_partial_window};
 
 /// Width of the display.
 pub const WIDTH: u32 = 280;
@@ -29,9 +50,18 @@ pub const HEIGHT: u32 = 480;
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 
 const IS_BUSY_LOW: bool = false;
+/// -1.7 V
+const DEFAULT_VCOM: u8 = 0x44;
+
+/// Length of a LUT for [`EPD3in7::set_custom_lut`], written to the LUT register (0x32)
+pub const CUSTOM_LUT_LEN: usize = 105;
 
 const SINGLE_BYTE_WRITE: bool = true;
 
+/// Number of bytes of a full frame with 2 bits per pixel, as used by
+/// [`EPD3in7::update_and_display_frame_grayscale`]
+pub const GRAY_BUFFER_LEN: usize = buffer_len(WIDTH as usize * 2, HEIGHT as usize);
+
 /// Display with Fullsize buffer for use with the 3in7 EPD
 #[cfg(feature = "graphics")]
 pub type Display3in7 = crate::graphics::Display<
@@ -42,36 +72,50 @@ pub type Display3in7 = crate::graphics::Display<
     Color,
 >;
 
+/// Full size buffer with 4 gray levels for use with
+/// [`EPD3in7::update_and_display_frame_grayscale`]
+#[cfg(feature = "graphics")]
+pub type Display3in7Gray = crate::graphics::DisplayGray2<WIDTH, HEIGHT, GRAY_BUFFER_LEN>;
+
 /// EPD3in7 driver
-pub struct EPD3in7<SPI, BUSY, DC, RST, DELAY> {
+pub struct EPD3in7<SPI, BUSY, DC, RST, DELAY, PWR> {
     /// Connection Interface
-    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
     /// Background Color
     background_color: Color,
+    /// Whether the registers are currently set up for the 4-gray mode
+    gray_mode: bool,
+    /// LUT of the 1-gray mode, chosen with `set_lut`
+    refresh: RefreshLut,
+    /// Replaces the built-in 1-gray LUTs if set
+    custom_lut: Option<[u8; CUSTOM_LUT_LEN]>,
+    /// VCOM register value, see [`set_vcom`](Self::set_vcom)
+    vcom: u8,
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
-    for EPD3in7<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for EPD3in7<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         // reset the device
-        self.interface.reset(delay, 30, 10);
+        self.interface.reset(delay, 30, 10)?;
 
         self.interface.cmd(spi, Command::SwReset)?;
         delay.delay_us(300000u32);
 
         self.interface
             .cmd_with_data(spi, Command::AutoWriteRedRamRegularPattern, &[0xF7])?;
-        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)?;
         self.interface
             .cmd_with_data(spi, Command::AutoWriteBwRamRegularPattern, &[0xF7])?;
-        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)?;
 
         self.interface
             .cmd_with_data(spi, Command::GateSetting, &[0xDF, 0x01, 0x00])?;
@@ -83,9 +127,6 @@ where
         self.interface
             .cmd_with_data(spi, Command::DataEntrySequence, &[0x03])?;
 
-        self.interface
-            .cmd_with_data(spi, Command::BorderWaveformControl, &[0x03])?;
-
         self.interface.cmd_with_data(
             spi,
             Command::BoosterSoftStartControl,
@@ -96,13 +137,10 @@ where
             .cmd_with_data(spi, Command::TemperatureSensorSelection, &[0x80])?;
 
         self.interface
-            .cmd_with_data(spi, Command::WriteVcomRegister, &[0x44])?;
+            .cmd_with_data(spi, Command::WriteVcomRegister, &[self.vcom])?;
 
-        self.interface.cmd_with_data(
-            spi,
-            Command::DisplayOption,
-            &[0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0x4F, 0xFF, 0xFF, 0xFF, 0xFF],
-        )?;
+        self.gray_mode = false;
+        self.write_mode_registers(spi)?;
 
         self.interface.cmd_with_data(
             spi,
@@ -118,55 +156,89 @@ where
         self.interface
             .cmd_with_data(spi, Command::DisplayUpdateSequenceSetting, &[0xCF])?;
 
-        self.set_lut(spi, delay, Some(RefreshLut::Full))?;
         Ok(())
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
-    for EPD3in7<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for EPD3in7<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     type DisplayColor = Color;
+    const REFRESH_LUTS: &'static [RefreshLut] = &[
+        RefreshLut::Full,
+        RefreshLut::Quick,
+        RefreshLut::PartialRefresh,
+    ];
 
     fn new(
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
+    ) -> Result<Self, Error<SPI::Error>> {
         let mut epd = EPD3in7 {
-            interface: DisplayInterface::new(busy, dc, rst, delay_us),
+            interface: DisplayInterface::new(busy, dc, rst, pwr, delay_us),
             background_color: DEFAULT_BACKGROUND_COLOR,
+            gray_mode: false,
+            refresh: RefreshLut::Full,
+            custom_lut: None,
+            vcom: DEFAULT_VCOM,
         };
 
         epd.init(spi, delay)?;
         Ok(epd)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(spi, Command::Sleep, &[0xF7])?;
         self.interface.cmd(spi, Command::PowerOff)?;
         self.interface
             .cmd_with_data(spi, Command::Sleep2, &[0xA5])?;
-        Ok(())
+        self.interface.power_off()
     }
 
     fn set_background_color(&mut self, color: Self::DisplayColor) {
         self.background_color = color;
     }
 
+    fn set_vcom(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        millivolts: i16,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.vcom = ssd_vcom(millivolts)?;
+        self.wait_until_idle(spi, delay)?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteVcomRegister, &[self.vcom])
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
     fn background_color(&self) -> &Self::DisplayColor {
         &self.background_color
     }
@@ -184,12 +256,9 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
-        assert!(buffer.len() == buffer_len(WIDTH as usize, HEIGHT as usize));
-        self.interface
-            .cmd_with_data(spi, Command::SetRamXAddressCounter, &[0x00, 0x00])?;
-        self.interface
-            .cmd_with_data(spi, Command::SetRamYAddressCounter, &[0x00, 0x00])?;
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer.len(), buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.set_window(spi, 0, 0, WIDTH, HEIGHT)?;
 
         self.interface
             .cmd_with_data(spi, Command::WriteRam, buffer)?;
@@ -197,26 +266,29 @@ where
         Ok(())
     }
 
-    #[allow(unused)]
+    /// Writes a window of the frame to the RAM, without refreshing the display
+    ///
+    /// `x` and `width` must be multiples of 8, the RAM is addressed in bytes.
     fn update_partial_frame(
         &mut self,
         spi: &mut SPI,
-        delay: &mut DELAY,
+        _delay: &mut DELAY,
         buffer: &[u8],
         x: u32,
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
-        todo!()
+    ) -> Result<(), Error<SPI::Error>> {
+        check_partial_window(buffer.len(), x, y, width, height, WIDTH, HEIGHT)?;
+        self.set_window(spi, x, y, width, height)?;
+
+        self.interface.cmd_with_data(spi, Command::WriteRam, buffer)
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        //self.interface
-        //    .cmd_with_data(spi, Command::WRITE_LUT_REGISTER, &LUT_1GRAY_GC)?;
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.set_gray_mode(spi, false)?;
         self.interface.cmd(spi, Command::DisplayUpdateSequence)?;
-        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
-        Ok(())
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)
     }
 
     fn update_and_display_frame(
@@ -224,21 +296,22 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface
-            .cmd_with_data(spi, Command::SetRamXAddressCounter, &[0x00, 0x00])?;
-        self.interface
-            .cmd_with_data(spi, Command::SetRamYAddressCounter, &[0x00, 0x00])?;
+    fn clear_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.set_window(spi, 0, 0, WIDTH, HEIGHT)?;
 
         let color = self.background_color.get_byte_value();
         self.interface.cmd(spi, Command::WriteRam)?;
-        self.interface.data_x_times(spi, color, WIDTH * HEIGHT)?;
+        self.interface.data_x_times(
+            spi,
+            color,
+            buffer_len(WIDTH as usize, HEIGHT as usize) as u32,
+        )?;
 
         Ok(())
     }
@@ -248,19 +321,464 @@ where
         spi: &mut SPI,
         _delay: &mut DELAY,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
-        let buffer = match refresh_rate {
-            Some(RefreshLut::Full) | None => &LUT_1GRAY_GC,
-            Some(RefreshLut::Quick | RefreshLut::PartialRefresh) => &LUT_1GRAY_DU,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_refresh_lut(refresh_rate, Self::REFRESH_LUTS)?;
+        if let Some(refresh) = refresh_rate {
+            self.refresh = refresh;
+        }
+        if self.gray_mode {
+            // loaded when leaving the grayscale mode
+            return Ok(());
+        }
+        self.load_lut(spi, self.refresh)
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> EPD3in7<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Writes a window of the frame to the RAM like
+    /// [`update_partial_frame`](WaveshareDisplay::update_partial_frame), with the data taken
+    /// from an iterator
+    ///
+    /// Exactly `width / 8 * height` bytes are taken, it fails with
+    /// `Error::InvalidBufferLength` if `data` ends early and leaves any further items unconsumed.
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_partial_frame_iter<I: IntoIterator<Item = u8>>(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+        data: I,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        let len = buffer_len(width as usize, height as usize);
+        check_partial_window(len, x, y, width, height, WIDTH, HEIGHT)?;
+        self.set_window(spi, x, y, width, height)?;
+
+        self.interface.cmd(spi, Command::WriteRam)?;
+        self.interface.data_iter(spi, data, len)
+    }
+
+    /// Loads `lut` instead of the built-in 1-gray LUTs
+    ///
+    /// The refresh mode selected with [`set_lut`](WaveshareDisplay::set_lut) still decides how
+    /// the frame is displayed. Fails with `Error::InvalidBufferLength` if `lut` doesn't have
+    /// [`CUSTOM_LUT_LEN`] bytes.
+    pub fn set_custom_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        lut: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.custom_lut = Some(copy_lut(lut)?);
+        self.set_lut(spi, delay, None)
+    }
+
+    /// Drops the LUT of `set_custom_lut` and loads the built-in one of the refresh mode again
+    pub fn clear_custom_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.custom_lut = None;
+        self.set_lut(spi, delay, None)
+    }
+
+    /// Transmit a full frame with 4 gray levels and display it
+    ///
+    /// The buffer holds 2 bits per pixel (4 pixels per byte, first pixel in the most
+    /// significant bits) and must be of size [`GRAY_BUFFER_LEN`]:
+    ///
+    /// - `0b00`: black
+    /// - `0b01`: dark gray
+    /// - `0b10`: light gray
+    /// - `0b11`: white
+    ///
+    /// The 4-gray LUT stays loaded until the next call to
+    /// [`display_frame`](WaveshareDisplay::display_frame), which switches back to black/white.
+    pub fn update_and_display_frame_grayscale(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer.len(), GRAY_BUFFER_LEN)?;
+        self.set_gray_mode(spi, true)?;
+
+        // the low bit of each pixel goes to the B/W RAM, the high bit to the RED RAM
+        for (command, plane) in [(Command::WriteRam, 0), (Command::WriteRam2, 1)] {
+            self.set_window(spi, 0, 0, WIDTH, HEIGHT)?;
+            self.interface.cmd(spi, command)?;
+            for pair in buffer.chunks(2) {
+                self.interface
+                    .data(spi, &[gray_plane_byte(pair[0], pair[1], plane)])?;
+            }
+        }
+
+        self.interface.cmd(spi, Command::DisplayUpdateSequence)?;
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)
+    }
+
+    /// Sets the RAM window and moves the address counter to its start
+    ///
+    /// The SSD1677 addresses x and y in pixels with two bytes each, the panel is wider than the
+    /// 255 pixels of a single byte.
+    fn set_window(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        let [x_start, x_end] = [x, x + width - 1].map(|v| (v as u16).to_le_bytes());
+        let [y_start, y_end] = [y, y + height - 1].map(|v| (v as u16).to_le_bytes());
+
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamXAddressStartEndPosition,
+            &[x_start[0], x_start[1], x_end[0], x_end[1]],
+        )?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamYAddressStartEndPosition,
+            &[y_start[0], y_start[1], y_end[0], y_end[1]],
+        )?;
+        self.interface
+            .cmd_with_data(spi, Command::SetRamXAddressCounter, &x_start)?;
+        self.interface
+            .cmd_with_data(spi, Command::SetRamYAddressCounter, &y_start)
+    }
+
+    /// Leaves the grayscale mode and loads the direct update LUT
+    fn use_quick_lut(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        self.set_gray_mode(spi, false)?;
+        self.load_lut(spi, RefreshLut::Quick)
+    }
+
+    /// Writes the 1-gray LUT of `refresh`
+    fn load_lut(&mut self, spi: &mut SPI, refresh: RefreshLut) -> Result<(), Error<SPI::Error>> {
+        let buffer = match (self.custom_lut, refresh) {
+            (Some(lut), _) => lut,
+            (None, RefreshLut::Full) => LUT_1GRAY_GC,
+            (None, RefreshLut::Quick | RefreshLut::PartialRefresh) => LUT_1GRAY_DU,
         };
 
         self.interface
-            .cmd_with_data(spi, Command::WriteLutRegister, buffer)?;
+            .cmd_with_data(spi, Command::WriteLutRegister, &buffer)
+    }
+
+    /// Switches between 1-gray and 4-gray mode if necessary
+    fn set_gray_mode(&mut self, spi: &mut SPI, gray: bool) -> Result<(), Error<SPI::Error>> {
+        if self.gray_mode != gray {
+            self.gray_mode = gray;
+            self.write_mode_registers(spi)?;
+        }
         Ok(())
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
+    /// Writes the border, display option and LUT registers of the current mode
+    fn write_mode_registers(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        if self.gray_mode {
+            self.interface
+                .cmd_with_data(spi, Command::BorderWaveformControl, &[0x00])?;
+            self.interface
+                .cmd_with_data(spi, Command::DisplayOption, &[0x00; 10])?;
+            self.interface
+                .cmd_with_data(spi, Command::WriteLutRegister, &LUT_4GRAY_GC)
+        } else {
+            self.interface
+                .cmd_with_data(spi, Command::BorderWaveformControl, &[0x03])?;
+            self.interface.cmd_with_data(
+                spi,
+                Command::DisplayOption,
+                &[0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0x4F, 0xFF, 0xFF, 0xFF, 0xFF],
+            )?;
+            self.load_lut(spi, self.refresh)
+        }
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> QuickRefresh<SPI, BUSY, DC, RST, DELAY, PWR>
+    for EPD3in7<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Writes the frame which is on the display to the second RAM
+    fn update_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer.len(), buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.set_window(spi, 0, 0, WIDTH, HEIGHT)?;
+
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam2, buffer)
+    }
+
+    /// Loads the direct update LUT and writes the new frame, to be shown with
+    /// `display_new_frame`
+    fn update_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.use_quick_lut(spi)?;
+        self.update_frame(spi, buffer, delay)
+    }
+
+    /// Refreshes the display with the LUT loaded by `update_new_frame`
+    fn display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.display_frame(spi, delay)
+    }
+
+    /// Updates and displays the new frame.
+    fn update_and_display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_new_frame(spi, buffer, delay)?;
+        self.display_new_frame(spi, delay)
+    }
+
+    /// Writes the old content of a window to the second RAM
+    ///
+    /// `x` and `width` must be multiples of 8.
+    fn update_partial_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_partial_window(buffer.len(), x, y, width, height, WIDTH, HEIGHT)?;
+        self.set_window(spi, x, y, width, height)?;
+
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam2, buffer)
+    }
+
+    /// Loads the direct update LUT and writes the new content of a window, to be shown with
+    /// `display_new_frame`
+    ///
+    /// `x` and `width` must be multiples of 8.
+    fn update_partial_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        // don't load the LUT for a window which is rejected
+        check_partial_window(buffer.len(), x, y, width, height, WIDTH, HEIGHT)?;
+        self.use_quick_lut(spi)?;
+        self.update_partial_frame(spi, delay, buffer, x, y, width, height)
+    }
+
+    /// Clears a window of both RAMs with the background color
+    ///
+    /// `x` and `width` must be multiples of 8.
+    fn clear_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        let len = buffer_len(width as usize, height as usize);
+        check_partial_window(len, x, y, width, height, WIDTH, HEIGHT)?;
+
+        let color = self.background_color.get_byte_value();
+        for ram in [Command::WriteRam, Command::WriteRam2] {
+            self.set_window(spi, x, y, width, height)?;
+            self.interface.cmd(spi, ram)?;
+            self.interface.data_x_times(spi, color, len as u32)?;
+        }
         Ok(())
     }
 }
+
+/// Extracts one bit plane out of two bytes of a 2 bits per pixel buffer
+///
+/// `plane` 0 selects the low bit, 1 the high bit of each pixel.
+fn gray_plane_byte(first: u8, second: u8, plane: u8) -> u8 {
+    let pixels = u16::from_be_bytes([first, second]);
+    (0..8).fold(0, |byte, i| {
+        let bit = (pixels >> (14 - 2 * i + plane as u16)) & 0x01;
+        byte | (bit as u8) << (7 - i)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interface::mock::expect_commands;
+    use crate::NoPin;
+    use embedded_hal_mock::eh1::{
+        delay::NoopDelay, digital::Mock as PinMock, spi::Mock as SpiMock,
+    };
+
+    /// Runs `f` on a driver which expects `commands`
+    fn with_epd(
+        commands: &[(u8, &[u8])],
+        f: impl FnOnce(
+            &mut EPD3in7<SpiMock<u8>, PinMock, PinMock, PinMock, NoopDelay, NoPin>,
+            &mut SpiMock<u8>,
+        ),
+    ) {
+        let (spi_expectations, dc_expectations) = expect_commands(commands);
+        let mut spi = SpiMock::new(&spi_expectations);
+        let mut busy = PinMock::new(&[]);
+        let mut dc = PinMock::new(&dc_expectations);
+        let mut rst = PinMock::new(&[]);
+
+        let mut epd = EPD3in7 {
+            interface: DisplayInterface::new(
+                busy.clone(),
+                dc.clone(),
+                Some(rst.clone()),
+                None::<NoPin>,
+                None,
+            ),
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            gray_mode: false,
+            refresh: RefreshLut::Full,
+            custom_lut: None,
+            vcom: DEFAULT_VCOM,
+        };
+        f(&mut epd, &mut spi);
+        drop(epd);
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 280);
+        assert_eq!(HEIGHT, 480);
+        assert_eq!(GRAY_BUFFER_LEN, 33600);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+
+    #[test]
+    fn gray_planes() {
+        // white, dark gray, light gray, black, black, light gray, dark gray, white
+        let (first, second) = (0b11_01_10_00, 0b00_10_01_11);
+        assert_eq!(gray_plane_byte(first, second, 0), 0b1100_0011);
+        assert_eq!(gray_plane_byte(first, second, 1), 0b1010_0101);
+    }
+
+    #[test]
+    fn window_in_the_bottom_right_corner() {
+        let buffer = [0xAA; 2 * 4];
+        with_epd(
+            &[
+                (0x44, &[0x08, 0x01, 0x17, 0x01]),
+                (0x45, &[0xDC, 0x01, 0xDF, 0x01]),
+                (0x4E, &[0x08, 0x01]),
+                (0x4F, &[0xDC, 0x01]),
+                (0x24, &buffer),
+            ],
+            |epd, spi| {
+                epd.update_partial_frame(spi, &mut NoopDelay::new(), &buffer, 264, 476, 16, 4)
+                    .unwrap();
+            },
+        );
+    }
+
+    #[test]
+    fn quick_window_loads_the_direct_update_lut() {
+        let buffer = [0x55; 1];
+        with_epd(
+            &[
+                (0x32, &LUT_1GRAY_DU),
+                (0x44, &[0x10, 0x01, 0x17, 0x01]),
+                (0x45, &[0x00, 0x00, 0x00, 0x00]),
+                (0x4E, &[0x10, 0x01]),
+                (0x4F, &[0x00, 0x00]),
+                (0x24, &buffer),
+            ],
+            |epd, spi| {
+                epd.update_partial_new_frame(spi, &mut NoopDelay::new(), &buffer, 272, 0, 8, 1)
+                    .unwrap();
+            },
+        );
+    }
+
+    #[test]
+    fn window_past_the_right_edge() {
+        with_epd(&[], |epd, spi| {
+            assert_eq!(
+                epd.update_partial_frame(spi, &mut NoopDelay::new(), &[0; 2], 272, 0, 16, 1),
+                Err(Error::OutOfBounds {
+                    x: 272,
+                    y: 0,
+                    width: 16,
+                    height: 1
+                })
+            );
+        });
+    }
+
+    #[test]
+    fn iterator_shorter_than_the_window() {
+        let commands: [(u8, &[u8]); 5] = [
+            (0x44, &[0x00, 0x00, 0x0F, 0x00]),
+            (0x45, &[0x00, 0x00, 0x01, 0x00]),
+            (0x4E, &[0x00, 0x00]),
+            (0x4F, &[0x00, 0x00]),
+            (0x24, &[0xFF; 3]),
+        ];
+        with_epd(&commands, |epd, spi| {
+            assert_eq!(
+                epd.update_partial_frame_iter(spi, &mut NoopDelay::new(), [0xFF; 3], 0, 0, 16, 2),
+                Err(Error::InvalidBufferLength {
+                    expected: 4,
+                    got: 3
+                })
+            );
+        });
+    }
+}
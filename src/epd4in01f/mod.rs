@@ -0,0 +1,326 @@
+//! A simple Driver for the Waveshare 4.01 inch (F) E-Ink Display via SPI
+//!
+//! # References
+//!
+//! - [Datasheet](https://www.waveshare.com/wiki/4.01inch_e-Paper_HAT_(F))
+//! - [Waveshare C driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_4in01f.c)
+//! - [Waveshare Python driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd4in01f.py)
+
+use crate::error::Error;
+use embedded_hal::{
+    delay::DelayNs,
+    digital::{InputPin, OutputPin},
+    spi::SpiDevice,
+};
+
+use crate::color::OctColor;
+use crate::interface::DisplayInterface;
+use crate::traits::{InternalWiAdditions, WaveshareDisplay};
+
+pub(crate) mod command;
+use self::command::Command;
+use crate::{buffer_len, check_buffer_len};
+
+/// Full size buffer for use with the 4in01f EPD
+///
+/// At 4 bits per pixel this takes 128 000 bytes of RAM, which is more than many
+/// microcontrollers have available.
+#[cfg(feature = "graphics")]
+pub type Display4in01f = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize, HEIGHT as usize * 4) },
+    OctColor,
+>;
+
+/// Width of the display
+pub const WIDTH: u32 = 640;
+/// Height of the display
+pub const HEIGHT: u32 = 400;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: OctColor = OctColor::White;
+/// Default mode of writing data (single byte vs blockwise)
+const SINGLE_BYTE_WRITE: bool = true;
+
+/// Epd4in01f driver
+///
+pub struct Epd4in01f<SPI, BUSY, DC, RST, DELAY, PWR> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    color: OctColor,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd4in01f<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        // Reset the device
+        self.interface.reset(delay, 10_000, 2_000)?;
+        self.interface
+            .uc_software_reset(spi, delay, Command::PanelSetting, 0x2F, true)?;
+        self.wait_until_idle(spi, delay)?;
+
+        self.cmd_with_data(spi, Command::PanelSetting, &[0x2F, 0x00])?;
+        self.cmd_with_data(spi, Command::PowerSetting, &[0x37, 0x00, 0x05, 0x05])?;
+        self.cmd_with_data(spi, Command::PowerOffSequenceSetting, &[0x00])?;
+        self.cmd_with_data(spi, Command::BoosterSoftStart, &[0xC7, 0xC7, 0x1D])?;
+        self.cmd_with_data(spi, Command::TemperatureCalibration, &[0x00])?;
+        self.update_vcom(spi)?;
+        self.cmd_with_data(spi, Command::TconSetting, &[0x22])?;
+        self.send_resolution(spi)?;
+        self.cmd_with_data(spi, Command::FlashMode, &[0xAA])?;
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd4in01f<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    type DisplayColor = OctColor;
+    fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new_color(busy, dc, rst, pwr, delay_us);
+        let color = DEFAULT_BACKGROUND_COLOR;
+
+        let mut epd = Epd4in01f { interface, color };
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.init(spi, delay)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
+        self.interface.power_off()
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(
+            buffer.len(),
+            buffer_len(WIDTH as usize, HEIGHT as usize * 4),
+        )?;
+        self.wait_until_idle(spi, delay)?;
+        self.update_vcom(spi)?;
+        self.send_resolution(spi)?;
+        self.cmd_with_data(spi, Command::DataStartTransmission1, buffer)?;
+        Ok(())
+    }
+
+    fn update_partial_frame(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+        _buffer: &[u8],
+        _x: u32,
+        _y: u32,
+        _width: u32,
+        _height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::Unsupported)
+    }
+
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.command(spi, Command::PowerOn)?;
+        self.wait_until_idle(spi, delay)?;
+        self.command(spi, Command::DisplayRefresh)?;
+        self.wait_until_idle(spi, delay)?;
+        self.command(spi, Command::PowerOff)?;
+        self.wait_busy_low(delay)?;
+        delay.delay_us(200_000);
+        Ok(())
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)?;
+        Ok(())
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let bg = OctColor::colors_byte(self.color, self.color);
+        self.wait_until_idle(spi, delay)?;
+        self.update_vcom(spi)?;
+        self.send_resolution(spi)?;
+        self.command(spi, Command::DataStartTransmission1)?;
+        self.interface.data_x_times(spi, bg, WIDTH * HEIGHT / 2)?;
+        self.display_frame(spi, delay)?;
+        Ok(())
+    }
+
+    fn set_background_color(&mut self, color: OctColor) {
+        self.color = color;
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(true)
+    }
+
+    fn background_color(&self) -> &OctColor {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, true)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd4in01f<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    fn wait_busy_low(&mut self, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, false)
+    }
+
+    /// The 4in01f expects the resolution as 16 bit big endian values: `[0x02, 0x80, 0x01, 0x90]`
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        let w = self.width();
+        let h = self.height();
+
+        self.cmd_with_data(
+            spi,
+            Command::TconResolution,
+            &[(w >> 8) as u8, w as u8, (h >> 8) as u8, h as u8],
+        )
+    }
+
+    fn update_vcom(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        let bg_color = (self.color.get_nibble() & 0b111) << 5;
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x17 | bg_color])?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interface::mock::expect_commands;
+    use crate::NoPin;
+    use embedded_hal_mock::eh1::{
+        delay::NoopDelay,
+        digital::{Mock as PinMock, State, Transaction as PinTransaction},
+        spi::Mock as SpiMock,
+    };
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 640);
+        assert_eq!(HEIGHT, 400);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, OctColor::White);
+    }
+
+    #[test]
+    fn init_sequence() {
+        let (spi_expectations, dc_expectations) = expect_commands(&[
+            (0x00, &[0x2F, 0x00]),
+            (0x01, &[0x37, 0x00, 0x05, 0x05]),
+            (0x03, &[0x00]),
+            (0x06, &[0xC7, 0xC7, 0x1D]),
+            (0x41, &[0x00]),
+            (0x50, &[0x37]),
+            (0x60, &[0x22]),
+            (0x61, &[0x02, 0x80, 0x01, 0x90]),
+            (0xE3, &[0xAA]),
+        ]);
+        let mut spi = SpiMock::new(&spi_expectations);
+        let mut busy = PinMock::new(&[PinTransaction::get(State::High)]);
+        let mut dc = PinMock::new(&dc_expectations);
+        let mut rst = PinMock::new(&[
+            PinTransaction::set(State::High),
+            PinTransaction::set(State::Low),
+            PinTransaction::set(State::High),
+        ]);
+
+        let epd = Epd4in01f::new(
+            &mut spi,
+            busy.clone(),
+            dc.clone(),
+            Some(rst.clone()),
+            None::<NoPin>,
+            &mut NoopDelay::new(),
+            None,
+        )
+        .unwrap();
+        drop(epd);
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
+}
@@ -87,6 +87,10 @@ pub(crate) enum Command {
     ///
     /// from IL0373
     LutBlackToBlack = 0x24,
+    /// Stores the Look-Up Table of the border, with 7 groups of 6 bytes like the other LUTs
+    ///
+    /// The vendor's 4-gray mode loads the white to white waveform into it.
+    LutForBorder = 0x25,
     /// The command controls the PLL clock frequency.
     PllControl = 0x30,
     /// This command reads the temperature sensed by the temperature sensor.
@@ -9,7 +9,7 @@
 //!
 //!```rust, no_run
 //!# use embedded_hal_mock::eh1::*;
-//!# fn main() -> Result<(), embedded_hal::spi::ErrorKind> {
+//!# fn main() -> Result<(), epd_waveshare::error::Error<embedded_hal::spi::ErrorKind>> {
 //!use embedded_graphics::{
 //!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
 //!};
@@ -25,7 +25,7 @@
 //!# let mut delay = delay::NoopDelay::new();
 //!
 //!// Setup EPD
-//!let mut epd = Epd4in2::new(&mut spi, busy_in, dc, rst, &mut delay, None)?;
+//!let mut epd = Epd4in2::new(&mut spi, busy_in, dc, Some(rst), None::<NoPin>, &mut delay, None)?;
 //!
 //!// Use display graphics from embedded-graphics
 //!let mut display = Display4in2::default();
@@ -45,14 +45,60 @@
 //!# }
 //!```
 //!
+//! # Quick refresh
 //!
+//! [`set_lut`](WaveshareDisplay::set_lut) with [`RefreshLut::Quick`] uploads the quick LUTs, which
+//! only drive the pixels that differ between the old and the new frame. With [`QuickRefresh`] the
+//! old and the new content of a window are written, e.g. to redraw a status area:
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), epd_waveshare::error::Error<embedded_hal::spi::ErrorKind>> {
+//!# use epd_waveshare::{epd4in2::*, prelude::*};
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = digital::Mock::new(&expectations);
+//!# let dc = digital::Mock::new(&expectations);
+//!# let rst = digital::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
+//!# let mut epd = Epd4in2::new(&mut spi, busy_in, dc, Some(rst), None::<NoPin>, &mut delay, None)?;
+//!// 100x32 pixels at (8, 268), every row takes 13 bytes
+//!let old_status = [0xFF; 13 * 32];
+//!let new_status = [0x00; 13 * 32];
+//!epd.set_lut(&mut spi, &mut delay, Some(RefreshLut::Quick))?;
+//!epd.update_partial_old_frame(&mut spi, &mut delay, &old_status, 8, 268, 100, 32)?;
+//!epd.update_partial_new_frame(&mut spi, &mut delay, &new_status, 8, 268, 100, 32)?;
+//!epd.display_new_frame(&mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+//!
+//! The x coordinate of a window must be a multiple of 8, the width is padded to whole bytes.
 //!
 //! BE CAREFUL! The screen can get ghosting/burn-ins through the Partial Fast Update Drawing.
+//! Waveshare recommends a full refresh after about 5 quick refreshes.
+//!
+//! # Grayscale
+//!
+//! Four gray levels can be shown with [`Epd4in2::update_and_display_frame_grayscale`], e.g. drawn
+//! on a [`Display4in2Gray`]. The next black/white update resets the display to leave the 4-gray
+//! mode again.
+//!
+//! # Custom LUT
+//!
+//! [`Epd4in2::set_custom_lut`] replaces the built-in [`LUT_FULL`] and [`LUT_QUICK`] of the
+//! black/white refreshes until [`Epd4in2::clear_custom_lut`], also after
+//! [`wake_up`](WaveshareDisplay::wake_up). The grayscale frames keep their LUTs.
 
+use crate::error::Error;
 use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
 
 use crate::interface::DisplayInterface;
-use crate::traits::{InternalWiAdditions, QuickRefresh, RefreshLut, WaveshareDisplay};
+use crate::traits::{
+    check_refresh_lut, uc_border, uc_vcm_dc, BorderColor, InternalWiAdditions, LutBanks,
+    LutBanksBuf, QuickRefresh, RefreshLut, WaveshareDisplay,
+};
 
 //The Lookup Tables for the Display
 mod constants;
@@ -64,14 +110,37 @@ pub const WIDTH: u32 = 400;
 pub const HEIGHT: u32 = 300;
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+/// Number of bytes of a full frame with 2 bits per pixel, as used by
+/// [`Epd4in2::update_and_display_frame_grayscale`]
+pub const GRAY_BUFFER_LEN: usize = buffer_len(WIDTH as usize * 2, HEIGHT as usize);
+/// Built-in LUTs of the full refresh, e.g. to pass to [`Epd4in2::set_custom_lut`]
+pub const LUT_FULL: LutBanks<'static> = LutBanks {
+    vcom: &LUT_VCOM0,
+    ww: &LUT_WW,
+    bw: &LUT_BW,
+    wb: &LUT_WB,
+    bb: &LUT_BB,
+};
+/// Built-in LUTs of the quick refresh
+pub const LUT_QUICK: LutBanks<'static> = LutBanks {
+    vcom: &LUT_VCOM0_QUICK,
+    ww: &LUT_WW_QUICK,
+    bw: &LUT_BW_QUICK,
+    wb: &LUT_WB_QUICK,
+    bb: &LUT_BB_QUICK,
+};
 const IS_BUSY_LOW: bool = true;
+/// VCOM and data interval setting until `set_border` is called
+const DEFAULT_VCOM_DATA_INTERVAL: u8 = 0x97;
+/// -1.0 V
+const DEFAULT_VCOM: u8 = 0x12;
 const SINGLE_BYTE_WRITE: bool = true;
 
 use crate::color::Color;
 
 pub(crate) mod command;
 use self::command::Command;
-use crate::buffer_len;
+use crate::{buffer_len, check_buffer_len};
 
 /// Full size buffer for use with the 4in2 EPD
 #[cfg(feature = "graphics")]
@@ -83,29 +152,47 @@ pub type Display4in2 = crate::graphics::Display<
     Color,
 >;
 
+/// Full size buffer with 4 gray levels for use with
+/// [`Epd4in2::update_and_display_frame_grayscale`]
+#[cfg(feature = "graphics")]
+pub type Display4in2Gray = crate::graphics::DisplayGray2<WIDTH, HEIGHT, GRAY_BUFFER_LEN>;
+
 /// Epd4in2 driver
 ///
-pub struct Epd4in2<SPI, BUSY, DC, RST, DELAY> {
+pub struct Epd4in2<SPI, BUSY, DC, RST, DELAY, PWR> {
     /// Connection Interface
-    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
     /// Background Color
     color: Color,
     /// Refresh LUT
     refresh: RefreshLut,
+    /// Whether the registers are currently set up for the 4-gray mode
+    gray_mode: bool,
+    /// Replaces the built-in black/white LUTs if set
+    custom_lut: Option<LutBanksBuf<44, 42, 42>>,
+    /// VCOM register value, see [`set_vcom`](Self::set_vcom)
+    vcom: u8,
+    /// VCOM and data interval setting, holds the border of
+    /// [`set_border`](WaveshareDisplay::set_border)
+    vcom_data_interval: u8,
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
-    for Epd4in2<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd4in2<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         // reset the device
-        self.interface.reset(delay, 10_000, 10_000);
+        self.interface.reset(delay, 10_000, 10_000)?;
+        self.interface
+            .uc_software_reset(spi, delay, Command::PanelSetting, 0x3F, IS_BUSY_LOW)?;
+        self.gray_mode = false;
 
         // set the power settings
         self.interface.cmd_with_data(
@@ -135,44 +222,57 @@ where
         self.send_resolution(spi)?;
 
         self.interface
-            .cmd_with_data(spi, Command::VcmDcSetting, &[0x12])?;
+            .cmd_with_data(spi, Command::VcmDcSetting, &[self.vcom])?;
 
         //VBDF 17|D7 VBDW 97  VBDB 57  VBDF F7  VBDW 77  VBDB 37  VBDR B7
-        self.interface
-            .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x97])?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::VcomAndDataIntervalSetting,
+            &[self.vcom_data_interval],
+        )?;
 
         self.set_lut(spi, delay, None)?;
 
-        self.wait_until_idle(spi, delay)?;
-        Ok(())
+        self.wait_until_idle(spi, delay)
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
-    for Epd4in2<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd4in2<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     type DisplayColor = Color;
+    const REFRESH_LUTS: &'static [RefreshLut] = &[
+        RefreshLut::Full,
+        RefreshLut::Quick,
+        RefreshLut::PartialRefresh,
+    ];
     fn new(
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(busy, dc, rst, pwr, delay_us);
         let color = DEFAULT_BACKGROUND_COLOR;
 
         let mut epd = Epd4in2 {
             interface,
             color,
             refresh: RefreshLut::Full,
+            gray_mode: false,
+            custom_lut: None,
+            vcom: DEFAULT_VCOM,
+            vcom_data_interval: DEFAULT_VCOM_DATA_INTERVAL,
         };
 
         epd.init(spi, delay)?;
@@ -180,7 +280,7 @@ where
         Ok(epd)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.interface
             .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x17])?; //border floating
@@ -196,10 +296,10 @@ where
         self.wait_until_idle(spi, delay)?;
         self.interface
             .cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
-        Ok(())
+        self.interface.power_off()
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.init(spi, delay)
     }
 
@@ -207,6 +307,30 @@ where
         self.color = color;
     }
 
+    fn set_vcom(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        millivolts: i16,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.vcom = uc_vcm_dc(millivolts)?;
+        self.wait_until_idle(spi, delay)?;
+        self.interface
+            .cmd_with_data(spi, Command::VcmDcSetting, &[self.vcom])
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
     fn background_color(&self) -> &Color {
         &self.color
     }
@@ -224,7 +348,8 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.leave_gray_mode(spi, delay)?;
         self.wait_until_idle(spi, delay)?;
         let color_value = self.color.get_byte_value();
 
@@ -246,45 +371,23 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        check_partial_buffer(buffer, x, y, width, height)?;
+        self.leave_gray_mode(spi, delay)?;
         self.wait_until_idle(spi, delay)?;
-        if buffer.len() as u32 != width / 8 * height {
-            //TODO: panic!! or sth like that
-            //return Err("Wrong buffersize");
-        }
 
         self.command(spi, Command::PartialIn)?;
         self.command(spi, Command::PartialWindow)?;
-        self.send_data(spi, &[(x >> 8) as u8])?;
-        let tmp = x & 0xf8;
-        self.send_data(spi, &[tmp as u8])?; // x should be the multiple of 8, the last 3 bit will always be ignored
-        let tmp = tmp + width - 1;
-        self.send_data(spi, &[(tmp >> 8) as u8])?;
-        self.send_data(spi, &[(tmp | 0x07) as u8])?;
-
-        self.send_data(spi, &[(y >> 8) as u8])?;
-        self.send_data(spi, &[y as u8])?;
-
-        self.send_data(spi, &[((y + height - 1) >> 8) as u8])?;
-        self.send_data(spi, &[(y + height - 1) as u8])?;
-
-        self.send_data(spi, &[0x01])?; // Gates scan both inside and outside of the partial window. (default)
-
-        //TODO: handle dtm somehow
-        let is_dtm1 = false;
-        if is_dtm1 {
-            self.command(spi, Command::DataStartTransmission1)? //TODO: check if data_start transmission 1 also needs "old"/background data here
-        } else {
-            self.command(spi, Command::DataStartTransmission2)?
-        }
+        self.shift_display(spi, x, y, width, height)?;
 
+        self.command(spi, Command::DataStartTransmission2)?;
         self.send_data(spi, buffer)?;
 
         self.command(spi, Command::PartialOut)?;
         Ok(())
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.command(spi, Command::DisplayRefresh)?;
         Ok(())
@@ -295,13 +398,14 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.command(spi, Command::DisplayRefresh)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.leave_gray_mode(spi, delay)?;
         self.wait_until_idle(spi, delay)?;
         self.send_resolution(spi)?;
 
@@ -322,45 +426,52 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        check_refresh_lut(refresh_rate, Self::REFRESH_LUTS)?;
         if let Some(refresh_lut) = refresh_rate {
             self.refresh = refresh_lut;
         }
-        match self.refresh {
-            RefreshLut::Full => {
-                self.set_lut_helper(spi, delay, &LUT_VCOM0, &LUT_WW, &LUT_BW, &LUT_WB, &LUT_BB)
+        match (self.custom_lut, self.refresh) {
+            (Some(lut), _) => self.set_lut_helper(spi, delay, lut.banks()),
+            (None, RefreshLut::Full) => self.set_lut_helper(spi, delay, LUT_FULL),
+            (None, RefreshLut::Quick | RefreshLut::PartialRefresh) => {
+                self.set_lut_helper(spi, delay, LUT_QUICK)
             }
-            RefreshLut::Quick | RefreshLut::PartialRefresh => self.set_lut_helper(
-                spi,
-                delay,
-                &LUT_VCOM0_QUICK,
-                &LUT_WW_QUICK,
-                &LUT_BW_QUICK,
-                &LUT_WB_QUICK,
-                &LUT_BB_QUICK,
-            ),
         }
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
-        Ok(())
+    fn set_border(&mut self, spi: &mut SPI, border: BorderColor) -> Result<(), Error<SPI::Error>> {
+        self.vcom_data_interval = uc_border(self.vcom_data_interval, border)?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::VcomAndDataIntervalSetting,
+            &[self.vcom_data_interval],
+        )
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> Epd4in2<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd4in2<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
         self.interface.data(spi, data)
     }
 
@@ -369,11 +480,11 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
-    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
         let w = self.width();
         let h = self.height();
 
@@ -384,37 +495,160 @@ where
         self.send_data(spi, &[h as u8])
     }
 
-    #[allow(clippy::too_many_arguments)]
     fn set_lut_helper(
         &mut self,
         spi: &mut SPI,
         delay: &mut DELAY,
-        lut_vcom: &[u8],
-        lut_ww: &[u8],
-        lut_bw: &[u8],
-        lut_wb: &[u8],
-        lut_bb: &[u8],
-    ) -> Result<(), SPI::Error> {
+        luts: LutBanks,
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         // LUT VCOM
-        self.cmd_with_data(spi, Command::LutForVcom, lut_vcom)?;
+        self.cmd_with_data(spi, Command::LutForVcom, luts.vcom)?;
 
         // LUT WHITE to WHITE
-        self.cmd_with_data(spi, Command::LutWhiteToWhite, lut_ww)?;
+        self.cmd_with_data(spi, Command::LutWhiteToWhite, luts.ww)?;
 
         // LUT BLACK to WHITE
-        self.cmd_with_data(spi, Command::LutBlackToWhite, lut_bw)?;
+        self.cmd_with_data(spi, Command::LutBlackToWhite, luts.bw)?;
 
         // LUT WHITE to BLACK
-        self.cmd_with_data(spi, Command::LutWhiteToBlack, lut_wb)?;
+        self.cmd_with_data(spi, Command::LutWhiteToBlack, luts.wb)?;
 
         // LUT BLACK to BLACK
-        self.cmd_with_data(spi, Command::LutBlackToBlack, lut_bb)?;
+        self.cmd_with_data(spi, Command::LutBlackToBlack, luts.bb)?;
+        Ok(())
+    }
+
+    /// Loads `luts` instead of the built-in black/white LUTs
+    ///
+    /// The VCOM LUT has 44 bytes, the others 42. The refresh mode selected with
+    /// [`set_lut`](WaveshareDisplay::set_lut) still decides how the frame is displayed. Fails
+    /// with `Error::InvalidBufferLength` for a LUT of another length.
+    pub fn set_custom_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        luts: LutBanks,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.custom_lut = Some(LutBanksBuf::new(luts)?);
+        self.set_lut(spi, delay, None)
+    }
+
+    /// Drops the LUTs of `set_custom_lut` and loads the built-in ones of the refresh mode again
+    pub fn clear_custom_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.custom_lut = None;
+        self.set_lut(spi, delay, None)
+    }
+
+    /// Transmit a full frame with 4 gray levels and display it
+    ///
+    /// The buffer holds 2 bits per pixel (4 pixels per byte, first pixel in the most
+    /// significant bits) and must be of size [`GRAY_BUFFER_LEN`]:
+    ///
+    /// - `0b00`: black
+    /// - `0b01`: dark gray
+    /// - `0b10`: light gray
+    /// - `0b11`: white
+    ///
+    /// The next black/white update resets the display to leave the 4-gray mode again.
+    pub fn update_and_display_frame_grayscale(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer.len(), GRAY_BUFFER_LEN)?;
+        self.enter_gray_mode(spi, delay)?;
+        self.wait_until_idle(spi, delay)?;
+
+        // the high bit of each pixel is the old data, the low bit the new data
+        for (command, plane) in [
+            (Command::DataStartTransmission1, 1),
+            (Command::DataStartTransmission2, 0),
+        ] {
+            self.command(spi, command)?;
+            for pair in buffer.chunks(2) {
+                self.send_data(spi, &[gray_plane_byte(pair[0], pair[1], plane)])?;
+            }
+        }
+
+        self.set_lut_helper(
+            spi,
+            delay,
+            LutBanks {
+                vcom: &LUT_VCOM0_GRAY,
+                ww: &LUT_WW_GRAY,
+                bw: &LUT_BW_GRAY,
+                wb: &LUT_WB_GRAY,
+                bb: &LUT_BB_GRAY,
+            },
+        )?;
+        self.cmd_with_data(spi, Command::LutForBorder, &LUT_WW_GRAY)?;
+
+        self.command(spi, Command::DisplayRefresh)?;
+        delay.delay_us(100);
+        self.wait_until_idle(spi, delay)
+    }
+
+    /// Resets the display into the registers of the vendor's 4-gray mode, if not done yet
+    fn enter_gray_mode(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        if self.gray_mode {
+            return Ok(());
+        }
+        self.interface.reset(delay, 10_000, 10_000)?;
+        self.interface
+            .uc_software_reset(spi, delay, Command::PanelSetting, 0x3F, IS_BUSY_LOW)?;
+        self.gray_mode = true;
+
+        self.interface.cmd_with_data(
+            spi,
+            Command::PowerSetting,
+            &[0x03, 0x00, 0x2b, 0x2b, 0x13],
+        )?;
+        self.interface
+            .cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x17])?;
+        self.command(spi, Command::PowerOn)?;
+        delay.delay_us(5000);
+        self.wait_until_idle(spi, delay)?;
+
+        // KW mode with the LUTs from the registers
+        self.cmd_with_data(spi, Command::PanelSetting, &[0x3F])?;
+        // 100 Hz
+        self.cmd_with_data(spi, Command::PllControl, &[0x3C])?;
+        self.send_resolution(spi)?;
+        self.interface
+            .cmd_with_data(spi, Command::VcmDcSetting, &[self.vcom])?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::VcomAndDataIntervalSetting,
+            &[self.vcom_data_interval],
+        )
+    }
+
+    /// Resets the display to its black/white mode after a grayscale frame
+    fn leave_gray_mode(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        if self.gray_mode {
+            self.init(spi, delay)?;
+        }
         Ok(())
     }
 
     /// Helper function. Sets up the display to send pixel data to a custom
     /// starting point.
+    ///
+    /// Sends the data of the partial window command, the window is extended to whole bytes.
     pub fn shift_display(
         &mut self,
         spi: &mut SPI,
@@ -422,33 +656,19 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
-        self.send_data(spi, &[(x >> 8) as u8])?;
-        let tmp = x & 0xf8;
-        self.send_data(spi, &[tmp as u8])?; // x should be the multiple of 8, the last 3 bit will always be ignored
-        let tmp = tmp + width - 1;
-        self.send_data(spi, &[(tmp >> 8) as u8])?;
-        self.send_data(spi, &[(tmp | 0x07) as u8])?;
-
-        self.send_data(spi, &[(y >> 8) as u8])?;
-        self.send_data(spi, &[y as u8])?;
-
-        self.send_data(spi, &[((y + height - 1) >> 8) as u8])?;
-        self.send_data(spi, &[(y + height - 1) as u8])?;
-
-        self.send_data(spi, &[0x01])?; // Gates scan both inside and outside of the partial window. (default)
-
-        Ok(())
+    ) -> Result<(), Error<SPI::Error>> {
+        self.send_data(spi, &partial_window(x, y, width, height))
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> QuickRefresh<SPI, BUSY, DC, RST, DELAY>
-    for Epd4in2<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> QuickRefresh<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd4in2<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     /// To be followed immediately after by `update_old_frame`.
@@ -457,7 +677,8 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.leave_gray_mode(spi, delay)?;
         self.wait_until_idle(spi, delay)?;
 
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
@@ -473,7 +694,8 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.leave_gray_mode(spi, delay)?;
         self.wait_until_idle(spi, delay)?;
         // self.send_resolution(spi)?;
 
@@ -486,7 +708,11 @@ where
 
     /// This is a wrapper around `display_frame` for using this device as a true
     /// `QuickRefresh` device.
-    fn display_new_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
         self.display_frame(spi, delay)
     }
 
@@ -499,7 +725,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_new_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)
     }
@@ -513,14 +739,11 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        check_partial_buffer(buffer, x, y, width, height)?;
+        self.leave_gray_mode(spi, delay)?;
         self.wait_until_idle(spi, delay)?;
 
-        if buffer.len() as u32 != width / 8 * height {
-            //TODO: panic!! or sth like that
-            //return Err("Wrong buffersize");
-        }
-
         self.interface.cmd(spi, Command::PartialIn)?;
         self.interface.cmd(spi, Command::PartialWindow)?;
 
@@ -530,11 +753,14 @@ where
 
         self.interface.data(spi, buffer)?;
 
+        self.interface.cmd(spi, Command::PartialOut)?;
         Ok(())
     }
 
-    /// Always call `update_partial_old_frame` before this, with buffer-updating code
-    /// between the calls.
+    /// Writes the new content of a window
+    ///
+    /// Call `update_partial_old_frame` with the same window before this, the quick LUTs only
+    /// drive the pixels which differ between the old and the new frame.
     fn update_partial_new_frame(
         &mut self,
         spi: &mut SPI,
@@ -544,12 +770,13 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        check_partial_buffer(buffer, x, y, width, height)?;
+        self.leave_gray_mode(spi, delay)?;
         self.wait_until_idle(spi, delay)?;
-        if buffer.len() as u32 != width / 8 * height {
-            //TODO: panic!! or sth like that
-            //return Err("Wrong buffersize");
-        }
+
+        self.interface.cmd(spi, Command::PartialIn)?;
+        self.interface.cmd(spi, Command::PartialWindow)?;
 
         self.shift_display(spi, x, y, width, height)?;
 
@@ -569,7 +796,8 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.leave_gray_mode(spi, delay)?;
         self.wait_until_idle(spi, delay)?;
         self.send_resolution(spi)?;
 
@@ -580,27 +808,300 @@ where
 
         self.shift_display(spi, x, y, width, height)?;
 
+        let len = buffer_len(width as usize, height as usize) as u32;
+
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
-        self.interface
-            .data_x_times(spi, color_value, width / 8 * height)?;
+        self.interface.data_x_times(spi, color_value, len)?;
 
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
-        self.interface
-            .data_x_times(spi, color_value, width / 8 * height)?;
+        self.interface.data_x_times(spi, color_value, len)?;
 
         self.interface.cmd(spi, Command::PartialOut)?;
         Ok(())
     }
 }
 
+/// Extracts one bit plane out of two bytes of a 2 bits per pixel buffer
+///
+/// `plane` 0 selects the low bit, 1 the high bit of each pixel.
+fn gray_plane_byte(first: u8, second: u8, plane: u8) -> u8 {
+    let pixels = u16::from_be_bytes([first, second]);
+    (0..8).fold(0, |byte, i| {
+        let bit = (pixels >> (14 - 2 * i + plane as u16)) & 0x01;
+        byte | (bit as u8) << (7 - i)
+    })
+}
+
+/// Checks that a window starts at a byte, fits on the display and that the buffer has its size
+///
+/// The width doesn't need to be a multiple of 8, every row of the buffer is padded to whole bytes.
+fn check_partial_buffer<SpiE>(
+    buffer: &[u8],
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> Result<(), Error<SpiE>> {
+    if x % 8 != 0 || width == 0 || height == 0 || x + width > WIDTH || y + height > HEIGHT {
+        return Err(Error::OutOfBounds {
+            x,
+            y,
+            width,
+            height,
+        });
+    }
+    check_buffer_len(buffer.len(), buffer_len(width as usize, height as usize))
+}
+
+/// Data of the partial window command
+///
+/// The horizontal end is extended to the last pixel of its byte.
+fn partial_window(x: u32, y: u32, width: u32, height: u32) -> [u8; 9] {
+    let x_start = x & !0x07; // the last 3 bits are always ignored
+    let x_end = (x_start + width - 1) | 0x07;
+    let y_end = y + height - 1;
+    [
+        (x_start >> 8) as u8,
+        x_start as u8,
+        (x_end >> 8) as u8,
+        x_end as u8,
+        (y >> 8) as u8,
+        y as u8,
+        (y_end >> 8) as u8,
+        y_end as u8,
+        0x01, // Gates scan both inside and outside of the partial window. (default)
+    ]
+}
+
 #[cfg(test)]
 mod tests {
+    extern crate std;
     use super::*;
+    use crate::interface::mock::expect_commands;
+    use crate::NoPin;
+    use embedded_hal_mock::eh1::{
+        delay::NoopDelay,
+        digital::{Mock as PinMock, State, Transaction as PinTransaction},
+        spi::Mock as SpiMock,
+    };
+    use std::{vec, vec::Vec};
 
     #[test]
     fn epd_size() {
         assert_eq!(WIDTH, 400);
         assert_eq!(HEIGHT, 300);
+        assert_eq!(GRAY_BUFFER_LEN, 30_000);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    /// Commands loading the black/white LUTs
+    fn lut_commands<'a>(luts: LutBanks<'a>) -> [(u8, &'a [u8]); 5] {
+        [
+            (0x20, luts.vcom),
+            (0x21, luts.ww),
+            (0x22, luts.bw),
+            (0x23, luts.wb),
+            (0x24, luts.bb),
+        ]
+    }
+
+    #[test]
+    fn custom_lut_until_cleared() {
+        let (vcom, lut) = ([0x01; 44], [0x02; 42]);
+        let custom = LutBanks {
+            vcom: &vcom,
+            ww: &lut,
+            bw: &lut,
+            wb: &lut,
+            bb: &lut,
+        };
+        let commands = [lut_commands(custom), lut_commands(LUT_FULL)].concat();
+        let (spi_expectations, dc_expectations) = expect_commands(&commands);
+        let mut spi = SpiMock::new(&spi_expectations);
+        let mut busy = PinMock::new(&vec![PinTransaction::get(State::High); 2]);
+        let mut dc = PinMock::new(&dc_expectations);
+        let mut rst = PinMock::new(&[]);
+
+        let mut epd = Epd4in2 {
+            interface: DisplayInterface::new(
+                busy.clone(),
+                dc.clone(),
+                Some(rst.clone()),
+                None::<NoPin>,
+                None,
+            ),
+            color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+            gray_mode: false,
+            custom_lut: None,
+            vcom: DEFAULT_VCOM,
+            vcom_data_interval: DEFAULT_VCOM_DATA_INTERVAL,
+        };
+        let delay = &mut NoopDelay::new();
+        assert_eq!(
+            epd.set_custom_lut(
+                &mut spi,
+                delay,
+                LutBanks {
+                    bb: &vcom,
+                    ..custom
+                }
+            ),
+            Err(Error::InvalidBufferLength {
+                expected: 42,
+                got: 44
+            })
+        );
+        epd.set_custom_lut(&mut spi, delay, custom).unwrap();
+        epd.clear_custom_lut(&mut spi, delay).unwrap();
+        drop(epd);
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
+
+    #[test]
+    fn wake_up_restores_the_settings() {
+        let (vcom, lut) = ([0x01; 44], [0x02; 42]);
+        let custom = LutBanks {
+            vcom: &vcom,
+            ww: &lut,
+            bw: &lut,
+            wb: &lut,
+            bb: &lut,
+        };
+        // the data of these commands is sent with DC set high for every byte
+        let bytewise = |command: u8, data: &[u8]| {
+            let (spi, mut dc) = expect_commands(&[(command, data)]);
+            dc.extend(vec![PinTransaction::set(State::High); data.len() - 1]);
+            (spi, dc)
+        };
+        let segments = [
+            expect_commands(&lut_commands(custom)),
+            expect_commands(&[(0x82, &[0x1C]), (0x50, &[0x57])]),
+            // sleep
+            expect_commands(&[(0x50, &[0x17]), (0x82, &[]), (0x00, &[])]),
+            bytewise(0x01, &[0x00; 4]),
+            expect_commands(&[(0x02, &[]), (0x07, &[0xA5])]),
+            // reset and init
+            expect_commands(&[
+                (0x01, &[0x03, 0x00, 0x2B, 0x2B, 0xFF]),
+                (0x06, &[0x17, 0x17, 0x17]),
+                (0x04, &[]),
+                (0x00, &[0x3F]),
+                (0x30, &[0x3A]),
+            ]),
+            bytewise(0x61, &[0x01, 0x90, 0x01, 0x2C]),
+            expect_commands(&[(0x82, &[0x1C]), (0x50, &[0x57])]),
+            expect_commands(&lut_commands(custom)),
+        ];
+        let (mut spi_expectations, mut dc_expectations) = (Vec::new(), Vec::new());
+        for (spi, dc) in segments {
+            spi_expectations.extend(spi);
+            dc_expectations.extend(dc);
+        }
+        let mut spi = SpiMock::new(&spi_expectations);
+        let mut busy = PinMock::new(&vec![PinTransaction::get(State::High); 7]);
+        let mut dc = PinMock::new(&dc_expectations);
+        let mut rst = PinMock::new(&[
+            PinTransaction::set(State::High),
+            PinTransaction::set(State::Low),
+            PinTransaction::set(State::High),
+        ]);
+
+        let mut epd = Epd4in2 {
+            interface: DisplayInterface::new(
+                busy.clone(),
+                dc.clone(),
+                Some(rst.clone()),
+                None::<NoPin>,
+                None,
+            ),
+            color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+            gray_mode: false,
+            custom_lut: None,
+            vcom: DEFAULT_VCOM,
+            vcom_data_interval: DEFAULT_VCOM_DATA_INTERVAL,
+        };
+        let delay = &mut NoopDelay::new();
+        epd.set_custom_lut(&mut spi, delay, custom).unwrap();
+        epd.set_vcom(&mut spi, delay, -1500).unwrap();
+        epd.set_border(&mut spi, BorderColor::Black).unwrap();
+        epd.sleep(&mut spi, delay).unwrap();
+        epd.wake_up(&mut spi, delay).unwrap();
+        drop(epd);
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
+
+    #[test]
+    fn gray_planes() {
+        // white, dark gray, light gray, black, black, light gray, dark gray, white
+        let (first, second) = (0b11_01_10_00, 0b00_10_01_11);
+        // old data: white and light gray are set
+        assert_eq!(gray_plane_byte(first, second, 1), 0b1010_0101);
+        // new data: white and dark gray are set
+        assert_eq!(gray_plane_byte(first, second, 0), 0b1100_0011);
+    }
+
+    #[test]
+    fn partial_windows() {
+        assert_eq!(
+            partial_window(0, 0, WIDTH, HEIGHT),
+            [0x00, 0x00, 0x01, 0x8F, 0x00, 0x00, 0x01, 0x2B, 0x01]
+        );
+        // a window beyond x = 255 and a width which isn't a multiple of 8
+        assert_eq!(
+            partial_window(296, 268, 100, 32),
+            [0x01, 0x28, 0x01, 0x8F, 0x01, 0x0C, 0x01, 0x2B, 0x01]
+        );
+    }
+
+    #[test]
+    fn partial_new_frame_sets_its_window() {
+        let buffer = [0xAA; 13 * 2];
+        let (spi_expectations, dc_expectations) = expect_commands(&[
+            (0x91, &[]),
+            (
+                0x90,
+                &[0x00, 0x08, 0x00, 0x6F, 0x00, 0x20, 0x00, 0x21, 0x01],
+            ),
+            (0x13, &buffer),
+            (0x92, &[]),
+        ]);
+        let mut spi = SpiMock::new(&spi_expectations);
+        let mut busy = PinMock::new(&[PinTransaction::get(State::High)]);
+        let mut dc = PinMock::new(&dc_expectations);
+        let mut rst = PinMock::new(&[]);
+
+        let mut epd = Epd4in2 {
+            interface: DisplayInterface::new(
+                busy.clone(),
+                dc.clone(),
+                Some(rst.clone()),
+                None::<NoPin>,
+                None,
+            ),
+            color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Quick,
+            gray_mode: false,
+            custom_lut: None,
+            vcom: DEFAULT_VCOM,
+            vcom_data_interval: DEFAULT_VCOM_DATA_INTERVAL,
+        };
+        epd.update_partial_new_frame(&mut spi, &mut NoopDelay::new(), &buffer, 8, 32, 100, 2)
+            .unwrap();
+        drop(epd);
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
 }
@@ -0,0 +1,510 @@
+//! A simple Driver for the Waveshare 4.2" E-Ink Display (V2) via SPI
+//!
+//! The V2 revision replaced the UC8176 controller of the [`epd4in2`](crate::epd4in2) with an
+//! SSD1683, which has a different command set and doesn't react to the init of the old driver:
+//! the display just stays white. Boards with the new controller are marked "Rev 2.2" (or "V2")
+//! on the back of the PCB, the older ones "Rev 2.1" or lower. If in doubt, try both drivers.
+//!
+//! Besides the full refresh, the fast refresh of the vendor is selected with
+//! [`RefreshLut::Quick`] and four gray levels can be shown with
+//! [`Epd4in2::update_and_display_frame_grayscale`].
+//!
+//! # References
+//!
+//! - [Waveshare Wiki](https://www.waveshare.com/wiki/4.2inch_e-Paper_Module_Manual)
+//! - [Waveshare C driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_4in2_V2.c)
+//!
+//! # Example for the 4.2" V2 E-Ink Display
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), epd_waveshare::error::Error<embedded_hal::spi::ErrorKind>> {
+//!use embedded_graphics::{prelude::*, primitives::{Line, PrimitiveStyle}};
+//!use epd_waveshare::{epd4in2_v2::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = digital::Mock::new(&expectations);
+//!# let dc = digital::Mock::new(&expectations);
+//!# let rst = digital::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
+//!
+//!// Setup EPD
+//!let mut epd = Epd4in2::new(&mut spi, busy_in, dc, Some(rst), None::<NoPin>, &mut delay, None)?;
+//!
+//!// Use display graphics from embedded-graphics
+//!let mut display = Display4in2::default();
+//!let _ = Line::new(Point::new(0, 120), Point::new(0, 295))
+//!    .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
+//!    .draw(&mut display);
+//!
+//!// Display updated frame with the fast refresh
+//!epd.set_lut(&mut spi, &mut delay, Some(RefreshLut::Quick))?;
+//!epd.update_and_display_frame(&mut spi, display.buffer(), &mut delay)?;
+//!
+//!// Set the EPD to sleep
+//!epd.sleep(&mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+
+use crate::error::Error;
+use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
+
+use crate::color::Color;
+use crate::interface::DisplayInterface;
+use crate::traits::{check_refresh_lut, InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::type_a::command::Command;
+use crate::{buffer_len, check_buffer_len, check_partial_window};
+
+/// Width of the display
+pub const WIDTH: u32 = 400;
+/// Height of the display
+pub const HEIGHT: u32 = 300;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+/// Number of bytes of a full frame with 2 bits per pixel, as used by
+/// [`Epd4in2::update_and_display_frame_grayscale`]
+pub const GRAY_BUFFER_LEN: usize = buffer_len(WIDTH as usize * 2, HEIGHT as usize);
+/// BUSY is high active on the SSD1683, unlike on the V1
+const IS_BUSY_LOW: bool = false;
+const SINGLE_BYTE_WRITE: bool = false;
+
+/// Full size buffer for use with the 4in2 V2 EPD
+#[cfg(feature = "graphics")]
+pub type Display4in2 = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize, HEIGHT as usize) },
+    Color,
+>;
+
+/// Full size buffer with 4 gray levels for use with
+/// [`Epd4in2::update_and_display_frame_grayscale`]
+#[cfg(feature = "graphics")]
+pub type Display4in2Gray = crate::graphics::DisplayGray2<WIDTH, HEIGHT, GRAY_BUFFER_LEN>;
+
+/// Epd4in2 (V2) driver
+pub struct Epd4in2<SPI, BUSY, DC, RST, DELAY, PWR> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    background_color: Color,
+    /// Refresh LUT
+    refresh: RefreshLut,
+    /// Whether the registers are currently set up for the 4-gray mode
+    gray_mode: bool,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd4in2<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, 100_000, 2_000)?;
+        self.gray_mode = false;
+
+        self.wait_until_idle(spi, delay)?;
+        self.interface.cmd(spi, Command::SwReset)?;
+        self.wait_until_idle(spi, delay)?;
+
+        // bypass the RED RAM as 0, the B/W RAM as is
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl1, &[0x40, 0x00])?;
+        self.interface
+            .cmd_with_data(spi, Command::BorderWaveformControl, &[0x05])?;
+
+        if self.refresh == RefreshLut::Quick {
+            self.load_fast_temperature(spi, delay)?;
+        }
+
+        // x increment, y increment, address counter is updated in x direction
+        self.interface
+            .cmd_with_data(spi, Command::DataEntryModeSetting, &[0x03])?;
+        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
+        self.set_ram_counter(spi, 0, 0)?;
+        self.wait_until_idle(spi, delay)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd4in2<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    type DisplayColor = Color;
+    const REFRESH_LUTS: &'static [RefreshLut] = &[RefreshLut::Full, RefreshLut::Quick];
+
+    fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(busy, dc, rst, pwr, delay_us);
+
+        let mut epd = Epd4in2 {
+            interface,
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+            gray_mode: false,
+        };
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        // deep sleep mode 1, the RAM content is retained
+        self.interface
+            .cmd_with_data(spi, Command::DeepSleepMode, &[0x01])?;
+        self.interface.power_off()
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.init(spi, delay)
+    }
+
+    fn set_background_color(&mut self, background_color: Color) {
+        self.background_color = background_color;
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.background_color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer.len(), buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.leave_gray_mode(spi, delay)?;
+        self.wait_until_idle(spi, delay)?;
+
+        // both RAMs get the frame, like in the vendor driver
+        for command in [Command::WriteRam, Command::WriteRam2] {
+            self.set_ram_counter(spi, 0, 0)?;
+            self.interface.cmd_with_data(spi, command, buffer)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a window of the frame to the RAM, without refreshing the display
+    ///
+    /// `x` and `width` must be multiples of 8.
+    fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_partial_window(buffer.len(), x, y, width, height, WIDTH, HEIGHT)?;
+        self.leave_gray_mode(spi, delay)?;
+        self.wait_until_idle(spi, delay)?;
+
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)?;
+        self.set_ram_counter(spi, x, y)?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam, buffer)?;
+        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)
+    }
+
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        let sequence = display_update_sequence(self.refresh)?;
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[sequence])?;
+        self.interface.cmd(spi, Command::MasterActivation)?;
+        self.wait_until_idle(spi, delay)
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.leave_gray_mode(spi, delay)?;
+        self.wait_until_idle(spi, delay)?;
+
+        let color = self.background_color.get_byte_value();
+        for command in [Command::WriteRam, Command::WriteRam2] {
+            self.set_ram_counter(spi, 0, 0)?;
+            self.interface.cmd(spi, command)?;
+            self.interface
+                .data_x_times(spi, color, WIDTH / 8 * HEIGHT)?;
+        }
+        Ok(())
+    }
+
+    /// [`RefreshLut::Full`] and the fast [`RefreshLut::Quick`] are supported
+    fn set_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_refresh_lut(refresh_rate, Self::REFRESH_LUTS)?;
+        if let Some(refresh_lut) = refresh_rate {
+            if refresh_lut != self.refresh {
+                self.refresh = refresh_lut;
+                // the temperature of the fast refresh is only reset by a hardware reset
+                self.init(spi, delay)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd4in2<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Transmit a full frame with 4 gray levels and display it
+    ///
+    /// The buffer holds 2 bits per pixel (4 pixels per byte, first pixel in the most
+    /// significant bits) and must be of size [`GRAY_BUFFER_LEN`]:
+    ///
+    /// - `0b00`: black
+    /// - `0b01`: dark gray
+    /// - `0b10`: light gray
+    /// - `0b11`: white
+    ///
+    /// The next black/white update resets the display to leave the 4-gray mode again.
+    pub fn update_and_display_frame_grayscale(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer.len(), GRAY_BUFFER_LEN)?;
+        self.enter_gray_mode(spi, delay)?;
+
+        // the high bit of each pixel goes to the B/W RAM, the low bit to the RED RAM
+        for (command, plane) in [(Command::WriteRam, 1), (Command::WriteRam2, 0)] {
+            self.set_ram_counter(spi, 0, 0)?;
+            self.interface.cmd(spi, command)?;
+            for pair in buffer.chunks(2) {
+                self.interface
+                    .data(spi, &[gray_plane_byte(pair[0], pair[1], plane)])?;
+            }
+        }
+
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xCF])?;
+        self.interface.cmd(spi, Command::MasterActivation)?;
+        self.wait_until_idle(spi, delay)
+    }
+
+    /// Sets up the registers of the vendor's 4-gray mode, if not done yet
+    fn enter_gray_mode(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        if self.gray_mode {
+            return Ok(());
+        }
+        self.gray_mode = true;
+        self.wait_until_idle(spi, delay)?;
+
+        // both RAMs are used as is
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl1, &[0x00, 0x00])?;
+        self.interface
+            .cmd_with_data(spi, Command::BorderWaveformControl, &[0x03])?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::BoosterSoftStartControl,
+            &[0x8B, 0x9C, 0xA4, 0x0F],
+        )?;
+        self.load_fast_temperature(spi, delay)
+    }
+
+    /// Resets the display to its black/white mode after a grayscale frame
+    fn leave_gray_mode(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        if self.gray_mode {
+            self.init(spi, delay)?;
+        }
+        Ok(())
+    }
+
+    /// Overwrites the temperature, which selects the fast waveform of the OTP
+    fn load_fast_temperature(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .cmd_with_data(spi, Command::TemperatureSensorControl, &[0x5A])?;
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0x91])?;
+        self.interface.cmd(spi, Command::MasterActivation)?;
+        self.wait_until_idle(spi, delay)
+    }
+
+    fn set_ram_area(
+        &mut self,
+        spi: &mut SPI,
+        start_x: u32,
+        start_y: u32,
+        end_x: u32,
+        end_y: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        // the x addresses are given in bytes
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamXAddressStartEndPosition,
+            &[(start_x >> 3) as u8, (end_x >> 3) as u8],
+        )?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamYAddressStartEndPosition,
+            &[
+                start_y as u8,
+                (start_y >> 8) as u8,
+                end_y as u8,
+                (end_y >> 8) as u8,
+            ],
+        )
+    }
+
+    fn set_ram_counter(&mut self, spi: &mut SPI, x: u32, y: u32) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .cmd_with_data(spi, Command::SetRamXAddressCounter, &[(x >> 3) as u8])?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamYAddressCounter,
+            &[y as u8, (y >> 8) as u8],
+        )
+    }
+}
+
+/// Display update control 2 sequence used by `display_frame` for each refresh mode, fails with
+/// `Error::Unsupported` for the partial refresh
+fn display_update_sequence<SpiE>(refresh: RefreshLut) -> Result<u8, Error<SpiE>> {
+    match refresh {
+        // Enable clock signal, Enable Analog, Load temperature value, Load LUT from OTP,
+        // DISPLAY with DISPLAY Mode 1, Disable Analog, Disable OSC
+        RefreshLut::Full => Ok(0xF7),
+        // same without loading the temperature, which was overwritten for the fast waveform
+        RefreshLut::Quick => Ok(0xC7),
+        RefreshLut::PartialRefresh => Err(Error::Unsupported),
+    }
+}
+
+/// Extracts one bit plane out of two bytes of a 2 bits per pixel buffer
+///
+/// `plane` 0 selects the low bit, 1 the high bit of each pixel.
+fn gray_plane_byte(first: u8, second: u8, plane: u8) -> u8 {
+    let pixels = u16::from_be_bytes([first, second]);
+    (0..8).fold(0, |byte, i| {
+        let bit = (pixels >> (14 - 2 * i + plane as u16)) & 0x01;
+        byte | (bit as u8) << (7 - i)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 400);
+        assert_eq!(HEIGHT, 300);
+        assert_eq!(GRAY_BUFFER_LEN, 30_000);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+
+    #[test]
+    fn update_sequences() {
+        assert!(matches!(
+            display_update_sequence::<()>(RefreshLut::Full),
+            Ok(0xF7)
+        ));
+        assert!(matches!(
+            display_update_sequence::<()>(RefreshLut::Quick),
+            Ok(0xC7)
+        ));
+        assert!(matches!(
+            display_update_sequence::<()>(RefreshLut::PartialRefresh),
+            Err(Error::Unsupported)
+        ));
+    }
+
+    #[test]
+    fn gray_planes() {
+        // white, dark gray, light gray, black, black, light gray, dark gray, white
+        let (first, second) = (0b11_01_10_00, 0b00_10_01_11);
+        // B/W RAM: white and light gray are set
+        assert_eq!(gray_plane_byte(first, second, 1), 0b1010_0101);
+        // RED RAM: white and dark gray are set
+        assert_eq!(gray_plane_byte(first, second, 0), 0b1100_0011);
+    }
+}
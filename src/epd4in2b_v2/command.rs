@@ -0,0 +1,21 @@
+//! SPI Commands for the Waveshare 4.2" B V2 E-Ink Display
+use crate::traits;
+
+#[derive(Copy, Clone)]
+pub(crate) enum Command {
+    PanelSetting = 0x00,
+    PowerOff = 0x02,
+    PowerOn = 0x04,
+    DeepSleep = 0x07,
+    DataStartTransmission1 = 0x10,
+    DisplayRefresh = 0x12,
+    DataStartTransmission2 = 0x13,
+    VcomAndDataIntervalSetting = 0x50,
+}
+
+impl traits::Command for Command {
+    /// Returns the address of the command
+    fn address(self) -> u8 {
+        self as u8
+    }
+}
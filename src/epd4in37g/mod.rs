@@ -0,0 +1,382 @@
+//! A simple Driver for the Waveshare 4.37" (G) 4-Color E-Ink Display via SPI
+//!
+//! The panel shows black, white, yellow and red. Each pixel takes 2 bits of the buffer
+//! (see [`QuadColor`]), the whole frame is written at once into a single RAM.
+//!
+//! A full refresh takes about 20 seconds at room temperature and a lot longer when it is cold.
+//! [`display_frame`](WaveshareDisplay::display_frame) doesn't wait for a fixed time but polls the
+//! busy pin until the panel is done, the `delay_us` given to
+//! [`new`](WaveshareDisplay::new) is only the interval between two polls.
+//!
+//! # References
+//!
+//! - [Waveshare Wiki](https://www.waveshare.com/wiki/7.3inch_e-Paper_HAT_(G))
+//! - [Waveshare C driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_4in37g.c)
+//!
+//! # Example for the 4.37" G E-Ink Display
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), epd_waveshare::error::Error<embedded_hal::spi::ErrorKind>> {
+//!use embedded_graphics::{prelude::*, primitives::{Line, PrimitiveStyle}};
+//!use epd_waveshare::{epd4in37g::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = digital::Mock::new(&expectations);
+//!# let dc = digital::Mock::new(&expectations);
+//!# let rst = digital::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
+//!
+//!// Setup EPD
+//!let mut epd = Epd4in37g::new(&mut spi, busy_in, dc, Some(rst), None::<NoPin>, &mut delay, None)?;
+//!
+//!// Use display graphics from embedded-graphics
+//!let mut display = Display4in37g::default();
+//!display.clear(QuadColor::White);
+//!
+//!// A red and a yellow line in the same frame
+//!let _ = Line::new(Point::new(0, 120), Point::new(0, 200))
+//!    .into_styled(PrimitiveStyle::with_stroke(QuadColor::Red, 1))
+//!    .draw(&mut display);
+//!let _ = Line::new(Point::new(15, 120), Point::new(15, 200))
+//!    .into_styled(PrimitiveStyle::with_stroke(QuadColor::Yellow, 1))
+//!    .draw(&mut display);
+//!
+//!// Display updated frame
+//!epd.update_and_display_frame(&mut spi, display.buffer(), &mut delay)?;
+//!
+//!// Set the EPD to sleep
+//!epd.sleep(&mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+
+use crate::error::Error;
+use embedded_hal::{
+    delay::DelayNs,
+    digital::{InputPin, OutputPin},
+    spi::SpiDevice,
+};
+
+use crate::color::QuadColor;
+use crate::interface::DisplayInterface;
+use crate::traits::{InternalWiAdditions, WaveshareDisplay};
+
+use crate::type_g::command::Command;
+use crate::{buffer_len, check_buffer_len};
+
+/// Width of the display
+pub const WIDTH: u32 = 512;
+/// Height of the display
+pub const HEIGHT: u32 = 368;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: QuadColor = QuadColor::White;
+/// Number of bytes of a full frame at 2 bits per pixel
+const NUM_DISPLAY_BYTES: u32 = WIDTH / 4 * HEIGHT;
+const IS_BUSY_LOW: bool = true;
+const SINGLE_BYTE_WRITE: bool = true;
+
+/// Full size buffer for use with the 4in37g EPD
+///
+/// It takes 47 104 bytes of RAM.
+#[cfg(feature = "graphics")]
+pub type Display4in37g = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize * 2, HEIGHT as usize) },
+    QuadColor,
+>;
+
+/// Epd4in37g driver
+pub struct Epd4in37g<SPI, BUSY, DC, RST, DELAY, PWR> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    color: QuadColor,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd4in37g<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, 20_000, 2_000)?;
+        self.interface
+            .uc_software_reset(spi, delay, Command::PanelSetting, 0x2F, IS_BUSY_LOW)?;
+        self.wait_until_idle(spi, delay)?;
+
+        self.cmd_with_data(spi, Command::Undocumented4D, &[0x78])?;
+        self.cmd_with_data(spi, Command::PanelSetting, &[0x2F, 0x29])?;
+        self.cmd_with_data(spi, Command::PowerSetting, &[0x07, 0x00])?;
+        self.cmd_with_data(spi, Command::PowerOffSequenceSetting, &[0x10, 0x54, 0x44])?;
+        self.cmd_with_data(
+            spi,
+            Command::BoosterSoftStart,
+            &[0x0F, 0x0A, 0x2F, 0x25, 0x22, 0x2E, 0x21],
+        )?;
+        self.cmd_with_data(spi, Command::TemperatureSensorEnable, &[0x00])?;
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x37])?;
+        self.cmd_with_data(spi, Command::TconSetting, &[0x02, 0x02])?;
+        self.send_resolution(spi)?;
+        self.cmd_with_data(
+            spi,
+            Command::GateSourceStartSetting,
+            &[0x00, 0x00, 0x00, 0x00],
+        )?;
+        self.cmd_with_data(spi, Command::UndocumentedE7, &[0x1C])?;
+        self.cmd_with_data(spi, Command::PowerSaving, &[0x22])?;
+        self.cmd_with_data(spi, Command::UndocumentedB4, &[0xD0])?;
+        self.cmd_with_data(spi, Command::UndocumentedB5, &[0x03])?;
+        self.cmd_with_data(spi, Command::UndocumentedE9, &[0x01])?;
+        self.cmd_with_data(spi, Command::PllControl, &[0x08])?;
+
+        self.power_on(spi, delay)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd4in37g<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    type DisplayColor = QuadColor;
+
+    fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new_color(busy, dc, rst, pwr, delay_us);
+        let color = DEFAULT_BACKGROUND_COLOR;
+
+        let mut epd = Epd4in37g { interface, color };
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.cmd_with_data(spi, Command::PowerOff, &[0x00])?;
+        self.wait_until_idle(spi, delay)?;
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
+        self.interface.power_off()
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.init(spi, delay)
+    }
+
+    fn set_background_color(&mut self, color: QuadColor) {
+        self.color = color;
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
+    fn background_color(&self) -> &QuadColor {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer.len(), NUM_DISPLAY_BYTES as usize)?;
+        self.wait_until_idle(spi, delay)?;
+        self.cmd_with_data(spi, Command::DataStartTransmission, buffer)
+    }
+
+    fn update_partial_frame(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+        _buffer: &[u8],
+        _x: u32,
+        _y: u32,
+        _width: u32,
+        _height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::Unsupported)
+    }
+
+    /// Starts the refresh and polls the busy pin until it is finished
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.cmd_with_data(spi, Command::DisplayRefresh, &[0x00])?;
+        self.wait_until_idle(spi, delay)
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.command(spi, Command::DataStartTransmission)?;
+        // 0x00 would be all black, every pixel has to be set to the background color
+        self.interface
+            .data_x_times(spi, self.color.get_byte_value(), NUM_DISPLAY_BYTES)
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd4in37g<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    fn power_on(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.command(spi, Command::PowerOn)?;
+        self.wait_until_idle(spi, delay)
+    }
+
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        self.cmd_with_data(
+            spi,
+            Command::TconResolution,
+            &[
+                (WIDTH >> 8) as u8,
+                WIDTH as u8,
+                (HEIGHT >> 8) as u8,
+                HEIGHT as u8,
+            ],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+    use crate::NoPin;
+    use embedded_hal_mock::eh1::{
+        delay::NoopDelay,
+        digital::{Mock as PinMock, State, Transaction as PinTransaction},
+        spi::{Mock as SpiMock, Transaction as SpiTransaction},
+    };
+    use std::{vec, vec::Vec};
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 512);
+        assert_eq!(HEIGHT, 368);
+        assert_eq!(NUM_DISPLAY_BYTES, 47_104);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, QuadColor::White);
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn white_background() {
+        use embedded_graphics::prelude::*;
+
+        let mut display = Display4in37g::default();
+        assert_eq!(display.buffer().len(), NUM_DISPLAY_BYTES as usize);
+        display.clear(DEFAULT_BACKGROUND_COLOR).unwrap();
+        assert!(display.buffer().iter().all(|&byte| byte == 0b01010101));
+    }
+
+    #[test]
+    fn refresh_polls_busy() {
+        let mut spi_expectations = Vec::new();
+        for byte in [0x12, 0x00] {
+            spi_expectations.push(SpiTransaction::transaction_start());
+            spi_expectations.push(SpiTransaction::write_vec(vec![byte]));
+            spi_expectations.push(SpiTransaction::transaction_end());
+        }
+        let mut spi = SpiMock::new(&spi_expectations);
+        // however long the refresh takes, it is only done once busy is released
+        let mut busy_expectations = vec![PinTransaction::get(State::Low); 50];
+        busy_expectations.push(PinTransaction::get(State::High));
+        let mut busy = PinMock::new(&busy_expectations);
+        let mut dc = PinMock::new(&[
+            PinTransaction::set(State::Low),
+            PinTransaction::set(State::High),
+        ]);
+        let mut rst = PinMock::new(&[]);
+
+        let mut epd = Epd4in37g {
+            interface: DisplayInterface::new(
+                busy.clone(),
+                dc.clone(),
+                Some(rst.clone()),
+                None::<NoPin>,
+                None,
+            ),
+            color: DEFAULT_BACKGROUND_COLOR,
+        };
+        epd.display_frame(&mut spi, &mut NoopDelay::new()).unwrap();
+        drop(epd);
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
+}
@@ -5,7 +5,16 @@
 //! - [Datasheet](https://www.waveshare.com/wiki/5.65inch_e-Paper_Module_(F))
 //! - [Waveshare C driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi%26JetsonNano/c/lib/e-Paper/EPD_5in65f.c)
 //! - [Waveshare Python driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi%26JetsonNano/python/lib/waveshare_epd/epd5in65f.py)
+//!
+//! # Banded updates
+//!
+//! A full [`Display5in65f`] needs 134 kB of RAM. Instead the frame can be streamed with
+//! [`Epd5in65f::update_frame_iter`], or rendered a few rows at a time into a small strip
+//! (e.g. a `VarDisplay<OctColor>` of `WIDTH` x `n` pixels) that is written with
+//! [`Epd5in65f::update_band`]. Each row takes [`ROW_BYTES`] bytes, two pixels per byte.
+//! The bands have to be written top to bottom before calling `display_frame`.
 
+use crate::error::Error;
 use embedded_hal::{
     delay::DelayNs,
     digital::{InputPin, OutputPin},
@@ -14,11 +23,11 @@ use embedded_hal::{
 
 use crate::color::OctColor;
 use crate::interface::DisplayInterface;
-use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::traits::{InternalWiAdditions, WaveshareDisplay};
 
 pub(crate) mod command;
 use self::command::Command;
-use crate::buffer_len;
+use crate::{buffer_len, check_buffer_len};
 
 /// Full size buffer for use with the 5in65f EPD
 #[cfg(feature = "graphics")]
@@ -36,30 +45,38 @@ pub const WIDTH: u32 = 600;
 pub const HEIGHT: u32 = 448;
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: OctColor = OctColor::White;
+/// Bytes of one row of pixels, with 4 bits per pixel
+pub const ROW_BYTES: usize = WIDTH as usize / 2;
 /// Default mode of writing data (single byte vs blockwise)
 const SINGLE_BYTE_WRITE: bool = true;
 
 /// Epd5in65f driver
 ///
-pub struct Epd5in65f<SPI, BUSY, DC, RST, DELAY> {
+pub struct Epd5in65f<SPI, BUSY, DC, RST, DELAY, PWR> {
     /// Connection Interface
-    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
     /// Background Color
     color: OctColor,
+    /// Row the next band has to start at, `None` if no banded write is open
+    next_band_row: Option<u32>,
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
-    for Epd5in65f<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd5in65f<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         // Reset the device
-        self.interface.reset(delay, 10_000, 2_000);
+        self.interface.reset(delay, 10_000, 2_000)?;
+        self.interface
+            .uc_software_reset(spi, delay, Command::PanelSetting, 0xEF, true)?;
+        self.next_band_row = None;
 
         self.cmd_with_data(spi, Command::PanelSetting, &[0xEF, 0x08])?;
         self.cmd_with_data(spi, Command::PowerSetting, &[0x37, 0x00, 0x23, 0x23])?;
@@ -80,13 +97,14 @@ where
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
-    for Epd5in65f<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd5in65f<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     type DisplayColor = OctColor;
@@ -94,27 +112,33 @@ where
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new_color(busy, dc, rst, pwr, delay_us);
         let color = DEFAULT_BACKGROUND_COLOR;
 
-        let mut epd = Epd5in65f { interface, color };
+        let mut epd = Epd5in65f {
+            interface,
+            color,
+            next_band_row: None,
+        };
 
         epd.init(spi, delay)?;
 
         Ok(epd)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.next_band_row = None;
         self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
-        Ok(())
+        self.interface.power_off()
     }
 
     fn update_frame(
@@ -122,11 +146,9 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle(spi, delay)?;
-        self.update_vcom(spi)?;
-        self.send_resolution(spi)?;
-        self.cmd_with_data(spi, Command::DataStartTransmission1, buffer)?;
+    ) -> Result<(), Error<SPI::Error>> {
+        self.start_frame(spi, delay)?;
+        self.send_data(spi, buffer)?;
         Ok(())
     }
 
@@ -139,18 +161,19 @@ where
         _y: u32,
         _width: u32,
         _height: u32,
-    ) -> Result<(), SPI::Error> {
-        unimplemented!();
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::Unsupported)
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.next_band_row = None;
         self.wait_until_idle(spi, delay)?;
         self.command(spi, Command::PowerOn)?;
         self.wait_until_idle(spi, delay)?;
         self.command(spi, Command::DisplayRefresh)?;
         self.wait_until_idle(spi, delay)?;
         self.command(spi, Command::PowerOff)?;
-        self.wait_busy_low(delay);
+        self.wait_busy_low(delay)?;
         Ok(())
     }
 
@@ -159,18 +182,15 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         let bg = OctColor::colors_byte(self.color, self.color);
-        self.wait_until_idle(spi, delay)?;
-        self.update_vcom(spi)?;
-        self.send_resolution(spi)?;
-        self.command(spi, Command::DataStartTransmission1)?;
+        self.start_frame(spi, delay)?;
         self.interface.data_x_times(spi, bg, WIDTH * HEIGHT / 2)?;
         self.display_frame(spi, delay)?;
         Ok(())
@@ -180,6 +200,18 @@ where
         self.color = color;
     }
 
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(true)
+    }
+
     fn background_color(&self) -> &OctColor {
         &self.color
     }
@@ -192,34 +224,86 @@ where
         HEIGHT
     }
 
-    fn set_lut(
+    fn wait_until_idle(
         &mut self,
         _spi: &mut SPI,
-        _delay: &mut DELAY,
-        _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
-        unimplemented!();
-    }
-
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.wait_until_idle(delay, true);
-        Ok(())
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, true)
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> Epd5in65f<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd5in65f<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    /// Writes a whole frame from an iterator of packed `OctColor` bytes, two pixels per byte
+    ///
+    /// Takes [`ROW_BYTES`] * `HEIGHT` bytes; fails with `Error::InvalidBufferLength` if the iterator
+    /// ends early.
+    pub fn update_frame_iter<I: IntoIterator<Item = u8>>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        data: I,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.start_frame(spi, delay)?;
+        self.interface
+            .data_iter(spi, data, ROW_BYTES * HEIGHT as usize)
+    }
+
+    /// Writes a band of whole rows starting at row `y`
+    ///
+    /// `buffer` holds [`ROW_BYTES`] bytes per row. A band at `y == 0` starts a new frame, every
+    /// other band has to continue right below the previous one, so the frame memory write stays
+    /// open across bands. Call `display_frame` once all rows are written.
+    ///
+    /// Fails with [`Error::InvalidBufferLength`] if the band doesn't hold whole rows and with
+    /// [`Error::OutOfBounds`] if it is out of order or runs past the bottom.
+    pub fn update_band(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        y: u32,
+        buffer: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        let rows = (buffer.len() / ROW_BYTES).max(1) as u32;
+        check_buffer_len(buffer.len(), rows as usize * ROW_BYTES)?;
+        let fits = y.checked_add(rows).map_or(false, |end| end <= HEIGHT);
+        if !fits || (y != 0 && self.next_band_row != Some(y)) {
+            return Err(Error::OutOfBounds {
+                x: 0,
+                y,
+                width: WIDTH,
+                height: rows,
+            });
+        }
+        if y == 0 {
+            self.start_frame(spi, delay)?;
+        }
+        self.send_data(spi, buffer)?;
+        self.next_band_row = Some(y + rows);
+        Ok(())
+    }
+
+    fn start_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.next_band_row = None;
+        self.wait_until_idle(spi, delay)?;
+        self.update_vcom(spi)?;
+        self.send_resolution(spi)?;
+        self.command(spi, Command::DataStartTransmission1)
+    }
+
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
         self.interface.data(spi, data)
     }
 
@@ -228,14 +312,14 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
-    fn wait_busy_low(&mut self, delay: &mut DELAY) {
-        self.interface.wait_until_idle(delay, false);
+    fn wait_busy_low(&mut self, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, false)
     }
-    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
         let w = self.width();
         let h = self.height();
 
@@ -246,7 +330,7 @@ where
         self.send_data(spi, &[h as u8])
     }
 
-    fn update_vcom(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn update_vcom(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
         let bg_color = (self.color.get_nibble() & 0b111) << 5;
         self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x17 | bg_color])?;
         Ok(())
@@ -255,12 +339,115 @@ where
 
 #[cfg(test)]
 mod tests {
+    extern crate std;
     use super::*;
+    use crate::NoPin;
+    use embedded_hal_mock::eh1::{
+        delay::NoopDelay,
+        digital::{Mock as PinMock, State, Transaction as PinTransaction},
+        spi::{Mock as SpiMock, Transaction as SpiTransaction},
+    };
+    use std::{vec, vec::Vec};
+
+    /// Writes of the start of a frame: vcom, resolution and `DataStartTransmission1`
+    const START_FRAME: [(State, &[u8]); 8] = [
+        (State::Low, &[0x50]),
+        (State::High, &[0x37]),
+        (State::Low, &[0x61]),
+        (State::High, &[0x02]),
+        (State::High, &[0x58]),
+        (State::High, &[0x01]),
+        (State::High, &[0xC0]),
+        (State::Low, &[0x10]),
+    ];
+
+    /// Runs `f` on a driver which expects `writes`, each of them with its dc level
+    fn with_epd(
+        writes: &[(State, &[u8])],
+        busy_reads: usize,
+        f: impl FnOnce(
+            &mut Epd5in65f<SpiMock<u8>, PinMock, PinMock, PinMock, NoopDelay, NoPin>,
+            &mut SpiMock<u8>,
+        ),
+    ) {
+        let mut spi_expectations = Vec::new();
+        let mut dc_expectations = Vec::new();
+        for (level, data) in writes {
+            dc_expectations.push(PinTransaction::set(*level));
+            for byte in data.iter() {
+                spi_expectations.push(SpiTransaction::transaction_start());
+                spi_expectations.push(SpiTransaction::write_vec(vec![*byte]));
+                spi_expectations.push(SpiTransaction::transaction_end());
+            }
+        }
+        let mut spi = SpiMock::new(&spi_expectations);
+        let mut busy = PinMock::new(&vec![PinTransaction::get(State::High); busy_reads]);
+        let mut dc = PinMock::new(&dc_expectations);
+        let mut rst = PinMock::new(&[]);
+
+        let mut epd = Epd5in65f {
+            interface: DisplayInterface::new(
+                busy.clone(),
+                dc.clone(),
+                Some(rst.clone()),
+                None::<NoPin>,
+                None,
+            ),
+            color: DEFAULT_BACKGROUND_COLOR,
+            next_band_row: None,
+        };
+        f(&mut epd, &mut spi);
+        drop(epd);
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
 
     #[test]
     fn epd_size() {
         assert_eq!(WIDTH, 600);
         assert_eq!(HEIGHT, 448);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, OctColor::White);
+        assert_eq!(ROW_BYTES, 300);
+    }
+
+    #[test]
+    fn bands_continue_one_frame_write() {
+        let first = [0x11; 2 * ROW_BYTES];
+        let second = [0x22; ROW_BYTES];
+        let mut writes = START_FRAME.to_vec();
+        writes.push((State::High, &first));
+        writes.push((State::High, &second));
+        with_epd(&writes, 1, |epd, spi| {
+            let mut delay = NoopDelay::new();
+            epd.update_band(spi, &mut delay, 0, &first).unwrap();
+            epd.update_band(spi, &mut delay, 2, &second).unwrap();
+            assert_eq!(epd.next_band_row, Some(3));
+        });
+    }
+
+    #[test]
+    fn band_skipping_rows() {
+        let band = [0x11; ROW_BYTES];
+        let mut writes = START_FRAME.to_vec();
+        writes.push((State::High, &band));
+        with_epd(&writes, 1, |epd, spi| {
+            let mut delay = NoopDelay::new();
+            epd.update_band(spi, &mut delay, 0, &band).unwrap();
+            assert!(matches!(
+                epd.update_band(spi, &mut delay, 2, &band),
+                Err(Error::OutOfBounds { y: 2, .. })
+            ));
+            assert!(matches!(
+                epd.update_band(spi, &mut delay, 1, &band[1..]),
+                Err(Error::InvalidBufferLength { .. })
+            ));
+            assert!(matches!(
+                epd.update_band(spi, &mut delay, HEIGHT, &band),
+                Err(Error::OutOfBounds { .. })
+            ));
+        });
     }
 }
@@ -0,0 +1,77 @@
+//! SPI Commands for the Waveshare 5.79" E-Ink Display
+
+use crate::traits;
+
+/// Epd5in79 commands
+///
+/// Should rarely (never?) be needed directly.
+///
+/// The panel is driven by two SSD1683 controllers. Commands for the
+/// secondary (right) controller are the ones of the primary (left) controller with the
+/// highest bit set.
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub(crate) enum Command {
+    /// Driver Output control
+    DriverOutputControl = 0x01,
+    /// Deep Sleep Mode Control
+    DeepSleepMode = 0x10,
+    /// Data Entry mode setting of the primary controller
+    DataEntryModeSetting = 0x11,
+    /// SW RESET of both controllers
+    SwReset = 0x12,
+    /// Activate Display Update Sequence
+    MasterActivation = 0x20,
+    /// Display Update Control 2
+    DisplayUpdateControl2 = 0x22,
+    /// Write RAM of the primary controller
+    WriteRam = 0x24,
+    /// Border Waveform Control
+    BorderWaveformControl = 0x3C,
+    /// Specify the start/end positions of the window address in the X direction of the primary
+    /// controller, in bytes
+    SetRamXAddressStartEndPosition = 0x44,
+    /// Specify the start/end positions of the window address in the Y direction of the primary
+    /// controller
+    SetRamYAddressStartEndPosition = 0x45,
+    /// RAM x address counter of the primary controller
+    SetRamXAddressCounter = 0x4E,
+    /// RAM y address counter of the primary controller
+    SetRamYAddressCounter = 0x4F,
+
+    /// Data Entry mode setting of the secondary controller
+    SecondaryDataEntryModeSetting = 0x91,
+    /// Write RAM of the secondary controller
+    SecondaryWriteRam = 0xA4,
+    /// X window of the secondary controller
+    SecondarySetRamXAddressStartEndPosition = 0xC4,
+    /// Y window of the secondary controller
+    SecondarySetRamYAddressStartEndPosition = 0xC5,
+    /// RAM x address counter of the secondary controller
+    SecondarySetRamXAddressCounter = 0xCE,
+    /// RAM y address counter of the secondary controller
+    SecondarySetRamYAddressCounter = 0xCF,
+}
+
+impl traits::Command for Command {
+    /// Returns the address of the command
+    fn address(self) -> u8 {
+        self as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Command as CommandTrait;
+
+    #[test]
+    fn command_addr() {
+        assert_eq!(Command::WriteRam.address(), 0x24);
+        assert_eq!(Command::SecondaryWriteRam.address(), 0xA4);
+        assert_eq!(
+            Command::SecondarySetRamXAddressCounter.address(),
+            Command::SetRamXAddressCounter.address() | 0x80
+        );
+    }
+}
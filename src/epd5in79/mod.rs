@@ -0,0 +1,353 @@
+//! A simple Driver for the Waveshare 5.79" B/W E-Ink Display via SPI
+//!
+//! The 792x272 panel is driven by two SSD1683 controllers on the same SPI bus, each owning
+//! 396 columns. Both get a 400 pixel (50 byte) wide RAM window, so the byte holding the
+//! columns 392..400 is sent to both of them and each controller ignores the 4 columns
+//! belonging to the other one.
+//!
+//! The secondary controller is mounted mirrored. Its x address counter runs backwards from the
+//! seam, which mirrors the right half without reordering the bytes of the buffer.
+//!
+//! # References
+//!
+//! - [Waveshare Wiki](https://www.waveshare.com/wiki/5.79inch_e-Paper_Module)
+//! - [Waveshare C driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_5in79.c)
+
+use crate::error::Error;
+use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
+
+use crate::color::Color;
+use crate::interface::DisplayInterface;
+use crate::traits::{InternalWiAdditions, WaveshareDisplay};
+use crate::{buffer_len, check_buffer_len};
+
+pub(crate) mod command;
+use self::command::Command;
+
+/// Width of the display
+pub const WIDTH: u32 = 792;
+/// Height of the display
+pub const HEIGHT: u32 = 272;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+const IS_BUSY_LOW: bool = false;
+const SINGLE_BYTE_WRITE: bool = false;
+
+/// Bytes of a line of the full buffer
+const LINE_BYTES: usize = WIDTH as usize / 8;
+/// Bytes of a line in the RAM of each controller
+const HALF_LINE_BYTES: usize = 50;
+
+/// Full size buffer for use with the 5in79 EPD
+#[cfg(feature = "graphics")]
+pub type Display5in79 = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize, HEIGHT as usize) },
+    Color,
+>;
+
+/// Epd5in79 driver
+pub struct Epd5in79<SPI, BUSY, DC, RST, DELAY, PWR> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    background_color: Color,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd5in79<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, 10_000, 2_000)?;
+
+        self.wait_until_idle(spi, delay)?;
+        self.interface.cmd(spi, Command::SwReset)?;
+        self.wait_until_idle(spi, delay)?;
+
+        let last_x = HALF_LINE_BYTES as u8 - 1;
+        let [last_y_low, last_y_high] = [(HEIGHT - 1) as u8, ((HEIGHT - 1) >> 8) as u8];
+
+        // primary controller: x increment, y decrement, starting top left
+        self.interface
+            .cmd_with_data(spi, Command::DataEntryModeSetting, &[0x01])?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamXAddressStartEndPosition,
+            &[0x00, last_x],
+        )?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamYAddressStartEndPosition,
+            &[last_y_low, last_y_high, 0x00, 0x00],
+        )?;
+
+        // secondary controller: x decrement, y decrement, starting at the seam
+        self.interface
+            .cmd_with_data(spi, Command::SecondaryDataEntryModeSetting, &[0x00])?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::SecondarySetRamXAddressStartEndPosition,
+            &[last_x, 0x00],
+        )?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::SecondarySetRamYAddressStartEndPosition,
+            &[last_y_low, last_y_high, 0x00, 0x00],
+        )?;
+
+        self.set_ram_counters(spi)?;
+        self.wait_until_idle(spi, delay)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd5in79<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    type DisplayColor = Color;
+
+    fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(busy, dc, rst, pwr, delay_us);
+
+        let mut epd = Epd5in79 {
+            interface,
+            background_color: DEFAULT_BACKGROUND_COLOR,
+        };
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        // deep sleep mode 2, the RAM content is not retained
+        self.interface
+            .cmd_with_data(spi, Command::DeepSleepMode, &[0x03])?;
+        self.interface.power_off()
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.init(spi, delay)
+    }
+
+    fn set_background_color(&mut self, background_color: Color) {
+        self.background_color = background_color;
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.background_color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    /// Splits the buffer into the halves of both controllers and writes them to their RAM
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer.len(), buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.wait_until_idle(spi, delay)?;
+        self.set_ram_counters(spi)?;
+
+        self.interface.cmd(spi, Command::WriteRam)?;
+        for line in buffer.chunks(LINE_BYTES) {
+            self.interface.data(spi, left_half(line))?;
+        }
+
+        self.interface.cmd(spi, Command::SecondaryWriteRam)?;
+        for line in buffer.chunks(LINE_BYTES) {
+            self.interface.data(spi, right_half(line))?;
+        }
+        Ok(())
+    }
+
+    fn update_partial_frame(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+        _buffer: &[u8],
+        _x: u32,
+        _y: u32,
+        _width: u32,
+        _height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::Unsupported)
+    }
+
+    /// Refreshes both halves at once
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xF7])?;
+        self.interface.cmd(spi, Command::MasterActivation)?;
+        self.wait_until_idle(spi, delay)
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.set_ram_counters(spi)?;
+
+        let color = self.background_color.get_byte_value();
+        let half_bytes = HALF_LINE_BYTES as u32 * HEIGHT;
+
+        self.interface.cmd(spi, Command::WriteRam)?;
+        self.interface.data_x_times(spi, color, half_bytes)?;
+
+        self.interface.cmd(spi, Command::SecondaryWriteRam)?;
+        self.interface.data_x_times(spi, color, half_bytes)
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd5in79<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Moves the counters of both controllers to their first byte
+    fn set_ram_counters(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        let last_y = [(HEIGHT - 1) as u8, ((HEIGHT - 1) >> 8) as u8];
+
+        self.interface
+            .cmd_with_data(spi, Command::SetRamXAddressCounter, &[0x00])?;
+        self.interface
+            .cmd_with_data(spi, Command::SetRamYAddressCounter, &last_y)?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::SecondarySetRamXAddressCounter,
+            &[HALF_LINE_BYTES as u8 - 1],
+        )?;
+        self.interface
+            .cmd_with_data(spi, Command::SecondarySetRamYAddressCounter, &last_y)
+    }
+}
+
+/// Bytes of a line for the primary controller, columns 0..400
+fn left_half(line: &[u8]) -> &[u8] {
+    &line[..HALF_LINE_BYTES]
+}
+
+/// Bytes of a line for the secondary controller, columns 392..792
+fn right_half(line: &[u8]) -> &[u8] {
+    &line[LINE_BYTES - HALF_LINE_BYTES..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 792);
+        assert_eq!(HEIGHT, 272);
+        assert_eq!(LINE_BYTES, 99);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+
+    #[test]
+    fn split_line() {
+        let mut line = [0u8; LINE_BYTES];
+        for (i, byte) in line.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+
+        let left = left_half(&line);
+        let right = right_half(&line);
+        assert_eq!(left.len(), HALF_LINE_BYTES);
+        assert_eq!(right.len(), HALF_LINE_BYTES);
+
+        assert_eq!(left[0], 0);
+        assert_eq!(left[HALF_LINE_BYTES - 1], 49);
+        // the byte of the seam goes to both controllers
+        assert_eq!(right[0], 49);
+        assert_eq!(right[HALF_LINE_BYTES - 1], 98);
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn split_seam_pixels() {
+        use embedded_graphics::prelude::*;
+
+        let mut display = Display5in79::default();
+        // the last column of the primary and the first one of the secondary controller
+        Pixel(Point::new(395, 0), Color::White)
+            .draw(&mut display)
+            .unwrap();
+        Pixel(Point::new(396, 0), Color::White)
+            .draw(&mut display)
+            .unwrap();
+
+        let line = &display.buffer()[..LINE_BYTES];
+        assert_eq!(left_half(line)[HALF_LINE_BYTES - 1], 0b0001_1000);
+        assert_eq!(right_half(line)[0], 0b0001_1000);
+        assert!(left_half(line)[..HALF_LINE_BYTES - 1]
+            .iter()
+            .chain(&right_half(line)[1..])
+            .all(|&byte| byte == 0));
+    }
+}
@@ -5,7 +5,12 @@
 //! - [Datasheet](https://www.waveshare.com/wiki/7.3inch_e-Paper_HAT_(F))
 //! - [Waveshare C driver](https://github.com/waveshareteam/e-Paper/blob/8be47b27f1a6808fd82ea9ceeac04c172e4ee9a8/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_7in3f.c)
 //! - [Waveshare Python driver](https://github.com/waveshareteam/e-Paper/blob/8be47b27f1a6808fd82ea9ceeac04c172e4ee9a8/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd7in3f.py)
+//!
+//! A full refresh takes about 35 seconds, during which [`WaveshareDisplay::display_frame`]
+//! keeps waiting on the busy pin. Use [`Epd7in3f::show_7block`] to check a new panel
+//! without drawing anything.
 
+use crate::error::Error;
 use embedded_hal::{
     delay::DelayNs,
     digital::{InputPin, OutputPin},
@@ -13,7 +18,7 @@ use embedded_hal::{
 };
 
 use crate::{
-    buffer_len,
+    buffer_len, check_buffer_len,
     color::OctColor,
     interface::DisplayInterface,
     traits::{InternalWiAdditions, WaveshareDisplay},
@@ -43,25 +48,28 @@ pub const DEFAULT_BACKGROUND_COLOR: OctColor = OctColor::White;
 const SINGLE_BYTE_WRITE: bool = true;
 
 /// Epd57n3f driver
-pub struct Epd7in3f<SPI, BUSY, DC, RST, DELAY> {
+pub struct Epd7in3f<SPI, BUSY, DC, RST, DELAY, PWR> {
     /// Connection Interface
-    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
     /// Background Color
     color: OctColor,
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
-    for Epd7in3f<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd7in3f<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), <SPI>::Error> {
-        self.interface.reset(delay, 20_000, 2_000);
-        self.wait_busy_low(delay);
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, 20_000, 2_000)?;
+        self.interface
+            .uc_software_reset(spi, delay, Command::Ox00, 0x5F, true)?;
+        self.wait_busy_low(delay)?;
         delay.delay_ms(30);
 
         self.cmd_with_data(spi, Command::CMDH, &[0x49, 0x55, 0x20, 0x08, 0x09, 0x18])?;
@@ -86,13 +94,14 @@ where
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
-    for Epd7in3f<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd7in3f<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     type DisplayColor = OctColor;
@@ -101,14 +110,15 @@ where
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, <SPI>::Error>
+    ) -> Result<Self, Error<SPI::Error>>
     where
         Self: Sized,
     {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        let interface = DisplayInterface::new_color(busy, dc, rst, pwr, delay_us);
         let color = DEFAULT_BACKGROUND_COLOR;
 
         let mut epd = Epd7in3f { interface, color };
@@ -118,11 +128,12 @@ where
         Ok(epd)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), <SPI>::Error> {
-        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])
+    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
+        self.interface.power_off()
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), <SPI>::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.init(spi, delay)
     }
 
@@ -130,6 +141,18 @@ where
         self.color = color;
     }
 
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(true)
+    }
+
     fn background_color(&self) -> &Self::DisplayColor {
         &self.color
     }
@@ -147,7 +170,11 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), <SPI>::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(
+            buffer.len(),
+            buffer_len(WIDTH as usize, HEIGHT as usize * 4),
+        )?;
         self.wait_until_idle(spi, delay)?;
         self.cmd_with_data(spi, Command::DataStartTransmission, buffer)
     }
@@ -161,19 +188,19 @@ where
         _y: u32,
         _width: u32,
         _height: u32,
-    ) -> Result<(), <SPI>::Error> {
-        unimplemented!()
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::Unsupported)
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), <SPI>::Error> {
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.command(spi, Command::PowerOn)?;
-        self.wait_busy_low(delay);
+        self.wait_busy_low(delay)?;
 
         self.cmd_with_data(spi, Command::DataFresh, &[0x00])?;
-        self.wait_busy_low(delay);
+        self.wait_busy_low(delay)?;
 
         self.cmd_with_data(spi, Command::PowerOff, &[0x00])?;
-        self.wait_busy_low(delay);
+        self.wait_busy_low(delay)?;
 
         Ok(())
     }
@@ -183,45 +210,41 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), <SPI>::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), <SPI>::Error> {
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         let bg = OctColor::colors_byte(self.color, self.color);
 
-        self.wait_busy_low(delay);
+        self.wait_busy_low(delay)?;
         self.command(spi, Command::DataStartTransmission)?;
         self.interface.data_x_times(spi, bg, WIDTH * HEIGHT / 2)?;
 
         self.display_frame(spi, delay)
     }
 
-    fn set_lut(
+    fn wait_until_idle(
         &mut self,
         _spi: &mut SPI,
-        _delay: &mut DELAY,
-        _refresh_rate: Option<crate::traits::RefreshLut>,
-    ) -> Result<(), <SPI>::Error> {
-        unimplemented!()
-    }
-
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), <SPI>::Error> {
-        self.wait_busy_low(delay);
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.wait_busy_low(delay)?;
         Ok(())
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> Epd7in3f<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd7in3f<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
@@ -230,16 +253,23 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
-    fn wait_busy_low(&mut self, delay: &mut DELAY) {
-        self.interface.wait_until_idle(delay, true);
+    fn wait_busy_low(&mut self, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, true)
     }
 
     /// Show 7 blocks of color, used for quick testing
-    pub fn show_7block(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    ///
+    /// The upper half of the screen shows black, white, green and blue bars, the lower half
+    /// red, yellow, orange and white ones.
+    pub fn show_7block(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
         let color_7 = [
             OctColor::Black,
             OctColor::White,
@@ -250,22 +280,19 @@ where
             OctColor::Orange,
             OctColor::White,
         ];
+        // two pixels per byte, four bars per line
+        let bar_bytes = WIDTH / 2 / 4;
 
+        self.wait_busy_low(delay)?;
         self.command(spi, Command::DataStartTransmission)?;
-        for _ in 0..240 {
-            for color in color_7.iter().take(4) {
-                for _ in 0..100 {
-                    self.interface
-                        .data(spi, &[OctColor::colors_byte(*color, *color)])?;
-                }
-            }
-        }
-
-        for _ in 0..240 {
-            for color in color_7.iter().skip(4) {
-                for _ in 0..100 {
-                    self.interface
-                        .data(spi, &[OctColor::colors_byte(*color, *color)])?;
+        for half in color_7.chunks(4) {
+            for _ in 0..HEIGHT / 2 {
+                for color in half {
+                    self.interface.data_x_times(
+                        spi,
+                        OctColor::colors_byte(*color, *color),
+                        bar_bytes,
+                    )?;
                 }
             }
         }
@@ -273,3 +300,15 @@ where
         self.display_frame(spi, delay)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 800);
+        assert_eq!(HEIGHT, 480);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, OctColor::White);
+    }
+}
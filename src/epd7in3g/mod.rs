@@ -0,0 +1,374 @@
+//! A simple Driver for the Waveshare 7.3" (G) 4-Color E-Ink Display via SPI
+//!
+//! The panel shows black, white, yellow and red. Each pixel takes 2 bits of the buffer
+//! (see [`QuadColor`]), the whole frame is written at once into a single RAM.
+//!
+//! A full refresh takes about 25 seconds, during which
+//! [`display_frame`](WaveshareDisplay::display_frame) keeps waiting for the busy pin.
+//!
+//! The full frame buffer takes 96 000 bytes. Instead of a [`Display7in3g`] the frame can also be
+//! streamed, e.g. from an SD card, with [`Epd7in3g::update_frame_from_iter`].
+//!
+//! # References
+//!
+//! - [Waveshare Wiki](https://www.waveshare.com/wiki/7.3inch_e-Paper_HAT_(G))
+//! - [Waveshare C driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_7in3g.c)
+//!
+//! # Example for the 7.3" G E-Ink Display
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), epd_waveshare::error::Error<embedded_hal::spi::ErrorKind>> {
+//!use embedded_graphics::{prelude::*, primitives::{Line, PrimitiveStyle}};
+//!use epd_waveshare::{epd7in3g::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = digital::Mock::new(&expectations);
+//!# let dc = digital::Mock::new(&expectations);
+//!# let rst = digital::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
+//!
+//!// Setup EPD
+//!let mut epd = Epd7in3g::new(&mut spi, busy_in, dc, Some(rst), None::<NoPin>, &mut delay, None)?;
+//!
+//!// Use display graphics from embedded-graphics
+//!let mut display = Display7in3g::default();
+//!display.clear(QuadColor::White);
+//!
+//!// A red and a yellow line in the same frame
+//!let _ = Line::new(Point::new(0, 120), Point::new(0, 200))
+//!    .into_styled(PrimitiveStyle::with_stroke(QuadColor::Red, 1))
+//!    .draw(&mut display);
+//!let _ = Line::new(Point::new(15, 120), Point::new(15, 200))
+//!    .into_styled(PrimitiveStyle::with_stroke(QuadColor::Yellow, 1))
+//!    .draw(&mut display);
+//!
+//!// Display updated frame
+//!epd.update_and_display_frame(&mut spi, display.buffer(), &mut delay)?;
+//!
+//!// Set the EPD to sleep
+//!epd.sleep(&mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+
+use crate::error::Error;
+use embedded_hal::{
+    delay::DelayNs,
+    digital::{InputPin, OutputPin},
+    spi::SpiDevice,
+};
+
+use crate::color::QuadColor;
+use crate::interface::DisplayInterface;
+use crate::traits::{InternalWiAdditions, WaveshareDisplay};
+
+use crate::type_g::command::Command;
+use crate::{buffer_len, check_buffer_len};
+
+/// Width of the display
+pub const WIDTH: u32 = 800;
+/// Height of the display
+pub const HEIGHT: u32 = 480;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: QuadColor = QuadColor::White;
+/// Number of bytes of a full frame at 2 bits per pixel
+const NUM_DISPLAY_BYTES: u32 = WIDTH / 4 * HEIGHT;
+const IS_BUSY_LOW: bool = true;
+const SINGLE_BYTE_WRITE: bool = true;
+
+/// Full size buffer for use with the 7in3g EPD
+///
+/// It takes 96 000 bytes of RAM.
+#[cfg(feature = "graphics")]
+pub type Display7in3g = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize * 2, HEIGHT as usize) },
+    QuadColor,
+>;
+
+/// Epd7in3g driver
+pub struct Epd7in3g<SPI, BUSY, DC, RST, DELAY, PWR> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    color: QuadColor,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd7in3g<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, 20_000, 2_000)?;
+        self.interface
+            .uc_software_reset(spi, delay, Command::PanelSetting, 0x4F, IS_BUSY_LOW)?;
+        self.wait_until_idle(spi, delay)?;
+
+        self.cmd_with_data(
+            spi,
+            Command::UndocumentedAA,
+            &[0x49, 0x55, 0x20, 0x08, 0x09, 0x18],
+        )?;
+        self.cmd_with_data(spi, Command::PowerSetting, &[0x3F])?;
+        self.cmd_with_data(spi, Command::PanelSetting, &[0x4F, 0x69])?;
+        self.cmd_with_data(spi, Command::Undocumented05, &[0x40, 0x1F, 0x1F, 0x2C])?;
+        self.cmd_with_data(spi, Command::Undocumented08, &[0x6F, 0x1F, 0x1F, 0x22])?;
+        self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x6F, 0x1F, 0x14, 0x14])?;
+        self.cmd_with_data(
+            spi,
+            Command::PowerOffSequenceSetting,
+            &[0x00, 0x54, 0x00, 0x44],
+        )?;
+        self.cmd_with_data(spi, Command::TconSetting, &[0x02, 0x00])?;
+        self.cmd_with_data(spi, Command::PllControl, &[0x08])?;
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x3F])?;
+        self.send_resolution(spi)?;
+        self.cmd_with_data(spi, Command::PowerSaving, &[0x2F])?;
+        self.cmd_with_data(spi, Command::Undocumented84, &[0x01])?;
+
+        self.power_on(spi, delay)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd7in3g<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    type DisplayColor = QuadColor;
+
+    fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new_color(busy, dc, rst, pwr, delay_us);
+        let color = DEFAULT_BACKGROUND_COLOR;
+
+        let mut epd = Epd7in3g { interface, color };
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.cmd_with_data(spi, Command::PowerOff, &[0x00])?;
+        self.wait_until_idle(spi, delay)?;
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
+        self.interface.power_off()
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.init(spi, delay)
+    }
+
+    fn set_background_color(&mut self, color: QuadColor) {
+        self.color = color;
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
+    fn background_color(&self) -> &QuadColor {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer.len(), NUM_DISPLAY_BYTES as usize)?;
+        self.wait_until_idle(spi, delay)?;
+        self.cmd_with_data(spi, Command::DataStartTransmission, buffer)
+    }
+
+    fn update_partial_frame(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+        _buffer: &[u8],
+        _x: u32,
+        _y: u32,
+        _width: u32,
+        _height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::Unsupported)
+    }
+
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.cmd_with_data(spi, Command::DisplayRefresh, &[0x00])?;
+        self.wait_until_idle(spi, delay)
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.command(spi, Command::DataStartTransmission)?;
+        // 0x00 would be all black, every pixel has to be set to the background color
+        self.interface
+            .data_x_times(spi, self.color.get_byte_value(), NUM_DISPLAY_BYTES)
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd7in3g<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    /// Writes the frame from an iterator of packed bytes (4 pixels per byte, see
+    /// [`QuadColor::get_bits`]), without the need for a full frame buffer
+    ///
+    /// The iterator has to yield exactly `WIDTH / 4 * HEIGHT` bytes, row by row, otherwise this
+    /// fails with [`Error::InvalidBufferLength`]. Finish by calling
+    /// [`display_frame`](WaveshareDisplay::display_frame).
+    pub fn update_frame_from_iter<I>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        bytes: I,
+    ) -> Result<(), Error<SPI::Error>>
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        self.wait_until_idle(spi, delay)?;
+        self.command(spi, Command::DataStartTransmission)?;
+
+        let mut chunk = [0u8; 64];
+        let mut len = 0;
+        let mut count = 0;
+        let expected = NUM_DISPLAY_BYTES as usize;
+        for byte in bytes {
+            chunk[len] = byte;
+            len += 1;
+            count += 1;
+            if count > expected {
+                return Err(Error::InvalidBufferLength {
+                    expected,
+                    got: count,
+                });
+            }
+            if len == chunk.len() {
+                self.interface.data(spi, &chunk)?;
+                len = 0;
+            }
+        }
+        self.interface.data(spi, &chunk[..len])?;
+        check_buffer_len(count, expected)
+    }
+
+    fn power_on(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.command(spi, Command::PowerOn)?;
+        self.wait_until_idle(spi, delay)
+    }
+
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        self.cmd_with_data(
+            spi,
+            Command::TconResolution,
+            &[
+                (WIDTH >> 8) as u8,
+                WIDTH as u8,
+                (HEIGHT >> 8) as u8,
+                HEIGHT as u8,
+            ],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 800);
+        assert_eq!(HEIGHT, 480);
+        assert_eq!(NUM_DISPLAY_BYTES, 96_000);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, QuadColor::White);
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn white_background() {
+        use embedded_graphics::prelude::*;
+
+        let mut display = Display7in3g::default();
+        assert_eq!(display.buffer().len(), NUM_DISPLAY_BYTES as usize);
+        display.clear(DEFAULT_BACKGROUND_COLOR).unwrap();
+        assert!(display.buffer().iter().all(|&byte| byte == 0b01010101));
+    }
+}
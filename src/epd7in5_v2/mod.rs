@@ -9,20 +9,65 @@
 //! Important note for V2:
 //! Revision V2 has been released on 2019.11, the resolution is upgraded to 800×480, from 640×384 of V1.
 //! The hardware and interface of V2 are compatible with V1, however, the related software should be updated.
+//!
+//! # Fast full refresh
+//!
+//! [`set_lut`](WaveshareDisplay::set_lut)`(.., Some(RefreshLut::Quick))` selects the fast
+//! waveform of the vendor's `Init_Fast`: a stronger booster (0x06) and a forced waveform
+//! temperature (0xE0/0xE5), which brings a full refresh down to about 2 s with a bit less
+//! contrast. `Some(RefreshLut::Full)` switches back to the normal quality waveform, which is the
+//! default. The mode can be switched at any time and is kept over
+//! [`wake_up`](WaveshareDisplay::wake_up), e.g. for fast updates during an interaction and one
+//! quality refresh afterwards.
+//!
+//! [`set_temperature`](WaveshareDisplay::set_temperature) forces the temperature of the quality
+//! waveform the same way, with a real temperature instead of the index of a custom waveform.
+//!
+//! # Quick refresh
+//!
+//! A full refresh takes about 4 s. [`QuickRefresh`] writes the old frame (0x10) and the new frame
+//! (0x13), optionally restricted to a partial window (0x90/0x91), so that only the changed pixels
+//! are driven. Select the partial waveform with
+//! [`set_lut`](WaveshareDisplay::set_lut)`(.., Some(RefreshLut::PartialRefresh))` first.
+//!
+//! The partial waveform doesn't clear the ghosting of the previous content, it builds up with
+//! every update. Do a full refresh periodically, e.g. every few minutes for a clock, to keep the
+//! image clean.
+//!
+//! # Color polarity
+//!
+//! The panel shows a set bit as black, so `Color::Black` draws white pixels on a
+//! [`Display7in5`] and [`DEFAULT_BACKGROUND_COLOR`] is `Color::Black`. With the `color_polarity`
+//! feature the display stores its pixels inverted, `Color::Black` draws black, the default
+//! background is `Color::White` and the background color of the driver is inverted as well.
+//! It's a breaking change for drawings which swapped the colors, so it's opt-in until the next
+//! minor version. [`draw_in_bands`](crate::graphics::draw_in_bands) and the raw buffer functions
+//! don't invert.
+//!
+//! # Streaming a frame
+//!
+//! The full [`Display7in5`] buffer takes 48 kB. The frame can also be written in chunks of any
+//! size: [`Epd7in5::start_frame`], then [`Epd7in5::write_frame_chunk`] for the rows from top to
+//! bottom, and [`Epd7in5::finish_frame_and_display`]. With the `graphics` feature
+//! [`draw_in_bands`](crate::graphics::draw_in_bands) renders a drawable into a small strip buffer
+//! and hands over one band after the other, e.g. 2 kB for 20 rows.
 
+use crate::error::Error;
 use embedded_hal::{
     delay::DelayNs,
     digital::{InputPin, OutputPin},
     spi::SpiDevice,
 };
 
-use crate::color::Color;
+use crate::color::{Color, DeghostColors};
 use crate::interface::DisplayInterface;
-use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::traits::{
+    check_refresh_lut, InternalWiAdditions, QuickRefresh, RefreshLut, WaveshareDisplay,
+};
 
 pub(crate) mod command;
 use self::command::Command;
-use crate::buffer_len;
+use crate::{buffer_len, check_buffer_len};
 
 /// Full size buffer for use with the 7in5 v2 EPD
 #[cfg(feature = "graphics")]
@@ -32,39 +77,57 @@ pub type Display7in5 = crate::graphics::Display<
     false,
     { buffer_len(WIDTH as usize, HEIGHT as usize) },
     Color,
+    [u8; buffer_len(WIDTH as usize, HEIGHT as usize)],
+    INVERTED,
 >;
 
 /// Width of the display
 pub const WIDTH: u32 = 800;
 /// Height of the display
 pub const HEIGHT: u32 = 480;
-/// Default Background Color
+/// Default Background Color, white on the panel
+#[cfg(not(feature = "color_polarity"))]
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::Black;
+/// Default Background Color
+#[cfg(feature = "color_polarity")]
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+/// `Color` is flipped on its way to the panel, see the module docs
+const INVERTED: bool = cfg!(feature = "color_polarity");
 const IS_BUSY_LOW: bool = true;
 const SINGLE_BYTE_WRITE: bool = false;
+/// Bytes of a full frame
+const FRAME_LEN: u32 = WIDTH / 8 * HEIGHT;
 
 /// Epd7in5 (V2) driver
-pub struct Epd7in5<SPI, BUSY, DC, RST, DELAY> {
+pub struct Epd7in5<SPI, BUSY, DC, RST, DELAY, PWR> {
     /// Connection Interface
-    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
     /// Background Color
     color: Color,
     /// LUT refresh mode
     refresh: RefreshLut,
+    /// Temperature of [`set_temperature`](WaveshareDisplay::set_temperature), `None` for the
+    /// internal sensor
+    temperature: Option<i8>,
+    /// Bytes written since `start_frame`, `None` if no streamed frame is open
+    frame_written: Option<u32>,
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
-    for Epd7in5<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd7in5<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         // Reset the device
-        self.interface.reset(delay, 10_000, 2_000);
+        self.interface.reset(delay, 10_000, 2_000)?;
+        self.interface
+            .uc_software_reset(spi, delay, Command::PanelSetting, 0x1F, IS_BUSY_LOW)?;
 
         // V2 procedure as described here:
         // https://github.com/waveshare/e-Paper/blob/master/RaspberryPi%26JetsonNano/python/lib/waveshare_epd/epd7in5bc_V2.py
@@ -79,35 +142,49 @@ where
         self.wait_until_idle(spi, delay)?;
         self.cmd_with_data(spi, Command::PanelSetting, &[0x1F])?; // Sets black and white as opposed to black, white and red.
         self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x29, 0x07])?; // Sets NEW/OLD buffer behavior and polarity
+
+        // the reset forgot the waveform selected with `set_lut` and the forced temperature
+        if self.refresh != RefreshLut::Full || self.temperature.is_some() {
+            self.select_waveform(spi, self.refresh)?;
+        }
         Ok(())
     }
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
-    for Epd7in5<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd7in5<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     type DisplayColor = Color;
+    const REFRESH_LUTS: &'static [RefreshLut] = &[
+        RefreshLut::Full,
+        RefreshLut::Quick,
+        RefreshLut::PartialRefresh,
+    ];
     fn new(
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(busy, dc, rst, pwr, delay_us);
         let color = DEFAULT_BACKGROUND_COLOR;
 
         let mut epd = Epd7in5 {
             interface,
             color,
             refresh: RefreshLut::default(),
+            temperature: None,
+            frame_written: None,
         };
 
         epd.init(spi, delay)?;
@@ -115,15 +192,15 @@ where
         Ok(epd)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.cmd(spi, Command::PowerOff)?;
         self.wait_until_idle(spi, delay)?;
         self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
-        Ok(())
+        self.interface.power_off()
     }
 
     fn update_frame(
@@ -131,7 +208,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(spi, Command::DataStartTransmission2, buffer)?;
         Ok(())
     }
@@ -145,39 +222,14 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
-        let expected_size = buffer_len(width as usize, height as usize);
-        let actual_size = buffer.len();
-        if actual_size != expected_size {
-            panic!("Buffer is incorrect size. Expected: {expected_size}. Actual: {actual_size}.")
-        }
-
-        let x_aligned = x & !0b111; // force to 8-bit-boundary
-        let x_end = x_aligned + width - 1;
-        let x_end_aligned = x_end | 0b111; // exclusive end boundary, ending with 3 1's (following spec)
-
-        let hrst_upper = (x_aligned >> 8) as u8;
-        let hrst_lower = x_aligned as u8;
-        let hred_upper = (x_end_aligned >> 8) as u8;
-        let hred_lower = x_end_aligned as u8;
-
-        let y_end = y + height - 1;
-
-        let vrst_upper = (y >> 8) as u8;
-        let vrst_lower = y as u8;
-        let vred_upper = (y_end >> 8) as u8;
-        let vred_lower = y_end as u8;
-
-        let pt_scan = 0x01; // Gates scan both inside and outside of the partial window. (default)
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_size(buffer, width, height)?;
 
         self.cmd(spi, Command::PartialIn)?;
         self.cmd_with_data(
             spi,
             Command::PartialWindow,
-            &[
-                hrst_upper, hrst_lower, hred_upper, hred_lower, vrst_upper, vrst_lower, vred_upper,
-                vred_lower, pt_scan,
-            ],
+            &partial_window(x, y, width, height),
         )?;
 
         self.update_frame(spi, buffer, delay)?;
@@ -186,10 +238,9 @@ where
         Ok(())
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.cmd(spi, Command::DisplayRefresh)?;
-        self.wait_until_idle(spi, delay)?;
-        Ok(())
+        self.wait_until_idle(spi, delay)
     }
 
     fn update_and_display_frame(
@@ -197,19 +248,39 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.cmd(spi, Command::DisplayRefresh)?;
-        self.wait_until_idle(spi, delay)?;
-        Ok(())
+        self.wait_until_idle(spi, delay)
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.cmd(spi, Command::DataStartTransmission2)?;
         self.interface.data_x_times(spi, 0xFF, WIDTH / 8 * HEIGHT)?;
 
         self.cmd(spi, Command::DisplayRefresh)?;
-        self.wait_until_idle(spi, delay)?;
+        self.wait_until_idle(spi, delay)
+    }
+
+    /// `clear_frame` always clears to white, so the RAM is filled directly
+    fn deghost(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        cycles: u8,
+    ) -> Result<(), Error<SPI::Error>>
+    where
+        Self::DisplayColor: DeghostColors,
+    {
+        self.set_lut(spi, delay, Some(RefreshLut::Full))?;
+
+        for _ in 0..cycles {
+            for byte in [0x00, 0xFF] {
+                self.cmd(spi, Command::DataStartTransmission2)?;
+                self.interface.data_x_times(spi, byte, WIDTH / 8 * HEIGHT)?;
+                self.display_frame(spi, delay)?;
+            }
+        }
         Ok(())
     }
 
@@ -217,6 +288,18 @@ where
         self.color = color;
     }
 
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
     fn background_color(&self) -> &Color {
         &self.color
     }
@@ -234,64 +317,285 @@ where
         spi: &mut SPI,
         _delay: &mut DELAY,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
-        if Some(self.refresh) == refresh_rate {
-            return Ok(());
+    ) -> Result<(), Error<SPI::Error>> {
+        check_refresh_lut(refresh_rate, Self::REFRESH_LUTS)?;
+        let refresh = refresh_rate.unwrap_or(self.refresh);
+        if refresh != self.refresh {
+            self.select_waveform(spi, refresh)?;
+            self.refresh = refresh;
         }
+        Ok(())
+    }
+
+    fn read_temperature(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<i8, Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.frame_written = None;
+        let mut celsius = [0; 2];
+        self.interface
+            .cmd_and_read(spi, Command::TemperatureSensor, &mut celsius)?;
+        Ok(celsius[0] as i8)
+    }
+
+    /// Forces the temperature of the full refresh, the fast and the partial waveform are selected
+    /// by forcing their own
+    fn set_temperature(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+        celsius: Option<i8>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.temperature = celsius;
+        if self.refresh == RefreshLut::Full {
+            self.select_waveform(spi, RefreshLut::Full)?;
+        }
+        Ok(())
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .wait_until_idle_with_cmd(spi, delay, IS_BUSY_LOW, Command::GetStatus)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> QuickRefresh<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd7in5<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    /// To be followed immediately by `update_new_frame`.
+    fn update_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.cmd_with_data(spi, Command::DataStartTransmission1, buffer)
+    }
+
+    /// To be used immediately after `update_old_frame`.
+    fn update_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.cmd_with_data(spi, Command::DataStartTransmission2, buffer)
+    }
+
+    /// This is a wrapper around `display_frame`.
+    fn display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.display_frame(spi, delay)
+    }
+
+    /// To be used immediately after `update_old_frame`.
+    fn update_and_display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_new_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)
+    }
+
+    fn update_partial_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_size(buffer, width, height)?;
+        self.wait_until_idle(spi, delay)?;
+
+        self.cmd(spi, Command::PartialIn)?;
+        self.cmd_with_data(
+            spi,
+            Command::PartialWindow,
+            &partial_window(x, y, width, height),
+        )?;
+        self.cmd_with_data(spi, Command::DataStartTransmission1, buffer)?;
+        self.cmd(spi, Command::PartialOut)
+    }
+
+    /// Always call `update_partial_old_frame` with the same window before this.
+    fn update_partial_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_size(buffer, width, height)?;
+        self.wait_until_idle(spi, delay)?;
+
+        self.cmd(spi, Command::PartialIn)?;
+        self.cmd_with_data(
+            spi,
+            Command::PartialWindow,
+            &partial_window(x, y, width, height),
+        )?;
+        self.cmd_with_data(spi, Command::DataStartTransmission2, buffer)?;
+        self.cmd(spi, Command::PartialOut)
+    }
+
+    fn clear_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+
+        let color = if INVERTED {
+            self.color.inverse().get_byte_value()
+        } else {
+            self.color.get_byte_value()
+        };
+        let len = buffer_len(width as usize, height as usize) as u32;
+
+        self.cmd(spi, Command::PartialIn)?;
+        self.cmd_with_data(
+            spi,
+            Command::PartialWindow,
+            &partial_window(x, y, width, height),
+        )?;
+        self.cmd(spi, Command::DataStartTransmission1)?;
+        self.interface.data_x_times(spi, color, len)?;
+        self.cmd(spi, Command::DataStartTransmission2)?;
+        self.interface.data_x_times(spi, color, len)?;
+        self.cmd(spi, Command::PartialOut)
+    }
+}
 
-        if self.refresh == RefreshLut::Quick {
-            // Return booster power settings to default
-            self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x28, 0x17])?;
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd7in5<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Starts a frame which is written with `write_frame_chunk`
+    pub fn start_frame(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        self.cmd(spi, Command::DataStartTransmission2)?;
+        self.frame_written = Some(0);
+        Ok(())
+    }
+
+    /// Writes the next bytes of the frame started with `start_frame`
+    ///
+    /// The chunks can have any size, `WIDTH / 8` bytes make up a row. Fails with
+    /// [`Error::InvalidBufferLength`] if no frame is started or if the chunks run past the end of
+    /// the frame.
+    pub fn write_frame_chunk(
+        &mut self,
+        spi: &mut SPI,
+        chunk: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        // without a started frame there is no room left for chunks
+        let written = self.frame_written.unwrap_or(FRAME_LEN) as usize;
+        let remaining = FRAME_LEN as usize - written;
+        if self.frame_written.is_none() || chunk.len() > remaining {
+            return Err(Error::InvalidBufferLength {
+                expected: remaining,
+                got: chunk.len(),
+            });
         }
+        let written = (written + chunk.len()) as u32;
+        self.interface.data(spi, chunk)?;
+        self.frame_written = Some(written);
+        Ok(())
+    }
 
+    /// Displays the frame written with `write_frame_chunk`
+    ///
+    /// Fails with [`Error::InvalidBufferLength`] if the chunks don't cover the whole frame.
+    pub fn finish_frame_and_display(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        let written = self.frame_written.unwrap_or(0);
+        check_buffer_len(written as usize, FRAME_LEN as usize)?;
+        self.display_frame(spi, delay)
+    }
+
+    fn cmd(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
+        // any command ends a streamed frame
+        self.frame_written = None;
+        self.interface.cmd(spi, command)
+    }
+
+    fn select_waveform(
+        &mut self,
+        spi: &mut SPI,
+        refresh: RefreshLut,
+    ) -> Result<(), Error<SPI::Error>> {
         // NOT DOCUMENTED IN OFFICIAL SPEC: Override temperature-based LUT selection for fast refresh mode
         // The cascade temperature setting (0xE5) accepts out-of-range values (beyond the 49°C max)
         // which the manufacturer uses as custom LUT indices in OTP memory.
         // This is used in official demo's and libraries, but is not documented behavior.
-        match refresh_rate {
-            Some(RefreshLut::Full) | None => {
-                // This disables custom LUT indices and uses normal temperature-based operation
-                self.cmd_with_data(spi, Command::CascadeSetting, &[0x00])?;
+        match refresh {
+            RefreshLut::Full => {
+                // Return booster power settings to default
+                self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x28, 0x17])?;
+                match self.temperature {
+                    Some(celsius) => {
+                        self.cmd_with_data(spi, Command::CascadeSetting, &[0x02])?;
+                        self.cmd_with_data(spi, Command::ForceTemperature, &[celsius as u8])
+                    }
+                    // This disables custom LUT indices and uses normal temperature-based operation
+                    None => self.cmd_with_data(spi, Command::CascadeSetting, &[0x00]),
+                }
             }
-            Some(RefreshLut::Quick) => {
+            RefreshLut::Quick => {
                 // Booster power settings for quick LUT
                 self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x27, 0x27, 0x18, 0x17])?;
                 // This selects a speed-optimized waveform: fewer voltage transitions mean faster updates
                 // (~2s vs ~4s) at the cost of increased ghosting.
                 self.cmd_with_data(spi, Command::CascadeSetting, &[0x02])?;
-                self.cmd_with_data(spi, Command::ForceTemperature, &[0x5A])?;
+                self.cmd_with_data(spi, Command::ForceTemperature, &[0x5A])
             }
-            Some(RefreshLut::PartialRefresh) => {
+            RefreshLut::PartialRefresh => {
+                self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x28, 0x17])?;
                 // This waveform applies gentle voltage transitions that update only the changed
                 // pixels without the full-screen flicker normally required to clear ghosting.
                 // Will accumulate hosting over many cycles - requires occasional full refresh to
                 // maintain image quality.
                 self.cmd_with_data(spi, Command::CascadeSetting, &[0x02])?;
-                self.cmd_with_data(spi, Command::ForceTemperature, &[0x6E])?;
+                self.cmd_with_data(spi, Command::ForceTemperature, &[0x6E])
             }
         }
-
-        self.refresh = refresh_rate.unwrap_or_default();
-
-        Ok(())
-    }
-
-    fn wait_until_idle(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface
-            .wait_until_idle_with_cmd(spi, delay, IS_BUSY_LOW, Command::GetStatus)
-    }
-}
-
-impl<SPI, BUSY, DC, RST, DELAY> Epd7in5<SPI, BUSY, DC, RST, DELAY>
-where
-    SPI: SpiDevice,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
-    DELAY: DelayNs,
-{
-    fn cmd(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
-        self.interface.cmd(spi, command)
     }
 
     fn cmd_with_data(
@@ -299,19 +603,277 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.frame_written = None;
         self.interface.cmd_with_data(spi, command, data)
     }
 }
 
+fn check_buffer_size<SpiE>(buffer: &[u8], width: u32, height: u32) -> Result<(), Error<SpiE>> {
+    check_buffer_len(buffer.len(), buffer_len(width as usize, height as usize))
+}
+
+/// Data of the partial window command
+fn partial_window(x: u32, y: u32, width: u32, height: u32) -> [u8; 9] {
+    let x_aligned = x & !0b111; // force to 8-bit-boundary
+    let x_end = x_aligned + width - 1;
+    let x_end_aligned = x_end | 0b111; // exclusive end boundary, ending with 3 1's (following spec)
+
+    let y_end = y + height - 1;
+
+    let pt_scan = 0x01; // Gates scan both inside and outside of the partial window. (default)
+
+    [
+        (x_aligned >> 8) as u8,
+        x_aligned as u8,
+        (x_end_aligned >> 8) as u8,
+        x_end_aligned as u8,
+        (y >> 8) as u8,
+        y as u8,
+        (y_end >> 8) as u8,
+        y_end as u8,
+        pt_scan,
+    ]
+}
+
 #[cfg(test)]
 mod tests {
+    extern crate std;
     use super::*;
+    use crate::interface::mock::expect_commands_blockwise;
+    use crate::NoPin;
+    use embedded_hal_mock::eh1::{
+        delay::NoopDelay,
+        digital::{Mock as PinMock, State, Transaction as PinTransaction},
+        spi::{Mock as SpiMock, Transaction as SpiTransaction},
+    };
+    use std::{vec, vec::Vec};
+
+    type MockEpd = Epd7in5<SpiMock<u8>, PinMock, PinMock, PinMock, NoopDelay, NoPin>;
+
+    /// Runs `f` on a driver with `refresh` which expects `commands`, `busy_reads` idle reads of
+    /// BUSY and `resets` pulses of RST
+    fn with_epd(
+        refresh: RefreshLut,
+        commands: &[(u8, &[u8])],
+        busy_reads: usize,
+        resets: usize,
+        f: impl FnOnce(&mut MockEpd, &mut SpiMock<u8>),
+    ) {
+        with_expectations(
+            refresh,
+            expect_commands_blockwise(commands),
+            busy_reads,
+            resets,
+            f,
+        );
+    }
+
+    /// Same as `with_epd`, with the spi and dc transactions given directly
+    fn with_expectations(
+        refresh: RefreshLut,
+        (spi_expectations, dc_expectations): (Vec<SpiTransaction<u8>>, Vec<PinTransaction>),
+        busy_reads: usize,
+        resets: usize,
+        f: impl FnOnce(&mut MockEpd, &mut SpiMock<u8>),
+    ) {
+        let mut spi = SpiMock::new(&spi_expectations);
+        let mut busy = PinMock::new(&vec![PinTransaction::get(State::High); busy_reads]);
+        let mut dc = PinMock::new(&dc_expectations);
+        let pulses: Vec<_> = (0..resets)
+            .flat_map(|_| [State::High, State::Low, State::High])
+            .map(PinTransaction::set)
+            .collect();
+        let mut rst = PinMock::new(&pulses);
+
+        let mut epd = Epd7in5 {
+            interface: DisplayInterface::new(
+                busy.clone(),
+                dc.clone(),
+                Some(rst.clone()),
+                None::<NoPin>,
+                None,
+            ),
+            color: DEFAULT_BACKGROUND_COLOR,
+            refresh,
+            temperature: None,
+            frame_written: None,
+        };
+        f(&mut epd, &mut spi);
+        drop(epd);
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
 
     #[test]
     fn epd_size() {
         assert_eq!(WIDTH, 800);
         assert_eq!(HEIGHT, 480);
-        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::Black);
+        // white on the panel either way
+        let background = if INVERTED { Color::White } else { Color::Black };
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, background);
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn black_pixel_bit() {
+        use crate::graphics::DisplayRotation;
+        use embedded_graphics_core::prelude::*;
+
+        let mut display = Display7in5::default();
+        display.set_rotation(DisplayRotation::Rotate90);
+        display.set_pixel(Pixel(Point::new(0, 0), Color::Black));
+        // the top left corner of the drawing is the top right one of the panel
+        let last = display.buffer()[WIDTH as usize / 8 - 1];
+        assert_eq!(last & 1, u8::from(INVERTED));
+        assert_eq!(display.get_pixel(0, 0), Some(Color::Black));
+    }
+
+    #[test]
+    fn switch_between_fast_and_quality_refresh() {
+        let fast: [(u8, &[u8]); 3] = [
+            (0x06, &[0x27, 0x27, 0x18, 0x17]),
+            (0xE0, &[0x02]),
+            (0xE5, &[0x5A]),
+        ];
+        let quality: [(u8, &[u8]); 2] = [(0x06, &[0x17, 0x17, 0x28, 0x17]), (0xE0, &[0x00])];
+        let commands = [&fast[..], &quality[..]].concat();
+        with_epd(RefreshLut::Full, &commands, 0, 0, |epd, spi| {
+            let delay = &mut NoopDelay::new();
+            epd.set_lut(spi, delay, Some(RefreshLut::Quick)).unwrap();
+            // already selected
+            epd.set_lut(spi, delay, Some(RefreshLut::Quick)).unwrap();
+            epd.set_lut(spi, delay, Some(RefreshLut::Full)).unwrap();
+            epd.set_lut(spi, delay, None).unwrap();
+        });
+    }
+
+    #[test]
+    fn wake_up_keeps_the_fast_refresh() {
+        let commands: [(u8, &[u8]); 8] = [
+            (0x06, &[0x17, 0x17, 0x28, 0x17]),
+            (0x04, &[]),
+            (0x71, &[]),
+            (0x00, &[0x1F]),
+            (0x50, &[0x29, 0x07]),
+            (0x06, &[0x27, 0x27, 0x18, 0x17]),
+            (0xE0, &[0x02]),
+            (0xE5, &[0x5A]),
+        ];
+        with_epd(RefreshLut::Quick, &commands, 1, 1, |epd, spi| {
+            epd.wake_up(spi, &mut NoopDelay::new()).unwrap();
+        });
+    }
+
+    #[test]
+    fn forced_temperature_of_the_full_refresh() {
+        let commands: [(u8, &[u8]); 7] = [
+            (0x06, &[0x17, 0x17, 0x28, 0x17]),
+            (0xE0, &[0x02]),
+            (0xE5, &[0xFB]),
+            (0x06, &[0x17, 0x17, 0x28, 0x17]),
+            (0xE0, &[0x00]),
+            // the status is polled before reading
+            (0x71, &[]),
+            (0x40, &[]),
+        ];
+        let (mut spi_expectations, mut dc_expectations) = expect_commands_blockwise(&commands);
+        dc_expectations.push(PinTransaction::set(State::High));
+        spi_expectations.push(SpiTransaction::transaction_start());
+        spi_expectations.push(SpiTransaction::read_vec(vec![0x1C, 0x40]));
+        spi_expectations.push(SpiTransaction::transaction_end());
+        with_expectations(
+            RefreshLut::Full,
+            (spi_expectations, dc_expectations),
+            1,
+            0,
+            |epd, spi| {
+                let delay = &mut NoopDelay::new();
+                epd.set_temperature(spi, delay, Some(-5)).unwrap();
+                epd.set_temperature(spi, delay, None).unwrap();
+                assert_eq!(epd.read_temperature(spi, delay), Ok(28));
+            },
+        );
+    }
+
+    #[test]
+    fn partial_windows() {
+        assert_eq!(
+            partial_window(0, 0, WIDTH, HEIGHT),
+            [0x00, 0x00, 0x03, 0x1F, 0x00, 0x00, 0x01, 0xDF, 0x01]
+        );
+        // x is aligned down to a byte, the end is the last pixel of its byte
+        assert_eq!(
+            partial_window(299, 200, 49, 10),
+            [0x01, 0x28, 0x01, 0x5F, 0x00, 0xC8, 0x00, 0xD1, 0x01]
+        );
+    }
+
+    #[test]
+    fn frame_in_chunks() {
+        // below the 4096 bytes a transfer is split into on linux
+        let first = [0x0F; 4000];
+        let second = [0xF0; 4000];
+        let (mut spi_expectations, mut dc_expectations) =
+            expect_commands_blockwise(&[(0x13, &first)]);
+        // the second chunk continues the data of the same command
+        dc_expectations.push(PinTransaction::set(State::High));
+        spi_expectations.push(SpiTransaction::transaction_start());
+        spi_expectations.push(SpiTransaction::write_vec(second.to_vec()));
+        spi_expectations.push(SpiTransaction::transaction_end());
+        let (spi_refresh, dc_refresh) = expect_commands_blockwise(&[(0x12, &[]), (0x71, &[])]);
+        spi_expectations.extend(spi_refresh);
+        dc_expectations.extend(dc_refresh);
+
+        with_expectations(
+            RefreshLut::Full,
+            (spi_expectations, dc_expectations),
+            1,
+            0,
+            |epd, spi| {
+                epd.start_frame(spi).unwrap();
+                epd.write_frame_chunk(spi, &first).unwrap();
+                epd.write_frame_chunk(spi, &second).unwrap();
+                // the rest of the frame, as if it had been written
+                epd.frame_written = Some(FRAME_LEN);
+                epd.finish_frame_and_display(spi, &mut NoopDelay::new())
+                    .unwrap();
+                assert_eq!(epd.frame_written, None);
+            },
+        );
+    }
+
+    #[test]
+    fn display_an_incomplete_frame() {
+        let chunk = [0x00; 100];
+        with_epd(RefreshLut::Full, &[(0x13, &chunk)], 0, 0, |epd, spi| {
+            epd.start_frame(spi).unwrap();
+            epd.write_frame_chunk(spi, &chunk).unwrap();
+            assert!(matches!(
+                epd.finish_frame_and_display(spi, &mut NoopDelay::new()),
+                Err(Error::InvalidBufferLength { got: 100, .. })
+            ));
+        });
+    }
+
+    #[test]
+    fn chunks_outside_a_frame() {
+        with_epd(RefreshLut::Full, &[], 0, 0, |epd, spi| {
+            assert!(matches!(
+                epd.write_frame_chunk(spi, &[]),
+                Err(Error::InvalidBufferLength { expected: 0, .. })
+            ));
+            epd.frame_written = Some(FRAME_LEN - 1);
+            assert!(matches!(
+                epd.write_frame_chunk(spi, &[0x00; 2]),
+                Err(Error::InvalidBufferLength {
+                    expected: 1,
+                    got: 2
+                })
+            ));
+        });
     }
 }
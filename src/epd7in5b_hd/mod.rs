@@ -0,0 +1,478 @@
+//! A simple Driver for the Waveshare 7.5" (B) HD Black/White/Red E-Ink Display via SPI
+//!
+//! The panel (880x528) uses the same SSD1677 class controller as the [B/W HD](crate::epd7in5_hd),
+//! with the black/white plane in RAM 0x24 and the red plane in RAM 0x26.
+//!
+//! A full [`Display7in5b`] takes 2 * [`PLANE_LEN`] (116 160) bytes. Instead of holding both
+//! planes in RAM, they can be streamed in chunks of full lines with
+//! [`Epd7in5b::update_achromatic_lines`] and [`Epd7in5b::update_chromatic_lines`].
+//!
+//! The vendor driver inverts its red image before writing it, because a red pixel is a cleared
+//! bit in its buffers. In a [`Display7in5b`] a red pixel is a set bit already, which is what the
+//! red RAM expects, so both planes are sent as they are.
+//!
+//! # References
+//!
+//! - [Waveshare Wiki](https://www.waveshare.com/wiki/7.5inch_HD_e-Paper_HAT_(B))
+//! - [Waveshare C driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_7in5b_HD.c)
+//!
+//! # Example for the 7.5" B HD E-Ink Display
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), epd_waveshare::error::Error<embedded_hal::spi::ErrorKind>> {
+//!use embedded_graphics::{prelude::*, primitives::{Line, PrimitiveStyle}};
+//!use epd_waveshare::{epd7in5b_hd::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = digital::Mock::new(&expectations);
+//!# let dc = digital::Mock::new(&expectations);
+//!# let rst = digital::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
+//!
+//!// Setup EPD
+//!let mut epd = Epd7in5b::new(&mut spi, busy_in, dc, Some(rst), None::<NoPin>, &mut delay, None)?;
+//!
+//!// Use display graphics from embedded-graphics
+//!let mut display = Box::new(Display7in5b::default());
+//!display.clear(TriColor::White);
+//!
+//!// A red line
+//!let _ = Line::new(Point::new(0, 120), Point::new(0, 200))
+//!    .into_styled(PrimitiveStyle::with_stroke(TriColor::Chromatic, 1))
+//!    .draw(&mut *display);
+//!
+//!// Display updated frame
+//!epd.update_color_frame(
+//!    &mut spi,
+//!    &mut delay,
+//!    display.bw_buffer(),
+//!    display.chromatic_buffer(),
+//!)?;
+//!epd.display_frame(&mut spi, &mut delay)?;
+//!
+//!// Set the EPD to sleep
+//!epd.sleep(&mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+
+use crate::error::Error;
+use embedded_hal::{
+    delay::DelayNs,
+    digital::{InputPin, OutputPin},
+    spi::SpiDevice,
+};
+
+use crate::color::TriColor;
+use crate::interface::DisplayInterface;
+use crate::traits::{InternalWiAdditions, WaveshareDisplay, WaveshareThreeColorDisplay};
+use crate::{buffer_len, check_buffer_len, check_partial_window};
+
+pub(crate) mod command;
+use self::command::Command;
+
+/// Width of the display
+pub const WIDTH: u32 = 880;
+/// Height of the display
+pub const HEIGHT: u32 = 528;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: TriColor = TriColor::White;
+/// Number of bytes of one color plane (black/white or red)
+pub const PLANE_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize);
+const LINE_LEN: usize = buffer_len(WIDTH as usize, 1);
+/// Gate and RAM y address of the first line, the controller drives 688 gates
+const FIRST_LINE_ADDRESS: u32 = 0x2AF;
+const IS_BUSY_LOW: bool = false;
+const SINGLE_BYTE_WRITE: bool = false;
+
+/// Full size buffer for use with the 7in5b HD EPD
+///
+/// It takes 2 * [`PLANE_LEN`] bytes of RAM, too much for the stack of most microcontrollers.
+#[cfg(feature = "graphics")]
+pub type Display7in5b = crate::graphics::Display<WIDTH, HEIGHT, true, { PLANE_LEN * 2 }, TriColor>;
+
+/// Epd7in5b (HD) driver
+pub struct Epd7in5b<SPI, BUSY, DC, RST, DELAY, PWR> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    background_color: TriColor,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd7in5b<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, 10_000, 2_000)?;
+
+        self.wait_until_idle(spi, delay)?;
+        self.command(spi, Command::SwReset)?;
+        self.wait_until_idle(spi, delay)?;
+
+        self.cmd_with_data(spi, Command::AutoWriteRed, &[0xF7])?;
+        self.wait_until_idle(spi, delay)?;
+        self.cmd_with_data(spi, Command::AutoWriteBw, &[0xF7])?;
+        self.wait_until_idle(spi, delay)?;
+
+        self.cmd_with_data(spi, Command::SoftStart, &[0xAE, 0xC7, 0xC3, 0xC0, 0x40])?;
+
+        self.cmd_with_data(
+            spi,
+            Command::DriverOutputControl,
+            &[
+                FIRST_LINE_ADDRESS as u8,
+                (FIRST_LINE_ADDRESS >> 8) as u8,
+                0x01,
+            ],
+        )?;
+
+        // x increment, y decrement: the first line is at the highest y address
+        self.cmd_with_data(spi, Command::DataEntry, &[0x01])?;
+        self.cmd_with_data(
+            spi,
+            Command::SetRamXStartEnd,
+            &[0x00, 0x00, (WIDTH - 1) as u8, ((WIDTH - 1) >> 8) as u8],
+        )?;
+        self.cmd_with_data(
+            spi,
+            Command::SetRamYStartEnd,
+            &[
+                FIRST_LINE_ADDRESS as u8,
+                (FIRST_LINE_ADDRESS >> 8) as u8,
+                0x00,
+                0x00,
+            ],
+        )?;
+
+        self.cmd_with_data(spi, Command::VbdControl, &[0x01])?;
+
+        // internal temperature sensor, load the waveform
+        self.cmd_with_data(spi, Command::TemperatureSensorControl, &[0x80])?;
+        self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xB1])?;
+        self.command(spi, Command::MasterActivation)?;
+        self.wait_until_idle(spi, delay)?;
+
+        self.set_ram_counter(spi, 0)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd7in5b<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    fn update_color_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+        chromatic: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_achromatic_frame(spi, delay, black)?;
+        self.update_chromatic_frame(spi, delay, chromatic)
+    }
+
+    fn update_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(black.len(), PLANE_LEN)?;
+        self.update_achromatic_lines(spi, delay, 0, black)
+    }
+
+    /// A set bit in `chromatic` is a red pixel
+    fn update_chromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        chromatic: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(chromatic.len(), PLANE_LEN)?;
+        self.update_chromatic_lines(spi, delay, 0, chromatic)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd7in5b<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    type DisplayColor = TriColor;
+
+    fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new_color(busy, dc, rst, pwr, delay_us);
+
+        let mut epd = Epd7in5b {
+            interface,
+            background_color: DEFAULT_BACKGROUND_COLOR,
+        };
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.cmd_with_data(spi, Command::DeepSleep, &[0x01])?;
+        self.interface.power_off()
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.init(spi, delay)
+    }
+
+    fn set_background_color(&mut self, background_color: TriColor) {
+        self.background_color = background_color;
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
+    fn background_color(&self) -> &TriColor {
+        &self.background_color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    /// Updates the black/white plane and clears the red one
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_achromatic_frame(spi, delay, buffer)?;
+        self.fill_ram(spi, Command::WriteRamRed, 0x00)
+    }
+
+    fn update_partial_frame(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+        _buffer: &[u8],
+        _x: u32,
+        _y: u32,
+        _width: u32,
+        _height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::Unsupported)
+    }
+
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        // the waveform was loaded during the init
+        self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xC7])?;
+        self.command(spi, Command::MasterActivation)?;
+        self.wait_until_idle(spi, delay)
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+
+        let (black, chromatic) = match self.background_color {
+            TriColor::White => (0xFF, 0x00),
+            TriColor::Black => (0x00, 0x00),
+            TriColor::Chromatic => (0x00, 0xFF),
+        };
+        self.fill_ram(spi, Command::WriteRamBw, black)?;
+        self.fill_ram(spi, Command::WriteRamRed, chromatic)
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd7in5b<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Writes full lines of the black/white plane, starting at line `y`
+    ///
+    /// `lines` must contain a multiple of `WIDTH / 8` bytes, so a plane can be sent in chunks.
+    pub fn update_achromatic_lines(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        y: u32,
+        lines: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.write_lines(spi, delay, Command::WriteRamBw, y, lines)
+    }
+
+    /// Writes full lines of the red plane, starting at line `y`
+    ///
+    /// `lines` must contain a multiple of `WIDTH / 8` bytes, so a plane can be sent in chunks.
+    /// A set bit is a red pixel.
+    pub fn update_chromatic_lines(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        y: u32,
+        lines: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.write_lines(spi, delay, Command::WriteRamRed, y, lines)
+    }
+
+    fn write_lines(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        ram: Command,
+        y: u32,
+        lines: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        // only full lines of WIDTH / 8 bytes can be written
+        let height = (lines.len() / LINE_LEN) as u32;
+        check_buffer_len(lines.len(), height as usize * LINE_LEN)?;
+        if height == 0 {
+            return Ok(());
+        }
+        check_partial_window(lines.len(), 0, y, WIDTH, height, WIDTH, HEIGHT)?;
+
+        self.wait_until_idle(spi, delay)?;
+        self.set_ram_counter(spi, y)?;
+        self.cmd_with_data(spi, ram, lines)
+    }
+
+    fn fill_ram(
+        &mut self,
+        spi: &mut SPI,
+        ram: Command,
+        value: u8,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.set_ram_counter(spi, 0)?;
+        self.command(spi, ram)?;
+        self.interface.data_x_times(spi, value, PLANE_LEN as u32)
+    }
+
+    /// Sets the RAM address counters to the start of line `y`
+    fn set_ram_counter(&mut self, spi: &mut SPI, y: u32) -> Result<(), Error<SPI::Error>> {
+        self.cmd_with_data(spi, Command::SetRamXAc, &[0x00, 0x00])?;
+        self.cmd_with_data(spi, Command::SetRamYAc, &line_address(y))
+    }
+
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd_with_data(spi, command, data)
+    }
+}
+
+/// RAM y address of line `y`, low byte first
+///
+/// The y address counter decrements, the first line is at the highest address.
+fn line_address(y: u32) -> [u8; 2] {
+    let address = FIRST_LINE_ADDRESS - y;
+    [address as u8, (address >> 8) as u8]
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 880);
+        assert_eq!(HEIGHT, 528);
+        assert_eq!(PLANE_LEN, 58_080);
+        assert_eq!(LINE_LEN, 110);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, TriColor::White);
+    }
+
+    #[test]
+    fn line_addresses() {
+        assert_eq!(line_address(0), [0xAF, 0x02]);
+        assert_eq!(line_address(16), [0x9F, 0x02]);
+        assert_eq!(line_address(HEIGHT - 1), [0xA0, 0x00]);
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn red_is_a_set_bit() {
+        use embedded_graphics::prelude::*;
+
+        let mut display = std::boxed::Box::new(Display7in5b::default());
+        display.clear(TriColor::White).unwrap();
+        Pixel(Point::new(0, 0), TriColor::Chromatic)
+            .draw(&mut *display)
+            .unwrap();
+        // as expected by the red RAM, without inverting the plane
+        assert_eq!(display.chromatic_buffer()[0], 0b1000_0000);
+        assert_eq!(display.chromatic_buffer()[1], 0x00);
+        assert_eq!(display.bw_buffer().len(), PLANE_LEN);
+    }
+}
@@ -0,0 +1,92 @@
+//! Errors of the drivers
+
+use embedded_hal::digital::ErrorKind;
+
+/// Error of the drivers, returned by the functions of [`WaveshareDisplay`], [`QuickRefresh`]
+/// and the other traits
+///
+/// `SpiE` is the error of the SPI device. The errors of the BUSY, DC and RST pins are kept as
+/// their [`ErrorKind`] by default, as every pin may have a different error type.
+///
+/// [`WaveshareDisplay`]: crate::prelude::WaveshareDisplay
+/// [`QuickRefresh`]: crate::prelude::QuickRefresh
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error<SpiE, PinE = ErrorKind> {
+    /// A transfer over SPI failed
+    Spi(SpiE),
+    /// Reading the BUSY pin or setting the DC or RST pin failed
+    Pin(PinE),
+    /// The BUSY line didn't go idle within the timeout, see
+    /// [`WaveshareDisplay::set_busy_timeout`]
+    ///
+    /// The display may be miswired or its cable loose. The driver is still usable, e.g. to reset
+    /// the display with `wake_up`.
+    ///
+    /// [`WaveshareDisplay::set_busy_timeout`]: crate::prelude::WaveshareDisplay::set_busy_timeout
+    BusyTimeout,
+    /// The window at `x`, `y` of `width` x `height` pixels doesn't fit on the display, is empty,
+    /// or its `x` or `width` isn't a multiple of 8 where the controller addresses whole bytes
+    OutOfBounds {
+        /// Left edge of the window
+        x: u32,
+        /// Top edge of the window
+        y: u32,
+        /// Width of the window
+        width: u32,
+        /// Height of the window
+        height: u32,
+    },
+    /// The display or its controller doesn't support the operation, e.g. a refresh mode or a
+    /// partial update
+    Unsupported,
+    /// A buffer doesn't have the length the operation needs
+    InvalidBufferLength {
+        /// Number of bytes the operation needs
+        expected: usize,
+        /// Number of bytes given
+        got: usize,
+    },
+}
+
+impl<SpiE> Error<SpiE> {
+    /// Keeps the kind of a pin error
+    pub(crate) fn pin<E: embedded_hal::digital::Error>(error: E) -> Self {
+        Error::Pin(error.kind())
+    }
+}
+
+impl<SpiE, PinE> From<SpiE> for Error<SpiE, PinE> {
+    fn from(error: SpiE) -> Self {
+        Error::Spi(error)
+    }
+}
+
+impl<SpiE: core::fmt::Debug, PinE: core::fmt::Debug> core::fmt::Display for Error<SpiE, PinE> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::Spi(error) => write!(f, "SPI error: {:?}", error),
+            Error::Pin(error) => write!(f, "pin error: {:?}", error),
+            Error::BusyTimeout => write!(f, "the display stayed busy beyond the timeout"),
+            Error::OutOfBounds {
+                x,
+                y,
+                width,
+                height,
+            } => write!(
+                f,
+                "the window of {}x{} at ({}, {}) doesn't fit on the display",
+                width, height, x, y
+            ),
+            Error::Unsupported => write!(f, "the display doesn't support the operation"),
+            Error::InvalidBufferLength { expected, got } => {
+                write!(f, "the buffer has {} bytes instead of {}", got, expected)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(feature = "std")]
+impl<SpiE: core::fmt::Debug, PinE: core::fmt::Debug> std::error::Error for Error<SpiE, PinE> {}
@@ -1,14 +1,19 @@
 //! Graphics Support for EPDs
 
 use crate::color::{Color, ColorType, TriColor};
+use crate::rect::Rect;
 use core::marker::PhantomData;
+use embedded_graphics_core::pixelcolor::{Gray2, Gray8};
 use embedded_graphics_core::prelude::*;
+use embedded_graphics_core::primitives::Rectangle;
 
+#[cfg(feature = "alloc")]
 extern crate alloc;
-use alloc::vec::Vec;
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, vec, vec::Vec};
 
 /// Display rotation, only 90° increments supported
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, Debug)]
 pub enum DisplayRotation {
     /// No rotation
     #[default]
@@ -21,6 +26,20 @@ pub enum DisplayRotation {
     Rotate270,
 }
 
+/// Display mirroring, applied to the drawing as seen with the rotation
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum DisplayMirror {
+    /// No mirroring
+    #[default]
+    None,
+    /// Mirror left to right
+    Horizontal,
+    /// Mirror top to bottom
+    Vertical,
+    /// Mirror both ways, the same as a rotation by 180 degrees
+    Both,
+}
+
 /// count the number of bytes per line knowing that it may contains padding bits
 const fn line_bytes(width: u32, bits_per_pixel: usize) -> usize {
     // round to upper 8 bit count
@@ -33,10 +52,28 @@ const fn line_bytes(width: u32, bits_per_pixel: usize) -> usize {
 /// - WIDTH: width in pixel when display is not rotated
 /// - HEIGHT: height in pixel when display is not rotated
 /// - BWRBIT: mandatory value of the B/W when chromatic bit is set, can be any value for non
-///           tricolor epd
+///   tricolor epd
 /// - COLOR: color type used by the target display
+/// - BUFFER: storage of the pixels, the owned `[u8; BYTECOUNT]` by default or a borrowed
+///   `&mut [u8]` from [`Display::with_buffer`]
+/// - INVERTED: flips the bits of the first plane, for panels which show a set bit as black, so
+///   that `Color::Black` still draws black. `false` by default, the aliases of such panels set it
+///   with the `color_polarity` feature. The raw buffer functions, like `copy_from_slice`, don't
+///   flip anything.
 /// - BYTECOUNT: This is redundant with previous data and should be removed when const generic
-///              expressions are stabilized
+///   expressions are stabilized. Use [`crate::buffer_len`] to compute it, a wrong value fails to
+///   compile:
+///
+/// ```compile_fail
+/// use epd_waveshare::{color::Color, graphics::Display};
+///
+/// // 200 x 200 pixels take 5000 bytes
+/// let display = Display::<200, 200, false, 4000, Color>::default();
+/// ```
+///
+/// Every `DisplayXinY` type of the drivers is an alias of this type, so generic code can take
+/// any of them, e.g. `&mut Display<W, H, B, N, Color>` to draw on it and hand its `buffer()` to
+/// the driver.
 ///
 /// More on BWRBIT:
 ///
@@ -55,9 +92,13 @@ pub struct Display<
     const BWRBIT: bool,
     const BYTECOUNT: usize,
     COLOR: ColorType + PixelColor,
+    BUFFER = [u8; BYTECOUNT],
+    const INVERTED: bool = false,
 > {
-    buffer: [u8; BYTECOUNT],
+    buffer: BUFFER,
     rotation: DisplayRotation,
+    mirror: DisplayMirror,
+    dirty: Option<Rect>,
     _color: PhantomData<COLOR>,
 }
 
@@ -67,7 +108,8 @@ impl<
         const BWRBIT: bool,
         const BYTECOUNT: usize,
         COLOR: ColorType + PixelColor,
-    > Default for Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR>
+        const INVERTED: bool,
+    > Default for Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR, [u8; BYTECOUNT], INVERTED>
 {
     /// Initialize display with the color '0', which may not be the same on all device.
     /// Many devices have a bit parameter polarity that should be changed if this is not the right
@@ -79,15 +121,118 @@ impl<
     // inline is necessary here to allow heap allocation via Box on stack limited programs
     #[inline(always)]
     fn default() -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::BYTECOUNT_MATCHES;
         Self {
             // default color must be 0 for every bit in a pixel to make this work everywere
             buffer: [0u8; BYTECOUNT],
             rotation: DisplayRotation::default(),
+            mirror: DisplayMirror::default(),
+            dirty: None,
+            _color: PhantomData,
+        }
+    }
+}
+
+/// Constructors from an owned or a borrowed buffer
+impl<
+        const WIDTH: u32,
+        const HEIGHT: u32,
+        const BWRBIT: bool,
+        const BYTECOUNT: usize,
+        COLOR: ColorType + PixelColor,
+        const INVERTED: bool,
+    > Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR, [u8; BYTECOUNT], INVERTED>
+{
+    /// Creates a display holding `buffer`, a frame in the layout of the panel
+    ///
+    /// The buffer is taken as it is, the rotation and the mirroring only apply to what is drawn on
+    /// top of it. The whole display is marked dirty.
+    pub fn from_buffer(buffer: [u8; BYTECOUNT]) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::BYTECOUNT_MATCHES;
+        Self {
+            buffer,
+            rotation: DisplayRotation::default(),
+            mirror: DisplayMirror::default(),
+            dirty: Some(Rect::new(0, 0, WIDTH, HEIGHT)),
+            _color: PhantomData,
+        }
+    }
+
+    /// Creates a display drawing into `buffer`, e.g. a framebuffer in a static or a reserved
+    /// region of RAM
+    ///
+    /// Everything works the same as with the owned buffer, the size of the panel is still part of
+    /// the type, e.g. `Display7in5::with_buffer(&mut frame)`. The buffer is taken as it is and the
+    /// whole display is marked dirty, like `from_buffer`. Fails if `buffer` isn't `BYTECOUNT` bytes
+    /// long.
+    pub fn with_buffer(
+        buffer: &mut [u8],
+    ) -> Result<
+        Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR, &mut [u8], INVERTED>,
+        BufferLengthError,
+    > {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::BYTECOUNT_MATCHES;
+        if buffer.len() != BYTECOUNT {
+            return Err(BufferLengthError {
+                expected: BYTECOUNT,
+                actual: buffer.len(),
+            });
+        }
+        Ok(Display {
+            buffer,
+            rotation: DisplayRotation::default(),
+            mirror: DisplayMirror::default(),
+            dirty: Some(Rect::new(0, 0, WIDTH, HEIGHT)),
+            _color: PhantomData,
+        })
+    }
+
+    /// Creates a display whose buffer is allocated on the heap, see `BoxedDisplay`
+    #[cfg(feature = "alloc")]
+    ///
+    /// The buffer never is on the stack, e.g. `Display7in5::new_boxed()` works in a thread with a
+    /// small stack and the display is moved as a pointer. It's filled with the color '0' like
+    /// `default` does.
+    pub fn new_boxed() -> BoxedDisplay<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR, INVERTED> {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::BYTECOUNT_MATCHES;
+        Display {
+            buffer: vec![0u8; BYTECOUNT].into_boxed_slice(),
+            rotation: DisplayRotation::default(),
+            mirror: DisplayMirror::default(),
+            dirty: None,
             _color: PhantomData,
         }
     }
 }
 
+/// `Display` with its buffer on the heap, as returned by `Display::new_boxed`
+///
+/// It draws and is passed to the drivers like the `Display` of the panel, e.g.
+/// `epd.update_frame(&mut spi, display.buffer(), &mut delay)`. [`OwnedDisplay`] is sized at
+/// runtime instead.
+#[cfg(feature = "alloc")]
+pub type BoxedDisplay<
+    const WIDTH: u32,
+    const HEIGHT: u32,
+    const BWRBIT: bool,
+    const BYTECOUNT: usize,
+    COLOR,
+    const INVERTED: bool = false,
+> = Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR, Box<[u8]>, INVERTED>;
+
+/// `Display` with 4 gray levels, as taken by the `update_and_display_frame_grayscale` functions
+/// of the drivers
+///
+/// Every byte holds 4 pixels, the first one in the most significant bits. The luma of `Gray2` is
+/// stored as it is, `Gray2::BLACK` is `0b00` and `Gray2::WHITE` is `0b11`. `BYTECOUNT` is
+/// `buffer_len(WIDTH as usize * 2, HEIGHT as usize)`, the `GRAY_BUFFER_LEN` of the drivers.
+pub type DisplayGray2<const WIDTH: u32, const HEIGHT: u32, const BYTECOUNT: usize> =
+    Display<WIDTH, HEIGHT, false, BYTECOUNT, Gray2>;
+
 /// For use with embedded_grahics
 impl<
         const WIDTH: u32,
@@ -95,7 +240,9 @@ impl<
         const BWRBIT: bool,
         const BYTECOUNT: usize,
         COLOR: ColorType + PixelColor,
-    > DrawTarget for Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR>
+        BUFFER: AsRef<[u8]> + AsMut<[u8]>,
+        const INVERTED: bool,
+    > DrawTarget for Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR, BUFFER, INVERTED>
 {
     type Color = COLOR;
     type Error = core::convert::Infallible;
@@ -104,11 +251,40 @@ impl<
     where
         I: IntoIterator<Item = Pixel<Self::Color>>,
     {
+        let frame = self.frame();
         for pixel in pixels {
-            self.set_pixel(pixel);
+            if let Some((x, y)) = frame.set_pixel(self.buffer.as_mut(), pixel) {
+                mark_dirty(&mut self.dirty, x, y);
+            }
+        }
+        Ok(())
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        let frame = self.frame();
+        if let Some(rect) = frame.fill_contiguous(self.buffer.as_mut(), area, colors) {
+            mark_dirty_rect(&mut self.dirty, rect);
+        }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let frame = self.frame();
+        if let Some(rect) = frame.fill_solid(self.buffer.as_mut(), area, color) {
+            mark_dirty_rect(&mut self.dirty, rect);
         }
         Ok(())
     }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        let frame = self.frame();
+        let rect = frame.clear(self.buffer.as_mut(), color);
+        mark_dirty_rect(&mut self.dirty, rect);
+        Ok(())
+    }
 }
 
 /// For use with embedded_grahics
@@ -118,7 +294,9 @@ impl<
         const BWRBIT: bool,
         const BYTECOUNT: usize,
         COLOR: ColorType + PixelColor,
-    > OriginDimensions for Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR>
+        BUFFER: AsRef<[u8]> + AsMut<[u8]>,
+        const INVERTED: bool,
+    > OriginDimensions for Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR, BUFFER, INVERTED>
 {
     fn size(&self) -> Size {
         match self.rotation {
@@ -134,40 +312,267 @@ impl<
         const BWRBIT: bool,
         const BYTECOUNT: usize,
         COLOR: ColorType + PixelColor,
-    > Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR>
+        BUFFER: AsRef<[u8]> + AsMut<[u8]>,
+        const INVERTED: bool,
+    > Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR, BUFFER, INVERTED>
 {
+    /// Fails the build if `BYTECOUNT` doesn't match the size and color
+    const BYTECOUNT_MATCHES: () = assert!(
+        BYTECOUNT
+            == HEIGHT as usize
+                * line_bytes(WIDTH, COLOR::BITS_PER_PIXEL_PER_BUFFER)
+                * COLOR::BUFFER_COUNT,
+        "BYTECOUNT must match the size and the color of the display"
+    );
+
     /// get internal buffer to use it (to draw in epd)
     pub fn buffer(&self) -> &[u8] {
-        &self.buffer
+        self.buffer.as_ref()
+    }
+
+    /// Replaces the buffer with `data`, a frame in the layout of the panel, e.g. as returned by
+    /// `buffer`
+    ///
+    /// The data is copied as it is, neither the rotation nor the mirroring is applied to it. The
+    /// whole display is marked dirty. Fails if `data` isn't `BYTECOUNT` bytes long, the buffer is
+    /// left untouched then.
+    pub fn copy_from_slice(&mut self, data: &[u8]) -> Result<(), BufferLengthError> {
+        if data.len() != BYTECOUNT {
+            return Err(BufferLengthError {
+                expected: BYTECOUNT,
+                actual: data.len(),
+            });
+        }
+        self.buffer.as_mut().copy_from_slice(data);
+        self.dirty = Some(Rect::new(0, 0, WIDTH, HEIGHT));
+        Ok(())
     }
 
     /// Set the display rotation.
     ///
     /// This only concerns future drawing made to it. Anything aready drawn
-    /// stays as it is in the buffer.
+    /// stays as it is in the buffer, use `set_rotation_preserving` to turn it along.
     pub fn set_rotation(&mut self, rotation: DisplayRotation) {
         self.rotation = rotation;
     }
 
+    /// Sets the display rotation and moves the pixels in the buffer, so the drawing looks the
+    /// same with the new rotation
+    ///
+    /// A turn by 180 degrees reverses the buffer in place when the lines fill whole bytes, other
+    /// turns move every pixel to its new place in the buffer. Turning by 90 or 270 degrees swaps
+    /// the width and the height of the drawing and fails if the panel isn't square, the display
+    /// is left as it is then. The whole display is marked dirty.
+    pub fn set_rotation_preserving(
+        &mut self,
+        rotation: DisplayRotation,
+    ) -> Result<(), RotationError> {
+        let turn = (quarter_turns(rotation) + 4 - quarter_turns(self.rotation)) % 4;
+        if turn % 2 == 1 && WIDTH != HEIGHT {
+            return Err(RotationError { size: self.size() });
+        }
+        let bits_per_pixel = COLOR::BITS_PER_PIXEL_PER_BUFFER;
+        if turn == 2 && WIDTH as usize * bits_per_pixel % 8 == 0 {
+            // the last pixel of the buffer becomes the first one
+            let buffer = self.buffer.as_mut();
+            buffer.reverse();
+            buffer
+                .iter_mut()
+                .for_each(|byte| *byte = reverse_pixels(*byte, bits_per_pixel));
+            if COLOR::BUFFER_COUNT == 2 {
+                // the planes have swapped places as well
+                let plane = buffer.len() / 2;
+                let (first, second) = buffer.split_at_mut(plane);
+                first.swap_with_slice(second);
+            }
+        } else if turn != 0 {
+            let from = Frame::new(WIDTH, HEIGHT, self.rotation, self.mirror, BWRBIT);
+            let to = Frame::new(WIDTH, HEIGHT, rotation, self.mirror, BWRBIT);
+            // the pixels move in cycles of up to 4, each one is moved once from its first pixel
+            let next = |(x, y)| {
+                let (x, y) = to.buffer_point(from.view_point(x, y));
+                (x as u32, y as u32)
+            };
+            let buffer = self.buffer.as_mut();
+            for start in Rectangle::new(Point::zero(), Size::new(WIDTH, HEIGHT)).points() {
+                let start = (start.x as u32, start.y as u32);
+                let mut at = next(start);
+                while at != start && (at.1, at.0) > (start.1, start.0) {
+                    at = next(at);
+                }
+                if at != start {
+                    continue;
+                }
+                let mut bits = from.read_pixel::<COLOR>(buffer, start.0, start.1);
+                loop {
+                    at = next(at);
+                    let moved = from.read_pixel::<COLOR>(buffer, at.0, at.1);
+                    from.write_bits::<COLOR>(buffer, at.0, at.1, bits);
+                    bits = moved;
+                    if at == start {
+                        break;
+                    }
+                }
+            }
+        }
+        self.rotation = rotation;
+        if turn != 0 {
+            self.dirty = Some(Rect::new(0, 0, WIDTH, HEIGHT));
+        }
+        Ok(())
+    }
+
     /// Get current rotation
     pub fn rotation(&self) -> DisplayRotation {
         self.rotation
     }
 
+    /// Set the display mirroring.
+    ///
+    /// Like the rotation this only concerns future drawing made to it.
+    pub fn set_mirror(&mut self, mirror: DisplayMirror) {
+        self.mirror = mirror;
+    }
+
+    /// Get current mirroring
+    pub fn mirror(&self) -> DisplayMirror {
+        self.mirror
+    }
+
     /// Set a specific pixel color on this display
     pub fn set_pixel(&mut self, pixel: Pixel<COLOR>) {
-        set_pixel(
-            &mut self.buffer,
-            WIDTH,
-            HEIGHT,
-            self.rotation,
-            BWRBIT,
-            pixel,
-        );
+        let frame = self.frame();
+        if let Some((x, y)) = frame.set_pixel(self.buffer.as_mut(), pixel) {
+            mark_dirty(&mut self.dirty, x, y);
+        }
+    }
+
+    /// The geometry and the polarity of the buffer
+    fn frame(&self) -> Frame {
+        Frame::new(WIDTH, HEIGHT, self.rotation, self.mirror, BWRBIT).inverted(INVERTED)
+    }
+
+    /// Sets a pixel like `set_pixel`, but fails if it's off the display instead of dropping it
+    ///
+    /// The error holds the point and the size of the drawing, i.e. with the rotation applied.
+    /// Drawing through `DrawTarget` keeps clipping, as embedded-graphics expects it.
+    pub fn try_set_pixel(&mut self, pixel: Pixel<COLOR>) -> Result<(), OutOfBoundsError> {
+        check_bounds(pixel.0, self.size())?;
+        self.set_pixel(pixel);
+        Ok(())
+    }
+
+    /// Returns the bounding box of the pixels set since the last call and starts over
+    ///
+    /// The box is in the coordinates of the buffer, i.e. of the panel without rotation, as the
+    /// `update_partial_*` functions expect them. Many controllers address bytes, round `x` and
+    /// `w` to multiples of 8 for them. `clear` marks the whole display.
+    pub fn take_dirty_rect(&mut self) -> Option<Rect> {
+        self.dirty.take()
+    }
+
+    /// Maps `area` of the drawing to the window of the panel which covers it, as
+    /// `update_partial_frame` takes it
+    ///
+    /// The rotation and the mirroring are undone, so the window is in the coordinates of the
+    /// buffer, like `take_dirty_rect`. It's clipped to the display and widened to whole bytes of a
+    /// line, i.e. to multiples of 8 pixels for `Color`. `None` if `area` is off the display.
+    pub fn to_panel_area(&self, area: Rectangle) -> Option<Rect> {
+        let frame = self.frame();
+        let (_, rect) = frame.buffer_rect(&area)?;
+        Some(widen_to_bytes::<COLOR>(rect, WIDTH))
+    }
+
+    /// Returns the smallest rectangle of the drawing which holds all pixels that differ from
+    /// `other`, `None` if both are the same
+    ///
+    /// The buffers are compared a byte at a time and the pixels are only looked at in the first
+    /// and the last differing byte of a line, the padding bits at the end of the lines are
+    /// ignored. The rectangle follows the rotation and the mirroring of `self`, `to_panel_area`
+    /// turns it into the window for `update_partial_new_frame`.
+    pub fn diff_region<OTHER: AsRef<[u8]> + AsMut<[u8]>>(
+        &self,
+        other: &Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR, OTHER, INVERTED>,
+    ) -> Option<Rectangle> {
+        let rect = changed_pixels::<COLOR>(self.buffer(), other.buffer(), WIDTH)?;
+        Some(self.frame().view_rect(rect))
+    }
+
+    /// Copies the bytes of `window` out of the buffer, the data `update_partial_frame` takes for
+    /// it
+    ///
+    /// The lines of the window are packed one after the other. For colors with two buffers the
+    /// black/white plane comes first, like in `buffer`. Fails if `window` doesn't start at a
+    /// byte or isn't on the display, windows from `to_panel_area` never do.
+    #[cfg(feature = "alloc")]
+    pub fn window_buffer(&self, window: Rect) -> Result<Vec<u8>, WindowError> {
+        let fits = |start: u32, len: u32, size: u32| {
+            start.checked_add(len).map_or(false, |end| end <= size)
+        };
+        if window.x as usize * COLOR::BITS_PER_PIXEL_PER_BUFFER % 8 != 0
+            || !fits(window.x, window.w, WIDTH)
+            || !fits(window.y, window.h, HEIGHT)
+        {
+            return Err(WindowError { window });
+        }
+        Ok(window_bytes::<COLOR>(self.buffer(), WIDTH, window))
+    }
+
+    /// Reads the bits of the pixel at `x`, `y` of the drawing in both planes
+    fn pixel_bits(&self, x: u32, y: u32) -> Option<(u8, u8)> {
+        let frame = self.frame();
+        let view = frame.view_size();
+        if x >= view.width || y >= view.height {
+            return None;
+        }
+        let (x, y) = frame.buffer_point(Point::new(x as i32, y as i32));
+        Some(frame.read_pixel::<COLOR>(self.buffer(), x as u32, y as u32))
+    }
+
+    /// Inverts the pixels in `area`, see the `invert_area` of the colors which support it
+    fn invert(&mut self, area: &Rectangle) {
+        let frame = self.frame();
+        if let Some(rect) = frame.invert::<COLOR>(self.buffer.as_mut(), area) {
+            mark_dirty_rect(&mut self.dirty, rect);
+        }
+    }
+
+    /// Returns a `DrawTarget` for the part of `area` on the display, with its own coordinates
+    ///
+    /// `area` is in the coordinates of the drawing, i.e. it's rotated with the display. A widget
+    /// drawn with it can't draw outside of `area`.
+    pub fn cropped(&mut self, area: Rectangle) -> CroppedDisplay<'_, Self> {
+        CroppedDisplay::new(self, area)
+    }
+
+    /// Moves the drawing `dx` pixels to the right, or to the left if `dx` is negative, and fills
+    /// the uncovered columns with `fill`
+    ///
+    /// The move follows the rotation and the mirroring, like drawing does. Moving by the width or
+    /// more fills the whole display. The whole display is marked dirty.
+    pub fn scroll_horizontal(&mut self, dx: i32, fill: COLOR) {
+        self.scroll(dx, 0, fill);
+    }
+
+    /// Moves the drawing `dy` pixels down, or up if `dy` is negative, and fills the uncovered
+    /// rows with `fill`
+    ///
+    /// A log scrolls with `scroll_vertical(-line_height, background)` before the new line is
+    /// drawn at the bottom. Otherwise the same as `scroll_horizontal`.
+    pub fn scroll_vertical(&mut self, dy: i32, fill: COLOR) {
+        self.scroll(0, dy, fill);
+    }
+
+    fn scroll(&mut self, dx: i32, dy: i32, fill: COLOR) {
+        let frame = self.frame();
+        if let Some(rect) = frame.scroll(self.buffer.as_mut(), dx, dy, fill) {
+            mark_dirty_rect(&mut self.dirty, rect);
+        }
     }
 
     /// Creates a virtual partial frame
     /// Handles byte-alignment for you and keeps the full display buffer in sync
+    #[cfg(feature = "alloc")]
     pub fn get_partial_frame<'a>(
         &'a mut self,
         x: u32,
@@ -180,7 +585,7 @@ impl<
             y,
             width,
             height,
-            &mut self.buffer,
+            self.buffer.as_mut(),
             WIDTH,
             BYTECOUNT,
             BWRBIT,
@@ -189,137 +594,690 @@ impl<
 }
 
 /// Some Tricolor specifics
-impl<const WIDTH: u32, const HEIGHT: u32, const BWRBIT: bool, const BYTECOUNT: usize>
-    Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, TriColor>
+impl<
+        const WIDTH: u32,
+        const HEIGHT: u32,
+        const BWRBIT: bool,
+        const BYTECOUNT: usize,
+        BUFFER: AsRef<[u8]> + AsMut<[u8]>,
+        const INVERTED: bool,
+    > Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, TriColor, BUFFER, INVERTED>
 {
     /// get black/white internal buffer to use it (to draw in epd)
     pub fn bw_buffer(&self) -> &[u8] {
-        &self.buffer[..self.buffer.len() / 2]
+        &self.buffer()[..self.buffer().len() / 2]
     }
 
     /// get chromatic internal buffer to use it (to draw in epd)
     pub fn chromatic_buffer(&self) -> &[u8] {
-        &self.buffer[self.buffer.len() / 2..]
+        &self.buffer()[self.buffer().len() / 2..]
     }
-}
 
-/// Same as `Display`, except that its characteristics are defined at runtime.
-/// See display for documentation as everything is the same except that default
-/// is replaced by a `new` method.
-pub struct VarDisplay<'a, COLOR: ColorType + PixelColor> {
-    width: u32,
-    height: u32,
-    bwrbit: bool,
-    buffer: &'a mut [u8],
-    rotation: DisplayRotation,
-    _color: PhantomData<COLOR>,
-}
+    /// Sets every pixel of the black/white plane to `color`, the chromatic plane is kept
+    ///
+    /// Pixels keep their chromatic bit, so chromatic pixels stay chromatic on displays where it
+    /// overrides the black/white plane (`BWRBIT = false`).
+    pub fn clear_bw(&mut self, color: Color) {
+        let flip = if INVERTED { 0xFF } else { 0x00 };
+        let buffer = self.buffer.as_mut();
+        let plane = buffer.len() / 2;
+        buffer[..plane].fill(color.get_byte_value() ^ flip);
+        self.dirty = Some(Rect::new(0, 0, WIDTH, HEIGHT));
+    }
 
-/// For use with embedded_grahics
-impl<COLOR: ColorType + PixelColor> DrawTarget for VarDisplay<'_, COLOR> {
-    type Color = COLOR;
-    type Error = core::convert::Infallible;
+    /// Sets the chromatic bit of every pixel if `chromatic`, clears it otherwise, the black/white
+    /// plane is kept
+    pub fn clear_chromatic(&mut self, chromatic: bool) {
+        let buffer = self.buffer.as_mut();
+        let plane = buffer.len() / 2;
+        buffer[plane..].fill(if chromatic { 0xFF } else { 0x00 });
+        self.dirty = Some(Rect::new(0, 0, WIDTH, HEIGHT));
+    }
 
-    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
-    where
-        I: IntoIterator<Item = Pixel<Self::Color>>,
-    {
-        for pixel in pixels {
-            self.set_pixel(pixel);
-        }
-        Ok(())
+    /// Swaps black and white in `area` of the drawing, chromatic pixels stay as they are
+    ///
+    /// Like `Display<_, _, _, _, Color>::invert_area` otherwise.
+    pub fn invert_area(&mut self, area: Rectangle) {
+        self.invert(&area);
+    }
+
+    /// Returns the color of the pixel at `x`, `y` of the drawing, `None` if it's off the display
+    ///
+    /// A pixel with its chromatic bit set is `Chromatic`, whatever its black/white bit.
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<TriColor> {
+        let (bw, chromatic) = self.pixel_bits(x, y)?;
+        Some(match (bw, chromatic) {
+            (_, 1) => TriColor::Chromatic,
+            (1, _) => TriColor::White,
+            _ => TriColor::Black,
+        })
+    }
+
+    /// Iterates over the pixels of the drawing and their colors, line by line
+    pub fn pixels(&self) -> impl Iterator<Item = Pixel<TriColor>> + '_ {
+        self.bounding_box()
+            .points()
+            .filter_map(|p| Some(Pixel(p, self.get_pixel(p.x as u32, p.y as u32)?)))
     }
 }
 
-/// For use with embedded_grahics
-impl<COLOR: ColorType + PixelColor> OriginDimensions for VarDisplay<'_, COLOR> {
-    fn size(&self) -> Size {
-        match self.rotation {
-            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => {
-                Size::new(self.width, self.height)
-            }
-            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => {
-                Size::new(self.height, self.width)
+impl<
+        const WIDTH: u32,
+        const HEIGHT: u32,
+        const BWRBIT: bool,
+        const BYTECOUNT: usize,
+        BUFFER: AsRef<[u8]> + AsMut<[u8]>,
+        const INVERTED: bool,
+    > Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, Color, BUFFER, INVERTED>
+{
+    /// Inverts the pixels in `area` of the drawing, e.g. to highlight a selected row
+    ///
+    /// `area` follows the rotation and the mirroring like drawing does, the part of it off the
+    /// display is ignored. The bits of the buffer are flipped in place, whole bytes at once.
+    pub fn invert_area(&mut self, area: Rectangle) {
+        self.invert(&area);
+    }
+
+    /// Copies a 1 bit per pixel BMP image to `x`, `y` of the buffer, a line at a time
+    ///
+    /// Like `copy_from_slice` the image is in the layout of the panel, it's neither rotated nor
+    /// mirrored. `x` must be a multiple of 8. Bottom-up and top-down files are supported, and the
+    /// bits are inverted if the first color of the palette is the brighter one. The covered part
+    /// is marked dirty.
+    ///
+    /// Fails without touching the buffer if the file isn't an uncompressed 1 bit BMP or the image
+    /// doesn't fit.
+    pub fn draw_bmp(&mut self, data: &[u8], x: u32, y: u32) -> Result<(), BmpError> {
+        let bmp = Bmp::parse(data)?;
+        if x % 8 != 0 {
+            return Err(BmpError::Unaligned);
+        }
+        if x + bmp.width > WIDTH || y + bmp.height > HEIGHT {
+            return Err(BmpError::TooLarge);
+        }
+
+        let line = line_bytes(WIDTH, 1);
+        let len = line_bytes(bmp.width, 1);
+        // the bits of the last byte which are in the image
+        let last_mask = 0xFFu8 << ((8 - bmp.width % 8) % 8);
+        let flip = if bmp.inverted != INVERTED { 0xFF } else { 0x00 };
+        let buffer = self.buffer.as_mut();
+        for row in 0..bmp.height {
+            let from = bmp.row(row);
+            let start = (y + row) as usize * line + x as usize / 8;
+            let to = &mut buffer[start..start + len];
+            for (to, from) in to[..len - 1].iter_mut().zip(from) {
+                *to = from ^ flip;
             }
+            to[len - 1] = to[len - 1] & !last_mask | (from[len - 1] ^ flip) & last_mask;
         }
+        mark_dirty_rect(&mut self.dirty, Rect::new(x, y, bmp.width, bmp.height));
+        Ok(())
     }
-}
-
-/// Error found during usage of VarDisplay
-#[derive(Debug)]
-pub enum VarDisplayError {
-    /// The provided buffer was too small
-    BufferTooSmall,
-}
 
-impl<'a, COLOR: ColorType + PixelColor> VarDisplay<'a, COLOR> {
-    /// You must allocate the buffer by yourself, it must be large enough to contain all pixels.
+    /// Copies a packed 1 bit per pixel image of `width` pixels to `top_left` of the drawing, a
+    /// byte at a time
     ///
-    /// Parameters are documented in `Display` as they are the same as the const generics there.
-    /// bwrbit should be false for non tricolor displays
-    pub fn new(
+    /// The image holds its lines one after the other, 8 pixels per byte with the first one in the
+    /// most significant bit, and a set bit is white like in `Color::get_byte_value`. `top_left`
+    /// follows the rotation and the mirroring like drawing does, at `Rotate180` or mirrored the
+    /// lines or the bytes are copied in reverse. The part of the image off the display is
+    /// clipped and the covered part is marked dirty.
+    ///
+    /// Fails without touching the buffer if `top_left.x` or `width` isn't a multiple of 8, if a
+    /// turn or a mirror from left to right doesn't keep the bytes of a panel whose width isn't a
+    /// multiple of 8, if `data` doesn't hold whole lines or if the display is rotated by 90 or 270
+    /// degrees.
+    pub fn blit_packed(
+        &mut self,
+        data: &[u8],
         width: u32,
-        height: u32,
-        buffer: &'a mut [u8],
-        bwrbit: bool,
-    ) -> Result<Self, VarDisplayError> {
-        let myself = Self {
-            width,
-            height,
-            bwrbit,
-            buffer,
-            rotation: DisplayRotation::default(),
-            _color: PhantomData,
-        };
-        // enfore some constraints dynamicly
-        if myself.buffer_size() > myself.buffer.len() {
-            return Err(VarDisplayError::BufferTooSmall);
+        top_left: Point,
+    ) -> Result<(), BlitError> {
+        if matches!(
+            self.rotation,
+            DisplayRotation::Rotate90 | DisplayRotation::Rotate270
+        ) {
+            return Err(BlitError::Unsupported);
         }
-        Ok(myself)
+        let rotated = matches!(self.rotation, DisplayRotation::Rotate180);
+        let flip_x =
+            rotated != matches!(self.mirror, DisplayMirror::Horizontal | DisplayMirror::Both);
+        let flip_y =
+            rotated != matches!(self.mirror, DisplayMirror::Vertical | DisplayMirror::Both);
+        if width % 8 != 0 || top_left.x % 8 != 0 || (flip_x && WIDTH % 8 != 0) {
+            return Err(BlitError::Unaligned);
+        }
+        let len = width as usize / 8;
+        if len == 0 || data.len() % len != 0 {
+            return Err(BlitError::Malformed);
+        }
+
+        // the bytes of a line and the lines of the image which are on the display
+        let line = line_bytes(WIDTH, 1) as i32;
+        let (x, height) = (top_left.x / 8, (data.len() / len) as i32);
+        let columns = x.max(0)..(x + len as i32).min(line);
+        let rows = top_left.y.max(0)..(top_left.y + height).min(HEIGHT as i32);
+        if columns.is_empty() || rows.is_empty() {
+            return Ok(());
+        }
+
+        let flip = if INVERTED { 0xFF } else { 0x00 };
+        let buffer = self.buffer.as_mut();
+        for y in rows.clone() {
+            let from = (y - top_left.y) as usize * len;
+            let from =
+                &data[from + (columns.start - x) as usize..from + (columns.end - x) as usize];
+            let y = if flip_y { HEIGHT as i32 - 1 - y } else { y };
+            if flip_x {
+                let start = (y * line + line - columns.end) as usize;
+                let to = &mut buffer[start..start + from.len()];
+                for (to, from) in to.iter_mut().zip(from.iter().rev()) {
+                    *to = from.reverse_bits() ^ flip;
+                }
+            } else {
+                let start = (y * line + columns.start) as usize;
+                let to = &mut buffer[start..start + from.len()];
+                to.copy_from_slice(from);
+                if INVERTED {
+                    to.iter_mut().for_each(|byte| *byte = !*byte);
+                }
+            }
+        }
+
+        let area = Rectangle::new(
+            Point::new(columns.start * 8, rows.start),
+            Size::new(
+                (columns.end * 8).min(WIDTH as i32) as u32 - columns.start as u32 * 8,
+                rows.len() as u32,
+            ),
+        );
+        if let Some((_, rect)) = self.frame().buffer_rect(&area) {
+            mark_dirty_rect(&mut self.dirty, rect);
+        }
+        Ok(())
     }
 
-    /// get the number of used bytes in the buffer
-    fn buffer_size(&self) -> usize {
-        self.height as usize
-            * line_bytes(
-                self.width,
-                COLOR::BITS_PER_PIXEL_PER_BUFFER * COLOR::BUFFER_COUNT,
-            )
+    /// Returns the color of the pixel at `x`, `y` of the drawing, `None` if it's off the display
+    ///
+    /// The point follows the rotation and the mirroring like drawing does, so reading a pixel
+    /// returns what was drawn there.
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<Color> {
+        let (bit, _) = self.pixel_bits(x, y)?;
+        Some(Color::from(bit))
     }
 
-    /// get internal buffer to use it (to draw in epd)
-    pub fn buffer(&self) -> &[u8] {
-        &self.buffer[..self.buffer_size()]
+    /// Iterates over the pixels of the drawing and their colors, line by line
+    pub fn pixels(&self) -> impl Iterator<Item = Pixel<Color>> + '_ {
+        self.bounding_box()
+            .points()
+            .filter_map(|p| Some(Pixel(p, self.get_pixel(p.x as u32, p.y as u32)?)))
     }
+}
 
-    /// Set the display rotation.
+impl<const WIDTH: u32, const HEIGHT: u32, const BYTECOUNT: usize, BUFFER, const INVERTED: bool>
+    Display<WIDTH, HEIGHT, false, BYTECOUNT, Gray2, BUFFER, INVERTED>
+where
+    BUFFER: AsRef<[u8]> + AsMut<[u8]>,
+{
+    /// Inverts the gray levels in `area` of the drawing, black becomes white and dark gray light
+    /// gray
     ///
-    /// This only concerns future drawing made to it. Anything aready drawn
-    /// stays as it is in the buffer.
-    pub fn set_rotation(&mut self, rotation: DisplayRotation) {
-        self.rotation = rotation;
+    /// Like `Display<_, _, _, _, Color>::invert_area` otherwise.
+    pub fn invert_area(&mut self, area: Rectangle) {
+        self.invert(&area);
     }
 
-    /// Get current rotation
-    pub fn rotation(&self) -> DisplayRotation {
-        self.rotation
+    /// Returns the gray level of the pixel at `x`, `y` of the drawing, `None` if it's off the
+    /// display
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<Gray2> {
+        let (luma, _) = self.pixel_bits(x, y)?;
+        Some(Gray2::new(luma))
     }
 
-    /// Set a specific pixel color on this display
-    pub fn set_pixel(&mut self, pixel: Pixel<COLOR>) {
-        let size = self.buffer_size();
-        set_pixel(
-            &mut self.buffer[..size],
-            self.width,
-            self.height,
-            self.rotation,
-            self.bwrbit,
-            pixel,
-        );
+    /// Iterates over the pixels of the drawing and their gray levels, line by line
+    pub fn pixels(&self) -> impl Iterator<Item = Pixel<Gray2>> + '_ {
+        self.bounding_box()
+            .points()
+            .filter_map(|p| Some(Pixel(p, self.get_pixel(p.x as u32, p.y as u32)?)))
+    }
+
+    /// Returns a `DrawTarget` for `Gray8` pixels, which are drawn as the nearest of the 4 gray
+    /// levels, see `Gray8Display`
+    pub fn gray8(&mut self, dither: bool) -> Gray8Display<'_, Self> {
+        Gray8Display::new(self).with_dither(dither)
+    }
+}
+
+/// Error of `Display::try_set_pixel`, the point is off the display
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutOfBoundsError {
+    /// The point of the pixel
+    pub point: Point,
+    /// The size of the drawing, with the rotation applied
+    pub size: Size,
+}
+
+/// Fails if `point` isn't on a drawing of `size`
+fn check_bounds(point: Point, size: Size) -> Result<(), OutOfBoundsError> {
+    if Rectangle::new(Point::zero(), size).contains(point) {
+        Ok(())
+    } else {
+        Err(OutOfBoundsError { point, size })
+    }
+}
+
+/// Error of `Display::blit_packed`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlitError {
+    /// The image doesn't start at or doesn't cover whole bytes of the buffer
+    Unaligned,
+    /// The data doesn't hold whole lines of the image
+    Malformed,
+    /// The display is rotated by 90 or 270 degrees, the lines of the image would be columns of
+    /// the buffer
+    Unsupported,
+}
+
+/// Error of `Display::draw_bmp`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BmpError {
+    /// The data isn't a BMP file or it ends early
+    Malformed,
+    /// The file is compressed, has more than 1 bit per pixel or an old header
+    Unsupported,
+    /// The image doesn't start at a byte of the buffer
+    Unaligned,
+    /// The image doesn't fit on the display at the position
+    TooLarge,
+}
+
+/// The pixel data of a 1 bit per pixel BMP file, see `Display::draw_bmp`
+struct Bmp<'a> {
+    pixels: &'a [u8],
+    width: u32,
+    height: u32,
+    /// The lines are stored from the top
+    top_down: bool,
+    /// The first color of the palette is brighter than the second one
+    inverted: bool,
+}
+
+impl<'a> Bmp<'a> {
+    fn parse(data: &'a [u8]) -> Result<Self, BmpError> {
+        let u16_at = |at: usize| {
+            data.get(at..at + 2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]))
+                .ok_or(BmpError::Malformed)
+        };
+        let u32_at = |at: usize| {
+            data.get(at..at + 4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .ok_or(BmpError::Malformed)
+        };
+
+        if data.get(..2) != Some(b"BM") {
+            return Err(BmpError::Malformed);
+        }
+        let offset = u32_at(10)? as usize;
+        let header = u32_at(14)? as usize;
+        // BITMAPINFOHEADER and its successors, the core header of OS/2 is left out
+        if header < 40 {
+            return Err(BmpError::Unsupported);
+        }
+        let width = u32_at(18)? as i32;
+        let height = u32_at(22)? as i32;
+        // uncompressed with 1 bit per pixel
+        if u16_at(28)? != 1 || u32_at(30)? != 0 {
+            return Err(BmpError::Unsupported);
+        }
+        if width <= 0 || height == 0 {
+            return Err(BmpError::Malformed);
+        }
+
+        // the palette follows the header, the colors take 4 bytes, blue first
+        let palette = 14 + header;
+        let luma = |at: usize| -> Result<u32, BmpError> {
+            let color = data.get(at..at + 3).ok_or(BmpError::Malformed)?;
+            Ok(29 * u32::from(color[0]) + 150 * u32::from(color[1]) + 77 * u32::from(color[2]))
+        };
+        let inverted = luma(palette)? > luma(palette + 4)?;
+
+        let (width, height) = (width as u32, height.unsigned_abs());
+        // the lines are padded to 4 bytes
+        let stride = (width as usize + 31) / 32 * 4;
+        let pixels = stride
+            .checked_mul(height as usize)
+            .and_then(|len| data.get(offset..)?.get(..len))
+            .ok_or(BmpError::Malformed)?;
+        Ok(Bmp {
+            pixels,
+            width,
+            height,
+            top_down: (u32_at(22)? as i32) < 0,
+            inverted,
+        })
+    }
+
+    /// The bytes of line `y`, counted from the top
+    fn row(&self, y: u32) -> &'a [u8] {
+        let stride = self.pixels.len() / self.height as usize;
+        let y = if self.top_down {
+            y
+        } else {
+            self.height - 1 - y
+        };
+        &self.pixels[y as usize * stride..(y as usize + 1) * stride]
+    }
+}
+
+/// Error of `Display::set_rotation_preserving`, a drawing on a panel which isn't square can't be
+/// turned by 90 or 270 degrees
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RotationError {
+    /// The size of the drawing, with the current rotation
+    pub size: Size,
+}
+
+/// Clockwise turns by 90 degrees of `rotation`
+fn quarter_turns(rotation: DisplayRotation) -> u8 {
+    match rotation {
+        DisplayRotation::Rotate0 => 0,
+        DisplayRotation::Rotate90 => 1,
+        DisplayRotation::Rotate180 => 2,
+        DisplayRotation::Rotate270 => 3,
+    }
+}
+
+/// Reverses the order of the pixels of `bits_per_pixel` bits in `byte`
+fn reverse_pixels(byte: u8, bits_per_pixel: usize) -> u8 {
+    match bits_per_pixel {
+        1 => byte.reverse_bits(),
+        2 => {
+            let byte = byte.reverse_bits();
+            (byte & 0xAA) >> 1 | (byte & 0x55) << 1
+        }
+        4 => byte.rotate_left(4),
+        _ => byte,
+    }
+}
+
+/// Error of `Display::window_buffer`, the window doesn't start at a byte of the buffer or isn't
+/// on the display
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowError {
+    /// The rejected window
+    pub window: Rect,
+}
+
+/// Error of `Display::copy_from_slice` and `Display::with_buffer`, the data didn't have the
+/// length of the buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferLengthError {
+    /// The length of the buffer, `BYTECOUNT`
+    pub expected: usize,
+    /// The length of the data
+    pub actual: usize,
+}
+
+/// Same as `Display`, except that its characteristics are defined at runtime.
+/// See display for documentation as everything is the same except that default
+/// is replaced by a `new` method.
+///
+/// - BUFFER: storage of the pixels, the borrowed `&mut [u8]` of [`VarDisplay::new`] by default
+///   or the boxed slice of an [`OwnedDisplay`]
+pub struct VarDisplay<'a, COLOR: ColorType + PixelColor, BUFFER = &'a mut [u8]> {
+    width: u32,
+    height: u32,
+    bwrbit: bool,
+    buffer: BUFFER,
+    rotation: DisplayRotation,
+    mirror: DisplayMirror,
+    dirty: Option<Rect>,
+    _color: PhantomData<COLOR>,
+    _buffer: PhantomData<&'a mut [u8]>,
+}
+
+/// For use with embedded_grahics
+impl<COLOR: ColorType + PixelColor, BUFFER: AsRef<[u8]> + AsMut<[u8]>> DrawTarget
+    for VarDisplay<'_, COLOR, BUFFER>
+{
+    type Color = COLOR;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let size = self.buffer_size();
+        let frame = Frame::new(
+            self.width,
+            self.height,
+            self.rotation,
+            self.mirror,
+            self.bwrbit,
+        );
+        for pixel in pixels {
+            if let Some((x, y)) = frame.set_pixel(&mut self.buffer.as_mut()[..size], pixel) {
+                mark_dirty(&mut self.dirty, x, y);
+            }
+        }
+        Ok(())
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        let size = self.buffer_size();
+        let frame = Frame::new(
+            self.width,
+            self.height,
+            self.rotation,
+            self.mirror,
+            self.bwrbit,
+        );
+        if let Some(rect) = frame.fill_contiguous(&mut self.buffer.as_mut()[..size], area, colors) {
+            mark_dirty_rect(&mut self.dirty, rect);
+        }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let size = self.buffer_size();
+        let frame = Frame::new(
+            self.width,
+            self.height,
+            self.rotation,
+            self.mirror,
+            self.bwrbit,
+        );
+        if let Some(rect) = frame.fill_solid(&mut self.buffer.as_mut()[..size], area, color) {
+            mark_dirty_rect(&mut self.dirty, rect);
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        let size = self.buffer_size();
+        let frame = Frame::new(
+            self.width,
+            self.height,
+            self.rotation,
+            self.mirror,
+            self.bwrbit,
+        );
+        let rect = frame.clear(&mut self.buffer.as_mut()[..size], color);
+        mark_dirty_rect(&mut self.dirty, rect);
+        Ok(())
+    }
+}
+
+/// For use with embedded_grahics
+impl<COLOR: ColorType + PixelColor, BUFFER> OriginDimensions for VarDisplay<'_, COLOR, BUFFER> {
+    fn size(&self) -> Size {
+        match self.rotation {
+            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => {
+                Size::new(self.width, self.height)
+            }
+            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => {
+                Size::new(self.height, self.width)
+            }
+        }
+    }
+}
+
+/// Error found during usage of VarDisplay
+#[derive(Debug)]
+pub enum VarDisplayError {
+    /// The provided buffer was too small
+    BufferTooSmall,
+    /// The provided buffer was larger than both planes of a two buffer color like `TriColor`,
+    /// the planes wouldn't be its halves
+    BufferTooLarge,
+}
+
+impl<'a, COLOR: ColorType + PixelColor> VarDisplay<'a, COLOR> {
+    /// You must allocate the buffer by yourself, it must be large enough to contain all pixels.
+    /// For colors with two buffers, like `TriColor`, it must hold exactly both planes, i.e.
+    /// `2 * buffer_len(width, height)` bytes, the first half is the black/white plane.
+    ///
+    /// Parameters are documented in `Display` as they are the same as the const generics there.
+    /// bwrbit should be false for non tricolor displays
+    pub fn new(
+        width: u32,
+        height: u32,
+        buffer: &'a mut [u8],
+        bwrbit: bool,
+    ) -> Result<Self, VarDisplayError> {
+        let myself = Self::with_storage(width, height, buffer, bwrbit);
+        // enfore some constraints dynamicly
+        if myself.buffer_size() > myself.buffer.len() {
+            return Err(VarDisplayError::BufferTooSmall);
+        }
+        if COLOR::BUFFER_COUNT == 2 && myself.buffer_size() < myself.buffer.len() {
+            return Err(VarDisplayError::BufferTooLarge);
+        }
+        Ok(myself)
+    }
+}
+
+impl<COLOR: ColorType + PixelColor, BUFFER: AsRef<[u8]> + AsMut<[u8]>>
+    VarDisplay<'_, COLOR, BUFFER>
+{
+    fn with_storage(width: u32, height: u32, buffer: BUFFER, bwrbit: bool) -> Self {
+        VarDisplay {
+            width,
+            height,
+            bwrbit,
+            buffer,
+            rotation: DisplayRotation::default(),
+            mirror: DisplayMirror::default(),
+            dirty: None,
+            _color: PhantomData,
+            _buffer: PhantomData,
+        }
+    }
+
+    /// get the number of used bytes in the buffer
+    fn buffer_size(&self) -> usize {
+        // every plane pads its lines on its own
+        self.height as usize
+            * line_bytes(self.width, COLOR::BITS_PER_PIXEL_PER_BUFFER)
+            * COLOR::BUFFER_COUNT
+    }
+
+    /// get internal buffer to use it (to draw in epd)
+    pub fn buffer(&self) -> &[u8] {
+        &self.buffer.as_ref()[..self.buffer_size()]
+    }
+
+    /// Set the display rotation.
+    ///
+    /// This only concerns future drawing made to it. Anything aready drawn
+    /// stays as it is in the buffer.
+    pub fn set_rotation(&mut self, rotation: DisplayRotation) {
+        self.rotation = rotation;
+    }
+
+    /// Get current rotation
+    pub fn rotation(&self) -> DisplayRotation {
+        self.rotation
+    }
+
+    /// Set the display mirroring.
+    ///
+    /// Like the rotation this only concerns future drawing made to it.
+    pub fn set_mirror(&mut self, mirror: DisplayMirror) {
+        self.mirror = mirror;
+    }
+
+    /// Get current mirroring
+    pub fn mirror(&self) -> DisplayMirror {
+        self.mirror
+    }
+
+    /// Set a specific pixel color on this display
+    pub fn set_pixel(&mut self, pixel: Pixel<COLOR>) {
+        let size = self.buffer_size();
+        if let Some((x, y)) = set_pixel(
+            &mut self.buffer.as_mut()[..size],
+            self.width,
+            self.height,
+            self.rotation,
+            self.mirror,
+            self.bwrbit,
+            pixel,
+        ) {
+            mark_dirty(&mut self.dirty, x, y);
+        }
+    }
+
+    /// Sets a pixel or fails if it's off the display, see [`Display::try_set_pixel`]
+    pub fn try_set_pixel(&mut self, pixel: Pixel<COLOR>) -> Result<(), OutOfBoundsError> {
+        check_bounds(pixel.0, self.size())?;
+        self.set_pixel(pixel);
+        Ok(())
+    }
+
+    /// Returns the bounding box of the pixels set since the last call and starts over, see
+    /// [`Display::take_dirty_rect`]
+    pub fn take_dirty_rect(&mut self) -> Option<Rect> {
+        self.dirty.take()
+    }
+
+    /// Returns a `DrawTarget` for the part of `area` on the display, see [`Display::cropped`]
+    pub fn cropped(&mut self, area: Rectangle) -> CroppedDisplay<'_, Self> {
+        CroppedDisplay::new(self, area)
+    }
+
+    /// Moves the drawing `dx` pixels to the right, see [`Display::scroll_horizontal`]
+    pub fn scroll_horizontal(&mut self, dx: i32, fill: COLOR) {
+        self.scroll(dx, 0, fill);
+    }
+
+    /// Moves the drawing `dy` pixels down, see [`Display::scroll_vertical`]
+    pub fn scroll_vertical(&mut self, dy: i32, fill: COLOR) {
+        self.scroll(0, dy, fill);
+    }
+
+    fn scroll(&mut self, dx: i32, dy: i32, fill: COLOR) {
+        let size = self.buffer_size();
+        let frame = Frame::new(
+            self.width,
+            self.height,
+            self.rotation,
+            self.mirror,
+            self.bwrbit,
+        );
+        if let Some(rect) = frame.scroll(&mut self.buffer.as_mut()[..size], dx, dy, fill) {
+            mark_dirty_rect(&mut self.dirty, rect);
+        }
     }
 
     /// Creates a virtual partial frame
     /// Handles byte-alignment for you and keeps the full display buffer in sync
+    #[cfg(feature = "alloc")]
     pub fn get_partial_frame<'b>(
         &'b mut self,
         x: u32,
@@ -333,7 +1291,7 @@ impl<'a, COLOR: ColorType + PixelColor> VarDisplay<'a, COLOR> {
             y,
             width,
             height,
-            &mut self.buffer,
+            self.buffer.as_mut(),
             self.width,
             buffer_size,
             self.bwrbit,
@@ -341,16 +1299,615 @@ impl<'a, COLOR: ColorType + PixelColor> VarDisplay<'a, COLOR> {
     }
 }
 
-/// Some Tricolor specifics
-impl VarDisplay<'_, TriColor> {
-    /// get black/white internal buffer to use it (to draw in epd)
-    pub fn bw_buffer(&self) -> &[u8] {
-        &self.buffer[..self.buffer_size() / 2]
+/// Some Tricolor specifics
+impl<BUFFER: AsRef<[u8]> + AsMut<[u8]>> VarDisplay<'_, TriColor, BUFFER> {
+    /// get black/white internal buffer to use it (to draw in epd)
+    pub fn bw_buffer(&self) -> &[u8] {
+        &self.buffer.as_ref()[..self.buffer_size() / 2]
+    }
+
+    /// get chromatic internal buffer to use it (to draw in epd)
+    pub fn chromatic_buffer(&self) -> &[u8] {
+        &self.buffer.as_ref()[self.buffer_size() / 2..self.buffer_size()]
+    }
+
+    /// Sets every pixel of the black/white plane to `color`, see [`Display::clear_bw`]
+    pub fn clear_bw(&mut self, color: Color) {
+        let plane = self.buffer_size() / 2;
+        self.buffer.as_mut()[..plane].fill(color.get_byte_value());
+        self.dirty = Some(Rect::new(0, 0, self.width, self.height));
+    }
+
+    /// Sets or clears the chromatic bit of every pixel, see [`Display::clear_chromatic`]
+    pub fn clear_chromatic(&mut self, chromatic: bool) {
+        let (plane, size) = (self.buffer_size() / 2, self.buffer_size());
+        self.buffer.as_mut()[plane..size].fill(if chromatic { 0xFF } else { 0x00 });
+        self.dirty = Some(Rect::new(0, 0, self.width, self.height));
+    }
+}
+
+/// `VarDisplay` with its buffer on the heap, for a display whose size is only known at runtime
+///
+/// It dereferences to the `VarDisplay` for its functions, draws like it and its `buffer()` is
+/// passed to the drivers the same way, e.g.
+/// `epd.update_frame(&mut spi, display.buffer(), &mut delay)`. `Display::new_boxed`, e.g.
+/// `Display7in5::new_boxed()`, allocates the display of a panel whose size is known at compile
+/// time.
+#[cfg(feature = "alloc")]
+pub struct OwnedDisplay<COLOR: ColorType + PixelColor> {
+    display: VarDisplay<'static, COLOR, Box<[u8]>>,
+}
+
+#[cfg(feature = "alloc")]
+impl<COLOR: ColorType + PixelColor> OwnedDisplay<COLOR> {
+    /// Allocates a display of `width` x `height` pixels, filled with the color '0' like
+    /// `Display::default`
+    ///
+    /// The black/white bit of chromatic pixels is cleared, see
+    /// [`with_bwrbit`](Self::with_bwrbit) for the panels which need it set.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self::with_bwrbit(width, height, false)
+    }
+
+    /// Allocates a display like [`new`](Self::new) with the `bwrbit` of `Display`
+    pub fn with_bwrbit(width: u32, height: u32, bwrbit: bool) -> Self {
+        let mut display = VarDisplay::with_storage(width, height, Box::default(), bwrbit);
+        display.buffer = vec![0u8; display.buffer_size()].into_boxed_slice();
+        OwnedDisplay { display }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<COLOR: ColorType + PixelColor> core::ops::Deref for OwnedDisplay<COLOR> {
+    type Target = VarDisplay<'static, COLOR, Box<[u8]>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.display
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<COLOR: ColorType + PixelColor> core::ops::DerefMut for OwnedDisplay<COLOR> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.display
+    }
+}
+
+/// For use with embedded_grahics
+#[cfg(feature = "alloc")]
+impl<COLOR: ColorType + PixelColor> DrawTarget for OwnedDisplay<COLOR> {
+    type Color = COLOR;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.display.draw_iter(pixels)
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        self.display.fill_contiguous(area, colors)
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.display.fill_solid(area, color)
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.display.clear(color)
+    }
+}
+
+/// For use with embedded_grahics
+#[cfg(feature = "alloc")]
+impl<COLOR: ColorType + PixelColor> OriginDimensions for OwnedDisplay<COLOR> {
+    fn size(&self) -> Size {
+        self.display.size()
+    }
+}
+
+/// Renders `drawable` on a `width` x `height` frame as bands of rows, which only need the memory
+/// of `strip`
+///
+/// Each band takes as many rows as fit into `strip`. It's cleared to `background`, the part of
+/// `drawable` which falls into it is drawn, and its buffer is passed to `write`, from the top band
+/// to the bottom one. The bytes handed to `write` add up to the buffer of a full `Display`, so
+/// they can be streamed to a driver, e.g. `Epd7in5::write_frame_chunk`.
+///
+/// Only colors with a single buffer are supported and the drawing isn't rotated. Panics if
+/// `strip` can't hold a single row.
+pub fn draw_in_bands<COLOR, D, E>(
+    width: u32,
+    height: u32,
+    strip: &mut [u8],
+    background: COLOR,
+    drawable: &D,
+    write: impl FnMut(&[u8]) -> Result<(), E>,
+) -> Result<(), E>
+where
+    COLOR: ColorType + PixelColor,
+    D: Drawable<Color = COLOR>,
+{
+    let band_height = (strip.len() / line_bytes(width, COLOR::BITS_PER_PIXEL_PER_BUFFER)) as u32;
+    assert!(band_height > 0, "the strip must hold at least one row");
+    render_in_bands(
+        width,
+        height,
+        band_height,
+        strip,
+        background,
+        |band| {
+            let _ = drawable.draw(band);
+        },
+        write,
+    )
+}
+
+/// Renders the scene drawn by `draw` on a `width` x `height` frame as bands of `band_height` rows,
+/// like `draw_in_bands`
+///
+/// `draw` is called once per band and draws the whole scene in the coordinates of the frame, the
+/// `BandDisplay` drops everything outside of the band. Filled rectangles and images are clipped
+/// to the band before they are written, so drawing the scene again for every band mostly costs
+/// the pixels inside of it. The band is cleared to `background` before `draw` and passed to
+/// `write` after it, the last band may have fewer rows.
+///
+/// Only colors with a single buffer are supported and the drawing isn't rotated. Panics if
+/// `band_height` is 0 or `strip` can't hold `band_height` rows.
+pub fn render_in_bands<COLOR, E>(
+    width: u32,
+    height: u32,
+    band_height: u32,
+    strip: &mut [u8],
+    background: COLOR,
+    mut draw: impl FnMut(&mut BandDisplay<'_, COLOR>),
+    mut write: impl FnMut(&[u8]) -> Result<(), E>,
+) -> Result<(), E>
+where
+    COLOR: ColorType + PixelColor,
+{
+    assert_eq!(COLOR::BUFFER_COUNT, 1, "the color must use a single buffer");
+    assert!(band_height > 0, "the band must hold at least one row");
+    let row_bytes = line_bytes(width, COLOR::BITS_PER_PIXEL_PER_BUFFER);
+    assert!(
+        strip.len() >= row_bytes * band_height as usize,
+        "the strip must hold the band"
+    );
+
+    let mut y = 0;
+    while y < height {
+        let rows = band_height.min(height - y);
+        let mut band = BandDisplay {
+            display: VarDisplay::new(width, rows, &mut *strip, false)
+                .expect("the strip holds the band"),
+            y: y as i32,
+            height,
+        };
+        let _ = band.display.clear(background);
+        draw(&mut band);
+        write(band.display.buffer())?;
+        y += rows;
+    }
+    Ok(())
+}
+
+/// Rows `y..y + rows` of a frame, the `DrawTarget` of `render_in_bands`
+///
+/// It has the size of the whole frame, points are in its coordinates.
+pub struct BandDisplay<'a, COLOR: ColorType + PixelColor> {
+    display: VarDisplay<'a, COLOR>,
+    y: i32,
+    height: u32,
+}
+
+impl<COLOR: ColorType + PixelColor> BandDisplay<'_, COLOR> {
+    /// The rows of the frame in the band, e.g. to skip the parts of the scene outside of it
+    pub fn rows(&self) -> core::ops::Range<u32> {
+        self.y as u32..self.y as u32 + self.display.height
+    }
+
+    /// Moves `area` of the frame to the band
+    fn to_band(&self, area: &Rectangle) -> Rectangle {
+        Rectangle::new(area.top_left - Point::new(0, self.y), area.size)
+    }
+}
+
+impl<COLOR: ColorType + PixelColor> DrawTarget for BandDisplay<'_, COLOR> {
+    type Color = COLOR;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        // pixels outside of the band are dropped by the display
+        let offset = Point::new(0, self.y);
+        self.display.draw_iter(
+            pixels
+                .into_iter()
+                .map(|Pixel(point, color)| Pixel(point - offset, color)),
+        )
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        // the display skips the colors of the rows outside of the band without drawing them
+        let area = self.to_band(area);
+        self.display.fill_contiguous(&area, colors)
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let area = self.to_band(area);
+        self.display.fill_solid(&area, color)
+    }
+}
+
+impl<COLOR: ColorType + PixelColor> OriginDimensions for BandDisplay<'_, COLOR> {
+    fn size(&self) -> Size {
+        Size::new(self.display.width, self.height)
+    }
+}
+
+/// A rectangle of a display, as returned by `Display::cropped`
+///
+/// Its coordinates start at the top left corner of the rectangle and anything drawn outside of it
+/// is dropped. The drawing goes through the parent, so it follows its rotation and mirroring and
+/// grows its dirty rectangle only by what is drawn.
+pub struct CroppedDisplay<'a, D> {
+    parent: &'a mut D,
+    area: Rectangle,
+}
+
+impl<'a, D: DrawTarget> CroppedDisplay<'a, D> {
+    /// Restricts `parent` to the part of `area` which is on it
+    pub fn new(parent: &'a mut D, area: Rectangle) -> Self {
+        let area = area.intersection(&parent.bounding_box());
+        Self { parent, area }
+    }
+
+    /// The rectangle of the parent drawn to
+    pub fn area(&self) -> Rectangle {
+        self.area
+    }
+}
+
+impl<D: DrawTarget> DrawTarget for CroppedDisplay<'_, D> {
+    type Color = D::Color;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let bounds = self.bounding_box();
+        let offset = self.area.top_left;
+        self.parent.draw_iter(
+            pixels
+                .into_iter()
+                .filter(|Pixel(point, _)| bounds.contains(*point))
+                .map(|Pixel(point, color)| Pixel(point + offset, color)),
+        )
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        let bounds = self.bounding_box();
+        if area.intersection(&bounds) == *area {
+            let area = Rectangle::new(area.top_left + self.area.top_left, area.size);
+            self.parent.fill_contiguous(&area, colors)
+        } else {
+            let pixels = area.points().zip(colors).map(|(p, c)| Pixel(p, c));
+            self.draw_iter(pixels)
+        }
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let area = area.intersection(&self.bounding_box());
+        if area.is_zero_sized() {
+            return Ok(());
+        }
+        let area = Rectangle::new(area.top_left + self.area.top_left, area.size);
+        self.parent.fill_solid(&area, color)
+    }
+}
+
+impl<D> OriginDimensions for CroppedDisplay<'_, D> {
+    fn size(&self) -> Size {
+        self.area.size
+    }
+}
+
+/// A `Display` which keeps the frame last sent to the panel, for the `QuickRefresh` drivers
+///
+/// It draws like the display it holds. `swap` compares the drawing with the previous frame and
+/// returns both with the window which changed, which is what `update_partial_old_frame` and
+/// `update_partial_new_frame` take:
+///
+/// ```rust, ignore
+/// let diff = display.swap();
+/// if let Some(window) = diff.changed {
+///     let (old, new) = (diff.previous_window(), diff.current_window());
+///     let (x, y, w, h) = (window.x, window.y, window.w, window.h);
+///     epd.update_partial_old_frame(&mut spi, &mut delay, &old, x, y, w, h)?;
+///     epd.update_partial_new_frame(&mut spi, &mut delay, &new, x, y, w, h)?;
+///     epd.display_new_frame(&mut spi, &mut delay)?;
+/// }
+/// ```
+///
+/// It holds two buffers of `BYTECOUNT` bytes. The frames are compared a byte at a time, the window
+/// is made of whole bytes of a line and is in the coordinates of the buffer, like
+/// `Display::take_dirty_rect`.
+pub struct DoubleBufferedDisplay<
+    const WIDTH: u32,
+    const HEIGHT: u32,
+    const BWRBIT: bool,
+    const BYTECOUNT: usize,
+    COLOR: ColorType + PixelColor,
+    const INVERTED: bool = false,
+> {
+    display: Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR, [u8; BYTECOUNT], INVERTED>,
+    previous: [u8; BYTECOUNT],
+    /// The last swapped frame isn't copied to `previous` yet, which is done before drawing again
+    swapped: bool,
+}
+
+impl<
+        const WIDTH: u32,
+        const HEIGHT: u32,
+        const BWRBIT: bool,
+        const BYTECOUNT: usize,
+        COLOR: ColorType + PixelColor,
+        const INVERTED: bool,
+    > Default for DoubleBufferedDisplay<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR, INVERTED>
+{
+    /// Both frames are filled with the color '0', like `Display::default`
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new(Display::default())
+    }
+}
+
+impl<
+        const WIDTH: u32,
+        const HEIGHT: u32,
+        const BWRBIT: bool,
+        const BYTECOUNT: usize,
+        COLOR: ColorType + PixelColor,
+        const INVERTED: bool,
+    > DoubleBufferedDisplay<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR, INVERTED>
+{
+    /// Draws on top of `display`, whose frame is taken as the one on the panel
+    pub fn new(
+        display: Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR, [u8; BYTECOUNT], INVERTED>,
+    ) -> Self {
+        let previous = display.buffer;
+        Self {
+            display,
+            previous,
+            swapped: false,
+        }
+    }
+
+    /// The display drawn on
+    pub fn display(
+        &self,
+    ) -> &Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR, [u8; BYTECOUNT], INVERTED> {
+        &self.display
+    }
+
+    /// The display drawn on, e.g. to rotate it
+    pub fn display_mut(
+        &mut self,
+    ) -> &mut Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR, [u8; BYTECOUNT], INVERTED> {
+        self.sync();
+        &mut self.display
+    }
+
+    /// Compares the drawing with the previous frame, the drawing becomes the previous frame of
+    /// the next call
+    pub fn swap(&mut self) -> FrameDiff<'_> {
+        self.sync();
+        let changed = self.changed_window();
+        self.swapped = true;
+        FrameDiff {
+            previous: &self.previous,
+            current: self.display.buffer(),
+            changed,
+            #[cfg(feature = "alloc")]
+            width: WIDTH,
+            #[cfg(feature = "alloc")]
+            window_bytes: window_bytes::<COLOR>,
+        }
+    }
+
+    /// Copies the frame of the last `swap` to `previous`
+    fn sync(&mut self) {
+        if self.swapped {
+            self.previous = self.display.buffer;
+            self.swapped = false;
+        }
+    }
+
+    /// The bytes which differ between both frames, in pixels
+    fn changed_window(&self) -> Option<Rect> {
+        let changed = changed_pixels::<COLOR>(&self.previous, self.display.buffer(), WIDTH)?;
+        Some(widen_to_bytes::<COLOR>(changed, WIDTH))
+    }
+}
+
+impl<
+        const WIDTH: u32,
+        const HEIGHT: u32,
+        const BWRBIT: bool,
+        const BYTECOUNT: usize,
+        COLOR: ColorType + PixelColor,
+        const INVERTED: bool,
+    > DrawTarget for DoubleBufferedDisplay<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR, INVERTED>
+{
+    type Color = COLOR;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.display_mut().draw_iter(pixels)
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        self.display_mut().fill_contiguous(area, colors)
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.display_mut().fill_solid(area, color)
+    }
+}
+
+impl<
+        const WIDTH: u32,
+        const HEIGHT: u32,
+        const BWRBIT: bool,
+        const BYTECOUNT: usize,
+        COLOR: ColorType + PixelColor,
+        const INVERTED: bool,
+    > OriginDimensions
+    for DoubleBufferedDisplay<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR, INVERTED>
+{
+    fn size(&self) -> Size {
+        self.display.size()
+    }
+}
+
+/// The frames compared by `DoubleBufferedDisplay::swap`
+pub struct FrameDiff<'a> {
+    /// The previous frame, for `update_old_frame`
+    pub previous: &'a [u8],
+    /// The new frame, for `update_new_frame`
+    pub current: &'a [u8],
+    /// The smallest window of whole bytes which holds all changes, `None` if nothing changed
+    pub changed: Option<Rect>,
+    #[cfg(feature = "alloc")]
+    width: u32,
+    #[cfg(feature = "alloc")]
+    window_bytes: fn(&[u8], u32, Rect) -> Vec<u8>,
+}
+
+#[cfg(feature = "alloc")]
+impl FrameDiff<'_> {
+    /// The `changed` window of the previous frame, for `update_partial_old_frame`
+    ///
+    /// Empty if nothing changed, see `Display::window_buffer` for the layout.
+    pub fn previous_window(&self) -> Vec<u8> {
+        self.window(self.previous)
+    }
+
+    /// The `changed` window of the new frame, for `update_partial_new_frame`
+    pub fn current_window(&self) -> Vec<u8> {
+        self.window(self.current)
+    }
+
+    fn window(&self, buffer: &[u8]) -> Vec<u8> {
+        match self.changed {
+            Some(window) => (self.window_bytes)(buffer, self.width, window),
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Draws `Gray8` pixels on a display with 4 gray levels, as returned by `Display::gray8`
+///
+/// Every pixel is drawn as the nearest gray level. With dithering, the level above or below is
+/// picked by the threshold of a 4x4 Bayer matrix at the position of the pixel, so a gradient
+/// turns into patterns instead of 4 bands.
+pub struct Gray8Display<'a, D> {
+    parent: &'a mut D,
+    dither: bool,
+}
+
+impl<'a, D: DrawTarget<Color = Gray2>> Gray8Display<'a, D> {
+    /// Draws on `parent`, without dithering
+    pub fn new(parent: &'a mut D) -> Self {
+        Self {
+            parent,
+            dither: false,
+        }
+    }
+
+    /// Turns the ordered dithering on or off
+    pub fn with_dither(self, dither: bool) -> Self {
+        Self { dither, ..self }
+    }
+}
+
+impl<D: DrawTarget<Color = Gray2>> DrawTarget for Gray8Display<'_, D> {
+    type Color = Gray8;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let dither = self.dither;
+        self.parent.draw_iter(
+            pixels
+                .into_iter()
+                .map(|Pixel(point, color)| Pixel(point, quantize(dither, point, color))),
+        )
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        let dither = self.dither;
+        let colors = area
+            .points()
+            .zip(colors)
+            .map(|(point, color)| quantize(dither, point, color));
+        self.parent.fill_contiguous(area, colors)
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        if self.dither {
+            self.fill_contiguous(area, core::iter::repeat(color))
+        } else {
+            self.parent
+                .fill_solid(area, quantize(false, area.top_left, color))
+        }
+    }
+}
+
+impl<D: Dimensions> Dimensions for Gray8Display<'_, D> {
+    fn bounding_box(&self) -> Rectangle {
+        self.parent.bounding_box()
     }
+}
 
-    /// get chromatic internal buffer to use it (to draw in epd)
-    pub fn chromatic_buffer(&self) -> &[u8] {
-        &self.buffer[self.buffer_size() / 2..self.buffer_size()]
+/// The gray level drawn for `color` at `point` of a `Gray8Display`
+fn quantize(dither: bool, point: Point, color: Gray8) -> Gray2 {
+    // the luma on the scale of the 4 levels, the remainder is between two levels
+    let scaled = u16::from(color.luma()) * 3;
+    let (level, remainder) = ((scaled / 255) as u8, scaled % 255);
+    let threshold = if dither {
+        crate::dither::BAYER[point.y.rem_euclid(4) as usize][point.x.rem_euclid(4) as usize]
+    } else {
+        128
+    };
+    if remainder >= u16::from(threshold) {
+        Gray2::new(level + 1)
+    } else {
+        Gray2::new(level)
     }
 }
 
@@ -358,6 +1915,7 @@ impl VarDisplay<'_, TriColor> {
 /// byte-aligned relative to the full display.
 /// See display for documentation as everything is the same except that default
 /// is replaced by a `new` method.
+#[cfg(feature = "alloc")]
 pub struct PartialFrame<'a, COLOR: ColorType + PixelColor> {
     original_x: u32,
     aligned_x: u32,
@@ -390,6 +1948,7 @@ pub struct PartialUpdateParameters<'a> {
 }
 
 /// For use with embedded_grahics
+#[cfg(feature = "alloc")]
 impl<COLOR: ColorType + PixelColor> DrawTarget for PartialFrame<'_, COLOR> {
     type Color = COLOR;
     type Error = core::convert::Infallible;
@@ -406,6 +1965,7 @@ impl<COLOR: ColorType + PixelColor> DrawTarget for PartialFrame<'_, COLOR> {
 }
 
 /// For use with embedded_grahics
+#[cfg(feature = "alloc")]
 impl<COLOR: ColorType + PixelColor> OriginDimensions for PartialFrame<'_, COLOR> {
     fn size(&self) -> Size {
         match self.rotation {
@@ -419,11 +1979,13 @@ impl<COLOR: ColorType + PixelColor> OriginDimensions for PartialFrame<'_, COLOR>
     }
 }
 
+#[cfg(feature = "alloc")]
 impl<'a, COLOR: ColorType + PixelColor> PartialFrame<'a, COLOR> {
     /// Creates a byte-aligned buffer for you, based on X-coordinate and height.
     ///
     /// Parameters are documented in `Display` as they are the same as the const generics there.
     /// bwrbit should be false for non tricolor displays
+    #[allow(clippy::too_many_arguments)]
     fn new(
         x: u32,
         y: u32,
@@ -506,6 +2068,7 @@ impl<'a, COLOR: ColorType + PixelColor> PartialFrame<'a, COLOR> {
             self.aligned_width,
             self.height,
             self.rotation,
+            DisplayMirror::None,
             self.bwrbit,
             pixel,
         );
@@ -566,6 +2129,7 @@ impl<'a, COLOR: ColorType + PixelColor> PartialFrame<'a, COLOR> {
 }
 
 /// Some Monochrome specifics
+#[cfg(feature = "alloc")]
 impl PartialFrame<'_, Color> {
     /// To be used as parameters for the [`crate::traits::WaveshareDisplay::update_partial_frame`] function.
     ///
@@ -585,6 +2149,7 @@ impl PartialFrame<'_, Color> {
 }
 
 /// Some Tricolor specifics
+#[cfg(feature = "alloc")]
 impl PartialFrame<'_, TriColor> {
     /// get black/white internal buffer to use it (to draw in epd)
     pub fn bw_buffer(&self) -> &[u8] {
@@ -626,6 +2191,7 @@ impl PartialFrame<'_, TriColor> {
 }
 
 /// Copy the leftmost `offset_pixels` bits from src to dst
+#[cfg(feature = "alloc")]
 fn copy_left_padding_bits(dst: &mut u8, src: u8, offset_pixels: u32) {
     if offset_pixels == 0 {
         return;
@@ -639,61 +2205,556 @@ fn copy_left_padding_bits(dst: &mut u8, src: u8, offset_pixels: u32) {
     *dst = (*dst & !padding_mask) | (src & padding_mask);
 }
 
-/// Copy the rightmost `offset_pixels` bits from src to dst
-fn copy_right_padding_bits(dst: &mut u8, src: u8, offset_pixels: u32) {
-    if offset_pixels == 0 {
-        return;
+/// Copy the rightmost `offset_pixels` bits from src to dst
+#[cfg(feature = "alloc")]
+fn copy_right_padding_bits(dst: &mut u8, src: u8, offset_pixels: u32) {
+    if offset_pixels == 0 {
+        return;
+    }
+
+    // Create mask for the padding bits (rightmost offset_pixels bits)
+    // For example, if offset_pixels = 3: mask = 0b00000111
+    let padding_mask = (1u8 << offset_pixels) - 1;
+
+    // Clear padding bits in dst and copy from src
+    *dst = (*dst & !padding_mask) | (src & padding_mask);
+}
+
+// This is a function to share code between `Display` and `VarDisplay`
+// It sets a specific pixel in a buffer to a given color.
+// The big number of parameters is due to the fact that it is an internal function to both
+// strctures.
+fn set_pixel<COLOR: ColorType + PixelColor>(
+    buffer: &mut [u8],
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+    mirror: DisplayMirror,
+    bwrbit: bool,
+    pixel: Pixel<COLOR>,
+) -> Option<(u32, u32)> {
+    Frame::new(width, height, rotation, mirror, bwrbit).set_pixel(buffer, pixel)
+}
+
+/// Geometry of a buffer, shared by the per pixel and the filling paths
+struct Frame {
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+    mirror: DisplayMirror,
+    bwrbit: bool,
+    /// The bits of the first plane are flipped, see `Display`
+    inverted: bool,
+}
+
+impl Frame {
+    fn new(
+        width: u32,
+        height: u32,
+        rotation: DisplayRotation,
+        mirror: DisplayMirror,
+        bwrbit: bool,
+    ) -> Self {
+        Self {
+            width,
+            height,
+            rotation,
+            mirror,
+            bwrbit,
+            inverted: false,
+        }
+    }
+
+    fn inverted(self, inverted: bool) -> Self {
+        Self { inverted, ..self }
+    }
+
+    /// Sets the pixel of the drawing, returns where it landed in the buffer or `None` if it's off
+    /// the display
+    fn set_pixel<COLOR: ColorType + PixelColor>(
+        &self,
+        buffer: &mut [u8],
+        pixel: Pixel<COLOR>,
+    ) -> Option<(u32, u32)> {
+        let Pixel(point, color) = pixel;
+        let (x, y) = self.buffer_point(point);
+
+        // Out of range check
+        if (x < 0) || (x >= self.width as i32) || (y < 0) || (y >= self.height as i32) {
+            // don't do anything in case of out of range
+            return None;
+        }
+
+        self.write_pixel(buffer, x as u32, y as u32, color);
+        Some((x as u32, y as u32))
+    }
+
+    /// Size of the drawing, which is swapped for 90 and 270 degrees
+    fn view_size(&self) -> Size {
+        match self.rotation {
+            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => {
+                Size::new(self.width, self.height)
+            }
+            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => {
+                Size::new(self.height, self.width)
+            }
+        }
+    }
+
+    /// Maps a point of the drawing to the buffer, the result may be out of range
+    fn buffer_point(&self, mut point: Point) -> (i32, i32) {
+        let (width, height) = (self.width as i32, self.height as i32);
+        let view = self.view_size();
+
+        // mirror in the rotated coordinates
+        if matches!(self.mirror, DisplayMirror::Horizontal | DisplayMirror::Both) {
+            point.x = view.width as i32 - 1 - point.x;
+        }
+        if matches!(self.mirror, DisplayMirror::Vertical | DisplayMirror::Both) {
+            point.y = view.height as i32 - 1 - point.y;
+        }
+
+        // final coordinates
+        match self.rotation {
+            // as i32 = never use more than 2 billion pixel per line or per column
+            DisplayRotation::Rotate0 => (point.x, point.y),
+            DisplayRotation::Rotate90 => (width - 1 - point.y, point.x),
+            DisplayRotation::Rotate180 => (width - 1 - point.x, height - 1 - point.y),
+            DisplayRotation::Rotate270 => (point.y, height - 1 - point.x),
+        }
+    }
+
+    /// Maps the part of `area` on the display to the buffer, `None` if nothing is left
+    fn buffer_rect(&self, area: &Rectangle) -> Option<(Rectangle, Rect)> {
+        let area = area.intersection(&Rectangle::new(Point::zero(), self.view_size()));
+        let (a, b) = (
+            self.buffer_point(area.top_left),
+            self.buffer_point(area.bottom_right()?),
+        );
+        let (x, y) = (a.0.min(b.0) as u32, a.1.min(b.1) as u32);
+        let rect = Rect::new(
+            x,
+            y,
+            a.0.max(b.0) as u32 - x + 1,
+            a.1.max(b.1) as u32 - y + 1,
+        );
+        Some((area, rect))
+    }
+
+    /// Maps a point of the buffer to the drawing, the reverse of `buffer_point`
+    fn view_point(&self, x: u32, y: u32) -> Point {
+        let (x, y) = (x as i32, y as i32);
+        let (width, height) = (self.width as i32, self.height as i32);
+        let view = self.view_size();
+        let mut point = match self.rotation {
+            DisplayRotation::Rotate0 => Point::new(x, y),
+            DisplayRotation::Rotate90 => Point::new(y, width - 1 - x),
+            DisplayRotation::Rotate180 => Point::new(width - 1 - x, height - 1 - y),
+            DisplayRotation::Rotate270 => Point::new(height - 1 - y, x),
+        };
+        if matches!(self.mirror, DisplayMirror::Horizontal | DisplayMirror::Both) {
+            point.x = view.width as i32 - 1 - point.x;
+        }
+        if matches!(self.mirror, DisplayMirror::Vertical | DisplayMirror::Both) {
+            point.y = view.height as i32 - 1 - point.y;
+        }
+        point
+    }
+
+    /// Maps `rect` of the buffer to the drawing, the reverse of `buffer_rect`
+    fn view_rect(&self, rect: Rect) -> Rectangle {
+        Rectangle::with_corners(
+            self.view_point(rect.x, rect.y),
+            self.view_point(rect.x + rect.w - 1, rect.y + rect.h - 1),
+        )
+    }
+
+    /// Returns the bits of the pixel at `x`, `y` of the buffer, in the first and in the second
+    /// plane
+    fn read_pixel<COLOR: ColorType>(&self, buffer: &[u8], x: u32, y: u32) -> (u8, u8) {
+        let bits_per_pixel = COLOR::BITS_PER_PIXEL_PER_BUFFER;
+        let index =
+            x as usize * bits_per_pixel / 8 + y as usize * line_bytes(self.width, bits_per_pixel);
+        // the first pixel of a byte is in its most significant bits
+        let shift = 8 - bits_per_pixel * (x as usize % (8 / bits_per_pixel) + 1);
+        let bits = |byte: u8| (byte >> shift) & ((1u16 << bits_per_pixel) - 1) as u8;
+        let flip = if self.inverted { 0xFF } else { 0x00 };
+        if COLOR::BUFFER_COUNT == 2 {
+            (
+                bits(buffer[index] ^ flip),
+                bits(buffer[index + buffer.len() / 2]),
+            )
+        } else {
+            (bits(buffer[index] ^ flip), 0)
+        }
+    }
+
+    /// Sets the bits of the pixel at `x`, `y` of the buffer in both planes, as returned by
+    /// `read_pixel`
+    fn write_bits<COLOR: ColorType>(&self, buffer: &mut [u8], x: u32, y: u32, bits: (u8, u8)) {
+        let bits_per_pixel = COLOR::BITS_PER_PIXEL_PER_BUFFER;
+        let index =
+            x as usize * bits_per_pixel / 8 + y as usize * line_bytes(self.width, bits_per_pixel);
+        let shift = 8 - bits_per_pixel * (x as usize % (8 / bits_per_pixel) + 1);
+        let mask = (((1u16 << bits_per_pixel) - 1) as u8) << shift;
+        let flip = if self.inverted { mask } else { 0x00 };
+        buffer[index] = buffer[index] & !mask | ((bits.0 << shift) ^ flip);
+        if COLOR::BUFFER_COUNT == 2 {
+            let index = index + buffer.len() / 2;
+            buffer[index] = buffer[index] & !mask | bits.1 << shift;
+        }
+    }
+
+    fn write_pixel<COLOR: ColorType + PixelColor>(
+        &self,
+        buffer: &mut [u8],
+        x: u32,
+        y: u32,
+        color: COLOR,
+    ) {
+        let index = x as usize * COLOR::BITS_PER_PIXEL_PER_BUFFER / 8
+            + y as usize * line_bytes(self.width, COLOR::BITS_PER_PIXEL_PER_BUFFER);
+        let (mask, mut bits) = color.bitmask(self.bwrbit, x);
+        if self.inverted {
+            bits ^= u16::from(!mask);
+        }
+
+        if COLOR::BUFFER_COUNT == 2 {
+            // split buffer is for tricolor displays that use 2 buffer for 2 bits per pixel
+            buffer[index] = buffer[index] & mask | (bits & 0xFF) as u8;
+            let index = index + buffer.len() / 2;
+            buffer[index] = buffer[index] & mask | (bits >> 8) as u8;
+        } else {
+            buffer[index] = buffer[index] & mask | bits as u8;
+        }
+    }
+
+    /// Returns a byte with `color` in every pixel, for the first and the second plane
+    fn fill_bytes<COLOR: ColorType + PixelColor>(&self, color: COLOR) -> (u8, u8) {
+        let pixels_per_byte = (8 / COLOR::BITS_PER_PIXEL_PER_BUFFER) as u32;
+        let (first, second) = (0..pixels_per_byte).fold((0u8, 0u8), |(first, second), pos| {
+            let (mask, bits) = color.bitmask(self.bwrbit, pos);
+            (
+                first & mask | (bits & 0xFF) as u8,
+                second & mask | (bits >> 8) as u8,
+            )
+        });
+        if self.inverted {
+            (!first, second)
+        } else {
+            (first, second)
+        }
+    }
+
+    /// Fills the whole buffer with `color`, the padding bits at the end of the lines included
+    ///
+    /// A uniform fill doesn't depend on the rotation and the mirroring, every plane is filled with
+    /// a single byte.
+    fn clear<COLOR: ColorType + PixelColor>(&self, buffer: &mut [u8], color: COLOR) -> Rect {
+        let (first, second) = self.fill_bytes(color);
+        if COLOR::BUFFER_COUNT == 2 {
+            let (bw, chromatic) = buffer.split_at_mut(buffer.len() / 2);
+            bw.fill(first);
+            chromatic.fill(second);
+        } else {
+            buffer.fill(first);
+        }
+        Rect::new(0, 0, self.width, self.height)
+    }
+
+    /// Fills `area` of the drawing with `color`, returns the filled part of the buffer
+    ///
+    /// Any rotation and mirroring turns a rectangle into a rectangle of the buffer, its lines are
+    /// written a byte at a time and only the pixels of the partial bytes at both ends one by one.
+    fn fill_solid<COLOR: ColorType + PixelColor>(
+        &self,
+        buffer: &mut [u8],
+        area: &Rectangle,
+        color: COLOR,
+    ) -> Option<Rect> {
+        let (_, rect) = self.buffer_rect(area)?;
+
+        let pixels_per_byte = (8 / COLOR::BITS_PER_PIXEL_PER_BUFFER) as u32;
+        let line = line_bytes(self.width, COLOR::BITS_PER_PIXEL_PER_BUFFER);
+        let plane = buffer.len() / 2;
+        let (first, second) = self.fill_bytes(color);
+
+        let end = rect.x + rect.w;
+        // the whole bytes between the partial ones at the ends
+        let bytes_start = (rect.x + pixels_per_byte - 1) / pixels_per_byte;
+        let bytes_end = (end / pixels_per_byte).max(bytes_start);
+        for y in rect.y..rect.y + rect.h {
+            let start = y as usize * line;
+            for x in (rect.x..end.min(bytes_start * pixels_per_byte))
+                .chain((bytes_end * pixels_per_byte).max(rect.x)..end)
+            {
+                self.write_pixel(buffer, x, y, color);
+            }
+            let bytes = start + bytes_start as usize..start + bytes_end as usize;
+            if COLOR::BUFFER_COUNT == 2 {
+                buffer[bytes.start + plane..bytes.end + plane].fill(second);
+            }
+            buffer[bytes].fill(first);
+        }
+        Some(rect)
+    }
+
+    /// Fills `area` of the drawing with `colors` line by line, returns the written part of the
+    /// buffer
+    ///
+    /// Rotating and mirroring keep the axes, so the buffer point of a pixel is the one of the
+    /// start of its line plus a fixed step per pixel, and only the start of a line is mapped.
+    fn fill_contiguous<COLOR: ColorType + PixelColor>(
+        &self,
+        buffer: &mut [u8],
+        area: &Rectangle,
+        colors: impl IntoIterator<Item = COLOR>,
+    ) -> Option<Rect> {
+        let (visible, rect) = self.buffer_rect(area)?;
+        let origin = self.buffer_point(visible.top_left);
+        let step = |point: Point| {
+            let (x, y) = self.buffer_point(visible.top_left + point);
+            (x - origin.0, y - origin.1)
+        };
+        let (along, across) = (step(Point::new(1, 0)), step(Point::new(0, 1)));
+
+        // the colors of the pixels before, after and between the visible lines are skipped
+        let width = area.size.width as usize;
+        let offset = visible.top_left - area.top_left;
+        let mut colors = colors.into_iter();
+        let mut skip = offset.y as usize * width + offset.x as usize;
+        for line in 0..visible.size.height as i32 {
+            if skip > 0 && colors.nth(skip - 1).is_none() {
+                break;
+            }
+            let (mut x, mut y) = (origin.0 + across.0 * line, origin.1 + across.1 * line);
+            for color in colors.by_ref().take(visible.size.width as usize) {
+                self.write_pixel(buffer, x as u32, y as u32, color);
+                x += along.0;
+                y += along.1;
+            }
+            skip = width - visible.size.width as usize;
+        }
+        Some(rect)
+    }
+
+    /// Flips the bits of the pixels in `area` of the drawing, returns the changed part of the
+    /// buffer
+    ///
+    /// Only the first plane is flipped, and only where the second plane is clear, so the black and
+    /// white of `TriColor` are swapped and its chromatic pixels kept.
+    fn invert<COLOR: ColorType>(&self, buffer: &mut [u8], area: &Rectangle) -> Option<Rect> {
+        let (_, rect) = self.buffer_rect(area)?;
+
+        let bits_per_pixel = COLOR::BITS_PER_PIXEL_PER_BUFFER;
+        let line = line_bytes(self.width, bits_per_pixel);
+        let plane = buffer.len() / 2;
+        let start = rect.x as usize * bits_per_pixel;
+        let end = (rect.x + rect.w) as usize * bits_per_pixel;
+        for y in rect.y..rect.y + rect.h {
+            for byte in start / 8..(end + 7) / 8 {
+                // the bits of the byte in the rectangle, the first pixel is the most significant
+                let first = start.saturating_sub(byte * 8);
+                let last = (end - byte * 8).min(8);
+                let mut mask = (0xFFu16 >> first) as u8 & !((0xFFu16 >> last) as u8);
+
+                let index = y as usize * line + byte;
+                if COLOR::BUFFER_COUNT == 2 {
+                    mask &= !buffer[index + plane];
+                }
+                buffer[index] ^= mask;
+            }
+        }
+        Some(rect)
+    }
+
+    /// Moves the drawing by `dx`, `dy` and fills the uncovered part with `fill`, returns the
+    /// changed part of the buffer
+    ///
+    /// The move is done on the buffer, whole lines with `copy_within` and the pixels of a line by
+    /// shifting its bits. Moving the drawing off the display fills all of it.
+    fn scroll<COLOR: ColorType + PixelColor>(
+        &self,
+        buffer: &mut [u8],
+        dx: i32,
+        dy: i32,
+        fill: COLOR,
+    ) -> Option<Rect> {
+        let view = self.view_size();
+        let all = Rectangle::new(Point::zero(), view);
+        if dx.unsigned_abs() >= view.width || dy.unsigned_abs() >= view.height {
+            return self.fill_solid(buffer, &all, fill);
+        }
+        if dx == 0 && dy == 0 {
+            return None;
+        }
+
+        // rotating and mirroring keep the axes, a move of the drawing is a move of the buffer
+        let origin = self.buffer_point(Point::zero());
+        let moved = self.buffer_point(Point::new(dx, dy));
+        let (bx, by) = (moved.0 - origin.0, moved.1 - origin.1);
+
+        let bits_per_pixel = COLOR::BITS_PER_PIXEL_PER_BUFFER;
+        let line = line_bytes(self.width, bits_per_pixel);
+        let plane_len = line * self.height as usize;
+        let planes = if COLOR::BUFFER_COUNT == 2 {
+            [0, buffer.len() / 2]
+        } else {
+            [0, 0]
+        };
+        for start in &planes[..COLOR::BUFFER_COUNT] {
+            let plane = &mut buffer[*start..*start + plane_len];
+            let rows = by.unsigned_abs() as usize * line;
+            if by > 0 {
+                plane.copy_within(..plane_len - rows, rows);
+            } else if by < 0 {
+                plane.copy_within(rows.., 0);
+            }
+            if bx != 0 {
+                let padding = line * 8 - self.width as usize * bits_per_pixel;
+                for row in plane.chunks_exact_mut(line) {
+                    shift_line(row, bx as isize * bits_per_pixel as isize, padding);
+                }
+            }
+        }
+
+        let (w, h) = (view.width as i32, view.height as i32);
+        let columns = match dx {
+            0 => None,
+            1.. => Some(Rectangle::new(
+                Point::zero(),
+                Size::new(dx as u32, view.height),
+            )),
+            _ => Some(Rectangle::new(
+                Point::new(w + dx, 0),
+                Size::new(dx.unsigned_abs(), view.height),
+            )),
+        };
+        let rows = match dy {
+            0 => None,
+            1.. => Some(Rectangle::new(
+                Point::zero(),
+                Size::new(view.width, dy as u32),
+            )),
+            _ => Some(Rectangle::new(
+                Point::new(0, h + dy),
+                Size::new(view.width, dy.unsigned_abs()),
+            )),
+        };
+        for area in columns.iter().chain(rows.iter()) {
+            self.fill_solid(buffer, area, fill);
+        }
+        self.buffer_rect(&all).map(|(_, rect)| rect)
+    }
+}
+
+/// Shifts the bits of `line` by `bits` towards its end, or towards its start if `bits` is
+/// negative, and keeps the last `padding` bits, which aren't on the display
+fn shift_line(line: &mut [u8], bits: isize, padding: usize) {
+    let last = line.len() - 1;
+    let kept = line[last] & ((1u16 << padding) - 1) as u8;
+    let (bytes, bits_in_byte) = (bits.unsigned_abs() / 8, (bits.unsigned_abs() % 8) as u32);
+    let byte = |line: &[u8], i: usize| line.get(i).copied().unwrap_or(0);
+    if bits > 0 {
+        for i in (0..line.len()).rev() {
+            line[i] = match i.checked_sub(bytes) {
+                Some(src) => {
+                    let carry = src.checked_sub(1).map_or(0, |prev| byte(line, prev));
+                    (line[src] >> bits_in_byte) | carry.checked_shl(8 - bits_in_byte).unwrap_or(0)
+                }
+                None => 0,
+            };
+        }
+    } else {
+        for i in 0..line.len() {
+            let src = i + bytes;
+            line[i] = (byte(line, src) << bits_in_byte)
+                | byte(line, src + 1)
+                    .checked_shr(8 - bits_in_byte)
+                    .unwrap_or(0);
+        }
     }
+    line[last] = line[last] & !(((1u16 << padding) - 1) as u8) | kept;
+}
 
-    // Create mask for the padding bits (rightmost offset_pixels bits)
-    // For example, if offset_pixels = 3: mask = 0b00000111
-    let padding_mask = (1u8 << offset_pixels) - 1;
-
-    // Clear padding bits in dst and copy from src
-    *dst = (*dst & !padding_mask) | (src & padding_mask);
+/// Returns the bounding box in the buffer of the pixels which differ between `a` and `b`, the
+/// buffers of a display of `width` pixels
+fn changed_pixels<COLOR: ColorType>(a: &[u8], b: &[u8], width: u32) -> Option<Rect> {
+    let bits_per_pixel = COLOR::BITS_PER_PIXEL_PER_BUFFER;
+    let line = line_bytes(width, bits_per_pixel);
+    let plane = a.len() / COLOR::BUFFER_COUNT;
+    // the bits of the last byte of a line which are on the display
+    let padding = line * 8 - width as usize * bits_per_pixel;
+    let last_mask = (0xFFu16 << padding) as u8;
+
+    let mut changed = None;
+    for (a, b) in a.chunks_exact(plane).zip(b.chunks_exact(plane)) {
+        let lines = a.chunks_exact(line).zip(b.chunks_exact(line));
+        for (y, (a, b)) in lines.enumerate() {
+            let differ = |i: usize| (a[i] ^ b[i]) & if i == line - 1 { last_mask } else { 0xFF };
+            if let Some(first) = (0..line).find(|&i| differ(i) != 0) {
+                let last = (first..line).rfind(|&i| differ(i) != 0).unwrap_or(first);
+                let start = first * 8 + differ(first).leading_zeros() as usize;
+                let end = last * 8 + 7 - differ(last).trailing_zeros() as usize;
+                let (start, end) = (start / bits_per_pixel, end / bits_per_pixel);
+                mark_dirty_rect(
+                    &mut changed,
+                    Rect::new(start as u32, y as u32, (end - start + 1) as u32, 1),
+                );
+            }
+        }
+    }
+    changed
 }
 
-// This is a function to share code between `Display` and `VarDisplay`
-// It sets a specific pixel in a buffer to a given color.
-// The big number of parameters is due to the fact that it is an internal function to both
-// strctures.
-fn set_pixel<COLOR: ColorType + PixelColor>(
-    buffer: &mut [u8],
-    width: u32,
-    height: u32,
-    rotation: DisplayRotation,
-    bwrbit: bool,
-    pixel: Pixel<COLOR>,
-) {
-    let Pixel(point, color) = pixel;
-
-    // final coordinates
-    let (x, y) = match rotation {
-        // as i32 = never use more than 2 billion pixel per line or per column
-        DisplayRotation::Rotate0 => (point.x, point.y),
-        DisplayRotation::Rotate90 => (width as i32 - 1 - point.y, point.x),
-        DisplayRotation::Rotate180 => (width as i32 - 1 - point.x, height as i32 - 1 - point.y),
-        DisplayRotation::Rotate270 => (point.y, height as i32 - 1 - point.x),
-    };
+/// Widens `rect` of a buffer of a display of `width` pixels to whole bytes of a line
+fn widen_to_bytes<COLOR: ColorType>(rect: Rect, width: u32) -> Rect {
+    let pixels_per_byte = (8 / COLOR::BITS_PER_PIXEL_PER_BUFFER) as u32;
+    let x = rect.x / pixels_per_byte * pixels_per_byte;
+    let end =
+        ((rect.x + rect.w + pixels_per_byte - 1) / pixels_per_byte * pixels_per_byte).min(width);
+    Rect::new(x, rect.y, end - x, rect.h)
+}
 
-    // Out of range check
-    if (x < 0) || (x >= width as i32) || (y < 0) || (y >= height as i32) {
-        // don't do anything in case of out of range
-        return;
+/// Copies the bytes of `window` out of `buffer`, a frame of a display `width` pixels wide, see
+/// `Display::window_buffer`
+///
+/// `window` has to start at a byte and lie on the display.
+#[cfg(feature = "alloc")]
+fn window_bytes<COLOR: ColorType>(buffer: &[u8], width: u32, window: Rect) -> Vec<u8> {
+    let bits_per_pixel = COLOR::BITS_PER_PIXEL_PER_BUFFER;
+    let line = line_bytes(width, bits_per_pixel);
+    let start = window.x as usize * bits_per_pixel / 8;
+    let len = line_bytes(window.w, bits_per_pixel);
+    let plane = buffer.len() / COLOR::BUFFER_COUNT;
+    let mut bytes = Vec::with_capacity(len * window.h as usize * COLOR::BUFFER_COUNT);
+    for offset in (0..COLOR::BUFFER_COUNT).map(|i| i * plane) {
+        for y in window.y..window.y + window.h {
+            let from = offset + y as usize * line + start;
+            bytes.extend_from_slice(&buffer[from..from + len]);
+        }
     }
+    bytes
+}
 
-    let index = x as usize * COLOR::BITS_PER_PIXEL_PER_BUFFER / 8
-        + y as usize * line_bytes(width, COLOR::BITS_PER_PIXEL_PER_BUFFER);
-    let (mask, bits) = color.bitmask(bwrbit, x as u32);
+/// Grows the dirty bounding box to include `rect`
+fn mark_dirty_rect(dirty: &mut Option<Rect>, rect: Rect) {
+    mark_dirty(dirty, rect.x, rect.y);
+    mark_dirty(dirty, rect.x + rect.w - 1, rect.y + rect.h - 1);
+}
 
-    if COLOR::BUFFER_COUNT == 2 {
-        // split buffer is for tricolor displays that use 2 buffer for 2 bits per pixel
-        buffer[index] = buffer[index] & mask | (bits & 0xFF) as u8;
-        let index = index + buffer.len() / 2;
-        buffer[index] = buffer[index] & mask | (bits >> 8) as u8;
-    } else {
-        buffer[index] = buffer[index] & mask | bits as u8;
-    }
+/// Grows the dirty bounding box to include the pixel at `x`, `y`
+fn mark_dirty(dirty: &mut Option<Rect>, x: u32, y: u32) {
+    *dirty = Some(match *dirty {
+        None => Rect::new(x, y, 1, 1),
+        Some(rect) => {
+            let x0 = rect.x.min(x);
+            let y0 = rect.y.min(y);
+            let x1 = (rect.x + rect.w).max(x + 1);
+            let y1 = (rect.y + rect.h).max(y + 1);
+            Rect::new(x0, y0, x1 - x0, y1 - y0)
+        }
+    });
 }
 
 #[cfg(test)]
@@ -797,7 +2858,7 @@ mod tests {
 
     #[test]
     fn graphics_set_pixel_tricolor_false() {
-        let mut display = Display::<4, 4, false, { 4 * 4 * 2 / 8 }, TriColor>::default();
+        let mut display = Display::<4, 4, false, { 4 * 2 }, TriColor>::default();
         display.set_pixel(Pixel(Point::new(0, 0), TriColor::White));
         display.set_pixel(Pixel(Point::new(1, 0), TriColor::Chromatic));
         display.set_pixel(Pixel(Point::new(2, 0), TriColor::Black));
@@ -809,13 +2870,14 @@ mod tests {
         std::println!("{:?}", bw_buffer);
         std::println!("{:?}", chromatic_buffer);
 
-        assert_eq!(bw_buffer, [192, 0]);
-        assert_eq!(chromatic_buffer, [64, 0]);
+        // every line is padded to a byte
+        assert_eq!(bw_buffer, [192, 0, 0, 0]);
+        assert_eq!(chromatic_buffer, [64, 0, 0, 0]);
     }
 
     #[test]
     fn graphics_set_pixel_tricolor_true() {
-        let mut display = Display::<4, 4, true, { 4 * 4 * 2 / 8 }, TriColor>::default();
+        let mut display = Display::<4, 4, true, { 4 * 2 }, TriColor>::default();
         display.set_pixel(Pixel(Point::new(0, 0), TriColor::White));
         display.set_pixel(Pixel(Point::new(1, 0), TriColor::Chromatic));
         display.set_pixel(Pixel(Point::new(2, 0), TriColor::Black));
@@ -827,7 +2889,1203 @@ mod tests {
         std::println!("{:?}", bw_buffer);
         std::println!("{:?}", chromatic_buffer);
 
-        assert_eq!(bw_buffer, [128, 0]);
-        assert_eq!(chromatic_buffer, [64, 0]);
+        assert_eq!(bw_buffer, [128, 0, 0, 0]);
+        assert_eq!(chromatic_buffer, [64, 0, 0, 0]);
+    }
+
+    #[test]
+    fn graphics_set_pixel_quadcolor() {
+        // 6 pixels at 2 bits per pixel: each line takes 2 bytes, the last 4 bits are padding
+        let mut display = Display::<6, 2, false, { 2 * 2 }, QuadColor>::default();
+        display.set_pixel(Pixel(Point::new(0, 0), QuadColor::White));
+        display.set_pixel(Pixel(Point::new(5, 0), QuadColor::Red));
+        display.set_pixel(Pixel(Point::new(0, 1), QuadColor::Yellow));
+
+        assert_eq!(display.buffer(), [0b01000000, 0b00110000, 0b10000000, 0]);
+    }
+
+    #[test]
+    fn quadcolor_at_every_position_of_a_byte() {
+        for x in 0..4 {
+            for color in [QuadColor::White, QuadColor::Yellow, QuadColor::Red] {
+                let mut display = Display::<8, 1, false, 2, QuadColor>::default();
+                display.set_pixel(Pixel(Point::new(x, 0), color));
+                let bits = color.get_bits() << (6 - 2 * x);
+                assert_eq!(display.buffer(), [bits, 0]);
+            }
+        }
+
+        // rotated by 90 degrees, the 8th line of the drawing is the first pixel of the panel
+        let mut display = Display::<8, 1, false, 2, QuadColor>::default();
+        display.set_rotation(DisplayRotation::Rotate90);
+        display.set_pixel(Pixel(Point::new(0, 7), QuadColor::Red));
+        assert_eq!(display.buffer(), [0b11000000, 0]);
+    }
+
+    #[test]
+    fn draw_in_bands_matches_a_full_display() {
+        let line = Line::new(Point::new(0, 0), Point::new(15, 4))
+            .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1));
+        let mut display = Display::<16, 5, false, { 2 * 5 }, Color>::default();
+        display.clear(Color::White).unwrap();
+        line.draw(&mut display).unwrap();
+
+        // two rows per band, the last band has one row
+        let mut strip = [0u8; 4];
+        let mut frame = Vec::new();
+        let mut bands = 0;
+        draw_in_bands(16, 5, &mut strip, Color::White, &line, |band| {
+            frame.extend_from_slice(band);
+            bands += 1;
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+        assert_eq!(bands, 3);
+        assert_eq!(frame, display.buffer());
+    }
+
+    fn band_scene<D: DrawTarget<Color = Color>>(target: &mut D) {
+        let _ = Line::new(Point::new(0, 0), Point::new(19, 7))
+            .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
+            .draw(target);
+        let _ = Rectangle::new(Point::new(5, 2), Size::new(7, 3))
+            .into_styled(PrimitiveStyle::with_fill(Color::Black))
+            .draw(target);
+        let pattern = (0..).map(|i| {
+            if i % 3 == 0 {
+                Color::Black
+            } else {
+                Color::White
+            }
+        });
+        let _ =
+            target.fill_contiguous(&Rectangle::new(Point::new(-2, 1), Size::new(9, 6)), pattern);
+    }
+
+    #[test]
+    fn render_in_bands_across_band_boundaries() {
+        // 20 pixels leave padding bits in every line, the bands split at the rows 3 and 6
+        let mut display = Display::<20, 8, false, { 3 * 8 }, Color>::default();
+        display.clear(Color::White).unwrap();
+        band_scene(&mut display);
+
+        let mut strip = [0u8; 3 * 3];
+        let mut frame = Vec::new();
+        let mut rows = Vec::new();
+        render_in_bands(
+            20,
+            8,
+            3,
+            &mut strip,
+            Color::White,
+            |band| {
+                rows.push(band.rows());
+                assert_eq!(band.size(), Size::new(20, 8));
+                band_scene(band);
+            },
+            |band| {
+                frame.extend_from_slice(band);
+                Ok::<_, ()>(())
+            },
+        )
+        .unwrap();
+        assert_eq!(rows, [0..3, 3..6, 6..8]);
+        assert_eq!(frame, display.buffer());
+    }
+
+    #[test]
+    fn dirty_rect_in_buffer_coordinates() {
+        let mut display = Display::<16, 8, false, { 2 * 8 }, Color>::default();
+        assert_eq!(display.take_dirty_rect(), None);
+
+        display.set_rotation(DisplayRotation::Rotate90);
+        let _ = Line::new(Point::new(1, 2), Point::new(3, 2))
+            .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
+            .draw(&mut display);
+        // (1, 2)..(3, 2) rotated by 90 degrees is the column x = 13 from y = 1 to 3
+        assert_eq!(display.take_dirty_rect(), Some(Rect::new(13, 1, 1, 3)));
+        assert_eq!(display.take_dirty_rect(), None);
+
+        display.clear(Color::White).unwrap();
+        assert_eq!(display.take_dirty_rect(), Some(Rect::new(0, 0, 16, 8)));
+    }
+
+    #[test]
+    fn import_a_buffer() {
+        let mut frame = [0u8; 16];
+        for (i, byte) in frame.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let mut display = Display::<16, 8, false, 16, Color>::default();
+        assert_eq!(
+            display.copy_from_slice(&frame[..15]),
+            Err(BufferLengthError {
+                expected: 16,
+                actual: 15
+            })
+        );
+        assert_eq!(display.buffer(), [0; 16]);
+        assert_eq!(display.take_dirty_rect(), None);
+
+        // the data isn't rotated, only the pixels drawn on top of it
+        display.set_rotation(DisplayRotation::Rotate180);
+        display.copy_from_slice(&frame).unwrap();
+        assert_eq!(display.buffer(), frame);
+        assert_eq!(display.take_dirty_rect(), Some(Rect::new(0, 0, 16, 8)));
+        display.set_pixel(Pixel(Point::new(0, 0), Color::White));
+        assert_eq!(display.buffer()[15], 15 | 0x01);
+
+        let mut owned = Display::<16, 8, false, 16, Color>::from_buffer(frame);
+        assert_eq!(owned.buffer(), frame);
+        assert_eq!(owned.take_dirty_rect(), Some(Rect::new(0, 0, 16, 8)));
+    }
+
+    #[test]
+    fn var_display_tricolor_planes() {
+        // 10 pixels are padded to 2 bytes per line
+        let mut buffer = [0u8; 2 * 2 * 3];
+        assert!(matches!(
+            VarDisplay::<TriColor>::new(10, 3, &mut buffer[..11], true),
+            Err(VarDisplayError::BufferTooSmall)
+        ));
+        let mut larger = [0u8; 13];
+        assert!(matches!(
+            VarDisplay::<TriColor>::new(10, 3, &mut larger, true),
+            Err(VarDisplayError::BufferTooLarge)
+        ));
+
+        let mut display = VarDisplay::<TriColor>::new(10, 3, &mut buffer, true).unwrap();
+        display.set_rotation(DisplayRotation::Rotate180);
+        // land on the last two pixels of the first line
+        display.set_pixel(Pixel(Point::new(0, 2), TriColor::Chromatic));
+        display.set_pixel(Pixel(Point::new(1, 2), TriColor::White));
+        assert_eq!(display.bw_buffer(), [0x00, 0x80, 0, 0, 0, 0]);
+        assert_eq!(display.chromatic_buffer(), [0x00, 0x40, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn mirror_with_every_rotation() {
+        use DisplayMirror::*;
+        use DisplayRotation::*;
+
+        // where the top left pixel of the drawing lands in the 8 x 4 buffer
+        let expected = [
+            (Rotate0, None, (0, 0)),
+            (Rotate0, Horizontal, (7, 0)),
+            (Rotate0, Vertical, (0, 3)),
+            (Rotate0, Both, (7, 3)),
+            (Rotate90, None, (7, 0)),
+            (Rotate90, Horizontal, (7, 3)),
+            (Rotate90, Vertical, (0, 0)),
+            (Rotate90, Both, (0, 3)),
+            (Rotate180, None, (7, 3)),
+            (Rotate180, Horizontal, (0, 3)),
+            (Rotate180, Vertical, (7, 0)),
+            (Rotate180, Both, (0, 0)),
+            (Rotate270, None, (0, 3)),
+            (Rotate270, Horizontal, (0, 0)),
+            (Rotate270, Vertical, (7, 3)),
+            (Rotate270, Both, (7, 0)),
+        ];
+        for (rotation, mirror, (x, y)) in expected {
+            let mut display = Display::<8, 4, false, 4, Color>::default();
+            display.set_rotation(rotation);
+            display.set_mirror(mirror);
+            display.set_pixel(Pixel(Point::new(0, 0), Color::White));
+
+            let mut buffer = [0u8; 4];
+            buffer[y] = 0x80 >> x;
+            assert_eq!(display.buffer(), buffer, "{rotation:?} {mirror:?}");
+        }
+    }
+
+    /// Fills rectangles like the per pixel path would, for every rotation and mirroring
+    fn check_fills<COLOR: ColorType + PixelColor>(colors: [COLOR; 3]) {
+        use embedded_graphics::primitives::Rectangle;
+
+        // 13 pixels don't fill the last byte of a line
+        let (width, height) = (13, 7);
+        let len = height as usize
+            * line_bytes(width, COLOR::BITS_PER_PIXEL_PER_BUFFER)
+            * COLOR::BUFFER_COUNT;
+        let areas = [
+            Rectangle::new(Point::new(-3, -2), Size::new(9, 5)),
+            Rectangle::new(Point::new(1, 1), Size::new(20, 3)),
+            Rectangle::new(Point::new(5, 0), Size::new(1, 13)),
+            Rectangle::new(Point::new(2, 3), Size::new(11, 4)),
+            Rectangle::new(Point::new(3, 2), Size::new(3, 1)),
+            Rectangle::new(Point::new(0, 0), Size::new(0, 4)),
+            Rectangle::new(Point::new(20, 20), Size::new(4, 4)),
+        ];
+        let rotations = [
+            DisplayRotation::Rotate0,
+            DisplayRotation::Rotate90,
+            DisplayRotation::Rotate180,
+            DisplayRotation::Rotate270,
+        ];
+        let mirrors = [
+            DisplayMirror::None,
+            DisplayMirror::Horizontal,
+            DisplayMirror::Both,
+        ];
+        for rotation in rotations {
+            for mirror in mirrors {
+                for area in &areas {
+                    // a pattern underneath, the fills must keep the other pixels and the padding
+                    let mut fast_buffer: Vec<u8> = (0..len).map(|i| (i * 37) as u8).collect();
+                    let mut slow_buffer = fast_buffer.clone();
+                    let mut fast =
+                        VarDisplay::<COLOR>::new(width, height, &mut fast_buffer, true).unwrap();
+                    let mut slow =
+                        VarDisplay::<COLOR>::new(width, height, &mut slow_buffer, true).unwrap();
+                    for display in [&mut fast, &mut slow] {
+                        display.set_rotation(rotation);
+                        display.set_mirror(mirror);
+                    }
+
+                    fast.fill_solid(area, colors[0]).unwrap();
+                    let pattern = colors.iter().copied().cycle();
+                    fast.fill_contiguous(&area.translate(Point::new(2, 1)), pattern.clone())
+                        .unwrap();
+                    let pixels = area.points().map(|point| Pixel(point, colors[0]));
+                    slow.draw_iter(pixels).unwrap();
+                    let shifted = area.translate(Point::new(2, 1));
+                    let pixels = shifted.points().zip(pattern).map(|(p, c)| Pixel(p, c));
+                    slow.draw_iter(pixels).unwrap();
+
+                    assert_eq!(fast.take_dirty_rect(), slow.take_dirty_rect());
+                    let (fast, slow) = (fast.buffer(), slow.buffer());
+                    assert_eq!(fast, slow, "{rotation:?} {mirror:?} {area:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fills_match_the_per_pixel_path() {
+        check_fills([Color::Black, Color::White, Color::White]);
+        check_fills([TriColor::Chromatic, TriColor::Black, TriColor::White]);
+        check_fills([OctColor::Red, OctColor::Blue, OctColor::White]);
+        check_fills([QuadColor::Yellow, QuadColor::Black, QuadColor::Red]);
+    }
+
+    fn check_scrolls<COLOR: ColorType + PixelColor>(colors: [COLOR; 3]) {
+        // 13 pixels don't fill the last byte of a line, the padding must stay as it is
+        let (width, height) = (13, 7);
+        let len = height as usize
+            * line_bytes(width, COLOR::BITS_PER_PIXEL_PER_BUFFER)
+            * COLOR::BUFFER_COUNT;
+        let pattern = |p: Point| colors[((p.x * 3 + p.y * 5 + p.x * p.y) % 3) as usize];
+        let moves = [-14, -13, -9, -8, -3, -1, 0, 1, 2, 5, 8, 9, 12, 20];
+        for rotation in [
+            DisplayRotation::Rotate0,
+            DisplayRotation::Rotate90,
+            DisplayRotation::Rotate180,
+            DisplayRotation::Rotate270,
+        ] {
+            for mirror in [DisplayMirror::None, DisplayMirror::Both] {
+                for (dx, dy) in moves.iter().flat_map(|&d| [(d, 0), (0, d)]) {
+                    let mut scrolled_buffer = alloc::vec![0u8; len];
+                    let mut drawn_buffer = alloc::vec![0u8; len];
+                    let mut scrolled =
+                        VarDisplay::<COLOR>::new(width, height, &mut scrolled_buffer, true)
+                            .unwrap();
+                    let mut drawn =
+                        VarDisplay::<COLOR>::new(width, height, &mut drawn_buffer, true).unwrap();
+                    for display in [&mut scrolled, &mut drawn] {
+                        display.set_rotation(rotation);
+                        display.set_mirror(mirror);
+                    }
+                    let view = scrolled.bounding_box();
+
+                    let pixels = view.points().map(|p| Pixel(p, pattern(p)));
+                    scrolled.draw_iter(pixels).unwrap();
+                    if dx == 0 {
+                        scrolled.scroll_vertical(dy, colors[1]);
+                    } else {
+                        scrolled.scroll_horizontal(dx, colors[1]);
+                    }
+                    let pixels = view.points().map(|p| {
+                        let from = p - Point::new(dx, dy);
+                        let color = if view.contains(from) {
+                            pattern(from)
+                        } else {
+                            colors[1]
+                        };
+                        Pixel(p, color)
+                    });
+                    drawn.draw_iter(pixels).unwrap();
+
+                    let (scrolled, drawn) = (scrolled.buffer(), drawn.buffer());
+                    assert_eq!(scrolled, drawn, "{rotation:?} {mirror:?} {dx} {dy}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn scrolls_match_a_redraw() {
+        check_scrolls([Color::Black, Color::White, Color::White]);
+        check_scrolls([TriColor::Chromatic, TriColor::Black, TriColor::White]);
+        check_scrolls([OctColor::Red, OctColor::Blue, OctColor::White]);
+        check_scrolls([QuadColor::Yellow, QuadColor::Black, QuadColor::Red]);
+    }
+
+    fn check_clear<COLOR: ColorType + PixelColor>(colors: [COLOR; 3]) {
+        let (width, height) = (16, 3);
+        let len = height as usize
+            * line_bytes(width, COLOR::BITS_PER_PIXEL_PER_BUFFER)
+            * COLOR::BUFFER_COUNT;
+        for bwrbit in [false, true] {
+            for color in colors {
+                let mut fast_buffer = alloc::vec![0x5A; len];
+                let mut slow_buffer = fast_buffer.clone();
+                let mut fast =
+                    VarDisplay::<COLOR>::new(width, height, &mut fast_buffer, bwrbit).unwrap();
+                let mut slow =
+                    VarDisplay::<COLOR>::new(width, height, &mut slow_buffer, bwrbit).unwrap();
+                fast.set_rotation(DisplayRotation::Rotate90);
+                fast.clear(color).unwrap();
+                for point in Rectangle::new(Point::zero(), slow.size()).points() {
+                    slow.set_pixel(Pixel(point, color));
+                }
+                assert_eq!(fast.take_dirty_rect(), Some(Rect::new(0, 0, width, height)));
+                assert_eq!(fast.buffer(), slow.buffer());
+            }
+        }
+    }
+
+    #[test]
+    fn clear_fills_every_plane() {
+        check_clear([Color::Black, Color::White, Color::White]);
+        check_clear([TriColor::Chromatic, TriColor::Black, TriColor::White]);
+        check_clear([OctColor::Red, OctColor::HiZ, OctColor::White]);
+        check_clear([QuadColor::Yellow, QuadColor::Black, QuadColor::Red]);
+        check_clear([Gray2::new(1), Gray2::BLACK, Gray2::WHITE]);
+
+        // chromatic clears the black/white bit with BWRBIT = true and sets both bits without
+        let mut display = Display::<16, 2, true, { 2 * 2 * 2 }, TriColor>::default();
+        display.clear(TriColor::Chromatic).unwrap();
+        assert_eq!(display.bw_buffer(), [0x00; 4]);
+        assert_eq!(display.chromatic_buffer(), [0xFF; 4]);
+        let mut display = Display::<16, 2, false, { 2 * 2 * 2 }, TriColor>::default();
+        display.clear(TriColor::Chromatic).unwrap();
+        assert_eq!(display.bw_buffer(), [0xFF; 4]);
+        assert_eq!(display.chromatic_buffer(), [0xFF; 4]);
+    }
+
+    #[test]
+    fn scroll_a_log() {
+        let mut display = Display::<8, 4, false, 4, Color>::default();
+        for y in 0..4 {
+            display.set_pixel(Pixel(Point::new(y, y), Color::White));
+        }
+        display.take_dirty_rect();
+
+        display.scroll_vertical(-1, Color::Black);
+        assert_eq!(display.buffer(), [0b0100_0000, 0b0010_0000, 0b0001_0000, 0]);
+        assert_eq!(display.take_dirty_rect(), Some(Rect::new(0, 0, 8, 4)));
+
+        display.scroll_horizontal(-1, Color::White);
+        assert_eq!(
+            display.buffer(),
+            [0b1000_0001, 0b0100_0001, 0b0010_0001, 0b1]
+        );
+        display.scroll_vertical(4, Color::Black);
+        assert_eq!(display.buffer(), [0; 4]);
+    }
+
+    #[test]
+    fn cropped_display() {
+        use embedded_graphics::primitives::Rectangle;
+
+        let mut display = Display::<16, 8, false, { 2 * 8 }, Color>::default();
+        display.set_rotation(DisplayRotation::Rotate90);
+        {
+            let area = Rectangle::new(Point::new(2, 3), Size::new(4, 20));
+            let mut widget = display.cropped(area);
+            // clipped to the 8 x 16 drawing
+            assert_eq!(widget.size(), Size::new(4, 13));
+            widget.clear(Color::White).unwrap();
+            // only the 4 pixels of the widget are drawn
+            let _ = Line::new(Point::new(-2, 12), Point::new(6, 12))
+                .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
+                .draw(&mut widget);
+        }
+        // x = 2..6, y = 3..16 of the drawing rotated by 90 degrees
+        assert_eq!(display.take_dirty_rect(), Some(Rect::new(0, 2, 13, 4)));
+
+        let mut expected = Display::<16, 8, false, { 2 * 8 }, Color>::default();
+        expected.set_rotation(DisplayRotation::Rotate90);
+        let widget = Rectangle::new(Point::new(2, 3), Size::new(4, 13));
+        expected.fill_solid(&widget, Color::White).unwrap();
+        for x in 2..6 {
+            expected.set_pixel(Pixel(Point::new(x, 15), Color::Black));
+        }
+        assert_eq!(display.buffer(), expected.buffer());
+    }
+
+    #[test]
+    fn invert_area_at_odd_x() {
+        use embedded_graphics::primitives::Rectangle;
+
+        let mut display = Display::<16, 4, false, { 2 * 4 }, Color>::default();
+        display.set_pixel(Pixel(Point::new(8, 1), Color::White));
+        display.take_dirty_rect();
+
+        // x = 7..10 covers the last bit of the first byte and two bits of the second one
+        display.invert_area(Rectangle::new(Point::new(7, 0), Size::new(3, 3)));
+        assert_eq!(
+            display.buffer(),
+            [0x01, 0xC0, 0x01, 0x40, 0x01, 0xC0, 0x00, 0x00]
+        );
+        assert_eq!(display.take_dirty_rect(), Some(Rect::new(7, 0, 3, 3)));
+
+        // rotated by 180 degrees the same area is x = 6..9 and y = 1..4 of the buffer
+        display.set_rotation(DisplayRotation::Rotate180);
+        display.invert_area(Rectangle::new(Point::new(7, 0), Size::new(3, 3)));
+        assert_eq!(
+            display.buffer(),
+            [0x01, 0xC0, 0x02, 0xC0, 0x02, 0x40, 0x03, 0x80]
+        );
+    }
+
+    fn check_tricolor_invert<const BWRBIT: bool>() {
+        use embedded_graphics::primitives::Rectangle;
+
+        let mut display = Display::<8, 1, BWRBIT, 2, TriColor>::default();
+        display.set_pixel(Pixel(Point::new(0, 0), TriColor::White));
+        display.set_pixel(Pixel(Point::new(1, 0), TriColor::Chromatic));
+        display.invert_area(Rectangle::new(Point::zero(), Size::new(8, 1)));
+
+        let mut expected = Display::<8, 1, BWRBIT, 2, TriColor>::default();
+        expected.clear(TriColor::White).unwrap();
+        expected.set_pixel(Pixel(Point::new(0, 0), TriColor::Black));
+        expected.set_pixel(Pixel(Point::new(1, 0), TriColor::Chromatic));
+        assert_eq!(display.buffer(), expected.buffer());
+    }
+
+    #[test]
+    fn invert_area_keeps_chromatic() {
+        check_tricolor_invert::<false>();
+        check_tricolor_invert::<true>();
+    }
+
+    #[test]
+    fn tricolor_planes_for_the_drivers() {
+        // 104 x 212 pixels, each plane as `update_achromatic_frame` and `update_chromatic_frame`
+        // of the Epd 2in13bc take it
+        let mut display = crate::epd2in13bc::Display2in13bc::default();
+        let plane = crate::buffer_len(104, 212);
+        display.clear(TriColor::White).unwrap();
+        display.set_pixel(Pixel(Point::new(0, 0), TriColor::Chromatic));
+        display.set_pixel(Pixel(Point::new(9, 1), TriColor::Black));
+        assert_eq!(display.bw_buffer().len(), plane);
+        assert_eq!(display.chromatic_buffer().len(), plane);
+        // a line takes 13 bytes, x = 9 of the second one is the second bit of byte 14, and with
+        // `BWRBIT` the chromatic pixel clears its black/white bit
+        let black: Vec<usize> = (0..plane)
+            .filter(|&i| display.bw_buffer()[i] != 0xFF)
+            .collect();
+        assert_eq!(black, [0, 14]);
+        assert_eq!(display.bw_buffer()[14], 0b1011_1111);
+        let chromatic: Vec<usize> = (0..plane)
+            .filter(|&i| display.chromatic_buffer()[i] != 0)
+            .collect();
+        assert_eq!(chromatic, [0]);
+        assert_eq!(display.chromatic_buffer()[0], 0b1000_0000);
+
+        display.take_dirty_rect();
+        display.clear_chromatic(false);
+        assert!(display.chromatic_buffer().iter().all(|&b| b == 0));
+        assert_eq!(display.bw_buffer()[14], 0b1011_1111);
+        assert_eq!(display.take_dirty_rect(), Some(Rect::new(0, 0, 104, 212)));
+
+        display.clear_bw(Color::White);
+        assert!(display.bw_buffer().iter().all(|&b| b == 0xFF));
+        display.clear_chromatic(true);
+        assert!(display.chromatic_buffer().iter().all(|&b| b == 0xFF));
+        assert!(display.bw_buffer().iter().all(|&b| b == 0xFF));
+    }
+
+    #[test]
+    fn panel_area_for_every_rotation() {
+        use embedded_graphics::primitives::Rectangle;
+
+        // 32 x 16 pixels, the drawing is 16 x 32 when rotated by 90 or 270 degrees
+        let area = Rectangle::new(Point::new(3, 2), Size::new(4, 10));
+        let cases = [
+            // x = 3..7, y = 2..12 widened to a byte
+            (DisplayRotation::Rotate0, Rect::new(0, 2, 8, 10)),
+            // x = 32 - 12..32 - 2 = 20..30, y = 3..7
+            (DisplayRotation::Rotate90, Rect::new(16, 3, 16, 4)),
+            // x = 32 - 7..32 - 3 = 25..29, y = 16 - 12..16 - 2 = 4..14
+            (DisplayRotation::Rotate180, Rect::new(24, 4, 8, 10)),
+            // x = 2..12, y = 16 - 7..16 - 3 = 9..13
+            (DisplayRotation::Rotate270, Rect::new(0, 9, 16, 4)),
+        ];
+        for (rotation, window) in cases {
+            let mut display = Display::<32, 16, false, { 4 * 16 }, Color>::default();
+            display.set_rotation(rotation);
+            assert_eq!(display.to_panel_area(area), Some(window), "{rotation:?}");
+
+            // the dirty rectangle of the area lies in the window
+            display.fill_solid(&area, Color::White).unwrap();
+            let dirty = display.take_dirty_rect().unwrap();
+            assert!(dirty.x >= window.x && dirty.x + dirty.w <= window.x + window.w);
+            assert_eq!((dirty.y, dirty.h), (window.y, window.h));
+
+            // the window holds all of the area, i.e. all the white pixels
+            let bytes = display.window_buffer(window).unwrap();
+            assert_eq!(bytes.len(), window.w as usize / 8 * window.h as usize);
+            let white: u32 = bytes.iter().map(|b| b.count_ones()).sum();
+            assert_eq!(white, 4 * 10, "{rotation:?}");
+        }
+
+        let display = Display::<32, 16, false, { 4 * 16 }, Color>::default();
+        let off = Rectangle::new(Point::new(40, 0), Size::new(4, 4));
+        assert_eq!(display.to_panel_area(off), None);
+    }
+
+    #[test]
+    fn window_buffer_of_both_planes() {
+        let mut display = Display::<16, 2, false, { 2 * 2 * 2 }, TriColor>::default();
+        display.set_pixel(Pixel(Point::new(8, 0), TriColor::White));
+        display.set_pixel(Pixel(Point::new(9, 1), TriColor::Chromatic));
+        let bytes = display.window_buffer(Rect::new(8, 0, 8, 2)).unwrap();
+        // the black/white plane, then the chromatic plane
+        assert_eq!(bytes, [0x80, 0x40, 0x00, 0x40]);
+    }
+
+    #[test]
+    fn window_buffer_rejects_bad_windows() {
+        let display = Display::<16, 2, false, 4, Color>::default();
+        for window in [
+            // not at a byte
+            Rect::new(3, 0, 8, 2),
+            // off the display
+            Rect::new(8, 0, 16, 2),
+            Rect::new(0, 1, 8, 2),
+            Rect::new(8, 0, u32::MAX, 1),
+            Rect::new(0, u32::MAX, 8, 2),
+        ] {
+            assert_eq!(display.window_buffer(window), Err(WindowError { window }));
+        }
+
+        // 2 pixels per byte
+        let display = Display::<6, 2, false, 6, OctColor>::default();
+        assert!(display.window_buffer(Rect::new(3, 0, 2, 2)).is_err());
+        assert_eq!(display.window_buffer(Rect::new(2, 0, 4, 1)), Ok(vec![0, 0]));
+    }
+
+    #[test]
+    fn double_buffered_diff() {
+        let mut display = DoubleBufferedDisplay::<20, 4, false, { 3 * 4 }, Color>::default();
+        assert_eq!(display.swap().changed, None);
+
+        Pixel(Point::new(10, 1), Color::White)
+            .draw(&mut display)
+            .unwrap();
+        Pixel(Point::new(17, 2), Color::White)
+            .draw(&mut display)
+            .unwrap();
+        let diff = display.swap();
+        // the bytes 1 and 2 of the lines, the last byte has 4 pixels only
+        assert_eq!(diff.changed, Some(Rect::new(8, 1, 12, 2)));
+        assert_eq!(diff.previous, &[0u8; 12]);
+        assert_eq!(diff.previous_window(), [0u8; 4]);
+        assert_eq!(diff.current_window(), [0x20, 0x00, 0x00, 0x40]);
+        let first = diff.current.to_vec();
+
+        // the swapped frame becomes the previous one
+        assert_eq!(display.swap().changed, None);
+        Pixel(Point::new(0, 3), Color::White)
+            .draw(&mut display)
+            .unwrap();
+        let diff = display.swap();
+        assert_eq!(diff.changed, Some(Rect::new(0, 3, 8, 1)));
+        assert_eq!(diff.previous, &first[..]);
+        let current = diff.current.to_vec();
+        assert_eq!(current, display.display().buffer());
+    }
+
+    #[test]
+    fn double_buffered_chromatic_plane() {
+        let mut display = DoubleBufferedDisplay::<16, 2, true, { 2 * 2 * 2 }, TriColor>::default();
+        display
+            .display_mut()
+            .set_rotation(DisplayRotation::Rotate180);
+        Pixel(Point::new(6, 0), TriColor::Chromatic)
+            .draw(&mut display)
+            .unwrap();
+        let diff = display.swap();
+        assert_eq!(diff.changed, Some(Rect::new(8, 1, 8, 1)));
+        assert_eq!(diff.current_window().len(), 2);
+        assert_eq!(diff.previous_window(), [0, 0]);
+        assert!(display.swap().previous_window().is_empty());
+    }
+
+    #[test]
+    fn boxed_buffer() {
+        let mut owned = Display::<16, 8, true, { 2 * 2 * 8 }, TriColor>::default();
+        let mut boxed: BoxedDisplay<16, 8, true, { 2 * 2 * 8 }, TriColor> = Display::new_boxed();
+        let line = Line::new(Point::new(1, 2), Point::new(12, 6))
+            .into_styled(PrimitiveStyle::with_stroke(TriColor::Chromatic, 1));
+        line.draw(&mut owned).unwrap();
+        line.draw(&mut boxed).unwrap();
+        assert_eq!(boxed.buffer(), owned.buffer());
+        assert_eq!(boxed.chromatic_buffer(), owned.chromatic_buffer());
+        assert_eq!(boxed.take_dirty_rect(), owned.take_dirty_rect());
+    }
+
+    #[test]
+    fn owned_buffer() {
+        let mut owned = Display::<16, 8, true, { 2 * 2 * 8 }, TriColor>::default();
+        let mut heap = OwnedDisplay::<TriColor>::with_bwrbit(16, 8, true);
+        assert_eq!(heap.buffer().len(), 2 * 2 * 8);
+        heap.set_rotation(DisplayRotation::Rotate90);
+        owned.set_rotation(DisplayRotation::Rotate90);
+        assert_eq!(heap.size(), Size::new(8, 16));
+
+        let line = Line::new(Point::new(1, 2), Point::new(6, 12))
+            .into_styled(PrimitiveStyle::with_stroke(TriColor::Chromatic, 1));
+        line.draw(&mut owned).unwrap();
+        line.draw(&mut heap).unwrap();
+        assert_eq!(heap.buffer(), owned.buffer());
+        assert_eq!(heap.chromatic_buffer(), owned.chromatic_buffer());
+        assert_eq!(heap.take_dirty_rect(), owned.take_dirty_rect());
+
+        // a width which isn't a multiple of 8 pads every row
+        assert_eq!(OwnedDisplay::<Color>::new(10, 3).buffer(), [0; 6]);
+    }
+
+    fn check_diff_region<const WIDTH: u32, const HEIGHT: u32, const BYTECOUNT: usize, COLOR>(
+        background: COLOR,
+        color: COLOR,
+    ) where
+        COLOR: ColorType + PixelColor,
+    {
+        let rotations = [
+            DisplayRotation::Rotate0,
+            DisplayRotation::Rotate90,
+            DisplayRotation::Rotate180,
+            DisplayRotation::Rotate270,
+        ];
+        let mirrors = [
+            DisplayMirror::None,
+            DisplayMirror::Horizontal,
+            DisplayMirror::Vertical,
+        ];
+        // a linear congruential generator picks the pixels
+        let mut seed = 0x2545_f491u32;
+        for rotation in rotations {
+            for mirror in mirrors {
+                let mut old = Display::<WIDTH, HEIGHT, true, BYTECOUNT, COLOR>::default();
+                old.set_rotation(rotation);
+                old.set_mirror(mirror);
+                old.clear(background).unwrap();
+                let mut new = Display::<WIDTH, HEIGHT, true, BYTECOUNT, COLOR>::default();
+                new.copy_from_slice(old.buffer()).unwrap();
+                assert_eq!(old.diff_region(&new), None);
+
+                for _ in 0..20 {
+                    seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                    let size = old.size();
+                    let point = Point::new(
+                        (seed >> 8) as i32 % size.width as i32,
+                        (seed >> 20) as i32 % size.height as i32,
+                    );
+                    new.copy_from_slice(old.buffer()).unwrap();
+                    new.set_rotation(rotation);
+                    new.set_mirror(mirror);
+                    new.set_pixel(Pixel(point, color));
+                    let region = Rectangle::new(point, Size::new(1, 1));
+                    assert_eq!(
+                        old.diff_region(&new),
+                        Some(region),
+                        "{rotation:?} {mirror:?}"
+                    );
+
+                    let window = old.to_panel_area(region).unwrap();
+                    let (x, y) = old.frame().buffer_point(point);
+                    assert!(window.x <= x as u32 && x as u32 - window.x < window.w);
+                    assert_eq!((window.y, window.h), (y as u32, 1));
+                }
+
+                // a second pixel grows the region
+                new.set_pixel(Pixel(Point::zero(), color));
+                let region = old.diff_region(&new).unwrap();
+                assert!(region.contains(Point::zero()));
+            }
+        }
+    }
+
+    #[test]
+    fn diff_region_of_single_pixels() {
+        check_diff_region::<13, 7, { 2 * 7 }, Color>(Color::White, Color::Black);
+        check_diff_region::<16, 5, { 2 * 5 * 2 }, TriColor>(TriColor::White, TriColor::Chromatic);
+        check_diff_region::<7, 4, { 2 * 4 }, Gray2>(Gray2::BLACK, Gray2::new(2));
+
+        // the padding bits at the end of the lines aren't on the display
+        let old = Display::<13, 2, false, { 2 * 2 }, Color>::default();
+        let new = Display::<13, 2, false, { 2 * 2 }, Color>::from_buffer([0, 0x07, 0, 0x01]);
+        assert_eq!(old.diff_region(&new), None);
+    }
+
+    #[test]
+    fn borrowed_buffer() {
+        let mut frame = [0u8; 2 * 2 * 8];
+        assert_eq!(
+            Display::<16, 8, true, { 2 * 2 * 8 }, TriColor>::with_buffer(&mut frame[1..]).err(),
+            Some(BufferLengthError {
+                expected: 32,
+                actual: 31
+            })
+        );
+
+        let mut owned = Display::<16, 8, true, { 2 * 2 * 8 }, TriColor>::default();
+        let mut borrowed =
+            Display::<16, 8, true, { 2 * 2 * 8 }, TriColor>::with_buffer(&mut frame).unwrap();
+        borrowed.take_dirty_rect();
+        for rotation in [DisplayRotation::Rotate0, DisplayRotation::Rotate270] {
+            owned.set_rotation(rotation);
+            borrowed.set_rotation(rotation);
+            let line = Line::new(Point::new(1, 2), Point::new(5, 6))
+                .into_styled(PrimitiveStyle::with_stroke(TriColor::Chromatic, 1));
+            line.draw(&mut owned).unwrap();
+            line.draw(&mut borrowed).unwrap();
+            owned.clear_bw(Color::White);
+            borrowed.clear_bw(Color::White);
+            assert_eq!(owned.buffer(), borrowed.buffer());
+            assert_eq!(owned.chromatic_buffer(), borrowed.chromatic_buffer());
+            assert_eq!(owned.take_dirty_rect(), borrowed.take_dirty_rect());
+        }
+        assert!(frame[..16].iter().all(|&b| b == 0xFF));
+        assert!(frame[16..].iter().any(|&b| b != 0));
+    }
+
+    #[test]
+    fn read_back_every_rotation_and_mirror() {
+        use embedded_graphics::primitives::Rectangle;
+
+        for rotation in [
+            DisplayRotation::Rotate0,
+            DisplayRotation::Rotate90,
+            DisplayRotation::Rotate180,
+            DisplayRotation::Rotate270,
+        ] {
+            for mirror in [DisplayMirror::None, DisplayMirror::Vertical] {
+                let mut display = Display::<16, 8, false, { 2 * 8 }, Color>::default();
+                display.set_rotation(rotation);
+                display.set_mirror(mirror);
+                let area = Rectangle::new(Point::new(1, 2), Size::new(3, 2));
+                display.fill_solid(&area, Color::White).unwrap();
+
+                let size = display.size();
+                assert_eq!(display.get_pixel(size.width, 0), None);
+                assert_eq!(display.get_pixel(0, size.height), None);
+                assert_eq!(display.pixels().count(), 16 * 8);
+                for Pixel(point, color) in display.pixels() {
+                    let expected = if area.contains(point) {
+                        Color::White
+                    } else {
+                        Color::Black
+                    };
+                    assert_eq!(color, expected, "{rotation:?} {mirror:?} {point:?}");
+                }
+            }
+        }
+    }
+
+    fn check_rotation_preserving<
+        const WIDTH: u32,
+        const HEIGHT: u32,
+        const BYTECOUNT: usize,
+        COLOR: ColorType + PixelColor,
+    >(
+        colors: [COLOR; 3],
+    ) {
+        let rotations = [
+            DisplayRotation::Rotate0,
+            DisplayRotation::Rotate90,
+            DisplayRotation::Rotate180,
+            DisplayRotation::Rotate270,
+        ];
+        for from in rotations {
+            for to in rotations {
+                for mirror in [DisplayMirror::None, DisplayMirror::Horizontal] {
+                    let mut display = Display::<WIDTH, HEIGHT, true, BYTECOUNT, COLOR>::default();
+                    display.set_rotation(from);
+                    display.set_mirror(mirror);
+                    let size = display.size();
+                    let view = Rectangle::new(Point::zero(), size);
+                    for p in view.points() {
+                        let color = colors[((p.x * 3 + p.y * 5 + p.x * p.y) % 3) as usize];
+                        display.set_pixel(Pixel(p, color));
+                    }
+                    let bits = |display: &Display<WIDTH, HEIGHT, true, BYTECOUNT, COLOR>| {
+                        view.points()
+                            .map(|p| display.pixel_bits(p.x as u32, p.y as u32))
+                            .collect::<Vec<_>>()
+                    };
+                    let (before, buffer) = (bits(&display), display.buffer().to_vec());
+                    display.take_dirty_rect();
+
+                    let turn = (quarter_turns(to) + 4 - quarter_turns(from)) % 4;
+                    if WIDTH != HEIGHT && turn % 2 == 1 {
+                        assert_eq!(
+                            display.set_rotation_preserving(to),
+                            Err(RotationError { size })
+                        );
+                        assert_eq!(display.buffer(), buffer);
+                        assert_eq!(quarter_turns(display.rotation()), quarter_turns(from));
+                        continue;
+                    }
+                    display.set_rotation_preserving(to).unwrap();
+                    assert_eq!(bits(&display), before, "{from:?} {to:?} {mirror:?}");
+                    assert_eq!(quarter_turns(display.rotation()), quarter_turns(to));
+                    if turn != 0 {
+                        assert_eq!(
+                            display.take_dirty_rect(),
+                            Some(Rect::new(0, 0, WIDTH, HEIGHT))
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rotation_preserving_keeps_the_drawing() {
+        // square panels turn by any angle, whole bytes take the 180 degree shortcut
+        check_rotation_preserving::<8, 8, 8, Color>([Color::Black, Color::White, Color::White]);
+        check_rotation_preserving::<16, 4, { 2 * 4 }, Color>([
+            Color::Black,
+            Color::White,
+            Color::White,
+        ]);
+        check_rotation_preserving::<13, 7, { 2 * 7 }, Color>([
+            Color::Black,
+            Color::White,
+            Color::Black,
+        ]);
+        check_rotation_preserving::<8, 4, { 4 * 2 }, TriColor>([
+            TriColor::Chromatic,
+            TriColor::Black,
+            TriColor::White,
+        ]);
+        check_rotation_preserving::<5, 5, { 5 * 2 }, TriColor>([
+            TriColor::Chromatic,
+            TriColor::Black,
+            TriColor::White,
+        ]);
+        check_rotation_preserving::<8, 8, { 2 * 8 }, Gray2>([
+            Gray2::new(1),
+            Gray2::new(2),
+            Gray2::WHITE,
+        ]);
+        check_rotation_preserving::<6, 3, { 3 * 3 }, OctColor>([
+            OctColor::Red,
+            OctColor::Blue,
+            OctColor::White,
+        ]);
+    }
+
+    #[test]
+    fn read_back_tricolor() {
+        let mut display = Display::<10, 2, false, { 2 * 2 * 2 }, TriColor>::default();
+        display.set_rotation(DisplayRotation::Rotate90);
+        for (i, color) in [TriColor::White, TriColor::Chromatic, TriColor::Black]
+            .into_iter()
+            .enumerate()
+        {
+            // the last pixel of the buffer line is in the padded byte
+            display.set_pixel(Pixel(Point::new(0, 7 + i as i32), color));
+            assert_eq!(display.get_pixel(0, 7 + i as u32), Some(color));
+        }
+        assert_eq!(display.get_pixel(2, 0), None);
+
+        let mut gray = DisplayGray2::<8, 2, { 2 * 2 }>::default();
+        gray.set_pixel(Pixel(Point::new(5, 1), Gray2::new(2)));
+        assert_eq!(gray.get_pixel(5, 1), Some(Gray2::new(2)));
+        assert_eq!(gray.get_pixel(4, 1), Some(Gray2::BLACK));
+    }
+
+    /// A 1 bit BMP file, `rows` from the top, stored bottom-up if `height` is positive
+    fn bmp_file(width: i32, height: i32, palette: [[u8; 4]; 2], rows: &[&[u8]]) -> Vec<u8> {
+        let stride = (width as usize + 31) / 32 * 4;
+        let offset = 14 + 40 + 8;
+        let mut file = Vec::new();
+        file.extend_from_slice(b"BM");
+        file.extend_from_slice(&((offset + stride * rows.len()) as u32).to_le_bytes());
+        file.extend_from_slice(&[0; 4]);
+        file.extend_from_slice(&(offset as u32).to_le_bytes());
+        file.extend_from_slice(&40u32.to_le_bytes());
+        file.extend_from_slice(&width.to_le_bytes());
+        file.extend_from_slice(&height.to_le_bytes());
+        file.extend_from_slice(&1u16.to_le_bytes());
+        file.extend_from_slice(&1u16.to_le_bytes());
+        file.extend_from_slice(&[0; 24]);
+        file.extend_from_slice(&palette[0]);
+        file.extend_from_slice(&palette[1]);
+        let mut lines: Vec<&[u8]> = rows.to_vec();
+        if height > 0 {
+            lines.reverse();
+        }
+        for line in lines {
+            let mut padded = line.to_vec();
+            padded.resize(stride, 0xAA);
+            file.extend_from_slice(&padded);
+        }
+        file
+    }
+
+    const BLACK_WHITE: [[u8; 4]; 2] = [[0, 0, 0, 0], [0xFF, 0xFF, 0xFF, 0]];
+
+    #[test]
+    fn draw_bmp_lines() {
+        // 10 pixels take 2 bytes, the bits past them must not be copied
+        let rows: [&[u8]; 3] = [&[0b1100_0011, 0b1111_1111], &[0x0F, 0x00], &[0xF0, 0x80]];
+        let mut display = Display::<24, 5, false, { 3 * 5 }, Color>::default();
+        display.take_dirty_rect();
+        let file = bmp_file(10, 3, BLACK_WHITE, &rows);
+        display.draw_bmp(&file, 8, 1).unwrap();
+        #[rustfmt::skip]
+        let expected = [
+            0, 0, 0,
+            0, 0b1100_0011, 0b1100_0000,
+            0, 0x0F, 0x00,
+            0, 0xF0, 0x80,
+            0, 0, 0,
+        ];
+        assert_eq!(display.buffer(), expected);
+        assert_eq!(display.take_dirty_rect(), Some(Rect::new(8, 1, 10, 3)));
+
+        // top-down with white as the first color is the same image
+        let inverted: Vec<Vec<u8>> = rows
+            .iter()
+            .map(|r| r.iter().map(|b| !b).collect())
+            .collect();
+        let inverted: Vec<&[u8]> = inverted.iter().map(|r| r.as_slice()).collect();
+        let file = bmp_file(10, -3, [BLACK_WHITE[1], BLACK_WHITE[0]], &inverted);
+        let mut top_down = Display::<24, 5, false, { 3 * 5 }, Color>::default();
+        top_down.draw_bmp(&file, 8, 1).unwrap();
+        assert_eq!(top_down.buffer(), expected);
+    }
+
+    #[test]
+    fn draw_bmp_errors() {
+        let rows: [&[u8]; 2] = [&[0xFF], &[0x00]];
+        let file = bmp_file(8, 2, BLACK_WHITE, &rows);
+        let mut display = Display::<16, 2, false, 4, Color>::default();
+        assert_eq!(display.draw_bmp(&file, 4, 0), Err(BmpError::Unaligned));
+        assert_eq!(display.draw_bmp(&file, 16, 0), Err(BmpError::TooLarge));
+        assert_eq!(display.draw_bmp(&file, 0, 1), Err(BmpError::TooLarge));
+        assert_eq!(
+            display.draw_bmp(&file[..file.len() - 1], 0, 0),
+            Err(BmpError::Malformed)
+        );
+        assert_eq!(display.draw_bmp(b"GIF89a", 0, 0), Err(BmpError::Malformed));
+
+        let mut rle = file.clone();
+        rle[30] = 1;
+        assert_eq!(display.draw_bmp(&rle, 0, 0), Err(BmpError::Unsupported));
+        let mut rgb = file.clone();
+        rgb[28] = 24;
+        assert_eq!(display.draw_bmp(&rgb, 0, 0), Err(BmpError::Unsupported));
+        assert_eq!(display.buffer(), [0; 4]);
+
+        display.draw_bmp(&file, 8, 0).unwrap();
+        assert_eq!(display.buffer(), [0, 0xFF, 0, 0]);
+    }
+
+    fn check_blit<const INVERTED: bool>(rotation: DisplayRotation, mirror: DisplayMirror) {
+        // 16 pixels and 3 lines, the last line is off the bottom and the first byte off the left
+        let icon = [
+            0b1000_0001,
+            0b0110_0000,
+            0b0000_1111,
+            0b1111_0000,
+            0xFF,
+            0x00,
+        ];
+        for top_left in [Point::new(8, 5), Point::new(-8, 1), Point::new(16, -1)] {
+            let mut fast = Display::<24, 7, false, { 3 * 7 }, Color, _, INVERTED>::default();
+            let mut slow = Display::<24, 7, false, { 3 * 7 }, Color, _, INVERTED>::default();
+            for display in [&mut fast, &mut slow] {
+                display.set_rotation(rotation);
+                display.set_mirror(mirror);
+            }
+            fast.blit_packed(&icon, 16, top_left).unwrap();
+            for (i, byte) in icon.iter().enumerate() {
+                for bit in 0..8 {
+                    let color = if byte & (0x80 >> bit) != 0 {
+                        Color::White
+                    } else {
+                        Color::Black
+                    };
+                    let point = Point::new(i as i32 % 2 * 8 + bit, i as i32 / 2);
+                    slow.set_pixel(Pixel(top_left + point, color));
+                }
+            }
+            assert_eq!(
+                fast.buffer(),
+                slow.buffer(),
+                "{rotation:?} {mirror:?} {top_left:?}"
+            );
+            assert_eq!(fast.take_dirty_rect(), slow.take_dirty_rect());
+        }
+    }
+
+    #[test]
+    fn blit_packed_rows() {
+        for mirror in [
+            DisplayMirror::None,
+            DisplayMirror::Horizontal,
+            DisplayMirror::Vertical,
+            DisplayMirror::Both,
+        ] {
+            for rotation in [DisplayRotation::Rotate0, DisplayRotation::Rotate180] {
+                check_blit::<false>(rotation, mirror);
+                check_blit::<true>(rotation, mirror);
+            }
+        }
+
+        let mut display = Display::<24, 7, false, { 3 * 7 }, Color>::default();
+        let icon = [0xFF; 4];
+        assert_eq!(
+            display.blit_packed(&icon, 16, Point::new(4, 0)),
+            Err(BlitError::Unaligned)
+        );
+        assert_eq!(
+            display.blit_packed(&icon, 12, Point::new(0, 0)),
+            Err(BlitError::Unaligned)
+        );
+        assert_eq!(
+            display.blit_packed(&icon[..3], 16, Point::new(0, 0)),
+            Err(BlitError::Malformed)
+        );
+        assert_eq!(
+            display.blit_packed(&icon, 0, Point::new(0, 0)),
+            Err(BlitError::Malformed)
+        );
+        display.set_rotation(DisplayRotation::Rotate90);
+        assert_eq!(
+            display.blit_packed(&icon, 16, Point::new(0, 0)),
+            Err(BlitError::Unsupported)
+        );
+        assert_eq!(display.buffer(), [0; 3 * 7]);
+        assert_eq!(display.take_dirty_rect(), None);
+
+        // the bytes of the lines don't line up when turned
+        let mut display = Display::<20, 2, false, { 3 * 2 }, Color>::default();
+        display.blit_packed(&icon, 16, Point::new(8, 0)).unwrap();
+        display.set_rotation(DisplayRotation::Rotate180);
+        assert_eq!(
+            display.blit_packed(&icon, 16, Point::new(0, 0)),
+            Err(BlitError::Unaligned)
+        );
+    }
+
+    #[test]
+    fn try_set_pixel_reports_the_point() {
+        let mut display = Display::<16, 8, false, { 2 * 8 }, Color>::default();
+        display.set_rotation(DisplayRotation::Rotate90);
+        display
+            .try_set_pixel(Pixel(Point::new(7, 15), Color::White))
+            .unwrap();
+        assert_eq!(display.get_pixel(7, 15), Some(Color::White));
+        assert_eq!(
+            display.try_set_pixel(Pixel(Point::new(8, 0), Color::White)),
+            Err(OutOfBoundsError {
+                point: Point::new(8, 0),
+                size: Size::new(8, 16)
+            })
+        );
+        let mut buffer = [0u8; 4];
+        let mut var = VarDisplay::<Color>::new(16, 2, &mut buffer, false).unwrap();
+        assert!(var
+            .try_set_pixel(Pixel(Point::new(-1, 0), Color::White))
+            .is_err());
+        assert_eq!(var.take_dirty_rect(), None);
+    }
+
+    #[test]
+    fn inverted_polarity() {
+        use embedded_graphics::primitives::Rectangle;
+
+        let mut normal = Display::<16, 4, false, 8, Color>::default();
+        let mut inverted = Display::<16, 4, false, 8, Color, [u8; 8], true>::default();
+        inverted.clear(Color::Black).unwrap();
+        for rotation in [DisplayRotation::Rotate0, DisplayRotation::Rotate90] {
+            normal.set_rotation(rotation);
+            inverted.set_rotation(rotation);
+            let area = Rectangle::new(Point::new(1, 1), Size::new(11, 2));
+            normal.fill_solid(&area, Color::White).unwrap();
+            inverted.fill_solid(&area, Color::White).unwrap();
+            normal.set_pixel(Pixel(Point::new(3, 0), Color::White));
+            inverted.set_pixel(Pixel(Point::new(3, 0), Color::White));
+        }
+        let flipped: Vec<u8> = normal.buffer().iter().map(|b| !b).collect();
+        assert_eq!(inverted.buffer(), flipped);
+        assert!(inverted.pixels().eq(normal.pixels()));
+    }
+
+    #[test]
+    fn gray8_nearest_level() {
+        let mut display = DisplayGray2::<8, 1, 2>::default();
+        let lumas = [0, 42, 43, 85, 127, 128, 212, 255];
+        let pixels = lumas
+            .iter()
+            .enumerate()
+            .map(|(x, luma)| Pixel(Point::new(x as i32, 0), Gray8::new(*luma)));
+        display.gray8(false).draw_iter(pixels).unwrap();
+        let levels: Vec<u8> = display.pixels().map(|Pixel(_, gray)| gray.luma()).collect();
+        assert_eq!(levels, [0, 0, 1, 1, 1, 2, 2, 3]);
+    }
+
+    #[test]
+    fn gray8_ordered_dither() {
+        // 128 is halfway between the levels 1 and 2, the matrix picks either for half the pixels
+        let mut display = DisplayGray2::<8, 4, { 2 * 4 }>::default();
+        let area = Rectangle::new(Point::new(2, 0), Size::new(4, 4));
+        display
+            .gray8(true)
+            .fill_solid(&area, Gray8::new(128))
+            .unwrap();
+        let levels = area
+            .points()
+            .map(|p| display.get_pixel(p.x as u32, p.y as u32).unwrap().luma());
+        let (mut low, mut high) = (0, 0);
+        for level in levels {
+            match level {
+                1 => low += 1,
+                2 => high += 1,
+                _ => panic!("level {level}"),
+            }
+        }
+        assert_eq!((low, high), (8, 8));
+
+        // the pixel path matches the fills, the exact levels aren't dithered
+        let mut pixels = DisplayGray2::<8, 4, { 2 * 4 }>::default();
+        let points = area.points().map(|p| Pixel(p, Gray8::new(128)));
+        pixels.gray8(true).draw_iter(points).unwrap();
+        assert_eq!(pixels.buffer(), display.buffer());
+        display.gray8(true).clear(Gray8::new(170)).unwrap();
+        assert!(display.pixels().all(|Pixel(_, gray)| gray.luma() == 2));
+    }
+
+    #[test]
+    fn gray2_display() {
+        let mut display = DisplayGray2::<8, 2, { 2 * 2 }>::default();
+        display.clear(Gray2::WHITE).unwrap();
+        assert_eq!(display.buffer(), [0xFF; 4]);
+
+        display.clear(Gray2::BLACK).unwrap();
+        display.set_pixel(Pixel(Point::new(0, 0), Gray2::new(1)));
+        display.set_rotation(DisplayRotation::Rotate90);
+        // the last pixel of the last line
+        display.set_pixel(Pixel(Point::new(1, 0), Gray2::new(2)));
+        assert_eq!(display.buffer(), [0b01000000, 0, 0, 0b00000010]);
     }
 }
@@ -1,3 +1,4 @@
+use crate::error::Error;
 use crate::traits::Command;
 use core::marker::PhantomData;
 use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
@@ -6,7 +7,7 @@ use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
 ///
 /// SINGLE_BYTE_WRITE defines if a data block is written bytewise
 /// or blockwise to the spi device
-pub(crate) struct DisplayInterface<SPI, BUSY, DC, RST, DELAY, const SINGLE_BYTE_WRITE: bool> {
+pub(crate) struct DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, const SINGLE_BYTE_WRITE: bool> {
     /// SPI
     _spi: PhantomData<SPI>,
     /// DELAY
@@ -15,25 +16,70 @@ pub(crate) struct DisplayInterface<SPI, BUSY, DC, RST, DELAY, const SINGLE_BYTE_
     busy: BUSY,
     /// Data/Command Control Pin (High for data, Low for command)
     dc: DC,
-    /// Pin for Resetting
-    rst: RST,
+    /// Pin for Resetting, `None` if the reset line isn't connected to a GPIO
+    rst: Option<RST>,
+    /// Pin switching the power of the display, `None` if the display is always powered
+    pwr: Option<PWR>,
+    /// Whether the power pin was set since the last power off
+    powered: bool,
     /// number of ms the idle loop should sleep on
     delay_us: u32,
+    /// number of us the busy line may stay busy, `None` waits forever
+    busy_timeout_us: Option<u32>,
 }
 
-impl<SPI, BUSY, DC, RST, DELAY, const SINGLE_BYTE_WRITE: bool>
-    DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>
+/// Placeholder for a pin which isn't connected, e.g. `None::<NoPin>` for a display whose reset
+/// line is driven by the reset supervisor of the MCU
+///
+/// Setting it does nothing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NoPin;
+
+impl ErrorType for NoPin {
+    type Error = core::convert::Infallible;
+}
+
+impl OutputPin for NoPin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Time the supply of the display needs to settle after switching it on
+const POWER_ON_DELAY_US: u32 = 10_000;
+
+/// Time the busy line may stay busy by default, the refresh of a black and white panel takes a
+/// few seconds
+pub(crate) const DEFAULT_BUSY_TIMEOUT_US: u32 = 10_000_000;
+
+/// Time the busy line may stay busy by default on colored panels, whose refresh takes up to 35s
+pub(crate) const COLOR_BUSY_TIMEOUT_US: u32 = 60_000_000;
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR, const SINGLE_BYTE_WRITE: bool>
+    DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     /// Creates a new `DisplayInterface` struct
     ///
-    /// If no delay is given, a default delay of 10ms is used.
-    pub fn new(busy: BUSY, dc: DC, rst: RST, delay_us: Option<u32>) -> Self {
+    /// If no delay is given, a default delay of 10ms is used. The busy line may stay busy for
+    /// [`DEFAULT_BUSY_TIMEOUT_US`].
+    pub fn new(
+        busy: BUSY,
+        dc: DC,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
+        delay_us: Option<u32>,
+    ) -> Self {
         // default delay of 10ms
         let delay_us = delay_us.unwrap_or(10_000);
         DisplayInterface {
@@ -42,16 +88,41 @@ where
             busy,
             dc,
             rst,
+            pwr,
+            powered: false,
             delay_us,
+            busy_timeout_us: Some(DEFAULT_BUSY_TIMEOUT_US),
         }
     }
 
+    /// Sets the time the busy line may stay busy before waiting fails, `None` waits forever
+    pub(crate) fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.busy_timeout_us = timeout_us;
+    }
+
+    /// Same as `new`, with the default timeout of the colored panels
+    pub(crate) fn new_color(
+        busy: BUSY,
+        dc: DC,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
+        delay_us: Option<u32>,
+    ) -> Self {
+        let mut interface = Self::new(busy, dc, rst, pwr, delay_us);
+        interface.set_busy_timeout(Some(COLOR_BUSY_TIMEOUT_US));
+        interface
+    }
+
     /// Basic function for sending [Commands](Command).
     ///
     /// Enables direct interaction with the device with the help of [data()](DisplayInterface::data())
-    pub(crate) fn cmd<T: Command>(&mut self, spi: &mut SPI, command: T) -> Result<(), SPI::Error> {
+    pub(crate) fn cmd<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        command: T,
+    ) -> Result<(), Error<SPI::Error>> {
         // low for commands
-        let _ = self.dc.set_low();
+        self.dc.set_low().map_err(Error::pin)?;
 
         // Transfer the command over spi
         self.write(spi, &[command.address()])
@@ -60,9 +131,9 @@ where
     /// Basic function for sending an array of u8-values of data over spi
     ///
     /// Enables direct interaction with the device with the help of [command()](Epd4in2::command())
-    pub(crate) fn data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    pub(crate) fn data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
         // high for data
-        let _ = self.dc.set_high();
+        self.dc.set_high().map_err(Error::pin)?;
 
         if SINGLE_BYTE_WRITE {
             for val in data.iter().copied() {
@@ -84,31 +155,96 @@ where
         spi: &mut SPI,
         command: T,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.cmd(spi, command)?;
         self.data(spi, data)
     }
 
+    /// Sends a [Command](Command) and reads the bytes the controller answers with into `buffer`
+    ///
+    /// The controller answers on its data line, which has to be connected to MISO as well.
+    pub(crate) fn cmd_and_read<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        command: T,
+        buffer: &mut [u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.cmd(spi, command)?;
+
+        // high for data
+        self.dc.set_high().map_err(Error::pin)?;
+        spi.read(buffer)?;
+        Ok(())
+    }
+
     /// Basic function for sending the same byte of data (one u8) multiple times over spi
     ///
+    /// Unless `SINGLE_BYTE_WRITE` is set, the bytes are sent in chunks of 256 from a stack buffer.
+    ///
     /// Enables direct interaction with the device with the help of [command()](ConnectionInterface::command())
     pub(crate) fn data_x_times(
         &mut self,
         spi: &mut SPI,
         val: u8,
         repetitions: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        // high for data
+        self.dc.set_high().map_err(Error::pin)?;
+        if SINGLE_BYTE_WRITE {
+            // Transfer data (u8) over spi
+            for _ in 0..repetitions {
+                self.write(spi, &[val])?;
+            }
+        } else {
+            // one transfer per chunk instead of per byte, a clear takes a fraction of the time
+            let chunk = [val; 256];
+            let mut remaining = repetitions as usize;
+            while remaining > 0 {
+                let n = remaining.min(chunk.len());
+                self.write(spi, &chunk[..n])?;
+                remaining -= n;
+            }
+        }
+        Ok(())
+    }
+
+    /// Basic function for sending `len` bytes of data taken from an iterator over spi
+    ///
+    /// The bytes are sent in small chunks, so the data never has to be in memory at once.
+    /// Fails with [`Error::InvalidBufferLength`] if `data` ends before `len` bytes, further items
+    /// are left unconsumed.
+    pub(crate) fn data_iter<I: IntoIterator<Item = u8>>(
+        &mut self,
+        spi: &mut SPI,
+        data: I,
+        len: usize,
+    ) -> Result<(), Error<SPI::Error>> {
         // high for data
-        let _ = self.dc.set_high();
-        // Transfer data (u8) over spi
-        for _ in 0..repetitions {
-            self.write(spi, &[val])?;
+        self.dc.set_high().map_err(Error::pin)?;
+
+        let mut data = data.into_iter();
+        let mut chunk = [0u8; 32];
+        let mut remaining = len;
+        while remaining > 0 {
+            let n = if SINGLE_BYTE_WRITE {
+                1
+            } else {
+                remaining.min(chunk.len())
+            };
+            for (i, byte) in chunk[..n].iter_mut().enumerate() {
+                *byte = data.next().ok_or(Error::InvalidBufferLength {
+                    expected: len,
+                    got: len - remaining + i,
+                })?;
+            }
+            self.write(spi, &chunk[..n])?;
+            remaining -= n;
         }
         Ok(())
     }
 
     // spi write helper/abstraction function
-    fn write(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    fn write(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
         // transfer spi data
         // Be careful!! Linux has a default limit of 4096 bytes per spi transfer
         // see https://raspberrypi.stackexchange.com/questions/65595/spi-transfer-fails-with-buffer-size-greater-than-4096
@@ -118,7 +254,8 @@ where
             }
             Ok(())
         } else {
-            spi.write(data)
+            spi.write(data)?;
+            Ok(())
         }
     }
 
@@ -134,8 +271,18 @@ where
     ///  - FALSE for epd2in9, epd1in54 (for all Display Type A ones?)
     ///
     /// Most likely there was a mistake with the 2in9 busy connection
-    pub(crate) fn wait_until_idle(&mut self, delay: &mut DELAY, is_busy_low: bool) {
-        while self.is_busy(is_busy_low) {
+    ///
+    /// Fails with [`Error::BusyTimeout`] once the sleeps of the idle loop add up to the timeout.
+    /// Without a delay every check of the busy line counts as 1us, so the timeout is reached
+    /// later than set.
+    pub(crate) fn wait_until_idle(
+        &mut self,
+        delay: &mut DELAY,
+        is_busy_low: bool,
+    ) -> Result<(), Error<SPI::Error>> {
+        let mut waited = 0u32;
+        while self.is_busy(is_busy_low)? {
+            waited = self.wait_step(waited)?;
             // This has been removed and added many time :
             // - it is faster to not have it
             // - it is complicated to pass the delay everywhere all the time
@@ -146,6 +293,7 @@ where
                 delay.delay_us(self.delay_us);
             }
         }
+        Ok(())
     }
 
     /// Same as `wait_until_idle` for device needing a command to probe Busy pin
@@ -155,12 +303,14 @@ where
         delay: &mut DELAY,
         is_busy_low: bool,
         status_command: T,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.cmd(spi, status_command)?;
         if self.delay_us > 0 {
             delay.delay_us(self.delay_us);
         }
-        while self.is_busy(is_busy_low) {
+        let mut waited = 0u32;
+        while self.is_busy(is_busy_low)? {
+            waited = self.wait_step(waited)?;
             self.cmd(spi, status_command)?;
             if self.delay_us > 0 {
                 delay.delay_us(self.delay_us);
@@ -169,6 +319,17 @@ where
         Ok(())
     }
 
+    /// Adds a sleep of the idle loop to the `waited` us, fails if the timeout has passed
+    fn wait_step(&self, waited: u32) -> Result<u32, Error<SPI::Error>> {
+        if self
+            .busy_timeout_us
+            .map_or(false, |timeout| waited >= timeout)
+        {
+            return Err(Error::BusyTimeout);
+        }
+        Ok(waited.saturating_add(self.delay_us.max(1)))
+    }
+
     /// Checks if device is still busy
     ///
     /// This is normally handled by the more complicated commands themselves,
@@ -182,9 +343,89 @@ where
     ///
     /// Most likely there was a mistake with the 2in9 busy connection
     /// //TODO: use the #cfg feature to make this compile the right way for the certain types
-    pub(crate) fn is_busy(&mut self, is_busy_low: bool) -> bool {
-        (is_busy_low && self.busy.is_low().unwrap_or(false))
-            || (!is_busy_low && self.busy.is_high().unwrap_or(false))
+    pub(crate) fn is_busy(&mut self, is_busy_low: bool) -> Result<bool, Error<SPI::Error>> {
+        if is_busy_low {
+            self.busy.is_low().map_err(Error::pin)
+        } else {
+            self.busy.is_high().map_err(Error::pin)
+        }
+    }
+
+    /// Whether the reset line is connected, otherwise [`reset`](Self::reset) does nothing
+    pub(crate) fn has_reset_pin(&self) -> bool {
+        self.rst.is_some()
+    }
+
+    /// Resets an SSD controller by its software reset `command`, 0x12, in place of the reset
+    /// line, and waits until it's idle
+    ///
+    /// Does nothing with a reset pin, [`reset`](Self::reset) has reset the controller then.
+    pub(crate) fn ssd_software_reset<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        command: T,
+        is_busy_low: bool,
+    ) -> Result<(), Error<SPI::Error>> {
+        if self.has_reset_pin() {
+            return Ok(());
+        }
+        self.cmd(spi, command)?;
+        self.wait_until_idle(delay, is_busy_low)
+    }
+
+    /// Resets a UC controller in place of the reset line, and waits until it's idle
+    ///
+    /// The controller resets when RST_N, bit 0 of the first byte of the panel setting `command`
+    /// (0x00), is cleared. `panel_setting` is that byte, it's written with RST_N cleared and the
+    /// panel setting of the init sets it again. Does nothing with a reset pin, like
+    /// [`ssd_software_reset`](Self::ssd_software_reset).
+    pub(crate) fn uc_software_reset<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        command: T,
+        panel_setting: u8,
+        is_busy_low: bool,
+    ) -> Result<(), Error<SPI::Error>> {
+        if self.has_reset_pin() {
+            return Ok(());
+        }
+        self.cmd_with_data(spi, command, &[panel_setting & !0x01])?;
+        self.wait_until_idle(delay, is_busy_low)
+    }
+
+    /// Switches the power of the display on and waits for the supply to settle
+    ///
+    /// Does nothing without a power pin or if the display is already powered.
+    pub(crate) fn power_on(&mut self, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        if self.powered {
+            return Ok(());
+        }
+        if let Some(pwr) = self.pwr.as_mut() {
+            pwr.set_high().map_err(Error::pin)?;
+            delay.delay_us(POWER_ON_DELAY_US);
+            self.powered = true;
+        }
+        Ok(())
+    }
+
+    /// Switches the power of the display off
+    ///
+    /// DC and RST are pulled low as well, so the unpowered controller isn't supplied through
+    /// them. Does nothing without a power pin.
+    pub(crate) fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        let pwr = match self.pwr.as_mut() {
+            Some(pwr) => pwr,
+            None => return Ok(()),
+        };
+        pwr.set_low().map_err(Error::pin)?;
+        self.powered = false;
+        self.dc.set_low().map_err(Error::pin)?;
+        if let Some(rst) = self.rst.as_mut() {
+            rst.set_low().map_err(Error::pin)?;
+        }
+        Ok(())
     }
 
     /// Resets the device.
@@ -194,15 +435,345 @@ where
     /// The timing of keeping the reset pin low seems to be important and different per device.
     /// Most displays seem to require keeping it low for 10ms, but the 7in5_v2 only seems to reset
     /// properly with 2ms
-    pub(crate) fn reset(&mut self, delay: &mut DELAY, initial_delay: u32, duration: u32) {
-        let _ = self.rst.set_high();
+    ///
+    /// Without a reset pin nothing is done, the drivers send their software reset instead, see
+    /// [`ssd_software_reset`](Self::ssd_software_reset) and
+    /// [`uc_software_reset`](Self::uc_software_reset).
+    ///
+    /// A display switched off by [`power_off`](Self::power_off) is powered on first, the
+    /// controller has to be powered while the reset is released.
+    pub(crate) fn reset(
+        &mut self,
+        delay: &mut DELAY,
+        initial_delay: u32,
+        duration: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.power_on(delay)?;
+        let rst = match self.rst.as_mut() {
+            Some(rst) => rst,
+            None => return Ok(()),
+        };
+        rst.set_high().map_err(Error::pin)?;
         delay.delay_us(initial_delay);
 
-        let _ = self.rst.set_low();
+        rst.set_low().map_err(Error::pin)?;
         delay.delay_us(duration);
-        let _ = self.rst.set_high();
+        rst.set_high().map_err(Error::pin)?;
         //TODO: the upstream libraries always sleep for 200ms here
         // 10ms works fine with just for the 7in5_v2 but this needs to be validated for other devices
         delay.delay_us(200_000);
+        Ok(())
+    }
+}
+
+/// Expected transactions of a [`DisplayInterface`] for the mocks of embedded-hal-mock
+#[cfg(test)]
+pub(crate) mod mock {
+    extern crate std;
+    use embedded_hal_mock::eh1::{
+        digital::{State, Transaction as PinTransaction},
+        spi::Transaction as SpiTransaction,
+    };
+    use std::{vec, vec::Vec};
+
+    /// Expected spi and dc transactions for a list of commands with their data, written bytewise
+    pub(crate) fn expect_commands(
+        commands: &[(u8, &[u8])],
+    ) -> (Vec<SpiTransaction<u8>>, Vec<PinTransaction>) {
+        let mut spi = Vec::new();
+        let mut dc = Vec::new();
+        for (command, data) in commands {
+            dc.push(PinTransaction::set(State::Low));
+            if !data.is_empty() {
+                dc.push(PinTransaction::set(State::High));
+            }
+            for byte in core::iter::once(command).chain(data.iter()) {
+                spi.push(SpiTransaction::transaction_start());
+                spi.push(SpiTransaction::write_vec(vec![*byte]));
+                spi.push(SpiTransaction::transaction_end());
+            }
+        }
+        (spi, dc)
+    }
+
+    /// Like [`expect_commands`] for an interface which writes the data of a command in one block
+    pub(crate) fn expect_commands_blockwise(
+        commands: &[(u8, &[u8])],
+    ) -> (Vec<SpiTransaction<u8>>, Vec<PinTransaction>) {
+        let mut spi = Vec::new();
+        let mut dc = Vec::new();
+        for (command, data) in commands {
+            dc.push(PinTransaction::set(State::Low));
+            spi.push(SpiTransaction::transaction_start());
+            spi.push(SpiTransaction::write_vec(vec![*command]));
+            spi.push(SpiTransaction::transaction_end());
+            if !data.is_empty() {
+                dc.push(PinTransaction::set(State::High));
+                spi.push(SpiTransaction::transaction_start());
+                spi.push(SpiTransaction::write_vec(data.to_vec()));
+                spi.push(SpiTransaction::transaction_end());
+            }
+        }
+        (spi, dc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+    use embedded_hal_mock::eh1::{
+        delay::NoopDelay,
+        digital::{Mock as PinMock, State, Transaction as PinTransaction},
+        spi::Mock as SpiMock,
+    };
+
+    #[test]
+    fn busy_timeout() {
+        let mut busy = PinMock::new(&[
+            PinTransaction::get(State::High),
+            PinTransaction::get(State::High),
+            PinTransaction::get(State::High),
+            PinTransaction::get(State::High),
+            PinTransaction::get(State::Low),
+        ]);
+        let mut dc = PinMock::new(&[]);
+        let mut rst = PinMock::new(&[]);
+        let mut delay = NoopDelay::new();
+
+        let mut interface = DisplayInterface::<SpiMock<u8>, _, _, _, NoopDelay, NoPin, true>::new(
+            busy.clone(),
+            dc.clone(),
+            Some(rst.clone()),
+            None,
+            Some(10),
+        );
+        interface.set_busy_timeout(Some(30));
+        assert_eq!(
+            interface.wait_until_idle(&mut delay, false),
+            Err(Error::BusyTimeout)
+        );
+        // the next wait starts over
+        assert_eq!(interface.wait_until_idle(&mut delay, false), Ok(()));
+        drop(interface);
+
+        busy.done();
+        dc.done();
+        rst.done();
+    }
+
+    #[test]
+    fn pin_error() {
+        use embedded_hal_mock::eh1::MockError;
+        use std::io::ErrorKind as IoErrorKind;
+
+        let mut busy = PinMock::new(&[]);
+        let mut dc =
+            PinMock::new(&[PinTransaction::set(State::Low)
+                .with_error(MockError::Io(IoErrorKind::NotConnected))]);
+        let mut rst = PinMock::new(&[]);
+        let mut spi = SpiMock::new(&[]);
+
+        let mut interface = DisplayInterface::<_, _, _, _, NoopDelay, NoPin, true>::new(
+            busy.clone(),
+            dc.clone(),
+            Some(rst.clone()),
+            None,
+            None,
+        );
+        assert_eq!(
+            interface.cmd(&mut spi, TestCommand),
+            Err(Error::Pin(ErrorKind::Other))
+        );
+        drop(interface);
+
+        busy.done();
+        dc.done();
+        rst.done();
+        spi.done();
+    }
+
+    #[test]
+    fn power_sequence() {
+        let mut busy = PinMock::new(&[]);
+        let mut dc = PinMock::new(&[PinTransaction::set(State::Low)]);
+        // power comes up before the reset is released
+        let mut pwr = PinMock::new(&[
+            PinTransaction::set(State::High),
+            PinTransaction::set(State::Low),
+            PinTransaction::set(State::High),
+        ]);
+        let mut rst = PinMock::new(&[
+            PinTransaction::set(State::High),
+            PinTransaction::set(State::Low),
+            PinTransaction::set(State::High),
+            PinTransaction::set(State::Low),
+            PinTransaction::set(State::High),
+            PinTransaction::set(State::Low),
+            PinTransaction::set(State::High),
+        ]);
+        let mut delay = NoopDelay::new();
+
+        let mut interface = DisplayInterface::<SpiMock<u8>, _, _, _, NoopDelay, _, true>::new(
+            busy.clone(),
+            dc.clone(),
+            Some(rst.clone()),
+            Some(pwr.clone()),
+            None,
+        );
+        interface.reset(&mut delay, 10_000, 10_000).unwrap();
+        interface.power_off().unwrap();
+        interface.reset(&mut delay, 10_000, 10_000).unwrap();
+        drop(interface);
+
+        busy.done();
+        dc.done();
+        pwr.done();
+        rst.done();
+    }
+
+    #[test]
+    fn data_iter_fails_on_short_data() {
+        use embedded_hal_mock::eh1::spi::Transaction as SpiTransaction;
+        use std::{vec, vec::Vec};
+
+        // a full chunk of 32 bytes is written, the next one ends after 3 bytes
+        let mut spi = SpiMock::new(&[
+            SpiTransaction::transaction_start(),
+            SpiTransaction::write_vec((0..32).collect::<Vec<u8>>()),
+            SpiTransaction::transaction_end(),
+        ]);
+        let mut busy = PinMock::new(&[]);
+        let mut dc = PinMock::new(&[PinTransaction::set(State::High)]);
+        let mut interface = DisplayInterface::<_, _, _, PinMock, NoopDelay, NoPin, false>::new(
+            busy.clone(),
+            dc.clone(),
+            None,
+            None,
+            None,
+        );
+        assert_eq!(
+            interface.data_iter(&mut spi, 0..35, 40),
+            Err(Error::InvalidBufferLength {
+                expected: 40,
+                got: 35
+            })
+        );
+        drop(interface);
+        spi.done();
+        busy.done();
+        dc.done();
+
+        let mut spi = SpiMock::new(&[
+            SpiTransaction::transaction_start(),
+            SpiTransaction::write_vec(vec![1]),
+            SpiTransaction::transaction_end(),
+        ]);
+        let mut busy = PinMock::new(&[]);
+        let mut dc = PinMock::new(&[PinTransaction::set(State::High)]);
+        let mut interface = DisplayInterface::<_, _, _, PinMock, NoopDelay, NoPin, true>::new(
+            busy.clone(),
+            dc.clone(),
+            None,
+            None,
+            None,
+        );
+        assert_eq!(
+            interface.data_iter(&mut spi, [1], 2),
+            Err(Error::InvalidBufferLength {
+                expected: 2,
+                got: 1
+            })
+        );
+        drop(interface);
+        spi.done();
+        busy.done();
+        dc.done();
+    }
+
+    #[test]
+    fn software_reset_without_reset_pin() {
+        use super::mock::expect_commands;
+
+        let (spi_expectations, dc_expectations) = expect_commands(&[(0x12, &[]), (0x00, &[0x8E])]);
+        let mut spi = SpiMock::new(&spi_expectations);
+        // busy once after each reset
+        let mut busy = PinMock::new(&[
+            PinTransaction::get(State::High),
+            PinTransaction::get(State::Low),
+            PinTransaction::get(State::Low),
+            PinTransaction::get(State::High),
+        ]);
+        let mut dc = PinMock::new(&dc_expectations);
+        let mut delay = NoopDelay::new();
+
+        let mut interface = DisplayInterface::<_, _, _, PinMock, NoopDelay, NoPin, true>::new(
+            busy.clone(),
+            dc.clone(),
+            None,
+            None,
+            None,
+        );
+        interface.reset(&mut delay, 10_000, 2_000).unwrap();
+        interface
+            .ssd_software_reset(&mut spi, &mut delay, TestCommand, false)
+            .unwrap();
+        // RST_N is cleared
+        interface
+            .uc_software_reset(&mut spi, &mut delay, TestPanelSetting, 0x8F, true)
+            .unwrap();
+        drop(interface);
+
+        spi.done();
+        busy.done();
+        dc.done();
+
+        // the reset pin has done it already
+        let mut spi = SpiMock::new(&[]);
+        let mut busy = PinMock::new(&[]);
+        let mut dc = PinMock::new(&[]);
+        let mut rst = PinMock::new(&[
+            PinTransaction::set(State::High),
+            PinTransaction::set(State::Low),
+            PinTransaction::set(State::High),
+        ]);
+        let mut interface = DisplayInterface::<_, _, _, _, NoopDelay, NoPin, true>::new(
+            busy.clone(),
+            dc.clone(),
+            Some(rst.clone()),
+            None,
+            None,
+        );
+        interface.reset(&mut delay, 10_000, 2_000).unwrap();
+        interface
+            .ssd_software_reset(&mut spi, &mut delay, TestCommand, false)
+            .unwrap();
+        interface
+            .uc_software_reset(&mut spi, &mut delay, TestPanelSetting, 0x8F, true)
+            .unwrap();
+        drop(interface);
+
+        spi.done();
+        busy.done();
+        dc.done();
+        rst.done();
+    }
+
+    #[derive(Clone, Copy)]
+    struct TestCommand;
+
+    impl Command for TestCommand {
+        fn address(self) -> u8 {
+            0x12
+        }
+    }
+
+    #[derive(Clone, Copy)]
+    struct TestPanelSetting;
+
+    impl Command for TestPanelSetting {
+        fn address(self) -> u8 {
+            0x00
+        }
     }
 }
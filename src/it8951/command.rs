@@ -0,0 +1,32 @@
+//! SPI Commands and registers of the IT8951 controller
+
+/// Preamble of a command word
+pub(crate) const PREAMBLE_COMMAND: u16 = 0x6000;
+/// Preamble of data words written to the controller
+pub(crate) const PREAMBLE_WRITE: u16 = 0x0000;
+/// Preamble of data words read from the controller
+pub(crate) const PREAMBLE_READ: u16 = 0x1000;
+
+/// I80 command parameter configuration, enables packed writes
+pub(crate) const I80CPCR: u16 = 0x0004;
+/// Image buffer address of the next image load (low word, high word at `LISAR + 2`)
+pub(crate) const LISAR: u16 = 0x0208;
+/// Upper bits of the update parameter 1 setting, bit 2 selects the 1 bpp mode
+pub(crate) const UP1SR_HIGH: u16 = 0x113A;
+/// Gray values of cleared (bits 7:0) and set (bits 15:8) pixels in 1 bpp mode
+pub(crate) const BGVR: u16 = 0x1250;
+/// LUT engine status, 0 when all display updates are done
+pub(crate) const LUTAFSR: u16 = 0x1224;
+
+#[derive(Copy, Clone)]
+pub(crate) enum Command {
+    SystemRun = 0x0001,
+    Sleep = 0x0003,
+    RegisterRead = 0x0010,
+    RegisterWrite = 0x0011,
+    LoadImageArea = 0x0021,
+    LoadImageEnd = 0x0022,
+    DisplayArea = 0x0034,
+    Vcom = 0x0039,
+    GetDeviceInfo = 0x0302,
+}
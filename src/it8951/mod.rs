@@ -0,0 +1,831 @@
+//! A driver for the IT8951 controller of the large Waveshare E-Ink Displays
+//! (6", 7.8", 9.7", 10.3" e-Paper HAT)
+//!
+//! The IT8951 doesn't use a data/command pin: every transfer starts with a 16 bit preamble and
+//! the BUSY pin is the controller's HRDY signal (low while busy). The DC pin given to
+//! [`new`](WaveshareDisplay::new) is not used. The resolution is read from the controller, so
+//! there is no `Display` type for these panels, use a [`VarDisplay`](crate::graphics::VarDisplay)
+//! with `width()` and `height()`.
+//!
+//! [`WaveshareDisplay`] sends 1 bpp frames. The panels support 16 gray levels and several
+//! waveforms on top of that, see [`load_image_area`](Epd::load_image_area) and
+//! [`display_area`](Epd::display_area). [`WaveformMode::A2`] refreshes black/white content in
+//! less than a second.
+//!
+//! Every panel has its own VCOM, it is printed on the flexible cable and should be set with
+//! [`set_vcom`](Epd::set_vcom) after creating the driver.
+//!
+//! # References
+//!
+//! - [Waveshare Wiki](https://www.waveshare.com/wiki/10.3inch_e-Paper_HAT)
+//! - [IT8951 Datasheet](https://www.waveshare.com/w/upload/1/18/IT8951_D_V0.2.4.3_20170728.pdf)
+//! - [Waveshare C driver](https://github.com/waveshareteam/IT8951-ePaper/blob/master/Raspberry/lib/e-Paper/EPD_IT8951.c)
+//!
+//! # Example
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), epd_waveshare::error::Error<embedded_hal::spi::ErrorKind>> {
+//!use epd_waveshare::{it8951::*, prelude::*, rect::Rect};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = digital::Mock::new(&expectations);
+//!# let dc = digital::Mock::new(&expectations);
+//!# let rst = digital::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
+//!
+//!// Setup EPD, the VCOM of this panel is -1.48 V
+//!let mut epd = Epd::new(&mut spi, busy_in, dc, Some(rst), None::<NoPin>, &mut delay, None)?;
+//!epd.set_vcom(&mut spi, &mut delay, -1480)?;
+//!
+//!// A gradient of the 16 gray levels, two pixels per byte
+//!let area = Rect::new(0, 0, 32, 16);
+//!let mut image = [0u8; 16 * 16];
+//!for (i, byte) in image.iter_mut().enumerate() {
+//!    let gray = (i % 16) as u8;
+//!    *byte = gray | gray << 4;
+//!}
+//!epd.load_image_area(&mut spi, &mut delay, PixelFormat::Gray4, area, &image)?;
+//!epd.display_area(&mut spi, &mut delay, area, WaveformMode::Gc16)?;
+//!
+//!// Set the EPD to sleep
+//!epd.sleep(&mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+
+use crate::error::Error;
+use embedded_hal::{
+    delay::DelayNs,
+    digital::{InputPin, OutputPin},
+    spi::{Operation, SpiDevice},
+};
+
+use crate::color::Color;
+use crate::interface::DisplayInterface;
+use crate::rect::Rect;
+use crate::traits::{check_refresh_lut, InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::{check_buffer_len, check_partial_window};
+
+pub(crate) mod command;
+use self::command::*;
+
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+/// VCOM used until [`set_vcom`](WaveshareDisplay::set_vcom) is called, in mV below 0 V
+/// (-1.5 V)
+pub const DEFAULT_VCOM: u16 = 1500;
+/// HRDY is low while the controller is busy
+const IS_BUSY_LOW: bool = true;
+const SINGLE_BYTE_WRITE: bool = false;
+
+/// Bytes sent per SPI transaction of image data
+const CHUNK_LEN: usize = 512;
+/// Gray values of the 1 bpp mode: set bits are white, cleared bits black
+const ONE_BPP_GRAYS: u16 = 0xF0 << 8;
+
+/// Pixel formats for [`load_image_area`](Epd::load_image_area)
+///
+/// 0 is black, the highest value white. With [`Gray8`](PixelFormat::Gray8) only the upper 4 bits
+/// are used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// 2 bits per pixel, 4 gray levels
+    Gray2 = 0,
+    /// 4 bits per pixel, 16 gray levels
+    Gray4 = 2,
+    /// 8 bits per pixel
+    Gray8 = 3,
+}
+
+impl PixelFormat {
+    /// Bits per pixel
+    pub fn bits_per_pixel(self) -> u32 {
+        match self {
+            PixelFormat::Gray2 => 2,
+            PixelFormat::Gray4 => 4,
+            PixelFormat::Gray8 => 8,
+        }
+    }
+}
+
+/// Number of bytes of an image `width` x `height` pixels in `format`
+///
+/// Each row is padded to a 16 bit word, the first pixel is in the least significant bits.
+pub fn image_len(format: PixelFormat, width: u32, height: u32) -> usize {
+    let row_bytes = (width * format.bits_per_pixel() + 15) / 16 * 2;
+    (row_bytes * height) as usize
+}
+
+/// Waveforms of the display engine
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaveformMode {
+    /// Clears the display to white, with a lot of flashing
+    Init,
+    /// Fast, any gray to black or white
+    Du,
+    /// Full refresh with 16 gray levels
+    Gc16,
+    /// Like GC16 with less flashing, for gray on white content
+    Gl16,
+    /// Fastest, black to white and back only
+    A2,
+}
+
+/// Information read from the controller by [`new`](WaveshareDisplay::new)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceInfo {
+    /// Width of the panel in pixels
+    pub width: u16,
+    /// Height of the panel in pixels
+    pub height: u16,
+    /// Address of the image buffer in the controller's memory
+    pub image_buffer_address: u32,
+    /// Firmware version, a nul padded ASCII string
+    pub firmware_version: [u8; 16],
+    /// Waveform LUT version, a nul padded ASCII string
+    pub lut_version: [u8; 16],
+}
+
+impl DeviceInfo {
+    /// Parses the 20 words sent by `GetDeviceInfo`
+    fn from_bytes(bytes: &[u8; 40]) -> Self {
+        let word = |i: usize| u16::from_be_bytes([bytes[2 * i], bytes[2 * i + 1]]);
+        // the strings are stored in little endian words
+        let string = |start: usize| {
+            let mut s = [0u8; 16];
+            for (i, pair) in s.chunks_exact_mut(2).enumerate() {
+                pair.copy_from_slice(&word(start + i).to_le_bytes());
+            }
+            s
+        };
+        DeviceInfo {
+            width: word(0),
+            height: word(1),
+            image_buffer_address: u32::from(word(2)) | u32::from(word(3)) << 16,
+            firmware_version: string(4),
+            lut_version: string(12),
+        }
+    }
+
+    /// The A2 waveform is mode 4 for the M641 LUT (6" and 9.7" panels) and mode 6 otherwise
+    fn a2_mode(&self) -> u16 {
+        if self.lut_version.starts_with(b"M641") {
+            4
+        } else {
+            6
+        }
+    }
+}
+
+/// IT8951 driver
+pub struct Epd<SPI, BUSY, DC, RST, DELAY, PWR> {
+    /// Connection Interface, only used for the reset and busy (HRDY) pins
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    background_color: Color,
+    /// Panel information
+    info: DeviceInfo,
+    /// Waveform of `display_frame`
+    mode: WaveformMode,
+    /// VCOM in mV
+    vcom: u16,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, 200_000, 10_000)?;
+
+        self.command(spi, delay, Command::SystemRun)?;
+
+        self.command(spi, delay, Command::GetDeviceInfo)?;
+        let mut bytes = [0u8; 40];
+        self.read_data(spi, delay, &mut bytes)?;
+        self.info = DeviceInfo::from_bytes(&bytes);
+
+        // all image loads go to the image buffer
+        let address = self.info.image_buffer_address;
+        self.write_register(spi, delay, LISAR + 2, (address >> 16) as u16)?;
+        self.write_register(spi, delay, LISAR, address as u16)?;
+
+        // packed writes
+        self.write_register(spi, delay, I80CPCR, 0x0001)?;
+
+        let vcom = self.vcom;
+        self.command_with_args(spi, delay, Command::Vcom, &[0x0001, vcom])
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
+    for Epd<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    type DisplayColor = Color;
+    const REFRESH_LUTS: &'static [RefreshLut] = &[
+        RefreshLut::Full,
+        RefreshLut::Quick,
+        RefreshLut::PartialRefresh,
+    ];
+
+    fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(busy, dc, rst, pwr, delay_us);
+
+        let mut epd = Epd {
+            interface,
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            info: DeviceInfo::from_bytes(&[0; 40]),
+            mode: WaveformMode::Gc16,
+            vcom: DEFAULT_VCOM,
+        };
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.command(spi, delay, Command::Sleep)?;
+        self.interface.power_off()
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.init(spi, delay)
+    }
+
+    fn set_background_color(&mut self, background_color: Color) {
+        self.background_color = background_color;
+    }
+
+    /// The IT8951 takes the VCOM in steps of 1 mV, it fails with `Error::Unsupported` for a
+    /// positive VCOM
+    fn set_vcom(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        millivolts: i16,
+    ) -> Result<(), Error<SPI::Error>> {
+        if millivolts > 0 {
+            return Err(Error::Unsupported);
+        }
+        self.vcom = millivolts.unsigned_abs();
+        self.command_with_args(spi, delay, Command::Vcom, &[0x0001, self.vcom])
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.interface.power_off()
+    }
+
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        // HRDY only tells whether the controller takes a command, the refresh keeps running
+        // after it until `wait_until_idle` sees the LUT engines idle
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.background_color
+    }
+
+    fn width(&self) -> u32 {
+        self.info.width.into()
+    }
+
+    fn height(&self) -> u32 {
+        self.info.height.into()
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        let line_bytes = (self.width() as usize + 7) / 8;
+        check_buffer_len(buffer.len(), line_bytes * self.height() as usize)?;
+
+        // the loaded lines are padded to 32 bit
+        let load_bytes = (line_bytes + 3) / 4 * 4;
+        let area = Rect::new(0, 0, load_bytes as u32, self.height());
+        let bytes = buffer.chunks(line_bytes).flat_map(|line| {
+            line.iter()
+                .map(|byte| byte.reverse_bits())
+                .chain(core::iter::repeat(0xFF).take(load_bytes - line_bytes))
+        });
+        self.load_area(spi, delay, PixelFormat::Gray8, area, bytes)
+    }
+
+    /// Loads a window of a 1 bpp frame, `x` and `width` must be multiples of 32
+    fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        if x % 32 != 0 || width % 32 != 0 {
+            return Err(Error::OutOfBounds {
+                x,
+                y,
+                width,
+                height,
+            });
+        }
+        check_partial_window(
+            buffer.len(),
+            x,
+            y,
+            width,
+            height,
+            self.width(),
+            self.height(),
+        )?;
+
+        let area = Rect::new(x / 8, y, width / 8, height);
+        let bytes = buffer.iter().map(|byte| byte.reverse_bits());
+        self.load_area(spi, delay, PixelFormat::Gray8, area, bytes)
+    }
+
+    /// Shows the 1 bpp frame with the waveform selected by `set_lut`
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+
+        let up1sr = self.read_register(spi, delay, UP1SR_HIGH)?;
+        self.write_register(spi, delay, UP1SR_HIGH, up1sr | 1 << 2)?;
+        self.write_register(spi, delay, BGVR, ONE_BPP_GRAYS)?;
+
+        let area = Rect::new(0, 0, self.width(), self.height());
+        let mode = self.mode;
+        self.display_area(spi, delay, area, mode)?;
+        self.wait_until_idle(spi, delay)?;
+
+        self.write_register(spi, delay, UP1SR_HIGH, up1sr & !(1 << 2))
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let load_bytes = (self.width() + 31) / 32 * 4;
+        let area = Rect::new(0, 0, load_bytes, self.height());
+        let color = self.background_color.get_byte_value();
+        let len = (load_bytes * self.height()) as usize;
+        self.load_area(
+            spi,
+            delay,
+            PixelFormat::Gray8,
+            area,
+            core::iter::repeat(color).take(len),
+        )
+    }
+
+    /// `Full` selects [`WaveformMode::Gc16`], `Quick` [`WaveformMode::A2`] and `PartialRefresh`
+    /// [`WaveformMode::Du`] for `display_frame`
+    fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_refresh_lut(refresh_rate, Self::REFRESH_LUTS)?;
+        if let Some(refresh_lut) = refresh_rate {
+            self.mode = match refresh_lut {
+                RefreshLut::Full => WaveformMode::Gc16,
+                RefreshLut::Quick => WaveformMode::A2,
+                RefreshLut::PartialRefresh => WaveformMode::Du,
+            };
+        }
+        Ok(())
+    }
+
+    /// Waits until the display engine finished all updates
+    fn wait_until_idle(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        while self.read_register(spi, delay, LUTAFSR)? != 0 {
+            delay.delay_us(1_000);
+        }
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, PWR> Epd<SPI, BUSY, DC, RST, DELAY, PWR>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Information about the panel, read from the controller
+    pub fn device_info(&self) -> &DeviceInfo {
+        &self.info
+    }
+
+    /// Loads a gray image into an area of the controller's image buffer
+    ///
+    /// `data` holds `area.h` rows, each padded to 16 bit, see [`image_len`]. Show it with
+    /// [`display_area`](Epd::display_area).
+    pub fn load_image_area(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        format: PixelFormat,
+        area: Rect,
+        data: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        let inside =
+            |start: u32, len: u32, max: u32| start.checked_add(len).map_or(false, |end| end <= max);
+        if !inside(area.x, area.w, self.width()) || !inside(area.y, area.h, self.height()) {
+            return Err(Error::OutOfBounds {
+                x: area.x,
+                y: area.y,
+                width: area.w,
+                height: area.h,
+            });
+        }
+        check_buffer_len(data.len(), image_len(format, area.w, area.h))?;
+        self.load_area(spi, delay, format, area, data.iter().copied())
+    }
+
+    /// Refreshes an area of the display with the given waveform
+    ///
+    /// This doesn't wait for the refresh to finish, which allows several areas to be updated at
+    /// once. [`WaveformMode::A2`] only supports black and white pixels.
+    pub fn display_area(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        area: Rect,
+        mode: WaveformMode,
+    ) -> Result<(), Error<SPI::Error>> {
+        let mode = match mode {
+            WaveformMode::Init => 0,
+            WaveformMode::Du => 1,
+            WaveformMode::Gc16 => 2,
+            WaveformMode::Gl16 => 3,
+            WaveformMode::A2 => self.info.a2_mode(),
+        };
+        self.command_with_args(
+            spi,
+            delay,
+            Command::DisplayArea,
+            &[
+                area.x as u16,
+                area.y as u16,
+                area.w as u16,
+                area.h as u16,
+                mode,
+            ],
+        )
+    }
+
+    fn load_area<I: IntoIterator<Item = u8>>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        format: PixelFormat,
+        area: Rect,
+        bytes: I,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        // little endian, no rotation
+        self.command_with_args(
+            spi,
+            delay,
+            Command::LoadImageArea,
+            &[
+                (format as u16) << 4,
+                area.x as u16,
+                area.y as u16,
+                area.w as u16,
+                area.h as u16,
+            ],
+        )?;
+
+        let mut chunk = [0u8; CHUNK_LEN];
+        let mut len = 0;
+        for byte in bytes {
+            chunk[len] = byte;
+            len += 1;
+            if len == CHUNK_LEN {
+                self.write_image_chunk(spi, delay, &mut chunk)?;
+                len = 0;
+            }
+        }
+        if len > 0 {
+            if len % 2 == 1 {
+                chunk[len] = 0;
+                len += 1;
+            }
+            self.write_image_chunk(spi, delay, &mut chunk[..len])?;
+        }
+
+        self.command(spi, delay, Command::LoadImageEnd)
+    }
+
+    /// Sends image bytes, the controller takes them as little endian words
+    fn write_image_chunk(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        chunk: &mut [u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        swap_pairs(chunk);
+        self.transfer(spi, delay, PREAMBLE_WRITE, chunk)
+    }
+
+    fn command(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        command: Command,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.transfer(
+            spi,
+            delay,
+            PREAMBLE_COMMAND,
+            &(command as u16).to_be_bytes(),
+        )
+    }
+
+    fn command_with_args(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        command: Command,
+        args: &[u16],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.command(spi, delay, command)?;
+        let mut bytes = [0u8; 10];
+        for (pair, arg) in bytes.chunks_exact_mut(2).zip(args) {
+            pair.copy_from_slice(&arg.to_be_bytes());
+        }
+        self.transfer(spi, delay, PREAMBLE_WRITE, &bytes[..args.len() * 2])
+    }
+
+    fn write_register(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        register: u16,
+        value: u16,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.command_with_args(spi, delay, Command::RegisterWrite, &[register, value])
+    }
+
+    fn read_register(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        register: u16,
+    ) -> Result<u16, Error<SPI::Error>> {
+        self.command_with_args(spi, delay, Command::RegisterRead, &[register])?;
+        let mut bytes = [0u8; 2];
+        self.read_data(spi, delay, &mut bytes)?;
+        Ok(u16::from_be_bytes(bytes))
+    }
+
+    fn read_data(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        bytes: &mut [u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)?;
+        let mut dummy = [0u8; 2];
+        spi.transaction(&mut [
+            Operation::Write(&PREAMBLE_READ.to_be_bytes()),
+            Operation::Read(&mut dummy),
+            Operation::Read(bytes),
+        ])?;
+        Ok(())
+    }
+
+    /// Sends the preamble and its words in one transaction, once HRDY is set
+    fn transfer(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        preamble: u16,
+        bytes: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)?;
+        spi.transaction(&mut [
+            Operation::Write(&preamble.to_be_bytes()),
+            Operation::Write(bytes),
+        ])?;
+        Ok(())
+    }
+}
+
+/// Swaps the bytes of each 16 bit word
+fn swap_pairs(bytes: &mut [u8]) {
+    for pair in bytes.chunks_exact_mut(2) {
+        pair.swap(0, 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+    use crate::NoPin;
+    use embedded_hal_mock::eh1::{
+        delay::NoopDelay,
+        digital::{Mock as PinMock, State, Transaction as PinTransaction},
+        spi::{Mock as SpiMock, Transaction as SpiTransaction},
+    };
+    use std::vec;
+
+    #[test]
+    fn image_lengths() {
+        assert_eq!(image_len(PixelFormat::Gray4, 32, 16), 256);
+        // rows are padded to 16 bit
+        assert_eq!(image_len(PixelFormat::Gray4, 5, 2), 8);
+        assert_eq!(image_len(PixelFormat::Gray2, 9, 1), 4);
+        assert_eq!(image_len(PixelFormat::Gray8, 3, 3), 12);
+    }
+
+    #[test]
+    fn swapped_words() {
+        let mut bytes = [0x01, 0x02, 0x03, 0x04];
+        swap_pairs(&mut bytes);
+        assert_eq!(bytes, [0x02, 0x01, 0x04, 0x03]);
+    }
+
+    #[test]
+    fn device_info() {
+        let mut bytes = [0u8; 40];
+        // 1872 x 1404, image buffer at 0x0012_36E0
+        bytes[..8].copy_from_slice(&[0x07, 0x50, 0x05, 0x7C, 0x36, 0xE0, 0x00, 0x12]);
+        // "M641" as little endian words
+        bytes[24..28].copy_from_slice(b"6M14");
+
+        let info = DeviceInfo::from_bytes(&bytes);
+        assert_eq!(info.width, 1872);
+        assert_eq!(info.height, 1404);
+        assert_eq!(info.image_buffer_address, 0x0012_36E0);
+        assert_eq!(&info.lut_version[..5], b"M641\0");
+        assert_eq!(info.a2_mode(), 4);
+        assert_eq!(DeviceInfo::from_bytes(&[0; 40]).a2_mode(), 6);
+    }
+
+    #[test]
+    fn display_area_words() {
+        let mut expectations = vec![];
+        for words in [
+            &[0x60, 0x00, 0x00, 0x34][..],
+            &[
+                0x00, 0x00, 0x00, 0x10, 0x00, 0x20, 0x00, 0x30, 0x00, 0x40, 0x00, 0x02,
+            ],
+        ] {
+            expectations.push(SpiTransaction::transaction_start());
+            expectations.push(SpiTransaction::write_vec(words[..2].to_vec()));
+            expectations.push(SpiTransaction::write_vec(words[2..].to_vec()));
+            expectations.push(SpiTransaction::transaction_end());
+        }
+        let mut spi = SpiMock::new(&expectations);
+        // HRDY is checked before each transfer
+        let busy = PinMock::new(&[
+            PinTransaction::get(State::High),
+            PinTransaction::get(State::High),
+        ]);
+        let dc = PinMock::new(&[]);
+        let rst = PinMock::new(&[]);
+        let mut delay = NoopDelay::new();
+
+        let mut epd = Epd {
+            interface: DisplayInterface::new(
+                busy.clone(),
+                dc.clone(),
+                Some(rst.clone()),
+                None::<NoPin>,
+                None,
+            ),
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            info: DeviceInfo::from_bytes(&[0; 40]),
+            mode: WaveformMode::Gc16,
+            vcom: DEFAULT_VCOM,
+        };
+        epd.display_area(
+            &mut spi,
+            &mut delay,
+            Rect::new(0x10, 0x20, 0x30, 0x40),
+            WaveformMode::Gc16,
+        )
+        .unwrap();
+
+        spi.done();
+        busy.clone().done();
+        dc.clone().done();
+        rst.clone().done();
+    }
+
+    #[test]
+    fn bad_buffers_are_rejected() {
+        let mut spi = SpiMock::new(&[]);
+        let busy = PinMock::new(&[]);
+        let dc = PinMock::new(&[]);
+        let rst = PinMock::new(&[]);
+        let mut delay = NoopDelay::new();
+
+        let mut bytes = [0u8; 40];
+        // 64 x 16
+        bytes[..4].copy_from_slice(&[0x00, 0x40, 0x00, 0x10]);
+        let mut epd = Epd {
+            interface: DisplayInterface::new(
+                busy.clone(),
+                dc.clone(),
+                Some(rst.clone()),
+                None::<NoPin>,
+                None,
+            ),
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            info: DeviceInfo::from_bytes(&bytes),
+            mode: WaveformMode::Gc16,
+            vcom: DEFAULT_VCOM,
+        };
+        assert!(matches!(
+            epd.update_frame(&mut spi, &[0; 8], &mut delay),
+            Err(Error::InvalidBufferLength {
+                expected: 128,
+                got: 8
+            })
+        ));
+        assert!(matches!(
+            epd.update_partial_frame(&mut spi, &mut delay, &[0; 4], 16, 0, 32, 1),
+            Err(Error::OutOfBounds { x: 16, .. })
+        ));
+        assert!(matches!(
+            epd.update_partial_frame(&mut spi, &mut delay, &[0; 4], 32, 0, 64, 1),
+            Err(Error::OutOfBounds { x: 32, .. })
+        ));
+        assert!(matches!(
+            epd.load_image_area(
+                &mut spi,
+                &mut delay,
+                PixelFormat::Gray8,
+                Rect::new(0, 15, 2, 2),
+                &[0; 4]
+            ),
+            Err(Error::OutOfBounds { y: 15, .. })
+        ));
+        assert!(matches!(
+            epd.load_image_area(
+                &mut spi,
+                &mut delay,
+                PixelFormat::Gray8,
+                Rect::new(0, 0, 2, 2),
+                &[0; 3]
+            ),
+            Err(Error::InvalidBufferLength { got: 3, .. })
+        ));
+
+        spi.done();
+        busy.clone().done();
+        dc.clone().done();
+        rst.clone().done();
+    }
+}
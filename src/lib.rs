@@ -12,7 +12,7 @@
 //!
 //!```rust, no_run
 //!# use embedded_hal_mock::eh1::*;
-//!# fn main() -> Result<(), embedded_hal::spi::ErrorKind> {
+//!# fn main() -> Result<(), epd_waveshare::error::Error<embedded_hal::spi::ErrorKind>> {
 //!use embedded_graphics::{
 //!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
 //!};
@@ -28,7 +28,7 @@
 //!# let mut delay = delay::NoopDelay::new();
 //!
 //!// Setup EPD
-//!let mut epd = Epd1in54::new(&mut spi, busy_in, dc, rst, &mut delay, None)?;
+//!let mut epd = Epd1in54::new(&mut spi, busy_in, dc, Some(rst), None::<NoPin>, &mut delay, None)?;
 //!
 //!// Use display graphics from embedded-graphics
 //!let mut display = Display1in54::default();
@@ -49,6 +49,104 @@
 //!# }
 //!```
 //!
+//! # Sleep and wake up
+//!
+//! A display which is only updated now and then should spend the time in between in deep sleep.
+//! [`WaveshareDisplay::wake_up`](prelude::WaveshareDisplay::wake_up) resets and initialises the
+//! controller again and applies everything the driver was configured with, so a battery powered
+//! application only loops over `wake_up`, `update_frame`, `display_frame` and `sleep`:
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), epd_waveshare::error::Error<embedded_hal::spi::ErrorKind>> {
+//!use epd_waveshare::{epd2in9_v2::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = digital::Mock::new(&expectations);
+//!# let dc = digital::Mock::new(&expectations);
+//!# let rst = digital::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
+//!# let frame = [0xFF; 128 / 8 * 296];
+//!
+//!let mut epd = Epd2in9::new(&mut spi, busy_in, dc, Some(rst), None::<NoPin>, &mut delay, None)?;
+//!epd.set_vcom(&mut spi, &mut delay, -1500)?;
+//!epd.set_border(&mut spi, BorderColor::White)?;
+//!epd.sleep(&mut spi, &mut delay)?;
+//!
+//!loop {
+//!    // the VCOM and the border are written again
+//!    epd.wake_up(&mut spi, &mut delay)?;
+//!    epd.update_frame(&mut spi, &frame, &mut delay)?;
+//!    epd.display_frame(&mut spi, &mut delay)?;
+//!    epd.sleep(&mut spi, &mut delay)?;
+//!    // sleep the MCU until the next update
+//!}
+//!# }
+//!```
+//!
+//! # Refresh modes
+//!
+//! Besides the default full refresh, some controllers have waveforms which only drive the
+//! changed pixels. They're selected with
+//! [`WaveshareDisplay::set_lut`](prelude::WaveshareDisplay::set_lut), the modes of each
+//! display are listed in
+//! [`WaveshareDisplay::REFRESH_LUTS`](prelude::WaveshareDisplay::REFRESH_LUTS):
+//!
+//! | Display | `Full` | `Quick` | `PartialRefresh` |
+//! |---|:-:|:-:|:-:|
+//! | [`epd1in02`] | ✓ | ✓ | ✓ |
+//! | [`epd1in54`] | ✓ | ✓ | ✓ |
+//! | [`epd1in54_v2`] | ✓ | ✓ | ✓ |
+//! | [`epd1in54b`] | ✓ | | |
+//! | [`epd1in54b_v2`] | ✓ | | |
+//! | [`epd1in54c`] | ✓ | | |
+//! | [`epd1in64g`] | ✓ | | |
+//! | [`epd2in13_v2`] | ✓ | ✓ | ✓ |
+//! | [`epd2in13_v4`] | ✓ | ✓ | ✓ |
+//! | [`epd2in13b_v4`] | ✓ | | |
+//! | [`epd2in13bc`] | ✓ | | |
+//! | [`epd2in13d`] | ✓ | | |
+//! | [`epd2in13g`] | ✓ | | |
+//! | [`epd2in15g`] | ✓ | | |
+//! | [`epd2in36g`] | ✓ | | |
+//! | [`epd2in66`] | ✓ | | |
+//! | [`epd2in66b`] | ✓ | | |
+//! | [`epd2in7`] | ✓ | | |
+//! | [`epd2in7_v2`] | ✓ | ✓ | |
+//! | [`epd2in7b`] | ✓ | | |
+//! | [`epd2in7b_v2`] | ✓ | | |
+//! | [`epd2in9`] | ✓ | ✓ | ✓ |
+//! | [`epd2in9_v2`] | ✓ | | |
+//! | [`epd2in9b_v3`] | ✓ | | |
+//! | [`epd2in9b_v4`] | ✓ | ✓ | |
+//! | [`epd2in9bc`] | ✓ | | |
+//! | [`epd2in9d`] | ✓ | | |
+//! | [`epd3in0g`] | ✓ | | |
+//! | [`epd3in52`] | ✓ | ✓ | |
+//! | [`epd3in7`] | ✓ | ✓ | ✓ |
+//! | [`epd4in01f`] | ✓ | | |
+//! | [`epd4in2`] | ✓ | ✓ | ✓ |
+//! | [`epd4in26`] | ✓ | | |
+//! | [`epd4in2_v2`] | ✓ | ✓ | |
+//! | [`epd4in2b_v2`] | ✓ | | |
+//! | [`epd4in37g`] | ✓ | | |
+//! | [`epd5in65f`] | ✓ | | |
+//! | [`epd5in79`] | ✓ | | |
+//! | [`epd5in83_v2`] | ✓ | | |
+//! | [`epd5in83b_v2`] | ✓ | | |
+//! | [`epd7in3f`] | ✓ | | |
+//! | [`epd7in3g`] | ✓ | | |
+//! | [`epd7in5`] | ✓ | | |
+//! | [`epd7in5_hd`] | ✓ | | |
+//! | [`epd7in5_v2`] | ✓ | ✓ | ✓ |
+//! | [`epd7in5b_hd`] | ✓ | | |
+//! | [`epd7in5b_v2`] | ✓ | | |
+//! | [`epd10in2b`] | ✓ | | |
+//! | [`epd13in3k`] | ✓ | | |
+//! | [`it8951`] | ✓ | ✓ | ✓ |
+//!
 //! # Other information and requirements
 //!
 //! - Buffersize: Wherever a buffer is used it always needs to be of the size: `width / 8 * length`,
@@ -66,58 +164,96 @@
 #[cfg(feature = "graphics")]
 pub mod graphics;
 
+#[cfg(feature = "graphics")]
+pub mod dither;
+
 mod traits;
 
 pub mod color;
 
+pub mod error;
+
 pub mod rect;
 
+pub mod auto_refresh;
+
 /// Interface for the physical connection between display and the controlling device
 mod interface;
 
+pub use interface::NoPin;
+
 pub mod epd1in02;
 pub mod epd1in54;
 pub mod epd1in54_v2;
 pub mod epd1in54b;
+pub mod epd1in54b_v2;
 pub mod epd1in54c;
+pub mod epd1in64g;
+pub mod epd1in9;
 pub mod epd2in13_v2;
+pub mod epd2in13_v4;
 pub mod epd2in13b_v4;
 pub mod epd2in13bc;
+pub mod epd2in13d;
+pub mod epd2in13g;
+pub mod epd2in15g;
+pub mod epd2in36g;
+pub mod epd2in66;
 pub mod epd2in66b;
 pub mod epd2in7;
 pub mod epd2in7_v2;
 pub mod epd2in7b;
+pub mod epd2in7b_v2;
 pub mod epd2in9;
 pub mod epd2in9_v2;
+pub mod epd2in9b_v3;
 pub mod epd2in9b_v4;
 pub mod epd2in9bc;
 pub mod epd2in9d;
+pub mod epd3in0g;
+pub mod epd3in52;
 pub mod epd3in7;
+pub mod epd4in01f;
 pub mod epd4in2;
+pub mod epd4in26;
+pub mod epd4in2_v2;
+pub mod epd4in2b_v2;
+pub mod epd4in37g;
 pub mod epd5in65f;
+pub mod epd5in79;
 pub mod epd5in83_v2;
 pub mod epd5in83b_v2;
 pub mod epd7in3f;
+pub mod epd7in3g;
 pub mod epd7in5;
 pub mod epd7in5_hd;
 pub mod epd7in5_v2;
+pub mod epd7in5b_hd;
 pub mod epd7in5b_v2;
 pub use epd7in5b_v2 as epd7in5b_v3;
+pub mod epd10in2b;
 pub mod epd12in48b_v2;
+pub mod epd13in3k;
+pub mod it8951;
 
+pub(crate) mod controllers;
 pub(crate) mod type_a;
+pub(crate) mod type_g;
 
 /// Includes everything important besides the chosen Display
 pub mod prelude {
-    pub use crate::color::{Color, OctColor, TriColor};
+    pub use crate::color::{
+        Color, DeghostColors, DisplayColorRendering, OctColor, QuadColor, TriColor,
+    };
     pub use crate::traits::{
-        QuickRefresh, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+        BorderColor, LutBanks, QuickRefresh, RefreshLut, WaveshareDisplay,
+        WaveshareThreeColorDisplay,
     };
 
-    pub use crate::SPI_MODE;
+    pub use crate::{NoPin, SPI_MODE};
 
     #[cfg(feature = "graphics")]
-    pub use crate::graphics::{Display, DisplayRotation};
+    pub use crate::graphics::{Display, DisplayMirror, DisplayRotation};
 }
 
 /// Computes the needed buffer length. Takes care of rounding up in case width
@@ -133,6 +269,54 @@ pub const fn buffer_len(width: usize, height: usize) -> usize {
     (width + 7) / 8 * height
 }
 
+/// Checks that a partial window is byte aligned, fits on a `display_width` x `display_height`
+/// display and is covered by a buffer of `len` bytes
+///
+/// The controllers address their RAM in bytes of 8 horizontal pixels, so a window whose `x` or
+/// `width` is not a multiple of 8 can't be written without shifting the image and fails with
+/// [`Error::OutOfBounds`](error::Error::OutOfBounds) like a window off the display.
+pub(crate) fn check_partial_window<SpiE>(
+    len: usize,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    display_width: u32,
+    display_height: u32,
+) -> Result<(), error::Error<SpiE>> {
+    if x % 8 != 0
+        || width % 8 != 0
+        || width == 0
+        || height == 0
+        || x.checked_add(width).map_or(true, |end| end > display_width)
+        || y.checked_add(height)
+            .map_or(true, |end| end > display_height)
+    {
+        return Err(error::Error::OutOfBounds {
+            x,
+            y,
+            width,
+            height,
+        });
+    }
+    let expected = buffer_len(width as usize, height as usize);
+    if len != expected {
+        return Err(error::Error::InvalidBufferLength { expected, got: len });
+    }
+    Ok(())
+}
+
+/// Checks that a buffer has the `expected` number of bytes
+pub(crate) fn check_buffer_len<SpiE>(
+    len: usize,
+    expected: usize,
+) -> Result<(), error::Error<SpiE>> {
+    if len != expected {
+        return Err(error::Error::InvalidBufferLength { expected, got: len });
+    }
+    Ok(())
+}
+
 use embedded_hal::spi::{Mode, Phase, Polarity};
 
 /// SPI mode -
@@ -141,3 +325,140 @@ pub const SPI_MODE: Mode = Mode {
     phase: Phase::CaptureOnFirstTransition,
     polarity: Polarity::IdleLow,
 };
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::{RefreshLut, WaveshareDisplay};
+    use embedded_hal_mock::eh1::{
+        delay::NoopDelay, digital::Mock as PinMock, spi::Mock as SpiMock,
+    };
+
+    fn luts<EPD: WaveshareDisplay<SpiMock<u8>, PinMock, PinMock, PinMock, NoopDelay, NoPin>>(
+    ) -> &'static [RefreshLut] {
+        EPD::REFRESH_LUTS
+    }
+
+    macro_rules! displays {
+        ($($module:ident::$epd:ident,)*) => {
+            [$((
+                stringify!($module),
+                luts::<$module::$epd<SpiMock<u8>, PinMock, PinMock, PinMock, NoopDelay, NoPin>>(),
+            ),)*]
+        };
+    }
+
+    #[test]
+    fn partial_window_checks() {
+        type E = error::Error<()>;
+
+        assert_eq!(check_partial_window::<()>(20, 8, 4, 16, 10, 32, 16), Ok(()));
+        let out_of_bounds = |x, y, width, height| {
+            Err::<(), E>(error::Error::OutOfBounds {
+                x,
+                y,
+                width,
+                height,
+            })
+        };
+        for (x, y, width, height) in [
+            (4, 0, 8, 1),
+            (0, 0, 12, 1),
+            (0, 0, 0, 1),
+            (0, 0, 8, 0),
+            (32, 0, 8, 1),
+            (0, 16, 8, 1),
+            // the end of the window overflows
+            (0xFFFF_FFF8, 0, 8, 1),
+            (0, u32::MAX, 8, 2),
+        ] {
+            assert_eq!(
+                check_partial_window(1, x, y, width, height, 32, 16),
+                out_of_bounds(x, y, width, height)
+            );
+        }
+        assert_eq!(
+            check_partial_window::<()>(19, 8, 4, 16, 10, 32, 16),
+            Err(error::Error::InvalidBufferLength {
+                expected: 20,
+                got: 19
+            })
+        );
+    }
+
+    #[test]
+    fn refresh_modes_table() {
+        let displays = displays!(
+            epd1in02::Epd1in02,
+            epd1in54::Epd1in54,
+            epd1in54_v2::Epd1in54,
+            epd1in54b::Epd1in54b,
+            epd1in54b_v2::Epd1in54b,
+            epd1in54c::Epd1in54c,
+            epd1in64g::Epd1in64g,
+            epd2in13_v2::Epd2in13,
+            epd2in13_v4::Epd2in13,
+            epd2in13b_v4::Epd2in13b,
+            epd2in13bc::Epd2in13bc,
+            epd2in13d::Epd2in13d,
+            epd2in13g::Epd2in13g,
+            epd2in15g::Epd2in15g,
+            epd2in36g::Epd2in36g,
+            epd2in66::Epd2in66,
+            epd2in66b::Epd2in66b,
+            epd2in7::Epd2in7,
+            epd2in7_v2::Epd2in7,
+            epd2in7b::Epd2in7b,
+            epd2in7b_v2::Epd2in7b,
+            epd2in9::Epd2in9,
+            epd2in9_v2::Epd2in9,
+            epd2in9b_v3::Epd2in9b,
+            epd2in9b_v4::Epd2in9b,
+            epd2in9bc::Epd2in9bc,
+            epd2in9d::Epd2in9d,
+            epd3in0g::Epd3in0g,
+            epd3in52::Epd3in52,
+            epd3in7::EPD3in7,
+            epd4in01f::Epd4in01f,
+            epd4in2::Epd4in2,
+            epd4in26::Epd4in26,
+            epd4in2_v2::Epd4in2,
+            epd4in2b_v2::Epd4in2b,
+            epd4in37g::Epd4in37g,
+            epd5in65f::Epd5in65f,
+            epd5in79::Epd5in79,
+            epd5in83_v2::Epd5in83,
+            epd5in83b_v2::Epd5in83,
+            epd7in3f::Epd7in3f,
+            epd7in3g::Epd7in3g,
+            epd7in5::Epd7in5,
+            epd7in5_hd::Epd7in5,
+            epd7in5_v2::Epd7in5,
+            epd7in5b_hd::Epd7in5b,
+            epd7in5b_v2::Epd7in5,
+            epd10in2b::Epd10in2b,
+            epd13in3k::Epd13in3k,
+            it8951::Epd,
+        );
+
+        let mut rows = include_str!("lib.rs")
+            .lines()
+            .filter_map(|line| line.strip_prefix("//! | [`"));
+        for (module, luts) in displays {
+            let row = rows
+                .next()
+                .unwrap_or_else(|| panic!("no row for {}", module));
+            let (name, cells) = row.split_once('`').unwrap();
+            assert_eq!(name, module);
+            let cells = cells.split('|').skip(1).map(str::trim);
+            for (cell, lut) in cells.zip([
+                RefreshLut::Full,
+                RefreshLut::Quick,
+                RefreshLut::PartialRefresh,
+            ]) {
+                assert_eq!(cell == "✓", luts.contains(&lut), "{} {:?}", module, lut);
+            }
+        }
+        assert_eq!(rows.next(), None);
+    }
+}
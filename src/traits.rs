@@ -1,6 +1,9 @@
+use crate::error::Error;
 use core::marker::Sized;
 use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
 
+use crate::color::DeghostColors;
+
 /// All commands need to have this trait which gives the address of the command
 /// which needs to be send via SPI with activated CommandsPin (Data/Command Pin in CommandMode)
 pub(crate) trait Command: Copy {
@@ -21,12 +24,165 @@ pub enum RefreshLut {
     PartialRefresh,
 }
 
-pub(crate) trait InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
+/// Color of the border around the active area, see [`WaveshareDisplay::set_border`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BorderColor {
+    /// White border
+    White,
+    /// Black border
+    Black,
+    /// Red or yellow border of the three color displays
+    Chromatic,
+    /// The border isn't driven and keeps its current shade
+    HiZ,
+}
+
+/// Fails with [`Error::Unsupported`] if `refresh_rate` isn't one of the `supported` modes
+pub(crate) fn check_refresh_lut<SpiE>(
+    refresh_rate: Option<RefreshLut>,
+    supported: &[RefreshLut],
+) -> Result<(), Error<SpiE>> {
+    match refresh_rate {
+        Some(refresh_lut) if !supported.contains(&refresh_lut) => Err(Error::Unsupported),
+        _ => Ok(()),
+    }
+}
+
+/// The five LUT registers of the UltraChip controllers (UC8151, UC8176, UC8253, ...), which
+/// their drivers take in `set_custom_lut`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LutBanks<'a> {
+    /// VCOM LUT (0x20)
+    pub vcom: &'a [u8],
+    /// White to white LUT (0x21)
+    pub ww: &'a [u8],
+    /// Black to white LUT (0x22)
+    pub bw: &'a [u8],
+    /// White to black LUT (0x23)
+    pub wb: &'a [u8],
+    /// Black to black LUT (0x24)
+    pub bb: &'a [u8],
+}
+
+/// Copy of [`LutBanks`] kept by a driver to load it again after `wake_up`
+///
+/// `VCOM` and `WW` are the lengths of the first two banks, `LEN` the one of the others.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LutBanksBuf<const VCOM: usize, const WW: usize, const LEN: usize> {
+    vcom: [u8; VCOM],
+    ww: [u8; WW],
+    bw: [u8; LEN],
+    wb: [u8; LEN],
+    bb: [u8; LEN],
+}
+
+impl<const VCOM: usize, const WW: usize, const LEN: usize> LutBanksBuf<VCOM, WW, LEN> {
+    /// Fails with [`Error::InvalidBufferLength`] for the first bank of the wrong length
+    pub(crate) fn new<SpiE>(banks: LutBanks) -> Result<Self, Error<SpiE>> {
+        Ok(LutBanksBuf {
+            vcom: copy_lut(banks.vcom)?,
+            ww: copy_lut(banks.ww)?,
+            bw: copy_lut(banks.bw)?,
+            wb: copy_lut(banks.wb)?,
+            bb: copy_lut(banks.bb)?,
+        })
+    }
+
+    pub(crate) fn banks(&self) -> LutBanks<'_> {
+        LutBanks {
+            vcom: &self.vcom,
+            ww: &self.ww,
+            bw: &self.bw,
+            wb: &self.wb,
+            bb: &self.bb,
+        }
+    }
+}
+
+/// Copies a LUT of exactly `N` bytes, fails with [`Error::InvalidBufferLength`] otherwise
+pub(crate) fn copy_lut<SpiE, const N: usize>(lut: &[u8]) -> Result<[u8; N], Error<SpiE>> {
+    lut.try_into().map_err(|_| Error::InvalidBufferLength {
+        expected: N,
+        got: lut.len(),
+    })
+}
+
+/// Register values of the "write VCOM register" command (0x2C) of the Solomon Systech
+/// controllers for -0.2 V to -3.0 V in steps of 100 mV, as listed in their datasheets
+const SSD_VCOM: [u8; 29] = [
+    0x08, 0x0B, 0x10, 0x14, 0x17, 0x1B, 0x20, 0x24, 0x28, 0x2C, 0x2F, 0x34, 0x37, 0x3C, 0x40, 0x44,
+    0x48, 0x4B, 0x50, 0x54, 0x58, 0x5B, 0x5F, 0x64, 0x68, 0x6C, 0x6F, 0x73, 0x78,
+];
+
+/// Encodes `millivolts` for the "write VCOM register" command (0x2C) of the Solomon Systech
+/// controllers (SSD1675, SSD1680, SSD1677, ...)
+///
+/// Values in between the steps of the table are rounded towards 0 V, to the lower table entry.
+/// Fails with [`Error::Unsupported`] outside of -200 to -3000 mV.
+pub(crate) fn ssd_vcom<SpiE>(millivolts: i16) -> Result<u8, Error<SpiE>> {
+    if !(-3000..=-200).contains(&millivolts) {
+        return Err(Error::Unsupported);
+    }
+    Ok(SSD_VCOM[(-200 - millivolts) as usize / 100])
+}
+
+/// Encodes `millivolts` for the VCM_DC setting (0x82) of the UltraChip controllers (UC8151,
+/// UC8176, ...)
+///
+/// One step of the register is 50 mV, values in between are rounded towards 0 V. Fails with
+/// [`Error::Unsupported`] outside of -100 to -3000 mV.
+pub(crate) fn uc_vcm_dc<SpiE>(millivolts: i16) -> Result<u8, Error<SpiE>> {
+    if !(-3000..=-100).contains(&millivolts) {
+        return Err(Error::Unsupported);
+    }
+    Ok(((-100 - millivolts) / 50) as u8)
+}
+
+/// Value of the border waveform control (0x3C) of the Solomon Systech controllers
+///
+/// The border is driven with the level the pixels of its color end with. `Chromatic` fails with
+/// [`Error::Unsupported`] unless the panel is a `chromatic` one.
+pub(crate) fn ssd_border<SpiE>(border: BorderColor, chromatic: bool) -> Result<u8, Error<SpiE>> {
+    match border {
+        // fix level VSL
+        BorderColor::White => Ok(0x60),
+        // fix level VSH1
+        BorderColor::Black => Ok(0x50),
+        // fix level VSH2
+        BorderColor::Chromatic if chromatic => Ok(0x70),
+        BorderColor::Chromatic => Err(Error::Unsupported),
+        BorderColor::HiZ => Ok(0xC0),
+    }
+}
+
+/// Replaces the border data (VBD) of the VCOM and data interval setting (0x50) `cdi` of the
+/// UltraChip controllers, its data polarity (DDX) and interval are kept
+///
+/// VBD depends on the mode DDX selects, the black and white mode can't drive a chromatic border
+/// and fails with [`Error::Unsupported`] like the other modes.
+pub(crate) fn uc_border<SpiE>(cdi: u8, border: BorderColor) -> Result<u8, Error<SpiE>> {
+    let vbd = match (cdi & 0x30, border) {
+        // black, white and red (KWR)
+        (0x30, BorderColor::Black) => 0x00,
+        (0x30, BorderColor::White) => 0x40,
+        (0x30, BorderColor::Chromatic) => 0x80,
+        (0x30, BorderColor::HiZ) => 0xC0,
+        // black and white (KW)
+        (0x10, BorderColor::HiZ) => 0x00,
+        (0x10, BorderColor::Black) => 0x40,
+        (0x10, BorderColor::White) => 0x80,
+        _ => return Err(Error::Unsupported),
+    };
+    Ok(vbd | cdi & 0x3F)
+}
+
+pub(crate) trait InternalWiAdditions<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     /// This initialises the EPD and powers it up
@@ -39,17 +195,18 @@ where
     /// This function calls [reset](WaveshareDisplay::reset),
     /// so you don't need to call reset your self when trying to wake your device up
     /// after setting it to sleep.
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>>;
 }
 
 /// Functions to interact with three color panels
-pub trait WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>:
-    WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+pub trait WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY, PWR>:
+    WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     /// Transmit data to the SRAM of the EPD
@@ -61,7 +218,7 @@ where
         delay: &mut DELAY,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Update only the black/white data of the display.
     ///
@@ -71,7 +228,7 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         black: &[u8],
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Update only the chromatic data of the display.
     ///
@@ -82,7 +239,22 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
+
+    /// Writes only the black/white data and displays the frame.
+    ///
+    /// The chromatic data the controller already holds is shown again, so it neither has to be
+    /// sent nor kept in memory, e.g. for a fixed red logo. The controller RAM usually doesn't
+    /// survive `sleep`, write the chromatic data again after waking the display up.
+    fn update_achromatic_and_display(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_achromatic_frame(spi, delay, black)?;
+        self.display_frame(spi, delay)
+    }
 }
 
 /// All the functions to interact with the EPDs
@@ -93,7 +265,7 @@ where
 ///
 ///```rust, no_run
 ///# use embedded_hal_mock::eh1::*;
-///# fn main() -> Result<(), embedded_hal::spi::ErrorKind> {
+///# fn main() -> Result<(), epd_waveshare::error::Error<embedded_hal::spi::ErrorKind>> {
 ///use embedded_graphics::{
 ///    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
 ///};
@@ -109,7 +281,7 @@ where
 ///# let mut delay = delay::NoopDelay::new();
 ///
 ///// Setup EPD
-///let mut epd = Epd4in2::new(&mut spi, busy_in, dc, rst, &mut delay, None)?;
+///let mut epd = Epd4in2::new(&mut spi, busy_in, dc, Some(rst), None::<NoPin>, &mut delay, None)?;
 ///
 ///// Use display graphics from embedded-graphics
 ///let mut display = Display4in2::default();
@@ -147,43 +319,85 @@ where
 ///     .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
 ///     .draw(&mut *display);
 ///```
-pub trait WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+///
+/// `Box::new` may still build the display on the stack first without optimizations.
+/// `Displayxxxx::new_boxed()` allocates the buffer on the heap right away, the returned
+/// [`BoxedDisplay`](crate::graphics::BoxedDisplay) is used like the display itself.
+///
+///```rust, no_run
+///# use epd_waveshare::epd4in2::Display4in2;
+///# use epd_waveshare::prelude::*;
+///# use embedded_graphics_core::prelude::*;
+///# use embedded_graphics::primitives::*;
+///let mut display = Display4in2::new_boxed();
+///let _ = Line::new(Point::new(0, 120), Point::new(0, 295))
+///     .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
+///     .draw(&mut display);
+///```
+pub trait WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     /// The Color Type used by the Display
     type DisplayColor;
+
+    /// The refresh modes [`set_lut`](WaveshareDisplay::set_lut) accepts, see the
+    /// [table of all displays](crate#refresh-modes)
+    const REFRESH_LUTS: &'static [RefreshLut] = &[RefreshLut::Full];
+
     /// Creates a new driver from a SPI peripheral, CS Pin, Busy InputPin, DC
     ///
     /// `delay_us` is the number of us the idle loop should sleep on.
     /// Setting it to 0 implies busy waiting.
     /// Setting it to None means a default value is used.
     ///
+    /// `rst` is `None` if the reset line isn't connected to a GPIO, e.g. because the reset
+    /// supervisor of the MCU drives it, [`NoPin`] fills in its type. The drivers send the software
+    /// reset of their controller instead, except for the IT8951 of [`it8951`](crate::it8951)
+    /// which has none and needs the pin. A display in deep sleep only wakes up by a reset of its
+    /// reset line though, so without the pin `sleep` lasts until the next reset of the MCU.
+    ///
+    /// `pwr` is the pin switching the power of the display, e.g. GPIO 18 of the Raspberry Pi HAT,
+    /// or `None` if the display is always powered. The driver switches it on before releasing the
+    /// reset and off at the end of [`sleep`](WaveshareDisplay::sleep) and in
+    /// [`power_off`](WaveshareDisplay::power_off).
+    ///
     /// This already initialises the device.
+    ///
+    /// [`NoPin`]: crate::NoPin
     fn new(
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        pwr: Option<PWR>,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error>
+    ) -> Result<Self, Error<SPI::Error>>
     where
         Self: Sized;
 
     /// Let the device enter deep-sleep mode to save power.
     ///
-    /// The deep sleep mode returns to standby with a hardware reset.
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    /// The deep sleep mode returns to standby with a hardware reset, see
+    /// [`wake_up`](WaveshareDisplay::wake_up).
+    ///
+    /// With a power pin the display is switched off afterwards, so the content of its RAM is lost.
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>>;
 
     /// Wakes the device up from sleep
     ///
-    /// Also reintialises the device if necessary.
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    /// Resets the controller, initialises it again and applies the settings of the driver, e.g.
+    /// the refresh mode of [`set_lut`](WaveshareDisplay::set_lut), a custom LUT, the VCOM, the
+    /// border and a forced temperature. So there's no need to create the driver again with
+    /// [`new`](WaveshareDisplay::new). The RAM may be lost in deep sleep, write the whole frame
+    /// again before the next refresh.
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>>;
 
     /// Sets the backgroundcolor for various commands like [clear_frame](WaveshareDisplay::clear_frame)
     fn set_background_color(&mut self, color: Self::DisplayColor);
@@ -191,6 +405,29 @@ where
     /// Get current background color
     fn background_color(&self) -> &Self::DisplayColor;
 
+    /// Sets the number of us the BUSY line may stay busy before waiting for it fails with
+    /// [`Error::BusyTimeout`], `None` waits forever
+    ///
+    /// Defaults to 10s, and to 60s on the colored panels whose refresh takes up to 35s.
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>);
+
+    /// Switches the power of the display off without talking to the controller, e.g. after an
+    /// [`Error::BusyTimeout`]
+    ///
+    /// DC and RST are pulled low as well. Does nothing without a power pin.
+    /// [`wake_up`](WaveshareDisplay::wake_up) switches the power on again.
+    fn power_off(&mut self) -> Result<(), Error<SPI::Error>>;
+
+    /// Checks whether the display is busy, e.g. with a refresh, without waiting for it
+    ///
+    /// This only reads the BUSY line with the polarity of the controller. The driver still waits
+    /// for the display itself: [`display_frame`](WaveshareDisplay::display_frame) mostly returns
+    /// after the refresh, and all functions wait until the display is idle before talking to it.
+    /// So poll `is_busy` before calling them, e.g. from a main loop which has other things to do,
+    /// and they won't block. While the display is powered off or in deep sleep the BUSY line is
+    /// undefined.
+    fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>>;
+
     /// Get the width of the display
     fn width(&self) -> u32;
 
@@ -203,7 +440,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Transmits partial data to the SRAM of the EPD
     ///
@@ -220,12 +457,12 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Displays the frame data from SRAM
     ///
     /// This function waits until the device isn`t busy anymore
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>>;
 
     /// Provide a combined update&display and save some time (skipping a busy check in between)
     fn update_and_display_frame(
@@ -233,12 +470,54 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Clears the frame buffer on the EPD with the declared background color
     ///
     /// The background color can be changed with [`WaveshareDisplay::set_background_color`]
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>>;
+
+    /// Drives every pixel through `cycles` rounds of the colors of [`DeghostColors::CYCLE`] with
+    /// full refreshes to clear accumulated ghosting, then clears to the background color
+    ///
+    /// The default implementation uses [`clear_frame`](WaveshareDisplay::clear_frame) with the
+    /// background color set to each color, so no frame buffer is needed, and sets the background
+    /// color back even if a refresh fails. Drivers whose `clear_frame` always clears to white fill
+    /// their RAM directly instead. This takes a full refresh per color, so e.g. run it once a
+    /// night.
+    ///
+    /// The refresh mode is switched to `RefreshLut::Full` with
+    /// [`set_lut`](WaveshareDisplay::set_lut) first and stays selected, a quick mode has to be
+    /// selected again afterwards.
+    fn deghost(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        cycles: u8,
+    ) -> Result<(), Error<SPI::Error>>
+    where
+        Self::DisplayColor: DeghostColors,
+    {
+        self.set_lut(spi, delay, Some(RefreshLut::Full))?;
+
+        let background = *self.background_color();
+        let mut drive_cycles = || -> Result<(), Error<SPI::Error>> {
+            for _ in 0..cycles {
+                for color in <Self::DisplayColor as DeghostColors>::CYCLE {
+                    self.set_background_color(*color);
+                    self.clear_frame(spi, delay)?;
+                    self.display_frame(spi, delay)?;
+                }
+            }
+            Ok(())
+        };
+        let result = drive_cycles();
+        self.set_background_color(background);
+        result?;
+
+        self.clear_frame(spi, delay)?;
+        self.display_frame(spi, delay)
+    }
 
     /// Trait for using various Waveforms from different LUTs
     /// E.g. for partial refreshes
@@ -248,17 +527,88 @@ where
     /// WARNING: Quick Refresh might lead to ghosting-effects/problems with your display. Especially for the 4.2in Display!
     ///
     /// If None is used the old value will be loaded on the LUTs once more
+    ///
+    /// The selected mode is kept across [`sleep`](WaveshareDisplay::sleep) and
+    /// [`wake_up`](WaveshareDisplay::wake_up). Fails with [`Error::Unsupported`] for a mode
+    /// which isn't in [`REFRESH_LUTS`](WaveshareDisplay::REFRESH_LUTS), the default
+    /// implementation only accepts `RefreshLut::Full`.
     fn set_lut(
         &mut self,
-        spi: &mut SPI,
-        delay: &mut DELAY,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>> {
+        check_refresh_lut(refresh_rate, Self::REFRESH_LUTS)
+    }
+
+    /// Sets the color of the border around the active area
+    ///
+    /// The border is kept for all following refreshes, also the quick ones, and written again by
+    /// [`wake_up`](WaveshareDisplay::wake_up). Fails with [`Error::Unsupported`] if the controller
+    /// can't drive the border that way, the default implementation for every border.
+    fn set_border(
+        &mut self,
+        _spi: &mut SPI,
+        _border: BorderColor,
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::Unsupported)
+    }
+
+    /// Sets the VCOM to `millivolts`, e.g. -1480 for the -1.48 V printed on the flex cable
+    ///
+    /// The VCOM is kept for all following refreshes and written again by
+    /// [`wake_up`](WaveshareDisplay::wake_up). The Solomon Systech controllers take -200 to
+    /// -3000 mV in steps of 100 mV, the UltraChip ones -100 to -3000 mV in steps of 50 mV, values
+    /// in between are rounded towards 0 V. Fails with [`Error::Unsupported`] outside of that
+    /// range or if the driver can't set the VCOM, the default implementation for every value.
+    fn set_vcom(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+        _millivolts: i16,
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::Unsupported)
+    }
+
+    /// Measures the temperature with the on-die sensor of the controller, in °C
+    ///
+    /// The controller answers on its data line, which has to be connected to MISO as well, the
+    /// HATs only connect it to MOSI. The waveform of a forced temperature or of a fast refresh is
+    /// loaded again afterwards. Fails with [`Error::Unsupported`] if the controller can't be read,
+    /// the default implementation.
+    fn read_temperature(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<i8, Error<SPI::Error>> {
+        Err(Error::Unsupported)
+    }
+
+    /// Forces the temperature the waveform is chosen with to `celsius`, `None` goes back to the
+    /// on-die sensor
+    ///
+    /// E.g. for a panel behind glass in the sun, where the sensor measures the PCB and not the
+    /// film. The temperature is used for all following full refreshes and written again by
+    /// [`wake_up`](WaveshareDisplay::wake_up), the fast refresh modes keep forcing their own.
+    /// Fails with [`Error::Unsupported`] if the controller can't be given a temperature, the
+    /// default implementation.
+    fn set_temperature(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+        _celsius: Option<i8>,
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::Unsupported)
+    }
 
     /// Wait until the display has stopped processing data
     ///
     /// You can call this to make sure a frame is displayed before goin further
-    fn wait_until_idle(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn wait_until_idle(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>;
 }
 
 /// Allows quick refresh support for displays that support it; lets you send both
@@ -272,7 +622,7 @@ where
 /// Example:
 ///```rust, no_run
 ///# use embedded_hal_mock::eh1::*;
-///# fn main() -> Result<(), embedded_hal::spi::ErrorKind> {
+///# fn main() -> Result<(), epd_waveshare::error::Error<embedded_hal::spi::ErrorKind>> {
 ///# use embedded_graphics::{
 ///#   pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
 ///# };
@@ -289,7 +639,7 @@ where
 ///# let mut delay = delay::NoopDelay::new();
 ///#
 ///# // Setup EPD
-///# let mut epd = Epd4in2::new(&mut spi, busy_in, dc, rst, &mut delay, None)?;
+///# let mut epd = Epd4in2::new(&mut spi, busy_in, dc, Some(rst), None::<NoPin>, &mut delay, None)?;
 ///let (x, y, frame_width, frame_height) = (20, 40, 80,80);
 ///
 ///let mut buffer = [DEFAULT_BACKGROUND_COLOR.get_byte_value(); 80 / 8 * 80];
@@ -306,12 +656,13 @@ where
 ///# Ok(())
 ///# }
 ///```
-pub trait QuickRefresh<SPI, BUSY, DC, RST, DELAY>
+pub trait QuickRefresh<SPI, BUSY, DC, RST, DELAY, PWR>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
     DC: OutputPin,
     RST: OutputPin,
+    PWR: OutputPin,
     DELAY: DelayNs,
 {
     /// Updates the old frame.
@@ -320,7 +671,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Updates the new frame.
     fn update_new_frame(
@@ -328,10 +679,14 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Displays the new frame
-    fn display_new_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Updates and displays the new frame.
     fn update_and_display_new_frame(
@@ -339,7 +694,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Updates the old frame for a portion of the display.
     #[allow(clippy::too_many_arguments)]
@@ -352,7 +707,7 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Updates the new frame for a portion of the display.
     #[allow(clippy::too_many_arguments)]
@@ -365,7 +720,7 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Clears the partial frame buffer on the EPD with the declared background color
     /// The background color can be changed with [`WaveshareDisplay::set_background_color`]
@@ -377,5 +732,49 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ssd_vcom_encoding() {
+        assert_eq!(ssd_vcom::<()>(-200), Ok(0x08));
+        assert_eq!(ssd_vcom::<()>(-900), Ok(0x24));
+        assert_eq!(ssd_vcom::<()>(-275), Ok(0x08));
+        assert_eq!(ssd_vcom::<()>(-1480), Ok(0x37));
+        assert_eq!(ssd_vcom::<()>(-2100), Ok(0x54));
+        assert_eq!(ssd_vcom::<()>(-3000), Ok(0x78));
+        assert_eq!(ssd_vcom::<()>(-199), Err(Error::Unsupported));
+        assert_eq!(ssd_vcom::<()>(-3001), Err(Error::Unsupported));
+        assert_eq!(ssd_vcom::<()>(1500), Err(Error::Unsupported));
+    }
+
+    #[test]
+    fn uc_border_keeps_the_data_polarity() {
+        assert_eq!(uc_border::<()>(0x97, BorderColor::Black), Ok(0x57));
+        assert_eq!(uc_border::<()>(0x57, BorderColor::HiZ), Ok(0x17));
+        assert_eq!(
+            uc_border::<()>(0x97, BorderColor::Chromatic),
+            Err(Error::Unsupported)
+        );
+        assert_eq!(uc_border::<()>(0x37, BorderColor::White), Ok(0x77));
+        assert_eq!(uc_border::<()>(0x77, BorderColor::Chromatic), Ok(0xB7));
+        assert_eq!(
+            uc_border::<()>(0x87, BorderColor::White),
+            Err(Error::Unsupported)
+        );
+    }
+
+    #[test]
+    fn uc_vcm_dc_encoding() {
+        assert_eq!(uc_vcm_dc::<()>(-100), Ok(0x00));
+        assert_eq!(uc_vcm_dc::<()>(-1000), Ok(0x12));
+        assert_eq!(uc_vcm_dc::<()>(-1480), Ok(0x1B));
+        assert_eq!(uc_vcm_dc::<()>(-3000), Ok(0x3A));
+        assert_eq!(uc_vcm_dc::<()>(-50), Err(Error::Unsupported));
+        assert_eq!(uc_vcm_dc::<()>(-3050), Err(Error::Unsupported));
+    }
 }
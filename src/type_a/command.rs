@@ -45,6 +45,8 @@ pub(crate) enum Command {
 
     TemperatureSensorControl = 0x1A,
 
+    TemperatureSensorRead = 0x1B,
+
     MasterActivation = 0x20,
 
     DisplayUpdateControl1 = 0x21,
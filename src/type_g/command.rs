@@ -0,0 +1,129 @@
+//! SPI Commands for the Waveshare 4 color (G) E-Ink Displays
+
+use crate::traits;
+
+/// Epd1in64g, Epd2in13g, Epd2in15g, Epd2in36g, Epd3in0g, Epd4in37g and Epd7in3g commands
+///
+/// Should rarely (never?) be needed directly.
+///
+/// Some of the commands are sent by the Waveshare driver but are not documented.
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub(crate) enum Command {
+    /// Set Resolution, LUT selection, gate scan direction, source shift direction,
+    /// booster switch, soft reset.
+    PanelSetting = 0x00,
+
+    /// Selecting internal and external power
+    PowerSetting = 0x01,
+
+    /// After the Power Off command, the driver will power off following the Power Off
+    /// Sequence; BUSY signal will become "0".
+    PowerOff = 0x02,
+
+    /// Setting Power OFF sequence
+    PowerOffSequenceSetting = 0x03,
+
+    /// Turning On the Power
+    ///
+    /// After the Power ON command, the driver will power on following the Power ON
+    /// sequence. Once complete, the BUSY signal will become "1".
+    PowerOn = 0x04,
+
+    /// Undocumented, booster setting of the 7.3" panel
+    Undocumented05 = 0x05,
+
+    /// Starting data transmission
+    BoosterSoftStart = 0x06,
+
+    /// This command makes the chip enter the deep-sleep mode to save power.
+    ///
+    /// The deep sleep mode would return to stand-by by hardware reset.
+    ///
+    /// The only one parameter is a check code, the command would be excuted if check code = 0xA5.
+    DeepSleep = 0x07,
+
+    /// Undocumented, booster setting of the 7.3" panel
+    Undocumented08 = 0x08,
+
+    /// Writes the 2 bit per pixel frame into the SRAM
+    DataStartTransmission = 0x10,
+
+    /// After this command is issued, driver will refresh display (data/VCOM) according to
+    /// SRAM data and LUT.
+    ///
+    /// After Display Refresh command, BUSY signal will become "0" until the display
+    /// update is finished.
+    DisplayRefresh = 0x12,
+
+    /// The command controls the PLL clock frequency.
+    PllControl = 0x30,
+
+    /// This command enables the internal temperature sensor (0x00) or selects an external one.
+    TemperatureSensorEnable = 0x41,
+
+    /// Undocumented, sent first by the init of the 2.13" panel
+    Undocumented4D = 0x4D,
+
+    /// This command indicates the interval of Vcom and data output.
+    VcomAndDataIntervalSetting = 0x50,
+
+    /// This command defines non-overlap period of Gate and Source.
+    TconSetting = 0x60,
+    /// This command defines alternative resolution and this setting is of higher priority
+    /// than the RES\[1:0\] in R00H (PSR).
+    TconResolution = 0x61,
+
+    /// This command defines the first gate and source line of the resolution.
+    GateSourceStartSetting = 0x65,
+
+    /// Undocumented, unlocks the following undocumented commands
+    Undocumented66 = 0x66,
+
+    /// Undocumented, enables (0x01) and disables (0x00) the write to the frame SRAM
+    Undocumented68 = 0x68,
+
+    /// Undocumented
+    Undocumented84 = 0x84,
+
+    /// Undocumented, unlocks the following undocumented commands on the 7.3" panel
+    UndocumentedAA = 0xAA,
+
+    /// Undocumented
+    UndocumentedB0 = 0xB0,
+
+    /// Undocumented
+    UndocumentedB4 = 0xB4,
+
+    /// Undocumented
+    UndocumentedB5 = 0xB5,
+
+    /// Power saving setting
+    PowerSaving = 0xE3,
+
+    /// Undocumented
+    UndocumentedE7 = 0xE7,
+
+    /// Undocumented
+    UndocumentedE9 = 0xE9,
+}
+
+impl traits::Command for Command {
+    /// Returns the address of the command
+    fn address(self) -> u8 {
+        self as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Command as CommandTrait;
+
+    #[test]
+    fn command_addr() {
+        assert_eq!(Command::PanelSetting.address(), 0x00);
+        assert_eq!(Command::DataStartTransmission.address(), 0x10);
+        assert_eq!(Command::Undocumented66.address(), 0x66);
+    }
+}
@@ -0,0 +1,202 @@
+//! Controller code shared by the 2.13" and 2.15" (G) panels
+//!
+//! Both panels take the same init sequence and only differ in their resolution. The lines are
+//! written one by one, so that the padding pixels of a width that isn't a multiple of 4 can be
+//! sent as white.
+
+use crate::error::Error;
+use embedded_hal::{
+    delay::DelayNs,
+    digital::{InputPin, OutputPin},
+    spi::SpiDevice,
+};
+
+use crate::check_buffer_len;
+use crate::color::QuadColor;
+use crate::interface::DisplayInterface;
+use crate::type_g::command::Command;
+
+/// BUSY is low active on every G panel
+pub(crate) const IS_BUSY_LOW: bool = true;
+
+/// Bytes of a line at 2 bits per pixel, including the padding
+pub(crate) const fn line_bytes(width: u32) -> u32 {
+    (width + 3) / 4
+}
+
+/// Resets and initialises the panel, then powers it on
+pub(crate) fn init<SPI, BUSY, DC, RST, DELAY, PWR, const SINGLE_BYTE_WRITE: bool>(
+    interface: &mut DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+    width: u32,
+    height: u32,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    interface.reset(delay, 20_000, 2_000)?;
+    interface.uc_software_reset(spi, delay, Command::PanelSetting, 0x0F, IS_BUSY_LOW)?;
+    interface.wait_until_idle(delay, IS_BUSY_LOW)?;
+
+    interface.cmd_with_data(spi, Command::Undocumented4D, &[0x78])?;
+    interface.cmd_with_data(spi, Command::PanelSetting, &[0x0F, 0x29])?;
+    interface.cmd_with_data(spi, Command::PowerSetting, &[0x07, 0x00])?;
+    interface.cmd_with_data(spi, Command::PowerOffSequenceSetting, &[0x10, 0x54, 0x44])?;
+    interface.cmd_with_data(
+        spi,
+        Command::BoosterSoftStart,
+        &[0x05, 0x00, 0x3F, 0x0A, 0x25, 0x12, 0x1A],
+    )?;
+    interface.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x37])?;
+    interface.cmd_with_data(spi, Command::TconSetting, &[0x02, 0x02])?;
+    interface.cmd_with_data(
+        spi,
+        Command::TconResolution,
+        &[
+            (width >> 8) as u8,
+            width as u8,
+            (height >> 8) as u8,
+            height as u8,
+        ],
+    )?;
+    interface.cmd_with_data(spi, Command::UndocumentedE7, &[0x1C])?;
+    interface.cmd_with_data(spi, Command::PowerSaving, &[0x22])?;
+    interface.cmd_with_data(spi, Command::UndocumentedB4, &[0xD0])?;
+    interface.cmd_with_data(spi, Command::UndocumentedB5, &[0x03])?;
+    interface.cmd_with_data(spi, Command::UndocumentedE9, &[0x01])?;
+    interface.cmd_with_data(spi, Command::PllControl, &[0x08])?;
+
+    interface.cmd(spi, Command::PowerOn)?;
+    interface.wait_until_idle(delay, IS_BUSY_LOW)
+}
+
+/// Writes a full frame of `width` pixels per line, with white padding
+pub(crate) fn update_frame<SPI, BUSY, DC, RST, DELAY, PWR, const SINGLE_BYTE_WRITE: bool>(
+    interface: &mut DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+    buffer: &[u8],
+    width: u32,
+    height: u32,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    check_buffer_len(buffer.len(), (line_bytes(width) * height) as usize)?;
+    interface.wait_until_idle(delay, IS_BUSY_LOW)?;
+    interface.cmd(spi, Command::DataStartTransmission)?;
+    for line in buffer.chunks(line_bytes(width) as usize) {
+        if let Some((last, pixels)) = line.split_last() {
+            interface.data(spi, pixels)?;
+            interface.data(spi, &[pad_line_end(width, *last)])?;
+        }
+    }
+    Ok(())
+}
+
+/// Fills the whole frame with `color`
+pub(crate) fn clear_frame<SPI, BUSY, DC, RST, DELAY, PWR, const SINGLE_BYTE_WRITE: bool>(
+    interface: &mut DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+    color: QuadColor,
+    width: u32,
+    height: u32,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    interface.wait_until_idle(delay, IS_BUSY_LOW)?;
+    let color = color.get_byte_value();
+    interface.cmd(spi, Command::DataStartTransmission)?;
+    for _ in 0..height {
+        interface.data_x_times(spi, color, line_bytes(width) - 1)?;
+        interface.data(spi, &[pad_line_end(width, color)])?;
+    }
+    Ok(())
+}
+
+/// Refreshes the display and waits until it is done
+pub(crate) fn display_frame<SPI, BUSY, DC, RST, DELAY, PWR, const SINGLE_BYTE_WRITE: bool>(
+    interface: &mut DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    interface.cmd_with_data(spi, Command::DisplayRefresh, &[0x00])?;
+    interface.wait_until_idle(delay, IS_BUSY_LOW)
+}
+
+/// Powers the panel off and lets it enter the deep sleep
+pub(crate) fn sleep<SPI, BUSY, DC, RST, DELAY, PWR, const SINGLE_BYTE_WRITE: bool>(
+    interface: &mut DisplayInterface<SPI, BUSY, DC, RST, DELAY, PWR, SINGLE_BYTE_WRITE>,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+    DELAY: DelayNs,
+{
+    interface.cmd_with_data(spi, Command::PowerOff, &[0x00])?;
+    interface.wait_until_idle(delay, IS_BUSY_LOW)?;
+    interface.cmd_with_data(spi, Command::DeepSleep, &[0xA5])
+}
+
+/// Sets the padding pixels in the last byte of a line to white
+pub(crate) fn pad_line_end(width: u32, byte: u8) -> u8 {
+    if width % 4 == 0 {
+        return byte;
+    }
+    // bits of the pixels that are on the panel
+    let mask = !(0xFFu8 >> ((width % 4) * 2));
+    (byte & mask) | (QuadColor::White.get_byte_value() & !mask)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_lengths() {
+        assert_eq!(line_bytes(122), 31);
+        assert_eq!(line_bytes(160), 40);
+    }
+
+    #[test]
+    fn padding_is_white() {
+        // 122 pixels: pixel 120 red, pixel 121 yellow, 2 black padding pixels
+        assert_eq!(pad_line_end(122, 0b11_10_00_00), 0b11_10_01_01);
+        assert_eq!(pad_line_end(122, 0x00), 0b00_00_01_01);
+        assert_eq!(pad_line_end(122, 0x55), 0x55);
+        // no padding at all
+        assert_eq!(pad_line_end(160, 0x00), 0x00);
+        assert_eq!(pad_line_end(160, 0b11_10_00_00), 0b11_10_00_00);
+    }
+}